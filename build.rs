@@ -0,0 +1,5 @@
+fn main() {
+    // Exposes PyO3's `Py_3_*` and `Py_LIMITED_API` cfgs, for APIs that the limited API
+    // only offers from a given Python version
+    pyo3_build_config::use_pyo3_cfgs();
+}
@@ -0,0 +1,58 @@
+/*!
+Address derivation and checksums.
+
+Checksums follow EIP-55, or EIP-1191 when given a chain ID, as RSK and a few other
+chains use: the chain ID is hashed along with the address, so the same address has a
+different checksum on each chain.
+
+Contract addresses are derived as the EVM derives them, from the deployer and its nonce
+for `CREATE` and from the deployer, a salt and the init code hash for `CREATE2`.
+*/
+
+use ethers_core::types::Address;
+use ethers_core::utils::rlp::RlpStream;
+use k256::ecdsa::VerifyingKey;
+
+use crate::keccak::keccak256;
+
+/// Returns the address controlled by `key`.
+pub fn key_address(key: &VerifyingKey) -> Address {
+    let point = key.to_encoded_point(false);
+    Address::from_slice(&keccak256(&point.as_bytes()[1..])[12..])
+}
+
+/// Returns the checksummed form of `address`: EIP-55, or EIP-1191 for `chain_id`.
+///
+/// ethers' `to_checksum` takes the chain ID as a `u8`, which covers too few chains.
+pub fn checksum(address: &Address, chain_id: Option<u64>) -> String {
+    let digits = hex::encode(address.as_bytes());
+    let hash = match chain_id {
+        Some(chain_id) => keccak256(format!("{}0x{}", chain_id, digits).as_bytes()),
+        None => keccak256(digits.as_bytes()),
+    };
+    let mut checksummed = String::with_capacity(42);
+    checksummed.push_str("0x");
+    for (i, digit) in digits.chars().enumerate() {
+        let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0xf;
+        checksummed.push(if nibble >= 8 { digit.to_ascii_uppercase() } else { digit });
+    }
+    checksummed
+}
+
+/// Returns the address `CREATE` deploys to.
+pub fn create(deployer: &Address, nonce: u64) -> Address {
+    let mut rlp = RlpStream::new_list(2);
+    rlp.append(deployer);
+    rlp.append(&nonce);
+    Address::from_slice(&keccak256(&rlp.out())[12..])
+}
+
+/// Returns the address `CREATE2` deploys to.
+pub fn create2(deployer: &Address, salt: &[u8; 32], init_code_hash: &[u8; 32]) -> Address {
+    let mut preimage = [0u8; 85];
+    preimage[0] = 0xff;
+    preimage[1..21].copy_from_slice(deployer.as_bytes());
+    preimage[21..53].copy_from_slice(salt);
+    preimage[53..].copy_from_slice(init_code_hash);
+    Address::from_slice(&keccak256(&preimage)[12..])
+}
@@ -0,0 +1,143 @@
+/*!
+EIP-712 struct hashing with precomputed type hashes.
+
+`ethers` recomputes the encoded type string and its hash for every struct it encodes,
+which dominates the cost of hashing small messages. `TypeHashes` computes them once per
+type set so that many messages sharing the same types only pay for encoding their data.
+The encoding itself matches `ethers_core::types::transaction::eip712::encode_data`.
+*/
+
+use std::collections::HashMap;
+
+use ethers_core::abi::{encode, Token};
+use ethers_core::types::transaction::eip712::{
+    encode_eip712_type, encode_field, hash_type, Eip712Error, Types,
+};
+use ethers_core::types::U256;
+use ethers_core::utils::keccak256;
+
+/// Struct hasher for a fixed set of EIP-712 types.
+pub struct TypeHashes<'a> {
+    types: &'a Types,
+    hashes: HashMap<&'a str, [u8; 32]>,
+}
+
+impl<'a> TypeHashes<'a> {
+    /// Computes the type hash of every type in `types`.
+    pub fn new(types: &'a Types) -> Result<Self, Eip712Error> {
+        let hashes = types
+            .keys()
+            .map(|name| Ok((name.as_str(), hash_type(name, types)?)))
+            .collect::<Result<_, Eip712Error>>()?;
+
+        Ok(Self { types, hashes })
+    }
+
+    /// Returns the `hashStruct` of `data` as an instance of `primary_type`.
+    pub fn hash_struct(
+        &self,
+        primary_type: &str,
+        data: &serde_json::Value,
+    ) -> Result<[u8; 32], Eip712Error> {
+        Ok(keccak256(encode(&self.encode_data(primary_type, data)?)))
+    }
+
+    /// Returns the final EIP-712 digest of `data` under the given domain separator.
+    pub fn digest(
+        &self,
+        domain_separator: &[u8; 32],
+        primary_type: &str,
+        data: &serde_json::Value,
+    ) -> Result<[u8; 32], Eip712Error> {
+        let mut digest_input = [0u8; 66];
+        digest_input[..2].copy_from_slice(&[0x19, 0x01]);
+        digest_input[2..34].copy_from_slice(domain_separator);
+
+        if primary_type == "EIP712Domain" {
+            // compatibility with <https://github.com/MetaMask/eth-sig-util>
+            return Ok(keccak256(&digest_input[..34]));
+        }
+
+        digest_input[34..].copy_from_slice(&self.hash_struct(primary_type, data)?);
+        Ok(keccak256(digest_input))
+    }
+
+    fn encode_data(
+        &self,
+        primary_type: &str,
+        data: &serde_json::Value,
+    ) -> Result<Vec<Token>, Eip712Error> {
+        let hash = self.hashes.get(primary_type).ok_or_else(|| {
+            Eip712Error::Message(format!("No type definition found for: `{}`", primary_type))
+        })?;
+        let mut tokens = vec![Token::Uint(U256::from(hash))];
+
+        for field in &self.types[primary_type] {
+            if let Some(value) = data.get(&field.name) {
+                tokens.push(self.encode_field(&field.r#type, value)?);
+            } else if self.types.contains_key(&field.r#type) {
+                tokens.push(Token::Uint(U256::zero()));
+            } else {
+                return Err(Eip712Error::Message(format!(
+                    "No data found for: `{}`",
+                    field.name
+                )));
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    fn encode_field(
+        &self,
+        field_type: &str,
+        value: &serde_json::Value,
+    ) -> Result<Token, Eip712Error> {
+        if self.types.contains_key(field_type) {
+            let encoded = encode(&self.encode_data(field_type, value)?);
+            return Ok(encode_eip712_type(Token::Bytes(encoded)));
+        }
+
+        if let Some((element_type, _)) = field_type.rsplit_once('[') {
+            let values = value.as_array().ok_or_else(|| {
+                Eip712Error::Message(format!(
+                    "Expected array for type `{}`, but got `{}`",
+                    field_type, value
+                ))
+            })?;
+            let tokens = values
+                .iter()
+                .map(|value| self.encode_field(element_type, value))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            return Ok(encode_eip712_type(Token::Bytes(encode(&tokens))));
+        }
+
+        // Atomic types don't depend on the type set, so defer to ethers
+        encode_field(self.types, "", field_type, value)
+    }
+}
+
+/// Returns the primary type of `types`: the single struct not referenced by any other.
+pub fn infer_primary_type(types: &Types) -> Result<&str, Eip712Error> {
+    let referenced = |name: &str| {
+        types
+            .values()
+            .flatten()
+            .any(|field| field.r#type.split('[').next() == Some(name))
+    };
+
+    let candidates = types
+        .keys()
+        .map(String::as_str)
+        .filter(|name| *name != "EIP712Domain" && !referenced(name))
+        .collect::<Vec<_>>();
+
+    match candidates.as_slice() {
+        [primary_type] => Ok(primary_type),
+        _ => Err(Eip712Error::Message(format!(
+            "Unable to infer primary type, candidates: {:?}",
+            candidates
+        ))),
+    }
+}
@@ -0,0 +1,22 @@
+/*!
+Keccak-256 backend selection.
+
+By default hashing goes through `tiny-keccak`, the same implementation ethers uses.
+Building with the `asm-keccak` feature switches to the RustCrypto `keccak` permutation
+with its assembly backend, which detects the ARMv8 SHA-3 extensions at runtime and
+falls back to the portable implementation on CPUs without them.
+*/
+
+/// Computes the keccak256 digest of `data`.
+#[cfg(feature = "asm-keccak")]
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    use sha3::{Digest, Keccak256};
+
+    Keccak256::digest(data).into()
+}
+
+/// Computes the keccak256 digest of `data`.
+#[cfg(not(feature = "asm-keccak"))]
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    ethers_core::utils::keccak256(data)
+}
@@ -0,0 +1,30 @@
+/*!
+ferrite-core: the transaction building, hashing and signing behind ferrite, without
+Python.
+
+The Python extension module, the `ferrite` binary and any other binding are layers
+over this crate, so a Rust service depending on it signs through exactly the same code
+paths as the Python services using ferrite. Everything here is synchronous, holds no
+global state beyond per-thread scratch buffers, and reports errors as values rather
+than exceptions.
+
+- [`request`] parses and validates transaction JSON, field by field.
+- [`tx`] encodes transactions for signing and broadcast.
+- [`eip712`] hashes EIP-712 structs with precomputed type hashes.
+- [`signing`] parses keys and signs hashes, typed data and transactions.
+- [`address`] derives and checksums addresses.
+- [`keccak`] selects the keccak256 backend.
+
+The key cache, signing policy, approvals, rate limits and audit log are configured
+from Python and live in the extension module, not here.
+*/
+
+pub mod address;
+pub mod eip712;
+pub mod keccak;
+pub mod pool;
+pub mod request;
+pub mod signing;
+pub mod tx;
+
+pub use signing::{Error, ErrorKind};
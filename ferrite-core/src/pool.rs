@@ -0,0 +1,76 @@
+/*!
+Per-thread pool of scratch buffers for encoding output.
+
+Signing a transaction builds its RLP encoding in a heap buffer that only lives until it
+is copied out, such as into a Python object. Buffers are handed out from, and returned
+to, a small pool owned by the current thread, so hot loops and batches running on a
+thread pool reuse the same few allocations instead of going through the allocator on
+every call.
+*/
+
+use std::cell::RefCell;
+use std::ops::Deref;
+
+use bytes::BytesMut;
+
+/// Buffers are kept per thread up to this many at a time.
+const MAX_POOLED: usize = 4;
+
+/// Buffers that grew beyond this capacity are freed rather than pooled, so one huge
+/// payload doesn't pin its memory for the lifetime of the thread.
+const MAX_RETAINED_CAPACITY: usize = 1 << 20;
+
+thread_local! {
+    static POOL: RefCell<Vec<BytesMut>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A scratch buffer that returns itself to the current thread's pool when dropped.
+pub struct Buffer(Option<BytesMut>);
+
+impl Buffer {
+    /// Takes an empty buffer from the pool, allocating one if the pool is empty.
+    pub fn take() -> Self {
+        let buffer = POOL
+            .with(|pool| pool.borrow_mut().pop())
+            .unwrap_or_default();
+        Buffer(Some(buffer))
+    }
+
+    /// Takes the underlying buffer out, e.g. to hand it to an `RlpStream`.
+    pub fn into_inner(mut self) -> BytesMut {
+        self.0.take().unwrap_or_default()
+    }
+
+    /// Wraps a buffer previously obtained with [`Buffer::into_inner`].
+    pub fn from_inner(buffer: BytesMut) -> Self {
+        Buffer(Some(buffer))
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.0.as_deref().unwrap_or_default()
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        let Some(mut buffer) = self.0.take() else {
+            return;
+        };
+        if buffer.capacity() > MAX_RETAINED_CAPACITY {
+            return;
+        }
+
+        buffer.clear();
+        // The pool may already be gone if this runs during thread teardown
+        let _ = POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.len() < MAX_POOLED {
+                pool.push(buffer);
+            }
+        });
+    }
+}
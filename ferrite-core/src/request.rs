@@ -0,0 +1,347 @@
+/*!
+Transaction payload parsing and validation.
+
+Payloads are parsed field by field rather than through a single `serde` derive so that
+every error names the field it came from, and so that the transaction type can be
+chosen from the fields present the same way eth-account does.
+*/
+
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{
+    Address, Bytes, Eip1559TransactionRequest, Eip2930TransactionRequest, NameOrAddress,
+    TransactionRequest, U256, U64,
+};
+use ethers_core::utils::to_checksum;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+pub type Fields = Map<String, Value>;
+
+/// Largest accepted `data` field, in bytes. geth's transaction pool rejects any
+/// transaction over 128 KiB, so anything larger can never be included anyway.
+const MAX_DATA_LEN: usize = 128 * 1024;
+
+/// Optional checks applied while parsing, beyond the payload being well-formed.
+#[derive(Clone, Copy)]
+pub struct Checks {
+    /// Reject single-case addresses too, not only mixed-case ones with a bad checksum.
+    pub require_checksum: bool,
+    /// Reject dynamic fee transactions whose priority fee exceeds their max fee, which
+    /// no node will ever include.
+    pub fee_cap: bool,
+}
+
+impl Default for Checks {
+    fn default() -> Self {
+        Self { require_checksum: false, fee_cap: true }
+    }
+}
+
+/// Result of parsing one field: its value if present, or a message naming the field.
+pub type FieldResult<T> = Result<Option<T>, String>;
+
+/// Field errors collected over a whole payload, so that every invalid field is
+/// reported in one error rather than one per attempt.
+#[derive(Default)]
+pub struct Problems(Vec<String>);
+
+impl Problems {
+    /// Returns the parsed value, recording the error and treating the field as absent
+    /// if it was invalid.
+    pub fn check<T>(&mut self, result: FieldResult<T>) -> Option<T> {
+        result.unwrap_or_else(|message| {
+            self.0.push(message);
+            None
+        })
+    }
+
+    /// Fails with every recorded error in one message, if there were any.
+    pub fn into_message(self) -> Result<(), String> {
+        match self.0.as_slice() {
+            [] => Ok(()),
+            [message] => Err(message.clone()),
+            messages => Err(format!("{} invalid fields: {}", messages.len(), messages.join("; "))),
+        }
+    }
+}
+
+/// Turns an absent field into an error naming it.
+pub fn required<T>(name: &str, result: FieldResult<T>) -> FieldResult<T> {
+    result?.map(Some).ok_or_else(|| format!("Missing `{}`", name))
+}
+
+/// Deserializes the optional field `name`, treating `null` as absent.
+pub fn field<T: DeserializeOwned>(fields: &Fields, name: &str) -> FieldResult<T> {
+    match fields.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => T::deserialize(value)
+            .map(Some)
+            .map_err(|e| format!("Invalid `{}`: {}", name, e)),
+    }
+}
+
+/// Parses the address field `name`.
+///
+/// Mixed-case addresses must carry a valid EIP-55 checksum, as in eth-account. With
+/// `require_checksum`, single-case addresses are rejected as well.
+pub fn address(fields: &Fields, name: &str, require_checksum: bool) -> FieldResult<Address> {
+    let text = match fields.get(name) {
+        None | Some(Value::Null) => return Ok(None),
+        Some(Value::String(text)) => text,
+        Some(other) => {
+            return Err(format!(
+                "Invalid `{}`: expected a hex address string, got {}",
+                name, other
+            ))
+        }
+    };
+
+    // An empty recipient denotes contract creation
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    if digits.len() != 40 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!(
+            "Invalid `{}`: expected 20 bytes as 40 hex digits, got {:?}",
+            name, text
+        ));
+    }
+
+    let mut bytes = [0u8; 20];
+    hex::decode_to_slice(digits, &mut bytes)
+        .map_err(|e| format!("Invalid `{}`: {}", name, e))?;
+    let address = Address::from(bytes);
+
+    let mixed_case = digits.bytes().any(|b| b.is_ascii_lowercase())
+        && digits.bytes().any(|b| b.is_ascii_uppercase());
+    if mixed_case || require_checksum {
+        let checksummed = to_checksum(&address, None);
+        if checksummed[2..] != *digits {
+            return Err(format!(
+                "Invalid `{}`: {:?} does not match its EIP-55 checksum {}",
+                name, text, checksummed
+            ));
+        }
+    }
+
+    Ok(Some(address))
+}
+
+/// Parses the unsigned integer field `name`, which must fit in `bits` bits.
+///
+/// Accepts JSON integers and decimal or `0x`-prefixed hex strings.
+pub fn quantity(fields: &Fields, name: &str, bits: usize) -> FieldResult<U256> {
+    let negative = || format!("Invalid `{}`: must not be negative", name);
+    let too_large = |value: &dyn std::fmt::Display| {
+        format!(
+            "Invalid `{}`: {} exceeds the maximum of 2^{} - 1",
+            name, value, bits
+        )
+    };
+
+    let value = match fields.get(name) {
+        None | Some(Value::Null) => return Ok(None),
+        Some(Value::Number(number)) => match number.as_u64() {
+            Some(value) => U256::from(value),
+            None if number.is_i64() => return Err(negative()),
+            None => {
+                return Err(format!(
+                    "Invalid `{}`: expected an integer, got {}",
+                    name, number
+                ))
+            }
+        },
+        Some(Value::String(text)) => {
+            let text = text.trim();
+            if text.starts_with('-') {
+                return Err(negative());
+            }
+            let hex = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X"));
+            let (digits, radix) = hex.map_or((text, 10), |digits| (digits, 16));
+            let well_formed = digits.chars().all(|c| c.is_digit(radix));
+            if !well_formed || (hex.is_none() && digits.is_empty()) {
+                return Err(format!(
+                    "Invalid `{}`: {:?} is not an integer",
+                    name, text
+                ));
+            }
+
+            // The digits are valid, so parsing can only fail by overflowing
+            match digits.trim_start_matches('0') {
+                "" => U256::zero(),
+                digits => U256::from_str_radix(digits, radix).map_err(|_| too_large(&text))?,
+            }
+        }
+        Some(other) => {
+            return Err(format!(
+                "Invalid `{}`: expected an integer, got {}",
+                name, other
+            ))
+        }
+    };
+
+    if value.bits() > bits {
+        return Err(too_large(&value));
+    }
+
+    Ok(Some(value))
+}
+
+/// Parses the hex byte string field `name`.
+pub fn hex_data(fields: &Fields, name: &str) -> FieldResult<Bytes> {
+    let text = match fields.get(name) {
+        None | Some(Value::Null) => return Ok(None),
+        Some(Value::String(text)) => text,
+        Some(other) => {
+            return Err(format!(
+                "Invalid `{}`: expected a hex string, got {}",
+                name, other
+            ))
+        }
+    };
+
+    let prefix = if text.starts_with("0x") || text.starts_with("0X") { 2 } else { 0 };
+    let digits = &text[prefix..];
+
+    if let Some((offset, c)) = digits.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "Invalid `{}`: non-hex character {:?} at offset {}",
+            name,
+            c,
+            prefix + offset
+        ));
+    }
+    if digits.len() % 2 != 0 {
+        return Err(format!(
+            "Invalid `{}`: odd number of hex digits ({})",
+            name,
+            digits.len()
+        ));
+    }
+    if digits.len() / 2 > MAX_DATA_LEN {
+        return Err(format!(
+            "Invalid `{}`: {} bytes exceeds the {} byte limit",
+            name,
+            digits.len() / 2,
+            MAX_DATA_LEN
+        ));
+    }
+
+    let bytes = hex::decode(digits).map_err(|e| format!("Invalid `{}`: {}", name, e))?;
+    Ok(Some(Bytes::from(bytes)))
+}
+
+/// Returns the EIP-2718 type of the payload, inferred from its fields when not given.
+fn transaction_type(fields: &Fields, problems: &mut Problems) -> u64 {
+    let explicit = match fields.get("type") {
+        None | Some(Value::Null) => None,
+        Some(value) => {
+            let kind = match value {
+                Value::Number(number) => number.as_u64(),
+                Value::String(text) => match text.strip_prefix("0x") {
+                    Some(digits) => u64::from_str_radix(digits, 16).ok(),
+                    None => text.parse().ok(),
+                },
+                _ => None,
+            };
+            let kind = kind.filter(|kind| *kind <= 2);
+            if kind.is_none() {
+                problems.0.push(format!("Invalid `type`: unsupported transaction type {}", value));
+            }
+            kind
+        }
+    };
+
+    // An invalid explicit type falls back to inference, so the remaining fields are
+    // still checked against the most plausible type
+    let present = |name: &str| fields.get(name).is_some_and(|value| !value.is_null());
+    explicit.unwrap_or(if present("maxFeePerGas") || present("maxPriorityFeePerGas") {
+        2
+    } else if present("accessList") {
+        1
+    } else {
+        0
+    })
+}
+
+/// Parses a transaction JSON payload into the transaction type it describes.
+///
+/// Every field is checked before returning, so a payload with several invalid fields
+/// fails with a single error listing all of them.
+pub fn parse_transaction(payload: &str, checks: Checks) -> Result<TypedTransaction, String> {
+    let fields: Fields = serde_json::from_str(payload)
+        .map_err(|e| format!("Invalid Transaction JSON: {}", e))?;
+    let mut problems = Problems::default();
+
+    let to = problems
+        .check(address(&fields, "to", checks.require_checksum))
+        .map(NameOrAddress::Address);
+    let nonce = problems.check(quantity(&fields, "nonce", 64));
+    let gas = problems.check(quantity(&fields, "gas", 256));
+    let value = problems.check(quantity(&fields, "value", 256));
+    let data = match problems.check(hex_data(&fields, "data")) {
+        Some(data) => Some(data),
+        None => problems.check(hex_data(&fields, "input")),
+    };
+    let chain_id = problems
+        .check(quantity(&fields, "chainId", 64))
+        .map(|id| U64::from(id.as_u64()));
+
+    let legacy = |gas_price: Option<U256>| TransactionRequest {
+        to: to.clone(),
+        gas,
+        gas_price,
+        value,
+        data: data.clone(),
+        nonce,
+        chain_id,
+        ..Default::default()
+    };
+
+    let tx = match transaction_type(&fields, &mut problems) {
+        0 => TypedTransaction::Legacy(legacy(problems.check(quantity(&fields, "gasPrice", 256)))),
+        1 => TypedTransaction::Eip2930(Eip2930TransactionRequest::new(
+            legacy(problems.check(quantity(&fields, "gasPrice", 256))),
+            problems.check(field(&fields, "accessList")).unwrap_or_default(),
+        )),
+        _ => {
+            if fields.get("gasPrice").is_some_and(|value| !value.is_null()) {
+                problems.0.push(
+                    "Invalid `gasPrice`: not allowed in a dynamic fee transaction".to_string(),
+                );
+            }
+
+            let max_priority_fee_per_gas =
+                problems.check(quantity(&fields, "maxPriorityFeePerGas", 256));
+            let max_fee_per_gas = problems.check(quantity(&fields, "maxFeePerGas", 256));
+            if let (true, Some(priority_fee), Some(max_fee)) =
+                (checks.fee_cap, max_priority_fee_per_gas, max_fee_per_gas)
+            {
+                if priority_fee > max_fee {
+                    problems.0.push(format!(
+                        "Invalid `maxPriorityFeePerGas`: {} exceeds `maxFeePerGas` {}",
+                        priority_fee, max_fee
+                    ));
+                }
+            }
+
+            TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                to,
+                gas,
+                value,
+                data,
+                nonce,
+                access_list: problems.check(field(&fields, "accessList")).unwrap_or_default(),
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+                chain_id,
+                ..Default::default()
+            })
+        }
+    };
+
+    problems.into_message()?;
+    Ok(tx)
+}
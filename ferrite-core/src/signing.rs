@@ -0,0 +1,323 @@
+/*!
+Signing without Python: the key parsing, digest signing, EIP-712 hashing and
+transaction steps every binding shares.
+
+The Python extension module adds the key cache, the signing policy, approvals, rate
+limits and audit records on top, all of which are configured from Python. Other
+bindings call these functions directly: the `ferrite` binary does, and so can a napi-rs
+binding for Node.js, so that every language signs with one implementation. Such a
+binding doesn't get the Python-side controls unless it exposes their setters as well.
+
+Errors carry an [`ErrorKind`] for each binding to map onto its own error types; the
+extension module maps them onto its Python exception hierarchy.
+*/
+
+use std::fmt;
+
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::transaction::eip712::{Eip712, TypedData};
+use ethers_core::types::{Signature, H256, U256};
+use ethers_signers::{to_eip155_v, LocalWallet};
+use k256::ecdsa::hazmat::SignPrimitive;
+use k256::ecdsa::SigningKey;
+
+use crate::{keccak, request, tx};
+
+/// What went wrong, for bindings to pick an error type by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// An argument other than the key, transaction or typed data is invalid.
+    Invalid,
+    InvalidKey,
+    InvalidTransaction,
+    TypedData,
+    Signing,
+}
+
+/// A signing failure. Messages never contain the key.
+#[derive(Clone, Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Error { kind, message: message.into() }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Removes any rendering of `secret` that could have leaked into `message`.
+///
+/// Covers the hex forms and the `Debug` byte-list form. Every error raised while key
+/// material is in scope passes through here, so that neither error messages nor logs
+/// of them can carry the key.
+pub fn redact(message: String, secret: &[u8]) -> String {
+    if secret.is_empty() {
+        return message;
+    }
+
+    let lower = hex::encode(secret);
+    [lower.to_uppercase(), lower, format!("{:?}", secret)]
+        .iter()
+        .fold(message, |message, form| message.replace(form, "<redacted>"))
+}
+
+/// Half the secp256k1 group order, the largest `s` of a canonical signature.
+const HALF_ORDER: U256 = U256([
+    0xDFE92F46681B20A0,
+    0x5D576E7357A4501D,
+    0xFFFFFFFFFFFFFFFF,
+    0x7FFFFFFFFFFFFFFF,
+]);
+
+/// Parses a raw private key. Unlike the extension module, nothing is cached.
+pub fn wallet(private_key: &[u8]) -> Result<LocalWallet, Error> {
+    // `LocalWallet::from_bytes` panics on keys that aren't 32 bytes long, so parse the
+    // scalar through `SigningKey`, which checks the length
+    SigningKey::from_slice(private_key).map(LocalWallet::from).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidKey,
+            redact(format!("Invalid private key: {}", e), private_key),
+        )
+    })
+}
+
+/// Signs `hash` with an RFC 6979 nonce hedged with `extra_entropy` (section 3.6), as
+/// `Wallet::sign_hash` does with no extra entropy.
+fn hedged_signature(
+    key: &SigningKey,
+    hash: H256,
+    extra_entropy: &[u8; 32],
+) -> Result<Signature, k256::ecdsa::Error> {
+    let (signature, recovery_id) = key
+        .as_nonzero_scalar()
+        .as_ref()
+        .try_sign_prehashed_rfc6979::<sha2::Sha256>(&hash.0.into(), extra_entropy)?;
+    let recovery_id = recovery_id.ok_or_else(k256::ecdsa::Error::new)?;
+
+    Ok(Signature {
+        r: U256::from_big_endian(&signature.r().to_bytes()),
+        s: U256::from_big_endian(&signature.s().to_bytes()),
+        v: recovery_id.to_byte() as u64 + 27,
+    })
+}
+
+/// Signs `hash` with `wallet`, guaranteeing a low-s signature with `v` set to the
+/// recovery id + 27. `private_key` is only used to redact error messages.
+///
+/// k256 already normalizes `s`, but contracts using OpenZeppelin's ECDSA revert on
+/// high-s signatures, so every signature is checked here before it can be emitted
+/// rather than relying on each path to normalize.
+pub fn sign_digest(
+    wallet: &LocalWallet,
+    hash: H256,
+    private_key: &[u8],
+    extra_entropy: Option<&[u8; 32]>,
+) -> Result<Signature, Error> {
+    let _span = tracing::debug_span!("sign", hedged = extra_entropy.is_some()).entered();
+    let signature = match extra_entropy {
+        Some(entropy) => {
+            hedged_signature(wallet.signer(), hash, entropy).map_err(|e| e.to_string())
+        }
+        None => wallet.sign_hash(hash).map_err(|e| e.to_string()),
+    }
+    .map_err(|e| {
+        Error::new(
+            ErrorKind::Signing,
+            redact(format!("Signing failed: {}", e), private_key),
+        )
+    })?;
+
+    if signature.s > HALF_ORDER {
+        return Err(Error::new(
+            ErrorKind::Signing,
+            "Signing failed: produced a non-canonical high-s signature",
+        ));
+    }
+
+    Ok(signature)
+}
+
+/// Checks that `hash` is a 32-byte digest.
+pub fn digest(hash: &[u8]) -> Result<H256, Error> {
+    let hash: [u8; 32] = hash.try_into().map_err(|_| {
+        Error::new(
+            ErrorKind::Invalid,
+            format!("Hash must be exactly 32 bytes, got {}", hash.len()),
+        )
+    })?;
+    Ok(H256(hash))
+}
+
+/// Parses an EIP-712 TypedData JSON payload.
+pub fn parse_typed_data(payload: &str) -> Result<TypedData, Error> {
+    let _span = tracing::debug_span!("parse", bytes = payload.len()).entered();
+    serde_json::from_str(payload).map_err(|e| {
+        Error::new(ErrorKind::TypedData, format!("Invalid TypedData JSON: {}", e))
+    })
+}
+
+/// Encodes typed data according to EIP-712 to get the message hash.
+pub fn typed_data_hash(typed_data: &TypedData) -> Result<H256, Error> {
+    let _span = tracing::debug_span!("hash", primary_type = %typed_data.primary_type).entered();
+    let hash = typed_data.encode_eip712().map_err(|e| {
+        Error::new(ErrorKind::TypedData, format!("Failed to encode EIP-712 data: {}", e))
+    })?;
+    Ok(H256::from(hash))
+}
+
+/// Parses and validates a transaction JSON payload into the transaction type it
+/// describes.
+pub fn parse_transaction(
+    payload: &str,
+    checks: request::Checks,
+) -> Result<TypedTransaction, Error> {
+    let _span = tracing::debug_span!("parse", bytes = payload.len()).entered();
+    request::parse_transaction(payload, checks)
+        .map_err(|e| Error::new(ErrorKind::InvalidTransaction, e))
+}
+
+/// Sets the chain id a transaction is signed for, returning it, or `None` for a legacy
+/// transaction signed without replay protection. Typed transactions default to 1.
+pub fn chain_id_to_sign(tx: &mut TypedTransaction) -> Result<Option<u64>, Error> {
+    let chain_id = match (&*tx, tx.chain_id()) {
+        (TypedTransaction::Legacy(_), None) => None,
+        (_, chain_id) => Some(chain_id.map_or(1, |id| id.as_u64())),
+    };
+    if let Some(chain_id) = chain_id {
+        // EIP-155 `v` is the recovery id + 35 + 2 * chain id, which must not overflow
+        if chain_id > (u64::MAX - 36) / 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidTransaction,
+                format!("Invalid Transaction: chainId {} is too large for EIP-155", chain_id),
+            ));
+        }
+        tx.set_chain_id(chain_id);
+    }
+    Ok(chain_id)
+}
+
+/// Returns the `v` a transaction is signed with, from the `v` of its sighash's
+/// signature, which [`sign_digest`] sets to the recovery id + 27.
+pub fn transaction_v(tx: &TypedTransaction, chain_id: Option<u64>, v: u64) -> u64 {
+    // Already right for a legacy transaction without a chain id. Typed transactions
+    // carry the bare y-parity, as eth-account reports it
+    let recovery_id = v as u8 - 27;
+    match (tx, chain_id) {
+        (TypedTransaction::Legacy(_), Some(chain_id)) => to_eip155_v(recovery_id, chain_id),
+        (TypedTransaction::Legacy(_), None) => v,
+        _ => recovery_id as u64,
+    }
+}
+
+/// Returns the 0/1 y-parity of a signature from any of the `v` conventions ferrite
+/// emits: bare y-parity (typed transactions), 27/28 (messages and legacy transactions
+/// without a chain id) or EIP-155 (legacy transactions with one).
+///
+/// No convention uses any other `v` below 35; for those the low bit is returned rather
+/// than underflowing.
+pub fn y_parity(v: u64) -> u64 {
+    match v {
+        0 | 1 => v,
+        27 | 28 => v - 27,
+        35.. => (v - 35) % 2,
+        _ => v % 2,
+    }
+}
+
+/// Returns the 65-byte `r || s || v` form of a signature, with `v` as 27 + y-parity
+/// whatever `v` convention the signed object uses.
+pub fn signature_bytes(signature: &Signature) -> [u8; 65] {
+    let mut bytes = [0u8; 65];
+    signature.r.to_big_endian(&mut bytes[..32]);
+    signature.s.to_big_endian(&mut bytes[32..64]);
+    bytes[64] = 27 + y_parity(signature.v) as u8;
+    bytes
+}
+
+/// A signed transaction.
+pub struct SignedTransaction {
+    pub signature: Signature,
+    pub raw_transaction: Vec<u8>,
+    pub hash: H256,
+}
+
+/// Signs a 32-byte hash with a raw private key.
+pub fn sign_hash(hash: &[u8], private_key: &[u8]) -> Result<Signature, Error> {
+    let hash = digest(hash)?;
+    sign_digest(&wallet(private_key)?, hash, private_key, None)
+}
+
+/// Signs an EIP-712 TypedData JSON payload with a raw private key.
+pub fn sign_typed_data(payload: &str, private_key: &[u8]) -> Result<Signature, Error> {
+    let hash = typed_data_hash(&parse_typed_data(payload)?)?;
+    sign_digest(&wallet(private_key)?, hash, private_key, None)
+}
+
+/// Signs a transaction JSON payload, in the shape `sign_transaction` takes, with a raw
+/// private key.
+pub fn sign_transaction(payload: &str, private_key: &[u8]) -> Result<SignedTransaction, Error> {
+    let tx = parse_transaction(payload, request::Checks::default())?;
+    sign_parsed_transaction(tx, &wallet(private_key)?)
+}
+
+/// Signs a parsed transaction with `wallet`.
+pub fn sign_parsed_transaction(
+    mut tx: TypedTransaction,
+    wallet: &LocalWallet,
+) -> Result<SignedTransaction, Error> {
+    let chain_id = chain_id_to_sign(&mut tx)?;
+    let fields = tx::UnsignedFields::new(&tx);
+    let private_key = zeroize::Zeroizing::new(wallet.signer().to_bytes());
+    let mut signature = sign_digest(wallet, fields.sighash(), &private_key, None)?;
+    signature.v = transaction_v(&tx, chain_id, signature.v);
+
+    let raw_transaction = fields.encode_signed(&signature).to_vec();
+    let hash = H256(keccak::keccak256(&raw_transaction));
+    Ok(SignedTransaction { signature, raw_transaction, hash })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn y_parity_of_bare_parity() {
+        assert_eq!(y_parity(0), 0);
+        assert_eq!(y_parity(1), 1);
+    }
+
+    #[test]
+    fn y_parity_of_27_28() {
+        assert_eq!(y_parity(27), 0);
+        assert_eq!(y_parity(28), 1);
+    }
+
+    #[test]
+    fn y_parity_of_eip155() {
+        // Chain ids 0, 1 and 137
+        assert_eq!(y_parity(35), 0);
+        assert_eq!(y_parity(36), 1);
+        assert_eq!(y_parity(37), 0);
+        assert_eq!(y_parity(38), 1);
+        assert_eq!(y_parity(309), 0);
+        assert_eq!(y_parity(310), 1);
+        assert_eq!(y_parity(u64::MAX), 0);
+    }
+
+    #[test]
+    fn y_parity_of_unused_v_does_not_underflow() {
+        for v in (2..27).chain(29..35) {
+            assert_eq!(y_parity(v), v % 2);
+        }
+    }
+}
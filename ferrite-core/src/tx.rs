@@ -0,0 +1,152 @@
+/*!
+Transaction RLP encoding into pooled buffers.
+
+Produces byte-for-byte the same signing hash and signed encoding as
+`TypedTransaction::sighash` and `TypedTransaction::rlp_signed`, which build each
+encoding from scratch through several intermediate allocations, but encodes the
+unsigned fields once and writes everything into [`Buffer`]s taken from the per-thread
+pool.
+*/
+
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{Signature, H256, U64};
+use ethers_core::utils::rlp::{Encodable, RlpStream};
+
+use crate::keccak::keccak256;
+use crate::pool::Buffer;
+
+/// Appends `value`, or the empty string when it is unset, as ethers does.
+fn append_opt<T: Encodable>(rlp: &mut RlpStream, value: &Option<T>) {
+    match value {
+        Some(value) => rlp.append(value),
+        None => rlp.append(&""),
+    };
+}
+
+/// Converts an EIP-155 `v` back to a 0/1 y-parity for typed transactions.
+fn normalize_v(v: u64, chain_id: U64) -> u64 {
+    if v > 1 {
+        v - chain_id.as_u64() * 2 - 35
+    } else {
+        v
+    }
+}
+
+/// RLP encoding of a transaction's unsigned fields.
+///
+/// The signing hash and the signed encoding both wrap the same run of fields in a
+/// list, so the fields are encoded once and copied into each list rather than
+/// re-encoded, which matters for large calldata.
+pub struct UnsignedFields<'a> {
+    tx: &'a TypedTransaction,
+    fields: Buffer,
+    count: usize,
+}
+
+impl<'a> UnsignedFields<'a> {
+    /// Encodes the unsigned fields of `tx`.
+    pub fn new(tx: &'a TypedTransaction) -> Self {
+        let mut rlp = RlpStream::new_with_buffer(Buffer::take().into_inner());
+
+        let count = match tx {
+            TypedTransaction::Legacy(tx) => {
+                append_opt(&mut rlp, &tx.nonce);
+                append_opt(&mut rlp, &tx.gas_price);
+                append_opt(&mut rlp, &tx.gas);
+                append_opt(&mut rlp, &tx.to.as_ref());
+                append_opt(&mut rlp, &tx.value);
+                append_opt(&mut rlp, &tx.data.as_deref());
+                6
+            }
+            TypedTransaction::Eip2930(inner) => {
+                let tx = &inner.tx;
+                rlp.append(&tx.chain_id.unwrap_or_else(U64::one));
+                append_opt(&mut rlp, &tx.nonce);
+                append_opt(&mut rlp, &tx.gas_price);
+                append_opt(&mut rlp, &tx.gas);
+                append_opt(&mut rlp, &tx.to.as_ref());
+                append_opt(&mut rlp, &tx.value);
+                append_opt(&mut rlp, &tx.data.as_deref());
+                rlp.append(&inner.access_list);
+                8
+            }
+            TypedTransaction::Eip1559(tx) => {
+                append_opt(&mut rlp, &tx.chain_id);
+                append_opt(&mut rlp, &tx.nonce);
+                append_opt(&mut rlp, &tx.max_priority_fee_per_gas);
+                append_opt(&mut rlp, &tx.max_fee_per_gas);
+                append_opt(&mut rlp, &tx.gas);
+                append_opt(&mut rlp, &tx.to.as_ref());
+                append_opt(&mut rlp, &tx.value);
+                append_opt(&mut rlp, &tx.data.as_deref());
+                rlp.append(&tx.access_list);
+                9
+            }
+        };
+
+        Self { tx, fields: Buffer::from_inner(rlp.out()), count }
+    }
+
+    /// Returns a list stream over the fields, prefixed with the EIP-2718 type byte for
+    /// typed transactions and leaving room for `trailing` more items.
+    fn list(&self, trailing: usize) -> RlpStream {
+        let mut buffer = Buffer::take().into_inner();
+        match self.tx {
+            TypedTransaction::Legacy(_) => {}
+            TypedTransaction::Eip2930(_) => buffer.extend_from_slice(&[0x01]),
+            TypedTransaction::Eip1559(_) => buffer.extend_from_slice(&[0x02]),
+        }
+
+        let mut rlp = RlpStream::new_with_buffer(buffer);
+        rlp.begin_list(self.count + trailing);
+        rlp.append_raw(&self.fields, self.count);
+        rlp
+    }
+
+    /// Returns the hash the transaction is signed over, as `TypedTransaction::sighash`.
+    pub fn sighash(&self) -> H256 {
+        let _span = tracing::debug_span!("hash").entered();
+        H256(keccak256(&self.unsigned()))
+    }
+
+    /// Returns the unsigned encoding of the transaction, which [`Self::sighash`] hashes.
+    pub fn unsigned(&self) -> Buffer {
+        let rlp = match self.tx {
+            TypedTransaction::Legacy(tx) => match tx.chain_id {
+                // EIP-155 replay protection
+                Some(chain_id) => {
+                    let mut rlp = self.list(3);
+                    rlp.append(&chain_id);
+                    rlp.append(&0u8);
+                    rlp.append(&0u8);
+                    rlp
+                }
+                None => self.list(0),
+            },
+            _ => self.list(0),
+        };
+
+        Buffer::from_inner(rlp.out())
+    }
+
+    /// Returns the signed encoding of the transaction, as `TypedTransaction::rlp_signed`.
+    pub fn encode_signed(&self, signature: &Signature) -> Buffer {
+        let mut rlp = self.list(3);
+
+        match self.tx {
+            TypedTransaction::Legacy(_) => rlp.append(&signature.v),
+            TypedTransaction::Eip2930(inner) => {
+                let chain_id = inner.tx.chain_id.unwrap_or_else(U64::one);
+                rlp.append(&normalize_v(signature.v, chain_id))
+            }
+            TypedTransaction::Eip1559(tx) => {
+                let chain_id = tx.chain_id.unwrap_or_else(U64::one);
+                rlp.append(&normalize_v(signature.v, chain_id))
+            }
+        };
+        rlp.append(&signature.r);
+        rlp.append(&signature.s);
+
+        Buffer::from_inner(rlp.out())
+    }
+}
@@ -0,0 +1,133 @@
+#ifndef FERRITE_H
+#define FERRITE_H
+
+/* Generated with cbindgen from ferrite-ffi/src/lib.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Outcome of a call into ferrite.
+ */
+typedef enum {
+  FERRITE_STATUS_OK = 0,
+  /**
+   * An argument is null, not UTF-8 or otherwise unusable.
+   */
+  FERRITE_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * The private key is not a valid secp256k1 scalar.
+   */
+  FERRITE_STATUS_INVALID_KEY = 2,
+  /**
+   * The transaction JSON is malformed or cannot be signed as given.
+   */
+  FERRITE_STATUS_INVALID_TRANSACTION = 3,
+  /**
+   * The EIP-712 typed data JSON is malformed or cannot be encoded.
+   */
+  FERRITE_STATUS_TYPED_DATA = 4,
+  /**
+   * Signing failed.
+   */
+  FERRITE_STATUS_SIGNING_FAILED = 5,
+  /**
+   * The output buffer is too small; the required length has been written.
+   */
+  FERRITE_STATUS_BUFFER_TOO_SMALL = 6,
+  /**
+   * ferrite panicked. This is a bug.
+   */
+  FERRITE_STATUS_INTERNAL = 7,
+} FerriteStatus;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns the message describing the last failed call on this thread, or an empty
+ * string if it succeeded. The string stays valid until the thread's next call into
+ * ferrite.
+ */
+const char *ferrite_last_error(void);
+
+/**
+ * Returns the library version, as a static NUL-terminated string.
+ */
+const char *ferrite_version(void);
+
+/**
+ * Computes the keccak256 digest of `len` bytes at `data` into `hash_out`.
+ *
+ * # Safety
+ * `data` must point to `len` readable bytes, or may be null if `len` is 0.
+ * `hash_out` must point to 32 writable bytes.
+ */
+FerriteStatus ferrite_keccak256(const uint8_t *data, size_t len, uint8_t *hash_out);
+
+/**
+ * Writes the EIP-55 checksummed address of a private key into `address_out`, as 42
+ * characters and a terminating NUL.
+ *
+ * # Safety
+ * `private_key` must point to 32 readable bytes and `address_out` to 43 writable ones.
+ */
+FerriteStatus ferrite_address(const uint8_t *private_key, char *address_out);
+
+/**
+ * Signs a 32-byte hash, writing the 65-byte `r || s || v` signature into
+ * `signature_out`.
+ *
+ * # Safety
+ * `hash` and `private_key` must each point to 32 readable bytes, and `signature_out`
+ * to 65 writable ones.
+ */
+FerriteStatus ferrite_sign_hash(const uint8_t *hash,
+                                const uint8_t *private_key,
+                                uint8_t *signature_out);
+
+/**
+ * Signs EIP-712 typed data, given as NUL-terminated JSON in the shape
+ * `eth_signTypedData_v4` takes, writing the 65-byte `r || s || v` signature into
+ * `signature_out`.
+ *
+ * # Safety
+ * `typed_data_json` must be a NUL-terminated string, `private_key` must point to 32
+ * readable bytes, and `signature_out` to 65 writable ones.
+ */
+FerriteStatus ferrite_sign_typed_data(const char *typed_data_json,
+                                      const uint8_t *private_key,
+                                      uint8_t *signature_out);
+
+/**
+ * Signs a transaction, given as NUL-terminated JSON in the shape the Python
+ * `sign_transaction` takes, writing the raw signed transaction into `raw_out` and its
+ * 32-byte hash into `hash_out`.
+ *
+ * The length of the raw transaction is written to `raw_len` whether or not it fits in
+ * the `raw_capacity` bytes at `raw_out`. If it doesn't, nothing else is written and
+ * `FERRITE_STATUS_BUFFER_TOO_SMALL` is returned, so the call can be retried with a
+ * larger buffer.
+ *
+ * # Safety
+ * `transaction_json` must be a NUL-terminated string, `private_key` must point to 32
+ * readable bytes, `raw_out` to `raw_capacity` writable bytes, `raw_len` to a writable
+ * `size_t`, and `hash_out` to 32 writable bytes. `hash_out` may be null if the hash
+ * isn't needed.
+ */
+FerriteStatus ferrite_sign_transaction(const char *transaction_json,
+                                       const uint8_t *private_key,
+                                       uint8_t *raw_out,
+                                       size_t raw_capacity,
+                                       size_t *raw_len,
+                                       uint8_t *hash_out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FERRITE_H */
@@ -0,0 +1,248 @@
+/*!
+C interface to ferrite, for C and C++ systems that embed the signer directly.
+
+Every function returns a [`FerriteStatus`] and writes its results into buffers the
+caller owns, so no memory crosses the boundary in either direction. On failure,
+`ferrite_last_error` returns a message describing it, owned by the library and valid
+on the calling thread until its next call into ferrite. Private keys are never part of
+error messages.
+
+Signing goes through `ferrite_core`, the same code the Python module runs, so a C
+caller gets byte-identical signatures and raw transactions for the same inputs. The
+header, `include/ferrite.h`, is generated from this file with cbindgen.
+*/
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use ferrite_core::{address, keccak, signing, ErrorKind};
+
+/// Outcome of a call into ferrite.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FerriteStatus {
+    Ok = 0,
+    /// An argument is null, not UTF-8 or otherwise unusable.
+    InvalidArgument = 1,
+    /// The private key is not a valid secp256k1 scalar.
+    InvalidKey = 2,
+    /// The transaction JSON is malformed or cannot be signed as given.
+    InvalidTransaction = 3,
+    /// The EIP-712 typed data JSON is malformed or cannot be encoded.
+    TypedData = 4,
+    /// Signing failed.
+    SigningFailed = 5,
+    /// The output buffer is too small; the required length has been written.
+    BufferTooSmall = 6,
+    /// ferrite panicked. This is a bug.
+    Internal = 7,
+}
+
+impl From<ErrorKind> for FerriteStatus {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::Invalid => FerriteStatus::InvalidArgument,
+            ErrorKind::InvalidKey => FerriteStatus::InvalidKey,
+            ErrorKind::InvalidTransaction => FerriteStatus::InvalidTransaction,
+            ErrorKind::TypedData => FerriteStatus::TypedData,
+            ErrorKind::Signing => FerriteStatus::SigningFailed,
+        }
+    }
+}
+
+/// Length of a private key, a hash, and a 65-byte `r || s || v` signature.
+const KEY_LEN: usize = 32;
+const HASH_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 65;
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+struct Failure(FerriteStatus, String);
+
+impl From<signing::Error> for Failure {
+    fn from(error: signing::Error) -> Self {
+        Failure(error.kind.into(), error.message)
+    }
+}
+
+fn invalid(message: impl Into<String>) -> Failure {
+    Failure(FerriteStatus::InvalidArgument, message.into())
+}
+
+/// Runs `f`, recording its error message for `ferrite_last_error`, and turns panics
+/// into [`FerriteStatus::Internal`] rather than unwinding into C.
+fn call(f: impl FnOnce() -> Result<(), Failure>) -> FerriteStatus {
+    let (status, message) = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => (FerriteStatus::Ok, String::new()),
+        Ok(Err(Failure(status, message))) => (status, message),
+        Err(_) => (FerriteStatus::Internal, "ferrite panicked".to_string()),
+    };
+    // Messages come from Rust strings, which may contain NULs in quoted input
+    let message = CString::new(message.replace('\0', "\\0")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    status
+}
+
+/// Borrows `len` bytes at `data`, which may only be null when `len` is 0.
+unsafe fn bytes<'a>(data: *const u8, len: usize, name: &str) -> Result<&'a [u8], Failure> {
+    match (data.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(invalid(format!("{} is null", name))),
+        (false, _) => Ok(slice::from_raw_parts(data, len)),
+    }
+}
+
+/// Borrows a NUL-terminated UTF-8 string.
+unsafe fn text<'a>(data: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if data.is_null() {
+        return Err(invalid(format!("{} is null", name)));
+    }
+    CStr::from_ptr(data).to_str().map_err(|_| invalid(format!("{} is not UTF-8", name)))
+}
+
+/// Copies `data` into the caller's `len`-byte buffer at `out`.
+unsafe fn write(data: &[u8], out: *mut u8, len: usize, name: &str) -> Result<(), Failure> {
+    if out.is_null() {
+        return Err(invalid(format!("{} is null", name)));
+    }
+    if data.len() > len {
+        return Err(Failure(FerriteStatus::BufferTooSmall, format!("{} is too small", name)));
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), out, data.len());
+    Ok(())
+}
+
+/// Returns the message describing the last failed call on this thread, or an empty
+/// string if it succeeded. The string stays valid until the thread's next call into
+/// ferrite.
+#[no_mangle]
+pub extern "C" fn ferrite_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// Returns the library version, as a static NUL-terminated string.
+#[no_mangle]
+pub extern "C" fn ferrite_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Computes the keccak256 digest of `len` bytes at `data` into `hash_out`.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes, or may be null if `len` is 0.
+/// `hash_out` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_keccak256(
+    data: *const u8,
+    len: usize,
+    hash_out: *mut u8,
+) -> FerriteStatus {
+    call(|| {
+        let digest = keccak::keccak256(bytes(data, len, "data")?);
+        write(&digest, hash_out, HASH_LEN, "hash_out")
+    })
+}
+
+/// Writes the EIP-55 checksummed address of a private key into `address_out`, as 42
+/// characters and a terminating NUL.
+///
+/// # Safety
+/// `private_key` must point to 32 readable bytes and `address_out` to 43 writable ones.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_address(
+    private_key: *const u8,
+    address_out: *mut c_char,
+) -> FerriteStatus {
+    call(|| {
+        let wallet = signing::wallet(bytes(private_key, KEY_LEN, "private_key")?)?;
+        let key = wallet.signer().verifying_key();
+        let address = CString::new(address::checksum(&address::key_address(key), None))
+            .map_err(|_| invalid("address contains a NUL"))?;
+        write(address.as_bytes_with_nul(), address_out.cast(), 43, "address_out")
+    })
+}
+
+/// Signs a 32-byte hash, writing the 65-byte `r || s || v` signature into
+/// `signature_out`.
+///
+/// # Safety
+/// `hash` and `private_key` must each point to 32 readable bytes, and `signature_out`
+/// to 65 writable ones.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_sign_hash(
+    hash: *const u8,
+    private_key: *const u8,
+    signature_out: *mut u8,
+) -> FerriteStatus {
+    call(|| {
+        let hash = bytes(hash, HASH_LEN, "hash")?;
+        let signature = signing::sign_hash(hash, bytes(private_key, KEY_LEN, "private_key")?)?;
+        write(&signing::signature_bytes(&signature), signature_out, SIGNATURE_LEN, "signature_out")
+    })
+}
+
+/// Signs EIP-712 typed data, given as NUL-terminated JSON in the shape
+/// `eth_signTypedData_v4` takes, writing the 65-byte `r || s || v` signature into
+/// `signature_out`.
+///
+/// # Safety
+/// `typed_data_json` must be a NUL-terminated string, `private_key` must point to 32
+/// readable bytes, and `signature_out` to 65 writable ones.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_sign_typed_data(
+    typed_data_json: *const c_char,
+    private_key: *const u8,
+    signature_out: *mut u8,
+) -> FerriteStatus {
+    call(|| {
+        let payload = text(typed_data_json, "typed_data_json")?;
+        let private_key = bytes(private_key, KEY_LEN, "private_key")?;
+        let signature = signing::sign_typed_data(payload, private_key)?;
+        write(&signing::signature_bytes(&signature), signature_out, SIGNATURE_LEN, "signature_out")
+    })
+}
+
+/// Signs a transaction, given as NUL-terminated JSON in the shape the Python
+/// `sign_transaction` takes, writing the raw signed transaction into `raw_out` and its
+/// 32-byte hash into `hash_out`.
+///
+/// The length of the raw transaction is written to `raw_len` whether or not it fits in
+/// the `raw_capacity` bytes at `raw_out`. If it doesn't, nothing else is written and
+/// `FERRITE_STATUS_BUFFER_TOO_SMALL` is returned, so the call can be retried with a
+/// larger buffer.
+///
+/// # Safety
+/// `transaction_json` must be a NUL-terminated string, `private_key` must point to 32
+/// readable bytes, `raw_out` to `raw_capacity` writable bytes, `raw_len` to a writable
+/// `size_t`, and `hash_out` to 32 writable bytes. `hash_out` may be null if the hash
+/// isn't needed.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_sign_transaction(
+    transaction_json: *const c_char,
+    private_key: *const u8,
+    raw_out: *mut u8,
+    raw_capacity: usize,
+    raw_len: *mut usize,
+    hash_out: *mut u8,
+) -> FerriteStatus {
+    call(|| {
+        let payload = text(transaction_json, "transaction_json")?;
+        let private_key = bytes(private_key, KEY_LEN, "private_key")?;
+        if raw_len.is_null() {
+            return Err(invalid("raw_len is null"));
+        }
+
+        let signed = signing::sign_transaction(payload, private_key)?;
+        *raw_len = signed.raw_transaction.len();
+        write(&signed.raw_transaction, raw_out, raw_capacity, "raw_out")?;
+        if !hash_out.is_null() {
+            write(signed.hash.as_bytes(), hash_out, HASH_LEN, "hash_out")?;
+        }
+        Ok(())
+    })
+}
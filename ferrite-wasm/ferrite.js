@@ -0,0 +1,134 @@
+// JavaScript API over ferrite_wasm.wasm, for browsers, Node and edge runtimes.
+//
+//   import { init } from "./ferrite.js";
+//   const ferrite = await init(fetch("ferrite_wasm.wasm"));
+//   const { rawTransaction } = ferrite.signTransaction(tx, privateKey);
+//
+// Byte strings go in as Uint8Array or 0x-prefixed hex and come out as 0x-prefixed hex.
+// Failures throw a FerriteError whose `kind` names the category, matching the
+// exception classes of the Python module.
+
+const KINDS = {
+  1: "invalid",
+  2: "invalid_key",
+  3: "invalid_transaction",
+  4: "typed_data",
+  5: "signing",
+};
+
+export class FerriteError extends Error {
+  constructor(kind, message) {
+    super(message);
+    this.name = "FerriteError";
+    this.kind = kind;
+  }
+}
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+function toBytes(value, length) {
+  let bytes = value;
+  if (typeof value === "string") {
+    const digits = value.startsWith("0x") ? value.slice(2) : value;
+    if (digits.length % 2 !== 0 || /[^0-9a-fA-F]/.test(digits)) {
+      throw new FerriteError("invalid", "Expected 0x-prefixed hex");
+    }
+    bytes = new Uint8Array(digits.length / 2);
+    for (let i = 0; i < bytes.length; i++) {
+      bytes[i] = parseInt(digits.slice(2 * i, 2 * i + 2), 16);
+    }
+  }
+  if (!(bytes instanceof Uint8Array)) {
+    throw new FerriteError("invalid", "Expected a Uint8Array or hex string");
+  }
+  if (length !== undefined && bytes.length !== length) {
+    throw new FerriteError("invalid", `Expected ${length} bytes, got ${bytes.length}`);
+  }
+  return bytes;
+}
+
+function toHex(bytes) {
+  return "0x" + Array.from(bytes, (byte) => byte.toString(16).padStart(2, "0")).join("");
+}
+
+function toJson(value) {
+  return encoder.encode(typeof value === "string" ? value : JSON.stringify(value));
+}
+
+// Instantiates the module from a Response, a promise of one, a BufferSource or a
+// compiled WebAssembly.Module.
+export async function init(source) {
+  source = await source;
+  let instance;
+  if (source instanceof WebAssembly.Module) {
+    instance = await WebAssembly.instantiate(source, {});
+  } else if (typeof Response !== "undefined" && source instanceof Response) {
+    ({ instance } = await WebAssembly.instantiate(await source.arrayBuffer(), {}));
+  } else {
+    ({ instance } = await WebAssembly.instantiate(source, {}));
+  }
+  const wasm = instance.exports;
+
+  // Copies each input into module memory, runs `f` with their addresses and lengths,
+  // and wipes and frees the copies again, since they may hold keys.
+  function call(inputs, f) {
+    const buffers = inputs.map((bytes) => {
+      const pointer = wasm.ferrite_alloc(bytes.length);
+      new Uint8Array(wasm.memory.buffer, pointer, bytes.length).set(bytes);
+      return [pointer, bytes.length];
+    });
+    try {
+      const status = f(...buffers);
+      const result = new Uint8Array(
+        wasm.memory.buffer,
+        wasm.ferrite_result_ptr(),
+        wasm.ferrite_result_len(),
+      ).slice();
+      if (status !== 0) {
+        throw new FerriteError(KINDS[status] ?? "internal", decoder.decode(result));
+      }
+      return result;
+    } finally {
+      for (const [pointer, length] of buffers) {
+        wasm.ferrite_free(pointer, length);
+      }
+    }
+  }
+
+  return {
+    keccak256(data) {
+      const bytes = typeof data === "string" && !data.startsWith("0x")
+        ? encoder.encode(data)
+        : toBytes(data);
+      return toHex(call([bytes], ([p, n]) => wasm.ferrite_keccak256(p, n)));
+    },
+
+    hashTypedData(typedData) {
+      return toHex(call([toJson(typedData)], ([p, n]) => wasm.ferrite_hash_typed_data(p, n)));
+    },
+
+    address(privateKey) {
+      const key = toBytes(privateKey, 32);
+      return decoder.decode(call([key], ([k]) => wasm.ferrite_address(k)));
+    },
+
+    signHash(hash, privateKey) {
+      const inputs = [toBytes(hash, 32), toBytes(privateKey, 32)];
+      const result = call(inputs, ([h], [k]) => wasm.ferrite_sign_hash(h, k));
+      return JSON.parse(decoder.decode(result));
+    },
+
+    signTypedData(typedData, privateKey) {
+      const inputs = [toJson(typedData), toBytes(privateKey, 32)];
+      const result = call(inputs, ([p, n], [k]) => wasm.ferrite_sign_typed_data(p, n, k));
+      return JSON.parse(decoder.decode(result));
+    },
+
+    signTransaction(transaction, privateKey) {
+      const inputs = [toJson(transaction), toBytes(privateKey, 32)];
+      const result = call(inputs, ([p, n], [k]) => wasm.ferrite_sign_transaction(p, n, k));
+      return JSON.parse(decoder.decode(result));
+    },
+  };
+}
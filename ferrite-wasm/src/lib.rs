@@ -0,0 +1,216 @@
+/*!
+WebAssembly build of ferrite, for browsers and edge functions.
+
+Built for `wasm32-unknown-unknown`, the module has no imports, so it runs anywhere
+WebAssembly does, and `ferrite.js` next to this crate wraps it in a JavaScript API.
+Signing goes through `ferrite_core`, the same code the Python module runs, so the
+same inputs give byte-identical signatures and raw transactions.
+
+The exports pass data through the module's memory: the caller copies its inputs into
+buffers from `ferrite_alloc` and releases them with `ferrite_free`, which wipes them
+first since they may hold keys. Every call returns a status, 0 on success or an
+[`ErrorKind`] code, and leaves its result in a buffer read with `ferrite_result_ptr`
+and `ferrite_result_len`: the output on success and the error message on failure. That
+buffer is overwritten by the next call.
+*/
+
+use std::cell::RefCell;
+use std::slice;
+
+use ethers_core::types::Signature;
+use ferrite_core::{address, keccak, signing, ErrorKind};
+use serde_json::json;
+use zeroize::Zeroize;
+
+const KEY_LEN: usize = 32;
+const HASH_LEN: usize = 32;
+
+thread_local! {
+    static RESULT: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns the status code of a failure.
+fn status(kind: ErrorKind) -> u32 {
+    match kind {
+        ErrorKind::Invalid => 1,
+        ErrorKind::InvalidKey => 2,
+        ErrorKind::InvalidTransaction => 3,
+        ErrorKind::TypedData => 4,
+        ErrorKind::Signing => 5,
+    }
+}
+
+/// Stores the result of `f` for `ferrite_result_ptr` and returns its status.
+fn call(f: impl FnOnce() -> Result<Vec<u8>, signing::Error>) -> u32 {
+    let (status, result) = match f() {
+        Ok(output) => (0, output),
+        Err(error) => (status(error.kind), error.message.into_bytes()),
+    };
+    RESULT.with(|last| {
+        let mut last = last.borrow_mut();
+        last.zeroize();
+        *last = result;
+    });
+    status
+}
+
+/// Borrows `len` bytes of module memory at `data`.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes, or `len` must be 0.
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        return &[];
+    }
+    slice::from_raw_parts(data, len)
+}
+
+/// Borrows `len` bytes of UTF-8 text at `data`.
+///
+/// # Safety
+/// As [`bytes`].
+unsafe fn text<'a>(data: *const u8, len: usize) -> Result<&'a str, signing::Error> {
+    std::str::from_utf8(bytes(data, len))
+        .map_err(|_| signing::Error::new(ErrorKind::Invalid, "Input is not UTF-8"))
+}
+
+/// Returns the JSON object the Python module returns for a signature, with byte
+/// strings as 0x-prefixed hex.
+fn signature_json(signature: &Signature) -> serde_json::Value {
+    let bytes = signing::signature_bytes(signature);
+    json!({
+        "r": format!("0x{}", hex::encode(&bytes[..32])),
+        "s": format!("0x{}", hex::encode(&bytes[32..64])),
+        "v": signature.v,
+        "y_parity": signing::y_parity(signature.v),
+        "signature": format!("0x{}", hex::encode(bytes)),
+    })
+}
+
+/// Reserves `len` bytes of module memory for an input.
+#[no_mangle]
+pub extern "C" fn ferrite_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let data = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    data
+}
+
+/// Wipes and releases a buffer from `ferrite_alloc`.
+///
+/// # Safety
+/// `data` must have come from `ferrite_alloc(len)` and not been released already.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_free(data: *mut u8, len: usize) {
+    let mut buffer = Vec::from_raw_parts(data, len, len);
+    buffer.zeroize();
+}
+
+/// Returns where the result of the last call starts.
+#[no_mangle]
+pub extern "C" fn ferrite_result_ptr() -> *const u8 {
+    RESULT.with(|last| last.borrow().as_ptr())
+}
+
+/// Returns the length of the result of the last call.
+#[no_mangle]
+pub extern "C" fn ferrite_result_len() -> usize {
+    RESULT.with(|last| last.borrow().len())
+}
+
+/// Computes the keccak256 digest of `len` bytes at `data`. The result is the 32-byte
+/// digest.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_keccak256(data: *const u8, len: usize) -> u32 {
+    call(|| Ok(keccak::keccak256(bytes(data, len)).to_vec()))
+}
+
+/// Computes the EIP-712 hash of typed data, given as `len` bytes of JSON at `data`.
+/// The result is the 32-byte hash.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_hash_typed_data(data: *const u8, len: usize) -> u32 {
+    call(|| {
+        let typed_data = signing::parse_typed_data(text(data, len)?)?;
+        Ok(signing::typed_data_hash(&typed_data)?.as_bytes().to_vec())
+    })
+}
+
+/// Derives the address of a 32-byte private key. The result is the EIP-55 checksummed
+/// address as text.
+///
+/// # Safety
+/// `private_key` must point to 32 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_address(private_key: *const u8) -> u32 {
+    call(|| {
+        let wallet = signing::wallet(bytes(private_key, KEY_LEN))?;
+        let key = wallet.signer().verifying_key();
+        Ok(address::checksum(&address::key_address(key), None).into_bytes())
+    })
+}
+
+/// Signs a 32-byte hash with a 32-byte private key. The result is the signature as a
+/// JSON object of `r`, `s`, `v`, `y_parity` and `signature`.
+///
+/// # Safety
+/// `hash` and `private_key` must each point to 32 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_sign_hash(hash: *const u8, private_key: *const u8) -> u32 {
+    call(|| {
+        let signature = signing::sign_hash(bytes(hash, HASH_LEN), bytes(private_key, KEY_LEN))?;
+        Ok(signature_json(&signature).to_string().into_bytes())
+    })
+}
+
+/// Signs typed data, given as `len` bytes of JSON at `data`, with a 32-byte private
+/// key. The result is as for `ferrite_sign_hash`.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes and `private_key` to 32.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_sign_typed_data(
+    data: *const u8,
+    len: usize,
+    private_key: *const u8,
+) -> u32 {
+    call(|| {
+        let signature = signing::sign_typed_data(text(data, len)?, bytes(private_key, KEY_LEN))?;
+        Ok(signature_json(&signature).to_string().into_bytes())
+    })
+}
+
+/// Signs a transaction, given as `len` bytes of JSON at `data` in the shape the Python
+/// `sign_transaction` takes, with a 32-byte private key. The result is a JSON object of
+/// the signature fields plus `rawTransaction` and `hash`.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes and `private_key` to 32.
+#[no_mangle]
+pub unsafe extern "C" fn ferrite_sign_transaction(
+    data: *const u8,
+    len: usize,
+    private_key: *const u8,
+) -> u32 {
+    call(|| {
+        let signed = signing::sign_transaction(text(data, len)?, bytes(private_key, KEY_LEN))?;
+        let mut result = signature_json(&signed.signature);
+        result["rawTransaction"] = format!("0x{}", hex::encode(&signed.raw_transaction)).into();
+        result["hash"] = format!("0x{:x}", signed.hash).into();
+        Ok(result.to_string().into_bytes())
+    })
+}
+
+/// Always fails: nothing in ferrite needs randomness.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn no_randomness(_: &mut [u8]) -> Result<(), getrandom::Error> {
+    Err(getrandom::Error::UNSUPPORTED)
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+getrandom::register_custom_getrandom!(no_randomness);
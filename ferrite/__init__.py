@@ -6,16 +6,281 @@ performance increase.
 """
 
 import logging
-from typing import Any, cast
+from typing import Any, Dict, List, Optional, cast
 
 from eth_account import Account
 from eth_account.messages import SignableMessage
 from eth_account.datastructures import SignedMessage
-from .account import patch_eth_account
+from .account import addresses_from_keys, addresses_from_mnemonic
+from .account import audit_context, decrypt_keystores, patch_eth_account
+from .account import keychain_password, store_keychain_password
+from .account import set_max_transaction_fee, set_signing_policy, set_typed_data_guard
+from .account import sign_user_operation, user_operation_hash
+from .account import sign_hashes as _sign_hashes
+from .account import (
+    sign_hash_async,
+    sign_message_async,
+    sign_transaction_async,
+    sign_typed_data_async,
+)
+from .account import sign_typed_data_batch as _sign_typed_data_batch
+from .remote import RemoteAccount, aws_kms_account, gcp_kms_account
+from .remote import azure_key_vault_account, yubihsm_account, yubihsm_session
+from .remote import pkcs11_account, pkcs11_session, vault_transit_account
+from .remote import ledger_account, trezor_account, web3signer_account
+from .remote import Signer, register_signer, registered_signers, signer_account
+from .threshold import combine_additive_shares, threshold_account
+from .qr import decode_qr_signature, qr_sign_request, signed_transaction_from_qr
+from .safe import SafeSignature, pack_safe_signatures, safe_tx, safe_tx_hash
+from .safe import safe_message, safe_message_hash, sign_safe_message, sign_safe_tx
+from .permit import PermitSignature, permit, permit_hash, sign_permit
+from .permit import PERMIT2_ADDRESS, PermitDetails, permit2_batch, permit2_single
+from .permit import dai_permit, permit2_transfer_from, transfer_authorization
+from .forwarder import SignedForwardRequest, forward_request, forward_request_hash
+from .forwarder import sign_forward_request
+from .cow import COW_SETTLEMENT_ADDRESS, CowOrderSignature, cow_order, cow_order_hash
+from .cow import cow_order_uid, sign_cow_order, sign_cow_orders
+from .seaport import SEAPORT_ADDRESSES, ConsiderationItem, OfferItem, SeaportSignature
+from .seaport import seaport_order, seaport_order_hash, sign_seaport_order
+from .flashbots import FLASHBOTS_SIGNATURE_HEADER, flashbots_signature
+from .opstack import op_deposit_source_hash, op_deposit_transaction_hash
+from .opstack import op_l1_info_deposit_source_hash, op_withdrawal_hash
+from .erc7739 import erc7739_personal_message, erc7739_typed_data
+from .erc7739 import sign_erc7739_personal_message, sign_erc7739_typed_data
+from .erc7739 import wrap_erc7739_signature
+from .p256 import P256_VERIFY_PRECOMPILE, P256PublicKey, P256Signature, create_p256_key
+from .p256 import p256_precompile_input, p256_public_key, p256_sign_hash, p256_verify
+from .webauthn import WEBAUTHN_USER_PRESENT, WEBAUTHN_USER_VERIFIED, WebAuthnAuth
+from .webauthn import coinbase_smart_wallet_signature, encode_webauthn_auth
+from .webauthn import sign_webauthn, webauthn_auth, webauthn_authenticator_data
+from .webauthn import webauthn_client_data_json, webauthn_message_hash
+from .bls import BLS_DOMAIN, aggregate_bls_signatures, bls_public_key, bls_sign
+from .bls import bls_user_operation_hash, create_bls_key, sign_bls_user_operation
+from .units import from_wei, to_wei
+from .conversions import to_bytes, to_hex, to_int
+from .deposit import DEPOSIT_AMOUNT, GENESIS_FORK_VERSIONS, deposit_data
+from .deposit import deposit_signing_root, withdrawal_credentials
+from .multicall import MULTICALL3_ADDRESS, Call3, Result3, decode_multicall3_results
+from .multicall import multicall3_calldata
+from .server import IpcSignerServer, SignerServer
+from .grpc_signer import SignerService, serve_grpc
+from .prometheus import MetricsServer, prometheus_metrics
+from .config import ConfigError, load_config
+from _ferrite import clear_key_cache, key_cache_locked  # type: ignore
+from _ferrite import set_keystore_limits, set_keystore_threads, warm_up  # type: ignore
+from _ferrite import set_approval_hook, signing_policy_locked  # type: ignore
+from _ferrite import set_audit_log, set_rate_limit  # type: ignore
+from _ferrite import get_metrics, verify_audit_log  # type: ignore
+from _ferrite import flush_logs, set_log_level  # type: ignore
+from _ferrite import encode_calldata, keccak, rlp_decode, rlp_encode  # type: ignore
+from _ferrite import event_topic, event_topics, solidity_keccak  # type: ignore
+from _ferrite import unwrap_typed_transaction, wrap_typed_transaction  # type: ignore
+from _ferrite import decode_abi, decode_log, decode_logs  # type: ignore
+from _ferrite import is_checksum_address, is_checksum_addresses  # type: ignore
+from _ferrite import to_checksum_address, to_checksum_addresses  # type: ignore
+from _ferrite import create_address, create2_address, mine_create2_salt  # type: ignore
+from _ferrite import dns_encode, labelhash, namehash  # type: ignore
+from _ferrite import function_selector, function_selectors  # type: ignore
+from _ferrite import (  # type: ignore
+    ApprovalDeniedError,
+    DangerousTypedDataWarning,
+    FerriteError,
+    InvalidKeyError,
+    InvalidTransactionError,
+    PolicyViolationError,
+    RateLimitError,
+    SigningError,
+    TypedDataError,
+)
 
 log = logging.getLogger(__name__)
 
-__all__ = ["sign_message", "sign_hash", "sign_typed_data", "__version__"]
+__all__ = [
+    "sign_message",
+    "sign_hash",
+    "sign_hashes",
+    "sign_typed_data",
+    "sign_typed_data_batch",
+    "sign_message_async",
+    "sign_hash_async",
+    "sign_typed_data_async",
+    "sign_transaction_async",
+    "sign_user_operation",
+    "user_operation_hash",
+    "addresses_from_keys",
+    "addresses_from_mnemonic",
+    "RemoteAccount",
+    "Signer",
+    "register_signer",
+    "registered_signers",
+    "signer_account",
+    "threshold_account",
+    "combine_additive_shares",
+    "qr_sign_request",
+    "decode_qr_signature",
+    "signed_transaction_from_qr",
+    "SafeSignature",
+    "safe_tx",
+    "safe_tx_hash",
+    "sign_safe_tx",
+    "pack_safe_signatures",
+    "safe_message",
+    "safe_message_hash",
+    "sign_safe_message",
+    "PermitSignature",
+    "permit",
+    "permit_hash",
+    "sign_permit",
+    "dai_permit",
+    "transfer_authorization",
+    "SignedForwardRequest",
+    "forward_request",
+    "forward_request_hash",
+    "sign_forward_request",
+    "COW_SETTLEMENT_ADDRESS",
+    "CowOrderSignature",
+    "cow_order",
+    "cow_order_hash",
+    "cow_order_uid",
+    "sign_cow_order",
+    "sign_cow_orders",
+    "SEAPORT_ADDRESSES",
+    "OfferItem",
+    "ConsiderationItem",
+    "SeaportSignature",
+    "seaport_order",
+    "seaport_order_hash",
+    "sign_seaport_order",
+    "FLASHBOTS_SIGNATURE_HEADER",
+    "flashbots_signature",
+    "op_withdrawal_hash",
+    "op_deposit_source_hash",
+    "op_l1_info_deposit_source_hash",
+    "op_deposit_transaction_hash",
+    "erc7739_typed_data",
+    "wrap_erc7739_signature",
+    "sign_erc7739_typed_data",
+    "erc7739_personal_message",
+    "sign_erc7739_personal_message",
+    "P256_VERIFY_PRECOMPILE",
+    "P256PublicKey",
+    "P256Signature",
+    "create_p256_key",
+    "p256_public_key",
+    "p256_sign_hash",
+    "p256_verify",
+    "p256_precompile_input",
+    "WEBAUTHN_USER_PRESENT",
+    "WEBAUTHN_USER_VERIFIED",
+    "WebAuthnAuth",
+    "webauthn_authenticator_data",
+    "webauthn_client_data_json",
+    "webauthn_message_hash",
+    "webauthn_auth",
+    "encode_webauthn_auth",
+    "sign_webauthn",
+    "coinbase_smart_wallet_signature",
+    "BLS_DOMAIN",
+    "create_bls_key",
+    "bls_public_key",
+    "bls_sign",
+    "bls_user_operation_hash",
+    "sign_bls_user_operation",
+    "aggregate_bls_signatures",
+    "DEPOSIT_AMOUNT",
+    "GENESIS_FORK_VERSIONS",
+    "withdrawal_credentials",
+    "deposit_signing_root",
+    "deposit_data",
+    "keccak",
+    "rlp_encode",
+    "rlp_decode",
+    "wrap_typed_transaction",
+    "unwrap_typed_transaction",
+    "encode_calldata",
+    "function_selector",
+    "function_selectors",
+    "event_topic",
+    "event_topics",
+    "solidity_keccak",
+    "decode_abi",
+    "decode_log",
+    "decode_logs",
+    "to_wei",
+    "from_wei",
+    "to_hex",
+    "to_bytes",
+    "to_int",
+    "MULTICALL3_ADDRESS",
+    "Call3",
+    "Result3",
+    "multicall3_calldata",
+    "decode_multicall3_results",
+    "SignerServer",
+    "IpcSignerServer",
+    "SignerService",
+    "serve_grpc",
+    "to_checksum_address",
+    "to_checksum_addresses",
+    "is_checksum_address",
+    "is_checksum_addresses",
+    "create_address",
+    "create2_address",
+    "mine_create2_salt",
+    "namehash",
+    "labelhash",
+    "dns_encode",
+    "PERMIT2_ADDRESS",
+    "PermitDetails",
+    "permit2_single",
+    "permit2_batch",
+    "permit2_transfer_from",
+    "aws_kms_account",
+    "gcp_kms_account",
+    "azure_key_vault_account",
+    "yubihsm_account",
+    "yubihsm_session",
+    "pkcs11_account",
+    "pkcs11_session",
+    "vault_transit_account",
+    "web3signer_account",
+    "ledger_account",
+    "trezor_account",
+    "decrypt_keystores",
+    "keychain_password",
+    "store_keychain_password",
+    "set_keystore_threads",
+    "set_keystore_limits",
+    "clear_key_cache",
+    "key_cache_locked",
+    "set_signing_policy",
+    "set_max_transaction_fee",
+    "signing_policy_locked",
+    "set_approval_hook",
+    "set_audit_log",
+    "verify_audit_log",
+    "audit_context",
+    "set_rate_limit",
+    "set_typed_data_guard",
+    "get_metrics",
+    "prometheus_metrics",
+    "MetricsServer",
+    "load_config",
+    "ConfigError",
+    "set_log_level",
+    "flush_logs",
+    "warm_up",
+    "FerriteError",
+    "InvalidKeyError",
+    "InvalidTransactionError",
+    "TypedDataError",
+    "SigningError",
+    "PolicyViolationError",
+    "ApprovalDeniedError",
+    "RateLimitError",
+    "DangerousTypedDataWarning",
+    "__version__",
+]
 __version__ = "0.1.0"
 
 _patch_applied = False
@@ -74,6 +339,23 @@ def sign_hash(message_hash: bytes, private_key: str) -> SignedMessage:
     return cast(Any, Account).signHash(message_hash, private_key)
 
 
+def sign_hashes(message_hashes: List[bytes], private_key: str) -> List[SignedMessage]:
+    """
+    Sign a batch of raw message hashes in parallel with the Rust backend.
+
+    The whole batch is signed in a single call into Rust, which avoids the per-call
+    overhead of signing each hash separately.
+
+    Args:
+        message_hashes: The 32-byte message hashes to sign.
+        private_key: The private key as a hex string.
+
+    Returns:
+        The signed messages, in the same order as the input hashes.
+    """
+    return _sign_hashes(message_hashes, private_key)
+
+
 def sign_typed_data(full_message: Any, private_key: str) -> SignedMessage:
     """
     Sign an EIP-712 typed data message with the high-performance Rust backend.
@@ -87,3 +369,29 @@ def sign_typed_data(full_message: Any, private_key: str) -> SignedMessage:
     """
     install()
     return cast(Any, Account).sign_typed_data(private_key, full_message)
+
+
+def sign_typed_data_batch(
+    domain: Dict[str, Any],
+    types: Dict[str, Any],
+    messages: List[Dict[str, Any]],
+    private_key: str,
+    primary_type: Optional[str] = None,
+) -> List[SignedMessage]:
+    """
+    Sign many EIP-712 messages that share a domain with the Rust backend.
+
+    The domain separator and type hashes are computed once for the whole batch and
+    the messages are signed in parallel.
+
+    Args:
+        domain: The EIP-712 domain shared by every message.
+        types: The EIP-712 type definitions.
+        messages: The messages to sign.
+        private_key: The private key as a hex string.
+        primary_type: The type of every message. Inferred from ``types`` if omitted.
+
+    Returns:
+        The signed messages, in the same order as ``messages``.
+    """
+    return _sign_typed_data_batch(domain, types, messages, private_key, primary_type)
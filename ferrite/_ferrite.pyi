@@ -1,9 +1,256 @@
-from typing import Dict, Any, TypedDict
+from contextvars import ContextVar
+from typing import (
+    Awaitable,
+    Callable,
+    Dict,
+    Any,
+    Iterable,
+    List,
+    Optional,
+    Sequence,
+    Tuple,
+    TypedDict,
+    Union,
+)
+
+class FerriteError(ValueError): ...
+class InvalidKeyError(FerriteError): ...
+class InvalidTransactionError(FerriteError): ...
+class TypedDataError(FerriteError): ...
+class SigningError(FerriteError): ...
+class PolicyViolationError(FerriteError): ...
+class ApprovalDeniedError(FerriteError): ...
+class RateLimitError(FerriteError): ...
+class DangerousTypedDataWarning(UserWarning): ...
 
 class SignatureDict(TypedDict):
     r: bytes
     s: bytes
     v: int
+    y_parity: int
     signature: bytes
 
-def sign_hash(message_hash: bytes, private_key_hex: str) -> SignatureDict: ...
+class TransactionSignatureDict(SignatureDict):
+    rawTransaction: bytes
+    hash: bytes
+
+def sign_hash(
+    message_hash: bytes,
+    private_key: bytes,
+    *,
+    signature_out: Optional[Union[bytearray, memoryview]] = None,
+    extra_entropy: Optional[bytes] = None,
+    preimage: Optional[bytes] = None,
+) -> SignatureDict: ...
+def sign_hashes(
+    message_hashes: List[bytes],
+    private_key: bytes,
+    *,
+    extra_entropy: Optional[bytes] = None,
+) -> List[SignatureDict]: ...
+def sign_typed_data(
+    payload: str,
+    private_key: bytes,
+    *,
+    signature_out: Optional[Union[bytearray, memoryview]] = None,
+    extra_entropy: Optional[bytes] = None,
+) -> SignatureDict: ...
+def hash_typed_data(payload: str, *, struct_hash: bool = False) -> bytes: ...
+def sign_typed_data_batch(
+    domain: str,
+    types: str,
+    messages: str,
+    private_key: bytes,
+    primary_type: Optional[str] = None,
+    *,
+    extra_entropy: Optional[bytes] = None,
+) -> List[SignatureDict]: ...
+def sign_transaction(
+    payload: str,
+    private_key: bytes,
+    *,
+    sighash: Optional[bytes] = None,
+    require_checksum: bool = False,
+    check_fee_cap: bool = True,
+    raw_transaction_out: Optional[Union[bytearray, memoryview]] = None,
+    extra_entropy: Optional[bytes] = None,
+) -> TransactionSignatureDict: ...
+def encode_unsigned_transaction(payload: str) -> bytes: ...
+def op_withdrawal_hash(payload: str) -> bytes: ...
+def op_deposit_source_hash(
+    l1_block_hash: bytes, index: int, *, l1_info: bool = False
+) -> bytes: ...
+def op_deposit_transaction_hash(payload: str) -> bytes: ...
+def p256_public_key(private_key: bytes) -> bytes: ...
+def p256_sign_hash(msg_hash: bytes, private_key: bytes) -> Tuple[bytes, bytes]: ...
+def p256_verify(msg_hash: bytes, r: bytes, s: bytes, public_key: bytes) -> bool: ...
+def bls_public_key(secret_key: bytes) -> bytes: ...
+def bls_hash_to_point(domain: bytes, message: bytes) -> bytes: ...
+def bls_sign(domain: bytes, message: bytes, secret_key: bytes) -> bytes: ...
+def bls_aggregate(signatures: List[bytes]) -> bytes: ...
+def bls_user_operation_hash(
+    payload: str,
+    aggregator: str,
+    chain_id: int,
+    public_key: bytes,
+    *,
+    version: Optional[str] = None,
+) -> bytes: ...
+def deposit_message_root(
+    pubkey: bytes, withdrawal_credentials: bytes, amount: int
+) -> bytes: ...
+def deposit_signing_root(
+    pubkey: bytes, withdrawal_credentials: bytes, amount: int, fork_version: bytes
+) -> bytes: ...
+def deposit_data_root(
+    pubkey: bytes, withdrawal_credentials: bytes, amount: int, signature: bytes
+) -> bytes: ...
+# Only present when built with the `stark` feature
+def stark_pedersen_hash(a: bytes, b: bytes) -> bytes: ...
+def stark_grind_key(seed: bytes) -> bytes: ...
+def stark_public_key(private_key: bytes) -> bytes: ...
+def stark_sign_hash(msg_hash: bytes, private_key: bytes) -> Tuple[bytes, bytes]: ...
+# Only present when built with the `rpc` feature
+def fill_transaction(
+    payload: str, rpc_url: str, timeout: float = 10.0
+) -> Dict[str, str]: ...
+def send_raw_transaction(
+    raw_transaction: bytes,
+    rpc_url: str,
+    timeout: float = 10.0,
+    retries: int = 3,
+    backoff: float = 0.1,
+) -> bytes: ...
+def user_operation_hash(
+    payload: str, entry_point: str, chain_id: int, *, version: Optional[str] = None
+) -> bytes: ...
+def sign_user_operation(
+    payload: str,
+    entry_point: str,
+    chain_id: int,
+    private_key: bytes,
+    *,
+    eip191: bool = True,
+    version: Optional[str] = None,
+) -> Dict[str, Any]: ...
+def sign_hash_async(
+    message_hash: bytes, private_key: bytes, *, preimage: Optional[bytes] = None
+) -> Awaitable[SignatureDict]: ...
+def sign_typed_data_async(
+    payload: str, private_key: bytes
+) -> Awaitable[SignatureDict]: ...
+def sign_transaction_async(
+    payload: str, private_key: bytes
+) -> Awaitable[TransactionSignatureDict]: ...
+def sign_hash_remote(
+    message_hash: bytes,
+    address: str,
+    sign: Callable[[bytes], bytes],
+    *,
+    prehashed: bool = True,
+    preimage: Optional[bytes] = None,
+) -> SignatureDict: ...
+def sign_typed_data_remote(
+    payload: str,
+    address: str,
+    sign: Callable[[bytes], bytes],
+    *,
+    prehashed: bool = True,
+) -> SignatureDict: ...
+def sign_transaction_remote(
+    payload: str,
+    address: str,
+    sign: Callable[[bytes], bytes],
+    *,
+    prehashed: bool = True,
+) -> TransactionSignatureDict: ...
+def addresses_from_keys(private_keys: List[bytes]) -> List[str]: ...
+def address_from_public_key(public_key: bytes) -> str: ...
+def to_checksum_address(address: str, chain_id: Optional[int] = None) -> str: ...
+def to_checksum_addresses(
+    addresses: List[str], chain_id: Optional[int] = None
+) -> List[str]: ...
+def is_checksum_address(address: str, chain_id: Optional[int] = None) -> bool: ...
+def is_checksum_addresses(
+    addresses: List[str], chain_id: Optional[int] = None
+) -> List[bool]: ...
+def create_address(deployer: str, nonce: int) -> str: ...
+def create2_address(deployer: str, salt: bytes, init_code_hash: bytes) -> str: ...
+def mine_create2_salt(
+    deployer: str,
+    init_code_hash: bytes,
+    prefix: str = "",
+    suffix: str = "",
+    salt_prefix: bytes = b"",
+    start: int = 0,
+    attempts: int = 1 << 32,
+) -> Optional[Tuple[bytes, str]]: ...
+def keccak(data: Union[bytes, bytearray, memoryview]) -> bytes: ...
+def rlp_encode(item: Any) -> bytes: ...
+def rlp_decode(data: bytes) -> Union[bytes, List[Any]]: ...
+def wrap_typed_transaction(tx_type: int, payload: bytes) -> bytes: ...
+def unwrap_typed_transaction(envelope: bytes) -> Tuple[int, bytes]: ...
+def encode_calldata(signature: str, args: Sequence[Any]) -> bytes: ...
+def function_selector(signature: str) -> bytes: ...
+def function_selectors(signatures: List[str]) -> List[bytes]: ...
+def event_topic(signature: str) -> bytes: ...
+def event_topics(signatures: List[str]) -> List[bytes]: ...
+def solidity_keccak(types: Sequence[str], values: Sequence[Any]) -> bytes: ...
+def decode_abi(types: Sequence[str], data: bytes) -> List[Any]: ...
+def decode_log(
+    event: Union[str, Dict[str, Any]],
+    topics: Sequence[Union[bytes, str]],
+    data: Union[bytes, str],
+) -> Dict[Union[str, int], Any]: ...
+def decode_logs(
+    event: Union[str, Dict[str, Any]], logs: Iterable[Any]
+) -> List[Dict[Union[str, int], Any]]: ...
+def labelhash(label: str) -> bytes: ...
+def namehash(name: str) -> bytes: ...
+def dns_encode(name: str) -> bytes: ...
+def addresses_from_seed(
+    seed: bytes, start: int, count: int, path: str = "m/44'/60'/0'/0"
+) -> List[str]: ...
+def decrypt_keystore(keystore: str, password: bytes) -> bytes: ...
+def decrypt_keystores(keystores: List[str], passwords: List[bytes]) -> List[bytes]: ...
+def encrypt_keystore(
+    private_key: bytes,
+    password: bytes,
+    kdf: str = "scrypt",
+    iterations: Optional[int] = None,
+) -> str: ...
+def set_keystore_threads(threads: int) -> None: ...
+def set_keystore_limits(
+    *, max_memory: int = 1 << 30, max_iterations: int = 10_000_000
+) -> None: ...
+def clear_key_cache() -> None: ...
+def key_cache_locked() -> bool: ...
+def set_signing_policy(policy: Optional[str], *, lock: bool = False) -> None: ...
+def signing_policy_locked() -> bool: ...
+def set_max_transaction_fee(max_fee: Optional[str]) -> None: ...
+def set_approval_hook(hook: Optional[Callable[[Dict[str, Any]], bool]]) -> None: ...
+def set_audit_log(
+    path: Optional[str] = None,
+    *,
+    callback: Optional[Callable[[Dict[str, Any]], Any]] = None,
+    chain: bool = False,
+) -> None: ...
+def verify_audit_log(path: str) -> Optional[str]: ...
+def set_rate_limit(
+    address: str,
+    *,
+    per_second: Optional[int] = None,
+    per_minute: Optional[int] = None,
+    lock: bool = False,
+) -> None: ...
+def set_typed_data_guard(
+    mode: str = "warn",
+    *,
+    allow: Optional[str] = None,
+    allow_raw_hashes: bool = False,
+) -> None: ...
+def get_metrics(*, reset: bool = False) -> Dict[str, Dict[str, Any]]: ...
+def set_log_level(level: Union[int, str, None]) -> None: ...
+def flush_logs() -> None: ...
+def audit_context_var() -> ContextVar[Optional[Dict[str, Any]]]: ...
+def warm_up() -> None: ...
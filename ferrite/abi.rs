@@ -0,0 +1,961 @@
+/*!
+ABI encoding of contract calls, decoding of return data and event logs, and function
+selectors and event topics, from signatures.
+
+Signatures are parsed strictly, unlike ethabi's own type reader, which reads any name it
+doesn't know as `uint8`: a misspelled type is an error rather than a call to a different
+selector. They may be written as in Solidity source, with parameter names, `indexed` and
+a leading `function` or `event`, and are canonicalized before hashing, so
+`Transfer(address indexed from, address indexed to, uint value)` gets the topic of
+`Transfer(address,address,uint256)`.
+
+`solidity_keccak` follows web3.py's `solidity_keccak` rather than Solidity's own
+`abi.encodePacked` where the two differ: byte strings, including `bytesN`, are hashed as
+given rather than padded, even as array elements.
+
+Return data and logs are decoded by a decoder of our own rather than ethabi's, which
+ignores padding, replaces invalid UTF-8 and can panic on offsets past the end of the
+data. Like eth-abi, it rejects words whose padding isn't zero or that are out of range
+for their type, so data that isn't what its types say it is raises instead of decoding
+to something else.
+*/
+
+use ethers_core::abi::{encode, ParamType, Token};
+use ethers_core::types::{Address, U256};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyByteArray, PyBytes, PyDict, PyInt, PyList, PyString, PyTuple};
+use rayon::prelude::*;
+
+use crate::{address, errors, keccak};
+
+fn invalid_signature(signature: &str, reason: &str) -> PyErr {
+    PyErr::new::<errors::FerriteError, _>(format!(
+        "Invalid signature {:?}: {}",
+        signature, reason
+    ))
+}
+
+/// Parses an elementary type name such as `uint256` or `bytes32`.
+fn elementary(name: &str) -> Option<ParamType> {
+    // Sizes must be written canonically, so `uint08` isn't taken for `uint8`
+    let size = |digits: &str| digits.parse::<usize>().ok().filter(|n| n.to_string() == digits);
+    match name {
+        "address" => Some(ParamType::Address),
+        "bool" => Some(ParamType::Bool),
+        "string" => Some(ParamType::String),
+        "bytes" => Some(ParamType::Bytes),
+        "uint" => Some(ParamType::Uint(256)),
+        "int" => Some(ParamType::Int(256)),
+        _ => {
+            if let Some(bits) = name.strip_prefix("uint") {
+                size(bits).filter(|n| (8..=256).contains(n) && n % 8 == 0).map(ParamType::Uint)
+            } else if let Some(bits) = name.strip_prefix("int") {
+                size(bits).filter(|n| (8..=256).contains(n) && n % 8 == 0).map(ParamType::Int)
+            } else if let Some(len) = name.strip_prefix("bytes") {
+                size(len).filter(|n| (1..=32).contains(n)).map(ParamType::FixedBytes)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Parses the type at the start of `input`, returning it and what follows it.
+fn parse_type(input: &str) -> Result<(ParamType, &str), String> {
+    let input = input.trim_start();
+    let input = input.strip_prefix("tuple").filter(|s| s.starts_with('(')).unwrap_or(input);
+    let (mut kind, mut rest) = if input.starts_with('(') {
+        let (types, rest) = parse_list(input)?;
+        (ParamType::Tuple(types), rest)
+    } else {
+        let end = input.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(input.len());
+        let name = &input[..end];
+        if name.is_empty() {
+            return Err("expected a type".to_string());
+        }
+        let kind = elementary(name).ok_or_else(|| format!("unknown type {:?}", name))?;
+        (kind, &input[end..])
+    };
+    while let Some(suffix) = rest.strip_prefix('[') {
+        let end = suffix.find(']').ok_or("unclosed array brackets")?;
+        kind = match &suffix[..end] {
+            "" => ParamType::Array(Box::new(kind)),
+            len => match len.parse::<usize>() {
+                Ok(len) if len > 0 && len.to_string() == suffix[..end] => {
+                    ParamType::FixedArray(Box::new(kind), len)
+                }
+                _ => return Err(format!("invalid array length {:?}", len)),
+            },
+        };
+        rest = &suffix[end + 1..];
+    }
+    Ok((kind, rest))
+}
+
+/// Splits off the words that may follow a parameter's type, such as its name and
+/// `indexed`.
+fn words(mut input: &str) -> (Vec<&str>, &str) {
+    let mut words = Vec::new();
+    loop {
+        input = input.trim_start();
+        let end = input
+            .find(|c: char| !(c == '_' || c == '$' || c.is_ascii_alphanumeric()))
+            .unwrap_or(input.len());
+        if end == 0 {
+            return (words, input);
+        }
+        words.push(&input[..end]);
+        input = &input[end..];
+    }
+}
+
+/// A parameter's type and the words written after it.
+type Param<'a> = (ParamType, Vec<&'a str>);
+
+/// Parses a parenthesized, comma-separated list of parameters at the start of `input`.
+fn parse_params(input: &str) -> Result<(Vec<Param<'_>>, &str), String> {
+    let mut rest = input.strip_prefix('(').ok_or("expected '('")?;
+    let mut params = Vec::new();
+    if let Some(after) = rest.trim_start().strip_prefix(')') {
+        return Ok((params, after));
+    }
+    loop {
+        let (kind, after) = parse_type(rest)?;
+        let (names, after) = words(after);
+        params.push((kind, names));
+        match after.chars().next() {
+            Some(',') => rest = &after[1..],
+            Some(')') => return Ok((params, &after[1..])),
+            _ => return Err("expected ',' or ')' after a type".to_string()),
+        }
+    }
+}
+
+/// Parses a parenthesized, comma-separated list of types at the start of `input`.
+fn parse_list(input: &str) -> Result<(Vec<ParamType>, &str), String> {
+    let (params, rest) = parse_params(input)?;
+    Ok((params.into_iter().map(|(kind, _)| kind).collect(), rest))
+}
+
+/// Parses `name(type,...)` into its name, its parameters and what follows them.
+fn parse_declaration(signature: &str) -> PyResult<(&str, Vec<Param<'_>>, &str)> {
+    let text = signature.trim();
+    let text = ["function", "event"]
+        .iter()
+        .find_map(|keyword| text.strip_prefix(keyword).filter(|s| s.starts_with(' ')))
+        .unwrap_or(text);
+    let open = text.find('(').ok_or_else(|| invalid_signature(signature, "no '('"))?;
+    let name = text[..open].trim();
+    let identifier = name.chars().enumerate().all(|(i, c)| {
+        c == '_' || c == '$' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit())
+    });
+    if name.is_empty() || !identifier {
+        return Err(invalid_signature(signature, "the name isn't an identifier"));
+    }
+    let (params, rest) =
+        parse_params(&text[open..]).map_err(|reason| invalid_signature(signature, &reason))?;
+    Ok((name, params, rest.trim()))
+}
+
+fn canonical_signature(name: &str, types: &[ParamType]) -> String {
+    let canonical: Vec<String> = types.iter().map(ToString::to_string).collect();
+    format!("{}({})", name, canonical.join(","))
+}
+
+/// Parses `name(type,...)` into its canonical signature and parameter types.
+fn parse_signature(signature: &str) -> PyResult<(String, Vec<ParamType>)> {
+    let (name, params, rest) = parse_declaration(signature)?;
+    if !rest.is_empty() {
+        return Err(invalid_signature(signature, "unexpected text after the parameters"));
+    }
+    let types: Vec<ParamType> = params.into_iter().map(|(kind, _)| kind).collect();
+    Ok((canonical_signature(name, &types), types))
+}
+
+/// Returns the keccak256 of a signature's canonical form, which a function's selector
+/// is the first four bytes of and an event's topic is all of.
+fn signature_hash(signature: &str) -> PyResult<[u8; 32]> {
+    let (canonical, _) = parse_signature(signature)?;
+    Ok(keccak::keccak256(canonical.as_bytes()))
+}
+
+/// Hashes each signature, keeping the first `len` bytes of each hash.
+fn signature_hashes(py: Python, signatures: Vec<String>, len: usize) -> PyResult<PyObject> {
+    let hashes = py.allow_threads(|| {
+        signatures.iter().map(|s| signature_hash(s)).collect::<PyResult<Vec<_>>>()
+    })?;
+    let hashes = hashes.iter().map(|hash| PyBytes::new(py, &hash[..len]));
+    Ok(PyList::new(py, hashes)?.into_any().unbind())
+}
+
+fn invalid_type(type_name: &str, reason: &str) -> PyErr {
+    PyErr::new::<errors::FerriteError, _>(format!("Invalid type {:?}: {}", type_name, reason))
+}
+
+/// Parses standalone type names, such as `"address"` or `"(bool,bytes)[]"`.
+fn parse_types(types: &[String]) -> PyResult<Vec<ParamType>> {
+    types
+        .iter()
+        .map(|type_name| match parse_type(type_name) {
+            Ok((kind, rest)) if rest.trim().is_empty() => Ok(kind),
+            Ok(_) => Err(invalid_type(type_name, "unexpected text after the type")),
+            Err(reason) => Err(invalid_type(type_name, &reason)),
+        })
+        .collect()
+}
+
+fn type_error(kind: &ParamType, value: &Bound<PyAny>) -> PyErr {
+    let type_name = value.get_type().name().map(|n| n.to_string()).unwrap_or_default();
+    PyErr::new::<errors::FerriteError, _>(format!("Cannot encode '{}' as {}", type_name, kind))
+}
+
+fn range_error(kind: &ParamType, value: &Bound<PyAny>) -> PyErr {
+    PyErr::new::<errors::FerriteError, _>(format!("{} is out of range for {}", value, kind))
+}
+
+/// Converts bytes or a `0x` hex string to bytes.
+fn bytes_value(kind: &ParamType, value: &Bound<PyAny>) -> PyResult<Vec<u8>> {
+    if let Ok(bytes) = value.downcast::<PyBytes>() {
+        return Ok(bytes.as_bytes().to_vec());
+    }
+    if let Ok(bytes) = value.downcast::<PyByteArray>() {
+        return Ok(bytes.to_vec());
+    }
+    if let Ok(text) = value.downcast::<PyString>() {
+        let text = text.to_cow()?;
+        if let Some(digits) = text.strip_prefix("0x") {
+            return hex::decode(digits).map_err(|_| {
+                PyErr::new::<errors::FerriteError, _>(format!(
+                    "Invalid hex for {}: {:?}",
+                    kind, text
+                ))
+            });
+        }
+    }
+    Err(type_error(kind, value))
+}
+
+/// Converts a Python int to the 256-bit two's complement word of a `bits`-bit integer.
+fn int_value(kind: &ParamType, value: &Bound<PyAny>, bits: usize) -> PyResult<U256> {
+    let signed = matches!(kind, ParamType::Int(_));
+    let int = match value.downcast::<PyInt>() {
+        Ok(int) if !value.is_instance_of::<PyBool>() => int,
+        _ => return Err(type_error(kind, value)),
+    };
+    let negative = int.lt(0)?;
+    // A negative value needs the bits of its complement, plus the sign bit
+    let magnitude = if negative { int.call_method0("__invert__")? } else { int.clone().into_any() };
+    let used: usize = magnitude.call_method0("bit_length")?.extract()?;
+    if (negative && !signed) || used > bits - usize::from(signed) {
+        return Err(range_error(kind, value));
+    }
+    let kwargs = PyDict::new(value.py());
+    kwargs.set_item("signed", negative)?;
+    let word: Vec<u8> = int.call_method("to_bytes", (32, "big"), Some(&kwargs))?.extract()?;
+    Ok(U256::from_big_endian(&word))
+}
+
+/// Converts the items of a list or tuple to tokens of `kinds`.
+fn sequence(
+    kind: &ParamType,
+    value: &Bound<PyAny>,
+    kinds: &mut dyn Iterator<Item = &ParamType>,
+    len: Option<usize>,
+) -> PyResult<Vec<Token>> {
+    if !value.is_instance_of::<PyList>() && !value.is_instance_of::<PyTuple>() {
+        return Err(type_error(kind, value));
+    }
+    let items = value.try_iter()?.collect::<PyResult<Vec<_>>>()?;
+    if len.is_some_and(|len| len != items.len()) {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "Expected {} values for {}, got {}",
+            len.unwrap_or_default(),
+            kind,
+            items.len()
+        )));
+    }
+    items.iter().zip(kinds).map(|(item, kind)| tokenize(kind, item)).collect()
+}
+
+/// Converts a Python value to the token of an ABI type.
+fn tokenize(kind: &ParamType, value: &Bound<PyAny>) -> PyResult<Token> {
+    Ok(match kind {
+        ParamType::Address => match value.downcast::<PyString>() {
+            Ok(text) => Token::Address(address::parse(&text.to_cow()?)?),
+            Err(_) => {
+                let bytes = bytes_value(kind, value)?;
+                if bytes.len() != 20 {
+                    return Err(range_error(kind, value));
+                }
+                Token::Address(Address::from_slice(&bytes))
+            }
+        },
+        ParamType::Bool => match value.downcast::<PyBool>() {
+            Ok(flag) => Token::Bool(flag.is_true()),
+            Err(_) => return Err(type_error(kind, value)),
+        },
+        ParamType::String => match value.downcast::<PyString>() {
+            Ok(text) => Token::String(text.to_cow()?.into_owned()),
+            Err(_) => return Err(type_error(kind, value)),
+        },
+        ParamType::Bytes => Token::Bytes(bytes_value(kind, value)?),
+        ParamType::FixedBytes(len) => {
+            let bytes = bytes_value(kind, value)?;
+            if bytes.len() != *len {
+                return Err(PyErr::new::<errors::FerriteError, _>(format!(
+                    "Expected {} bytes for {}, got {}",
+                    len,
+                    kind,
+                    bytes.len()
+                )));
+            }
+            Token::FixedBytes(bytes)
+        }
+        ParamType::Uint(bits) => Token::Uint(int_value(kind, value, *bits)?),
+        ParamType::Int(bits) => Token::Int(int_value(kind, value, *bits)?),
+        ParamType::Array(inner) => {
+            Token::Array(sequence(kind, value, &mut std::iter::repeat(&**inner), None)?)
+        }
+        ParamType::FixedArray(inner, len) => Token::FixedArray(sequence(
+            kind,
+            value,
+            &mut std::iter::repeat(&**inner),
+            Some(*len),
+        )?),
+        ParamType::Tuple(kinds) => {
+            Token::Tuple(sequence(kind, value, &mut kinds.iter(), Some(kinds.len()))?)
+        }
+    })
+}
+
+/// Appends the packed encoding of `value` to `out`, as web3.py's `hex_encode_abi_type`
+/// builds it; array elements other than byte strings are padded to 32 bytes.
+fn pack(kind: &ParamType, value: &Bound<PyAny>, in_array: bool, out: &mut Vec<u8>) -> PyResult<()> {
+    let mut padded = |bytes: &[u8], width: usize| {
+        let width = if in_array { 32 } else { width };
+        out.extend(std::iter::repeat_n(0, width - bytes.len()));
+        out.extend_from_slice(bytes);
+    };
+    match kind {
+        ParamType::Bool => match value.downcast::<PyBool>() {
+            Ok(flag) => padded(&[u8::from(flag.is_true())], 1),
+            Err(_) => return Err(type_error(kind, value)),
+        },
+        ParamType::Uint(bits) | ParamType::Int(bits) => {
+            let word = int_value(kind, value, *bits)?;
+            let width = if in_array { 32 } else { bits / 8 };
+            let mut bytes = [0u8; 32];
+            word.to_big_endian(&mut bytes);
+            padded(&bytes[32 - width..], width);
+        }
+        ParamType::Address => match tokenize(kind, value)? {
+            Token::Address(address) => padded(address.as_bytes(), 20),
+            _ => unreachable!("addresses tokenize to addresses"),
+        },
+        ParamType::Bytes | ParamType::FixedBytes(_) => {
+            out.extend_from_slice(&bytes_value(kind, value)?);
+        }
+        ParamType::String => match value.downcast::<PyString>() {
+            Ok(text) => out.extend_from_slice(text.to_str()?.as_bytes()),
+            Err(_) => return Err(type_error(kind, value)),
+        },
+        ParamType::Array(inner) | ParamType::FixedArray(inner, _) => {
+            if !value.is_instance_of::<PyList>() && !value.is_instance_of::<PyTuple>() {
+                return Err(type_error(kind, value));
+            }
+            let items = value.try_iter()?.collect::<PyResult<Vec<_>>>()?;
+            if let ParamType::FixedArray(_, len) = kind {
+                if items.len() != *len {
+                    return Err(PyErr::new::<errors::FerriteError, _>(format!(
+                        "Expected {} values for {}, got {}",
+                        len,
+                        kind,
+                        items.len()
+                    )));
+                }
+            }
+            for item in &items {
+                pack(inner, item, true, out)?;
+            }
+        }
+        ParamType::Tuple(_) => {
+            return Err(PyErr::new::<errors::FerriteError, _>(format!(
+                "Cannot pack tuple type {}",
+                kind
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// A parameter of an event, and whether it is logged as a topic rather than in the data.
+struct EventParam {
+    kind: ParamType,
+    indexed: bool,
+    name: Option<String>,
+}
+
+/// An event, as needed to decode its logs.
+struct Event {
+    signature: String,
+    /// The topic logs carry first, unless the event is anonymous.
+    topic: Option<[u8; 32]>,
+    params: Vec<EventParam>,
+}
+
+impl Event {
+    fn parse(signature: &str) -> PyResult<Self> {
+        let (name, params, rest) = parse_declaration(signature)?;
+        let anonymous = match rest {
+            "" => false,
+            "anonymous" => true,
+            _ => return Err(invalid_signature(signature, "unexpected text after the parameters")),
+        };
+        let params = params
+            .into_iter()
+            .map(|(kind, words)| {
+                let (indexed, names) = match words.split_first() {
+                    Some((&"indexed", names)) => (true, names),
+                    _ => (false, &words[..]),
+                };
+                match names {
+                    [] => Ok(EventParam { kind, indexed, name: None }),
+                    [name] => Ok(EventParam { kind, indexed, name: Some(name.to_string()) }),
+                    _ => Err(invalid_signature(signature, "expected `indexed` and a name at most")),
+                }
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        let types: Vec<ParamType> = params.iter().map(|param| param.kind.clone()).collect();
+        let signature = canonical_signature(name, &types);
+        let topic = (!anonymous).then(|| keccak::keccak256(signature.as_bytes()));
+        Ok(Event { signature, topic, params })
+    }
+
+    /// Reads an event from its signature or its JSON ABI entry.
+    fn extract(value: &Bound<PyAny>) -> PyResult<Self> {
+        if let Ok(signature) = value.downcast::<PyString>() {
+            return Self::parse(&signature.to_cow()?);
+        }
+        if let Ok(entry) = value.downcast::<PyDict>() {
+            return Self::parse(&json_signature(entry)?);
+        }
+        Err(PyTypeError::new_err(format!(
+            "Expected an event signature or ABI entry, not '{}'",
+            value.get_type().name()?
+        )))
+    }
+
+    /// Decodes the parameters of a log of this event, in declaration order.
+    fn decode(&self, topics: &[[u8; 32]], data: &[u8]) -> Result<Vec<Token>, String> {
+        let mut topics = topics.iter();
+        if let Some(topic) = self.topic {
+            if topics.next() != Some(&topic) {
+                return Err("topic0 is not the event's topic".to_string());
+            }
+        }
+        let indexed = self.params.iter().filter(|param| param.indexed).count();
+        if topics.len() != indexed {
+            return Err(format!("expected {} indexed topics, got {}", indexed, topics.len()));
+        }
+        let unindexed = self.params.iter().filter(|param| !param.indexed);
+        let mut values = decode_tuple(unindexed.map(|param| &param.kind), data)?.into_iter();
+        self.params
+            .iter()
+            .map(|param| {
+                if !param.indexed {
+                    return Ok(values.next().expect("one value per unindexed parameter"));
+                }
+                let topic = topics.next().expect("one topic per indexed parameter");
+                match param.kind {
+                    ParamType::Address
+                    | ParamType::Bool
+                    | ParamType::Int(_)
+                    | ParamType::Uint(_)
+                    | ParamType::FixedBytes(_) => decode_word(&param.kind, topic),
+                    // Other types are logged as the keccak256 of their encoding
+                    _ => Ok(Token::FixedBytes(topic.to_vec())),
+                }
+            })
+            .collect()
+    }
+}
+
+fn missing_field(field: &str) -> PyErr {
+    PyErr::new::<errors::FerriteError, _>(format!("Event ABI entry has no {:?}", field))
+}
+
+/// Writes the type of a JSON ABI parameter as a signature would, expanding tuples.
+fn json_type(input: &Bound<PyDict>) -> PyResult<String> {
+    let kind: String = input.get_item("type")?.ok_or_else(|| missing_field("type"))?.extract()?;
+    let Some(suffix) = kind.strip_prefix("tuple") else {
+        return Ok(kind);
+    };
+    let components = input.get_item("components")?.ok_or_else(|| missing_field("components"))?;
+    let components = components
+        .try_iter()?
+        .map(|component| json_type(component?.downcast()?))
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(format!("({}){}", components.join(","), suffix))
+}
+
+/// Writes a JSON ABI event entry as a signature with `indexed` and parameter names.
+fn json_signature(entry: &Bound<PyDict>) -> PyResult<String> {
+    if let Some(kind) = entry.get_item("type")? {
+        if kind.extract::<String>()? != "event" {
+            return Err(PyErr::new::<errors::FerriteError, _>(format!(
+                "Expected an event ABI entry, got type {}",
+                kind
+            )));
+        }
+    }
+    let name: String = entry.get_item("name")?.ok_or_else(|| missing_field("name"))?.extract()?;
+    let mut params = Vec::new();
+    if let Some(inputs) = entry.get_item("inputs")? {
+        for input in inputs.try_iter()? {
+            let input = input?;
+            let input = input.downcast::<PyDict>()?;
+            let mut param = json_type(input)?;
+            if let Some(indexed) = input.get_item("indexed")? {
+                if indexed.is_truthy()? {
+                    param.push_str(" indexed");
+                }
+            }
+            if let Some(name) = input.get_item("name")? {
+                let name: String = name.extract()?;
+                if !name.is_empty() {
+                    param.push(' ');
+                    param.push_str(&name);
+                }
+            }
+            params.push(param);
+        }
+    }
+    let anonymous = match entry.get_item("anonymous")? {
+        Some(anonymous) => anonymous.is_truthy()?,
+        None => false,
+    };
+    Ok(format!("{}({}){}", name, params.join(","), if anonymous { " anonymous" } else { "" }))
+}
+
+/// Reads the word at `offset`.
+fn word(data: &[u8], offset: usize) -> Result<&[u8; 32], String> {
+    offset
+        .checked_add(32)
+        .and_then(|end| data.get(offset..end))
+        .map(|word| word.try_into().expect("a 32-byte slice"))
+        .ok_or_else(|| "data is too short".to_string())
+}
+
+/// Reads the word at `offset` as an offset or a length.
+fn size(data: &[u8], offset: usize) -> Result<usize, String> {
+    let word = word(data, offset)?;
+    let size = U256::from_big_endian(word);
+    if size.bits() > 32 {
+        return Err(format!("offset or length {} is out of range", size));
+    }
+    Ok(size.as_usize())
+}
+
+/// How many bytes a value of `kind` takes in the head of the tuple containing it.
+fn head_size(kind: &ParamType) -> usize {
+    match kind {
+        ParamType::FixedArray(inner, len) if !kind.is_dynamic() => {
+            head_size(inner).saturating_mul(*len)
+        }
+        ParamType::Tuple(kinds) if !kind.is_dynamic() => {
+            kinds.iter().fold(0, |size, kind| size.saturating_add(head_size(kind)))
+        }
+        _ => 32,
+    }
+}
+
+/// Decodes a word holding a value type, which must be padded as its type requires.
+fn decode_word(kind: &ParamType, word: &[u8; 32]) -> Result<Token, String> {
+    let value = U256::from_big_endian(word);
+    match kind {
+        ParamType::Address if value.bits() <= 160 => {
+            return Ok(Token::Address(Address::from_slice(&word[12..])))
+        }
+        ParamType::Bool if value.bits() <= 1 => return Ok(Token::Bool(!value.is_zero())),
+        ParamType::Uint(bits) if value.bits() <= *bits => return Ok(Token::Uint(value)),
+        ParamType::Int(bits) => {
+            // Every bit above the value's sign bit is a copy of it
+            let magnitude = if value.bit(255) { !value } else { value };
+            if magnitude.bits() < *bits {
+                return Ok(Token::Int(value));
+            }
+        }
+        ParamType::FixedBytes(len) if word[*len..].iter().all(|byte| *byte == 0) => {
+            return Ok(Token::FixedBytes(word[..*len].to_vec()))
+        }
+        _ => {}
+    }
+    Err(format!("0x{} is not a valid {}", hex::encode(word), kind))
+}
+
+/// Decodes values of `kinds` from `data`, which starts with their heads.
+fn decode_tuple<'a>(
+    kinds: impl Iterator<Item = &'a ParamType>,
+    data: &[u8],
+) -> Result<Vec<Token>, String> {
+    let mut head = 0;
+    kinds
+        .map(|kind| {
+            let value = if kind.is_dynamic() {
+                let offset = size(data, head)?;
+                data.get(offset..).ok_or("offset is past the end of the data")?
+            } else {
+                data.get(head..).ok_or("data is too short")?
+            };
+            head += head_size(kind);
+            decode_value(kind, value)
+        })
+        .collect()
+}
+
+/// Decodes a value of `kind` from the start of `data`.
+fn decode_value(kind: &ParamType, data: &[u8]) -> Result<Token, String> {
+    match kind {
+        ParamType::Bytes | ParamType::String => {
+            let len = size(data, 0)?;
+            let bytes = data[32..].get(..len).ok_or("data is too short")?.to_vec();
+            if !matches!(kind, ParamType::String) {
+                return Ok(Token::Bytes(bytes));
+            }
+            String::from_utf8(bytes)
+                .map(Token::String)
+                .map_err(|_| "string is not valid UTF-8".to_string())
+        }
+        ParamType::Array(inner) | ParamType::FixedArray(inner, _) => {
+            let (len, items) = match kind {
+                ParamType::FixedArray(_, len) => (*len, data),
+                _ => (size(data, 0)?, &data[32..]),
+            };
+            // Checked up front so a huge length can't allocate before running out of data
+            if len.saturating_mul(head_size(inner)) > items.len() {
+                return Err("data is too short".to_string());
+            }
+            let items = decode_tuple(std::iter::repeat_n(&**inner, len), items)?;
+            Ok(match kind {
+                ParamType::FixedArray(..) => Token::FixedArray(items),
+                _ => Token::Array(items),
+            })
+        }
+        ParamType::Tuple(kinds) => decode_tuple(kinds.iter(), data).map(Token::Tuple),
+        _ => decode_word(kind, word(data, 0)?),
+    }
+}
+
+/// Converts a 256-bit word to a Python int.
+fn int_object(py: Python, value: U256, signed: bool) -> PyResult<PyObject> {
+    let negative = signed && value.bit(255);
+    if !negative && value.bits() <= 128 {
+        return Ok(value.as_u128().into_pyobject(py)?.into_any().unbind());
+    }
+    if negative && (!value).bits() < 128 {
+        return Ok((!((!value).as_u128() as i128)).into_pyobject(py)?.into_any().unbind());
+    }
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("signed", negative)?;
+    let int = py.get_type::<PyInt>().call_method("from_bytes", (&word[..], "big"), Some(&kwargs))?;
+    Ok(int.unbind())
+}
+
+/// Converts a decoded token to a Python value.
+fn token_object(py: Python, token: Token) -> PyResult<PyObject> {
+    Ok(match token {
+        Token::Address(value) => {
+            address::checksum(&value, None).into_pyobject(py)?.into_any().unbind()
+        }
+        Token::Bool(value) => PyBool::new(py, value).to_owned().into_any().unbind(),
+        Token::String(value) => value.into_pyobject(py)?.into_any().unbind(),
+        Token::Bytes(value) | Token::FixedBytes(value) => {
+            PyBytes::new(py, &value).into_any().unbind()
+        }
+        Token::Uint(value) => int_object(py, value, false)?,
+        Token::Int(value) => int_object(py, value, true)?,
+        Token::Array(items) | Token::FixedArray(items) => {
+            let items = items.into_iter().map(|item| token_object(py, item));
+            PyList::new(py, items.collect::<PyResult<Vec<_>>>()?)?.into_any().unbind()
+        }
+        Token::Tuple(items) => {
+            let items = items.into_iter().map(|item| token_object(py, item));
+            PyTuple::new(py, items.collect::<PyResult<Vec<_>>>()?)?.into_any().unbind()
+        }
+    })
+}
+
+/// Converts log data or a topic, given as bytes or hex, to bytes.
+fn log_bytes(value: &Bound<PyAny>, what: &str) -> PyResult<Vec<u8>> {
+    if let Ok(bytes) = value.downcast::<PyBytes>() {
+        return Ok(bytes.as_bytes().to_vec());
+    }
+    if let Ok(bytes) = value.downcast::<PyByteArray>() {
+        return Ok(bytes.to_vec());
+    }
+    if let Ok(text) = value.downcast::<PyString>() {
+        let text = text.to_cow()?;
+        if let Ok(bytes) = hex::decode(text.strip_prefix("0x").unwrap_or(&text)) {
+            return Ok(bytes);
+        }
+    }
+    Err(PyErr::new::<errors::FerriteError, _>(format!(
+        "Invalid log {}: expected bytes or hex, got {}",
+        what,
+        value.repr()?
+    )))
+}
+
+/// Reads a log's topics and data.
+fn log_fields(
+    topics: &Bound<PyAny>,
+    data: &Bound<PyAny>,
+) -> PyResult<(Vec<[u8; 32]>, Vec<u8>)> {
+    let topics = topics
+        .try_iter()?
+        .map(|topic| {
+            let topic = log_bytes(&topic?, "topic")?;
+            <[u8; 32]>::try_from(topic.as_slice()).map_err(|_| {
+                PyErr::new::<errors::FerriteError, _>(format!(
+                    "Invalid log topic: expected 32 bytes, got {}",
+                    topic.len()
+                ))
+            })
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok((topics, log_bytes(data, "data")?))
+}
+
+/// The dictionary keys of an event's parameters: their names, or their positions.
+fn param_keys(py: Python, event: &Event) -> PyResult<Vec<PyObject>> {
+    event
+        .params
+        .iter()
+        .enumerate()
+        .map(|(i, param)| match &param.name {
+            Some(name) => Ok(PyString::new(py, name).into_any().unbind()),
+            None => Ok(i.into_pyobject(py)?.into_any().unbind()),
+        })
+        .collect()
+}
+
+fn args_dict(py: Python, keys: &[PyObject], values: Vec<Token>) -> PyResult<PyObject> {
+    let args = PyDict::new(py);
+    for (key, value) in keys.iter().zip(values) {
+        args.set_item(key, token_object(py, value)?)?;
+    }
+    Ok(args.into_any().unbind())
+}
+
+/// ABI-encodes a contract call from its function signature and arguments.
+///
+/// # Arguments
+/// * `signature` - The function signature, such as `"transfer(address,uint256)"`.
+///   Tuples are written as parenthesized type lists, optionally prefixed `tuple`.
+/// * `args` - The arguments, one per parameter: ints for integers, bools, strings, hex
+///   strings or bytes for addresses and byte strings, and lists or tuples for arrays
+///   and tuples.
+///
+/// # Returns
+/// The calldata: the 4-byte selector followed by the encoded arguments.
+#[pyfunction]
+pub fn encode_calldata(py: Python, signature: &str, args: &Bound<PyAny>) -> PyResult<PyObject> {
+    let (canonical, types) = parse_signature(signature)?;
+    let args = match args.downcast::<PyList>() {
+        Ok(list) => list.to_tuple(),
+        Err(_) => args.downcast::<PyTuple>().cloned().map_err(|_| {
+            PyTypeError::new_err("encode_calldata() args must be a list or tuple")
+        })?,
+    };
+    if args.len() != types.len() {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "{} takes {} arguments, got {}",
+            canonical,
+            types.len(),
+            args.len()
+        )));
+    }
+    let tokens = types
+        .iter()
+        .zip(args.iter())
+        .map(|(kind, arg)| tokenize(kind, &arg))
+        .collect::<PyResult<Vec<_>>>()?;
+    let selector = keccak::keccak256(canonical.as_bytes());
+    let calldata = [&selector[..4], &encode(&tokens)].concat();
+    Ok(PyBytes::new(py, &calldata).into_any().unbind())
+}
+
+/// Computes the 4-byte selector of a function signature.
+///
+/// # Arguments
+/// * `signature` - The function signature, such as `"transfer(address,uint256)"`.
+///
+/// # Returns
+/// The selector.
+#[pyfunction]
+pub fn function_selector(py: Python, signature: &str) -> PyResult<PyObject> {
+    let hash = signature_hash(signature)?;
+    Ok(PyBytes::new(py, &hash[..4]).into_any().unbind())
+}
+
+/// Computes the selectors of many function signatures.
+///
+/// # Arguments
+/// * `signatures` - List of function signatures.
+///
+/// # Returns
+/// A list of 4-byte selectors, in input order.
+#[pyfunction]
+pub fn function_selectors(py: Python, signatures: Vec<String>) -> PyResult<PyObject> {
+    signature_hashes(py, signatures, 4)
+}
+
+/// Computes the topic an event signature is logged under.
+///
+/// # Arguments
+/// * `signature` - The event signature, such as `"Transfer(address,address,uint256)"`.
+///
+/// # Returns
+/// The 32-byte `topic0`.
+#[pyfunction]
+pub fn event_topic(py: Python, signature: &str) -> PyResult<PyObject> {
+    let hash = signature_hash(signature)?;
+    Ok(PyBytes::new(py, &hash).into_any().unbind())
+}
+
+/// Computes the topics of many event signatures.
+///
+/// # Arguments
+/// * `signatures` - List of event signatures.
+///
+/// # Returns
+/// A list of 32-byte topics, in input order.
+#[pyfunction]
+pub fn event_topics(py: Python, signatures: Vec<String>) -> PyResult<PyObject> {
+    signature_hashes(py, signatures, 32)
+}
+
+/// Hashes values packed by their Solidity types, as web3.py's `solidity_keccak` does.
+///
+/// # Arguments
+/// * `types` - The Solidity type of each value, such as `"address"` or `"uint8[]"`.
+/// * `values` - The values, converted as for [`encode_calldata`].
+///
+/// # Returns
+/// The 32-byte keccak256 of the packed values.
+#[pyfunction]
+pub fn solidity_keccak(
+    py: Python,
+    types: Vec<String>,
+    values: Vec<Bound<PyAny>>,
+) -> PyResult<PyObject> {
+    if types.len() != values.len() {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "Length mismatch between types and values: got {} types and {} values",
+            types.len(),
+            values.len()
+        )));
+    }
+    let mut packed = Vec::new();
+    for (kind, value) in parse_types(&types)?.iter().zip(&values) {
+        pack(kind, value, false, &mut packed)?;
+    }
+    Ok(PyBytes::new(py, &keccak::keccak256(&packed)).into_any().unbind())
+}
+
+/// Decodes the parameters of a log.
+///
+/// # Arguments
+/// * `event` - The event's signature with `indexed` and parameter names, such as
+///   `"Transfer(address indexed from, address indexed to, uint256 value)"` and
+///   optionally ending in `anonymous`, or its JSON ABI entry.
+/// * `topics` - The log's topics, as bytes or hex strings.
+/// * `data` - The log's data, as bytes or a hex string.
+///
+/// # Returns
+/// A dictionary from parameter name, or position for unnamed parameters, to value:
+/// checksummed strings for addresses, ints, bools, strings, bytes, lists for arrays and
+/// tuples for tuples. Indexed parameters of other than value types are logged only as
+/// a hash, which is returned as 32 bytes.
+#[pyfunction]
+pub fn decode_log(
+    py: Python,
+    event: &Bound<PyAny>,
+    topics: &Bound<PyAny>,
+    data: &Bound<PyAny>,
+) -> PyResult<PyObject> {
+    let event = Event::extract(event)?;
+    let (topics, data) = log_fields(topics, data)?;
+    let values = event.decode(&topics, &data).map_err(|reason| {
+        PyErr::new::<errors::FerriteError, _>(format!(
+            "Cannot decode log of {}: {}",
+            event.signature, reason
+        ))
+    })?;
+    args_dict(py, &param_keys(py, &event)?, values)
+}
+
+/// Decodes the parameters of many logs of the same event in parallel.
+///
+/// # Arguments
+/// * `event` - The event's signature or JSON ABI entry, as for [`decode_log`].
+/// * `logs` - The logs, each a mapping with `topics` and `data`, as `eth_getLogs` and
+///   web3.py return them, or a `(topics, data)` pair.
+///
+/// # Returns
+/// A list of dictionaries, as [`decode_log`] returns, in input order.
+#[pyfunction]
+pub fn decode_logs(py: Python, event: &Bound<PyAny>, logs: &Bound<PyAny>) -> PyResult<PyObject> {
+    let event = Event::extract(event)?;
+    let logs = logs
+        .try_iter()?
+        .map(|log| {
+            let log = log?;
+            if log.is_instance_of::<PyList>() || log.is_instance_of::<PyTuple>() {
+                let (topics, data): (Bound<PyAny>, Bound<PyAny>) = log.extract()?;
+                log_fields(&topics, &data)
+            } else {
+                log_fields(&log.get_item("topics")?, &log.get_item("data")?)
+            }
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let decoded = py.allow_threads(|| {
+        logs.par_iter().map(|(topics, data)| event.decode(topics, data)).collect::<Vec<_>>()
+    });
+    let keys = param_keys(py, &event)?;
+    let decoded = decoded.into_iter().enumerate().map(|(i, values)| {
+        let values = values.map_err(|reason| {
+            PyErr::new::<errors::FerriteError, _>(format!(
+                "Cannot decode log of {} at index {}: {}",
+                event.signature, i, reason
+            ))
+        })?;
+        args_dict(py, &keys, values)
+    });
+    Ok(PyList::new(py, decoded.collect::<PyResult<Vec<_>>>()?)?.into_any().unbind())
+}
+
+/// Decodes ABI-encoded values, such as a call's return data.
+///
+/// # Arguments
+/// * `types` - The type of each value, such as `"uint256"` or `"(bool,bytes)[]"`.
+/// * `data` - The encoded values.
+///
+/// # Returns
+/// A list of the values, converted as by [`decode_log`].
+#[pyfunction]
+pub fn decode_abi(py: Python, types: Vec<String>, data: &[u8]) -> PyResult<PyObject> {
+    let kinds = parse_types(&types)?;
+    let tokens = decode_tuple(kinds.iter(), data).map_err(|reason| {
+        PyErr::new::<errors::FerriteError, _>(format!(
+            "Cannot decode ({}): {}",
+            types.join(","),
+            reason
+        ))
+    })?;
+    let values = tokens.into_iter().map(|token| token_object(py, token));
+    Ok(PyList::new(py, values.collect::<PyResult<Vec<_>>>()?)?.into_any().unbind())
+}
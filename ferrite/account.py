@@ -2,21 +2,62 @@
 This module handles the monkey-patching of eth-account to use the Rust-based signer.
 """
 
+import hashlib
 import json
 import logging
-from typing import Any, Dict
+import operator
+import unicodedata
+from contextlib import contextmanager
+from typing import Any, Dict, Iterator, List, Optional, Tuple, Union
 
 from eth_account.account import LocalAccount
 from eth_account import Account as EthAccount
-from eth_account.datastructures import SignedMessage
+from eth_account.datastructures import SignedMessage, SignedTransaction
+from eth_account.messages import SignableMessage, _hash_eip191_message
 from hexbytes import HexBytes
 from _ferrite import sign_hash as rust_sign_hash  # type: ignore
+from _ferrite import sign_hashes as rust_sign_hashes  # type: ignore
 from _ferrite import sign_typed_data as rust_sign_typed_data  # type: ignore
+from _ferrite import sign_typed_data_batch as rust_sign_typed_data_batch  # type: ignore
 from _ferrite import sign_transaction as rust_sign_transaction  # type: ignore
+from _ferrite import (  # type: ignore
+    addresses_from_keys as rust_addresses_from_keys,
+    addresses_from_seed as rust_addresses_from_seed,
+)
+from _ferrite import (  # type: ignore
+    decrypt_keystore as rust_decrypt_keystore,
+    decrypt_keystores as rust_decrypt_keystores,
+    encrypt_keystore as rust_encrypt_keystore,
+)
+from _ferrite import (  # type: ignore
+    audit_context_var as rust_audit_context_var,
+    set_max_transaction_fee as rust_set_max_transaction_fee,
+    set_signing_policy as rust_set_signing_policy,
+    set_typed_data_guard as rust_set_typed_data_guard,
+)
+from _ferrite import (  # type: ignore
+    sign_hash_async as rust_sign_hash_async,
+    sign_typed_data_async as rust_sign_typed_data_async,
+    sign_transaction_async as rust_sign_transaction_async,
+)
+from _ferrite import (  # type: ignore
+    sign_user_operation as rust_sign_user_operation,
+    user_operation_hash as rust_user_operation_hash,
+)
+from _ferrite import FerriteError  # type: ignore
 
 log = logging.getLogger(__name__)
 
 
+def _private_key_bytes(private_key: Any) -> bytes:
+    """Normalizes a hex string or raw bytes private key to raw bytes."""
+    if isinstance(private_key, (bytes, bytearray)):
+        return bytes(private_key)
+    if private_key.startswith("0x"):
+        private_key = private_key[2:]
+    return bytes.fromhex(private_key)
+
+
 def _sign_hash_wrapper(self, message_hash: bytes) -> SignedMessage:
     """Wraps the Rust-based sign_hash function for LocalAccount."""
     try:
@@ -34,17 +75,16 @@ def _sign_hash_wrapper(self, message_hash: bytes) -> SignedMessage:
         raise
 
 
-def _account_sign_hash_wrapper(message_hash: bytes, private_key: str) -> SignedMessage:
+def _account_sign_hash_wrapper(
+    message_hash: bytes, private_key: str, preimage: Optional[bytes] = None
+) -> SignedMessage:
     """Wraps the Rust-based sign_hash function for Account."""
     try:
-        if isinstance(private_key, (bytes, bytearray)):
-            private_key_bytes = private_key
-        else:
-            if private_key.startswith("0x"):
-                private_key = private_key[2:]
-            private_key_bytes = bytes.fromhex(private_key)
+        private_key_bytes = _private_key_bytes(private_key)
 
-        signature_dict = rust_sign_hash(message_hash, private_key_bytes)
+        signature_dict = rust_sign_hash(
+            message_hash, private_key_bytes, preimage=preimage
+        )
 
         return SignedMessage(
             message_hash=HexBytes(message_hash),
@@ -58,6 +98,52 @@ def _account_sign_hash_wrapper(message_hash: bytes, private_key: str) -> SignedM
         raise
 
 
+def _eip191_preimage(signable_message: SignableMessage) -> bytes:
+    """Returns the bytes an EIP-191 message's hash is the keccak256 of."""
+    return (
+        b"\x19"
+        + signable_message.version
+        + signable_message.header
+        + signable_message.body
+    )
+
+
+def _account_sign_message_wrapper(
+    signable_message: SignableMessage, private_key: str
+) -> SignedMessage:
+    """
+    Wraps the Rust-based sign_hash function for Account.sign_message, passing the
+    message along so that personal messages can be signed under a signing policy.
+    """
+    return _account_sign_hash_wrapper(
+        _hash_eip191_message(signable_message),
+        private_key,
+        _eip191_preimage(signable_message),
+    )
+
+
+def sign_hashes(message_hashes: List[bytes], private_key: Any) -> List[SignedMessage]:
+    """Signs a batch of hashes in parallel with the Rust-based sign_hashes function."""
+    try:
+        signature_dicts = rust_sign_hashes(
+            message_hashes, _private_key_bytes(private_key)
+        )
+
+        return [
+            SignedMessage(
+                message_hash=HexBytes(message_hash),
+                r=int.from_bytes(signature_dict["r"], "big"),
+                s=int.from_bytes(signature_dict["s"], "big"),
+                v=signature_dict["v"],
+                signature=HexBytes(signature_dict["signature"]),
+            )
+            for message_hash, signature_dict in zip(message_hashes, signature_dicts)
+        ]
+    except Exception as e:
+        log.error(f"Error in Rust batch signing operation: {e}")
+        raise
+
+
 def _sign_typed_data_wrapper(self, full_message: Dict[str, Any]) -> SignedMessage:
     """Wraps the Rust-based sign_typed_data function for LocalAccount."""
     try:
@@ -81,12 +167,7 @@ def _account_sign_typed_data_wrapper(
 ) -> SignedMessage:
     """Wraps the Rust-based sign_typed_data function for Account."""
     try:
-        if isinstance(private_key, (bytes, bytearray)):
-            private_key_bytes = private_key
-        else:
-            if private_key.startswith("0x"):
-                private_key = private_key[2:]
-            private_key_bytes = bytes.fromhex(private_key)
+        private_key_bytes = _private_key_bytes(private_key)
 
         json_payload = json.dumps(full_message)
         signature_dict = rust_sign_typed_data(json_payload, private_key_bytes)
@@ -103,39 +184,169 @@ def _account_sign_typed_data_wrapper(
         raise
 
 
+# EIP-712 domain fields in the order the standard defines them
+_DOMAIN_FIELDS = [
+    ("name", "string"),
+    ("version", "string"),
+    ("chainId", "uint256"),
+    ("verifyingContract", "address"),
+    ("salt", "bytes32"),
+]
+
+
+def _eip712_domain(domain: Dict[str, Any]) -> Dict[str, Any]:
+    """Returns the ``EIP712Domain`` type and values for a domain given as a dict."""
+    unknown = set(domain) - {name for name, _ in _DOMAIN_FIELDS}
+    if unknown:
+        raise ValueError(f"Unknown EIP-712 domain fields: {', '.join(sorted(unknown))}")
+
+    fields, values = [], {}
+    for name, kind in _DOMAIN_FIELDS:
+        if name not in domain:
+            continue
+        fields.append({"name": name, "type": kind})
+        value = domain[name]
+        values[name] = str(value) if name == "chainId" else value
+    return {"types": fields, "domain": values}
+
+
+def _sign_typed_data_with(
+    full_message: Dict[str, Any], signer: Any
+) -> Tuple[str, HexBytes]:
+    """
+    Signs typed data with a private key, as bytes or hex, or an account with
+    ``address`` and ``sign_typed_data``, returning the signer's address and the
+    signature.
+    """
+    if hasattr(signer, "sign_typed_data"):
+        signed = signer.sign_typed_data(full_message=full_message)
+        return signer.address, HexBytes(signed.signature)
+
+    private_key = _private_key_bytes(signer)
+    signature_dict = rust_sign_typed_data(json.dumps(full_message), private_key)
+    address = rust_addresses_from_keys([private_key])[0]
+    return address, HexBytes(signature_dict["signature"])
+
+
+def sign_typed_data_batch(
+    domain: Dict[str, Any],
+    types: Dict[str, Any],
+    messages: List[Dict[str, Any]],
+    private_key: Any,
+    primary_type: Optional[str] = None,
+) -> List[SignedMessage]:
+    """Signs a batch of typed data messages sharing one domain with the Rust backend."""
+    try:
+        signature_dicts = rust_sign_typed_data_batch(
+            json.dumps(domain),
+            json.dumps(types),
+            json.dumps(messages),
+            _private_key_bytes(private_key),
+            primary_type,
+        )
+
+        return [
+            SignedMessage(
+                message_hash=HexBytes(b""),
+                r=int.from_bytes(signature_dict["r"], "big"),
+                s=int.from_bytes(signature_dict["s"], "big"),
+                v=signature_dict["v"],
+                signature=HexBytes(signature_dict["signature"]),
+            )
+            for signature_dict in signature_dicts
+        ]
+    except Exception as e:
+        log.error(f"Error in Rust batch typed data signing operation: {e}")
+        raise
+
+
+_INTEGER_FIELDS = (
+    "value",
+    "gas",
+    "gasPrice",
+    "maxFeePerGas",
+    "maxPriorityFeePerGas",
+    "maxFeePerBlobGas",
+    "nonce",
+    "chainId",
+)
+
+
+def _json_value(path: str, value: Any) -> Any:
+    """
+    Converts a transaction value to JSON, raising a TypeError naming ``path`` for
+    anything JSON can't represent. Raw bytes become hex strings.
+    """
+    if value is None or isinstance(value, (str, bool)):
+        return value
+    if isinstance(value, (bytes, bytearray)):
+        return "0x" + bytes(value).hex()
+    if isinstance(value, int):
+        return int(value)
+    if isinstance(value, dict):
+        for key in value:
+            if not isinstance(key, str):
+                raise TypeError(
+                    f"Transaction keys must be strings, got {key!r} "
+                    f"({type(key).__name__}) in {path!r}"
+                )
+        return {key: _json_value(f"{path}.{key}", item) for key, item in value.items()}
+    if isinstance(value, (list, tuple)):
+        return [_json_value(f"{path}[{i}]", item) for i, item in enumerate(value)]
+
+    raise TypeError(
+        f"Transaction field {path!r} has unsupported type {type(value).__name__}"
+    )
+
+
 def _sanitize_transaction(transaction_dict: Dict[str, Any]) -> Dict[str, Any]:
     """
     Sanitizes the transaction dictionary for Rust compatibility.
     Converts integer fields to hex strings as expected by ethers-rs U256 deserialization.
+
+    Non-string keys and values of unexpected types raise a TypeError naming the key.
+    Integer fields also accept integer-like objects such as numpy integers, but not
+    floats or Decimals, which eth-account rejects as well.
     """
-    sanitized = transaction_dict.copy()
-    fields_to_hex = [
-        "value",
-        "gas",
-        "gasPrice",
-        "maxFeePerGas",
-        "maxPriorityFeePerGas",
-        "nonce",
-        "chainId",
-    ]
-
-    for field in fields_to_hex:
-        if field in sanitized:
-            val = sanitized[field]
-            if isinstance(val, int):
-                sanitized[field] = hex(val)
-            elif isinstance(val, str) and not val.startswith("0x") and val.isdigit():
-                sanitized[field] = hex(int(val))
+    sanitized = {}
+    for field, val in transaction_dict.items():
+        if not isinstance(field, str):
+            raise TypeError(
+                f"Transaction keys must be strings, got {field!r} "
+                f"({type(field).__name__})"
+            )
+
+        if field in _INTEGER_FIELDS and not isinstance(val, (str, bool, type(None))):
+            try:
+                val = hex(operator.index(val))
+            except TypeError:
+                raise TypeError(
+                    f"Transaction field {field!r} must be an integer or a string, "
+                    f"got {type(val).__name__}"
+                ) from None
+        elif isinstance(val, str) and field in _INTEGER_FIELDS:
+            if not val.startswith("0x") and val.isdigit():
+                val = hex(int(val))
+
+        sanitized[field] = _json_value(field, val)
 
     return sanitized
 
 
-def _sign_transaction_wrapper(self, transaction_dict: Dict[str, Any]) -> SignedMessage:
+def _sign_transaction_wrapper(
+    self,
+    transaction_dict: Dict[str, Any],
+    *,
+    sighash: Optional[bytes] = None,
+    check_fee_cap: bool = True,
+) -> SignedMessage:
     """Wraps the Rust-based sign_transaction function for LocalAccount."""
     try:
         sanitized_tx = _sanitize_transaction(transaction_dict)
         json_payload = json.dumps(sanitized_tx)
-        signature_dict = rust_sign_transaction(json_payload, self.key)
+        signature_dict = rust_sign_transaction(
+            json_payload, self.key, sighash=sighash, check_fee_cap=check_fee_cap
+        )
 
         from eth_account.datastructures import SignedTransaction
 
@@ -152,20 +363,24 @@ def _sign_transaction_wrapper(self, transaction_dict: Dict[str, Any]) -> SignedM
 
 
 def _account_sign_transaction_wrapper(
-    transaction_dict: Dict[str, Any], private_key: str
+    transaction_dict: Dict[str, Any],
+    private_key: str,
+    *,
+    sighash: Optional[bytes] = None,
+    check_fee_cap: bool = True,
 ) -> SignedMessage:
     """Wraps the Rust-based sign_transaction function for Account."""
     try:
-        if isinstance(private_key, (bytes, bytearray)):
-            private_key_bytes = private_key
-        else:
-            if private_key.startswith("0x"):
-                private_key = private_key[2:]
-            private_key_bytes = bytes.fromhex(private_key)
+        private_key_bytes = _private_key_bytes(private_key)
 
         sanitized_tx = _sanitize_transaction(transaction_dict)
         json_payload = json.dumps(sanitized_tx)
-        signature_dict = rust_sign_transaction(json_payload, private_key_bytes)
+        signature_dict = rust_sign_transaction(
+            json_payload,
+            private_key_bytes,
+            sighash=sighash,
+            check_fee_cap=check_fee_cap,
+        )
 
         from eth_account.datastructures import SignedTransaction
 
@@ -181,21 +396,398 @@ def _account_sign_transaction_wrapper(
         raise
 
 
+async def sign_hash_async(
+    message_hash: bytes, private_key: Any, preimage: Optional[bytes] = None
+) -> SignedMessage:
+    """Signs a hash on the Rust thread pool without blocking the event loop."""
+    try:
+        signature_dict = await rust_sign_hash_async(
+            message_hash, _private_key_bytes(private_key), preimage=preimage
+        )
+
+        return SignedMessage(
+            message_hash=HexBytes(message_hash),
+            r=int.from_bytes(signature_dict["r"], "big"),
+            s=int.from_bytes(signature_dict["s"], "big"),
+            v=signature_dict["v"],
+            signature=HexBytes(signature_dict["signature"]),
+        )
+    except Exception as e:
+        log.error(f"Error in Rust async signing operation: {e}")
+        raise
+
+
+async def sign_message_async(
+    signable_message: SignableMessage, private_key: Any
+) -> SignedMessage:
+    """Signs an EIP-191 message on the Rust thread pool without blocking the loop."""
+    return await sign_hash_async(
+        _hash_eip191_message(signable_message),
+        private_key,
+        _eip191_preimage(signable_message),
+    )
+
+
+async def sign_typed_data_async(
+    full_message: Dict[str, Any], private_key: Any
+) -> SignedMessage:
+    """Signs EIP-712 typed data on the Rust thread pool without blocking the loop."""
+    try:
+        json_payload = json.dumps(full_message)
+        signature_dict = await rust_sign_typed_data_async(
+            json_payload, _private_key_bytes(private_key)
+        )
+
+        return SignedMessage(
+            message_hash=HexBytes(b""),
+            r=int.from_bytes(signature_dict["r"], "big"),
+            s=int.from_bytes(signature_dict["s"], "big"),
+            v=signature_dict["v"],
+            signature=HexBytes(signature_dict["signature"]),
+        )
+    except Exception as e:
+        log.error(f"Error in Rust async typed data signing operation: {e}")
+        raise
+
+
+async def sign_transaction_async(
+    transaction_dict: Dict[str, Any], private_key: Any
+) -> SignedTransaction:
+    """Signs a transaction on the Rust thread pool without blocking the event loop."""
+    try:
+        sanitized_tx = _sanitize_transaction(transaction_dict)
+        json_payload = json.dumps(sanitized_tx)
+        signature_dict = await rust_sign_transaction_async(
+            json_payload, _private_key_bytes(private_key)
+        )
+
+        return SignedTransaction(
+            raw_transaction=HexBytes(signature_dict["rawTransaction"]),
+            hash=HexBytes(signature_dict["hash"]),
+            r=int.from_bytes(signature_dict["r"], "big"),
+            s=int.from_bytes(signature_dict["s"], "big"),
+            v=signature_dict["v"],
+        )
+    except Exception as e:
+        log.error(f"Error in Rust async transaction signing operation: {e}")
+        raise
+
+
+def _quantities_json(fields: Dict[str, Any]) -> str:
+    """Serializes a payload to JSON, with integers as hex quantities."""
+    return json.dumps(
+        {
+            field: (
+                hex(operator.index(val))
+                if isinstance(val, int) and not isinstance(val, bool)
+                else _json_value(field, val)
+            )
+            for field, val in fields.items()
+        }
+    )
+
+
+def user_operation_hash(
+    user_op: Dict[str, Any],
+    entry_point: str,
+    chain_id: int,
+    *,
+    version: Optional[str] = None,
+) -> HexBytes:
+    """Computes the ``userOpHash`` of an ERC-4337 v0.6 or v0.7 user operation."""
+    return HexBytes(
+        rust_user_operation_hash(
+            _quantities_json(user_op), entry_point, chain_id, version=version
+        )
+    )
+
+
+def sign_user_operation(
+    user_op: Dict[str, Any],
+    entry_point: str,
+    chain_id: int,
+    private_key: Any,
+    *,
+    eip191: bool = True,
+    version: Optional[str] = None,
+) -> SignedMessage:
+    """
+    Signs an ERC-4337 user operation for the EntryPoint at ``entry_point``.
+
+    v0.7 operations may be given packed, with ``accountGasLimits`` and ``gasFees``, or
+    unpacked as bundler RPCs take them, with ``factory`` and ``paymaster`` fields.
+    ``version`` is ``"0.6"`` or ``"0.7"``; if omitted it is detected from the canonical
+    EntryPoint addresses, or else from the fields only v0.7 has.
+
+    The ``userOpHash`` is signed as an EIP-191 personal message, as ``SimpleAccount``
+    and most accounts derived from it expect; pass ``eip191=False`` for accounts that
+    check a signature of the bare hash. The result's ``message_hash`` is the
+    ``userOpHash`` and its ``signature`` goes in the user operation's ``signature``.
+    """
+    try:
+        signature_dict = rust_sign_user_operation(
+            _quantities_json(user_op),
+            entry_point,
+            chain_id,
+            _private_key_bytes(private_key),
+            eip191=eip191,
+            version=version,
+        )
+
+        return SignedMessage(
+            message_hash=HexBytes(signature_dict["userOpHash"]),
+            r=int.from_bytes(signature_dict["r"], "big"),
+            s=int.from_bytes(signature_dict["s"], "big"),
+            v=signature_dict["v"],
+            signature=HexBytes(signature_dict["signature"]),
+        )
+    except Exception as e:
+        log.error(f"Error in Rust user operation signing operation: {e}")
+        raise
+
+
+def _password_bytes(password: Union[str, bytes]) -> bytes:
+    """Encodes a text password the same way eth-account does."""
+    if isinstance(password, str):
+        return password.encode("utf-8")
+    return bytes(password)
+
+
+def _keyfile_json(keyfile: Union[str, Dict[str, Any]]) -> str:
+    """Serializes a keyfile dictionary, passing JSON strings through unchanged."""
+    if isinstance(keyfile, str):
+        return keyfile
+    return json.dumps(keyfile)
+
+
+def _account_decrypt_wrapper(
+    keyfile_json: Union[str, Dict[str, Any]], password: Union[str, bytes]
+) -> HexBytes:
+    """Wraps the Rust-based decrypt_keystore function for Account."""
+    try:
+        return HexBytes(
+            rust_decrypt_keystore(
+                _keyfile_json(keyfile_json), _password_bytes(password)
+            )
+        )
+    except Exception as e:
+        log.error(f"Error in Rust keystore decryption (Account adapter): {e}")
+        raise
+
+
+def _account_encrypt_wrapper(
+    private_key: Any,
+    password: Union[str, bytes],
+    kdf: Optional[str] = None,
+    iterations: Optional[int] = None,
+) -> Dict[str, Any]:
+    """Wraps the Rust-based encrypt_keystore function for Account."""
+    try:
+        if hasattr(private_key, "to_bytes"):
+            private_key = private_key.to_bytes()
+
+        keystore = rust_encrypt_keystore(
+            _private_key_bytes(private_key),
+            _password_bytes(password),
+            kdf or "scrypt",
+            iterations,
+        )
+        return json.loads(keystore)
+    except Exception as e:
+        log.error(f"Error in Rust keystore encryption (Account adapter): {e}")
+        raise
+
+
+def decrypt_keystores(
+    keyfiles: List[Union[str, Dict[str, Any]]],
+    passwords: Union[str, bytes, List[Union[str, bytes]]],
+) -> List[HexBytes]:
+    """Decrypts a batch of keystores in parallel with the Rust backend."""
+    try:
+        if isinstance(passwords, (str, bytes)):
+            passwords = [passwords] * len(keyfiles)
+
+        private_keys = rust_decrypt_keystores(
+            [_keyfile_json(keyfile) for keyfile in keyfiles],
+            [_password_bytes(password) for password in passwords],
+        )
+        return [HexBytes(private_key) for private_key in private_keys]
+    except Exception as e:
+        log.error(f"Error in Rust batch keystore decryption: {e}")
+        raise
+
+
+def _keyring() -> Any:
+    """Imports the optional ``keyring`` package that fronts the OS keychain."""
+    try:
+        import keyring  # type: ignore
+    except ImportError as e:
+        raise ImportError(
+            "Keychain support needs the keyring package: pip install ferrite[keychain]"
+        ) from e
+    return keyring
+
+
+def store_keychain_password(
+    service: str, username: str, password: Union[str, bytes]
+) -> None:
+    """
+    Stores a keystore password in the OS keychain.
+
+    Uses the platform credential store through the ``keyring`` package: Keychain on
+    macOS, the Windows Credential Locker (DPAPI) and Secret Service on Linux.
+    """
+    if isinstance(password, bytes):
+        password = password.decode("utf-8")
+    _keyring().set_password(service, username, password)
+
+
+def keychain_password(service: str, username: str) -> str:
+    """
+    Reads a keystore password stored with ``store_keychain_password``.
+
+    Lets a restarted service decrypt its keystores without the password being typed
+    in or kept in an environment variable.
+    """
+    password = _keyring().get_password(service, username)
+    if password is None:
+        raise FerriteError(
+            f"No password for {username!r} in the OS keychain under {service!r}"
+        )
+    return password
+
+
+def addresses_from_keys(private_keys: List[Any]) -> List[str]:
+    """Derives checksummed addresses for a batch of private keys in parallel."""
+    return rust_addresses_from_keys([_private_key_bytes(key) for key in private_keys])
+
+
+def addresses_from_mnemonic(
+    mnemonic: str,
+    start: int = 0,
+    count: int = 1,
+    passphrase: str = "",
+    path: str = "m/44'/60'/0'/0",
+) -> List[str]:
+    """Derives checksummed addresses for consecutive HD indexes under ``path``."""
+    seed = hashlib.pbkdf2_hmac(
+        "sha512",
+        unicodedata.normalize("NFKD", mnemonic).encode("utf-8"),
+        unicodedata.normalize("NFKD", "mnemonic" + passphrase).encode("utf-8"),
+        2048,
+    )
+    return rust_addresses_from_seed(seed, start, count, path)
+
+
+def set_signing_policy(policy: Optional[Dict[str, Any]], *, lock: bool = False) -> None:
+    """
+    Sets the limits every transaction must meet before ferrite signs it.
+
+    The policy is enforced in Rust on the transaction exactly as it will be signed.
+    Pass ``None`` to remove it, or ``lock=True`` to keep it for the rest of the process.
+    Bare hashes other than of personal messages are refused under a policy unless it
+    sets ``allowRawHashes``.
+    """
+    if policy is None:
+        return rust_set_signing_policy(None, lock=lock)
+
+    limits = dict(policy)
+    for name in ("maxValue", "maxFee"):
+        # Wei amounts easily exceed what a JSON number carries exactly
+        if isinstance(limits.get(name), int):
+            limits[name] = hex(limits[name])
+    rust_set_signing_policy(json.dumps(limits), lock=lock)
+
+
+def set_typed_data_guard(
+    mode: str = "warn",
+    *,
+    allow: Optional[List[Dict[str, Any]]] = None,
+    allow_raw_hashes: bool = False,
+) -> None:
+    """
+    Configures the guard on high-risk typed data such as ``Permit``.
+
+    ``mode`` is ``"warn"`` (the default), ``"block"`` or ``"off"``. Domains in
+    ``allow`` may receive high-risk typed data without a warning or error. In block
+    mode, bare hashes other than of personal messages are refused as well, since they
+    may be of such typed data, unless ``allow_raw_hashes`` is set.
+    """
+    rust_set_typed_data_guard(
+        mode,
+        allow=None if allow is None else json.dumps(allow),
+        allow_raw_hashes=allow_raw_hashes,
+    )
+
+
+def set_max_transaction_fee(max_fee: Optional[int]) -> None:
+    """
+    Caps the fee in wei of every transaction ferrite signs, or removes the cap.
+
+    The fee is ``gas`` times ``gasPrice``, or times ``maxFeePerGas`` for a dynamic fee
+    transaction, plus ``maxFeePerBlobGas`` times the blob gas of any
+    ``blobVersionedHashes``. Transactions over the cap raise
+    ``InvalidTransactionError``.
+    """
+    rust_set_max_transaction_fee(None if max_fee is None else hex(max_fee))
+
+
+@contextmanager
+def audit_context(**context: Any) -> Iterator[None]:
+    """
+    Attaches ``context`` to the audit record of every signature made in the block.
+
+    Nested blocks add to the outer context. Values must be JSON serializable.
+    """
+    var = rust_audit_context_var()
+    token = var.set({**(var.get(None) or {}), **context})
+    try:
+        yield
+    finally:
+        var.reset(token)
+
+
+_original_methods: Dict[Tuple[type, str], Any] = {}
+
+
+def _patch(cls: type, name: str, replacement: Any) -> None:
+    """Replaces ``cls.name``, remembering the original so it can be restored."""
+    _original_methods.setdefault((cls, name), cls.__dict__.get(name))
+    setattr(cls, name, replacement)
+
+
 def patch_eth_account() -> None:
     """
     Replaces the core signing methods on LocalAccount and Account with the Rust implementation.
     """
     try:
-        setattr(LocalAccount, "_sign_hash", _sign_hash_wrapper)
-        setattr(EthAccount, "_sign_hash", _account_sign_hash_wrapper)
+        _patch(LocalAccount, "_sign_hash", _sign_hash_wrapper)
+        _patch(EthAccount, "_sign_hash", _account_sign_hash_wrapper)
+        _patch(EthAccount, "sign_message", _account_sign_message_wrapper)
+
+        _patch(LocalAccount, "sign_typed_data", _sign_typed_data_wrapper)
+        _patch(EthAccount, "sign_typed_data", _account_sign_typed_data_wrapper)
 
-        setattr(LocalAccount, "sign_typed_data", _sign_typed_data_wrapper)
-        setattr(EthAccount, "sign_typed_data", _account_sign_typed_data_wrapper)
+        _patch(LocalAccount, "sign_transaction", _sign_transaction_wrapper)
+        _patch(EthAccount, "sign_transaction", _account_sign_transaction_wrapper)
 
-        setattr(LocalAccount, "sign_transaction", _sign_transaction_wrapper)
-        setattr(EthAccount, "sign_transaction", _account_sign_transaction_wrapper)
+        _patch(EthAccount, "decrypt", staticmethod(_account_decrypt_wrapper))
+        _patch(EthAccount, "encrypt", staticmethod(_account_encrypt_wrapper))
 
         log.debug("Patched LocalAccount and Account methods with Rust implementation")
     except Exception as e:
         log.error(f"Failed to patch eth-account: {e}")
         raise
+
+
+def unpatch_eth_account() -> None:
+    """
+    Restores the original eth-account methods replaced by patch_eth_account.
+    """
+    for (cls, name), original in _original_methods.items():
+        if original is None:
+            delattr(cls, name)
+        else:
+            setattr(cls, name, original)
+
+    log.debug("Restored original LocalAccount and Account methods")
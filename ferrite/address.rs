@@ -0,0 +1,367 @@
+/*!
+Batch derivation of addresses from private keys, and of single addresses from public
+keys held elsewhere.
+
+Deriving an address costs one secp256k1 scalar multiplication and one keccak256, both
+independent per key, so batches are spread over the Rayon pool in a single GIL release.
+Keys are never cached here: recovery tooling walks through far more candidates than
+the signing key cache could usefully hold.
+
+Checksums and contract addresses are computed by `ferrite_core::address`. Mining a
+vanity `CREATE2` address tries consecutive salts over the Rayon pool, a chunk at a time,
+checking for `KeyboardInterrupt` between chunks.
+*/
+
+use coins_bip32::prelude::{Parent, XPriv};
+use ethers_core::types::Address;
+use k256::ecdsa::{SigningKey, VerifyingKey};
+use k256::pkcs8::DecodePublicKey;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyList, PyTuple};
+use rayon::prelude::*;
+use zeroize::Zeroizing;
+
+use crate::errors;
+
+pub use ferrite_core::address::{checksum, create2, key_address};
+
+/// Salts tried per GIL release while mining, which takes well under a second.
+const MINE_CHUNK: u64 = 1 << 20;
+
+/// Parses a hex address, with or without `0x`, ignoring its checksum.
+pub fn parse(address: &str) -> PyResult<Address> {
+    let digits = address.strip_prefix("0x").unwrap_or(address);
+    let mut bytes = [0u8; 20];
+    hex::decode_to_slice(digits, &mut bytes).map_err(|_| {
+        PyErr::new::<errors::FerriteError, _>(format!(
+            "Address must be 20 bytes as 40 hex digits, got {:?}",
+            address
+        ))
+    })?;
+    Ok(Address::from(bytes))
+}
+
+/// Derives the checksummed address of each private key.
+///
+/// # Arguments
+/// * `private_keys` - List of 32-byte raw private keys.
+///
+/// # Returns
+/// A list of EIP-55 checksummed address strings, in input order.
+#[pyfunction]
+pub fn addresses_from_keys(py: Python, private_keys: Vec<Bound<PyBytes>>) -> PyResult<PyObject> {
+    let private_keys = private_keys
+        .iter()
+        .map(|key| Zeroizing::new(key.as_bytes().to_vec()))
+        .collect::<Vec<_>>();
+
+    let addresses = py.allow_threads(|| {
+        private_keys
+            .par_iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let key = SigningKey::from_slice(key).map_err(|e| {
+                    PyErr::new::<errors::InvalidKeyError, _>(
+                        errors::redact(format!("Invalid private key at index {}: {}", i, e), key)
+                    )
+                })?;
+                Ok(checksum(&key_address(key.verifying_key()), None))
+            })
+            .collect::<PyResult<Vec<_>>>()
+    })?;
+
+    Ok(PyList::new(py, addresses)?.into_any().unbind())
+}
+
+/// Derives the checksummed addresses of consecutive BIP-32 children of a seed.
+///
+/// # Arguments
+/// * `seed` - BIP-39 seed bytes.
+/// * `start` - Index of the first child to derive.
+/// * `count` - Number of consecutive children to derive.
+/// * `path` - Derivation path of the parent of the children.
+///
+/// # Returns
+/// A list of EIP-55 checksummed address strings for `path/start` .. `path/start+count-1`.
+#[pyfunction]
+#[pyo3(signature = (seed, start, count, path = "m/44'/60'/0'/0"))]
+pub fn addresses_from_seed(
+    py: Python,
+    seed: &[u8],
+    start: u32,
+    count: u32,
+    path: &str,
+) -> PyResult<PyObject> {
+    let addresses = py.allow_threads(|| {
+        let parent = XPriv::root_from_seed(seed, None)
+            .and_then(|root| root.derive_path(path))
+            .map_err(|e| {
+                PyErr::new::<errors::FerriteError, _>(
+                    format!("Invalid seed or derivation path: {}", e)
+                )
+            })?;
+
+        (0..count)
+            .into_par_iter()
+            .map(|offset| {
+                let index = start.checked_add(offset).ok_or_else(|| {
+                    PyErr::new::<errors::FerriteError, _>(
+                        "Child index overflows 32 bits"
+                    )
+                })?;
+                let child = parent.derive_child(index).map_err(|e| {
+                    PyErr::new::<errors::FerriteError, _>(
+                        format!("Failed to derive child {}: {}", index, e)
+                    )
+                })?;
+                let key: &SigningKey = child.as_ref();
+                Ok(checksum(&key_address(key.verifying_key()), None))
+            })
+            .collect::<PyResult<Vec<_>>>()
+    })?;
+
+    Ok(PyList::new(py, addresses)?.into_any().unbind())
+}
+
+/// Derives the checksummed address of a public key.
+///
+/// # Arguments
+/// * `public_key` - secp256k1 public key as a DER `SubjectPublicKeyInfo` (as AWS and
+///   Google Cloud KMS return it), a SEC1 point (33 or 65 bytes) or the 64-byte `x || y`.
+///
+/// # Returns
+/// The EIP-55 checksummed address of the key.
+#[pyfunction]
+pub fn address_from_public_key(public_key: &[u8]) -> PyResult<String> {
+    let key = match public_key.len() {
+        64 => VerifyingKey::from_sec1_bytes(&[&[4u8][..], public_key].concat()),
+        33 | 65 => VerifyingKey::from_sec1_bytes(public_key),
+        _ => VerifyingKey::from_public_key_der(public_key).map_err(|_| k256::ecdsa::Error::new()),
+    }
+    .map_err(|_| {
+        PyErr::new::<errors::FerriteError, _>(format!(
+            "Invalid secp256k1 public key of {} bytes",
+            public_key.len()
+        ))
+    })?;
+    Ok(checksum(&key_address(&key), None))
+}
+
+/// Checksums an address.
+///
+/// # Arguments
+/// * `address` - Hex address, with or without `0x`, in any case.
+/// * `chain_id` - Chain to checksum for with EIP-1191. EIP-55 if `None`.
+///
+/// # Returns
+/// The checksummed address, with `0x`.
+#[pyfunction]
+#[pyo3(signature = (address, chain_id = None))]
+pub fn to_checksum_address(address: &str, chain_id: Option<u64>) -> PyResult<String> {
+    Ok(checksum(&parse(address)?, chain_id))
+}
+
+/// Checksums a batch of addresses.
+///
+/// # Arguments
+/// * `addresses` - Hex addresses, with or without `0x`, in any case.
+/// * `chain_id` - Chain to checksum for with EIP-1191. EIP-55 if `None`.
+///
+/// # Returns
+/// A list of checksummed addresses, in input order.
+#[pyfunction]
+#[pyo3(signature = (addresses, chain_id = None))]
+pub fn to_checksum_addresses(
+    py: Python,
+    addresses: Vec<String>,
+    chain_id: Option<u64>,
+) -> PyResult<PyObject> {
+    let addresses = addresses.iter().map(|address| parse(address)).collect::<PyResult<Vec<_>>>()?;
+    let checksummed = py.allow_threads(|| {
+        addresses.par_iter().map(|address| checksum(address, chain_id)).collect::<Vec<_>>()
+    });
+    Ok(PyList::new(py, checksummed)?.into_any().unbind())
+}
+
+/// Checks whether `address` is exactly its checksummed form.
+fn is_checksummed(address: &str, chain_id: Option<u64>) -> bool {
+    parse(address).is_ok_and(|parsed| checksum(&parsed, chain_id) == address)
+}
+
+/// Checks that an address carries a valid checksum.
+///
+/// # Arguments
+/// * `address` - The address to check.
+/// * `chain_id` - Chain the address was checksummed for with EIP-1191. EIP-55 if `None`.
+///
+/// # Returns
+/// `True` if `address` is `0x` and 40 hex digits cased as its checksum requires, and
+/// `False` for anything else, including single-case and malformed addresses.
+#[pyfunction]
+#[pyo3(signature = (address, chain_id = None))]
+pub fn is_checksum_address(address: &str, chain_id: Option<u64>) -> bool {
+    is_checksummed(address, chain_id)
+}
+
+/// Checks that each of a batch of addresses carries a valid checksum.
+///
+/// # Arguments
+/// * `addresses` - The addresses to check.
+/// * `chain_id` - Chain the addresses were checksummed for with EIP-1191. EIP-55 if `None`.
+///
+/// # Returns
+/// A list of booleans, as `is_checksum_address` returns, in input order.
+#[pyfunction]
+#[pyo3(signature = (addresses, chain_id = None))]
+pub fn is_checksum_addresses(
+    py: Python,
+    addresses: Vec<String>,
+    chain_id: Option<u64>,
+) -> PyResult<PyObject> {
+    let valid = py.allow_threads(|| {
+        addresses.par_iter().map(|address| is_checksummed(address, chain_id)).collect::<Vec<_>>()
+    });
+    Ok(PyList::new(py, valid)?.into_any().unbind())
+}
+
+fn bytes32(value: &[u8], name: &str) -> PyResult<[u8; 32]> {
+    value.try_into().map_err(|_| {
+        PyErr::new::<errors::FerriteError, _>(format!(
+            "{} must be exactly 32 bytes, got {}",
+            name,
+            value.len()
+        ))
+    })
+}
+
+/// Parses the hex digits an address must start or end with.
+fn nibbles(digits: &str, name: &str) -> PyResult<Vec<u8>> {
+    let digits = digits.strip_prefix("0x").unwrap_or(digits);
+    digits
+        .chars()
+        .map(|digit| digit.to_digit(16).map(|nibble| nibble as u8))
+        .collect::<Option<Vec<_>>>()
+        .filter(|nibbles| nibbles.len() <= 40)
+        .ok_or_else(|| {
+            PyErr::new::<errors::FerriteError, _>(format!(
+                "{} must be at most 40 hex digits, got {:?}",
+                name, digits
+            ))
+        })
+}
+
+fn nibble(address: &Address, index: usize) -> u8 {
+    let byte = address.as_bytes()[index / 2];
+    if index % 2 == 1 { byte & 0xf } else { byte >> 4 }
+}
+
+/// Computes the address a contract deployed with `CREATE` lands at.
+///
+/// # Arguments
+/// * `deployer` - Hex address of the deploying account or contract.
+/// * `nonce` - The deployer's nonce at deployment. Contracts start at 1.
+///
+/// # Returns
+/// The EIP-55 checksummed address of the contract.
+#[pyfunction]
+pub fn create_address(deployer: &str, nonce: u64) -> PyResult<String> {
+    Ok(checksum(&ferrite_core::address::create(&parse(deployer)?, nonce), None))
+}
+
+/// Computes the address a contract deployed with `CREATE2` lands at.
+///
+/// # Arguments
+/// * `deployer` - Hex address of the contract executing `CREATE2`, such as a factory.
+/// * `salt` - The 32-byte salt.
+/// * `init_code_hash` - The keccak256 of the init code, constructor arguments included.
+///
+/// # Returns
+/// The EIP-55 checksummed address of the contract.
+#[pyfunction]
+pub fn create2_address(deployer: &str, salt: &[u8], init_code_hash: &[u8]) -> PyResult<String> {
+    let salt = bytes32(salt, "Salt")?;
+    let init_code_hash = bytes32(init_code_hash, "Init code hash")?;
+    Ok(checksum(&create2(&parse(deployer)?, &salt, &init_code_hash), None))
+}
+
+/// Searches for a `CREATE2` salt giving an address that starts and ends with given hex
+/// digits.
+///
+/// Salts are `salt_prefix`, then zeros, then a big-endian 64-bit counter in the last 8
+/// bytes, tried from `start` for `attempts` values. Factories that only accept salts
+/// starting with the caller's address, to stop front-running, take it as `salt_prefix`.
+///
+/// # Arguments
+/// * `deployer` - Hex address of the contract executing `CREATE2`.
+/// * `init_code_hash` - The keccak256 of the init code.
+/// * `prefix` - Hex digits the address must start with, in any case.
+/// * `suffix` - Hex digits the address must end with, in any case.
+/// * `salt_prefix` - Up to 24 bytes every salt starts with.
+/// * `start` - The first counter value to try.
+/// * `attempts` - How many counter values to try.
+///
+/// # Returns
+/// A tuple of the 32-byte salt with the lowest matching counter and its checksummed
+/// address, or `None` if no salt tried matches.
+#[pyfunction]
+#[pyo3(signature = (
+    deployer,
+    init_code_hash,
+    prefix = "",
+    suffix = "",
+    salt_prefix = b"".as_slice(),
+    start = 0,
+    attempts = 1 << 32,
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn mine_create2_salt(
+    py: Python,
+    deployer: &str,
+    init_code_hash: &[u8],
+    prefix: &str,
+    suffix: &str,
+    salt_prefix: &[u8],
+    start: u64,
+    attempts: u64,
+) -> PyResult<Option<PyObject>> {
+    let deployer = parse(deployer)?;
+    let init_code_hash = bytes32(init_code_hash, "Init code hash")?;
+    let prefix = nibbles(prefix, "Prefix")?;
+    let suffix = nibbles(suffix, "Suffix")?;
+    if salt_prefix.len() > 24 {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "Salt prefix must be at most 24 bytes, got {}",
+            salt_prefix.len()
+        )));
+    }
+    let mut template = [0u8; 32];
+    template[..salt_prefix.len()].copy_from_slice(salt_prefix);
+    let end = start.checked_add(attempts).ok_or_else(|| {
+        PyErr::new::<errors::FerriteError, _>("Salt counter overflows 64 bits")
+    })?;
+
+    let matches = |address: &Address| {
+        prefix.iter().enumerate().all(|(i, digit)| nibble(address, i) == *digit)
+            && suffix.iter().rev().enumerate().all(|(i, digit)| nibble(address, 39 - i) == *digit)
+    };
+    let mut from = start;
+    while from < end {
+        let to = end.min(from.saturating_add(MINE_CHUNK));
+        let found = py.allow_threads(|| {
+            (from..to).into_par_iter().find_map_first(|counter| {
+                let mut salt = template;
+                salt[24..].copy_from_slice(&counter.to_be_bytes());
+                let address = create2(&deployer, &salt, &init_code_hash);
+                matches(&address).then_some((salt, address))
+            })
+        });
+        if let Some((salt, address)) = found {
+            let salt = PyBytes::new(py, &salt).into_any();
+            let address = checksum(&address, None).into_pyobject(py)?.into_any();
+            return Ok(Some(PyTuple::new(py, [salt, address])?.into_any().unbind()));
+        }
+        py.check_signals()?;
+        from = to;
+    }
+    Ok(None)
+}
@@ -0,0 +1,235 @@
+/*!
+Pre-sign approval hook.
+
+A Python callable registered with `set_approval_hook` is shown a summary of everything
+ferrite is about to sign, after it has been parsed and hashed, and must return `True`
+for the signature to be produced. This lets a human or an external policy service
+approve each signature without ferrite knowing anything about either.
+
+Signing runs without the GIL, so the hook is called with the GIL reacquired on
+whichever thread is signing; for the async and batch functions that is a worker thread
+rather than the caller's. Each interpreter has its own hook, which is called in the
+interpreter that asked for the signature. When no interpreter has a hook registered,
+the only cost is one atomic load.
+*/
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::transaction::eip712::EIP712Domain;
+use ethers_core::types::{Address, H256, U256};
+use ethers_core::utils::to_checksum;
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyInt, PyList};
+use serde_json::Value;
+
+use crate::errors;
+use crate::interpreter::{self, Local};
+
+static HOOK: Local<Mutex<Option<PyObject>>> = Local::new();
+
+/// How many interpreters have a hook registered, so signing without one never takes
+/// the lock.
+static INSTALLED: AtomicUsize = AtomicUsize::new(0);
+
+/// What is about to be signed, beyond the digest itself.
+pub enum Subject<'a> {
+    /// A bare 32-byte hash, whose preimage ferrite never sees.
+    Hash,
+    /// An EIP-712 message.
+    TypedData {
+        domain: &'a EIP712Domain,
+        primary_type: &'a str,
+        message: Value,
+    },
+    /// A transaction, exactly as it will be signed.
+    Transaction(&'a TypedTransaction),
+}
+
+fn int<'py>(py: Python<'py>, value: U256) -> PyResult<Bound<'py, PyAny>> {
+    py.get_type::<PyInt>().call1((value.to_string(),))
+}
+
+fn address(to: Option<&Address>) -> Option<String> {
+    to.map(|to| to_checksum(to, None))
+}
+
+fn json<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    py.import("json")?.call_method1("loads", (value.to_string(),))
+}
+
+fn domain_dict<'py>(py: Python<'py>, domain: &EIP712Domain) -> PyResult<Bound<'py, PyDict>> {
+    let result = PyDict::new(py);
+    result.set_item("name", &domain.name)?;
+    result.set_item("version", &domain.version)?;
+    result.set_item("chainId", domain.chain_id.map(|id| int(py, id)).transpose()?)?;
+    result.set_item("verifyingContract", address(domain.verifying_contract.as_ref()))?;
+    result.set_item("salt", domain.salt.map(|salt| PyBytes::new(py, &salt)))?;
+    Ok(result)
+}
+
+fn transaction_dict<'py>(
+    py: Python<'py>,
+    tx: &TypedTransaction,
+) -> PyResult<Bound<'py, PyDict>> {
+    let result = PyDict::new(py);
+    let (kind, max_fees) = match tx {
+        TypedTransaction::Legacy(_) => (0, None),
+        TypedTransaction::Eip2930(_) => (1, None),
+        TypedTransaction::Eip1559(tx) => {
+            (2, Some((tx.max_fee_per_gas, tx.max_priority_fee_per_gas)))
+        }
+    };
+    let optional = |value: Option<U256>| value.map(|value| int(py, value)).transpose();
+
+    result.set_item("type", kind)?;
+    result.set_item("chainId", tx.chain_id().map(|id| id.as_u64()))?;
+    result.set_item("nonce", optional(tx.nonce().copied())?)?;
+    result.set_item("to", address(tx.to_addr()))?;
+    result.set_item("value", int(py, tx.value().copied().unwrap_or_default())?)?;
+    result.set_item("gas", optional(tx.gas().copied())?)?;
+    match max_fees {
+        Some((max_fee, priority_fee)) => {
+            result.set_item("maxFeePerGas", optional(max_fee)?)?;
+            result.set_item("maxPriorityFeePerGas", optional(priority_fee)?)?;
+        }
+        None => result.set_item("gasPrice", optional(tx.gas_price())?)?,
+    }
+    result.set_item(
+        "data",
+        PyBytes::new(py, tx.data().map_or(&[][..], |data| data.as_ref())),
+    )?;
+
+    let access_list = PyList::empty(py);
+    for item in tx.access_list().map_or(&[][..], |list| list.0.as_slice()) {
+        let entry = PyDict::new(py);
+        entry.set_item("address", to_checksum(&item.address, None))?;
+        let storage_keys = item.storage_keys.iter().map(|key| PyBytes::new(py, key.as_bytes()));
+        entry.set_item("storageKeys", PyList::new(py, storage_keys)?)?;
+        access_list.append(entry)?;
+    }
+    result.set_item("accessList", access_list)?;
+
+    Ok(result)
+}
+
+/// Builds the summary passed to the hook.
+fn summary<'py>(
+    py: Python<'py>,
+    signer: Address,
+    digest: H256,
+    subject: &Subject,
+) -> PyResult<Bound<'py, PyDict>> {
+    let result = PyDict::new(py);
+    result.set_item("address", to_checksum(&signer, None))?;
+    result.set_item("hash", PyBytes::new(py, digest.as_bytes()))?;
+
+    match subject {
+        Subject::Hash => result.set_item("kind", "hash")?,
+        Subject::TypedData { domain, primary_type, message } => {
+            result.set_item("kind", "typed_data")?;
+            result.set_item("domain", domain_dict(py, domain)?)?;
+            result.set_item("primaryType", primary_type)?;
+            result.set_item("message", json(py, message)?)?;
+        }
+        Subject::Transaction(tx) => {
+            result.set_item("kind", "transaction")?;
+            result.set_item("transaction", transaction_dict(py, tx)?)?;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Whether any interpreter has a hook registered.
+pub fn is_installed() -> bool {
+    INSTALLED.load(Ordering::Acquire) > 0
+}
+
+/// Asks the registered hook, if any, to approve signing `digest` with `signer`'s key.
+///
+/// Called without the GIL, after everything has been validated and right before the
+/// digest is signed. `subject` is only built when a hook is registered. Exceptions
+/// raised by the hook propagate to the caller.
+pub fn approve<'a>(
+    signer: Address,
+    digest: H256,
+    subject: impl FnOnce() -> Subject<'a>,
+) -> PyResult<()> {
+    if !is_installed() {
+        return Ok(());
+    }
+
+    interpreter::with_gil(|py| {
+        let state = HOOK.get(py);
+        let hook = state.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|hook| {
+            // Dropped as `Bound`, since this may be a thread PyO3 doesn't know holds the GIL
+            hook.bind(py).clone()
+        });
+        let Some(hook) = hook else {
+            return Ok(());
+        };
+
+        let subject = subject();
+        let approved = hook.call1((summary(py, signer, digest, &subject)?,))?;
+        // Anything but `True` itself, including other truthy values, denies the signature
+        if approved.downcast::<PyBool>().is_ok_and(|approved| approved.is_true()) {
+            tracing::debug!(signer = ?signer, "approved by the approval hook");
+            return Ok(());
+        }
+        tracing::info!(signer = ?signer, "denied by the approval hook");
+        Err(PyErr::new::<errors::ApprovalDeniedError, _>(format!(
+            "Signing {} with {} was not approved",
+            match subject {
+                Subject::Hash => "a hash",
+                Subject::TypedData { .. } => "typed data",
+                Subject::Transaction(_) => "a transaction",
+            },
+            to_checksum(&signer, None)
+        )))
+    })
+}
+
+/// Registers a callable that must approve every signature the calling interpreter asks
+/// for before it is produced.
+///
+/// # Arguments
+/// * `hook` - Callable taking a summary dictionary and returning `True` to approve,
+///   or `None` to remove the hook. The summary always has `kind` (`"hash"`,
+///   `"typed_data"` or `"transaction"`), `address` (the signing key's address) and
+///   `hash` (the digest to be signed), plus `domain`, `primaryType` and `message` for
+///   typed data or `transaction` for transactions.
+#[pyfunction]
+#[pyo3(signature = (hook))]
+pub fn set_approval_hook(py: Python, hook: Option<Bound<PyAny>>) -> PyResult<()> {
+    if let Some(hook) = hook.as_ref().filter(|hook| !hook.is_callable()) {
+        return Err(PyTypeError::new_err(format!(
+            "Approval hook must be callable, got {}",
+            hook.get_type().name()?
+        )));
+    }
+
+    let state = HOOK.get(py);
+    let mut current = state.lock().unwrap_or_else(|e| e.into_inner());
+    match (current.is_some(), hook.is_some()) {
+        (false, true) => INSTALLED.fetch_add(1, Ordering::Release),
+        (true, false) => INSTALLED.fetch_sub(1, Ordering::Release),
+        _ => 0,
+    };
+    *current = hook.map(Bound::unbind);
+    Ok(())
+}
+
+/// Drops the hook of the interpreter the GIL is held in, once its module is freed.
+pub fn clear(py: Python) {
+    let Some(state) = HOOK.take(py) else {
+        return;
+    };
+    let hook = state.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(hook) = hook {
+        INSTALLED.fetch_sub(1, Ordering::Release);
+        drop(hook.into_bound(py));
+    }
+}
@@ -0,0 +1,93 @@
+/*!
+Bridges signing work on the Rust thread pool to asyncio futures.
+
+Work is spawned on the global Rayon pool and its result is delivered back to the
+calling event loop with `call_soon_threadsafe`, so awaiting it never blocks the loop
+and callers don't need to manage their own executor.
+*/
+
+use std::mem::ManuallyDrop;
+use std::sync::{mpsc, Mutex};
+
+use pyo3::prelude::*;
+use pyo3::types::PyCFunction;
+
+use crate::errors;
+use crate::interpreter::{self, Interpreter};
+
+/// Runs `work` on the Rust thread pool and returns an asyncio future for its result.
+///
+/// `work` runs without the GIL, as part of the calling interpreter's call. `convert`
+/// runs with the GIL in that interpreter once `work` finishes, and turns its output
+/// into the Python object the future resolves to. Must be called from a thread with a
+/// running event loop.
+pub fn spawn<T, W, C>(py: Python, work: W, convert: C) -> PyResult<PyObject>
+where
+    T: Send + 'static,
+    W: FnOnce() -> T + Send + 'static,
+    C: FnOnce(Python, T) -> PyResult<PyObject> + Send + 'static,
+{
+    let interpreter = Interpreter::current(py);
+    let event_loop = py.import("asyncio")?.call_method0("get_running_loop")?;
+    let future = event_loop.call_method0("create_future")?;
+    let awaitable = future.clone().unbind();
+    // Leaked if the interpreter exits first, since they can't be released without it
+    let pending = ManuallyDrop::new((event_loop.unbind(), future.unbind()));
+
+    rayon::spawn(move || {
+        let output = interpreter::enter(Some(interpreter), work);
+        let (detached, detaching) = mpsc::sync_channel(1);
+        let _ = interpreter.with_gil(|py| {
+            let (event_loop, future) = ManuallyDrop::into_inner(pending);
+            let event_loop = event_loop.into_bound(py);
+            let outcome = convert(py, output);
+            // Fails only if the loop has been closed, in which case nobody is awaiting
+            let _ = resolve(py, &event_loop, future, outcome, detaching);
+            Ok(())
+        });
+        let _ = detached.send(());
+    });
+
+    Ok(awaitable)
+}
+
+/// Schedules `future` to be completed with `outcome` on its event loop, once `detaching`
+/// says the worker has let go of the interpreter, which can't be shut down while the
+/// worker is still attached to it.
+fn resolve(
+    py: Python,
+    event_loop: &Bound<PyAny>,
+    future: PyObject,
+    outcome: PyResult<PyObject>,
+    detaching: mpsc::Receiver<()>,
+) -> PyResult<()> {
+    let outcome = Mutex::new(Some((outcome, detaching)));
+
+    let complete = PyCFunction::new_closure(py, None, None, move |args, _kwargs| {
+        let py = args.py();
+        let future = future.bind(py);
+
+        let Some((outcome, detaching)) = outcome.lock().unwrap_or_else(|e| e.into_inner()).take()
+        else {
+            return Ok(());
+        };
+        // Fails only if the worker panicked, which detaches it all the same
+        let _ = py.allow_threads(move || detaching.recv());
+        // The awaiting task may have been cancelled while the work was running
+        if future.call_method0("done")?.is_truthy()? {
+            return Ok(());
+        }
+
+        match outcome {
+            Ok(value) => future.call_method1("set_result", (value,))?,
+            Err(mut e) => {
+                errors::normalize(py, &mut e);
+                future.call_method1("set_exception", (e.into_value(py),))?
+            }
+        };
+        Ok::<_, PyErr>(())
+    })?;
+
+    event_loop.call_method1("call_soon_threadsafe", (complete,))?;
+    Ok(())
+}
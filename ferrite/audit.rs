@@ -0,0 +1,398 @@
+/*!
+Opt-in audit log of every signature ferrite produces.
+
+Each signature is recorded as one JSON object with the time, signing address, signed
+hash and, where the signed object has them, chain id, destination and value, plus any
+context the caller set with `ferrite.audit_context`. Records go either to a JSON Lines
+file or to a Python callback, configured with `set_audit_log`.
+
+Records are written after signing but before the signature is handed back, so a
+signature whose record could not be written is never returned. The caller's context is
+read from a `ContextVar` when the signing call starts, so it follows the caller across
+threads and asyncio tasks rather than the thread the signing happens to run on.
+
+With `chain=True`, each record also carries the previous record's receipt as `previous`
+and ends with its own `receipt`: the keccak256 of the line as written up to that field,
+closed with `}`. Editing, dropping or reordering any record breaks every receipt after
+it, which `verify_audit_log` detects. A callback receives records one call at a time,
+in the order they link, and can't itself sign.
+
+Each interpreter configures its own audit log and context, and records signatures it
+asked for.
+*/
+
+use std::cell::Cell;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::transaction::eip712::EIP712Domain;
+use ethers_core::types::{Address, H256, U256};
+use ethers_core::utils::to_checksum;
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use serde_json::{json, Value};
+
+use crate::interpreter::Local;
+use crate::{errors, keccak};
+
+enum Sink {
+    File(File),
+    Callback(PyObject),
+}
+
+struct Log {
+    sink: Sink,
+    /// The last receipt written, when records are hash-chained.
+    chain: Option<H256>,
+}
+
+/// An interpreter's audit log and the `ContextVar` holding its callers' context.
+#[derive(Default)]
+struct Audit {
+    sink: Mutex<Option<Log>>,
+    context: GILOnceCell<PyObject>,
+}
+
+static AUDIT: Local<Audit> = Local::new();
+
+/// Held from building records until the callback has received them, so that chained
+/// records reach it in the order they link. Taken before `sink`, which is released
+/// while the callback runs so that it can reconfigure auditing.
+static DELIVERY: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// Set while this thread is handing records to the callback.
+    static DELIVERING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// How many interpreters have a sink configured, so signing without one never takes
+/// the lock.
+static ENABLED: AtomicUsize = AtomicUsize::new(0);
+
+/// What was signed, as recorded in the audit log.
+pub struct Record {
+    pub kind: &'static str,
+    pub address: Address,
+    /// The transaction hash for transactions, the signed digest otherwise.
+    pub hash: H256,
+    pub chain_id: Option<U256>,
+    /// The recipient of a transaction, or the verifying contract of typed data.
+    pub to: Option<Address>,
+    /// Recorded as a decimal string, since wei amounts overflow JSON numbers.
+    pub value: Option<U256>,
+}
+
+impl Record {
+    /// A record for a bare hash, which carries nothing beyond the digest.
+    pub fn hash(address: Address, hash: H256) -> Self {
+        Record { kind: "hash", address, hash, chain_id: None, to: None, value: None }
+    }
+
+    /// A record for EIP-712 typed data, addressed to its verifying contract.
+    pub fn typed_data(address: Address, hash: H256, domain: &EIP712Domain) -> Self {
+        Record {
+            kind: "typed_data",
+            address,
+            hash,
+            chain_id: domain.chain_id,
+            to: domain.verifying_contract,
+            value: None,
+        }
+    }
+
+    /// A record for a signed transaction, under its transaction hash.
+    pub fn transaction(address: Address, hash: H256, tx: &TypedTransaction) -> Self {
+        Record {
+            kind: "transaction",
+            address,
+            hash,
+            chain_id: tx.chain_id().map(|id| U256::from(id.as_u64())),
+            to: tx.to_addr().copied(),
+            value: Some(tx.value().copied().unwrap_or_default()),
+        }
+    }
+
+    fn to_json(&self, timestamp: f64, context: Option<&Value>) -> Value {
+        json!({
+            "timestamp": timestamp,
+            "kind": self.kind,
+            "address": to_checksum(&self.address, None),
+            "hash": format!("{:?}", self.hash),
+            // Chain ids in typed data domains are uint256
+            "chainId": self.chain_id.map(|id| match u64::try_from(id) {
+                Ok(id) => json!(id),
+                Err(_) => json!(id.to_string()),
+            }),
+            "to": self.to.map(|to| to_checksum(&to, None)),
+            "value": self.value.map(|value| value.to_string()),
+            "context": context,
+        })
+    }
+}
+
+/// The `ContextVar` holding the caller's audit context.
+fn context_var<'py>(py: Python<'py>, audit: &Audit) -> PyResult<Bound<'py, PyAny>> {
+    audit
+        .context
+        .get_or_try_init(py, || {
+            let var = py
+                .import("contextvars")?
+                .getattr("ContextVar")?
+                .call1(("ferrite_audit_context",))?;
+            Ok::<_, PyErr>(var.unbind())
+        })
+        .map(|var| var.bind(py).clone())
+}
+
+/// Returns the caller's audit context, or `None` when auditing is off.
+///
+/// Must be called on the calling thread when the signing call starts.
+pub fn context(py: Python) -> PyResult<Option<Value>> {
+    if ENABLED.load(Ordering::Acquire) == 0 {
+        return Ok(None);
+    }
+    let audit = AUDIT.get(py);
+    if audit.sink.lock().unwrap_or_else(|e| e.into_inner()).is_none() {
+        return Ok(None);
+    }
+
+    let context = context_var(py, &audit)?.call_method1("get", (py.None(),))?;
+    if context.is_none() {
+        return Ok(None);
+    }
+    let text: String = py.import("json")?.call_method1("dumps", (context,))?.extract()?;
+    serde_json::from_str(&text).map(Some).map_err(|e| {
+        PyErr::new::<errors::FerriteError, _>(format!("Invalid audit context: {}", e))
+    })
+}
+
+/// Links `json` to the receipt before it, returning its line and advancing `previous`
+/// to its receipt.
+///
+/// The receipt hashes the exact text written rather than re-serialized JSON, which
+/// wouldn't reproduce floats such as the timestamp exactly.
+fn chain_record(json: &mut Value, previous: &mut H256) -> String {
+    json["previous"] = json!(format!("{:?}", previous));
+    let line = json.to_string();
+    *previous = H256(keccak::keccak256(line.as_bytes()));
+    format!("{},\"receipt\":\"{:?}\"}}", &line[..line.len() - 1], previous)
+}
+
+/// Checks every receipt in the hash-chained audit log `text`, returning the last one.
+fn verify_chain(text: &str) -> Result<Option<H256>, String> {
+    let mut previous = H256::zero();
+    let mut last = None;
+    for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+        let broken = |message: &str| format!("line {}: {}", i + 1, message);
+        let json: Value =
+            serde_json::from_str(line).map_err(|e| broken(&format!("invalid JSON ({})", e)))?;
+        let receipt = format!(",\"receipt\":{}}}", json["receipt"]);
+        let Some(body) = line.strip_suffix(&receipt).filter(|_| json["receipt"].is_string())
+        else {
+            return Err(broken("record doesn't end with a receipt"));
+        };
+        if json["previous"] != json!(format!("{:?}", previous)) {
+            return Err(broken("record doesn't follow the one before it"));
+        }
+        let expected = H256(keccak::keccak256(format!("{}}}", body).as_bytes()));
+        if json["receipt"] != json!(format!("{:?}", expected)) {
+            return Err(broken("receipt doesn't match the record"));
+        }
+        previous = expected;
+        last = Some(expected);
+    }
+    Ok(last)
+}
+
+/// Takes `DELIVERY`, waiting without the GIL so that the thread holding it can finish
+/// calling the callback.
+fn lock_delivery(py: Python) -> MutexGuard<'static, ()> {
+    loop {
+        match DELIVERY.try_lock() {
+            Ok(guard) => return guard,
+            Err(TryLockError::Poisoned(e)) => return e.into_inner(),
+            Err(TryLockError::WouldBlock) => py.allow_threads(|| drop(DELIVERY.lock())),
+        }
+    }
+}
+
+/// Writes `records` to the configured sink, if any.
+pub fn record(py: Python, records: &[Record], context: Option<&Value>) -> PyResult<()> {
+    if ENABLED.load(Ordering::Acquire) == 0 {
+        return Ok(());
+    }
+    if DELIVERING.get() {
+        return Err(PyErr::new::<errors::FerriteError, _>(
+            "Audit callbacks can't sign, since their records would be delivered out of order",
+        ));
+    }
+    let _delivery = lock_delivery(py);
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64());
+
+    let audit = AUDIT.get(py);
+    let mut log = audit.sink.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(Log { sink, chain }) = &mut *log else {
+        return Ok(());
+    };
+    // Built under the lock, so that chained records are written in the order they link
+    let lines: Vec<String> = records
+        .iter()
+        .map(|record| {
+            let mut json = record.to_json(timestamp, context);
+            match chain {
+                Some(previous) => chain_record(&mut json, previous),
+                None => json.to_string(),
+            }
+        })
+        .collect();
+
+    match sink {
+        Sink::File(file) => {
+            // One write per call, so that a batch's records are never interleaved
+            let text = lines.iter().fold(String::new(), |text, line| text + line + "\n");
+            file.write_all(text.as_bytes()).map_err(|e| {
+                PyErr::new::<errors::FerriteError, _>(format!("Failed to write audit log: {}", e))
+            })
+        }
+        Sink::Callback(callback) => {
+            let callback = callback.clone_ref(py);
+            drop(log);
+            let json = py.import("json")?;
+            DELIVERING.set(true);
+            let delivered = lines.iter().try_for_each(|line| {
+                callback.call1(py, (json.call_method1("loads", (line,))?,)).map(drop)
+            });
+            DELIVERING.set(false);
+            delivered
+        }
+    }
+}
+
+/// Configures where the calling interpreter's audit records are written, or turns its
+/// auditing off.
+///
+/// # Arguments
+/// * `path` - JSON Lines file to append one record per signature to. Created if it
+///   doesn't exist.
+/// * `callback` - Callable receiving each record as a dictionary, instead of a file.
+/// * `chain` - Whether to hash-chain the records with tamper-evident receipts. A file
+///   that already holds chained records is continued; a callback starts a new chain.
+///
+/// With neither `path` nor `callback`, auditing is turned off.
+#[pyfunction]
+#[pyo3(signature = (path = None, *, callback = None, chain = false))]
+pub fn set_audit_log(
+    py: Python,
+    path: Option<std::path::PathBuf>,
+    callback: Option<Bound<PyAny>>,
+    chain: bool,
+) -> PyResult<()> {
+    let mut previous = H256::zero();
+    let sink = match (path, callback) {
+        (Some(_), Some(_)) => {
+            return Err(PyErr::new::<errors::FerriteError, _>(
+                "Audit records go to either a file or a callback, not both",
+            ))
+        }
+        (Some(path), None) => {
+            let failed = |e: String| {
+                PyErr::new::<errors::FerriteError, _>(format!(
+                    "Failed to open audit log {}: {}",
+                    path.display(),
+                    e
+                ))
+            };
+            let mut file = OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| failed(e.to_string()))?;
+            if chain {
+                let mut text = String::new();
+                file.read_to_string(&mut text).map_err(|e| failed(e.to_string()))?;
+                previous = verify_chain(&text).map_err(failed)?.unwrap_or_default();
+            }
+            Some(Sink::File(file))
+        }
+        (None, Some(callback)) if !callback.is_callable() => {
+            return Err(pyo3::exceptions::PyTypeError::new_err(format!(
+                "Audit callback must be callable, got {}",
+                callback.get_type().name()?
+            )))
+        }
+        (None, Some(callback)) => Some(Sink::Callback(callback.unbind())),
+        (None, None) => None,
+    };
+
+    // Created up front, so that `audit_context` can set it before the first signature
+    let audit = AUDIT.get(py);
+    context_var(py, &audit)?;
+
+    let mut current = audit.sink.lock().unwrap_or_else(|e| e.into_inner());
+    match (current.is_some(), sink.is_some()) {
+        (false, true) => ENABLED.fetch_add(1, Ordering::Release),
+        (true, false) => ENABLED.fetch_sub(1, Ordering::Release),
+        _ => 0,
+    };
+    *current = sink.map(|sink| Log { sink, chain: chain.then_some(previous) });
+    Ok(())
+}
+
+/// Verifies the receipts of a hash-chained audit log written with `chain=True`.
+///
+/// # Arguments
+/// * `path` - The audit log file.
+///
+/// # Returns
+/// The last receipt as hex, or `None` for an empty log. Records dropped from the end of
+/// the log can only be detected by comparing it with a receipt kept elsewhere.
+#[pyfunction]
+pub fn verify_audit_log(path: std::path::PathBuf) -> PyResult<Option<String>> {
+    let text = std::fs::read_to_string(&path).map_err(|e| {
+        PyErr::new::<errors::FerriteError, _>(format!(
+            "Failed to read audit log {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let last = verify_chain(&text).map_err(|e| {
+        PyErr::new::<errors::FerriteError, _>(format!(
+            "Audit log {} has been tampered with at {}",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok(last.map(|receipt| format!("{:?}", receipt)))
+}
+
+/// Returns the `ContextVar` that `ferrite.audit_context` sets.
+#[pyfunction]
+pub fn audit_context_var(py: Python) -> PyResult<PyObject> {
+    Ok(context_var(py, &AUDIT.get(py))?.unbind())
+}
+
+/// Drops the audit log and context of the interpreter the GIL is held in, once its
+/// module is freed.
+pub fn clear(py: Python) {
+    let Some(audit) = AUDIT.take(py) else {
+        return;
+    };
+    let log = audit.sink.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(log) = log {
+        ENABLED.fetch_sub(1, Ordering::Release);
+        if let Sink::Callback(callback) = log.sink {
+            drop(callback.into_bound(py));
+        }
+    }
+    if let Some(context) = Arc::into_inner(audit).and_then(|audit| audit.context.into_inner()) {
+        drop(context.into_bound(py));
+    }
+}
@@ -0,0 +1,170 @@
+"""
+Standardized benchmarks comparing ferrite against stock eth-account.
+
+Each workload is run through the public eth-account API twice: once with the
+original eth-account implementation and once with ferrite's Rust backend patched
+in, so the numbers reflect what application code actually sees.
+
+Run with ``python -m ferrite.bench``.
+"""
+
+import argparse
+import time
+from contextlib import contextmanager
+from typing import Any, Callable, Dict, Iterator, List, Optional, Tuple, cast
+
+from eth_account import Account
+from eth_account.messages import encode_defunct
+
+from . import install
+from .account import patch_eth_account, unpatch_eth_account
+
+KEY = "0x" + "0" * 63 + "1"
+
+MESSAGE = encode_defunct(text="The quick brown fox jumps over the lazy dog")
+
+LEGACY_TRANSACTION = {
+    "to": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC",
+    "value": 1000000000000000000,
+    "gas": 21000,
+    "gasPrice": 1000000000,
+    "nonce": 0,
+    "chainId": 1,
+}
+
+EIP1559_TRANSACTION = {
+    "type": 2,
+    "to": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC",
+    "value": 1000000000000000000,
+    "gas": 21000,
+    "maxFeePerGas": 2000000000,
+    "maxPriorityFeePerGas": 1000000000,
+    "nonce": 0,
+    "chainId": 1,
+}
+
+TYPED_DATA = {
+    "types": {
+        "EIP712Domain": [
+            {"name": "name", "type": "string"},
+            {"name": "version", "type": "string"},
+            {"name": "chainId", "type": "uint256"},
+            {"name": "verifyingContract", "type": "address"},
+        ],
+        "Person": [
+            {"name": "name", "type": "string"},
+            {"name": "wallet", "type": "address"},
+        ],
+        "Mail": [
+            {"name": "from", "type": "Person"},
+            {"name": "to", "type": "Person"},
+            {"name": "contents", "type": "string"},
+        ],
+    },
+    "primaryType": "Mail",
+    "domain": {
+        "name": "Ether Mail",
+        "version": "1",
+        "chainId": 1,
+        "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC",
+    },
+    "message": {
+        "from": {"name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},
+        "to": {"name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},
+        "contents": "Hello, Bob!",
+    },
+}
+
+WORKLOADS: List[Tuple[str, Callable[[], Any]]] = [
+    (
+        "legacy transaction",
+        lambda: cast(Any, Account).sign_transaction(LEGACY_TRANSACTION, KEY),
+    ),
+    (
+        "EIP-1559 transaction",
+        lambda: cast(Any, Account).sign_transaction(EIP1559_TRANSACTION, KEY),
+    ),
+    (
+        "typed data (EIP-712)",
+        lambda: cast(Any, Account).sign_typed_data(KEY, full_message=TYPED_DATA),
+    ),
+    (
+        "personal_sign",
+        lambda: cast(Any, Account).sign_message(MESSAGE, KEY),
+    ),
+]
+
+
+@contextmanager
+def _stock_eth_account() -> Iterator[None]:
+    """Temporarily restores the original eth-account signing methods."""
+    unpatch_eth_account()
+    try:
+        yield
+    finally:
+        patch_eth_account()
+
+
+def measure(func: Callable[[], Any], iterations: int, warmup: int) -> float:
+    """Returns the throughput of ``func`` in operations per second."""
+    for _ in range(warmup):
+        func()
+
+    start = time.perf_counter()
+    for _ in range(iterations):
+        func()
+    elapsed = time.perf_counter() - start
+
+    return iterations / elapsed
+
+
+def run(iterations: int = 1000, warmup: int = 100) -> Dict[str, Dict[str, float]]:
+    """
+    Runs every workload against stock eth-account and against ferrite.
+
+    Args:
+        iterations: Number of timed operations per workload and backend.
+        warmup: Number of untimed operations run first.
+
+    Returns:
+        A mapping of workload name to ``eth_account`` and ``ferrite`` ops/sec and
+        the resulting ``speedup``.
+    """
+    install()
+
+    results: Dict[str, Dict[str, float]] = {}
+    for name, func in WORKLOADS:
+        with _stock_eth_account():
+            baseline = measure(func, iterations, warmup)
+        accelerated = measure(func, iterations, warmup)
+
+        results[name] = {
+            "eth_account": baseline,
+            "ferrite": accelerated,
+            "speedup": accelerated / baseline,
+        }
+
+    return results
+
+
+def main(argv: Optional[List[str]] = None) -> None:
+    parser = argparse.ArgumentParser(
+        prog="python -m ferrite.bench",
+        description="Compare ferrite signing throughput against eth-account.",
+    )
+    parser.add_argument("--iterations", type=int, default=1000)
+    parser.add_argument("--warmup", type=int, default=100)
+    args = parser.parse_args(argv)
+
+    results = run(args.iterations, args.warmup)
+
+    print(f"{'Workload':<24} {'eth-account':>14} {'ferrite':>14} {'Speedup':>9}")
+    for name, result in results.items():
+        print(
+            f"{name:<24} {result['eth_account']:>10.0f} op/s "
+            f"{result['ferrite']:>10.0f} op/s {result['speedup']:>8.1f}x"
+        )
+
+
+if __name__ == "__main__":
+    main()
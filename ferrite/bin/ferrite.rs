@@ -0,0 +1,7 @@
+//! The `ferrite` command-line signer. See `ferrite --help`.
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    _ferrite::cli::main()
+}
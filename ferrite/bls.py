@@ -0,0 +1,136 @@
+"""
+BLS signatures for ERC-4337 accounts validated by a signature aggregator.
+
+Accounts that name an aggregator in their validation data don't check their own
+signatures: each signs its operation with a BLS key, and the bundler replaces the
+signatures of every operation sharing the aggregator with one aggregated signature,
+which the aggregator checks in a single pairing. This follows the reference
+``BLSSignatureAggregator`` and ``BLSAccount`` from eth-infinitism, on the BN254 curve
+and with the hubble project's hash to the curve.
+"""
+
+import secrets
+from typing import Any, Dict, Iterable, Optional
+
+from eth_utils import keccak
+from hexbytes import HexBytes
+from _ferrite import (  # type: ignore
+    bls_aggregate as rust_bls_aggregate,
+    bls_public_key as rust_bls_public_key,
+    bls_sign as rust_bls_sign,
+    bls_user_operation_hash as rust_bls_user_operation_hash,
+)
+from .account import _private_key_bytes, _quantities_json
+
+# The domain the reference aggregator hashes user operation hashes to the curve under
+BLS_DOMAIN = HexBytes(keccak(text="eip4337.bls.domain"))
+
+_ORDER = 0x30644E72E131A029B85045B68181585D2833E84879B9709143E1F593F0000001
+
+
+def create_bls_key() -> HexBytes:
+    """Generates a random BLS secret key."""
+    return HexBytes((secrets.randbelow(_ORDER - 1) + 1).to_bytes(32, "big"))
+
+
+def bls_public_key(secret_key: Any) -> HexBytes:
+    """
+    Returns the public key of a BLS secret key, given as bytes or hex, as the 128-byte
+    ``abi.encode`` of the ``uint256[4]`` accounts are deployed with.
+    """
+    return HexBytes(rust_bls_public_key(_private_key_bytes(secret_key)))
+
+
+def bls_sign(
+    message: bytes, secret_key: Any, *, domain: bytes = BLS_DOMAIN
+) -> HexBytes:
+    """
+    Signs a message with a BLS secret key, hashing it to the curve under ``domain``.
+
+    Returns:
+        The 64-byte signature, the ``abi.encode`` of its ``uint256[2]``.
+    """
+    return HexBytes(
+        rust_bls_sign(
+            bytes(HexBytes(domain)),
+            bytes(HexBytes(message)),
+            _private_key_bytes(secret_key),
+        )
+    )
+
+
+def bls_user_operation_hash(
+    user_op: Dict[str, Any],
+    aggregator: str,
+    chain_id: int,
+    public_key: bytes,
+    *,
+    version: Optional[str] = None,
+) -> HexBytes:
+    """
+    Computes the hash the aggregator has an account sign for a user operation, which
+    binds the operation to the account's public key, the aggregator and the chain
+    rather than to the EntryPoint.
+
+    Args:
+        user_op: The user operation, as for ``user_operation_hash``.
+        aggregator: The address of the aggregator contract.
+        chain_id: The chain the aggregator is deployed on.
+        public_key: The account's 128-byte BLS public key.
+        version: The EntryPoint version, ``"0.6"`` or ``"0.7"``. Detected from the
+            fields only v0.7 has if omitted.
+
+    Returns:
+        The hash, as the aggregator's ``getUserOpHash`` returns it.
+    """
+    return HexBytes(
+        rust_bls_user_operation_hash(
+            _quantities_json(user_op),
+            aggregator,
+            chain_id,
+            bytes(HexBytes(public_key)),
+            version=version,
+        )
+    )
+
+
+def sign_bls_user_operation(
+    user_op: Dict[str, Any],
+    aggregator: str,
+    chain_id: int,
+    secret_key: Any,
+    *,
+    version: Optional[str] = None,
+) -> HexBytes:
+    """
+    Signs a user operation for an account validated by the BLS aggregator.
+
+    Args:
+        user_op: The user operation, as for ``user_operation_hash``.
+        aggregator: The address of the aggregator contract.
+        chain_id: The chain the aggregator is deployed on.
+        secret_key: The account's BLS secret key, as bytes or hex.
+        version: The EntryPoint version, as for ``bls_user_operation_hash``.
+
+    Returns:
+        The 64-byte partial signature, which goes in the user operation's
+        ``signature`` until the bundler aggregates it.
+    """
+    public_key = bls_public_key(secret_key)
+    user_op_hash = bls_user_operation_hash(
+        user_op, aggregator, chain_id, public_key, version=version
+    )
+    return bls_sign(user_op_hash, secret_key)
+
+
+def aggregate_bls_signatures(signatures: Iterable[bytes]) -> HexBytes:
+    """
+    Aggregates the partial signatures of the user operations sharing an aggregator, as
+    its ``aggregateSignatures`` does.
+
+    Returns:
+        The 64-byte aggregated signature, for the bundle's ``UserOpsPerAggregator``.
+    """
+    return HexBytes(
+        rust_bls_aggregate([bytes(HexBytes(signature)) for signature in signatures])
+    )
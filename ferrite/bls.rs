@@ -0,0 +1,387 @@
+/*!
+BLS signatures on BN254, for ERC-4337 accounts validated by a signature aggregator.
+
+The reference `BLSSignatureAggregator` from eth-infinitism checks BLS signatures with
+the hubble project's `BLS.sol`: public keys are G2 points, signatures and messages G1
+points, and messages are hashed to the curve with hubble's `hashToPoint`, an
+`expand_message_xmd` over SHA-256 followed by the Fouque-Tibouchi map. Each account
+signs a point derived from its operation's hash, and the bundler sums the signatures of
+every operation the aggregator validates into the single point it hands the EntryPoint.
+
+Points are encoded as the aggregator `abi.encode`s them: G1 points as `x || y` and G2
+points as `x.im || x.re || y.im || y.re`, each coordinate a 32-byte word.
+*/
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crypto_bigint::modular::constant_mod::{Residue, ResidueParams};
+use crypto_bigint::{impl_modulus, Encoding, NonZero, U256, U512};
+use ethers_core::abi::{encode, Token};
+use ethers_core::types::{H256, U256 as EthU256};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use sha2::{Digest, Sha256};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use zeroize::Zeroize;
+
+use crate::curve::{self, Curve, FieldElement};
+use crate::{address, errors, keccak, userop};
+
+impl_modulus!(
+    FieldPrime,
+    U256,
+    "30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47"
+);
+impl_modulus!(
+    CurveOrder,
+    U256,
+    "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001"
+);
+
+/// An element of the base field.
+type Fp = Residue<FieldPrime, { U256::LIMBS }>;
+
+const fn fp(hex: &str) -> Fp {
+    Fp::new(&U256::from_be_hex(hex))
+}
+
+/// An element of the quadratic extension `Fp[i] / (i² + 1)` the G2 twist is defined
+/// over.
+#[derive(Clone, Copy, PartialEq)]
+struct Fp2 {
+    re: Fp,
+    im: Fp,
+}
+
+impl Add for Fp2 {
+    type Output = Fp2;
+
+    fn add(self, other: Fp2) -> Fp2 {
+        Fp2 { re: self.re + other.re, im: self.im + other.im }
+    }
+}
+
+impl Sub for Fp2 {
+    type Output = Fp2;
+
+    fn sub(self, other: Fp2) -> Fp2 {
+        Fp2 { re: self.re - other.re, im: self.im - other.im }
+    }
+}
+
+impl Mul for Fp2 {
+    type Output = Fp2;
+
+    fn mul(self, other: Fp2) -> Fp2 {
+        Fp2 {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+}
+
+impl ConditionallySelectable for Fp2 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Fp2 {
+            re: Fp::conditional_select(&a.re, &b.re, choice),
+            im: Fp::conditional_select(&a.im, &b.im, choice),
+        }
+    }
+}
+
+impl ConstantTimeEq for Fp2 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.re.ct_eq(&other.re) & self.im.ct_eq(&other.im)
+    }
+}
+
+impl FieldElement for Fp2 {
+    const ZERO: Self = Fp2 { re: Fp::ZERO, im: Fp::ZERO };
+    const ONE: Self = Fp2 { re: Fp::ONE, im: Fp::ZERO };
+
+    fn square(&self) -> Self {
+        *self * *self
+    }
+
+    fn invert(&self) -> (Self, Choice) {
+        // 1 / (a + bi) = (a - bi) / (a² + b²)
+        let (norm_inv, invertible) = (self.re.square() + self.im.square()).invert();
+        (Fp2 { re: self.re * norm_inv, im: self.im.neg() * norm_inv }, invertible.into())
+    }
+}
+
+/// The curve `y² = x³ + 3` that signatures and messages live on.
+#[derive(Clone, Copy)]
+struct G1;
+
+impl Curve for G1 {
+    type Element = Fp;
+    const A: Fp = Fp::ZERO;
+    const B: Fp = Fp::new(&U256::from_u8(3));
+}
+
+/// The sextic twist `y² = x³ + 3 / (9 + i)` that public keys live on.
+#[derive(Clone, Copy)]
+struct G2;
+
+impl Curve for G2 {
+    type Element = Fp2;
+    const A: Fp2 = Fp2::ZERO;
+    const B: Fp2 = Fp2 {
+        re: fp("2b149d40ceb8aaae81be18991be06ac3b5b4c5e559dbefa33267e6dc24a138e5"),
+        im: fp("009713b03af0fed4cd2cafadeed8fdf4a74fa084e52d1852e4a2bd0685c315d2"),
+    };
+}
+
+type G1Point = curve::Point<G1>;
+type G2Point = curve::Point<G2>;
+
+fn g2_generator() -> G2Point {
+    let x = Fp2 {
+        re: fp("1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed"),
+        im: fp("198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2"),
+    };
+    let y = Fp2 {
+        re: fp("12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa"),
+        im: fp("090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b"),
+    };
+    G2Point::from_affine_elements(x, y).expect("generator is on the twist")
+}
+
+/// A square root of -3, and `(z0 - 1) / 2`, as `BLS.sol` names them.
+const Z0: Fp = fp("0000000000000000b3c4d79d41a91759a9e4c7e359b6b89eaec68e62effffffd");
+const Z1: Fp = fp("000000000000000059e26bcea0d48bacd4f263f1acdb5c4f5763473177fffffe");
+
+/// Returns `x^((p + 1) / 4)`, and whether it's a square root of `x`, which it is
+/// whenever there is one since `p ≡ 3 (mod 4)`.
+fn sqrt(x: &Fp) -> (Fp, bool) {
+    let root = x.pow(&FieldPrime::MODULUS.wrapping_add(&U256::ONE).shr_vartime(2));
+    (root, root.square() == *x)
+}
+
+/// Expands `message` to 96 bytes with `expand_message_xmd` over SHA-256, using the
+/// 32-byte domain as the tag, as `BLS.sol`'s `expandMsgTo96` does.
+fn expand_message(domain: &[u8; 32], message: &[u8]) -> [u8; 96] {
+    let tail = |hasher: Sha256| hasher.chain_update(domain).chain_update([32]).finalize();
+    let b0 = Sha256::new().chain_update([0; 64]).chain_update(message);
+    let b0 = tail(b0.chain_update([0, 96, 0]));
+    let mut out = [0u8; 96];
+    let mut previous = [0u8; 32];
+    for i in 0..3 {
+        let mut input: [u8; 32] = b0.into();
+        input.iter_mut().zip(previous).for_each(|(b, p)| *b ^= p);
+        previous = tail(Sha256::new().chain_update(input).chain_update([i as u8 + 1])).into();
+        out[32 * i..32 * (i + 1)].copy_from_slice(&previous);
+    }
+    out
+}
+
+/// Reduces 48 big-endian bytes modulo the field prime.
+fn field_element(bytes: &[u8]) -> Fp {
+    let mut wide = [0u8; 64];
+    wide[16..].copy_from_slice(bytes);
+    let modulus = NonZero::new(FieldPrime::MODULUS.resize::<{ U512::LIMBS }>()).unwrap();
+    let reduced = U512::from_be_slice(&wide).rem(&modulus);
+    Fp::new(&reduced.resize())
+}
+
+/// Maps a field element to G1 with `BLS.sol`'s Fouque-Tibouchi `mapToPoint`.
+fn map_to_point(u: Fp) -> G1Point {
+    let (_, decision) = sqrt(&u);
+    let a0 = u.square() + Fp::new(&U256::from_u8(4));
+    let a1 = u * Z0;
+    // `BLS.sol` inverts by exponentiation, which takes zero to zero
+    let a2 = (a1 * a0).pow(&FieldPrime::MODULUS.wrapping_sub(&U256::from_u8(2)));
+    let x1 = Z1 - u * a1.square() * a2;
+    let x2 = (x1 + Fp::ONE).neg();
+    let x3 = a0.square().square() * a2.square() + Fp::ONE;
+    for x in [x1, x2, x3] {
+        let (y, found) = sqrt(&(x.square() * x + G1::B));
+        if found {
+            let y = if decision { y } else { y.neg() };
+            return G1Point::from_affine_elements(x, y).expect("root is on the curve");
+        }
+    }
+    unreachable!("one of the three candidates is always on the curve")
+}
+
+/// Hashes a message to G1 as `BLS.sol`'s `hashToPoint` does.
+fn hash_to_point(domain: &[u8; 32], message: &[u8]) -> G1Point {
+    let expanded = expand_message(domain, message);
+    let p0 = map_to_point(field_element(&expanded[..48]));
+    let p1 = map_to_point(field_element(&expanded[48..]));
+    p0.add(&p1)
+}
+
+fn domain(bytes: &[u8]) -> PyResult<[u8; 32]> {
+    bytes.try_into().map_err(|_| {
+        PyErr::new::<errors::FerriteError, _>(format!(
+            "BLS domain must be 32 bytes, got {}",
+            bytes.len()
+        ))
+    })
+}
+
+/// Parses a BLS secret key, which must be non-zero and below the group order.
+fn secret_key(bytes: &[u8]) -> PyResult<U256> {
+    if bytes.len() != 32 {
+        return Err(PyErr::new::<errors::InvalidKeyError, _>(format!(
+            "BLS secret key must be 32 bytes, got {}",
+            bytes.len()
+        )));
+    }
+    let key = U256::from_be_slice(bytes);
+    if key == U256::ZERO || key >= CurveOrder::MODULUS {
+        return Err(PyErr::new::<errors::InvalidKeyError, _>(
+            "BLS secret key must be non-zero and below the group order",
+        ));
+    }
+    Ok(key)
+}
+
+/// Parses a 64-byte G1 signature, which must be on the curve.
+fn signature(bytes: &[u8]) -> PyResult<G1Point> {
+    if bytes.len() != 64 {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "BLS signature must be 64 bytes, got {}",
+            bytes.len()
+        )));
+    }
+    let x = U256::from_be_slice(&bytes[..32]);
+    let y = U256::from_be_slice(&bytes[32..]);
+    G1Point::from_affine(&x, &y).ok_or_else(|| {
+        PyErr::new::<errors::FerriteError, _>("BLS signature is not a point on the curve")
+    })
+}
+
+/// Encodes a G1 point as `x || y`, with the point at infinity as zeros as in `BLS.sol`.
+fn g1_bytes(point: &G1Point) -> Vec<u8> {
+    let (x, y) = point.affine().unwrap_or((U256::ZERO, U256::ZERO));
+    [x.to_be_bytes(), y.to_be_bytes()].concat()
+}
+
+/// Computes the hash the BLS aggregator has an account sign for a user operation:
+/// `keccak256(abi.encode(opHash, keccak256(abi.encode(publicKey)), aggregator,
+/// chainId))`, where `opHash` hashes the operation's fields alone.
+fn user_op_hash(
+    payload: &str,
+    aggregator: &str,
+    chain_id: u64,
+    public_key: &[u8],
+    version: Option<&str>,
+) -> PyResult<H256> {
+    if public_key.len() != 128 {
+        return Err(PyErr::new::<errors::InvalidKeyError, _>(format!(
+            "BLS public key must be 128 bytes, got {}",
+            public_key.len()
+        )));
+    }
+    let aggregator = address::parse(aggregator)?;
+    let op_hash = userop::user_op_struct_hash(payload, version)?;
+    Ok(H256(keccak::keccak256(&encode(&[
+        Token::FixedBytes(op_hash.as_bytes().to_vec()),
+        Token::FixedBytes(keccak::keccak256(public_key).to_vec()),
+        Token::Address(aggregator),
+        Token::Uint(EthU256::from(chain_id)),
+    ]))))
+}
+
+/// Computes the public key of a BLS secret key.
+///
+/// # Arguments
+/// * `secret_key` - The 32-byte big-endian secret key.
+///
+/// # Returns
+/// The 128-byte G2 public key, as the aggregator's `uint256[4]`.
+#[pyfunction]
+pub fn bls_public_key(py: Python, secret_key: &[u8]) -> PyResult<PyObject> {
+    let mut key = self::secret_key(secret_key)?;
+    let point = py.allow_threads(|| g2_generator().mul(&key, 256).affine_elements());
+    key.zeroize();
+    let (x, y) = point.expect("secret key is below the group order");
+    let public: Vec<u8> = [x.im, x.re, y.im, y.re]
+        .iter()
+        .flat_map(|c| c.retrieve().to_be_bytes())
+        .collect();
+    Ok(PyBytes::new(py, &public).into_any().unbind())
+}
+
+/// Hashes a message to a G1 point, as `BLS.sol`'s `hashToPoint` does.
+///
+/// # Arguments
+/// * `domain` - The 32-byte domain separating this use of BLS from others.
+/// * `message` - The message bytes.
+///
+/// # Returns
+/// The 64-byte point, its x and y coordinates.
+#[pyfunction]
+pub fn bls_hash_to_point(py: Python, domain: &[u8], message: &[u8]) -> PyResult<PyObject> {
+    let domain = self::domain(domain)?;
+    let point = py.allow_threads(|| hash_to_point(&domain, message));
+    Ok(PyBytes::new(py, &g1_bytes(&point)).into_any().unbind())
+}
+
+/// Signs a message with a BLS secret key.
+///
+/// # Arguments
+/// * `domain` - The 32-byte domain the message is hashed to the curve with.
+/// * `message` - The message bytes.
+/// * `secret_key` - The 32-byte big-endian secret key.
+///
+/// # Returns
+/// The 64-byte G1 signature.
+#[pyfunction]
+pub fn bls_sign(
+    py: Python,
+    domain: &[u8],
+    message: &[u8],
+    secret_key: &[u8],
+) -> PyResult<PyObject> {
+    let domain = self::domain(domain)?;
+    let mut key = self::secret_key(secret_key)?;
+    let point = py.allow_threads(|| hash_to_point(&domain, message).mul(&key, 256));
+    key.zeroize();
+    Ok(PyBytes::new(py, &g1_bytes(&point)).into_any().unbind())
+}
+
+/// Aggregates BLS signatures by summing them.
+///
+/// # Arguments
+/// * `signatures` - The 64-byte G1 signatures.
+///
+/// # Returns
+/// The 64-byte aggregate signature.
+#[pyfunction]
+pub fn bls_aggregate(py: Python, signatures: Vec<Vec<u8>>) -> PyResult<PyObject> {
+    let points = signatures.iter().map(|s| signature(s)).collect::<PyResult<Vec<_>>>()?;
+    let sum = points.iter().fold(G1Point::INFINITY, |sum, point| sum.add(point));
+    Ok(PyBytes::new(py, &g1_bytes(&sum)).into_any().unbind())
+}
+
+/// Computes the hash the reference BLS signature aggregator has an account sign for
+/// a user operation.
+///
+/// # Arguments
+/// * `payload` - JSON string of the user operation, with camelCase field names.
+/// * `aggregator` - Address of the aggregator contract.
+/// * `chain_id` - Chain the aggregator is deployed on.
+/// * `public_key` - The account's 128-byte BLS public key.
+/// * `version` - EntryPoint version, `"0.6"` or `"0.7"`. Detected from the fields only
+///   v0.7 has, if omitted.
+///
+/// # Returns
+/// The 32-byte hash, which is then hashed to the curve under the aggregator's domain.
+#[pyfunction]
+#[pyo3(signature = (payload, aggregator, chain_id, public_key, *, version = None))]
+pub fn bls_user_operation_hash(
+    py: Python,
+    payload: &str,
+    aggregator: &str,
+    chain_id: u64,
+    public_key: &[u8],
+    version: Option<&str>,
+) -> PyResult<PyObject> {
+    let hash =
+        py.allow_threads(|| user_op_hash(payload, aggregator, chain_id, public_key, version))?;
+    Ok(PyBytes::new(py, hash.as_bytes()).into_any().unbind())
+}
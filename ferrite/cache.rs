@@ -0,0 +1,113 @@
+/*!
+Bounded LRU cache of parsed signing keys.
+
+Parsing a private key derives its public key and address, which costs a full
+scalar multiplication on every call. Wallets are cached under the keccak256
+digest of the raw key, so the key bytes themselves are never stored as a lookup
+value. Evicted wallets are dropped, which zeroizes their secret scalar.
+
+Cached keys outlive the calls that parsed them, so the cache lives in a
+[`LockedBox`], out of swap and core dumps. Lookups compare digests in constant time
+and always scan every slot, so their timing doesn't depend on the key either.
+*/
+
+use std::sync::Mutex;
+
+use ethers_signers::LocalWallet;
+use ferrite_core::signing;
+use pyo3::prelude::*;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use crate::errors;
+use crate::keccak::keccak256;
+use crate::secure::LockedBox;
+
+/// Maximum number of distinct keys kept parsed at any time.
+const CAPACITY: usize = 64;
+
+/// A cached wallet, stamped with the tick of its last use.
+struct Entry {
+    digest: [u8; 32],
+    wallet: LocalWallet,
+    last_used: u64,
+}
+
+/// Fixed slots rather than a `Vec`, so the locked pages never grow or move.
+struct Slots {
+    entries: [Option<Entry>; CAPACITY],
+    tick: u64,
+}
+
+static CACHE: Mutex<Option<LockedBox<Slots>>> = Mutex::new(None);
+
+/// Returns the slot holding the wallet for `digest`, in time independent of `digest`.
+fn find(slots: &Slots, digest: &[u8; 32]) -> Option<usize> {
+    let mut found = u32::MAX;
+    for (i, entry) in slots.entries.iter().enumerate() {
+        let digest_eq = match entry {
+            Some(entry) => entry.digest.ct_eq(digest),
+            None => Choice::from(0),
+        };
+        found.conditional_assign(&(i as u32), digest_eq);
+    }
+    (found != u32::MAX).then_some(found as usize)
+}
+
+/// Runs `f` on the cache, allocating it on first use.
+fn with_cache<R>(f: impl FnOnce(&mut LockedBox<Slots>) -> R) -> R {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    f(cache.get_or_insert_with(|| {
+        LockedBox::new(Slots { entries: [const { None }; CAPACITY], tick: 0 })
+    }))
+}
+
+/// Returns the wallet for `private_key`, parsing it only on a cache miss.
+pub fn wallet_from_key(private_key: &[u8]) -> PyResult<LocalWallet> {
+    let digest = keccak256(private_key);
+
+    let cached = with_cache(|slots| {
+        slots.tick += 1;
+        let tick = slots.tick;
+        let slot = find(slots, &digest)?;
+        let entry = slots.entries[slot].as_mut()?;
+        entry.last_used = tick;
+        Some(entry.wallet.clone())
+    });
+    if let Some(wallet) = cached {
+        return Ok(wallet);
+    }
+
+    let wallet = signing::wallet(private_key).map_err(errors::from_core)?;
+
+    with_cache(|slots| {
+        if find(slots, &digest).is_some() {
+            return;
+        }
+
+        // Fill a free slot, or evict the least recently used wallet
+        let slot = slots.entries.iter().position(Option::is_none).unwrap_or_else(|| {
+            (0..CAPACITY)
+                .min_by_key(|&i| slots.entries[i].as_ref().map_or(0, |entry| entry.last_used))
+                .unwrap_or(0)
+        });
+        slots.tick += 1;
+        slots.entries[slot] = Some(Entry { digest, wallet: wallet.clone(), last_used: slots.tick });
+    });
+
+    Ok(wallet)
+}
+
+/// Drops every cached wallet, zeroizing the parsed key material.
+#[pyfunction]
+pub fn clear_key_cache() {
+    with_cache(|slots| slots.entries.iter_mut().for_each(|entry| *entry = None));
+}
+
+/// Returns whether the key cache is locked into RAM.
+///
+/// Locking fails when `RLIMIT_MEMLOCK` is too low, in which case cached keys are still
+/// zeroized on eviction but could be swapped to disk.
+#[pyfunction]
+pub fn key_cache_locked() -> bool {
+    with_cache(|slots| slots.is_locked())
+}
@@ -0,0 +1,375 @@
+/*!
+The `ferrite` command-line signer, for machines without a Python environment.
+
+Transactions are read as JSON, in the same shape `sign_transaction` takes them, from a
+file or stdin, and the signed raw transaction is printed as hex. Keys come from a V3
+keystore, a BIP-39 mnemonic or a raw private key, always read from a file or prompted
+for: secrets passed as arguments would show up in `ps` and the shell history.
+
+Nothing here touches Python, so the binary links without libpython. Transactions are
+parsed and encoded by the same code as the extension module, so the binary signs
+exactly what `sign_transaction` would for the same key.
+*/
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use coins_bip32::prelude::XPriv;
+use ethers_core::types::U256;
+use ethers_signers::{LocalWallet, Signer};
+use hmac::Hmac;
+use k256::ecdsa::SigningKey;
+use sha2::Sha512;
+use zeroize::Zeroizing;
+
+use crate::address::checksum;
+use crate::request::{self, Checks, Fields};
+use crate::signing;
+use crate::{keystore, policy};
+
+const USAGE: &str = "\
+Usage: ferrite sign-transaction [OPTIONS] [TX_FILE]
+       ferrite address [OPTIONS]
+
+Signs the transaction JSON in TX_FILE, or stdin if it is missing or `-`, and prints the
+raw transaction as hex. `address` prints the checksummed address of the key instead.
+
+Key (exactly one):
+      --keystore FILE          V3 keystore, decrypted with --password-file or a prompt
+      --mnemonic-file FILE     BIP-39 mnemonic, derived at --path/--index
+      --private-key-file FILE  Hex private key
+
+Options:
+      --password-file FILE     Keystore password, without its trailing newline
+      --passphrase-file FILE   BIP-39 passphrase, without its trailing newline
+      --path PATH              Derivation path of the mnemonic [default: m/44'/60'/0'/0]
+      --index N                Index of the key under --path [default: 0]
+      --max-fee WEI            Refuse transactions whose fee can exceed WEI
+  -h, --help                   Print this help
+  -V, --version                Print the version
+";
+
+const DEFAULT_PATH: &str = "m/44'/60'/0'/0";
+
+/// Why the command failed: bad arguments exit with 2, anything else with 1.
+enum Error {
+    Usage(String),
+    Failed(String),
+}
+
+impl From<signing::Error> for Error {
+    fn from(error: signing::Error) -> Self {
+        Error::Failed(error.message)
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Failed(message)
+    }
+}
+
+enum Command {
+    SignTransaction { input: Option<String> },
+    Address,
+}
+
+enum KeyFile {
+    Keystore(String),
+    Mnemonic(String),
+    PrivateKey(String),
+}
+
+struct Options {
+    command: Command,
+    key: KeyFile,
+    password_file: Option<String>,
+    passphrase_file: Option<String>,
+    path: String,
+    index: u32,
+    max_fee: Option<U256>,
+}
+
+/// Runs the command line in `std::env::args`, printing errors to stderr.
+pub fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        None | Some("-h" | "--help") => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Some("-V" | "--version") => {
+            println!("ferrite {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Some(_) => parse_args(&args).and_then(|options| run(&options)),
+    };
+
+    match result {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(Error::Usage(message)) => {
+            eprintln!("ferrite: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Error::Failed(message)) => {
+            eprintln!("ferrite: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, Error> {
+    let usage = |message: String| Error::Usage(message);
+    let mut command = None;
+    let mut input = None;
+    let mut keys = Vec::new();
+    let (mut password_file, mut passphrase_file) = (None, None);
+    let (mut path, mut index, mut max_fee) = (None, None, None);
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next().cloned())
+                .ok_or_else(|| usage(format!("{} needs a value", name)))
+        };
+        match name {
+            "-h" | "--help" => return Err(usage("--help must come first".to_string())),
+            "--keystore" => keys.push(KeyFile::Keystore(value()?)),
+            "--mnemonic-file" => keys.push(KeyFile::Mnemonic(value()?)),
+            "--private-key-file" => keys.push(KeyFile::PrivateKey(value()?)),
+            "--password-file" => password_file = Some(value()?),
+            "--passphrase-file" => passphrase_file = Some(value()?),
+            "--path" => path = Some(value()?),
+            "--index" => {
+                let text = value()?;
+                let parsed = text.parse().map_err(|_| usage(format!("Invalid --index: {}", text)));
+                index = Some(parsed?);
+            }
+            "--max-fee" => {
+                let text = value()?;
+                let parsed = U256::from_dec_str(&text)
+                    .map_err(|_| usage(format!("Invalid --max-fee: {}", text)));
+                max_fee = Some(parsed?);
+            }
+            "-" => input = Some(name.to_string()),
+            _ if name.starts_with('-') => return Err(usage(format!("Unknown option: {}", arg))),
+            "sign-transaction" if command.is_none() => command = Some(name),
+            "address" if command.is_none() => command = Some(name),
+            _ if command.is_none() => return Err(usage(format!("Unknown command: {}", arg))),
+            _ if input.is_none() => input = Some(arg.clone()),
+            _ => return Err(usage(format!("Unexpected argument: {}", arg))),
+        }
+    }
+
+    let command = match command {
+        Some("sign-transaction") => Command::SignTransaction { input },
+        Some(_) if input.is_some() => {
+            return Err(usage("address takes no TX_FILE".to_string()));
+        }
+        Some(_) => Command::Address,
+        None => return Err(usage("Missing command".to_string())),
+    };
+    if keys.len() != 1 {
+        return Err(usage(
+            "Give exactly one of --keystore, --mnemonic-file and --private-key-file".to_string(),
+        ));
+    }
+    let key = keys.remove(0);
+    let mnemonic = matches!(key, KeyFile::Mnemonic(_));
+    if !mnemonic && (path.is_some() || index.is_some() || passphrase_file.is_some()) {
+        return Err(usage(
+            "--path, --index and --passphrase-file only apply to --mnemonic-file".to_string(),
+        ));
+    }
+    if password_file.is_some() && !matches!(key, KeyFile::Keystore(_)) {
+        return Err(usage("--password-file only applies to --keystore".to_string()));
+    }
+
+    Ok(Options {
+        command,
+        key,
+        password_file,
+        passphrase_file,
+        path: path.unwrap_or_else(|| DEFAULT_PATH.to_string()),
+        index: index.unwrap_or(0),
+        max_fee,
+    })
+}
+
+fn run(options: &Options) -> Result<String, Error> {
+    let wallet = wallet(options)?;
+    match &options.command {
+        Command::Address => Ok(checksum(&wallet.address(), None)),
+        Command::SignTransaction { input } => {
+            let payload = match input.as_deref() {
+                None | Some("-") => {
+                    let mut payload = String::new();
+                    io::stdin()
+                        .read_to_string(&mut payload)
+                        .map_err(|e| format!("Failed to read stdin: {}", e))?;
+                    payload
+                }
+                Some(path) => {
+                    fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?
+                }
+            };
+            let raw = sign_transaction(&payload, &wallet, options.max_fee)?;
+            Ok(format!("0x{}", hex::encode(raw)))
+        }
+    }
+}
+
+/// Signs a transaction JSON payload, returning the raw signed transaction.
+fn sign_transaction(
+    payload: &str,
+    wallet: &LocalWallet,
+    max_fee: Option<U256>,
+) -> Result<Vec<u8>, Error> {
+    let tx = signing::parse_transaction(payload, Checks::default())?;
+    // A `from` that isn't the key's address means the wrong key was picked
+    let fields: Fields = serde_json::from_str(payload).map_err(|e| e.to_string())?;
+    if let Some(from) = request::address(&fields, "from", false)? {
+        if from != wallet.address() {
+            return Err(Error::Failed(format!(
+                "Invalid Transaction: `from` {} is not the key's address {}",
+                checksum(&from, None),
+                checksum(&wallet.address(), None)
+            )));
+        }
+    }
+
+    let over = max_fee.map(|max_fee| policy::fee_over(&tx, payload, max_fee)).transpose()?;
+    if let Some(message) = over.flatten() {
+        let message = format!("Invalid Transaction: {} set with --max-fee", message);
+        return Err(Error::Failed(message));
+    }
+
+    Ok(signing::sign_parsed_transaction(tx, wallet)?.raw_transaction)
+}
+
+/// Loads the signing key the options name.
+fn wallet(options: &Options) -> Result<LocalWallet, Error> {
+    let private_key: Zeroizing<Vec<u8>> = match &options.key {
+        KeyFile::PrivateKey(path) => {
+            let text = read_secret(path)?;
+            let digits = text.trim();
+            let digits = digits.strip_prefix("0x").unwrap_or(digits);
+            let key = hex::decode(digits).map_err(|_| format!("Invalid private key in {}", path))?;
+            Zeroizing::new(key)
+        }
+        KeyFile::Keystore(path) => {
+            let keystore = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let password = match &options.password_file {
+                Some(password_file) => without_newline(read_secret(password_file)?),
+                None => prompt(&format!("Password for {}: ", path))?,
+            };
+            keystore::decrypt(&keystore, password.as_bytes())
+                .map_err(|e| format!("Failed to decrypt {}: {}", path, e))?
+        }
+        KeyFile::Mnemonic(path) => {
+            let mnemonic = read_secret(path)?;
+            let passphrase = match &options.passphrase_file {
+                Some(passphrase_file) => without_newline(read_secret(passphrase_file)?),
+                None => Zeroizing::new(String::new()),
+            };
+            mnemonic_key(&mnemonic, &passphrase, &options.path, options.index)?
+        }
+    };
+
+    // `SigningKey::from_slice` takes shorter slices as big-endian integers, which would
+    // quietly accept a truncated key
+    if private_key.len() != 32 {
+        return Err(Error::Failed("Private key must be 32 bytes".to_string()));
+    }
+    Ok(signing::wallet(&private_key)?)
+}
+
+/// Derives the private key at `path/index` from a BIP-39 mnemonic.
+///
+/// The seed is derived as `addresses_from_mnemonic` derives it. Without a Unicode
+/// library at hand, mnemonics and passphrases are taken as ASCII only, for which NFKD
+/// normalization changes nothing.
+fn mnemonic_key(
+    mnemonic: &str,
+    passphrase: &str,
+    path: &str,
+    index: u32,
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    if !mnemonic.is_ascii() || !passphrase.is_ascii() {
+        return Err("Only ASCII mnemonics and passphrases are supported".to_string());
+    }
+    let words = Zeroizing::new(mnemonic.split_whitespace().collect::<Vec<_>>().join(" "));
+    let salt = Zeroizing::new(format!("mnemonic{}", passphrase));
+    let mut seed = Zeroizing::new([0u8; 64]);
+    pbkdf2::pbkdf2::<Hmac<Sha512>>(words.as_bytes(), salt.as_bytes(), 2048, &mut seed[..]);
+
+    let child = XPriv::root_from_seed(&seed[..], None)
+        .and_then(|root| root.derive_path(format!("{}/{}", path, index).as_str()))
+        .map_err(|e| format!("Invalid derivation path {}: {}", path, e))?;
+    let key: &SigningKey = child.as_ref();
+    Ok(Zeroizing::new(key.to_bytes().to_vec()))
+}
+
+fn read_secret(path: &str) -> Result<Zeroizing<String>, String> {
+    let mut text = Zeroizing::new(String::new());
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    Ok(text)
+}
+
+/// Strips one trailing newline, which editors and `echo` add, and nothing else: other
+/// whitespace may be part of the password.
+fn without_newline(text: Zeroizing<String>) -> Zeroizing<String> {
+    let line = text.strip_suffix('\n').unwrap_or(&text);
+    Zeroizing::new(line.strip_suffix('\r').unwrap_or(line).to_string())
+}
+
+/// Prompts for a secret on the terminal, with echo turned off.
+#[cfg(unix)]
+fn prompt(label: &str) -> Result<Zeroizing<String>, String> {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::io::AsRawFd;
+
+    let failed = |e: io::Error| format!("Failed to prompt for a password: {}", e);
+    let mut tty = File::options().read(true).write(true).open("/dev/tty").map_err(failed)?;
+    write!(tty, "{}", label).and_then(|_| tty.flush()).map_err(failed)?;
+
+    let fd = tty.as_raw_fd();
+    // SAFETY: `termios` is plain old data, filled in by `tcgetattr` before it's read
+    let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+    // SAFETY: `fd` is open for the lifetime of `tty`, and `saved` is a valid termios
+    if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+        return Err(failed(io::Error::last_os_error()));
+    }
+    let mut silent = saved;
+    silent.c_lflag &= !libc::ECHO;
+    // SAFETY: as above
+    if unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &silent) } != 0 {
+        return Err(failed(io::Error::last_os_error()));
+    }
+
+    let mut line = Zeroizing::new(String::new());
+    let read = BufReader::new(&tty).read_line(&mut line);
+    // SAFETY: as above. Echo is restored even if reading failed
+    unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &saved) };
+    let _ = writeln!(tty);
+    read.map_err(failed)?;
+    Ok(without_newline(line))
+}
+
+#[cfg(not(unix))]
+fn prompt(_label: &str) -> Result<Zeroizing<String>, String> {
+    Err("Prompting for a password is only supported on Unix; use --password-file".to_string())
+}
@@ -0,0 +1,581 @@
+"""
+Configuring a signer from a TOML file.
+
+One file sets up everything a long-running signer needs at startup: where its keys
+come from, the signing policy and other limits guarding them, the chains it signs for
+and how it is served::
+
+    [[keys]]
+    keystore_dir = "keys"
+    password_file = "/run/secrets/keystore-password"
+
+    [[keys]]
+    signer = "aws_kms"
+    options = { key_id = "alias/hot-wallet" }
+
+    [policy]
+    lock = true
+    max_transaction_fee = 10_000_000_000_000_000
+
+    [policy.limits]
+    maxValue = 1_000_000_000_000_000_000
+    chainIds = ["mainnet"]
+
+    [[policy.rate_limits]]
+    address = "0x..."
+    per_minute = 60
+
+    [chains.mainnet]
+    chain_id = 1
+    rpc_url = "http://127.0.0.1:8545"
+
+    [server]
+    transport = "http"
+    port = 8550
+    metrics = "127.0.0.1:9464"
+    log_level = "INFO"
+
+The whole file is validated before anything is applied, and a ``ConfigError`` names the
+offending key by its path in the file, such as ``keys[1].password_file``. Relative
+paths are taken from the directory of the file. ``chainIds`` in the policy, and the
+``chainId`` of its ``domains``, may name chains from ``[chains]`` as well as give their
+ids. Wei amounts beyond TOML's 64-bit integers can be given as decimal or hex strings.
+
+Run a server from a file with ``python -m ferrite.server --config ferrite.toml``.
+TOML is read with ``tomllib``, or with the ``tomli`` package before Python 3.11:
+``pip install ferrite[config]``.
+"""
+
+import glob
+import json
+import os
+import re
+from typing import Any, Callable, Dict, List, NamedTuple, Optional
+
+from _ferrite import FerriteError, set_log_level, set_rate_limit  # type: ignore
+
+from .account import decrypt_keystores, keychain_password, set_max_transaction_fee
+from .account import set_signing_policy, set_typed_data_guard
+from .remote import registered_signers, signer_account
+
+_TRANSPORTS = ("http", "ipc", "grpc")
+
+_LOG_LEVELS = ("TRACE", "DEBUG", "INFO", "WARNING", "ERROR", "CRITICAL")
+
+_PASSWORD_SOURCES = ("password_file", "password_env", "keychain")
+
+_LIMITS = (
+    "maxValue",
+    "maxFee",
+    "chainIds",
+    "allowTo",
+    "denyTo",
+    "selectors",
+    "domains",
+    "allowRawHashes",
+)
+
+_DOMAIN_FIELDS = ("name", "chainId", "verifyingContract")
+
+_ADDRESS = re.compile(r"0x[0-9a-fA-F]{40}")
+
+_SELECTOR = re.compile(r"(0x)?[0-9a-fA-F]{8}")
+
+_INTEGER = re.compile(r"0x[0-9a-fA-F]+|[0-9]+")
+
+
+class ConfigError(FerriteError):
+    """
+    A configuration file that can't be used, raised before any of it is applied.
+
+    ``key`` is the path of the offending key within the file, such as
+    ``keys[1].password_file``, or empty for problems with the file as a whole.
+    """
+
+    def __init__(self, file: str, key: str, message: str) -> None:
+        super().__init__(f"{file}: {key}: {message}" if key else f"{file}: {message}")
+        self.file = file
+        self.key = key
+
+
+class KeySource(NamedTuple):
+    """Where one or more keys come from: keystore files, or a signer backend."""
+
+    keystores: List[str]
+    password_file: Optional[str] = None
+    password_env: Optional[str] = None
+    keychain: Optional[Dict[str, str]] = None
+    signer: Optional[str] = None
+    options: Dict[str, Any] = {}
+
+
+class Chain(NamedTuple):
+    """A chain registry entry."""
+
+    name: str
+    chain_id: int
+    rpc_url: Optional[str] = None
+
+
+class ServerSettings(NamedTuple):
+    """How the signer is served."""
+
+    transport: str = "http"
+    host: str = "127.0.0.1"
+    port: int = 8550
+    path: Optional[str] = None
+    allowed_uids: Optional[List[int]] = None
+    allowed_gids: List[int] = []
+    allowed_origins: List[str] = []
+    address: str = "127.0.0.1:50051"
+    key_management: bool = False
+    metrics: Optional[str] = None
+    log_level: Optional[str] = None
+
+
+class _Reader:
+    """Checks values of a parsed file, raising errors that name where they are."""
+
+    def __init__(self, file: str) -> None:
+        self.file = file
+        self.directory = os.path.dirname(os.path.abspath(file))
+
+    def error(self, key: str, message: str) -> ConfigError:
+        return ConfigError(self.file, key, message)
+
+    def table(self, key: str, value: Any, allowed: Optional[tuple] = None) -> Dict:
+        if not isinstance(value, dict):
+            raise self.error(key, f"expected a table, got {_kind(value)}")
+        for name in value:
+            if allowed is not None and name not in allowed:
+                raise self.error(_join(key, name), "unknown key")
+        return value
+
+    def get(self, table: Dict, key: str, name: str, kind: type, default: Any = None):
+        value = table.get(name, default)
+        if value is None or value is default:
+            return value
+        # bool is an int in Python, but not in TOML
+        if not isinstance(value, kind) or (kind is int and isinstance(value, bool)):
+            raise self.error(
+                _join(key, name), f"expected {_KINDS[kind]}, got {_kind(value)}"
+            )
+        return value
+
+    def path(self, table: Dict, key: str, name: str) -> Optional[str]:
+        value = self.get(table, key, name, str)
+        return None if value is None else os.path.join(self.directory, value)
+
+    def array(self, key: str, value: Any) -> List:
+        if not isinstance(value, list):
+            raise self.error(key, f"expected an array, got {_kind(value)}")
+        return value
+
+    def integer(self, key: str, value: Any, bits: int) -> int:
+        """An unsigned integer, which may be given as a decimal or hex string too."""
+        if isinstance(value, str):
+            if not _INTEGER.fullmatch(value):
+                raise self.error(key, "expected a decimal or 0x-prefixed hex integer")
+            value = int(value, 16 if value.startswith("0x") else 10)
+        elif not isinstance(value, int) or isinstance(value, bool):
+            raise self.error(key, f"expected an integer, got {_kind(value)}")
+        if not 0 <= value < 1 << bits:
+            raise self.error(key, f"must be between 0 and 2^{bits} - 1")
+        return value
+
+    def address(self, key: str, value: Any) -> str:
+        if not isinstance(value, str) or not _ADDRESS.fullmatch(value):
+            raise self.error(key, "expected a 0x-prefixed address of 40 hex digits")
+        return value
+
+
+_KINDS = {
+    bool: "a boolean",
+    int: "an integer",
+    float: "a float",
+    str: "a string",
+    list: "an array",
+    dict: "a table",
+}
+
+
+def _kind(value: Any) -> str:
+    return _KINDS.get(type(value), "a date or time")
+
+
+def _join(key: str, name: Any) -> str:
+    if isinstance(name, int):
+        return f"{key}[{name}]"
+    return f"{key}.{name}" if key else name
+
+
+def _toml() -> Any:
+    try:
+        import tomllib  # type: ignore
+    except ImportError:
+        try:
+            import tomli as tomllib  # type: ignore
+        except ImportError as e:
+            raise ImportError(
+                "Reading TOML before Python 3.11 needs tomli: "
+                "pip install ferrite[config]"
+            ) from e
+    return tomllib
+
+
+class Config:
+    """
+    A parsed and validated configuration file; see ``load_config``.
+
+    Attributes:
+        keys: The key sources, in file order.
+        policy: The signing policy limits, as ``set_signing_policy`` takes them, or
+            ``None``.
+        lock_policy: Whether the policy is locked for the rest of the process.
+        max_transaction_fee: The fee cap in wei, or ``None``.
+        rate_limits: Keyword arguments of a ``set_rate_limit`` call per entry.
+        typed_data_guard: Keyword arguments of ``set_typed_data_guard``, or ``None``.
+        chains: The chain registry, by name.
+        server: The server settings.
+    """
+
+    def __init__(self, data: Dict[str, Any], file: str) -> None:
+        reader = _Reader(file)
+        reader.table("", data, ("keys", "policy", "chains", "server"))
+        self.file = file
+        self.chains = self._chains(reader, data.get("chains", {}))
+        self.keys = self._keys(reader, data.get("keys", []))
+        self._policy(reader, data.get("policy", {}))
+        self.server = self._server(reader, data.get("server", {}))
+
+    @staticmethod
+    def _chains(reader: _Reader, chains: Any) -> Dict[str, Chain]:
+        registry = {}
+        for name, entry in reader.table("chains", chains).items():
+            key = _join("chains", name)
+            reader.table(key, entry, ("chain_id", "rpc_url"))
+            chain_id = reader.get(entry, key, "chain_id", int)
+            if chain_id is None:
+                raise reader.error(_join(key, "chain_id"), "missing")
+            if chain_id <= 0:
+                raise reader.error(_join(key, "chain_id"), "must be positive")
+            rpc_url = reader.get(entry, key, "rpc_url", str)
+            if rpc_url is not None and not rpc_url.startswith(("http://", "https://")):
+                raise reader.error(_join(key, "rpc_url"), "expected an http(s) URL")
+            registry[name] = Chain(name, chain_id, rpc_url)
+        return registry
+
+    @staticmethod
+    def _keys(reader: _Reader, keys: Any) -> List[KeySource]:
+        if not isinstance(keys, list):
+            raise reader.error(
+                "keys", f"expected an array of tables, got {_kind(keys)}"
+            )
+        sources = []
+        fields = ("keystore", "keystore_dir", "signer", "options") + _PASSWORD_SOURCES
+        for i, entry in enumerate(keys):
+            key = _join("keys", i)
+            reader.table(key, entry, fields)
+            kinds = [n for n in ("keystore", "keystore_dir", "signer") if n in entry]
+            if len(kinds) != 1:
+                raise reader.error(
+                    key, "set exactly one of keystore, keystore_dir and signer"
+                )
+
+            if "signer" in entry:
+                name = reader.get(entry, key, "signer", str)
+                if name not in registered_signers():
+                    known = ", ".join(registered_signers())
+                    raise reader.error(
+                        _join(key, "signer"),
+                        f"unknown signer {name!r} (known: {known})",
+                    )
+                for extra in _PASSWORD_SOURCES:
+                    if extra in entry:
+                        raise reader.error(_join(key, extra), "only for keystores")
+                options = reader.table(_join(key, "options"), entry.get("options", {}))
+                sources.append(KeySource([], signer=name, options=dict(options)))
+                continue
+
+            if "options" in entry:
+                raise reader.error(_join(key, "options"), "only for signers")
+            if "keystore" in entry:
+                keystores = [reader.path(entry, key, "keystore")]
+                if not os.path.isfile(keystores[0]):
+                    raise reader.error(_join(key, "keystore"), "no such file")
+            else:
+                directory = reader.path(entry, key, "keystore_dir")
+                if not os.path.isdir(directory):
+                    raise reader.error(_join(key, "keystore_dir"), "no such directory")
+                keystores = sorted(glob.glob(os.path.join(directory, "*.json")))
+                if not keystores:
+                    raise reader.error(
+                        _join(key, "keystore_dir"), "holds no keystore .json files"
+                    )
+            if sum(1 for name in _PASSWORD_SOURCES if name in entry) > 1:
+                raise reader.error(
+                    key, "set at most one of password_file, password_env and keychain"
+                )
+            password_file = reader.path(entry, key, "password_file")
+            if password_file is not None and not os.path.isfile(password_file):
+                raise reader.error(_join(key, "password_file"), "no such file")
+            keychain = entry.get("keychain")
+            if keychain is not None:
+                reader.table(_join(key, "keychain"), keychain, ("service", "username"))
+                for name in ("service", "username"):
+                    if not isinstance(keychain.get(name), str):
+                        raise reader.error(
+                            _join(_join(key, "keychain"), name), "expected a string"
+                        )
+            sources.append(
+                KeySource(
+                    keystores,
+                    password_file=password_file,
+                    password_env=reader.get(entry, key, "password_env", str),
+                    keychain=keychain,
+                )
+            )
+        return sources
+
+    def _policy(self, reader: _Reader, policy: Any) -> None:
+        fields = ("lock", "max_transaction_fee", "limits", "rate_limits")
+        reader.table("policy", policy, fields + ("typed_data_guard",))
+        self.lock_policy = bool(reader.get(policy, "policy", "lock", bool, False))
+        fee = reader.get(policy, "policy", "max_transaction_fee", int)
+        if fee is not None and fee < 0:
+            raise reader.error("policy.max_transaction_fee", "must not be negative")
+        self.max_transaction_fee = fee
+
+        self.policy = None
+        if "limits" in policy:
+            self.policy = self._limits(reader, policy["limits"])
+
+        self.rate_limits = []
+        rate_limits = policy.get("rate_limits", [])
+        if not isinstance(rate_limits, list):
+            raise reader.error("policy.rate_limits", "expected an array of tables")
+        for i, entry in enumerate(rate_limits):
+            key = _join("policy.rate_limits", i)
+            reader.table(key, entry, ("address", "per_second", "per_minute"))
+            address = reader.get(entry, key, "address", str)
+            if address is None:
+                raise reader.error(_join(key, "address"), "missing")
+            limit = {"address": reader.address(_join(key, "address"), address)}
+            for name in ("per_second", "per_minute"):
+                value = reader.get(entry, key, name, int)
+                if value is not None:
+                    limit[name] = reader.integer(_join(key, name), value, 63)
+            self.rate_limits.append(limit)
+
+        self.typed_data_guard = None
+        if "typed_data_guard" in policy:
+            key = "policy.typed_data_guard"
+            fields = ("mode", "allow", "allow_raw_hashes")
+            guard = reader.table(key, policy["typed_data_guard"], fields)
+            mode = reader.get(guard, key, "mode", str, "warn")
+            if mode not in ("warn", "block", "off"):
+                raise reader.error(
+                    _join(key, "mode"), "expected \"warn\", \"block\" or \"off\""
+                )
+            self.typed_data_guard = {
+                "mode": mode,
+                "allow": reader.get(guard, key, "allow", list),
+                "allow_raw_hashes": reader.get(
+                    guard, key, "allow_raw_hashes", bool, False
+                ),
+            }
+
+    def _limits(self, reader: _Reader, limits: Any) -> Dict[str, Any]:
+        key = "policy.limits"
+        reader.table(key, limits, _LIMITS)
+        checked: Dict[str, Any] = {}
+        for name in ("maxValue", "maxFee"):
+            if name in limits:
+                checked[name] = reader.integer(_join(key, name), limits[name], 256)
+        if "chainIds" in limits:
+            chain_ids = reader.array(_join(key, "chainIds"), limits["chainIds"])
+            checked["chainIds"] = [
+                self._chain_id(reader, _join(_join(key, "chainIds"), i), chain)
+                for i, chain in enumerate(chain_ids)
+            ]
+        for name in ("allowTo", "denyTo"):
+            if name in limits:
+                addresses = reader.array(_join(key, name), limits[name])
+                checked[name] = [
+                    reader.address(_join(_join(key, name), i), address)
+                    for i, address in enumerate(addresses)
+                ]
+        if "selectors" in limits:
+            selectors = reader.array(_join(key, "selectors"), limits["selectors"])
+            for i, selector in enumerate(selectors):
+                if not isinstance(selector, str) or not _SELECTOR.fullmatch(selector):
+                    raise reader.error(
+                        _join(_join(key, "selectors"), i),
+                        "expected 4 bytes as 8 hex digits",
+                    )
+            checked["selectors"] = selectors
+        if "domains" in limits:
+            domains = reader.array(_join(key, "domains"), limits["domains"])
+            checked["domains"] = [
+                self._domain(reader, _join(_join(key, "domains"), i), domain)
+                for i, domain in enumerate(domains)
+            ]
+        if "allowRawHashes" in limits:
+            checked["allowRawHashes"] = reader.get(limits, key, "allowRawHashes", bool)
+        return checked
+
+    def _domain(self, reader: _Reader, key: str, domain: Any) -> Dict[str, Any]:
+        reader.table(key, domain, _DOMAIN_FIELDS)
+        if not domain:
+            raise reader.error(key, "an empty domain would allow every domain")
+        checked = {}
+        if "name" in domain:
+            checked["name"] = reader.get(domain, key, "name", str)
+        if "chainId" in domain:
+            checked["chainId"] = self._chain_id(
+                reader, _join(key, "chainId"), domain["chainId"]
+            )
+        if "verifyingContract" in domain:
+            checked["verifyingContract"] = reader.address(
+                _join(key, "verifyingContract"), domain["verifyingContract"]
+            )
+        return checked
+
+    def _chain_id(self, reader: _Reader, key: str, chain: Any) -> int:
+        if not isinstance(chain, str) or chain.startswith("0x"):
+            return reader.integer(key, chain, 64)
+        if chain not in self.chains:
+            raise reader.error(key, f"unknown chain {chain!r}, not in [chains]")
+        return self.chains[chain].chain_id
+
+    @staticmethod
+    def _server(reader: _Reader, server: Any) -> ServerSettings:
+        reader.table("server", server, ServerSettings._fields)
+        defaults = ServerSettings()
+        settings = {}
+        for name in ServerSettings._fields:
+            default = getattr(defaults, name)
+            kind = type(default) if default is not None else None
+            if name in ("allowed_uids", "allowed_gids", "allowed_origins"):
+                kind = list
+            elif kind is None:
+                kind = str
+            value = reader.get(server, "server", name, kind, default)
+            settings[name] = value
+        if settings["transport"] not in _TRANSPORTS:
+            raise reader.error(
+                "server.transport", f"expected one of {', '.join(_TRANSPORTS)}"
+            )
+        if settings["transport"] == "ipc" and settings["path"] is None:
+            raise reader.error("server.path", "required for the ipc transport")
+        if settings["path"] is not None:
+            settings["path"] = os.path.join(reader.directory, settings["path"])
+        for name in ("allowed_uids", "allowed_gids"):
+            for i, value in enumerate(settings[name] or []):
+                if not isinstance(value, int) or isinstance(value, bool):
+                    raise reader.error(
+                        _join(f"server.{name}", i), "expected an integer"
+                    )
+        for i, origin in enumerate(settings["allowed_origins"]):
+            if not isinstance(origin, str):
+                raise reader.error(
+                    _join("server.allowed_origins", i), "expected a string"
+                )
+        if not 0 <= settings["port"] < 1 << 16:
+            raise reader.error("server.port", "must be between 0 and 65535")
+        metrics = settings["metrics"]
+        if metrics is not None and not metrics.rpartition(":")[2].isdigit():
+            raise reader.error("server.metrics", "expected HOST:PORT")
+        level = settings["log_level"]
+        if level is not None and level.upper() not in _LOG_LEVELS:
+            raise reader.error(
+                "server.log_level", f"expected one of {', '.join(_LOG_LEVELS)}"
+            )
+        return ServerSettings(**settings)
+
+    def _password(self, index: int, source: KeySource, prompt: Any) -> str:
+        key = _join("keys", index)
+        if source.password_file is not None:
+            with open(source.password_file) as password_file:
+                return password_file.read().rstrip("\r\n")
+        if source.password_env is not None:
+            password = os.environ.get(source.password_env)
+            if password is None:
+                raise ConfigError(
+                    self.file,
+                    _join(key, "password_env"),
+                    f"{source.password_env} is not set",
+                )
+            return password
+        if source.keychain is not None:
+            return keychain_password(
+                source.keychain["service"], source.keychain["username"]
+            )
+        if prompt is None:
+            raise ConfigError(self.file, key, "no password source for the keystores")
+        return prompt(source.keystores)
+
+    def accounts(
+        self, prompt: Optional[Callable[[List[str]], str]] = None
+    ) -> List[Any]:
+        """
+        Loads the keys from every key source.
+
+        Args:
+            prompt: Called with the keystore paths of a source that configures no
+                password, to ask for it. Such sources are an error without it.
+
+        Returns:
+            eth-account accounts for keystores and ``RemoteAccount``s for signers, in
+            file order.
+        """
+        from eth_account import Account
+
+        accounts: List[Any] = []
+        for i, source in enumerate(self.keys):
+            if source.signer is not None:
+                accounts.append(signer_account(source.signer, **source.options))
+                continue
+            password = self._password(i, source, prompt)
+            keystores = []
+            for path in source.keystores:
+                with open(path) as keyfile:
+                    keystores.append(json.load(keyfile))
+            keys = decrypt_keystores(keystores, password)
+            accounts.extend(Account.from_key(key) for key in keys)
+        return accounts
+
+    def apply(self) -> None:
+        """Sets the policy, limits and log level the file configures."""
+        # First, so that a policy that can't be set, such as under a locked one, leaves
+        # everything else as it was
+        if self.policy is not None:
+            set_signing_policy(self.policy, lock=self.lock_policy)
+        if self.server.log_level is not None:
+            set_log_level(self.server.log_level.upper())
+        if self.max_transaction_fee is not None:
+            set_max_transaction_fee(self.max_transaction_fee)
+        if self.typed_data_guard is not None:
+            set_typed_data_guard(**self.typed_data_guard)
+        for limit in self.rate_limits:
+            set_rate_limit(**limit)
+
+
+def load_config(path: str) -> Config:
+    """
+    Reads and validates a TOML configuration file, without applying it.
+
+    Call ``apply()`` on the result to set its policy and limits, and ``accounts()`` to
+    load its keys.
+
+    Raises:
+        ConfigError: If the file isn't valid TOML or any setting is invalid, naming
+            the setting.
+    """
+    tomllib = _toml()
+    try:
+        with open(path, "rb") as file:
+            data = tomllib.load(file)
+    except tomllib.TOMLDecodeError as e:
+        raise ConfigError(path, "", f"invalid TOML: {e}") from None
+    return Config(data, path)
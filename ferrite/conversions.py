@@ -0,0 +1,111 @@
+"""
+Conversions between bytes, hex strings, text and ints, as eth-utils makes them.
+
+Each function takes exactly one of a ``primitive``, a ``hexstr`` or a ``text`` argument
+and follows eth-utils' rules for it, so services can drop eth-utils without changing
+their results: hex strings may omit the ``0x`` prefix, and odd-length hex gains a
+leading zero on its way to bytes.
+"""
+
+import binascii
+from typing import Any, Optional, Union
+
+
+def _check_one(primitive: Any, hexstr: Optional[str], text: Optional[str]) -> None:
+    if sum(value is not None for value in (primitive, hexstr, text)) != 1:
+        raise TypeError(
+            "Exactly one of the passed values can be specified. Instead, values were: "
+            f"{(primitive,)!r}, {{'hexstr': {hexstr!r}, 'text': {text!r}}}"
+        )
+
+
+def _strip_0x(hexstr: str) -> str:
+    return hexstr[2:] if hexstr[:2].lower() == "0x" else hexstr
+
+
+def to_hex(
+    primitive: Union[bytes, bytearray, memoryview, int, bool, None] = None,
+    hexstr: Optional[str] = None,
+    text: Optional[str] = None,
+) -> str:
+    """
+    Converts a value to a ``0x``-prefixed hex string.
+
+    Bytes are hex encoded, ints are written as ``hex`` writes them (``"0x0"``,
+    ``"-0x5"``), bools as ``"0x1"`` or ``"0x0"``, text is UTF-8 encoded first and hex
+    strings are lowercased and prefixed. Other strings must be passed as ``hexstr`` or
+    ``text``.
+    """
+    _check_one(primitive, hexstr, text)
+    if hexstr is not None:
+        return "0x" + _strip_0x(hexstr.lower())
+    if text is not None:
+        return "0x" + text.encode("utf-8").hex()
+    if isinstance(primitive, bool):
+        return "0x1" if primitive else "0x0"
+    if isinstance(primitive, (bytes, bytearray, memoryview)):
+        return "0x" + bytes(primitive).hex()
+    if isinstance(primitive, str):
+        raise TypeError(
+            "Unsupported type: The primitive argument must be one of: bytes, "
+            "bytearray, int or boolean."
+        )
+    if isinstance(primitive, int):
+        return hex(primitive)
+    raise TypeError(
+        f"Unsupported type: '{type(primitive)!r}'. "
+        "Must be one of: bool, str, bytes, bytearray or int."
+    )
+
+
+def to_bytes(
+    primitive: Union[bytes, bytearray, memoryview, int, bool, None] = None,
+    hexstr: Optional[str] = None,
+    text: Optional[str] = None,
+) -> bytes:
+    """
+    Converts a value to bytes.
+
+    Ints become their minimal big-endian bytes (``b"\\x00"`` for zero), bools a single
+    byte, text its UTF-8 encoding and hex strings their bytes, with or without ``0x``
+    and with a leading zero added to odd-length hex.
+    """
+    _check_one(primitive, hexstr, text)
+    if isinstance(primitive, bool):
+        return b"\x01" if primitive else b"\x00"
+    if isinstance(primitive, (bytes, bytearray, memoryview)):
+        return bytes(primitive)
+    if isinstance(primitive, int):
+        return to_bytes(hexstr=to_hex(primitive))
+    if hexstr is not None:
+        digits = _strip_0x(hexstr)
+        return binascii.unhexlify("0" * (len(digits) % 2) + digits)
+    if text is not None:
+        return text.encode("utf-8")
+    raise TypeError(
+        "expected a bool, int, byte or bytearray in first arg, or keyword of hexstr "
+        "or text"
+    )
+
+
+def to_int(
+    primitive: Union[bytes, bytearray, int, bool, float, None] = None,
+    hexstr: Optional[str] = None,
+    text: Optional[str] = None,
+) -> int:
+    """
+    Converts a value to an int.
+
+    Bytes are read as a big-endian unsigned integer, hex strings with ``int(hexstr,
+    16)`` and text with ``int(text)``; anything else goes through ``int``.
+    """
+    _check_one(primitive, hexstr, text)
+    if hexstr is not None:
+        return int(hexstr, 16)
+    if text is not None:
+        return int(text)
+    if isinstance(primitive, (bytes, bytearray)):
+        return int.from_bytes(primitive, "big")
+    if isinstance(primitive, str):
+        raise TypeError("Pass in strings with keyword hexstr or text")
+    return int(primitive or 0)
@@ -0,0 +1,195 @@
+"""
+CoW Protocol (GPv2) orders.
+
+An order is an EIP-712 ``Order`` struct signed in the domain of the GPv2 settlement
+contract. The helpers here build it, compute its hash and the 56-byte order UID the
+orderbook API identifies it by, and sign it with the ``eip712`` signing scheme, one
+order at a time or as a batch sharing one key.
+"""
+
+import json
+from typing import Any, Dict, Iterable, List, NamedTuple, Union
+
+from hexbytes import HexBytes
+from _ferrite import addresses_from_keys as rust_addresses_from_keys  # type: ignore
+from _ferrite import hash_typed_data as rust_hash_typed_data  # type: ignore
+from .account import _private_key_bytes, _sign_typed_data_with, sign_typed_data_batch
+
+# The settlement contract has the same address on every chain CoW Protocol runs on
+COW_SETTLEMENT_ADDRESS = "0x9008D19f58AAbD9eD0D60971565AA8510560ab41"
+
+_ZERO_ADDRESS = "0x" + "00" * 20
+
+_ORDER_TYPE = [
+    {"name": "sellToken", "type": "address"},
+    {"name": "buyToken", "type": "address"},
+    {"name": "receiver", "type": "address"},
+    {"name": "sellAmount", "type": "uint256"},
+    {"name": "buyAmount", "type": "uint256"},
+    {"name": "validTo", "type": "uint32"},
+    {"name": "appData", "type": "bytes32"},
+    {"name": "feeAmount", "type": "uint256"},
+    {"name": "kind", "type": "string"},
+    {"name": "partiallyFillable", "type": "bool"},
+    {"name": "sellTokenBalance", "type": "string"},
+    {"name": "buyTokenBalance", "type": "string"},
+]
+
+_DOMAIN_TYPE = [
+    {"name": "name", "type": "string"},
+    {"name": "version", "type": "string"},
+    {"name": "chainId", "type": "uint256"},
+    {"name": "verifyingContract", "type": "address"},
+]
+
+
+class CowOrderSignature(NamedTuple):
+    """An owner's ``eip712`` signature of an order, and the order's UID."""
+
+    owner: str
+    signature: HexBytes
+    uid: HexBytes
+
+
+def _choice(name: str, value: str, allowed: tuple) -> str:
+    if value not in allowed:
+        raise ValueError(f"{name} must be one of {', '.join(allowed)}, got {value!r}")
+    return value
+
+
+def cow_order(
+    chain_id: int,
+    sell_token: str,
+    buy_token: str,
+    sell_amount: int,
+    buy_amount: int,
+    valid_to: int,
+    *,
+    kind: str = "sell",
+    receiver: str = _ZERO_ADDRESS,
+    app_data: Union[bytes, str] = b"\x00" * 32,
+    fee_amount: int = 0,
+    partially_fillable: bool = False,
+    sell_token_balance: str = "erc20",
+    buy_token_balance: str = "erc20",
+    settlement: str = COW_SETTLEMENT_ADDRESS,
+) -> Dict[str, Any]:
+    """
+    Builds the GPv2 ``Order`` EIP-712 typed data.
+
+    Args:
+        chain_id: Chain the order settles on.
+        sell_token: Token sold.
+        buy_token: Token bought.
+        sell_amount: Amount of ``sell_token`` sold, or at most sold for buy orders.
+        buy_amount: Amount of ``buy_token`` bought, or at least bought for sell orders.
+        valid_to: Unix time the order expires at.
+        kind: ``"sell"`` or ``"buy"``.
+        receiver: Recipient of the bought tokens, or the zero address for the owner.
+        app_data: 32-byte hash of the order's app data document, as bytes or hex.
+        fee_amount: Fee in ``sell_token``; 0 for orders whose fee the solver takes.
+        partially_fillable: Whether the order may be filled in several parts.
+        sell_token_balance: ``"erc20"``, ``"external"`` or ``"internal"``, where the
+            sold tokens come from.
+        buy_token_balance: ``"erc20"`` or ``"internal"``, where the bought tokens go.
+        settlement: Address of the settlement contract, for other deployments.
+
+    Returns:
+        The typed data, for ``sign_cow_order`` or any EIP-712 signer.
+    """
+    if not 0 <= valid_to < 2**32:
+        raise ValueError(f"validTo must fit in uint32, got {valid_to}")
+    app_data = HexBytes(app_data)
+    if len(app_data) != 32:
+        raise ValueError(f"appData must be 32 bytes, got {len(app_data)}")
+
+    return {
+        "types": {"EIP712Domain": _DOMAIN_TYPE, "Order": _ORDER_TYPE},
+        "primaryType": "Order",
+        "domain": {
+            "name": "Gnosis Protocol",
+            "version": "v2",
+            "chainId": str(chain_id),
+            "verifyingContract": settlement,
+        },
+        # uint256 values as decimal strings, since token amounts overflow JSON numbers
+        "message": {
+            "sellToken": sell_token,
+            "buyToken": buy_token,
+            "receiver": receiver,
+            "sellAmount": str(sell_amount),
+            "buyAmount": str(buy_amount),
+            "validTo": valid_to,
+            "appData": "0x" + bytes(app_data).hex(),
+            "feeAmount": str(fee_amount),
+            "kind": _choice("kind", kind, ("sell", "buy")),
+            "partiallyFillable": bool(partially_fillable),
+            "sellTokenBalance": _choice(
+                "sellTokenBalance",
+                sell_token_balance,
+                ("erc20", "external", "internal"),
+            ),
+            "buyTokenBalance": _choice(
+                "buyTokenBalance", buy_token_balance, ("erc20", "internal")
+            ),
+        },
+    }
+
+
+def cow_order_hash(order: Dict[str, Any]) -> HexBytes:
+    """Returns the EIP-712 hash of an order, the digest its owner signs."""
+    return HexBytes(rust_hash_typed_data(json.dumps(order)))
+
+
+def cow_order_uid(order: Dict[str, Any], owner: str) -> HexBytes:
+    """
+    Returns an order's UID: its hash, its owner's address and its ``validTo`` as four
+    big-endian bytes.
+    """
+    valid_to = int(order["message"]["validTo"]).to_bytes(4, "big")
+    return HexBytes(bytes(cow_order_hash(order)) + bytes(HexBytes(owner)) + valid_to)
+
+
+def sign_cow_order(order: Dict[str, Any], owner: Any) -> CowOrderSignature:
+    """
+    Signs an order as its owner, for submission with ``signingScheme: "eip712"``.
+
+    Args:
+        order: The typed data ``cow_order`` built.
+        owner: The owner's private key, as bytes or hex, or an account with
+            ``address`` and ``sign_typed_data``, such as a ``RemoteAccount``.
+    """
+    address, signature = _sign_typed_data_with(order, owner)
+    return CowOrderSignature(address, signature, cow_order_uid(order, address))
+
+
+def sign_cow_orders(
+    orders: Iterable[Dict[str, Any]], private_key: Any
+) -> List[CowOrderSignature]:
+    """
+    Signs many orders with one private key in a single call to the Rust backend.
+
+    The orders must all be for the same chain and settlement contract.
+    """
+    orders = list(orders)
+    if not orders:
+        return []
+    domain = orders[0]["domain"]
+    for order in orders[1:]:
+        if order["domain"] != domain:
+            raise ValueError(
+                "Orders for different chains or settlement contracts can't be "
+                "signed in one batch"
+            )
+
+    private_key = _private_key_bytes(private_key)
+    address = rust_addresses_from_keys([private_key])[0]
+    types = {"EIP712Domain": _DOMAIN_TYPE, "Order": _ORDER_TYPE}
+    messages = [order["message"] for order in orders]
+    signed = sign_typed_data_batch(domain, types, messages, private_key, "Order")
+    return [
+        CowOrderSignature(
+            address, signed_order.signature, cow_order_uid(order, address)
+        )
+        for order, signed_order in zip(orders, signed)
+    ]
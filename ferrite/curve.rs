@@ -0,0 +1,190 @@
+/*!
+Short Weierstrass curve arithmetic for the curves k256 doesn't cover.
+
+Points are kept in Jacobian coordinates over any field implementing [`FieldElement`],
+which the `crypto-bigint` Montgomery residues do for prime fields, and every operation
+runs in time independent of the points and scalars involved, so the same code serves
+both public verification and secret-key signing.
+*/
+
+use std::ops::{Add, Mul, Sub};
+
+use crypto_bigint::modular::constant_mod::{Residue, ResidueParams};
+use crypto_bigint::U256;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// The field operations point arithmetic needs.
+pub trait FieldElement:
+    Copy
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + ConditionallySelectable
+    + ConstantTimeEq
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn square(&self) -> Self;
+
+    /// Returns the inverse, and whether there is one.
+    fn invert(&self) -> (Self, Choice);
+}
+
+/// An element of a prime field of at most 256 bits.
+pub type Residue256<M> = Residue<M, { U256::LIMBS }>;
+
+impl<M: ResidueParams<{ U256::LIMBS }> + Copy> FieldElement for Residue256<M> {
+    const ZERO: Self = Residue::ZERO;
+    const ONE: Self = Residue::ONE;
+
+    fn square(&self) -> Self {
+        Residue::square(self)
+    }
+
+    fn invert(&self) -> (Self, Choice) {
+        let (inverse, invertible) = Residue::invert(self);
+        (inverse, invertible.into())
+    }
+}
+
+/// A curve `y² = x³ + ax + b`.
+pub trait Curve: Copy {
+    type Element: FieldElement;
+    const A: Self::Element;
+    const B: Self::Element;
+}
+
+/// A curve point in Jacobian coordinates; `z` is zero for the point at infinity.
+#[derive(Clone, Copy)]
+pub struct Point<C: Curve> {
+    x: C::Element,
+    y: C::Element,
+    z: C::Element,
+}
+
+impl<C: Curve> ConditionallySelectable for Point<C> {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Point {
+            x: C::Element::conditional_select(&a.x, &b.x, choice),
+            y: C::Element::conditional_select(&a.y, &b.y, choice),
+            z: C::Element::conditional_select(&a.z, &b.z, choice),
+        }
+    }
+}
+
+impl<C: Curve> Point<C> {
+    pub const INFINITY: Self = Point {
+        x: C::Element::ONE,
+        y: C::Element::ONE,
+        z: C::Element::ZERO,
+    };
+
+    /// Builds a point from affine coordinates, or returns `None` if it isn't on the
+    /// curve.
+    pub fn from_affine_elements(x: C::Element, y: C::Element) -> Option<Self> {
+        let on_curve = y.square() == x.square() * x + C::A * x + C::B;
+        on_curve.then_some(Point { x, y, z: C::Element::ONE })
+    }
+
+    fn is_infinity(&self) -> Choice {
+        self.z.ct_eq(&C::Element::ZERO)
+    }
+
+    pub fn double(&self) -> Self {
+        let xx = self.x.square();
+        let yy = self.y.square();
+        let yyyy = yy.square();
+        let zz = self.z.square();
+        let s = (self.x + yy).square() - xx - yyyy;
+        let s = s + s;
+        let m = xx + xx + xx + C::A * zz.square();
+        let x = m.square() - s - s;
+        let yyyy2 = yyyy + yyyy;
+        let yyyy4 = yyyy2 + yyyy2;
+        let y = m * (s - x) - yyyy4 - yyyy4;
+        let z = (self.y + self.z).square() - yy - zz;
+        Point { x, y, z }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let z1z1 = self.z.square();
+        let z2z2 = other.z.square();
+        let u1 = self.x * z2z2;
+        let u2 = other.x * z1z1;
+        let s1 = self.y * other.z * z2z2;
+        let s2 = other.y * self.z * z1z1;
+        let h = u2 - u1;
+        let r = s2 - s1;
+        let r = r + r;
+        let i = (h + h).square();
+        let j = h * i;
+        let v = u1 * i;
+        let x = r.square() - j - v - v;
+        let s1j = s1 * j;
+        let y = r * (v - x) - s1j - s1j;
+        let z = ((self.z + other.z).square() - z1z1 - z2z2) * h;
+
+        // The formula breaks down for equal points and for the point at infinity, which
+        // are selected around in constant time rather than branched on
+        let equal = h.ct_eq(&C::Element::ZERO) & r.ct_eq(&C::Element::ZERO);
+        let sum = Point::conditional_select(&Point { x, y, z }, &self.double(), equal);
+        let sum = Point::conditional_select(&sum, other, self.is_infinity());
+        Point::conditional_select(&sum, self, other.is_infinity())
+    }
+
+    /// Multiplies the point by the low `bits` bits of `k`, in time independent of `k`.
+    pub fn mul(&self, k: &U256, bits: usize) -> Self {
+        let words = k.as_words();
+        let mut acc = Point::INFINITY;
+        for i in (0..bits).rev() {
+            acc = acc.double();
+            let bit = Choice::from(((words[i / 64] >> (i % 64)) & 1) as u8);
+            acc = Point::conditional_select(&acc, &acc.add(self), bit);
+        }
+        acc
+    }
+
+    /// Returns the affine coordinates, or `None` for the point at infinity.
+    pub fn affine_elements(&self) -> Option<(C::Element, C::Element)> {
+        let (z_inv, invertible) = self.z.invert();
+        let zz_inv = z_inv.square();
+        bool::from(invertible).then(|| (self.x * zz_inv, self.y * zz_inv * z_inv))
+    }
+}
+
+/// Conversions to and from integers, for curves over prime fields.
+impl<M, C> Point<C>
+where
+    M: ResidueParams<{ U256::LIMBS }> + Copy,
+    C: Curve<Element = Residue256<M>>,
+{
+    /// Builds a constant point from big-endian hex coordinates, 64 digits each.
+    pub const fn from_hex(x: &str, y: &str) -> Self {
+        Point {
+            x: Residue::new(&U256::from_be_hex(x)),
+            y: Residue::new(&U256::from_be_hex(y)),
+            z: Residue::ONE,
+        }
+    }
+
+    /// Builds a point from affine coordinates, or returns `None` if they aren't below
+    /// the field prime or the point isn't on the curve.
+    pub fn from_affine(x: &U256, y: &U256) -> Option<Self> {
+        if *x >= M::MODULUS || *y >= M::MODULUS {
+            return None;
+        }
+        Point::from_affine_elements(Residue::new(x), Residue::new(y))
+    }
+
+    /// Returns the affine coordinates, or `None` for the point at infinity.
+    pub fn affine(&self) -> Option<(U256, U256)> {
+        self.affine_elements().map(|(x, y)| (x.retrieve(), y.retrieve()))
+    }
+
+    /// Returns the affine x coordinate, or `None` for the point at infinity.
+    pub fn x(&self) -> Option<U256> {
+        self.affine().map(|(x, _)| x)
+    }
+}
@@ -0,0 +1,134 @@
+"""
+Validator deposit data, in the format of the staking deposit CLI's ``deposit_data``
+JSON files.
+
+Deposits are signed with the validator's BLS12-381 key, which ferrite doesn't hold: its
+BLS module is on BN254, for ERC-4337 aggregators. ``deposit_data`` takes the signature,
+or a function that signs the signing root with a BLS12-381 key held elsewhere, and
+computes everything else.
+"""
+
+from typing import Any, Callable, Dict, Optional, Union
+
+from hexbytes import HexBytes
+from _ferrite import (  # type: ignore
+    deposit_data_root as rust_deposit_data_root,
+    deposit_message_root as rust_deposit_message_root,
+    deposit_signing_root as rust_deposit_signing_root,
+    to_checksum_address as rust_to_checksum_address,
+)
+
+# The genesis fork version deposits are signed under on each network
+GENESIS_FORK_VERSIONS: Dict[str, HexBytes] = {
+    "mainnet": HexBytes("0x00000000"),
+    "sepolia": HexBytes("0x90000069"),
+    "holesky": HexBytes("0x01017000"),
+    "hoodi": HexBytes("0x10000910"),
+}
+
+# The full deposit for a validator before EIP-7251, in gwei
+DEPOSIT_AMOUNT = 32 * 10**9
+
+
+def withdrawal_credentials(address: str, *, compounding: bool = False) -> HexBytes:
+    """
+    Returns withdrawal credentials paying out to an execution layer address: ``0x01``
+    credentials, or ``0x02`` compounding credentials from EIP-7251 with ``compounding``.
+    """
+    prefix = b"\x02" if compounding else b"\x01"
+    return HexBytes(prefix + bytes(11) + HexBytes(rust_to_checksum_address(address)))
+
+
+def _fork_version(network: str, fork_version: Optional[bytes]) -> bytes:
+    if fork_version is not None:
+        return bytes(HexBytes(fork_version))
+    if network not in GENESIS_FORK_VERSIONS:
+        raise ValueError(
+            f"Unknown network {network!r}; pass its fork_version, or one of "
+            f"{', '.join(GENESIS_FORK_VERSIONS)}"
+        )
+    return bytes(GENESIS_FORK_VERSIONS[network])
+
+
+def deposit_signing_root(
+    pubkey: bytes,
+    withdrawal_credentials: bytes,
+    amount: int = DEPOSIT_AMOUNT,
+    *,
+    network: str = "mainnet",
+    fork_version: Optional[bytes] = None,
+) -> HexBytes:
+    """
+    Returns the root a validator's BLS12-381 key signs to make a deposit.
+
+    Args:
+        pubkey: The validator's 48-byte public key, as bytes or hex.
+        withdrawal_credentials: The 32-byte withdrawal credentials.
+        amount: The deposit amount in gwei.
+        network: The network the deposit is for, a key of ``GENESIS_FORK_VERSIONS``.
+        fork_version: The genesis fork version of a network not listed there.
+    """
+    return HexBytes(
+        rust_deposit_signing_root(
+            bytes(HexBytes(pubkey)),
+            bytes(HexBytes(withdrawal_credentials)),
+            amount,
+            _fork_version(network, fork_version),
+        )
+    )
+
+
+def deposit_data(
+    pubkey: bytes,
+    withdrawal_credentials: bytes,
+    signature: Union[bytes, Callable[[bytes], bytes]],
+    amount: int = DEPOSIT_AMOUNT,
+    *,
+    network: str = "mainnet",
+    fork_version: Optional[bytes] = None,
+    deposit_cli_version: str = "2.7.0",
+) -> Dict[str, Any]:
+    """
+    Builds one entry of a ``deposit_data`` JSON file, as the launchpad takes them.
+
+    Args:
+        pubkey: The validator's 48-byte public key, as bytes or hex.
+        withdrawal_credentials: The 32-byte withdrawal credentials.
+        signature: The 96-byte signature of ``deposit_signing_root``, or a function
+            that signs the signing root and returns it.
+        amount: The deposit amount in gwei.
+        network: The network the deposit is for, a key of ``GENESIS_FORK_VERSIONS``.
+        fork_version: The genesis fork version of a network not listed there.
+        deposit_cli_version: The deposit CLI version the file claims the format of,
+            which the launchpad checks.
+
+    Returns:
+        The entry, with hex fields written without ``0x`` as the deposit CLI does.
+        ``deposit_data_root`` is the last argument of the deposit contract's
+        ``deposit``.
+    """
+    pubkey = bytes(HexBytes(pubkey))
+    credentials = bytes(HexBytes(withdrawal_credentials))
+    version = _fork_version(network, fork_version)
+    if callable(signature):
+        signature = signature(
+            bytes(
+                deposit_signing_root(pubkey, credentials, amount, fork_version=version)
+            )
+        )
+    signature = bytes(HexBytes(signature))
+    return {
+        "pubkey": pubkey.hex(),
+        "withdrawal_credentials": credentials.hex(),
+        "amount": amount,
+        "signature": signature.hex(),
+        "deposit_message_root": rust_deposit_message_root(
+            pubkey, credentials, amount
+        ).hex(),
+        "deposit_data_root": rust_deposit_data_root(
+            pubkey, credentials, amount, signature
+        ).hex(),
+        "fork_version": version.hex(),
+        "network_name": network,
+        "deposit_cli_version": deposit_cli_version,
+    }
@@ -0,0 +1,103 @@
+/*!
+ENS name hashing and DNS wire-format encoding.
+
+Names are hashed exactly as given: ENSIP-15 normalization needs Unicode tables far larger
+than the rest of ferrite, so callers normalize first, with `ens-normalize` or web3's
+`ENS`. A label written as `[` and 64 hex digits `]` stands for a label whose hash is known
+but whose text is not, as in the ENS subgraph, and is used as its labelhash directly.
+*/
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::errors;
+use crate::keccak::keccak256;
+
+fn invalid(name: &str, reason: &str) -> PyErr {
+    PyErr::new::<errors::FerriteError, _>(format!("Invalid ENS name {:?}: {}", name, reason))
+}
+
+/// Splits `name` into its labels, rejecting empty ones. The empty name has no labels.
+fn labels(name: &str) -> PyResult<Vec<&str>> {
+    if name.is_empty() {
+        return Ok(Vec::new());
+    }
+    let labels: Vec<_> = name.split('.').collect();
+    if labels.iter().any(|label| label.is_empty()) {
+        return Err(invalid(name, "empty label"));
+    }
+    Ok(labels)
+}
+
+/// Returns the hash an encoded label like `[4f5b...]` stands for, if it is one.
+fn encoded_label_hash(label: &str) -> Option<[u8; 32]> {
+    let digits = label.strip_prefix('[')?.strip_suffix(']')?;
+    let mut hash = [0u8; 32];
+    hex::decode_to_slice(digits, &mut hash).ok()?;
+    Some(hash)
+}
+
+fn label_hash(label: &str) -> [u8; 32] {
+    encoded_label_hash(label).unwrap_or_else(|| keccak256(label.as_bytes()))
+}
+
+/// Computes the keccak256 of a single label, as ENS registrars take token IDs from it.
+///
+/// # Arguments
+/// * `label` - One label of a name, such as `"vitalik"`, already normalized.
+///
+/// # Returns
+/// The 32-byte labelhash.
+#[pyfunction]
+pub fn labelhash(py: Python, label: &str) -> PyResult<PyObject> {
+    if label.is_empty() || label.contains('.') {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "Invalid ENS label {:?}: expected a single non-empty label",
+            label
+        )));
+    }
+    Ok(PyBytes::new(py, &label_hash(label)).into_any().unbind())
+}
+
+/// Computes the EIP-137 namehash of a name, the node ENS contracts identify it by.
+///
+/// # Arguments
+/// * `name` - The name, such as `"vitalik.eth"`, already normalized. The empty name is
+///   the root node.
+///
+/// # Returns
+/// The 32-byte namehash.
+#[pyfunction]
+pub fn namehash(py: Python, name: &str) -> PyResult<PyObject> {
+    let node = labels(name)?.iter().rev().fold([0u8; 32], |node, label| {
+        keccak256(&[node, label_hash(label)].concat())
+    });
+    Ok(PyBytes::new(py, &node).into_any().unbind())
+}
+
+/// Encodes a name in DNS wire format, as ENSIP-10 wildcard resolvers and the
+/// `UniversalResolver` take it.
+///
+/// # Arguments
+/// * `name` - The name, already normalized.
+///
+/// # Returns
+/// Each label prefixed by its length in bytes, followed by a zero byte. Labels longer
+/// than 255 bytes are encoded as the `[...]` form of their labelhash, as viem does.
+#[pyfunction]
+pub fn dns_encode(py: Python, name: &str) -> PyResult<PyObject> {
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in labels(name)? {
+        let hashed;
+        let label = if label.len() > 255 {
+            hashed = format!("[{}]", hex::encode(label_hash(label)));
+            &hashed
+        } else {
+            label
+        };
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    Ok(PyBytes::new(py, &encoded).into_any().unbind())
+}
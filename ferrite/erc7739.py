@@ -0,0 +1,192 @@
+"""
+ERC-7739 defensive rehashing for smart account signatures.
+
+A smart account that checks ERC-1271 signatures against its owner's plain EIP-712
+signature can have one signature replayed across every account the owner controls.
+ERC-7739 accounts instead expect the owner to sign the app's typed data nested in a
+``TypedDataSign`` struct that also names the account, or a text message as a
+``PersonalSign`` struct in the account's own domain. Wallets still show the nested
+contents, so the owner sees what they sign.
+
+For typed data, the signature the account receives is the owner's signature followed
+by the app's domain separator, the contents' struct hash and the contents' type, from
+which the account rebuilds the ``TypedDataSign`` hash.
+"""
+
+import json
+from typing import Any, Dict, Set, Union
+
+from hexbytes import HexBytes
+from _ferrite import hash_typed_data as rust_hash_typed_data  # type: ignore
+from .account import _eip712_domain, _sign_typed_data_with
+
+# The smart account's domain fields, all of which TypedDataSign includes
+_ACCOUNT_FIELDS = [
+    {"name": "name", "type": "string"},
+    {"name": "version", "type": "string"},
+    {"name": "chainId", "type": "uint256"},
+    {"name": "verifyingContract", "type": "address"},
+    {"name": "salt", "type": "bytes32"},
+]
+
+_ZERO_SALT = "0x" + "00" * 32
+
+
+def _dependencies(types: Dict[str, Any], name: str, found: Set[str]) -> Set[str]:
+    """Collects ``name`` and every struct type it references, however deeply."""
+    if name not in found and name in types:
+        found.add(name)
+        for field in types[name]:
+            _dependencies(types, field["type"].split("[")[0], found)
+    return found
+
+
+def _contents_description(typed_data: Dict[str, Any]) -> str:
+    """
+    Returns the contents description for a signature of nested typed data: the
+    contents' types as ``TypedDataSign``'s EIP-712 encoding lists them, sorted by
+    name, followed by the contents' name if it isn't the first of them.
+    """
+    contents_name = typed_data["primaryType"]
+    if not contents_name or "a" <= contents_name[0] <= "z" or any(
+        c in contents_name for c in ", ()\x00"
+    ):
+        raise ValueError(f"{contents_name!r} can't be nested under ERC-7739")
+
+    types = {k: v for k, v in typed_data["types"].items() if k != "EIP712Domain"}
+    encoded_fields = {
+        name: ",".join(f"{field['type']} {field['name']}" for field in fields)
+        for name, fields in types.items()
+    }
+    contents_type = "".join(
+        f"{name}({encoded_fields[name]})"
+        for name in sorted(_dependencies(types, contents_name, set()))
+    )
+    if contents_type.startswith(contents_name + "("):
+        return contents_type
+    return contents_type + contents_name
+
+
+def erc7739_typed_data(
+    typed_data: Dict[str, Any], account_domain: Dict[str, Any]
+) -> Dict[str, Any]:
+    """
+    Nests an app's typed data in the ``TypedDataSign`` struct an ERC-7739 account's
+    owner signs.
+
+    Args:
+        typed_data: The app's EIP-712 typed data, as it would be signed directly.
+        account_domain: The smart account's EIP-712 domain, as its ``eip712Domain``
+            reports it: ``name``, ``version``, ``chainId``, ``verifyingContract`` and
+            optionally ``salt``.
+
+    Returns:
+        The typed data the owner signs, in the app's domain.
+    """
+    account = _eip712_domain({"salt": _ZERO_SALT, **account_domain})["domain"]
+    types = dict(typed_data["types"])
+    types["TypedDataSign"] = [
+        {"name": "contents", "type": typed_data["primaryType"]},
+        *_ACCOUNT_FIELDS,
+    ]
+    return {
+        "types": types,
+        "primaryType": "TypedDataSign",
+        "domain": typed_data["domain"],
+        "message": {"contents": typed_data["message"], **account},
+    }
+
+
+def wrap_erc7739_signature(typed_data: Dict[str, Any], signature: bytes) -> HexBytes:
+    """
+    Appends what an ERC-7739 account needs to rebuild the ``TypedDataSign`` hash to
+    the owner's signature of ``erc7739_typed_data``.
+
+    Args:
+        typed_data: The app's typed data, not the nested ``TypedDataSign``.
+        signature: The owner's signature of the nested typed data.
+
+    Returns:
+        The signature to pass to the account's ``isValidSignature``.
+    """
+    domain = {
+        "types": {"EIP712Domain": typed_data["types"]["EIP712Domain"]},
+        "primaryType": "EIP712Domain",
+        "domain": typed_data["domain"],
+        "message": typed_data["domain"],
+    }
+    app_domain_separator = rust_hash_typed_data(json.dumps(domain), struct_hash=True)
+    contents = rust_hash_typed_data(json.dumps(typed_data), struct_hash=True)
+    description = _contents_description(typed_data).encode()
+    return HexBytes(
+        bytes(signature)
+        + app_domain_separator
+        + contents
+        + description
+        + len(description).to_bytes(2, "big")
+    )
+
+
+def sign_erc7739_typed_data(
+    typed_data: Dict[str, Any], account_domain: Dict[str, Any], owner: Any
+) -> HexBytes:
+    """
+    Signs an app's typed data for an ERC-7739 smart account.
+
+    Args:
+        typed_data: The app's EIP-712 typed data.
+        account_domain: The smart account's EIP-712 domain, as for
+            ``erc7739_typed_data``.
+        owner: The account owner's private key, as bytes or hex, or an account with
+            ``address`` and ``sign_typed_data``.
+
+    Returns:
+        The signature to pass to the account's ``isValidSignature`` along with the
+        app's EIP-712 hash of ``typed_data``.
+    """
+    nested = erc7739_typed_data(typed_data, account_domain)
+    _, signature = _sign_typed_data_with(nested, owner)
+    return wrap_erc7739_signature(typed_data, signature)
+
+
+def erc7739_personal_message(
+    message: Union[str, bytes], account_domain: Dict[str, Any]
+) -> Dict[str, Any]:
+    """
+    Wraps a personal message in the ``PersonalSign`` struct an ERC-7739 account's
+    owner signs, in the account's own domain.
+
+    Args:
+        message: The message, as text or bytes, before EIP-191 prefixing.
+        account_domain: The smart account's EIP-712 domain, with only the fields its
+            ``eip712Domain`` reports as used.
+    """
+    if isinstance(message, str):
+        message = message.encode()
+    prefixed = b"\x19Ethereum Signed Message:\n" + str(len(message)).encode() + message
+    domain = _eip712_domain(account_domain)
+    return {
+        "types": {
+            "EIP712Domain": domain["types"],
+            "PersonalSign": [{"name": "prefixed", "type": "bytes"}],
+        },
+        "primaryType": "PersonalSign",
+        "domain": domain["domain"],
+        "message": {"prefixed": "0x" + prefixed.hex()},
+    }
+
+
+def sign_erc7739_personal_message(
+    message: Union[str, bytes], account_domain: Dict[str, Any], owner: Any
+) -> HexBytes:
+    """
+    Signs a personal message for an ERC-7739 smart account, which checks it against
+    the message's EIP-191 hash.
+
+    Args are as for ``erc7739_personal_message``, with the owner as for
+    ``sign_erc7739_typed_data``.
+    """
+    _, signature = _sign_typed_data_with(
+        erc7739_personal_message(message, account_domain), owner
+    )
+    return signature
@@ -0,0 +1,164 @@
+/*!
+Exception hierarchy raised by ferrite.
+
+Every exception derives from `FerriteError`, which itself derives from `ValueError` so
+that callers written against eth-account, which raises `ValueError` for the same
+failures, keep working unchanged.
+
+Classes belong to the interpreter that created them, so each interpreter importing
+ferrite creates its own, and raising an exception raises that interpreter's class.
+*/
+
+use std::ffi::CString;
+use std::mem;
+use std::sync::Arc;
+
+use ferrite_core::ErrorKind;
+use pyo3::exceptions::{PyUserWarning, PyValueError};
+use pyo3::ffi;
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::PyType;
+use pyo3::PyTypeInfo;
+
+use crate::interpreter::Local;
+
+pub use ferrite_core::signing::redact;
+
+/// Declares an exception class, whose Python class is looked up in the interpreter the
+/// GIL is held in.
+macro_rules! exception {
+    ($name:ident, $doc:literal) => {
+        #[doc = $doc]
+        pub struct $name;
+
+        impl $name {
+            const DOC: &'static str = $doc;
+        }
+
+        // SAFETY: `type_object_raw` returns this interpreter's class, a subclass of
+        // `BaseException` as `PyErr` requires
+        unsafe impl PyTypeInfo for $name {
+            const NAME: &'static str = stringify!($name);
+            const MODULE: Option<&'static str> = Some("_ferrite");
+
+            fn type_object_raw(py: Python<'_>) -> *mut ffi::PyTypeObject {
+                class(py, stringify!($name))
+            }
+        }
+    };
+}
+
+exception!(FerriteError, "Base class for all ferrite errors.");
+exception!(InvalidKeyError, "A private key could not be parsed.");
+exception!(
+    InvalidTransactionError,
+    "A transaction is malformed or cannot be signed as given."
+);
+exception!(TypedDataError, "EIP-712 typed data is malformed or cannot be encoded.");
+exception!(SigningError, "The signer failed to produce a signature.");
+exception!(PolicyViolationError, "A transaction falls outside the signing policy.");
+exception!(ApprovalDeniedError, "The approval hook did not approve a signature.");
+exception!(RateLimitError, "A key's signing rate limit would be exceeded.");
+exception!(
+    DangerousTypedDataWarning,
+    "Typed data that can grant control over the signer's assets is being signed."
+);
+
+/// An interpreter's classes, by name.
+type Classes = Vec<(&'static str, Py<PyType>)>;
+
+static CLASSES: Local<GILOnceCell<Classes>> = Local::new();
+
+/// Creates the classes for the interpreter the GIL is held in.
+fn create(py: Python) -> PyResult<Classes> {
+    let new = |name: &'static str, doc: &str, base: &Bound<PyType>| {
+        let qualified = CString::new(format!("_ferrite.{}", name))?;
+        let doc = CString::new(doc)?;
+        PyErr::new_type(py, &qualified, Some(&doc), Some(base), None).map(|class| (name, class))
+    };
+
+    let base = new(FerriteError::NAME, FerriteError::DOC, &py.get_type::<PyValueError>())?;
+    let errors = [
+        (InvalidKeyError::NAME, InvalidKeyError::DOC),
+        (InvalidTransactionError::NAME, InvalidTransactionError::DOC),
+        (TypedDataError::NAME, TypedDataError::DOC),
+        (SigningError::NAME, SigningError::DOC),
+        (PolicyViolationError::NAME, PolicyViolationError::DOC),
+        (ApprovalDeniedError::NAME, ApprovalDeniedError::DOC),
+        (RateLimitError::NAME, RateLimitError::DOC),
+    ];
+    let mut classes = errors
+        .into_iter()
+        .map(|(name, doc)| new(name, doc, base.1.bind(py)))
+        .collect::<PyResult<Vec<_>>>()?;
+    classes.insert(0, base);
+    let warning = &py.get_type::<PyUserWarning>();
+    classes.push(new(DangerousTypedDataWarning::NAME, DangerousTypedDataWarning::DOC, warning)?);
+    Ok(classes)
+}
+
+/// Returns the class named `name` in the interpreter the GIL is held in, creating the
+/// interpreter's classes on first use.
+fn class(py: Python, name: &str) -> *mut ffi::PyTypeObject {
+    let classes = CLASSES.get(py);
+    let classes = classes
+        .get_or_try_init(py, || create(py))
+        .expect("Failed to initialize the ferrite exception classes");
+    // Kept alive by `CLASSES` until the interpreter's module is freed
+    let (_, class) = classes.iter().find(|(of, _)| *of == name).expect("Unknown exception class");
+    class.as_ptr().cast()
+}
+
+/// Adds the exception classes to the module.
+pub fn register(m: &Bound<PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("FerriteError", py.get_type::<FerriteError>())?;
+    m.add("InvalidKeyError", py.get_type::<InvalidKeyError>())?;
+    m.add("InvalidTransactionError", py.get_type::<InvalidTransactionError>())?;
+    m.add("TypedDataError", py.get_type::<TypedDataError>())?;
+    m.add("SigningError", py.get_type::<SigningError>())?;
+    m.add("PolicyViolationError", py.get_type::<PolicyViolationError>())?;
+    m.add("ApprovalDeniedError", py.get_type::<ApprovalDeniedError>())?;
+    m.add("RateLimitError", py.get_type::<RateLimitError>())?;
+    m.add("DangerousTypedDataWarning", py.get_type::<DangerousTypedDataWarning>())?;
+    Ok(())
+}
+
+/// Drops the classes of the interpreter the GIL is held in, once its module is freed.
+pub fn clear(py: Python) {
+    let Some(classes) = CLASSES.take(py).and_then(Arc::into_inner) else {
+        return;
+    };
+    for (_, class) in classes.into_inner().into_iter().flatten() {
+        drop(class.into_bound(py));
+    }
+}
+
+/// Raises the field errors collected while parsing a payload.
+pub fn invalid_transaction(message: String) -> PyErr {
+    PyErr::new::<InvalidTransactionError, _>(message)
+}
+
+/// Converts a `ferrite_core` error into the matching exception.
+pub fn from_core(error: ferrite_core::Error) -> PyErr {
+    let message = error.message;
+    match error.kind {
+        ErrorKind::Invalid => PyErr::new::<FerriteError, _>(message),
+        ErrorKind::InvalidKey => PyErr::new::<InvalidKeyError, _>(message),
+        ErrorKind::InvalidTransaction => PyErr::new::<InvalidTransactionError, _>(message),
+        ErrorKind::TypedData => PyErr::new::<TypedDataError, _>(message),
+        ErrorKind::Signing => PyErr::new::<SigningError, _>(message),
+    }
+}
+
+/// Normalizes `error` in the interpreter the GIL is held in, as inspecting or converting
+/// it requires.
+///
+/// PyO3 normalizes errors after taking the GIL afresh, which on a thread that has run in
+/// another interpreter lands in that one. Raising and fetching the error again creates
+/// it here instead.
+pub fn normalize(py: Python, error: &mut PyErr) {
+    mem::replace(error, PyErr::new::<PyValueError, _>(())).restore(py);
+    *error = PyErr::fetch(py);
+}
@@ -0,0 +1,43 @@
+"""
+Flashbots relay authentication.
+
+Every request to a Flashbots relay carries an ``X-Flashbots-Signature`` header, the
+searcher's address and an EIP-191 signature of the keccak hash of the request body.
+The key only identifies the searcher for reputation; it needn't hold any funds.
+"""
+
+from typing import Any, Union
+
+from eth_account.messages import encode_defunct
+from eth_utils import keccak
+from _ferrite import addresses_from_keys as rust_addresses_from_keys  # type: ignore
+from .account import _account_sign_message_wrapper, _private_key_bytes
+
+FLASHBOTS_SIGNATURE_HEADER = "X-Flashbots-Signature"
+
+
+def flashbots_signature(body: Union[bytes, str], signer: Any) -> str:
+    """
+    Returns the ``X-Flashbots-Signature`` header value for a relay request.
+
+    Args:
+        body: The exact JSON-RPC request body sent, as bytes or text. Any difference,
+            even in whitespace, invalidates the signature.
+        signer: The searcher's private key, as bytes or hex, or an account with
+            ``address`` and ``sign_message``.
+
+    Returns:
+        ``"<address>:<signature>"``, the signature hex encoded with a ``0x`` prefix.
+    """
+    if isinstance(body, str):
+        body = body.encode()
+    # The relay checks a signature of the hash's hex text, not of the hash itself
+    message = encode_defunct(text="0x" + keccak(body).hex())
+
+    if hasattr(signer, "sign_message"):
+        address, signed = signer.address, signer.sign_message(message)
+    else:
+        private_key = _private_key_bytes(signer)
+        address = rust_addresses_from_keys([private_key])[0]
+        signed = _account_sign_message_wrapper(message, private_key)
+    return f"{address}:0x{bytes(signed.signature).hex()}"
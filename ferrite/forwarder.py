@@ -0,0 +1,117 @@
+"""
+EIP-2771 meta-transactions through OpenZeppelin's ``MinimalForwarder``.
+
+The user signs a ``ForwardRequest`` in the forwarder's EIP-712 domain, and a relayer
+submits it with ``execute(request, signature)``, paying the gas. The target contract
+then sees the user, not the relayer, as the sender.
+"""
+
+import json
+from typing import Any, Callable, Dict, NamedTuple, Union
+
+from hexbytes import HexBytes
+from _ferrite import hash_typed_data as rust_hash_typed_data  # type: ignore
+from .account import _json_value, _sign_typed_data_with
+
+_FORWARD_REQUEST_TYPE = [
+    {"name": "from", "type": "address"},
+    {"name": "to", "type": "address"},
+    {"name": "value", "type": "uint256"},
+    {"name": "gas", "type": "uint256"},
+    {"name": "nonce", "type": "uint256"},
+    {"name": "data", "type": "bytes"},
+]
+
+
+class SignedForwardRequest(NamedTuple):
+    """A ``ForwardRequest`` and its signature, the arguments to ``execute``."""
+
+    request: Dict[str, Any]
+    signature: HexBytes
+
+
+def forward_request(
+    forwarder: str,
+    chain_id: int,
+    sender: str,
+    to: str,
+    data: Union[bytes, str] = b"",
+    *,
+    gas: int,
+    nonce: Union[int, Callable[[str], int]],
+    value: int = 0,
+    name: str = "MinimalForwarder",
+    version: str = "0.0.1",
+) -> Dict[str, Any]:
+    """
+    Builds the ``ForwardRequest`` EIP-712 typed data for a meta-transaction.
+
+    Args:
+        forwarder: Address of the forwarder contract.
+        chain_id: Chain the forwarder is deployed on.
+        sender: Address of the user, who must sign the request.
+        to: Contract the request calls, which must trust the forwarder.
+        data: Calldata, as bytes or hex.
+        gas: Gas the forwarder gives the call.
+        nonce: The sender's nonce, or a callable returning it for the sender's
+            address, such as a wrapper around the forwarder's ``getNonce``.
+        value: Wei sent with the call.
+        name: Name in the forwarder's domain, for forwarders deployed under another.
+        version: Version in the forwarder's domain.
+
+    Returns:
+        The typed data, for ``sign_forward_request`` or any EIP-712 signer.
+    """
+    if callable(nonce):
+        nonce = nonce(sender)
+    return {
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"},
+            ],
+            "ForwardRequest": _FORWARD_REQUEST_TYPE,
+        },
+        "primaryType": "ForwardRequest",
+        "domain": {
+            "name": name,
+            "version": version,
+            "chainId": str(chain_id),
+            "verifyingContract": forwarder,
+        },
+        "message": {
+            "from": sender,
+            "to": to,
+            "value": str(value),
+            "gas": str(gas),
+            "nonce": str(nonce),
+            "data": _json_value("data", HexBytes(data)),
+        },
+    }
+
+
+def forward_request_hash(request: Dict[str, Any]) -> HexBytes:
+    """Returns the EIP-712 hash of a ``ForwardRequest``, which ``verify`` checks."""
+    return HexBytes(rust_hash_typed_data(json.dumps(request)))
+
+
+def sign_forward_request(request: Dict[str, Any], sender: Any) -> SignedForwardRequest:
+    """
+    Signs a ``ForwardRequest`` as its sender.
+
+    Args:
+        request: The typed data ``forward_request`` built.
+        sender: The sender's private key, as bytes or hex, or an account with
+            ``address`` and ``sign_typed_data``, such as a ``RemoteAccount``.
+
+    Raises:
+        ValueError: If the signer is not the request's ``from``, since the forwarder
+            would reject the signature.
+    """
+    address, signature = _sign_typed_data_with(request, sender)
+    named = request["message"]["from"]
+    if int(named, 16) != int(address, 16):
+        raise ValueError(f"Request is from {named}, but {address} signed it")
+    return SignedForwardRequest(request["message"], signature)
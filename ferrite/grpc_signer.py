@@ -0,0 +1,375 @@
+"""
+The signer as a gRPC service, for infrastructure that speaks gRPC rather than JSON-RPC.
+
+The service is defined in ``signer.proto``, shipped next to this module, so clients in
+any language can generate their stubs from it. It signs hashes, typed data and
+transactions with the keys it holds, through the same signing policy, rate limits,
+approval hook and audit log as in-process signing, and can optionally add and remove
+keys at runtime.
+
+Messages are encoded here directly rather than through generated code, so the only
+dependency is ``grpcio``: ``pip install ferrite[grpc]``.
+"""
+
+import json
+import threading
+from concurrent.futures import ThreadPoolExecutor
+from typing import Any, Callable, Dict, Iterable, Optional, Tuple
+
+from eth_account import Account
+
+from _ferrite import (  # type: ignore
+    ApprovalDeniedError,
+    PolicyViolationError,
+    RateLimitError,
+)
+
+from . import prometheus
+from .account import _account_sign_hash_wrapper
+from .remote import signer_account
+from .server import _account, _transaction_fields
+
+SERVICE_NAME = "ferrite.signer.v1.Signer"
+
+DEFAULT_ADDRESS = "127.0.0.1:50051"
+
+# Fields of each message: number, name and type, as in signer.proto
+_MESSAGES: Dict[str, Tuple[Tuple[int, str, str], ...]] = {
+    "ListKeysRequest": (),
+    "ListKeysResponse": ((1, "addresses", "repeated string"),),
+    "AddKeyRequest": (
+        (1, "private_key", "bytes"),
+        (2, "keystore_json", "string"),
+        (3, "password", "bytes"),
+        (4, "signer", "string"),
+        (5, "signer_options_json", "string"),
+    ),
+    "AddKeyResponse": ((1, "address", "string"),),
+    "RemoveKeyRequest": ((1, "address", "string"),),
+    "RemoveKeyResponse": ((1, "removed", "bool"),),
+    "SignHashRequest": ((1, "address", "string"), (2, "hash", "bytes")),
+    "SignTypedDataRequest": (
+        (1, "address", "string"),
+        (2, "typed_data_json", "string"),
+    ),
+    "SignTransactionRequest": (
+        (1, "address", "string"),
+        (2, "transaction_json", "string"),
+    ),
+    "Signature": (
+        (1, "r", "bytes"),
+        (2, "s", "bytes"),
+        (3, "v", "uint64"),
+        (4, "signature", "bytes"),
+    ),
+    "SignedTransaction": (
+        (1, "raw_transaction", "bytes"),
+        (2, "hash", "bytes"),
+        (3, "r", "bytes"),
+        (4, "s", "bytes"),
+        (5, "v", "uint64"),
+    ),
+}
+
+# Request and response message of each method
+_METHODS = {
+    "ListKeys": ("ListKeysRequest", "ListKeysResponse"),
+    "AddKey": ("AddKeyRequest", "AddKeyResponse"),
+    "RemoveKey": ("RemoveKeyRequest", "RemoveKeyResponse"),
+    "SignHash": ("SignHashRequest", "Signature"),
+    "SignTypedData": ("SignTypedDataRequest", "Signature"),
+    "SignTransaction": ("SignTransactionRequest", "SignedTransaction"),
+}
+
+_DEFAULTS = {"string": "", "bytes": b"", "uint64": 0, "bool": False}
+
+
+def _grpc() -> Any:
+    """Imports the optional ``grpcio`` package."""
+    try:
+        import grpc  # type: ignore
+    except ImportError as e:
+        raise ImportError(
+            "The gRPC signer needs the grpcio package: pip install ferrite[grpc]"
+        ) from e
+    return grpc
+
+
+def _varint(value: int) -> bytes:
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def _read_varint(data: bytes, offset: int) -> Tuple[int, int]:
+    value = shift = 0
+    while True:
+        if offset >= len(data) or shift > 63:
+            raise ValueError("Truncated or overlong varint")
+        byte = data[offset]
+        offset += 1
+        value |= (byte & 0x7F) << shift
+        shift += 7
+        if not byte & 0x80:
+            return value, offset
+
+
+def encode_message(name: str, fields: Dict[str, Any]) -> bytes:
+    """Encodes a message of ``signer.proto`` from a dict of its fields."""
+    out = bytearray()
+    for number, field, kind in _MESSAGES[name]:
+        repeated = kind.startswith("repeated ")
+        kind = kind.split()[-1]
+        values = fields.get(field, [] if repeated else _DEFAULTS[kind])
+        for value in values if repeated else [values]:
+            if not repeated and value == _DEFAULTS[kind]:
+                continue
+            if kind in ("uint64", "bool"):
+                out += _varint(number << 3) + _varint(int(value))
+            else:
+                data = value.encode() if kind == "string" else bytes(value)
+                out += _varint(number << 3 | 2) + _varint(len(data)) + data
+    return bytes(out)
+
+
+def decode_message(name: str, data: bytes) -> Dict[str, Any]:
+    """Decodes a message of ``signer.proto`` into a dict of all its fields."""
+    schema = {number: (field, kind) for number, field, kind in _MESSAGES[name]}
+    fields: Dict[str, Any] = {
+        field: [] if kind.startswith("repeated ") else _DEFAULTS[kind]
+        for field, kind in schema.values()
+    }
+    offset = 0
+    while offset < len(data):
+        key, offset = _read_varint(data, offset)
+        number, wire_type = key >> 3, key & 7
+        if wire_type == 0:
+            value, offset = _read_varint(data, offset)
+        elif wire_type == 2:
+            length, offset = _read_varint(data, offset)
+            if offset + length > len(data):
+                raise ValueError(f"Truncated {name} message")
+            value, offset = data[offset : offset + length], offset + length
+        elif wire_type in (1, 5):
+            offset += 8 if wire_type == 1 else 4
+            continue
+        else:
+            raise ValueError(f"Unsupported wire type {wire_type} in {name} message")
+        if number not in schema:
+            continue
+        field, kind = schema[number]
+        if kind.endswith("string"):
+            value = bytes(value).decode()
+        elif kind == "bool":
+            value = bool(value)
+        if kind.startswith("repeated "):
+            fields[field].append(value)
+        else:
+            fields[field] = value
+    return fields
+
+
+class _Status(Exception):
+    def __init__(self, code: str, message: str) -> None:
+        super().__init__(message)
+        self.code = code
+
+
+def _status(error: Exception) -> Tuple[str, str]:
+    """Returns the gRPC status code and details for a call that raised ``error``."""
+    if isinstance(error, _Status):
+        return error.code, str(error)
+    if isinstance(error, (PolicyViolationError, ApprovalDeniedError)):
+        return "PERMISSION_DENIED", str(error)
+    if isinstance(error, RateLimitError):
+        return "RESOURCE_EXHAUSTED", str(error)
+    if isinstance(error, (ValueError, TypeError)):
+        return "INVALID_ARGUMENT", str(error)
+    return "INTERNAL", str(error)
+
+
+def _signature(signed: Any) -> Dict[str, Any]:
+    return {
+        "r": signed.r.to_bytes(32, "big"),
+        "s": signed.s.to_bytes(32, "big"),
+        "v": signed.v,
+        "signature": bytes(signed.signature),
+    }
+
+
+class SignerService:
+    """
+    The ``Signer`` service of ``signer.proto``, signing with the keys it holds.
+
+    Args:
+        accounts: The keys to start with, as private keys or accounts, as for
+            ``SignerServer``.
+        key_management: Whether ``AddKey`` and ``RemoveKey`` are allowed. Anyone who
+            can reach the server can then add or remove keys, so leave it off unless
+            the channel is authenticated.
+    """
+
+    def __init__(self, accounts: Iterable[Any] = (), *, key_management: bool = False):
+        self._accounts: Dict[str, Any] = {}
+        self._lock = threading.Lock()
+        self._key_management = key_management
+        for signer in accounts:
+            self._add(_account(signer))
+
+    def _add(self, account: Any) -> str:
+        with self._lock:
+            self._accounts[account.address.lower()] = account
+        prometheus.watch_backend(account)
+        return account.address
+
+    def _signer(self, address: str) -> Any:
+        with self._lock:
+            account = self._accounts.get(address.lower())
+        if account is None:
+            raise _Status("NOT_FOUND", f"No key for {address}")
+        return account
+
+    def _check_key_management(self) -> None:
+        if not self._key_management:
+            raise _Status("PERMISSION_DENIED", "Key management is disabled")
+
+    def ListKeys(self, request: Dict[str, Any]) -> Dict[str, Any]:
+        with self._lock:
+            accounts = list(self._accounts.values())
+        return {"addresses": [account.address for account in accounts]}
+
+    def AddKey(self, request: Dict[str, Any]) -> Dict[str, Any]:
+        self._check_key_management()
+        sources = [request["private_key"], request["keystore_json"], request["signer"]]
+        if sum(1 for source in sources if source) != 1:
+            raise _Status(
+                "INVALID_ARGUMENT",
+                "Set exactly one of private_key, keystore_json and signer",
+            )
+        if request["private_key"]:
+            account = Account.from_key(request["private_key"])
+        elif request["keystore_json"]:
+            keystore = json.loads(request["keystore_json"])
+            account = Account.from_key(Account.decrypt(keystore, request["password"]))
+        else:
+            options = json.loads(request["signer_options_json"] or "{}")
+            account = signer_account(request["signer"], **options)
+        return {"address": self._add(account)}
+
+    def RemoveKey(self, request: Dict[str, Any]) -> Dict[str, Any]:
+        self._check_key_management()
+        with self._lock:
+            removed = self._accounts.pop(request["address"].lower(), None)
+        return {"removed": removed is not None}
+
+    def SignHash(self, request: Dict[str, Any]) -> Dict[str, Any]:
+        account = self._signer(request["address"])
+        if len(request["hash"]) != 32:
+            raise _Status("INVALID_ARGUMENT", "Hash must be exactly 32 bytes")
+        if hasattr(account, "key"):
+            signed = _account_sign_hash_wrapper(request["hash"], account.key)
+        else:
+            signed = prometheus.signed_with(
+                account, lambda: account.unsafe_sign_hash(request["hash"])
+            )
+        return _signature(signed)
+
+    def SignTypedData(self, request: Dict[str, Any]) -> Dict[str, Any]:
+        account = self._signer(request["address"])
+        typed_data = json.loads(request["typed_data_json"])
+        signed = prometheus.signed_with(
+            account, lambda: account.sign_typed_data(full_message=typed_data)
+        )
+        return _signature(signed)
+
+    def SignTransaction(self, request: Dict[str, Any]) -> Dict[str, Any]:
+        account = self._signer(request["address"])
+        tx = json.loads(request["transaction_json"])
+        if not isinstance(tx, dict):
+            raise _Status("INVALID_ARGUMENT", "Transaction must be a JSON object")
+        sender = tx.pop("from", None)
+        if sender is not None and str(sender).lower() != account.address.lower():
+            message = f"Transaction is from {sender}, not {account.address}"
+            raise _Status("INVALID_ARGUMENT", message)
+        fields = _transaction_fields(tx)
+        signed = prometheus.signed_with(
+            account, lambda: account.sign_transaction(fields)
+        )
+        return {
+            "raw_transaction": bytes(signed.raw_transaction),
+            "hash": bytes(signed.hash),
+            "r": signed.r.to_bytes(32, "big"),
+            "s": signed.s.to_bytes(32, "big"),
+            "v": signed.v,
+        }
+
+    def _handler(self, method: str) -> Callable[[Dict[str, Any], Any], Dict[str, Any]]:
+        def handle(request: Dict[str, Any], context: Any) -> Dict[str, Any]:
+            try:
+                call = getattr(self, method)
+                return prometheus.timed("grpc", method, lambda: call(request))
+            except Exception as e:
+                code, details = _status(e)
+                # Raises, ending the call with the status
+                context.abort(_grpc().StatusCode[code], details)
+                raise
+
+        return handle
+
+    def add_to_server(self, server: Any) -> None:
+        """Registers the service with a ``grpc.Server``."""
+        grpc = _grpc()
+        handlers = {}
+        for method, (request, response) in _METHODS.items():
+            handlers[method] = grpc.unary_unary_rpc_method_handler(
+                self._handler(method),
+                request_deserializer=lambda data, name=request: decode_message(
+                    name, data
+                ),
+                response_serializer=lambda fields, name=response: encode_message(
+                    name, fields
+                ),
+            )
+        server.add_generic_rpc_handlers(
+            (grpc.method_handlers_generic_handler(SERVICE_NAME, handlers),)
+        )
+
+
+def serve_grpc(
+    accounts: Iterable[Any] = (),
+    address: str = DEFAULT_ADDRESS,
+    *,
+    credentials: Optional[Any] = None,
+    key_management: bool = False,
+    max_workers: int = 8,
+) -> Any:
+    """
+    Starts a gRPC server running the signer service.
+
+    Args:
+        accounts: The keys to serve, as private keys or accounts.
+        address: The ``host:port`` to listen on.
+        credentials: ``grpc.ServerCredentials`` for TLS, such as
+            ``grpc.ssl_server_credentials`` with client certificates required. The
+            server listens without TLS if not given.
+        key_management: Whether clients may add and remove keys.
+        max_workers: Number of threads handling calls.
+
+    Returns:
+        The started ``grpc.Server``; call its ``stop`` to shut it down.
+    """
+    grpc = _grpc()
+    server = grpc.server(ThreadPoolExecutor(max_workers=max_workers))
+    SignerService(accounts, key_management=key_management).add_to_server(server)
+    if credentials is None:
+        server.add_insecure_port(address)
+    else:
+        server.add_secure_port(address, credentials)
+    server.start()
+    return server
@@ -0,0 +1,168 @@
+/*!
+Guard against signing high-risk EIP-712 messages.
+
+Some typed data grants spending rights rather than just authenticating a message: an
+ERC-2612 `Permit` or a Permit2 `PermitSingle` lets whoever holds the signature move the
+signer's tokens. Wallets single these out for extra confirmation, and so does ferrite.
+By default, signing one of these primary types emits a `DangerousTypedDataWarning`;
+`set_typed_data_guard` can make it an error instead, or turn the guard off, and lists
+the domains expected to receive them.
+
+A Permit's digest, computed from the typed data elsewhere, could otherwise be signed as
+a bare hash without the guard ever seeing the message. In block mode, bare hashes other
+than of EIP-191 personal messages are therefore refused too, unless the guard is told
+to allow them.
+*/
+
+use std::sync::RwLock;
+
+use ethers_core::types::transaction::eip712::{EIP712Domain, Types};
+use pyo3::prelude::*;
+
+use crate::{errors, interpreter};
+use crate::policy::{self, Domain};
+use crate::request::Fields;
+
+/// Primary types whose signature can grant a third party control over assets.
+const DANGEROUS_TYPES: [&str; 12] = [
+    // ERC-2612 and ERC-4494
+    "Permit",
+    "PermitForAll",
+    // Permit2
+    "PermitSingle",
+    "PermitBatch",
+    "PermitTransferFrom",
+    "PermitBatchTransferFrom",
+    "PermitWitnessTransferFrom",
+    "PermitBatchWitnessTransferFrom",
+    // EIP-3009 transfers, which move funds as soon as anyone submits them
+    "TransferWithAuthorization",
+    "ReceiveWithAuthorization",
+    // Operator approvals over a whole collection
+    "SetApprovalForAll",
+    "ApprovalForAll",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Off,
+    Warn,
+    Block,
+}
+
+struct Guard {
+    mode: Mode,
+    allow: Vec<Domain>,
+    allow_raw_hashes: bool,
+}
+
+static GUARD: RwLock<Guard> =
+    RwLock::new(Guard { mode: Mode::Warn, allow: Vec::new(), allow_raw_hashes: false });
+
+/// Returns the type whose contents a signature of `primary_type` authorizes, looking
+/// through an ERC-7739 `TypedDataSign` wrapper to the struct it nests, which a smart
+/// account accepts as if it had been signed directly.
+fn signed_type<'a>(types: &'a Types, primary_type: &'a str) -> &'a str {
+    if primary_type != "TypedDataSign" {
+        return primary_type;
+    }
+    types
+        .get(primary_type)
+        .and_then(|fields| fields.iter().find(|field| field.name == "contents"))
+        .map_or(primary_type, |contents| &contents.r#type)
+}
+
+/// Warns about or refuses signing `primary_type` for `domain` if it is high-risk.
+///
+/// May be called without the GIL; it is only taken to emit a warning.
+pub fn check(domain: &EIP712Domain, types: &Types, primary_type: &str) -> PyResult<()> {
+    let primary_type = signed_type(types, primary_type);
+    if !DANGEROUS_TYPES.contains(&primary_type) {
+        return Ok(());
+    }
+
+    let guard = GUARD.read().unwrap_or_else(|e| e.into_inner());
+    if guard.mode == Mode::Off || guard.allow.iter().any(|allowed| allowed.matches(domain)) {
+        return Ok(());
+    }
+
+    let message = format!(
+        "Signing `{}` typed data for domain {} can let a third party move the signer's \
+         assets; allow the domain with set_typed_data_guard if this is intended",
+        primary_type,
+        policy::describe(domain)
+    );
+    match guard.mode {
+        Mode::Block => Err(PyErr::new::<errors::PolicyViolationError, _>(message)),
+        _ => {
+            drop(guard);
+            interpreter::with_gil(|py| {
+                let category = py.get_type::<errors::DangerousTypedDataWarning>();
+                PyErr::warn(py, &category, &std::ffi::CString::new(message)?, 1)
+            })
+        }
+    }
+}
+
+/// Refuses to sign a bare hash while the guard blocks high-risk typed data, unless it
+/// allows them, since the hash may be of such typed data.
+///
+/// Callers that know the hash is of an EIP-191 personal message, with
+/// [`policy::is_personal_message`], don't need to check it.
+pub fn check_raw_hash() -> PyResult<()> {
+    let guard = GUARD.read().unwrap_or_else(|e| e.into_inner());
+    if guard.mode != Mode::Block || guard.allow_raw_hashes {
+        return Ok(());
+    }
+    Err(PyErr::new::<errors::PolicyViolationError, _>(
+        "Signing a bare hash while the typed data guard blocks high-risk typed data could \
+         sign a Permit it can't see; sign the typed data itself, or allow raw hashes with \
+         set_typed_data_guard",
+    ))
+}
+
+/// Configures the guard on high-risk typed data such as `Permit`.
+///
+/// # Arguments
+/// * `mode` - `"warn"` (the default) to emit a `DangerousTypedDataWarning`, `"block"`
+///   to raise `PolicyViolationError` instead, or `"off"`.
+/// * `allow` - JSON array of domains that may receive high-risk typed data without
+///   either, as objects of `name`, `chainId` and `verifyingContract`. Fields left out
+///   match any value.
+/// * `allow_raw_hashes` - Whether bare hashes may still be signed in block mode,
+///   unchecked.
+#[pyfunction]
+#[pyo3(signature = (mode = "warn", *, allow = None, allow_raw_hashes = false))]
+pub fn set_typed_data_guard(
+    mode: &str,
+    allow: Option<&str>,
+    allow_raw_hashes: bool,
+) -> PyResult<()> {
+    let invalid = |message: String| {
+        PyErr::new::<errors::FerriteError, _>(format!("Invalid typed data guard: {}", message))
+    };
+
+    let mode = match mode {
+        "off" => Mode::Off,
+        "warn" => Mode::Warn,
+        "block" => Mode::Block,
+        other => {
+            return Err(invalid(format!(
+                "mode must be \"warn\", \"block\" or \"off\", got {:?}",
+                other
+            )))
+        }
+    };
+    let allow = match allow {
+        Some(allow) => serde_json::from_str::<Vec<Fields>>(allow)
+            .map_err(|e| invalid(e.to_string()))?
+            .iter()
+            .map(Domain::parse)
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?,
+        None => Vec::new(),
+    };
+
+    *GUARD.write().unwrap_or_else(|e| e.into_inner()) = Guard { mode, allow, allow_raw_hashes };
+    Ok(())
+}
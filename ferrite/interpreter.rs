@@ -0,0 +1,295 @@
+/*!
+Per-interpreter state, so that ferrite can be imported in subinterpreters.
+
+ferrite declares multi-phase init, so each interpreter that imports it, the main one or
+a subinterpreter such as those mod_wsgi runs applications in, executes a module of its
+own. Python objects belong to the interpreter that created them, so everything holding
+one is kept per interpreter in a [`Local`]: the approval hook, the audit log and its
+context variable, the exception classes and the log level. State without Python objects
+in it, such as the signing policy, rate limits, metrics and the key cache, is shared by
+the whole process.
+
+Signing runs without the GIL, often on worker threads, and parts of it take the GIL back
+to call into Python. [`allow_threads`] remembers which interpreter the call came from,
+and [`with_gil`] attaches to that interpreter rather than the main one, with a thread
+state of its own when the thread has none there. An interpreter waits for the threads
+attached to it when it exits, and refuses new ones from then on.
+
+Subinterpreters that share the main interpreter's GIL are supported. Those with a GIL of
+their own are refused at import: PyO3 releases objects dropped without the GIL whenever
+it next takes it, in whichever interpreter that happens to be.
+
+The limited API only tells interpreters apart from Python 3.9, and Windows' python3.dll
+from 3.10, so builds for older versions see every interpreter as the main one, and are
+refused by subinterpreters that can check.
+*/
+
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::ffi;
+use pyo3::prelude::*;
+
+/// The main interpreter's id, which CPython never gives another.
+pub const MAIN: i64 = 0;
+
+/// Whether this build can tell interpreters apart.
+pub const SUPPORTED: bool = cfg!(all(Py_3_9, not(all(windows, Py_LIMITED_API, not(Py_3_10)))));
+
+#[cfg(all(Py_3_9, not(all(windows, Py_LIMITED_API, not(Py_3_10)))))]
+extern "C" {
+    // Part of the limited API since Python 3.9, but not bound by PyO3
+    fn PyThreadState_GetInterpreter(
+        state: *mut ffi::PyThreadState,
+    ) -> *mut ffi::PyInterpreterState;
+}
+
+/// An interpreter in this process.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Interpreter {
+    state: *mut ffi::PyInterpreterState,
+    id: i64,
+}
+
+// SAFETY: The pointer is only handed to CPython, once `Life` shows the interpreter is alive
+unsafe impl Send for Interpreter {}
+unsafe impl Sync for Interpreter {}
+
+/// An interpreter ferrite has been imported in, and the threads attached to it by
+/// [`Interpreter::with_gil`].
+struct Life {
+    interpreter: Interpreter,
+    attached: usize,
+    exiting: bool,
+}
+
+static LIVES: Mutex<Vec<Life>> = Mutex::new(Vec::new());
+static DETACHED: Condvar = Condvar::new();
+
+/// Module objects per interpreter, whose state is dropped along with the last one.
+static MODULES: Local<AtomicUsize> = Local::new();
+
+thread_local! {
+    /// The interpreter whose call this thread is working on.
+    static CALLING: Cell<Option<Interpreter>> = const { Cell::new(None) };
+}
+
+impl Interpreter {
+    /// Returns the interpreter the GIL is held in.
+    pub fn current(_py: Python) -> Self {
+        #[cfg(all(Py_3_9, not(all(windows, Py_LIMITED_API, not(Py_3_10)))))]
+        // SAFETY: The GIL is held, so there is a current interpreter
+        unsafe {
+            let state = ffi::PyInterpreterState_Get();
+            Interpreter { state, id: ffi::PyInterpreterState_GetID(state) }
+        }
+        #[cfg(not(all(Py_3_9, not(all(windows, Py_LIMITED_API, not(Py_3_10))))))]
+        Interpreter { state: std::ptr::null_mut(), id: MAIN }
+    }
+
+    pub fn id(self) -> i64 {
+        self.id
+    }
+
+    /// Runs `f` with the GIL held in this interpreter. Must be called without the GIL.
+    ///
+    /// Fails once the interpreter has started exiting, which waits for `f` to return.
+    pub fn with_gil<R>(self, f: impl FnOnce(Python) -> PyResult<R>) -> PyResult<R> {
+        self.attach()?;
+        // SAFETY: `attach` keeps the interpreter from exiting until `detach`
+        let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe { self.attached(f) }));
+        self.detach();
+        result.unwrap_or_else(|panic| panic::resume_unwind(panic))
+    }
+
+    /// Runs `f` with the GIL held in this interpreter, which a thread without the GIL
+    /// has been attached to.
+    ///
+    /// A thread with no thread state in this interpreter is given one for the call.
+    /// PyO3 doesn't know the GIL is held when the thread already has a thread state
+    /// elsewhere, so `f` has to drop the objects it makes as `Bound` rather than `Py`,
+    /// which would be released later.
+    unsafe fn attached<R>(self, f: impl FnOnce(Python) -> PyResult<R>) -> PyResult<R> {
+        let owned = ffi::PyGILState_GetThisThreadState();
+        #[cfg(all(Py_3_9, not(all(windows, Py_LIMITED_API, not(Py_3_10)))))]
+        let owner = (!owned.is_null()).then(|| PyThreadState_GetInterpreter(owned));
+        #[cfg(not(all(Py_3_9, not(all(windows, Py_LIMITED_API, not(Py_3_10))))))]
+        let owner = (!owned.is_null()).then_some(self.state);
+        if owner.map_or(self.id == MAIN, |owner| owner == self.state) {
+            return Python::with_gil(f);
+        }
+
+        let state = ffi::PyThreadState_New(self.state);
+        ffi::PyEval_RestoreThread(state);
+        // A thread that had no thread state at all has this one as its own, which PyO3
+        // can take the GIL with
+        let result = panic::catch_unwind(AssertUnwindSafe(|| match owner {
+            None => Python::with_gil(f),
+            Some(_) => f(Python::assume_gil_acquired()),
+        }));
+        ffi::PyThreadState_Clear(state);
+        ffi::PyEval_ReleaseThread(state);
+        ffi::PyThreadState_Delete(state);
+        result.unwrap_or_else(|panic| panic::resume_unwind(panic))
+    }
+
+    fn attach(self) -> PyResult<()> {
+        let mut lives = LIVES.lock().unwrap_or_else(|e| e.into_inner());
+        match lives.iter_mut().find(|life| life.interpreter == self && !life.exiting) {
+            Some(life) => {
+                life.attached += 1;
+                Ok(())
+            }
+            None => Err(PyRuntimeError::new_err(
+                "The interpreter that made this call is exiting",
+            )),
+        }
+    }
+
+    fn detach(self) {
+        let mut lives = LIVES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(life) = lives.iter_mut().find(|life| life.interpreter == self) {
+            life.attached -= 1;
+        }
+        DETACHED.notify_all();
+    }
+}
+
+/// Returns the interpreter whose call this thread is working on, if known.
+pub fn calling() -> Option<Interpreter> {
+    CALLING.get()
+}
+
+/// Runs `f` as working on a call from `interpreter`, as worker threads that take over
+/// part of a call do.
+pub fn enter<R>(interpreter: Option<Interpreter>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Interpreter>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CALLING.set(self.0);
+        }
+    }
+
+    let _restore = Restore(CALLING.replace(interpreter));
+    f()
+}
+
+/// Releases the GIL to run `f`, as [`Python::allow_threads`] does, remembering the
+/// interpreter for [`with_gil`].
+pub fn allow_threads<T, F>(py: Python, f: F) -> T
+where
+    F: Send + FnOnce() -> T,
+    T: Send,
+{
+    let interpreter = Interpreter::current(py);
+    py.allow_threads(|| enter(Some(interpreter), f))
+}
+
+/// Runs `f` with the GIL held in the interpreter whose call this thread is working on,
+/// or the main interpreter if that isn't known. Must be called without the GIL.
+pub fn with_gil<R>(f: impl FnOnce(Python) -> PyResult<R>) -> PyResult<R> {
+    match calling() {
+        Some(interpreter) => interpreter.with_gil(f),
+        None => Python::with_gil(f),
+    }
+}
+
+/// Counts a module object created in the interpreter the GIL is held in, the first of
+/// which has the interpreter wait for attached threads when it exits.
+pub fn open_module(py: Python) -> PyResult<()> {
+    MODULES.get(py).fetch_add(1, Ordering::Relaxed);
+
+    let interpreter = Interpreter::current(py);
+    let mut lives = LIVES.lock().unwrap_or_else(|e| e.into_inner());
+    if lives.iter().any(|life| life.interpreter == interpreter) {
+        return Ok(());
+    }
+    lives.push(Life { interpreter, attached: 0, exiting: false });
+    drop(lives);
+    py.import("atexit")?.call_method1("register", (wrap_pyfunction!(exit, py)?,))?;
+    Ok(())
+}
+
+/// Counts a module object freed in the interpreter the GIL is held in, returning
+/// whether it was the last one, so that the interpreter's state should be dropped.
+pub fn close_module(py: Python) -> bool {
+    let closed = MODULES.get(py).fetch_sub(1, Ordering::Relaxed) == 1;
+    if closed {
+        MODULES.take(py);
+    }
+    closed
+}
+
+/// Waits for the threads attached to the exiting interpreter, refusing new ones.
+#[pyfunction]
+fn exit(py: Python) {
+    let interpreter = Interpreter::current(py);
+    let attached = |lives: &mut Vec<Life>| {
+        lives.iter().any(|life| life.interpreter == interpreter && life.attached > 0)
+    };
+    let mut lives = LIVES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(life) = lives.iter_mut().find(|life| life.interpreter == interpreter) {
+        life.exiting = true;
+    }
+
+    // Attached threads need the GIL to finish. It is only released for them, since a
+    // subinterpreter ended while the process exits can't take it back.
+    if !attached(&mut lives) {
+        lives.retain(|life| life.interpreter != interpreter);
+        return;
+    }
+    drop(lives);
+    py.allow_threads(|| {
+        let lives = LIVES.lock().unwrap_or_else(|e| e.into_inner());
+        let mut lives = DETACHED.wait_while(lives, attached).unwrap_or_else(|e| e.into_inner());
+        lives.retain(|life| life.interpreter != interpreter);
+    });
+}
+
+/// A value kept for each interpreter, created on first use in it.
+pub struct Local<T> {
+    values: Mutex<Vec<(i64, Arc<T>)>>,
+}
+
+impl<T: Default> Local<T> {
+    pub const fn new() -> Self {
+        Local { values: Mutex::new(Vec::new()) }
+    }
+
+    /// Returns the value for the interpreter the GIL is held in.
+    pub fn get(&self, py: Python) -> Arc<T> {
+        let id = Interpreter::current(py).id;
+        let mut values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, value)) = values.iter().find(|(of, _)| *of == id) {
+            return value.clone();
+        }
+        let value = Arc::new(T::default());
+        values.push((id, value.clone()));
+        value
+    }
+
+    /// Returns the value for the interpreter with id `id`, if it has one.
+    pub fn of(&self, id: i64) -> Option<Arc<T>> {
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        values.iter().find(|(of, _)| *of == id).map(|(_, value)| value.clone())
+    }
+
+    /// Returns every interpreter's value.
+    pub fn all(&self) -> Vec<Arc<T>> {
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        values.iter().map(|(_, value)| value.clone()).collect()
+    }
+
+    /// Removes the value for the interpreter the GIL is held in, once its last module
+    /// object has been freed.
+    pub fn take(&self, py: Python) -> Option<Arc<T>> {
+        let id = Interpreter::current(py).id;
+        let mut values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        let at = values.iter().position(|(of, _)| *of == id)?;
+        Some(values.remove(at).1)
+    }
+}
@@ -0,0 +1,51 @@
+/*!
+Keccak-256 for Python.
+
+The backend, `tiny-keccak` or the assembly one of the `asm-keccak` feature, is selected
+in `ferrite_core::keccak`. The same digest is exposed to Python as `keccak`, so services
+can drop pysha3 or eth-hash once they depend on ferrite.
+*/
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyMemoryView};
+
+pub use ferrite_core::keccak::keccak256;
+
+/// Inputs at least this long are hashed with the GIL released; for shorter ones,
+/// releasing and reacquiring it costs more than the hash.
+const RELEASE_GIL_LEN: usize = 4096;
+
+/// Computes the keccak256 digest of a bytes-like object.
+///
+/// # Arguments
+/// * `data` - Any object supporting the buffer protocol, such as `bytes`, `bytearray`
+///   or `memoryview`.
+///
+/// # Returns
+/// The 32-byte digest.
+#[pyfunction]
+pub fn keccak(py: Python, data: &Bound<PyAny>) -> PyResult<PyObject> {
+    // `bytes` are immutable and hashed in place; any other buffer could be resized or
+    // written to once the GIL is released, so it's copied first
+    let data = match data.downcast::<PyBytes>() {
+        Ok(bytes) => bytes.clone(),
+        Err(_) => PyMemoryView::from(data)
+            .map_err(|_| {
+                let type_name = data.get_type().name().map(|n| n.to_string());
+                PyTypeError::new_err(format!(
+                    "keccak() argument must be a bytes-like object, not '{}'",
+                    type_name.unwrap_or_default()
+                ))
+            })?
+            .call_method0("tobytes")?
+            .downcast_into()?,
+    };
+    let data = data.as_bytes();
+    let digest = if data.len() >= RELEASE_GIL_LEN {
+        py.allow_threads(|| keccak256(data))
+    } else {
+        keccak256(data)
+    };
+    Ok(PyBytes::new(py, &digest).into_any().unbind())
+}
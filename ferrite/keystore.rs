@@ -0,0 +1,509 @@
+/*!
+Web3 Secret Storage (V3 keystore) encryption and decryption.
+
+Key derivation dominates the cost of loading a keystore: the scrypt parameters used by
+geth and eth-account allocate 256 MiB and take hundreds of milliseconds per file.
+Batches of keystores are derived in parallel, and so are the independent lanes of a
+single scrypt derivation when its `p` parameter is above one. All of it runs on a
+dedicated pool, sized with `set_keystore_threads`, so that memory-hungry derivations
+never compete with signing work on the global pool.
+
+Every buffer holding a password, derived key or decrypted private key is wiped when
+dropped, including the scrypt working memory derived from the password.
+
+Keystores may come from untrusted sources, so their KDF parameters are bounded before
+anything is allocated: scrypt's `128 * r * n * p` bytes of working memory, PBKDF2's
+iteration count and the derived key length. `set_keystore_limits` adjusts the first two.
+*/
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use ethers_signers::Signer;
+use ferrite_core::signing;
+use hmac::Hmac;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyList};
+use rand::RngCore;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use crate::errors;
+use crate::keccak::keccak256;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// Defaults used by eth-account when creating new keystores.
+const SCRYPT_N: u32 = 262_144;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const PBKDF2_ITERATIONS: u32 = 1_000_000;
+const DKLEN: u32 = 32;
+
+/// Default bounds on the KDF parameters of a keystore, four and ten times eth-account's
+/// defaults respectively.
+const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+/// Longest derived key accepted. Only the first 32 bytes are ever used.
+const MAX_DKLEN: u32 = 1024;
+
+static SCRYPT_MEMORY_LIMIT: AtomicU64 = AtomicU64::new(MAX_SCRYPT_MEMORY);
+static PBKDF2_ITERATION_LIMIT: AtomicU32 = AtomicU32::new(MAX_PBKDF2_ITERATIONS);
+
+/// Pool used for key derivation. Built on first use unless configured beforehand.
+static POOL: Mutex<Option<Arc<ThreadPool>>> = Mutex::new(None);
+
+#[derive(Serialize, Deserialize)]
+struct Keystore {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    #[serde(alias = "Crypto")]
+    crypto: Crypto,
+    #[serde(default)]
+    id: String,
+    version: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Crypto {
+    cipher: String,
+    cipherparams: CipherParams,
+    #[serde(serialize_with = "to_hex", deserialize_with = "from_hex")]
+    ciphertext: Vec<u8>,
+    kdf: String,
+    kdfparams: KdfParams,
+    #[serde(serialize_with = "to_hex", deserialize_with = "from_hex")]
+    mac: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CipherParams {
+    #[serde(serialize_with = "to_hex", deserialize_with = "from_hex")]
+    iv: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum KdfParams {
+    Pbkdf2 {
+        c: u32,
+        dklen: u32,
+        prf: String,
+        #[serde(serialize_with = "to_hex", deserialize_with = "from_hex")]
+        salt: Vec<u8>,
+    },
+    Scrypt {
+        dklen: u32,
+        n: u32,
+        r: u32,
+        p: u32,
+        #[serde(serialize_with = "to_hex", deserialize_with = "from_hex")]
+        salt: Vec<u8>,
+    },
+}
+
+fn to_hex<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(bytes))
+}
+
+fn from_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    hex::decode(value.trim_start_matches("0x")).map_err(serde::de::Error::custom)
+}
+
+fn invalid(message: String) -> PyErr {
+    PyErr::new::<errors::FerriteError, _>(message)
+}
+
+/// Returns the key derivation pool, building it with the default size if needed.
+fn pool() -> PyResult<Arc<ThreadPool>> {
+    let mut pool = POOL.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(pool) = pool.as_ref() {
+        return Ok(pool.clone());
+    }
+
+    let built = Arc::new(build_pool(0)?);
+    *pool = Some(built.clone());
+    Ok(built)
+}
+
+fn build_pool(threads: usize) -> PyResult<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("ferrite-keystore-{}", i))
+        .build()
+        .map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Failed to create keystore thread pool: {}", e)
+            )
+        })
+}
+
+/// scrypt with its `p` independent lanes mixed in parallel.
+///
+/// The `scrypt` crate mixes lanes one after another and keeps ROMix private, so lanes
+/// are split out here. Single-lane derivations go straight to the crate.
+fn scrypt(
+    password: &[u8],
+    salt: &[u8],
+    n: u32,
+    r: u32,
+    p: u32,
+    output: &mut [u8],
+) -> Result<(), String> {
+    if !n.is_power_of_two() || n < 2 {
+        return Err(format!("Invalid scrypt parameter n: {}", n));
+    }
+    let params = scrypt::Params::new(n.trailing_zeros() as u8, r, p)
+        .map_err(|e| format!("Invalid scrypt parameters: {}", e))?;
+
+    if p == 1 {
+        return scrypt::scrypt(password, salt, &params, output)
+            .map_err(|e| format!("Invalid scrypt parameters: {}", e));
+    }
+
+    let lane_len = r as usize * 128;
+    let mut lanes = Zeroizing::new(vec![0u8; p as usize * lane_len]);
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, 1, &mut lanes);
+
+    lanes
+        .par_chunks_mut(lane_len)
+        .for_each(|lane| ro_mix(lane, n as usize));
+
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password, &lanes, 1, output);
+    Ok(())
+}
+
+/// The scrypt ROMix function over a single lane, as specified in RFC 7914.
+fn ro_mix(lane: &mut [u8], n: usize) {
+    let len = lane.len();
+    let mut v = Zeroizing::new(vec![0u8; n * len]);
+    let mut t = Zeroizing::new(vec![0u8; len]);
+
+    for chunk in v.chunks_mut(len) {
+        chunk.copy_from_slice(lane);
+        block_mix(chunk, lane);
+    }
+
+    for _ in 0..n {
+        let tail = &lane[len - 64..len - 60];
+        let j = u32::from_le_bytes(tail.try_into().unwrap()) as usize & (n - 1);
+        let block = &v[j * len..(j + 1) * len];
+        for ((t, x), y) in t.iter_mut().zip(lane.iter()).zip(block) {
+            *t = x ^ y;
+        }
+        block_mix(&t, lane);
+    }
+}
+
+/// The scrypt BlockMix function with Salsa20/8 as its core.
+fn block_mix(input: &[u8], output: &mut [u8]) {
+    use salsa20::cipher::{typenum::U4, StreamCipherCore};
+
+    let mut x = [0u8; 64];
+    x.copy_from_slice(&input[input.len() - 64..]);
+
+    for (i, chunk) in input.chunks(64).enumerate() {
+        let mut state = [0u32; 16];
+        let words = x.chunks_exact(4).zip(chunk.chunks_exact(4));
+        for (word, (x, y)) in state.iter_mut().zip(words) {
+            *word = u32::from_le_bytes([x[0] ^ y[0], x[1] ^ y[1], x[2] ^ y[2], x[3] ^ y[3]]);
+        }
+        salsa20::SalsaCore::<U4>::from_raw_state(state)
+            .write_keystream_block((&mut x).into());
+
+        let pos = (i / 2) * 64 + (i % 2) * (input.len() / 2);
+        output[pos..pos + 64].copy_from_slice(&x);
+    }
+}
+
+/// Refuses KDF parameters that would take more memory or time than allowed.
+fn check_params(params: &KdfParams) -> Result<(), String> {
+    let dklen = match params {
+        KdfParams::Pbkdf2 { c, dklen, .. } => {
+            let limit = PBKDF2_ITERATION_LIMIT.load(Ordering::Relaxed);
+            if *c > limit {
+                return Err(format!("PBKDF2 iteration count {} exceeds the limit of {}", c, limit));
+            }
+            dklen
+        }
+        KdfParams::Scrypt { dklen, n, r, p, .. } => {
+            let limit = SCRYPT_MEMORY_LIMIT.load(Ordering::Relaxed);
+            let memory = 128 * u128::from(*r) * u128::from(*n) * u128::from(*p);
+            if memory > u128::from(limit) {
+                return Err(format!(
+                    "scrypt parameters n={}, r={}, p={} need {} bytes of memory, over the \
+                     limit of {}",
+                    n, r, p, memory, limit
+                ));
+            }
+            dklen
+        }
+    };
+    if *dklen > MAX_DKLEN {
+        return Err(format!("Keystore dklen must be at most {}, got {}", MAX_DKLEN, dklen));
+    }
+    Ok(())
+}
+
+/// Derives the encryption key described by `params`.
+fn derive_key(password: &[u8], params: &KdfParams) -> Result<Zeroizing<Vec<u8>>, String> {
+    check_params(params)?;
+    match params {
+        KdfParams::Pbkdf2 { c, dklen, prf, salt } => {
+            if prf != "hmac-sha256" {
+                return Err(format!("Unsupported PBKDF2 PRF: {}", prf));
+            }
+            let mut key = Zeroizing::new(vec![0u8; *dklen as usize]);
+            pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, *c, &mut key);
+            Ok(key)
+        }
+        KdfParams::Scrypt { dklen, n, r, p, salt } => {
+            let mut key = Zeroizing::new(vec![0u8; *dklen as usize]);
+            scrypt(password, salt, *n, *r, *p, &mut key)?;
+            Ok(key)
+        }
+    }
+}
+
+fn mac(derived_key: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    let mut input = Zeroizing::new(Vec::with_capacity(16 + ciphertext.len()));
+    input.extend_from_slice(&derived_key[16..32]);
+    input.extend_from_slice(ciphertext);
+    keccak256(&input)
+}
+
+/// Decrypts a V3 keystore JSON string. Does not touch the GIL.
+pub(crate) fn decrypt(keystore: &str, password: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let keystore: Keystore = serde_json::from_str(keystore)
+        .map_err(|e| format!("Invalid keystore JSON: {}", e))?;
+    if keystore.version != 3 {
+        return Err(format!(
+            "Unsupported keystore version: {}",
+            keystore.version
+        ));
+    }
+
+    let crypto = keystore.crypto;
+    match (crypto.kdf.as_str(), &crypto.kdfparams) {
+        ("pbkdf2", KdfParams::Pbkdf2 { .. }) | ("scrypt", KdfParams::Scrypt { .. }) => {}
+        (kdf, _) => return Err(format!("Unsupported or mismatched KDF: {}", kdf)),
+    }
+    if crypto.cipher != "aes-128-ctr" {
+        return Err(format!("Unsupported cipher: {}", crypto.cipher));
+    }
+    if crypto.cipherparams.iv.len() != 16 {
+        return Err("Keystore IV must be 16 bytes".to_string());
+    }
+
+    let key = derive_key(password, &crypto.kdfparams)?;
+    if key.len() < 32 {
+        return Err("Keystore dklen must be at least 32".to_string());
+    }
+    // Compare in constant time, so that timing doesn't reveal how much of a forged MAC
+    // is right
+    if !bool::from(mac(&key, &crypto.ciphertext).ct_eq(&crypto.mac[..])) {
+        return Err("MAC mismatch".to_string());
+    }
+
+    let mut private_key = Zeroizing::new(crypto.ciphertext);
+    Aes128Ctr::new(key[..16].into(), crypto.cipherparams.iv[..].into())
+        .apply_keystream(&mut private_key);
+
+    Ok(private_key)
+}
+
+/// Encrypts a private key into a V3 keystore JSON string. Does not touch the GIL.
+fn encrypt(
+    private_key: &[u8],
+    password: &[u8],
+    kdf: &str,
+    iterations: Option<u32>,
+) -> PyResult<String> {
+    // Parsed without the key cache, so that encrypting a key doesn't leave it resident
+    let address = signing::wallet(private_key).map_err(errors::from_core)?.address();
+
+    let mut rng = rand::thread_rng();
+    let mut salt = vec![0u8; 16];
+    let mut iv = vec![0u8; 16];
+    let mut id = [0u8; 16];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut iv);
+    rng.fill_bytes(&mut id);
+
+    let kdfparams = match kdf {
+        "scrypt" => KdfParams::Scrypt {
+            dklen: DKLEN,
+            n: iterations.unwrap_or(SCRYPT_N),
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt,
+        },
+        "pbkdf2" => KdfParams::Pbkdf2 {
+            c: iterations.unwrap_or(PBKDF2_ITERATIONS),
+            dklen: DKLEN,
+            prf: "hmac-sha256".to_string(),
+            salt,
+        },
+        _ => return Err(invalid(format!("Unsupported KDF: {}", kdf))),
+    };
+
+    let key = derive_key(password, &kdfparams).map_err(invalid)?;
+
+    let mut ciphertext = private_key.to_vec();
+    Aes128Ctr::new(key[..16].into(), iv[..].into()).apply_keystream(&mut ciphertext);
+
+    // Random (version 4) UUID
+    id[6] = (id[6] & 0x0f) | 0x40;
+    id[8] = (id[8] & 0x3f) | 0x80;
+    let id = hex::encode(id);
+
+    let keystore = Keystore {
+        address: Some(hex::encode(address)),
+        crypto: Crypto {
+            cipher: "aes-128-ctr".to_string(),
+            cipherparams: CipherParams { iv },
+            mac: mac(&key, &ciphertext).to_vec(),
+            ciphertext,
+            kdf: kdf.to_string(),
+            kdfparams,
+        },
+        id: format!(
+            "{}-{}-{}-{}-{}",
+            &id[..8],
+            &id[8..12],
+            &id[12..16],
+            &id[16..20],
+            &id[20..]
+        ),
+        version: 3,
+    };
+
+    serde_json::to_string(&keystore)
+        .map_err(|e| invalid(format!("Failed to serialize keystore: {}", e)))
+}
+
+/// Decrypts a V3 keystore.
+///
+/// # Arguments
+/// * `keystore` - Keystore JSON string.
+/// * `password` - Keystore password as bytes.
+///
+/// # Returns
+/// The 32-byte raw private key.
+#[pyfunction]
+pub fn decrypt_keystore(py: Python, keystore: &str, password: &[u8]) -> PyResult<PyObject> {
+    let pool = pool()?;
+    let private_key = py
+        .allow_threads(|| pool.install(|| decrypt(keystore, password)))
+        .map_err(invalid)?;
+
+    Ok(PyBytes::new(py, &private_key).into_any().unbind())
+}
+
+/// Decrypts a batch of V3 keystores in parallel.
+///
+/// # Arguments
+/// * `keystores` - Keystore JSON strings.
+/// * `passwords` - One password per keystore, as bytes.
+///
+/// # Returns
+/// A list of 32-byte raw private keys, in input order.
+#[pyfunction]
+pub fn decrypt_keystores(
+    py: Python,
+    keystores: Vec<String>,
+    passwords: Vec<Bound<PyBytes>>,
+) -> PyResult<PyObject> {
+    if keystores.len() != passwords.len() {
+        return Err(invalid(format!(
+            "Got {} keystores but {} passwords",
+            keystores.len(),
+            passwords.len()
+        )));
+    }
+    let passwords = passwords
+        .iter()
+        .map(|password| Zeroizing::new(password.as_bytes().to_vec()))
+        .collect::<Vec<_>>();
+
+    let pool = pool()?;
+    let private_keys = py.allow_threads(|| {
+        pool.install(|| {
+            keystores
+                .par_iter()
+                .zip(&passwords)
+                .enumerate()
+                .map(|(i, (keystore, password))| {
+                    decrypt(keystore, password)
+                        .map_err(|e| format!("Keystore at index {}: {}", i, e))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+    })
+    .map_err(invalid)?;
+
+    let results = private_keys
+        .iter()
+        .map(|private_key| PyBytes::new(py, private_key))
+        .collect::<Vec<_>>();
+
+    Ok(PyList::new(py, results)?.into_any().unbind())
+}
+
+/// Encrypts a private key into a V3 keystore.
+///
+/// # Arguments
+/// * `private_key` - 32-byte raw private key.
+/// * `password` - Keystore password as bytes.
+/// * `kdf` - Either `"scrypt"` or `"pbkdf2"`.
+/// * `iterations` - scrypt `n` or PBKDF2 iteration count. Defaults to eth-account's.
+///
+/// # Returns
+/// The keystore as a JSON string.
+#[pyfunction]
+#[pyo3(signature = (private_key, password, kdf = "scrypt", iterations = None))]
+pub fn encrypt_keystore(
+    py: Python,
+    private_key: &[u8],
+    password: &[u8],
+    kdf: &str,
+    iterations: Option<u32>,
+) -> PyResult<String> {
+    let pool = pool()?;
+    py.allow_threads(|| pool.install(|| encrypt(private_key, password, kdf, iterations)))
+}
+
+/// Sets the number of threads used for keystore key derivation.
+///
+/// Each concurrent scrypt derivation with eth-account's default parameters holds
+/// 256 MiB, so this also bounds peak memory. `0` picks one thread per CPU.
+#[pyfunction]
+pub fn set_keystore_threads(threads: usize) -> PyResult<()> {
+    let built = Arc::new(build_pool(threads)?);
+    *POOL.lock().unwrap_or_else(|e| e.into_inner()) = Some(built);
+    Ok(())
+}
+
+/// Bounds the KDF parameters keystores may use.
+///
+/// Decrypting or encrypting a keystore beyond either limit raises `FerriteError` instead
+/// of allocating the memory or spending the time.
+///
+/// # Arguments
+/// * `max_memory` - Most bytes of working memory a scrypt derivation may take,
+///   `128 * r * n * p`. Defaults to 1 GiB.
+/// * `max_iterations` - Highest PBKDF2 iteration count. Defaults to 10,000,000.
+#[pyfunction]
+#[pyo3(signature = (
+    *, max_memory = MAX_SCRYPT_MEMORY, max_iterations = MAX_PBKDF2_ITERATIONS
+))]
+pub fn set_keystore_limits(max_memory: u64, max_iterations: u32) {
+    SCRYPT_MEMORY_LIMIT.store(max_memory, Ordering::Relaxed);
+    PBKDF2_ITERATION_LIMIT.store(max_iterations, Ordering::Relaxed);
+}
@@ -4,66 +4,572 @@ Ferrite: A high-performance Rust-based signer for eth-account.
 This crate provides a Rust-based signer for eth-account, exposed to Python via PyO3.
 */
 
-use ethers_core::types::transaction::eip712::{Eip712, TypedData};
-use ethers_core::types::{H256, TransactionRequest};
+use std::ffi::c_void;
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::Once;
+use std::time::Instant;
+
+use ethers_core::types::transaction::eip712::{Eip712, EIP712Domain, Types};
+use ethers_core::types::{Address, Signature, H256};
 use ethers_signers::{LocalWallet, Signer};
+use ferrite_core::{eip712, pool, request, signing};
 use pyo3::prelude::*;
-use pyo3::types::{PyBytes, PyDict};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::ffi;
+use pyo3::panic::PanicException;
+use pyo3::types::{PyBytes, PyDict, PyList};
+#[cfg(any(not(Py_LIMITED_API), Py_3_11))]
+use pyo3::buffer::PyBuffer;
+#[cfg(all(Py_LIMITED_API, not(Py_3_11)))]
+use pyo3::types::{PyMemoryView, PySlice};
+use rayon::prelude::*;
+use zeroize::Zeroizing;
 
+mod abi;
+mod address;
+mod approval;
+mod asyncio;
+mod audit;
+mod bls;
+mod cache;
+pub mod cli;
+mod curve;
+mod ens;
+mod errors;
+mod guard;
+mod interpreter;
+mod keccak;
+mod keystore;
+mod logging;
+mod metrics;
+mod opstack;
+mod p256;
+mod policy;
+mod ratelimit;
+mod remote;
+mod rlp;
+#[cfg(feature = "rpc")]
+mod rpc;
+mod secure;
+mod ssz;
+#[cfg(feature = "stark")]
+mod stark;
+mod tx;
+mod userop;
 
-/// Signs a 32-byte hash with a private key.
-/// ... (keeping existing comments for brevity in thought, but need to be careful with replace)
-// Actually I will just update the imports and add the function at the end.
+/// The error for an output argument that isn't a bytes-like object.
+fn not_bytes_like(buffer: &Bound<PyAny>, name: &str) -> PyErr {
+    let type_name = buffer.get_type().name().map(|n| n.to_string());
+    PyTypeError::new_err(format!(
+        "{} must be a bytes-like object, not '{}'",
+        name,
+        type_name.unwrap_or_default()
+    ))
+}
+
+/// The error for an output buffer too small for `data`.
+fn buffer_too_small(name: &str, len: usize, data: &[u8]) -> PyErr {
+    PyValueError::new_err(format!(
+        "{} holds {} bytes, but {} are needed",
+        name,
+        len,
+        data.len()
+    ))
+}
+
+/// Copies `data` into the start of `buffer`, any writable, C-contiguous object
+/// supporting the buffer protocol with byte items.
+///
+/// The buffer is held through the copy, so it can't be resized or released while it is
+/// written, even by another thread on free-threaded builds.
+#[cfg(any(not(Py_LIMITED_API), Py_3_11))]
+fn write_into(buffer: &Bound<PyAny>, data: &[u8], name: &str) -> PyResult<()> {
+    let py = buffer.py();
+    let view = PyBuffer::<u8>::get(buffer).map_err(|_| not_bytes_like(buffer, name))?;
+    if view.readonly() {
+        return Err(PyTypeError::new_err(format!("{} must be a writable buffer", name)));
+    }
+    if !view.is_c_contiguous() {
+        return Err(PyTypeError::new_err(format!("{} must be C-contiguous", name)));
+    }
+    if view.len_bytes() < data.len() {
+        return Err(buffer_too_small(name, view.len_bytes(), data));
+    }
+    if let Some(cells) = view.as_mut_slice(py) {
+        for (cell, &byte) in cells.iter().zip(data) {
+            cell.set(byte);
+        }
+    }
+    view.release(py);
+    Ok(())
+}
+
+/// Copies `data` into the start of `buffer`, as above.
+///
+/// The limited API only exposes the buffer protocol from Python 3.11, so builds for
+/// older versions go through a memoryview instead.
+#[cfg(all(Py_LIMITED_API, not(Py_3_11)))]
+fn write_into(buffer: &Bound<PyAny>, data: &[u8], name: &str) -> PyResult<()> {
+    let py = buffer.py();
+    let view = PyMemoryView::from(buffer).map_err(|_| not_bytes_like(buffer, name))?;
+    if view.getattr("readonly")?.is_truthy()? {
+        return Err(PyTypeError::new_err(format!("{} must be a writable buffer", name)));
+    }
+    if !view.getattr("c_contiguous")?.is_truthy()? {
+        return Err(PyTypeError::new_err(format!("{} must be C-contiguous", name)));
+    }
+    if view.getattr("itemsize")?.extract::<usize>()? != 1 {
+        return Err(not_bytes_like(buffer, name));
+    }
+    if view.len()? < data.len() {
+        return Err(buffer_too_small(name, view.len()?, data));
+    }
+    let written = view.get_item(PySlice::new(py, 0, data.len() as isize, 1))?;
+    written.set_item(PySlice::full(py), PyBytes::new(py, data))
+}
+
+/// Returns `data` as a Python object, writing into `out` and returning it when the
+/// caller provided one instead of allocating a fresh `bytes`.
+fn bytes_or_into(
+    py: Python,
+    data: &[u8],
+    out: Option<&Bound<PyAny>>,
+    name: &str,
+) -> PyResult<PyObject> {
+    match out {
+        Some(buffer) => {
+            write_into(buffer, data, name)?;
+            Ok(buffer.clone().unbind())
+        }
+        None => Ok(PyBytes::new(py, data).into_any().unbind()),
+    }
+}
 
-// Re-declaring imports since I'm replacing the top block or inserting?
-// I'll replace the imports first.
+/// Builds the `r`, `s`, `v`, `y_parity`, `signature` dictionary shared by the signing
+/// functions.
+///
+/// `signature` is always the 65-byte `r || s || 27 + y_parity` form, whatever `v`
+/// convention the signed object uses. When `signature_out` is given, the signature is
+/// written into its first 65 bytes and the same object is returned under `signature`.
+fn signature_dict<'py>(
+    py: Python<'py>,
+    signature: &Signature,
+    signature_out: Option<&Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let result = PyDict::new(py);
+    let mut signature_bytes = [0u8; 65];
+    signature.r.to_big_endian(&mut signature_bytes[..32]);
+    result.set_item("r", PyBytes::new(py, &signature_bytes[..32]))?;
+
+    signature.s.to_big_endian(&mut signature_bytes[32..64]);
+    result.set_item("s", PyBytes::new(py, &signature_bytes[32..64]))?;
+
+    let y_parity = signing::y_parity(signature.v);
+    result.set_item("v", signature.v)?;
+    result.set_item("y_parity", y_parity)?;
+
+    signature_bytes[64] = 27 + y_parity as u8;
+    let signature_object = bytes_or_into(py, &signature_bytes, signature_out, "signature_out")?;
+    result.set_item("signature", signature_object)?;
+
+    Ok(result)
+}
+
+/// A signed transaction, as produced by [`signed_transaction`].
+struct SignedTransaction {
+    signature: Signature,
+    raw_transaction: pool::Buffer,
+    hash: H256,
+    record: audit::Record,
+}
+
+/// Builds the dictionary for a signed transaction: the [`signature_dict`] fields plus
+/// `rawTransaction` and `hash`.
+///
+/// When `raw_transaction_out` is given, the raw transaction is written into its start
+/// and the same object is returned under `rawTransaction`.
+fn transaction_dict<'py>(
+    py: Python<'py>,
+    signed: &SignedTransaction,
+    raw_transaction_out: Option<&Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let result = signature_dict(py, &signed.signature, None)?;
 
+    // rawTransaction
+    result.set_item(
+        "rawTransaction",
+        bytes_or_into(py, &signed.raw_transaction, raw_transaction_out, "raw_transaction_out")?,
+    )?;
+    // hash
+    result.set_item("hash", PyBytes::new(py, signed.hash.as_bytes()))?;
+
+    Ok(result)
+}
+
+/// Validates the optional `extra_entropy` argument of the signing functions.
+fn extra_entropy(extra_entropy: Option<&[u8]>) -> PyResult<Option<[u8; 32]>> {
+    extra_entropy
+        .map(|entropy| {
+            <[u8; 32]>::try_from(entropy).map_err(|_| {
+                PyErr::new::<errors::FerriteError, _>(
+                    format!("Extra entropy must be exactly 32 bytes, got {}", entropy.len())
+                )
+            })
+        })
+        .transpose()
+}
+
+/// Signs digests for a single address.
+///
+/// Implemented by keys held in memory and by [`remote::Remote`] signers, so that every
+/// signing path runs the same policy, approval, rate limit and audit steps whatever
+/// holds the key. The signing paths take a closure producing the signer, so that an
+/// invalid key is only reported after the payload has been validated.
+trait DigestSigner {
+    fn address(&self) -> Address;
+
+    /// Signs `hash`, returning a low-s signature with `v` set to the recovery id + 27.
+    ///
+    /// `preimage` returns the message `hash` is the keccak256 of, when known, for
+    /// signers that hash the message themselves.
+    fn sign(&self, hash: H256, preimage: impl FnOnce() -> Option<Vec<u8>>)
+        -> PyResult<Signature>;
+}
+
+/// A private key held in memory.
+struct LocalKey<'a> {
+    wallet: LocalWallet,
+    private_key: &'a [u8],
+    extra_entropy: Option<&'a [u8; 32]>,
+}
+
+impl<'a> LocalKey<'a> {
+    /// Parses `private_key`, storing its address in `address` for the caller's metrics.
+    fn new(
+        private_key: &'a [u8],
+        extra_entropy: Option<&'a [u8; 32]>,
+        address: &mut Option<Address>,
+    ) -> PyResult<Self> {
+        let wallet = cache::wallet_from_key(private_key)?;
+        *address = Some(wallet.address());
+        Ok(LocalKey { wallet, private_key, extra_entropy })
+    }
+}
+
+impl DigestSigner for LocalKey<'_> {
+    fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    fn sign(&self, hash: H256, _: impl FnOnce() -> Option<Vec<u8>>) -> PyResult<Signature> {
+        signing::sign_digest(&self.wallet, hash, self.private_key, self.extra_entropy)
+            .map_err(errors::from_core)
+    }
+}
+
+/// Signs a 32-byte hash, which `preimage` is the keccak256 of if given, returning the
+/// signature and its audit record. Does not touch the GIL.
+///
+/// `address` is set to the key's address once it is parsed, even if signing then fails.
+fn hash_signature(
+    hash: &[u8],
+    preimage: Option<&[u8]>,
+    private_key: &[u8],
+    extra_entropy: Option<&[u8; 32]>,
+    address: &mut Option<Address>,
+) -> PyResult<(Signature, audit::Record)> {
+    hash_signature_with(hash, preimage, || LocalKey::new(private_key, extra_entropy, address))
+}
+
+/// Signs a 32-byte hash, which `preimage` is the keccak256 of if given, with the signer
+/// `signer` produces.
+fn hash_signature_with<S: DigestSigner>(
+    hash: &[u8],
+    preimage: Option<&[u8]>,
+    signer: impl FnOnce() -> PyResult<S>,
+) -> PyResult<(Signature, audit::Record)> {
+    let span = tracing::debug_span!("sign_hash", signer = tracing::field::Empty).entered();
+    let hash = signing::digest(hash).map_err(errors::from_core)?;
+    if !policy::is_personal_message(hash, preimage) {
+        policy::check_raw_hash()?;
+        guard::check_raw_hash()?;
+    }
+
+    let signer = signer()?;
+    span.record("signer", tracing::field::debug(signer.address()));
+
+    ratelimit::take(signer.address(), 1)?;
+    approval::approve(signer.address(), hash, || approval::Subject::Hash)?;
+
+    let signature = signer.sign(hash, || preimage.map(<[u8]>::to_vec))?;
+    Ok((signature, audit::Record::hash(signer.address(), hash)))
+}
+
+/// Parses, encodes and signs an EIP-712 TypedData JSON payload, returning the signature
+/// and its audit record. Does not touch the GIL.
+///
+/// `address` is set to the key's address once it is parsed, even if signing then fails.
+fn typed_data_signature(
+    payload: &str,
+    private_key: &[u8],
+    extra_entropy: Option<&[u8; 32]>,
+    address: &mut Option<Address>,
+) -> PyResult<(Signature, audit::Record)> {
+    typed_data_signature_with(payload, || LocalKey::new(private_key, extra_entropy, address))
+}
+
+/// Parses, encodes and signs an EIP-712 TypedData JSON payload with the signer `signer`
+/// produces.
+fn typed_data_signature_with<S: DigestSigner>(
+    payload: &str,
+    signer: impl FnOnce() -> PyResult<S>,
+) -> PyResult<(Signature, audit::Record)> {
+    let span = tracing::debug_span!("sign_typed_data", signer = tracing::field::Empty).entered();
+    let typed_data = signing::parse_typed_data(payload).map_err(errors::from_core)?;
+
+    policy::check_domain(&typed_data.domain)?;
+    guard::check(&typed_data.domain, &typed_data.types, &typed_data.primary_type)?;
+    let signer = signer()?;
+    span.record("signer", tracing::field::debug(signer.address()));
+
+    let hash = signing::typed_data_hash(&typed_data).map_err(errors::from_core)?;
+    ratelimit::take(signer.address(), 1)?;
+    approval::approve(signer.address(), hash, || approval::Subject::TypedData {
+        domain: &typed_data.domain,
+        primary_type: &typed_data.primary_type,
+        message: serde_json::Value::Object(typed_data.message.clone().into_iter().collect()),
+    })?;
+
+    let signature = signer.sign(hash, || {
+        let struct_hash = typed_data.struct_hash().ok()?;
+        Some([&[0x19, 0x01][..], &typed_data.domain.separator(), &struct_hash].concat())
+    })?;
+    Ok((signature, audit::Record::typed_data(signer.address(), hash, &typed_data.domain)))
+}
+
+/// Parses and signs a transaction JSON payload. Does not touch the GIL.
+///
+/// When `sighash` is given it is signed as-is instead of being recomputed from the
+/// payload, so the caller is responsible for it matching the transaction. It is still
+/// recomputed and compared under a signing policy or an approval hook, and otherwise
+/// refused as a bare hash while the typed data guard blocks those.
+fn signed_transaction(
+    payload: &str,
+    private_key: &[u8],
+    sighash: Option<H256>,
+    checks: request::Checks,
+    extra_entropy: Option<&[u8; 32]>,
+    address: &mut Option<Address>,
+) -> PyResult<SignedTransaction> {
+    signed_transaction_with(payload, sighash, checks, || {
+        LocalKey::new(private_key, extra_entropy, address)
+    })
+}
+
+/// Parses and signs a transaction JSON payload with the signer `signer` produces.
+fn signed_transaction_with<S: DigestSigner>(
+    payload: &str,
+    sighash: Option<H256>,
+    checks: request::Checks,
+    signer: impl FnOnce() -> PyResult<S>,
+) -> PyResult<SignedTransaction> {
+    let span = tracing::debug_span!(
+        "sign_transaction",
+        signer = tracing::field::Empty,
+        chain_id = tracing::field::Empty,
+    )
+    .entered();
+    // 1. Parse and validate the payload into the transaction type it describes
+    let mut tx = signing::parse_transaction(payload, checks).map_err(errors::from_core)?;
+
+    // 2. Create the signer
+    let signer = signer()?;
+    span.record("signer", tracing::field::debug(signer.address()));
+
+    let chain_id = signing::chain_id_to_sign(&mut tx).map_err(errors::from_core)?;
+    if let Some(chain_id) = chain_id {
+        span.record("chain_id", chain_id);
+    }
+
+    // The fee cap and policy see the transaction exactly as it will be signed
+    policy::check_fee(&tx, payload)?;
+    policy::check(&tx, payload)?;
+
+    // 3. Sign the sighash, hashing the encoded fields only if it wasn't supplied. Under a
+    // policy or an approval hook a supplied sighash must still be checked, or the
+    // transaction they see need not be the one signed. Left unchecked, it is a bare hash
+    let fields = tx::UnsignedFields::new(&tx);
+    let supplied = sighash.is_some();
+    let sighash = match sighash {
+        Some(sighash) if policy::is_active() || approval::is_installed() => {
+            if sighash != fields.sighash() {
+                return Err(PyErr::new::<errors::PolicyViolationError, _>(
+                    "Refusing to sign: sighash does not match the payload",
+                ));
+            }
+            sighash
+        }
+        Some(sighash) => {
+            policy::check_raw_hash()?;
+            guard::check_raw_hash()?;
+            sighash
+        }
+        None => fields.sighash(),
+    };
+    ratelimit::take(signer.address(), 1)?;
+    approval::approve(signer.address(), sighash, || approval::Subject::Transaction(&tx))?;
+    // A supplied sighash may not be of these fields, so its preimage is unknown
+    let mut signature = signer.sign(sighash, || (!supplied).then(|| fields.unsigned().to_vec()))?;
+    signature.v = signing::transaction_v(&tx, chain_id, signature.v);
+
+    // 4. Compute outputs
+    let raw_transaction = fields.encode_signed(&signature);
+    let hash = H256(keccak::keccak256(&raw_transaction));
+    let record = audit::Record::transaction(signer.address(), hash, &tx);
+
+    Ok(SignedTransaction { signature, raw_transaction, hash, record })
+}
 
 /// Signs a 32-byte hash with a private key.
 ///
 /// # Arguments
 /// * `hash` - 32-byte message hash to sign.
 /// * `private_key` - Hex-encoded private key.
+/// * `signature_out` - Optional writable buffer of at least 65 bytes to write the
+///   signature into.
+/// * `extra_entropy` - Optional 32 bytes mixed into the RFC 6979 nonce derivation,
+///   for hedged rather than purely deterministic signatures.
+/// * `preimage` - The EIP-191 personal message `hash` is the keccak256 of, prefix
+///   included, which lets it be signed under a signing policy.
 ///
 /// # Returns
 /// A Python dictionary with the signature components:
-/// `r`, `s`, `v`, and `signature`.
+/// `r`, `s`, `v`, `y_parity`, and `signature`.
 #[pyfunction]
-fn sign_hash(py: Python, hash: &[u8], private_key: &[u8]) -> PyResult<PyObject> {
-    if hash.len() != 32 {
-        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            format!("Hash must be exactly 32 bytes, got {}", hash.len())
-        ));
-    }
+#[pyo3(signature = (
+    hash, private_key, *, signature_out = None, extra_entropy = None, preimage = None
+))]
+fn sign_hash(
+    py: Python,
+    hash: &[u8],
+    private_key: &[u8],
+    signature_out: Option<&Bound<PyAny>>,
+    extra_entropy: Option<&[u8]>,
+    preimage: Option<&[u8]>,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let extra_entropy = self::extra_entropy(extra_entropy)?;
+    let context = audit::context(py)?;
+    let mut address = None;
+    let mut result = interpreter::allow_threads(py, || {
+        hash_signature(hash, preimage, private_key, extra_entropy.as_ref(), &mut address)
+    });
+    metrics::observe(py, "hash", address, result.as_mut().map(|_| 1), started);
+    let (signature, record) = result?;
+    audit::record(py, &[record], context.as_ref())?;
 
-    let wallet = LocalWallet::from_bytes(private_key).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            format!("Invalid private key: {}", e)
-        )
-    })?;
+    Ok(signature_dict(py, &signature, signature_out)?.into_any().unbind())
+}
 
-    let hash_array: [u8; 32] = hash.try_into().unwrap();
-    let hash = H256(hash_array);
+/// Signs a batch of 32-byte hashes with a single private key.
+///
+/// The whole batch is signed in parallel in a single GIL release.
+///
+/// # Arguments
+/// * `hashes` - List of 32-byte message hashes to sign.
+/// * `private_key` - 32-byte raw private key.
+/// * `extra_entropy` - Optional 32 bytes mixed into every RFC 6979 nonce derivation.
+///
+/// # Returns
+/// A list of Python dictionaries, in input order, each with the signature
+/// components `r`, `s`, `v`, `y_parity`, and `signature`.
+#[pyfunction]
+#[pyo3(signature = (hashes, private_key, *, extra_entropy = None))]
+fn sign_hashes(
+    py: Python,
+    hashes: Vec<Bound<PyBytes>>,
+    private_key: &[u8],
+    extra_entropy: Option<&[u8]>,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let extra_entropy = self::extra_entropy(extra_entropy)?;
+    let hashes = hashes
+        .iter()
+        .enumerate()
+        .map(|(i, hash)| {
+            <[u8; 32]>::try_from(hash.as_bytes()).map(H256).map_err(|_| {
+                PyErr::new::<errors::FerriteError, _>(
+                    format!(
+                        "Hash at index {} must be exactly 32 bytes, got {}",
+                        i,
+                        hash.as_bytes().len()
+                    )
+                )
+            })
+        })
+        .collect::<PyResult<Vec<H256>>>()?;
 
-    let signature = py.allow_threads(|| wallet.sign_hash(hash)).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-            format!("Signing failed: {}", e)
-        )
-    })?;
+    let context = audit::context(py)?;
 
-    let result = PyDict::new(py);
-    let mut r_bytes = [0u8; 32];
-    signature.r.to_big_endian(&mut r_bytes);
-    result.set_item("r", PyBytes::new(py, &r_bytes))?;
+    let mut address = None;
+    let mut signed = interpreter::allow_threads(py, || {
+        policy::check_raw_hash()?;
+        guard::check_raw_hash()?;
+        let wallet = cache::wallet_from_key(private_key)?;
+        address = Some(wallet.address());
+        ratelimit::take(wallet.address(), hashes.len())?;
+        let calling = interpreter::calling();
+        let signatures = hashes
+            .par_iter()
+            .map(|hash| {
+                interpreter::enter(calling, || {
+                    approval::approve(wallet.address(), *hash, || approval::Subject::Hash)?;
+                    signing::sign_digest(&wallet, *hash, private_key, extra_entropy.as_ref())
+                        .map_err(errors::from_core)
+                })
+            })
+            .collect::<PyResult<Vec<Signature>>>()?;
+        Ok::<_, PyErr>((wallet.address(), signatures))
+    });
+    metrics::observe(py, "hash", address, signed.as_mut().map(|(_, s)| s.len()), started);
+    let (address, signatures) = signed?;
+    let records: Vec<_> = hashes.iter().map(|hash| audit::Record::hash(address, *hash)).collect();
+    audit::record(py, &records, context.as_ref())?;
 
-    let mut s_bytes = [0u8; 32];
-    signature.s.to_big_endian(&mut s_bytes);
-    result.set_item("s", PyBytes::new(py, &s_bytes))?;
+    let results = signatures
+        .iter()
+        .map(|signature| signature_dict(py, signature, None))
+        .collect::<PyResult<Vec<_>>>()?;
 
-    result.set_item("v", signature.v)?;
-    result.set_item("signature", PyBytes::new(py, &signature.to_vec()))?;
+    Ok(PyList::new(py, results)?.into_any().unbind())
+}
 
-    Ok(result.into())
+/// Computes the EIP-712 hash of a typed data object, without signing it.
+///
+/// # Arguments
+/// * `payload` - JSON string of the EIP-712 TypedData.
+/// * `struct_hash` - Return the `hashStruct` of the message alone, without the domain,
+///   as contracts such as Seaport use to identify orders.
+///
+/// # Returns
+/// The 32-byte hash a signature of the typed data signs, or the message's struct hash.
+#[pyfunction]
+#[pyo3(signature = (payload, *, struct_hash = false))]
+fn hash_typed_data(py: Python, payload: &str, struct_hash: bool) -> PyResult<PyObject> {
+    let hash = py.allow_threads(|| {
+        let typed_data = signing::parse_typed_data(payload).map_err(errors::from_core)?;
+        if !struct_hash {
+            return signing::typed_data_hash(&typed_data).map_err(errors::from_core);
+        }
+        typed_data.struct_hash().map(H256::from).map_err(|e| {
+            PyErr::new::<errors::TypedDataError, _>(
+                format!("Failed to encode EIP-712 data: {}", e)
+            )
+        })
+    })?;
+    Ok(PyBytes::new(py, hash.as_bytes()).into_any().unbind())
 }
 
 /// Signs an EIP-712 typed data object with a private key.
@@ -71,135 +577,542 @@ fn sign_hash(py: Python, hash: &[u8], private_key: &[u8]) -> PyResult<PyObject>
 /// # Arguments
 /// * `payload` - JSON string of the EIP-712 TypedData.
 /// * `private_key` - 32-byte raw private key.
+/// * `signature_out` - Optional writable buffer of at least 65 bytes to write the
+///   signature into.
+/// * `extra_entropy` - Optional 32 bytes mixed into the RFC 6979 nonce derivation.
 ///
 /// # Returns
 /// A Python dictionary with the signature components:
-/// `r`, `s`, `v`, and `signature`.
+/// `r`, `s`, `v`, `y_parity`, and `signature`.
 #[pyfunction]
-fn sign_typed_data(py: Python, payload: &str, private_key: &[u8]) -> PyResult<PyObject> {
-    let typed_data: TypedData = serde_json::from_str(payload).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            format!("Invalid TypedData JSON: {}", e)
-        )
-    })?;
+#[pyo3(signature = (payload, private_key, *, signature_out = None, extra_entropy = None))]
+fn sign_typed_data(
+    py: Python,
+    payload: &str,
+    private_key: &[u8],
+    signature_out: Option<&Bound<PyAny>>,
+    extra_entropy: Option<&[u8]>,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let extra_entropy = self::extra_entropy(extra_entropy)?;
+    let context = audit::context(py)?;
+    // Parsing, EIP-712 encoding and signing are all CPU-bound, so run them without the GIL
+    let mut address = None;
+    let mut result = interpreter::allow_threads(py, || {
+        typed_data_signature(payload, private_key, extra_entropy.as_ref(), &mut address)
+    });
+    metrics::observe(py, "typed_data", address, result.as_mut().map(|_| 1), started);
+    let (signature, record) = result?;
+    audit::record(py, &[record], context.as_ref())?;
 
-    let wallet = LocalWallet::from_bytes(private_key).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            format!("Invalid private key: {}", e)
-        )
-    })?;
+    Ok(signature_dict(py, &signature, signature_out)?.into_any().unbind())
+}
 
-    // Encode the typed data according to EIP-712 to get the message hash
-    let hash = typed_data.encode_eip712().map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            format!("Failed to encode EIP-712 data: {}", e)
-        )
-    })?;
-    let hash = H256::from(hash);
+/// Signs a batch of EIP-712 messages that share one domain and type set.
+///
+/// The domain separator and type hashes are computed once for the whole batch, then the
+/// messages are hashed and signed in parallel in a single GIL release.
+///
+/// # Arguments
+/// * `domain` - JSON string of the EIP-712 domain.
+/// * `types` - JSON string of the EIP-712 type definitions.
+/// * `messages` - JSON string of the array of messages to sign.
+/// * `private_key` - 32-byte raw private key.
+/// * `primary_type` - Type of every message; inferred from `types` when omitted.
+/// * `extra_entropy` - Optional 32 bytes mixed into every RFC 6979 nonce derivation.
+///
+/// # Returns
+/// A list of Python dictionaries, in input order, each with the signature
+/// components `r`, `s`, `v`, `y_parity`, and `signature`.
+#[pyfunction]
+#[pyo3(signature = (
+    domain,
+    types,
+    messages,
+    private_key,
+    primary_type = None,
+    *,
+    extra_entropy = None
+))]
+fn sign_typed_data_batch(
+    py: Python,
+    domain: &str,
+    types: &str,
+    messages: &str,
+    private_key: &[u8],
+    primary_type: Option<&str>,
+    extra_entropy: Option<&[u8]>,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let extra_entropy = self::extra_entropy(extra_entropy)?;
+    let context = audit::context(py)?;
+    let mut address = None;
+    let mut signed = interpreter::allow_threads(py, || {
+        let domain: EIP712Domain = serde_json::from_str(domain).map_err(|e| {
+            PyErr::new::<errors::TypedDataError, _>(
+                format!("Invalid EIP-712 domain JSON: {}", e)
+            )
+        })?;
+        let types: Types = serde_json::from_str(types).map_err(|e| {
+            PyErr::new::<errors::TypedDataError, _>(
+                format!("Invalid EIP-712 types JSON: {}", e)
+            )
+        })?;
+        let messages: Vec<serde_json::Value> = serde_json::from_str(messages).map_err(|e| {
+            PyErr::new::<errors::TypedDataError, _>(
+                format!("Invalid EIP-712 messages JSON: {}", e)
+            )
+        })?;
 
-    let signature = py.allow_threads(|| wallet.sign_hash(hash)).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-            format!("Signing failed: {}", e)
-        )
-    })?;
+        let primary_type = match primary_type {
+            Some(primary_type) => primary_type,
+            None => eip712::infer_primary_type(&types).map_err(|e| {
+                PyErr::new::<errors::TypedDataError, _>(
+                    format!("Failed to encode EIP-712 data: {}", e)
+                )
+            })?,
+        };
 
-    let result = PyDict::new(py);
-    let mut r_bytes = [0u8; 32];
-    signature.r.to_big_endian(&mut r_bytes);
-    result.set_item("r", PyBytes::new(py, &r_bytes))?;
+        policy::check_domain(&domain)?;
+        guard::check(&domain, &types, primary_type)?;
+        let wallet = cache::wallet_from_key(private_key)?;
+        address = Some(wallet.address());
+        ratelimit::take(wallet.address(), messages.len())?;
 
-    let mut s_bytes = [0u8; 32];
-    signature.s.to_big_endian(&mut s_bytes);
-    result.set_item("s", PyBytes::new(py, &s_bytes))?;
+        // Shared across the whole batch
+        let domain_separator = domain.separator();
+        let type_hashes = eip712::TypeHashes::new(&types).map_err(|e| {
+            PyErr::new::<errors::TypedDataError, _>(
+                format!("Failed to encode EIP-712 data: {}", e)
+            )
+        })?;
 
-    result.set_item("v", signature.v)?;
-    result.set_item("signature", PyBytes::new(py, &signature.to_vec()))?;
+        let calling = interpreter::calling();
+        let signed = messages
+            .par_iter()
+            .enumerate()
+            .map(|(i, message)| {
+                interpreter::enter(calling, || {
+                    let hash = type_hashes
+                        .digest(&domain_separator, primary_type, message)
+                        .map_err(|e| {
+                            PyErr::new::<errors::TypedDataError, _>(format!(
+                                "Failed to encode EIP-712 message at index {}: {}",
+                                i, e
+                            ))
+                        })?;
+                    approval::approve(wallet.address(), H256(hash), || {
+                        approval::Subject::TypedData {
+                            domain: &domain,
+                            primary_type,
+                            message: message.clone(),
+                        }
+                    })?;
 
-    Ok(result.into())
-}
+                    let extra_entropy = extra_entropy.as_ref();
+                    let signature =
+                        signing::sign_digest(&wallet, H256(hash), private_key, extra_entropy)
+                            .map_err(errors::from_core)?;
+                    let record = audit::Record::typed_data(wallet.address(), H256(hash), &domain);
+                    Ok((signature, record))
+                })
+            })
+            .collect::<PyResult<Vec<(Signature, audit::Record)>>>()?;
+        Ok::<_, PyErr>(signed)
+    });
+    metrics::observe(py, "typed_data", address, signed.as_mut().map(|s| s.len()), started);
+    let (signatures, records): (Vec<_>, Vec<_>) = signed?.into_iter().unzip();
+    audit::record(py, &records, context.as_ref())?;
 
+    let results = signatures
+        .iter()
+        .map(|signature| signature_dict(py, signature, None))
+        .collect::<PyResult<Vec<_>>>()?;
 
+    Ok(PyList::new(py, results)?.into_any().unbind())
+}
 
 /// Signs a transaction object with a private key.
 ///
 /// # Arguments
 /// * `payload` - JSON string of the transaction dictionary.
 /// * `private_key` - 32-byte raw private key.
+/// * `sighash` - Optional precomputed 32-byte signing hash of the transaction, which
+///   skips encoding and hashing the unsigned transaction. Under a signing policy or an
+///   approval hook it is checked against the payload, so nothing is skipped; otherwise
+///   it is refused as a bare hash while the typed data guard blocks those.
+/// * `require_checksum` - Reject addresses that are not EIP-55 checksummed. Mixed-case
+///   addresses with an invalid checksum are always rejected.
+/// * `check_fee_cap` - Reject dynamic fee transactions whose `maxPriorityFeePerGas`
+///   exceeds their `maxFeePerGas`.
+/// * `raw_transaction_out` - Optional writable buffer to write the signed raw transaction
+///   into; it must be at least as long as the transaction.
+/// * `extra_entropy` - Optional 32 bytes mixed into the RFC 6979 nonce derivation.
 ///
 /// # Returns
 /// A Python dictionary with the signature components and raw transaction:
-/// `r`, `s`, `v`, `hash`, `rawTransaction` (bytes).
+/// `r`, `s`, `v`, `y_parity`, `signature`, `hash`, `rawTransaction` (bytes).
 #[pyfunction]
-fn sign_transaction(py: Python, payload: &str, private_key: &[u8]) -> PyResult<PyObject> {
-    // 1. Parse TransactionRequest
-    let request: TransactionRequest = serde_json::from_str(payload).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            format!("Invalid Transaction JSON: {}", e)
-        )
-    })?;
+#[pyo3(signature = (
+    payload,
+    private_key,
+    *,
+    sighash = None,
+    require_checksum = false,
+    check_fee_cap = true,
+    raw_transaction_out = None,
+    extra_entropy = None
+))]
+#[allow(clippy::too_many_arguments)]
+fn sign_transaction(
+    py: Python,
+    payload: &str,
+    private_key: &[u8],
+    sighash: Option<&[u8]>,
+    require_checksum: bool,
+    check_fee_cap: bool,
+    raw_transaction_out: Option<&Bound<PyAny>>,
+    extra_entropy: Option<&[u8]>,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let extra_entropy = self::extra_entropy(extra_entropy)?;
+    let sighash = sighash
+        .map(|sighash| {
+            <[u8; 32]>::try_from(sighash).map(H256).map_err(|_| {
+                PyErr::new::<errors::InvalidTransactionError, _>(
+                    format!("Sighash must be exactly 32 bytes, got {}", sighash.len())
+                )
+            })
+        })
+        .transpose()?;
 
-    // 2. Convert to TypedTransaction
-    let tx: ethers_core::types::transaction::eip2718::TypedTransaction = request.into();
+    // Everything up to building the result dict is CPU-bound (JSON parsing, RLP encoding,
+    // keccak hashing and ECDSA), so it all runs without the GIL
+    let checks = request::Checks { require_checksum, fee_cap: check_fee_cap };
+    let context = audit::context(py)?;
+    let mut address = None;
+    let mut signed = interpreter::allow_threads(py, || {
+        let extra_entropy = extra_entropy.as_ref();
+        signed_transaction(payload, private_key, sighash, checks, extra_entropy, &mut address)
+    });
+    metrics::observe(py, "transaction", address, signed.as_mut().map(|_| 1), started);
+    let signed = signed?;
+    audit::record(py, std::slice::from_ref(&signed.record), context.as_ref())?;
 
-    // 3. Create Wallet
-    let wallet = LocalWallet::from_bytes(private_key).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            format!("Invalid private key: {}", e)
-        )
+    Ok(transaction_dict(py, &signed, raw_transaction_out)?.into_any().unbind())
+}
+
+/// Encodes a transaction as it would be signed, without signing it.
+///
+/// This is the message whose keccak256 is the transaction's sighash, for signers that
+/// are handed the transaction itself, such as air-gapped wallets.
+///
+/// # Arguments
+/// * `payload` - JSON string of the transaction dictionary.
+///
+/// # Returns
+/// The unsigned transaction as bytes: its RLP encoding for legacy transactions, or the
+/// type byte followed by it for typed ones.
+#[pyfunction]
+fn encode_unsigned_transaction(py: Python, payload: &str) -> PyResult<PyObject> {
+    let unsigned = py.allow_threads(|| {
+        let mut tx = signing::parse_transaction(payload, request::Checks::default())
+            .map_err(errors::from_core)?;
+        signing::chain_id_to_sign(&mut tx).map_err(errors::from_core)?;
+        Ok::<_, PyErr>(tx::UnsignedFields::new(&tx).unsigned().to_vec())
     })?;
+    Ok(PyBytes::new(py, &unsigned).into_any().unbind())
+}
 
-    // Ensure wallet has the chain_id from the transaction if present (corrects replay protection)
-    let chain_id = tx.chain_id().map(|id| id.as_u64()).unwrap_or(1);
-    let wallet = wallet.with_chain_id(chain_id);
-
-    // 4. Sign Transaction (using tokio runtime for async ethers call)
-    let signature = std::thread::scope(|_| {
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                    format!("Failed to create tokio runtime: {}", e)
-                )
-            })?;
+/// Asynchronous variant of [`sign_hash`].
+///
+/// Signs on the Rust thread pool and returns an asyncio future resolving to the same
+/// dictionary `sign_hash` returns. Must be called from a running event loop.
+#[pyfunction]
+#[pyo3(signature = (hash, private_key, *, preimage = None))]
+fn sign_hash_async(
+    py: Python,
+    hash: &[u8],
+    private_key: &[u8],
+    preimage: Option<&[u8]>,
+) -> PyResult<PyObject> {
+    let (hash, private_key) = (hash.to_vec(), Zeroizing::new(private_key.to_vec()));
+    let preimage = preimage.map(<[u8]>::to_vec);
+    let context = audit::context(py)?;
 
-        rt.block_on(async {
-            wallet.sign_transaction(&tx).await.map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
-                    format!("Signing failed: {}", e)
-                )
-            })
-        })
-    })?;
+    asyncio::spawn(
+        py,
+        move || {
+            let started = Instant::now();
+            let mut address = None;
+            let result =
+                hash_signature(&hash, preimage.as_deref(), &private_key, None, &mut address);
+            (result, address, started)
+        },
+        move |py, (mut result, address, started)| {
+            metrics::observe(py, "hash", address, result.as_mut().map(|_| 1), started);
+            let (signature, record) = result?;
+            audit::record(py, &[record], context.as_ref())?;
+            Ok(signature_dict(py, &signature, None)?.into_any().unbind())
+        },
+    )
+}
 
-    // 5. Compute outputs
-    let rlp_signed = tx.rlp_signed(&signature);
-    let tx_hash = tx.hash(&signature);
+/// Asynchronous variant of [`sign_typed_data`].
+///
+/// Signs on the Rust thread pool and returns an asyncio future resolving to the same
+/// dictionary `sign_typed_data` returns. Must be called from a running event loop.
+#[pyfunction]
+fn sign_typed_data_async(
+    py: Python,
+    payload: String,
+    private_key: &[u8],
+) -> PyResult<PyObject> {
+    let private_key = Zeroizing::new(private_key.to_vec());
+    let context = audit::context(py)?;
 
-    let result = PyDict::new(py);
+    asyncio::spawn(
+        py,
+        move || {
+            let started = Instant::now();
+            let mut address = None;
+            let result = typed_data_signature(&payload, &private_key, None, &mut address);
+            (result, address, started)
+        },
+        move |py, (mut result, address, started)| {
+            metrics::observe(py, "typed_data", address, result.as_mut().map(|_| 1), started);
+            let (signature, record) = result?;
+            audit::record(py, &[record], context.as_ref())?;
+            Ok(signature_dict(py, &signature, None)?.into_any().unbind())
+        },
+    )
+}
 
-    let mut r_bytes = [0u8; 32];
-    signature.r.to_big_endian(&mut r_bytes);
-    result.set_item("r", PyBytes::new(py, &r_bytes))?;
+/// Asynchronous variant of [`sign_transaction`].
+///
+/// Signs on the Rust thread pool and returns an asyncio future resolving to the same
+/// dictionary `sign_transaction` returns. Must be called from a running event loop.
+#[pyfunction]
+fn sign_transaction_async(
+    py: Python,
+    payload: String,
+    private_key: &[u8],
+) -> PyResult<PyObject> {
+    let private_key = Zeroizing::new(private_key.to_vec());
+    let context = audit::context(py)?;
 
-    let mut s_bytes = [0u8; 32];
-    signature.s.to_big_endian(&mut s_bytes);
-    result.set_item("s", PyBytes::new(py, &s_bytes))?;
+    asyncio::spawn(
+        py,
+        move || {
+            let started = Instant::now();
+            let checks = request::Checks::default();
+            let mut address = None;
+            let result =
+                signed_transaction(&payload, &private_key, None, checks, None, &mut address);
+            (result, address, started)
+        },
+        move |py, (mut result, address, started)| {
+            metrics::observe(py, "transaction", address, result.as_mut().map(|_| 1), started);
+            let signed = result?;
+            audit::record(py, std::slice::from_ref(&signed.record), context.as_ref())?;
+            Ok(transaction_dict(py, &signed, None)?.into_any().unbind())
+        },
+    )
+}
 
-    result.set_item("v", signature.v)?;
+/// Builds the process-wide secp256k1 tables and worker pool ahead of time.
+///
+/// k256 builds its generator multiplication tables lazily on first use and shares
+/// them across all keys and threads. Calling this at startup moves that one-off cost
+/// out of the first latency-sensitive signature.
+#[pyfunction]
+fn warm_up(py: Python) -> PyResult<()> {
+    py.allow_threads(|| {
+        let mut key = [0u8; 32];
+        key[31] = 1;
 
-    // rawTransaction
-    result.set_item("rawTransaction", PyBytes::new(py, &rlp_signed))?;
-    // hash
-    result.set_item("hash", PyBytes::new(py, &tx_hash.as_bytes()))?;
+        let wallet = LocalWallet::from_bytes(&key).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Warm-up failed: {}", e)
+            )
+        })?;
+        wallet.sign_hash(H256::zero()).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                format!("Warm-up failed: {}", e)
+            )
+        })?;
+
+        rayon::current_num_threads();
+        Ok(())
+    })
+}
+
+/// Module slots that older Pythons refuse, as the slot, the version that added it and
+/// the value ferrite declares: `Py_mod_multiple_interpreters`, supported for those that
+/// share the main interpreter's GIL when this build can tell interpreters apart, and
+/// `Py_mod_gil`, not used.
+const LATER_SLOTS: [(c_int, (u8, u8), usize); 2] =
+    [(3, (3, 12), interpreter::SUPPORTED as usize), (4, (3, 13), 1)];
+
+static mut SLOTS: [ffi::PyModuleDef_Slot; 4] =
+    [ffi::PyModuleDef_Slot { slot: 0, value: ptr::null_mut() }; 4];
 
-    Ok(result.into())
+static mut MODULE: ffi::PyModuleDef = ffi::PyModuleDef {
+    m_base: ffi::PyModuleDef_HEAD_INIT,
+    m_name: c"_ferrite".as_ptr(),
+    m_doc: c"".as_ptr(),
+    m_size: 0,
+    m_methods: ptr::null_mut(),
+    m_slots: ptr::null_mut(),
+    m_traverse: None,
+    m_clear: None,
+    m_free: Some(free_module),
+};
+
+/// Initializes the module in multiple phases, which PyO3 doesn't offer, so that each
+/// interpreter importing ferrite executes a module of its own. See `interpreter` for the
+/// state kept per interpreter.
+///
+/// # Safety
+/// Called by the import system, with the GIL held.
+#[no_mangle]
+pub unsafe extern "C" fn PyInit__ferrite() -> *mut ffi::PyObject {
+    static INIT: Once = Once::new();
+
+    let version = Python::assume_gil_acquired().version_info();
+    INIT.call_once(|| {
+        let slots = &mut *ptr::addr_of_mut!(SLOTS);
+        slots[0] = ffi::PyModuleDef_Slot {
+            slot: ffi::Py_mod_exec,
+            value: exec_module as unsafe extern "C" fn(_) -> _ as *mut c_void,
+        };
+        let later = LATER_SLOTS.iter().filter(|(_, since, _)| version >= *since);
+        for (slot, (id, _, value)) in slots[1..].iter_mut().zip(later) {
+            *slot = ffi::PyModuleDef_Slot { slot: *id, value: *value as *mut c_void };
+        }
+        (*ptr::addr_of_mut!(MODULE)).m_slots = slots.as_mut_ptr();
+    });
+    ffi::PyModuleDef_Init(ptr::addr_of_mut!(MODULE))
+}
+
+/// Executes the module in the interpreter importing it.
+unsafe extern "C" fn exec_module(module: *mut ffi::PyObject) -> c_int {
+    let py = Python::assume_gil_acquired();
+    let module = Bound::from_borrowed_ptr(py, module).downcast_into_unchecked::<PyModule>();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        interpreter::open_module(py)?;
+        _ferrite(&module)
+    }));
+    match result.unwrap_or_else(|_| Err(PanicException::new_err("Importing ferrite panicked"))) {
+        Ok(()) => 0,
+        Err(e) => {
+            e.restore(py);
+            -1
+        }
+    }
 }
 
-#[pymodule]
-fn _ferrite(_py: Python, m: &PyModule) -> PyResult<()> {
+/// Drops the state of the interpreter freeing the module, if it was its last.
+unsafe extern "C" fn free_module(_module: *mut c_void) {
+    let py = Python::assume_gil_acquired();
+    if interpreter::close_module(py) {
+        approval::clear(py);
+        audit::clear(py);
+        logging::clear(py);
+        errors::clear(py);
+    }
+}
+
+fn _ferrite(m: &Bound<PyModule>) -> PyResult<()> {
+    errors::register(m)?;
+    logging::install();
     m.add_function(wrap_pyfunction!(sign_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(sign_hashes, m)?)?;
     m.add_function(wrap_pyfunction!(sign_typed_data, m)?)?;
+    m.add_function(wrap_pyfunction!(hash_typed_data, m)?)?;
+    m.add_function(wrap_pyfunction!(sign_typed_data_batch, m)?)?;
     m.add_function(wrap_pyfunction!(sign_transaction, m)?)?;
+    m.add_function(wrap_pyfunction!(encode_unsigned_transaction, m)?)?;
+    m.add_function(wrap_pyfunction!(sign_hash_async, m)?)?;
+    m.add_function(wrap_pyfunction!(sign_typed_data_async, m)?)?;
+    m.add_function(wrap_pyfunction!(sign_transaction_async, m)?)?;
+    m.add_function(wrap_pyfunction!(address::addresses_from_keys, m)?)?;
+    m.add_function(wrap_pyfunction!(address::addresses_from_seed, m)?)?;
+    m.add_function(wrap_pyfunction!(keystore::decrypt_keystore, m)?)?;
+    m.add_function(wrap_pyfunction!(keystore::decrypt_keystores, m)?)?;
+    m.add_function(wrap_pyfunction!(keystore::encrypt_keystore, m)?)?;
+    m.add_function(wrap_pyfunction!(keystore::set_keystore_threads, m)?)?;
+    m.add_function(wrap_pyfunction!(keystore::set_keystore_limits, m)?)?;
+    m.add_function(wrap_pyfunction!(cache::clear_key_cache, m)?)?;
+    m.add_function(wrap_pyfunction!(cache::key_cache_locked, m)?)?;
+    m.add_function(wrap_pyfunction!(policy::set_signing_policy, m)?)?;
+    m.add_function(wrap_pyfunction!(approval::set_approval_hook, m)?)?;
+    m.add_function(wrap_pyfunction!(audit::set_audit_log, m)?)?;
+    m.add_function(wrap_pyfunction!(ratelimit::set_rate_limit, m)?)?;
+    m.add_function(wrap_pyfunction!(guard::set_typed_data_guard, m)?)?;
+    m.add_function(wrap_pyfunction!(audit::audit_context_var, m)?)?;
+    m.add_function(wrap_pyfunction!(audit::verify_audit_log, m)?)?;
+    m.add_function(wrap_pyfunction!(policy::signing_policy_locked, m)?)?;
+    m.add_function(wrap_pyfunction!(policy::set_max_transaction_fee, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics::get_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(logging::set_log_level, m)?)?;
+    m.add_function(wrap_pyfunction!(logging::flush_logs, m)?)?;
+    m.add_function(wrap_pyfunction!(address::address_from_public_key, m)?)?;
+    m.add_function(wrap_pyfunction!(address::to_checksum_address, m)?)?;
+    m.add_function(wrap_pyfunction!(address::to_checksum_addresses, m)?)?;
+    m.add_function(wrap_pyfunction!(address::is_checksum_address, m)?)?;
+    m.add_function(wrap_pyfunction!(address::is_checksum_addresses, m)?)?;
+    m.add_function(wrap_pyfunction!(address::create_address, m)?)?;
+    m.add_function(wrap_pyfunction!(address::create2_address, m)?)?;
+    m.add_function(wrap_pyfunction!(address::mine_create2_salt, m)?)?;
+    m.add_function(wrap_pyfunction!(keccak::keccak, m)?)?;
+    m.add_function(wrap_pyfunction!(rlp::rlp_encode, m)?)?;
+    m.add_function(wrap_pyfunction!(rlp::rlp_decode, m)?)?;
+    m.add_function(wrap_pyfunction!(tx::wrap_typed_transaction, m)?)?;
+    m.add_function(wrap_pyfunction!(tx::unwrap_typed_transaction, m)?)?;
+    m.add_function(wrap_pyfunction!(abi::encode_calldata, m)?)?;
+    m.add_function(wrap_pyfunction!(abi::function_selector, m)?)?;
+    m.add_function(wrap_pyfunction!(abi::function_selectors, m)?)?;
+    m.add_function(wrap_pyfunction!(abi::event_topic, m)?)?;
+    m.add_function(wrap_pyfunction!(abi::event_topics, m)?)?;
+    m.add_function(wrap_pyfunction!(abi::solidity_keccak, m)?)?;
+    m.add_function(wrap_pyfunction!(abi::decode_abi, m)?)?;
+    m.add_function(wrap_pyfunction!(abi::decode_log, m)?)?;
+    m.add_function(wrap_pyfunction!(abi::decode_logs, m)?)?;
+    m.add_function(wrap_pyfunction!(ens::labelhash, m)?)?;
+    m.add_function(wrap_pyfunction!(ens::namehash, m)?)?;
+    m.add_function(wrap_pyfunction!(ens::dns_encode, m)?)?;
+    m.add_function(wrap_pyfunction!(remote::sign_hash_remote, m)?)?;
+    m.add_function(wrap_pyfunction!(remote::sign_typed_data_remote, m)?)?;
+    m.add_function(wrap_pyfunction!(remote::sign_transaction_remote, m)?)?;
+    m.add_function(wrap_pyfunction!(userop::user_operation_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(userop::sign_user_operation, m)?)?;
+    m.add_function(wrap_pyfunction!(opstack::op_withdrawal_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(opstack::op_deposit_source_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(opstack::op_deposit_transaction_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(p256::p256_public_key, m)?)?;
+    m.add_function(wrap_pyfunction!(p256::p256_sign_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(p256::p256_verify, m)?)?;
+    m.add_function(wrap_pyfunction!(bls::bls_public_key, m)?)?;
+    m.add_function(wrap_pyfunction!(bls::bls_hash_to_point, m)?)?;
+    m.add_function(wrap_pyfunction!(bls::bls_sign, m)?)?;
+    m.add_function(wrap_pyfunction!(bls::bls_aggregate, m)?)?;
+    m.add_function(wrap_pyfunction!(bls::bls_user_operation_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(ssz::deposit_message_root, m)?)?;
+    m.add_function(wrap_pyfunction!(ssz::deposit_signing_root, m)?)?;
+    m.add_function(wrap_pyfunction!(ssz::deposit_data_root, m)?)?;
+    #[cfg(feature = "stark")]
+    {
+        m.add_function(wrap_pyfunction!(stark::stark_pedersen_hash, m)?)?;
+        m.add_function(wrap_pyfunction!(stark::stark_grind_key, m)?)?;
+        m.add_function(wrap_pyfunction!(stark::stark_public_key, m)?)?;
+        m.add_function(wrap_pyfunction!(stark::stark_sign_hash, m)?)?;
+    }
+    #[cfg(feature = "rpc")]
+    {
+        m.add_function(wrap_pyfunction!(rpc::fill_transaction, m)?)?;
+        m.add_function(wrap_pyfunction!(rpc::send_raw_transaction, m)?)?;
+    }
+    m.add_function(wrap_pyfunction!(warm_up, m)?)?;
     Ok(())
 }
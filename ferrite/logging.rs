@@ -0,0 +1,552 @@
+/*!
+Tracing forwarded to Python's `logging`.
+
+Parsing, hashing and signing run in spans, and policy decisions and remote signer calls
+emit events. A subscriber installed when the module loads turns each event, and each
+span as it closes, into a record on a logger under `ferrite`: `ferrite.signing` for the
+core steps, `ferrite.policy` for policy decisions, `ferrite.remote` for remote signers,
+and so on. The message is prefixed with the spans it happened in, and the fields of the
+event and its spans are attached to the record as the `ferrite_fields` dictionary.
+
+Nothing is forwarded until `set_log_level` enables it, so the default costs one atomic
+load per span. From there, loggers, handlers and their levels are configured in Python
+as for any other library; `set_log_level` only decides what ferrite produces.
+
+Much of the signing runs without the GIL, and a thread that takes it back while another
+waits on it in a parallel batch can deadlock. Records are therefore queued and forwarded
+by a background thread instead, and may reach the handlers shortly after the call that
+produced them returns; `flush_logs` forwards whatever is queued at once. Key material
+is never put in a span or event.
+
+Each interpreter sets its own level, and records go to the `logging` of the interpreter
+whose call produced them.
+*/
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Instant;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record as Values};
+use tracing::subscriber::{Interest, Subscriber};
+use tracing::{Event, Level, Metadata};
+
+use crate::errors;
+use crate::interpreter::{self, Interpreter, Local};
+
+/// Records queued while no thread could forward them are dropped past this many.
+const QUEUE_LIMIT: usize = 10_000;
+
+/// Most verbose level forwarded to any interpreter: 0 for none, then 1 (errors) to 5
+/// (trace).
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Each interpreter's most verbose level, as in `LEVEL`.
+static LEVELS: Local<AtomicU8> = Local::new();
+
+/// Records along with the interpreter whose call produced them, if known.
+static QUEUE: Mutex<VecDeque<(Option<Interpreter>, LogRecord)>> = Mutex::new(VecDeque::new());
+static QUEUED: Condvar = Condvar::new();
+static FORWARDER: OnceLock<()> = OnceLock::new();
+
+thread_local! {
+    /// Spans entered on this thread, innermost last.
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A field value, kept as the Python type it will become.
+#[derive(Clone)]
+enum Value {
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Text(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl Value {
+    fn to_python<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(match self {
+            Value::Bool(value) => value.into_pyobject(py)?.to_owned().into_any(),
+            Value::Int(value) => value.into_pyobject(py)?.into_any(),
+            Value::Float(value) => value.into_pyobject(py)?.into_any(),
+            Value::Text(value) => value.into_pyobject(py)?.into_any(),
+        })
+    }
+}
+
+/// Collects an event's message and the fields of an event or span.
+#[derive(Default)]
+struct Fields {
+    message: Option<String>,
+    values: Vec<(&'static str, Value)>,
+}
+
+impl Fields {
+    fn add(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            self.values.retain(|(name, _)| *name != field.name());
+            self.values.push((field.name(), value));
+        }
+    }
+
+    /// Formats the fields as `name=value` pairs.
+    fn pairs(&self) -> String {
+        let pairs: Vec<_> =
+            self.values.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        pairs.join(" ")
+    }
+}
+
+impl Visit for Fields {
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.add(field, Value::Bool(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.add(field, Value::Int(value.into()));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.add(field, Value::Int(value.into()));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.add(field, Value::Float(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.add(field, Value::Text(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.add(field, Value::Text(format!("{:?}", value)));
+    }
+}
+
+struct SpanData {
+    metadata: &'static Metadata<'static>,
+    fields: Fields,
+    parent: Option<u64>,
+    started: Instant,
+    references: usize,
+}
+
+/// A record on its way to a Python logger.
+struct LogRecord {
+    logger: String,
+    level: u8,
+    message: String,
+    fields: Vec<(&'static str, Value)>,
+}
+
+/// Returns the Python logging level of `level`. Trace has no name in `logging`, and
+/// sits below debug.
+fn python_level(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 40,
+        Level::WARN => 30,
+        Level::INFO => 20,
+        Level::DEBUG => 10,
+        Level::TRACE => 5,
+    }
+}
+
+/// Returns `level` as an index into `LEVEL`.
+fn verbosity(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 1,
+        Level::WARN => 2,
+        Level::INFO => 3,
+        Level::DEBUG => 4,
+        Level::TRACE => 5,
+    }
+}
+
+/// Returns the logger for a target: `ferrite.signing` for `ferrite_core::signing`,
+/// `ferrite.policy` for `_ferrite::policy`, and `ferrite` for the module root. Targets
+/// outside ferrite, such as those of its dependencies, aren't forwarded.
+fn logger(target: &str) -> Option<String> {
+    let module = target.strip_prefix("_ferrite").or_else(|| target.strip_prefix("ferrite_core"))?;
+    match module.rsplit("::").next() {
+        Some(name) if !name.is_empty() => Some(format!("ferrite.{}", name)),
+        Some(_) => Some("ferrite".into()),
+        None => None,
+    }
+}
+
+/// The subscriber installed by [`install`].
+#[derive(Default)]
+struct Bridge {
+    spans: Mutex<HashMap<u64, SpanData>>,
+    next_id: AtomicU64,
+}
+
+impl Bridge {
+    /// Returns the innermost span entered on this thread.
+    fn current(&self) -> Option<u64> {
+        ENTERED.with(|entered| entered.borrow().last().copied())
+    }
+
+    /// Returns the spans from the outermost down to `id` as `name{fields}:` prefixes,
+    /// along with the fields of them all.
+    fn context(&self, id: Option<u64>) -> (String, Vec<(&'static str, Value)>) {
+        let spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        let mut chain = Vec::new();
+        let mut next = id;
+        while let Some(span) = next.and_then(|id| spans.get(&id)) {
+            chain.push(span);
+            next = span.parent;
+        }
+
+        let mut prefix = String::new();
+        let mut fields = Vec::new();
+        for span in chain.iter().rev() {
+            let pairs = span.fields.pairs();
+            if pairs.is_empty() {
+                write!(prefix, "{}:", span.metadata.name()).ok();
+            } else {
+                write!(prefix, "{}{{{}}}:", span.metadata.name(), pairs).ok();
+            }
+            fields.extend(span.fields.values.iter().cloned());
+        }
+        (prefix, fields)
+    }
+}
+
+impl Subscriber for Bridge {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        // Decided per call, since the level can change at any time
+        match logger(metadata.target()) {
+            Some(_) => Interest::sometimes(),
+            None => Interest::never(),
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        verbosity(metadata.level()) <= LEVEL.load(Ordering::Relaxed)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(match LEVEL.load(Ordering::Relaxed) {
+            0 => LevelFilter::OFF,
+            1 => LevelFilter::ERROR,
+            2 => LevelFilter::WARN,
+            3 => LevelFilter::INFO,
+            4 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        })
+    }
+
+    fn new_span(&self, attributes: &Attributes) -> Id {
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+        let parent = match attributes.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if attributes.is_contextual() => self.current(),
+            None => None,
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let span = SpanData {
+            metadata: attributes.metadata(),
+            fields,
+            parent,
+            started: Instant::now(),
+            references: 1,
+        };
+        let mut spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(parent) = parent.and_then(|parent| spans.get_mut(&parent)) {
+            // Kept open for its children's records
+            parent.references += 1;
+        }
+        spans.insert(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Values) {
+        let mut spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(span) = spans.get_mut(&span.into_u64()) {
+            values.record(&mut span.fields);
+        }
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let metadata = event.metadata();
+        let Some(logger) = logger(metadata.target()) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let parent = match event.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if event.is_contextual() => self.current(),
+            None => None,
+        };
+
+        let (prefix, mut values) = self.context(parent);
+        let mut message = fields.message.take().unwrap_or_default();
+        if !fields.values.is_empty() {
+            message = format!("{} {}", message, fields.pairs()).trim_start().to_string();
+        }
+        if !prefix.is_empty() {
+            message = format!("{} {}", prefix, message);
+        }
+        values.extend(fields.values);
+
+        let level = python_level(metadata.level());
+        forward(metadata.level(), LogRecord { logger, level, message, fields: values });
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(at) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                entered.remove(at);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        let mut spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(span) = spans.get_mut(&span.into_u64()) {
+            span.references += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut closed = Vec::new();
+        {
+            let mut spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+            let mut next = Some(span.into_u64());
+            // Closing a span releases its parent's reference to it in turn
+            while let Some(id) = next.take() {
+                let Some(span) = spans.get_mut(&id) else {
+                    break;
+                };
+                span.references -= 1;
+                if span.references == 0 {
+                    next = span.parent;
+                    closed.push((id, span.started.elapsed(), span.metadata));
+                }
+            }
+        }
+
+        let closed_span = closed.first().map(|(id, ..)| *id) == Some(span.into_u64());
+        for (id, elapsed, metadata) in closed {
+            if let Some(logger) = logger(metadata.target()) {
+                let (prefix, mut values) = self.context(Some(id));
+                let seconds = elapsed.as_secs_f64();
+                values.push(("elapsed", Value::Float(seconds)));
+                let message = format!("{} finished in {:.3}ms", prefix, seconds * 1000.0);
+                forward(
+                    metadata.level(),
+                    LogRecord {
+                        logger,
+                        level: python_level(metadata.level()),
+                        message,
+                        fields: values,
+                    },
+                );
+            }
+            self.spans.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+        }
+        closed_span
+    }
+}
+
+/// Returns the id of the interpreter records from `interpreter` go to.
+fn destination(interpreter: Option<Interpreter>) -> i64 {
+    interpreter.map_or(interpreter::MAIN, Interpreter::id)
+}
+
+/// Queues `record`, of `level`, for the forwarding thread, starting it on the first
+/// record, unless the interpreter it goes to doesn't forward that level.
+fn forward(level: &Level, record: LogRecord) {
+    let interpreter = interpreter::calling();
+    let forwarded = LEVELS.of(destination(interpreter));
+    if verbosity(level) > forwarded.map_or(0, |forwarded| forwarded.load(Ordering::Relaxed)) {
+        return;
+    }
+    {
+        let mut queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+        if queue.len() < QUEUE_LIMIT {
+            queue.push_back((interpreter, record));
+        }
+    }
+
+    FORWARDER.get_or_init(|| {
+        thread::Builder::new()
+            .name("ferrite-logging".into())
+            .spawn(|| loop {
+                {
+                    let queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+                    drop(QUEUED.wait_while(queue, |queue| queue.is_empty()));
+                }
+                // SAFETY: Py_IsInitialized can be called at any time
+                if unsafe { pyo3::ffi::Py_IsInitialized() } == 0 {
+                    return;
+                }
+                drain_all();
+            })
+            .ok();
+    });
+    QUEUED.notify_one();
+}
+
+/// Sends every queued record to its logger in its interpreter, in order.
+fn drain_all() {
+    let mut records = std::mem::take(&mut *QUEUE.lock().unwrap_or_else(|e| e.into_inner()));
+    while let Some(&(interpreter, _)) = records.front() {
+        let id = destination(interpreter);
+        let (these, others): (VecDeque<_>, _) =
+            records.into_iter().partition(|(of, _)| destination(*of) == id);
+        records = others;
+        let emit_all = |py: Python| {
+            these.into_iter().for_each(|(_, record)| emit(py, record));
+            Ok(())
+        };
+        // Dropped along with their records if the interpreter is exiting
+        let _ = match interpreter {
+            Some(interpreter) => interpreter.with_gil(emit_all),
+            None => Python::with_gil(emit_all),
+        };
+    }
+}
+
+/// Sends the queued records of the interpreter the GIL is held in to their loggers, in
+/// order.
+fn drain(py: Python) {
+    let id = Interpreter::current(py).id();
+    let records = {
+        let mut queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+        let (these, others): (VecDeque<_>, _) =
+            queue.drain(..).partition(|(of, _)| destination(*of) == id);
+        *queue = others;
+        these
+    };
+    for (_, record) in records {
+        emit(py, record);
+    }
+}
+
+/// Sends `record` to its logger, reporting any failure as unraisable.
+fn emit(py: Python, record: LogRecord) {
+    if let Err(e) = log(py, record) {
+        e.write_unraisable(py, None);
+    }
+}
+
+fn log(py: Python, record: LogRecord) -> PyResult<()> {
+    let logger = py.import("logging")?.call_method1("getLogger", (record.logger,))?;
+    let fields = PyDict::new(py);
+    for (name, value) in record.fields {
+        fields.set_item(name, value.to_python(py)?)?;
+    }
+    let extra = PyDict::new(py);
+    extra.set_item("ferrite_fields", fields)?;
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("extra", extra)?;
+    logger.call_method("log", (record.level, "%s", record.message), Some(&kwargs))?;
+    Ok(())
+}
+
+/// Installs the subscriber as the process-wide default, unless a Rust extension loaded
+/// earlier has installed its own.
+pub fn install() {
+    tracing::subscriber::set_global_default(Bridge::default()).ok();
+}
+
+/// Sets which records ferrite forwards to the calling interpreter's `logging`.
+///
+/// # Arguments
+/// * `level` - A `logging` level or its name, such as `logging.DEBUG` or `"INFO"`, or
+///   `None` to forward nothing, the default. `"TRACE"` or 5 also forwards the most
+///   detailed records, which `logging` has no name for.
+#[pyfunction]
+#[pyo3(signature = (level))]
+pub fn set_log_level(py: Python, level: Option<&Bound<PyAny>>) -> PyResult<()> {
+    let number = match level {
+        None => None,
+        Some(level) => Some(match level.extract::<i64>() {
+            Ok(number) => number,
+            Err(_) => {
+                let name = level.extract::<String>()?;
+                match name.to_uppercase().as_str() {
+                    "TRACE" => 5,
+                    "DEBUG" => 10,
+                    "INFO" => 20,
+                    "WARN" | "WARNING" => 30,
+                    "ERROR" => 40,
+                    "CRITICAL" | "FATAL" => 50,
+                    _ => {
+                        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+                            "Unknown log level {:?}",
+                            name
+                        )))
+                    }
+                }
+            }
+        }),
+    };
+    let verbosity = match number {
+        None => 0,
+        Some(..=5) => 5,
+        Some(6..=10) => 4,
+        Some(11..=20) => 3,
+        Some(21..=30) => 2,
+        Some(_) => 1,
+    };
+    LEVELS.get(py).store(verbosity, Ordering::Relaxed);
+    update_level();
+    Ok(())
+}
+
+/// Sets `LEVEL` to the most verbose level of any interpreter.
+fn update_level() {
+    let levels = LEVELS.all();
+    let level = levels.iter().map(|level| level.load(Ordering::Relaxed)).max().unwrap_or(0);
+    LEVEL.store(level, Ordering::Relaxed);
+    // The macros check the highest level any subscriber wants before calling in
+    tracing::callsite::rebuild_interest_cache();
+}
+
+/// Stops forwarding to the interpreter the GIL is held in, once its module is freed.
+pub fn clear(py: Python) {
+    if LEVELS.take(py).is_some() {
+        update_level();
+    }
+    let id = Interpreter::current(py).id();
+    QUEUE.lock().unwrap_or_else(|e| e.into_inner()).retain(|(of, _)| destination(*of) != id);
+}
+
+/// Forwards the records queued so far, without waiting for the background thread to.
+#[pyfunction]
+pub fn flush_logs(py: Python) {
+    drain(py);
+}
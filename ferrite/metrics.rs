@@ -0,0 +1,121 @@
+/*!
+Per-key signing metrics.
+
+Every signing call is counted against the address of its key: signatures produced by
+kind, calls that failed, calls the policy, approval hook or rate limit denied, and a
+histogram of call latency. `get_metrics` returns a snapshot for monitoring to alert on,
+for example, a key suddenly signing far more than usual.
+
+Counting takes one lock per call rather than per signature, so batches cost the same
+as single signatures. Calls that fail before their key could be parsed are counted
+under `"unknown"`.
+*/
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use ethers_core::types::Address;
+use ethers_core::utils::to_checksum;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::errors;
+
+/// Upper bounds of the latency histogram buckets, in seconds. A final bucket counts
+/// everything slower.
+const BUCKETS: [f64; 10] = [0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.1, 1.0];
+
+const KINDS: [&str; 3] = ["hash", "typed_data", "transaction"];
+
+#[derive(Default)]
+struct KeyMetrics {
+    /// Signatures produced, indexed like `KINDS`.
+    signatures: [u64; KINDS.len()],
+    failures: u64,
+    denials: u64,
+    latency_counts: [u64; BUCKETS.len() + 1],
+    latency_sum: f64,
+}
+
+static METRICS: Mutex<Option<HashMap<Option<Address>, KeyMetrics>>> = Mutex::new(None);
+
+/// Counts one signing call of `kind` that started at `started`.
+///
+/// `address` is that of the signing key, or `None` if the call failed before it was
+/// known. `outcome` is the number of signatures produced, or the error the call failed
+/// with, which is told apart from a denial in the interpreter that made the call.
+pub fn observe(
+    py: Python,
+    kind: &'static str,
+    address: Option<Address>,
+    outcome: Result<usize, &mut PyErr>,
+    started: Instant,
+) {
+    let elapsed = started.elapsed().as_secs_f64();
+    let (signatures, failed, denied) = match outcome {
+        Ok(count) => (count as u64, false, false),
+        Err(e) => {
+            errors::normalize(py, e);
+            let denied = e.is_instance_of::<errors::PolicyViolationError>(py)
+                || e.is_instance_of::<errors::ApprovalDeniedError>(py)
+                || e.is_instance_of::<errors::RateLimitError>(py);
+            (0, !denied, denied)
+        }
+    };
+
+    let mut metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    let key = metrics.get_or_insert_with(HashMap::new).entry(address).or_default();
+    if let Some(index) = KINDS.iter().position(|k| *k == kind) {
+        key.signatures[index] += signatures;
+    }
+    key.failures += failed as u64;
+    key.denials += denied as u64;
+    let bucket = BUCKETS.iter().position(|bound| elapsed <= *bound).unwrap_or(BUCKETS.len());
+    key.latency_counts[bucket] += 1;
+    key.latency_sum += elapsed;
+}
+
+/// Returns signing metrics per key address.
+///
+/// # Arguments
+/// * `reset` - Whether to zero every counter after reading it.
+///
+/// # Returns
+/// A dictionary from checksummed address (or `"unknown"`) to a dictionary of:
+/// `signatures` (counts by `hash`, `typed_data` and `transaction`), `failures`,
+/// `denials` (by the policy, approval hook or rate limit) and `latency`, a histogram of
+/// call latency with `buckets` (upper bounds in seconds), `counts` (one more than
+/// `buckets`, the last for slower calls), `sum` (seconds) and `count` (calls).
+#[pyfunction]
+#[pyo3(signature = (*, reset = false))]
+pub fn get_metrics(py: Python, reset: bool) -> PyResult<PyObject> {
+    let mut metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    let snapshot = if reset { metrics.take() } else { None };
+    let metrics = snapshot.as_ref().or(metrics.as_ref());
+
+    let result = PyDict::new(py);
+    for (address, key) in metrics.into_iter().flatten() {
+        let signatures = PyDict::new(py);
+        for (kind, count) in KINDS.iter().zip(key.signatures) {
+            signatures.set_item(kind, count)?;
+        }
+
+        let latency = PyDict::new(py);
+        latency.set_item("buckets", BUCKETS.to_vec())?;
+        latency.set_item("counts", key.latency_counts.to_vec())?;
+        latency.set_item("sum", key.latency_sum)?;
+        latency.set_item("count", key.latency_counts.iter().sum::<u64>())?;
+
+        let entry = PyDict::new(py);
+        entry.set_item("signatures", signatures)?;
+        entry.set_item("failures", key.failures)?;
+        entry.set_item("denials", key.denials)?;
+        entry.set_item("latency", latency)?;
+
+        let name = address.map_or("unknown".to_string(), |address| to_checksum(&address, None));
+        result.set_item(name, entry)?;
+    }
+
+    Ok(result.into_any().unbind())
+}
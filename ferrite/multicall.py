@@ -0,0 +1,72 @@
+"""
+Multicall3 bundles.
+
+Multicall3 is deployed at the same address on most EVM chains, and its ``aggregate3``
+makes a list of calls in one transaction or ``eth_call``, each either required to
+succeed or allowed to fail. Batch jobs build the bundle here and sign it as an ordinary
+transaction to ``MULTICALL3_ADDRESS``.
+"""
+
+from typing import Iterable, List, NamedTuple, Tuple, Union
+
+from hexbytes import HexBytes
+from _ferrite import decode_abi, encode_calldata  # type: ignore
+
+MULTICALL3_ADDRESS = "0xcA11bde05977b3631167028862bE2a173976CA11"
+
+_AGGREGATE3 = "aggregate3((address,bool,bytes)[])"
+
+
+class Call3(NamedTuple):
+    """A call in an ``aggregate3`` bundle."""
+
+    target: str
+    call_data: bytes
+    allow_failure: bool = False
+
+
+class Result3(NamedTuple):
+    """The outcome of a call in an ``aggregate3`` bundle."""
+
+    success: bool
+    return_data: HexBytes
+
+
+def multicall3_calldata(
+    calls: Iterable[Union[Call3, Tuple[str, bytes], Tuple[str, bytes, bool]]],
+) -> HexBytes:
+    """
+    Encodes calls as the calldata of Multicall3's ``aggregate3``.
+
+    Args:
+        calls: The calls, as ``Call3`` or ``(target, call_data, allow_failure)``
+            tuples, in order. ``allow_failure`` defaults to ``False``, which makes the
+            whole bundle revert if the call does.
+
+    Returns:
+        The calldata, for a transaction or ``eth_call`` to ``MULTICALL3_ADDRESS``.
+    """
+    calls = [Call3(*call) for call in calls]
+    return HexBytes(
+        encode_calldata(
+            _AGGREGATE3,
+            [
+                [
+                    (call.target, call.allow_failure, bytes(HexBytes(call.call_data)))
+                    for call in calls
+                ]
+            ],
+        )
+    )
+
+
+def decode_multicall3_results(return_data: bytes) -> List[Result3]:
+    """
+    Decodes what ``aggregate3`` returns, such as the result of an ``eth_call``.
+
+    Returns:
+        A ``Result3`` for each call, in the order the calls were made. Failed calls
+        carry their revert data.
+    """
+    [results] = decode_abi(["(bool,bytes)[]"], bytes(HexBytes(return_data)))
+    return [Result3(success, HexBytes(data)) for success, data in results]
@@ -0,0 +1,72 @@
+"""
+OP Stack bridge hashes, for verifying bridge messages against the contracts.
+
+Withdrawals are identified on L1 by the hash of their ``WithdrawalTransaction``, and
+deposits on L2 by a transaction whose ``sourceHash`` derives from the L1 log that
+requested it.
+"""
+
+from typing import Any, Dict
+
+from hexbytes import HexBytes
+from _ferrite import (  # type: ignore
+    op_deposit_source_hash as rust_op_deposit_source_hash,
+    op_deposit_transaction_hash as rust_op_deposit_transaction_hash,
+    op_withdrawal_hash as rust_op_withdrawal_hash,
+)
+from .account import _quantities_json
+
+
+def op_withdrawal_hash(withdrawal: Dict[str, Any]) -> HexBytes:
+    """
+    Computes the hash of an L2-to-L1 withdrawal, as ``Hashing.hashWithdrawal`` does.
+
+    Args:
+        withdrawal: The ``WithdrawalTransaction`` fields, as the ``MessagePassed``
+            event logs them: ``nonce``, ``sender``, ``target``, ``value``,
+            ``gasLimit`` and ``data``.
+
+    Returns:
+        The withdrawal hash the ``OptimismPortal`` proves and finalizes, and the
+        ``L2ToL1MessagePasser`` marks in ``sentMessages``.
+    """
+    return HexBytes(rust_op_withdrawal_hash(_quantities_json(withdrawal)))
+
+
+def op_deposit_source_hash(l1_block_hash: bytes, log_index: int) -> HexBytes:
+    """
+    Computes the ``sourceHash`` of a user deposit, from the L1 block and the index
+    in it of the ``TransactionDeposited`` log that requested the deposit.
+    """
+    return HexBytes(
+        rust_op_deposit_source_hash(bytes(HexBytes(l1_block_hash)), log_index)
+    )
+
+
+def op_l1_info_deposit_source_hash(
+    l1_block_hash: bytes, sequence_number: int
+) -> HexBytes:
+    """
+    Computes the ``sourceHash`` of the L1 attributes deposit that starts an L2 block,
+    from the L1 origin block and the L2 block's sequence number in its epoch.
+    """
+    return HexBytes(
+        rust_op_deposit_source_hash(
+            bytes(HexBytes(l1_block_hash)), sequence_number, l1_info=True
+        )
+    )
+
+
+def op_deposit_transaction_hash(deposit: Dict[str, Any]) -> HexBytes:
+    """
+    Computes the L2 transaction hash of a deposit.
+
+    Args:
+        deposit: The deposit transaction's fields: ``sourceHash``, ``from``, ``to``
+            (absent or None for contract creation), ``mint``, ``value``, ``gas``,
+            ``isSystemTransaction`` and ``data``.
+
+    Returns:
+        The hash of the deposit's type ``0x7E`` transaction on L2.
+    """
+    return HexBytes(rust_op_deposit_transaction_hash(_quantities_json(deposit)))
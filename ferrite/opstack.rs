@@ -0,0 +1,215 @@
+/*!
+OP Stack bridge hashes, as the L1 and L2 bridge contracts and nodes compute them.
+
+A withdrawal is proven and finalized on L1 by the hash of its `WithdrawalTransaction`,
+which the `L2ToL1MessagePasser` records in its `sentMessages` mapping. A deposit shows
+up on L2 as a type `0x7E` transaction whose `sourceHash` ties it to the L1 log or block
+it came from, so that each deposit gets a unique L2 transaction hash.
+*/
+
+use ethers_core::abi::{encode, Token};
+use ethers_core::types::{Address, Bytes, H256, U256};
+use ethers_core::utils::rlp::RlpStream;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::request::{self, required};
+use crate::{errors, keccak};
+
+/// The EIP-2718 type of deposit transactions.
+const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+/// Source hash domains, separating deposits from different origins.
+const USER_DEPOSIT_DOMAIN: u64 = 0;
+const L1_INFO_DEPOSIT_DOMAIN: u64 = 1;
+
+/// Parses a JSON payload into its fields, naming `kind` if it isn't a JSON object.
+fn fields(payload: &str, kind: &str) -> PyResult<request::Fields> {
+    serde_json::from_str(payload).map_err(|e| {
+        PyErr::new::<errors::InvalidTransactionError, _>(format!(
+            "Invalid {} JSON: {}",
+            kind, e
+        ))
+    })
+}
+
+/// Parses the bytes32 field `name`.
+fn word(fields: &request::Fields, name: &str) -> request::FieldResult<H256> {
+    match request::hex_data(fields, name)? {
+        Some(data) if data.len() != 32 => {
+            Err(format!("Invalid `{}`: expected 32 bytes, got {}", name, data.len()))
+        }
+        data => Ok(data.map(|data| H256::from_slice(&data))),
+    }
+}
+
+/// The fields of an L2-to-L1 `WithdrawalTransaction`.
+struct Withdrawal {
+    nonce: U256,
+    sender: Address,
+    target: Address,
+    value: U256,
+    gas_limit: U256,
+    data: Bytes,
+}
+
+impl Withdrawal {
+    fn parse(payload: &str) -> PyResult<Self> {
+        let fields = fields(payload, "Withdrawal")?;
+        let mut problems = request::Problems::default();
+        let nonce = problems.check(required("nonce", request::quantity(&fields, "nonce", 256)));
+        let sender = problems.check(required("sender", request::address(&fields, "sender", false)));
+        let target = problems.check(required("target", request::address(&fields, "target", false)));
+        let value = problems.check(request::quantity(&fields, "value", 256));
+        let gas_limit = request::quantity(&fields, "gasLimit", 256);
+        let gas_limit = problems.check(required("gasLimit", gas_limit));
+        let data = problems.check(request::hex_data(&fields, "data"));
+        problems.into_message().map_err(errors::invalid_transaction)?;
+
+        // Every required field parsed, or `into_message` would have failed
+        Ok(Withdrawal {
+            nonce: nonce.unwrap(),
+            sender: sender.unwrap(),
+            target: target.unwrap(),
+            value: value.unwrap_or_default(),
+            gas_limit: gas_limit.unwrap(),
+            data: data.unwrap_or_default(),
+        })
+    }
+
+    /// Returns `Hashing.hashWithdrawal`: the keccak256 of the ABI-encoded fields.
+    fn hash(&self) -> H256 {
+        H256(keccak::keccak256(&encode(&[
+            Token::Uint(self.nonce),
+            Token::Address(self.sender),
+            Token::Address(self.target),
+            Token::Uint(self.value),
+            Token::Uint(self.gas_limit),
+            Token::Bytes(self.data.to_vec()),
+        ])))
+    }
+}
+
+/// The fields of a deposit transaction.
+struct Deposit {
+    source_hash: H256,
+    from: Address,
+    to: Option<Address>,
+    mint: U256,
+    value: U256,
+    gas: U256,
+    is_system_transaction: bool,
+    data: Bytes,
+}
+
+impl Deposit {
+    fn parse(payload: &str) -> PyResult<Self> {
+        let fields = fields(payload, "Deposit")?;
+        let mut problems = request::Problems::default();
+        let source_hash = problems.check(required("sourceHash", word(&fields, "sourceHash")));
+        let from = problems.check(required("from", request::address(&fields, "from", false)));
+        let to = problems.check(request::address(&fields, "to", false));
+        let mint = problems.check(request::quantity(&fields, "mint", 256));
+        let value = problems.check(request::quantity(&fields, "value", 256));
+        let gas = problems.check(required("gas", request::quantity(&fields, "gas", 64)));
+        let is_system_transaction = problems.check(request::field(&fields, "isSystemTransaction"));
+        let data = problems.check(request::hex_data(&fields, "data"));
+        problems.into_message().map_err(errors::invalid_transaction)?;
+
+        Ok(Deposit {
+            source_hash: source_hash.unwrap(),
+            from: from.unwrap(),
+            to,
+            mint: mint.unwrap_or_default(),
+            value: value.unwrap_or_default(),
+            gas: gas.unwrap(),
+            is_system_transaction: is_system_transaction.unwrap_or(false),
+            data: data.unwrap_or_default(),
+        })
+    }
+
+    /// Returns the L2 transaction hash: the keccak256 of the type byte and the RLP
+    /// encoded fields. Deposits carry no signature.
+    fn hash(&self) -> H256 {
+        let mut rlp = RlpStream::new_list(8);
+        rlp.append(&self.source_hash);
+        rlp.append(&self.from);
+        match &self.to {
+            Some(to) => rlp.append(to),
+            None => rlp.append(&""),
+        };
+        rlp.append(&self.mint);
+        rlp.append(&self.value);
+        rlp.append(&self.gas);
+        rlp.append(&u8::from(self.is_system_transaction));
+        rlp.append(&self.data.as_ref());
+
+        let encoded = [&[DEPOSIT_TX_TYPE][..], &rlp.out()].concat();
+        H256(keccak::keccak256(&encoded))
+    }
+}
+
+/// Computes the hash the OP Stack bridge contracts identify a withdrawal by.
+///
+/// # Arguments
+/// * `payload` - JSON string of the `WithdrawalTransaction`, with the fields of the
+///   `MessagePassed` event: `nonce`, `sender`, `target`, `value`, `gasLimit` and `data`.
+///
+/// # Returns
+/// The 32-byte withdrawal hash.
+#[pyfunction]
+pub fn op_withdrawal_hash(py: Python, payload: &str) -> PyResult<PyObject> {
+    let hash = py.allow_threads(|| Withdrawal::parse(payload).map(|w| w.hash()))?;
+    Ok(PyBytes::new(py, hash.as_bytes()).into_any().unbind())
+}
+
+/// Computes the source hash of a deposit transaction.
+///
+/// # Arguments
+/// * `l1_block_hash` - Hash of the L1 block the deposit came from.
+/// * `index` - Index of the `TransactionDeposited` log in the block for user deposits,
+///   or the sequence number in the epoch for L1 attributes deposits.
+/// * `l1_info` - Compute the source hash of an L1 attributes deposit rather than a user
+///   deposit.
+///
+/// # Returns
+/// The 32-byte source hash.
+#[pyfunction]
+#[pyo3(signature = (l1_block_hash, index, *, l1_info = false))]
+pub fn op_deposit_source_hash(
+    py: Python,
+    l1_block_hash: &[u8],
+    index: u64,
+    l1_info: bool,
+) -> PyResult<PyObject> {
+    if l1_block_hash.len() != 32 {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "L1 block hash must be 32 bytes, got {}",
+            l1_block_hash.len()
+        )));
+    }
+    let domain = if l1_info { L1_INFO_DEPOSIT_DOMAIN } else { USER_DEPOSIT_DOMAIN };
+
+    let mut deposit_id = [0u8; 64];
+    deposit_id[..32].copy_from_slice(l1_block_hash);
+    U256::from(index).to_big_endian(&mut deposit_id[32..]);
+    let mut input = [0u8; 64];
+    U256::from(domain).to_big_endian(&mut input[..32]);
+    input[32..].copy_from_slice(&keccak::keccak256(&deposit_id));
+
+    Ok(PyBytes::new(py, &keccak::keccak256(&input)).into_any().unbind())
+}
+
+/// Computes the L2 transaction hash of a deposit transaction.
+///
+/// # Arguments
+/// * `payload` - JSON string of the deposit: `sourceHash`, `from`, `to` (absent for
+///   contract creation), `mint`, `value`, `gas`, `isSystemTransaction` and `data`.
+///
+/// # Returns
+/// The 32-byte transaction hash.
+#[pyfunction]
+pub fn op_deposit_transaction_hash(py: Python, payload: &str) -> PyResult<PyObject> {
+    let hash = py.allow_threads(|| Deposit::parse(payload).map(|d| d.hash()))?;
+    Ok(PyBytes::new(py, hash.as_bytes()).into_any().unbind())
+}
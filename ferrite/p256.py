@@ -0,0 +1,128 @@
+"""
+secp256r1 (P-256) keys and signatures, for passkey and session-key smart accounts.
+
+Passkeys sign with P-256, and account-abstraction stacks that validate them through the
+RIP-7212 ``P256VERIFY`` precompile also let a backend hold P-256 session keys of its
+own. Signatures are plain ECDSA over a 32-byte hash, with ``s`` kept in the low half of
+the curve order.
+"""
+
+import secrets
+from typing import Any, NamedTuple, Tuple, Union
+
+from hexbytes import HexBytes
+from _ferrite import (  # type: ignore
+    p256_public_key as rust_p256_public_key,
+    p256_sign_hash as rust_p256_sign_hash,
+    p256_verify as rust_p256_verify,
+)
+from .account import _private_key_bytes
+
+# The RIP-7212 precompile, at the same address on every chain that has it
+P256_VERIFY_PRECOMPILE = "0x0000000000000000000000000000000000000100"
+
+_ORDER = 0xFFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551
+
+
+class P256PublicKey(NamedTuple):
+    """A P-256 public key's affine coordinates, as account contracts store them."""
+
+    x: int
+    y: int
+
+
+class P256Signature(NamedTuple):
+    """A P-256 ECDSA signature."""
+
+    r: int
+    s: int
+
+
+def _public_key_bytes(public_key: Union[Tuple[int, int], bytes, str]) -> bytes:
+    if isinstance(public_key, tuple):
+        x, y = public_key
+        return x.to_bytes(32, "big") + y.to_bytes(32, "big")
+    return bytes(HexBytes(public_key))
+
+
+def _signature_words(
+    signature: Union[Tuple[int, int], bytes, str]
+) -> Tuple[bytes, bytes]:
+    if isinstance(signature, tuple):
+        r, s = signature
+        return r.to_bytes(32, "big"), s.to_bytes(32, "big")
+    signature = bytes(HexBytes(signature))
+    if len(signature) != 64:
+        raise ValueError(f"Expected a 64-byte r || s signature, got {len(signature)}")
+    return signature[:32], signature[32:]
+
+
+def create_p256_key() -> HexBytes:
+    """Generates a random P-256 private key, such as for a new session key."""
+    return HexBytes((secrets.randbelow(_ORDER - 1) + 1).to_bytes(32, "big"))
+
+
+def p256_public_key(private_key: Any) -> P256PublicKey:
+    """Returns the public key of a P-256 private key, given as bytes or hex."""
+    public = rust_p256_public_key(_private_key_bytes(private_key))
+    return P256PublicKey(
+        int.from_bytes(public[:32], "big"), int.from_bytes(public[32:], "big")
+    )
+
+
+def p256_sign_hash(message_hash: bytes, private_key: Any) -> P256Signature:
+    """
+    Signs a 32-byte hash with a P-256 private key.
+
+    Args:
+        message_hash: The hash the account's validation checks, such as a user
+            operation hash.
+        private_key: The P-256 private key, as bytes or hex.
+
+    Returns:
+        The signature, with a deterministic RFC 6979 nonce and a low ``s``.
+    """
+    r, s = rust_p256_sign_hash(
+        bytes(HexBytes(message_hash)), _private_key_bytes(private_key)
+    )
+    return P256Signature(int.from_bytes(r, "big"), int.from_bytes(s, "big"))
+
+
+def p256_verify(
+    message_hash: bytes,
+    signature: Union[Tuple[int, int], bytes, str],
+    public_key: Union[Tuple[int, int], bytes, str],
+) -> bool:
+    """
+    Checks a P-256 signature as the RIP-7212 precompile does, accepting high ``s``.
+
+    Args:
+        message_hash: The signed 32-byte hash.
+        signature: The signature, as ``(r, s)`` or 64 bytes of ``r || s``.
+        public_key: The public key, as ``(x, y)`` or its 64 or 65-byte uncompressed
+            encoding.
+
+    Returns:
+        Whether the signature is valid.
+    """
+    r, s = _signature_words(signature)
+    return rust_p256_verify(
+        bytes(HexBytes(message_hash)), r, s, _public_key_bytes(public_key)
+    )
+
+
+def p256_precompile_input(
+    message_hash: bytes,
+    signature: Union[Tuple[int, int], bytes, str],
+    public_key: Union[Tuple[int, int], bytes, str],
+) -> HexBytes:
+    """
+    Builds the 160-byte input of the RIP-7212 precompile: the hash, ``r``, ``s`` and
+    the public key's ``x`` and ``y``, for simulating an account's validation with
+    ``eth_call``.
+    """
+    r, s = _signature_words(signature)
+    public = _public_key_bytes(public_key)
+    if len(public) == 65:
+        public = public[1:]
+    return HexBytes(bytes(HexBytes(message_hash)) + r + s + public)
@@ -0,0 +1,207 @@
+/*!
+secp256r1 (P-256) signatures, for passkey and session-key smart accounts.
+
+Accounts validated through the RIP-7212 `P256VERIFY` precompile, or a Solidity fallback
+such as OpenZeppelin's `P256`, check plain ECDSA signatures over a 32-byte hash, with the
+public key given as its affine coordinates. Signatures use RFC 6979 nonces and are
+normalized to the low half of the order, which the precompile accepts either way but
+contract verifiers often require to rule out malleability.
+*/
+
+use crypto_bigint::modular::constant_mod::{Residue, ResidueParams};
+use crypto_bigint::{impl_modulus, Encoding, U256};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rfc6979::consts::U32;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::curve::{self, Curve};
+use crate::errors;
+
+impl_modulus!(
+    FieldPrime,
+    U256,
+    "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff"
+);
+impl_modulus!(
+    CurveOrder,
+    U256,
+    "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551"
+);
+
+type Element = Residue<FieldPrime, { U256::LIMBS }>;
+type Scalar = Residue<CurveOrder, { U256::LIMBS }>;
+
+/// The NIST curve `y² = x³ - 3x + b`.
+#[derive(Clone, Copy)]
+struct P256;
+
+impl Curve for P256 {
+    type Element = Element;
+    const A: Element = Element::new(&U256::from_u8(3)).neg();
+    const B: Element = Element::new(&U256::from_be_hex(
+        "5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b",
+    ));
+}
+
+type Point = curve::Point<P256>;
+
+const GENERATOR: Point = Point::from_hex(
+    "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+    "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5",
+);
+
+/// Parses a 32-byte big-endian integer, naming it `what` if it's the wrong length.
+fn word(bytes: &[u8], what: &str) -> PyResult<U256> {
+    if bytes.len() != 32 {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "{} must be 32 bytes, got {}",
+            what,
+            bytes.len()
+        )));
+    }
+    Ok(U256::from_be_slice(bytes))
+}
+
+/// Parses a P-256 private key, which must be non-zero and below the curve order.
+fn private_key(bytes: &[u8]) -> PyResult<U256> {
+    let key = word(bytes, "P-256 private key")
+        .map_err(|e| PyErr::new::<errors::InvalidKeyError, _>(e.to_string()))?;
+    if key == U256::ZERO || key >= CurveOrder::MODULUS {
+        return Err(PyErr::new::<errors::InvalidKeyError, _>(
+            "P-256 private key must be non-zero and below the curve order",
+        ));
+    }
+    Ok(key)
+}
+
+/// Parses an uncompressed public key, with or without its `0x04` prefix.
+fn public_key(bytes: &[u8]) -> PyResult<Point> {
+    let coordinates = match bytes {
+        [0x04, rest @ ..] if rest.len() == 64 => rest,
+        _ if bytes.len() == 64 => bytes,
+        _ => {
+            return Err(PyErr::new::<errors::InvalidKeyError, _>(format!(
+                "P-256 public key must be 64 bytes, or 65 with a 0x04 prefix, got {}",
+                bytes.len()
+            )))
+        }
+    };
+    let x = U256::from_be_slice(&coordinates[..32]);
+    let y = U256::from_be_slice(&coordinates[32..]);
+    Point::from_affine(&x, &y).ok_or_else(|| {
+        PyErr::new::<errors::InvalidKeyError, _>("P-256 public key is not on the curve")
+    })
+}
+
+fn sign(msg_hash: &U256, private_key: &U256) -> (U256, U256) {
+    let d = Scalar::new(private_key);
+    let z = Scalar::new(msg_hash);
+    let mut x = rfc6979::ByteArray::<U32>::from(private_key.to_be_bytes());
+    let h = z.retrieve().to_be_bytes().into();
+    let n = CurveOrder::MODULUS.to_be_bytes().into();
+    let mut attempt = 0u64;
+    let signature = loop {
+        // A zero `r` or `s` has negligible odds; retry with the attempt as extra data
+        let extra = attempt.to_be_bytes();
+        let extra = if attempt == 0 { &[][..] } else { &extra[..] };
+        attempt += 1;
+
+        let mut k_bytes = rfc6979::generate_k::<Sha256, U32>(&x, &n, &h, extra);
+        let mut k = U256::from_be_slice(&k_bytes);
+        k_bytes.zeroize();
+        let r = GENERATOR.mul(&k, 256).x().expect("nonce is below the curve order");
+        let r = Scalar::new(&r);
+        let (k_inv, _) = Scalar::new(&k).invert();
+        k.zeroize();
+        let s = k_inv * (z + r * d);
+        if r == Scalar::ZERO || s == Scalar::ZERO {
+            continue;
+        }
+        let s = s.retrieve();
+        let high = s > CurveOrder::MODULUS.shr_vartime(1);
+        break (r.retrieve(), if high { CurveOrder::MODULUS.wrapping_sub(&s) } else { s });
+    };
+    x.zeroize();
+    signature
+}
+
+/// Checks a signature as the RIP-7212 precompile does, accepting either `s`.
+fn verify(msg_hash: &U256, r: &U256, s: &U256, public_key: &Point) -> bool {
+    let in_range = |v: &U256| *v != U256::ZERO && *v < CurveOrder::MODULUS;
+    if !in_range(r) || !in_range(s) {
+        return false;
+    }
+    let (w, _) = Scalar::new(s).invert();
+    let u1 = (Scalar::new(msg_hash) * w).retrieve();
+    let u2 = (Scalar::new(r) * w).retrieve();
+    let point = GENERATOR.mul(&u1, 256).add(&public_key.mul(&u2, 256));
+    point.x().is_some_and(|x| Scalar::new(&x).retrieve() == *r)
+}
+
+/// Computes the P-256 public key of a private key.
+///
+/// # Arguments
+/// * `private_key` - The 32-byte big-endian private key.
+///
+/// # Returns
+/// The 64-byte public key, its x and y coordinates.
+#[pyfunction]
+pub fn p256_public_key(py: Python, private_key: &[u8]) -> PyResult<PyObject> {
+    let mut key = self::private_key(private_key)?;
+    let point = py.allow_threads(|| GENERATOR.mul(&key, 256).affine());
+    key.zeroize();
+    let (x, y) = point.expect("private key is below the curve order");
+    let public = [x.to_be_bytes(), y.to_be_bytes()].concat();
+    Ok(PyBytes::new(py, &public).into_any().unbind())
+}
+
+/// Signs a 32-byte hash with a P-256 private key.
+///
+/// # Arguments
+/// * `msg_hash` - The 32-byte hash to sign.
+/// * `private_key` - The 32-byte big-endian private key.
+///
+/// # Returns
+/// A tuple of the 32-byte `r` and low `s`.
+#[pyfunction]
+pub fn p256_sign_hash(
+    py: Python,
+    msg_hash: &[u8],
+    private_key: &[u8],
+) -> PyResult<(PyObject, PyObject)> {
+    let msg_hash = word(msg_hash, "Message hash")?;
+    let mut key = self::private_key(private_key)?;
+    let (r, s) = py.allow_threads(|| sign(&msg_hash, &key));
+    key.zeroize();
+    Ok((
+        PyBytes::new(py, &r.to_be_bytes()).into_any().unbind(),
+        PyBytes::new(py, &s.to_be_bytes()).into_any().unbind(),
+    ))
+}
+
+/// Verifies a P-256 signature as the RIP-7212 precompile does.
+///
+/// # Arguments
+/// * `msg_hash` - The 32-byte signed hash.
+/// * `r` - The signature's 32-byte `r`.
+/// * `s` - The signature's 32-byte `s`, high or low.
+/// * `public_key` - The 64-byte public key, or 65 bytes with a `0x04` prefix.
+///
+/// # Returns
+/// Whether the signature is valid. Malformed public keys raise `InvalidKeyError`.
+#[pyfunction]
+pub fn p256_verify(
+    py: Python,
+    msg_hash: &[u8],
+    r: &[u8],
+    s: &[u8],
+    public_key: &[u8],
+) -> PyResult<bool> {
+    let msg_hash = word(msg_hash, "Message hash")?;
+    let r = word(r, "Signature r")?;
+    let s = word(s, "Signature s")?;
+    let public_key = self::public_key(public_key)?;
+    Ok(py.allow_threads(|| verify(&msg_hash, &r, &s, &public_key)))
+}
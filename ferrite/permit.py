@@ -0,0 +1,397 @@
+"""
+Gasless token approvals and transfers.
+
+An EIP-2612 ``permit`` lets a token holder approve a spender with a signature instead
+of an ``approve`` transaction: the holder signs a ``Permit`` in the token's EIP-712
+domain, and anyone can submit it with the signature split into ``v``, ``r`` and ``s``.
+DAI and a few older tokens predate EIP-2612 and sign a ``Permit`` of their own, which
+approves or revokes an unlimited allowance. Uniswap's Permit2 contract extends the
+same idea to any token through its own ``PermitSingle``, ``PermitBatch`` and
+``PermitTransferFrom`` structures. EIP-3009 tokens such as USDC go a step further and
+let the holder sign the transfer itself.
+
+Permits authorize spending, so they are typed data the guard set with
+``set_typed_data_guard`` treats as dangerous: by default signing one warns, and in
+block mode only tokens on its allowlist can be signed for.
+"""
+
+import json
+import secrets
+import time
+from typing import Any, Dict, Iterable, NamedTuple, Optional, Union
+
+from hexbytes import HexBytes
+from _ferrite import hash_typed_data as rust_hash_typed_data  # type: ignore
+from .account import _eip712_domain, _sign_typed_data_with
+
+_PERMIT_TYPE = [
+    {"name": "owner", "type": "address"},
+    {"name": "spender", "type": "address"},
+    {"name": "value", "type": "uint256"},
+    {"name": "nonce", "type": "uint256"},
+    {"name": "deadline", "type": "uint256"},
+]
+
+_DAI_PERMIT_TYPE = [
+    {"name": "holder", "type": "address"},
+    {"name": "spender", "type": "address"},
+    {"name": "nonce", "type": "uint256"},
+    {"name": "expiry", "type": "uint256"},
+    {"name": "allowed", "type": "bool"},
+]
+
+
+class PermitSignature(NamedTuple):
+    """A signed permit, split the way ``permit`` takes it."""
+
+    owner: str
+    v: int
+    r: HexBytes
+    s: HexBytes
+    signature: HexBytes
+
+
+def permit(
+    token_domain: Dict[str, Any],
+    owner: str,
+    spender: str,
+    value: int,
+    nonce: int,
+    deadline: int,
+) -> Dict[str, Any]:
+    """
+    Builds the EIP-2612 ``Permit`` typed data approving ``spender``.
+
+    Args:
+        token_domain: The token's EIP-712 domain, usually ``name``, ``version``,
+            ``chainId`` and ``verifyingContract``, as its ``DOMAIN_SEPARATOR`` uses.
+        owner: Address of the token holder, who must sign the permit.
+        spender: Address being approved.
+        value: Allowance granted, in the token's smallest unit.
+        nonce: The owner's current ``nonces(owner)`` on the token.
+        deadline: Unix time after which the permit can no longer be used.
+
+    Returns:
+        The typed data, for ``sign_permit`` or any EIP-712 signer.
+    """
+    domain = _eip712_domain(token_domain)
+    return {
+        "types": {"EIP712Domain": domain["types"], "Permit": _PERMIT_TYPE},
+        "primaryType": "Permit",
+        "domain": domain["domain"],
+        # uint256 values as decimal strings, since allowances overflow JSON numbers
+        "message": {
+            "owner": owner,
+            "spender": spender,
+            "value": str(value),
+            "nonce": str(nonce),
+            "deadline": str(deadline),
+        },
+    }
+
+
+def dai_permit(
+    token_domain: Dict[str, Any],
+    holder: str,
+    spender: str,
+    nonce: int,
+    expiry: int,
+    allowed: bool = True,
+) -> Dict[str, Any]:
+    """
+    Builds the DAI-style ``Permit`` typed data, for tokens that predate EIP-2612.
+
+    Args:
+        token_domain: The token's EIP-712 domain, as for ``permit``.
+        holder: Address of the token holder, who must sign the permit.
+        spender: Address being approved.
+        nonce: The holder's current ``nonces(holder)`` on the token.
+        expiry: Unix time after which the permit can no longer be used, or 0 for
+            never.
+        allowed: True to approve an unlimited allowance, False to revoke it.
+
+    Returns:
+        The typed data, for ``sign_permit`` or any EIP-712 signer.
+    """
+    domain = _eip712_domain(token_domain)
+    return {
+        "types": {"EIP712Domain": domain["types"], "Permit": _DAI_PERMIT_TYPE},
+        "primaryType": "Permit",
+        "domain": domain["domain"],
+        "message": {
+            "holder": holder,
+            "spender": spender,
+            "nonce": str(nonce),
+            "expiry": str(expiry),
+            "allowed": bool(allowed),
+        },
+    }
+
+
+def permit_hash(permit: Dict[str, Any]) -> HexBytes:
+    """Returns the EIP-712 hash of a permit, which its signature signs."""
+    return HexBytes(rust_hash_typed_data(json.dumps(permit)))
+
+
+def sign_permit(permit: Dict[str, Any], owner: Any) -> PermitSignature:
+    """
+    Signs a permit as its owner.
+
+    Args:
+        permit: The typed data ``permit``, ``dai_permit``, ``transfer_authorization``
+            or one of the Permit2 builders built.
+        owner: The owner's private key, as bytes or hex, or an account with
+            ``address`` and ``sign_typed_data``, such as a ``RemoteAccount``.
+
+    Raises:
+        ValueError: If the signer is not the owner the permit names, since the token
+            would reject the signature.
+    """
+    address, signature = _sign_typed_data_with(permit, owner)
+    message = permit["message"]
+    named = next(
+        (message[name] for name in ("owner", "holder", "from") if name in message), None
+    )
+    if named is not None and int(named, 16) != int(address, 16):
+        raise ValueError(f"Permit is for owner {named}, but {address} signed it")
+    return PermitSignature(
+        address, signature[64], signature[:32], signature[32:64], signature
+    )
+
+
+# Permit2 is deployed at the same address on every chain it supports
+PERMIT2_ADDRESS = "0x000000000022D473030F116dDEE9F6B43aC78BA3"
+
+_PERMIT_DETAILS_TYPE = [
+    {"name": "token", "type": "address"},
+    {"name": "amount", "type": "uint160"},
+    {"name": "expiration", "type": "uint48"},
+    {"name": "nonce", "type": "uint48"},
+]
+
+_TOKEN_PERMISSIONS_TYPE = [
+    {"name": "token", "type": "address"},
+    {"name": "amount", "type": "uint256"},
+]
+
+
+class PermitDetails(NamedTuple):
+    """One token's allowance in a Permit2 ``PermitSingle`` or ``PermitBatch``."""
+
+    token: str
+    amount: int
+    expiration: int
+    nonce: int
+
+
+def _uint(name: str, value: int, bits: int) -> str:
+    """Returns ``value`` as a decimal string, checking it fits in ``bits`` bits."""
+    if not 0 <= value < 2**bits:
+        raise ValueError(f"{name} must fit in uint{bits}, got {value}")
+    return str(value)
+
+
+def _details(details: PermitDetails) -> Dict[str, Any]:
+    token, amount, expiration, nonce = details
+    return {
+        "token": token,
+        "amount": _uint("amount", amount, 160),
+        "expiration": _uint("expiration", expiration, 48),
+        "nonce": _uint("nonce", nonce, 48),
+    }
+
+
+def _permit2(
+    chain_id: int, permit2: str, primary_type: str, types: Dict[str, Any], message: Any
+) -> Dict[str, Any]:
+    domain = _eip712_domain(
+        {"name": "Permit2", "chainId": chain_id, "verifyingContract": permit2}
+    )
+    return {
+        "types": {"EIP712Domain": domain["types"], **types},
+        "primaryType": primary_type,
+        "domain": domain["domain"],
+        "message": message,
+    }
+
+
+def permit2_single(
+    chain_id: int,
+    details: PermitDetails,
+    spender: str,
+    sig_deadline: int,
+    *,
+    permit2: str = PERMIT2_ADDRESS,
+) -> Dict[str, Any]:
+    """
+    Builds a Permit2 ``PermitSingle``, setting one token's allowance for ``spender``.
+
+    Args:
+        chain_id: Chain the Permit2 contract is on.
+        details: The token, amount, expiration of the allowance, and the owner's
+            current nonce for the token and spender from Permit2's ``allowance``.
+        spender: Address being approved.
+        sig_deadline: Unix time after which the signature can no longer be used.
+        permit2: Address of the Permit2 contract, for deployments elsewhere.
+
+    Returns:
+        The typed data, for ``sign_permit`` or any EIP-712 signer.
+    """
+    types = {
+        "PermitSingle": [
+            {"name": "details", "type": "PermitDetails"},
+            {"name": "spender", "type": "address"},
+            {"name": "sigDeadline", "type": "uint256"},
+        ],
+        "PermitDetails": _PERMIT_DETAILS_TYPE,
+    }
+    message = {
+        "details": _details(PermitDetails(*details)),
+        "spender": spender,
+        "sigDeadline": str(sig_deadline),
+    }
+    return _permit2(chain_id, permit2, "PermitSingle", types, message)
+
+
+def permit2_batch(
+    chain_id: int,
+    details: Iterable[PermitDetails],
+    spender: str,
+    sig_deadline: int,
+    *,
+    permit2: str = PERMIT2_ADDRESS,
+) -> Dict[str, Any]:
+    """
+    Builds a Permit2 ``PermitBatch``, setting several tokens' allowances for
+    ``spender`` with one signature.
+
+    Args are as for ``permit2_single``, with one ``PermitDetails`` per token.
+    """
+    types = {
+        "PermitBatch": [
+            {"name": "details", "type": "PermitDetails[]"},
+            {"name": "spender", "type": "address"},
+            {"name": "sigDeadline", "type": "uint256"},
+        ],
+        "PermitDetails": _PERMIT_DETAILS_TYPE,
+    }
+    message = {
+        "details": [_details(PermitDetails(*entry)) for entry in details],
+        "spender": spender,
+        "sigDeadline": str(sig_deadline),
+    }
+    if not message["details"]:
+        raise ValueError("PermitBatch needs at least one token")
+    return _permit2(chain_id, permit2, "PermitBatch", types, message)
+
+
+def permit2_transfer_from(
+    chain_id: int,
+    token: str,
+    amount: int,
+    spender: str,
+    nonce: int,
+    deadline: int,
+    *,
+    permit2: str = PERMIT2_ADDRESS,
+) -> Dict[str, Any]:
+    """
+    Builds a Permit2 ``PermitTransferFrom``, a one-off transfer signature.
+
+    Args:
+        chain_id: Chain the Permit2 contract is on.
+        token: Token that may be transferred.
+        amount: Most that may be transferred, in the token's smallest unit.
+        spender: The contract that will call ``permitTransferFrom``.
+        nonce: An unused nonce; Permit2 tracks these as a bitmap, in any order.
+        deadline: Unix time after which the signature can no longer be used.
+        permit2: Address of the Permit2 contract, for deployments elsewhere.
+    """
+    types = {
+        "PermitTransferFrom": [
+            {"name": "permitted", "type": "TokenPermissions"},
+            {"name": "spender", "type": "address"},
+            {"name": "nonce", "type": "uint256"},
+            {"name": "deadline", "type": "uint256"},
+        ],
+        "TokenPermissions": _TOKEN_PERMISSIONS_TYPE,
+    }
+    message = {
+        "permitted": {"token": token, "amount": _uint("amount", amount, 256)},
+        "spender": spender,
+        "nonce": str(nonce),
+        "deadline": str(deadline),
+    }
+    return _permit2(chain_id, permit2, "PermitTransferFrom", types, message)
+
+
+_AUTHORIZATION_TYPE = [
+    {"name": "from", "type": "address"},
+    {"name": "to", "type": "address"},
+    {"name": "value", "type": "uint256"},
+    {"name": "validAfter", "type": "uint256"},
+    {"name": "validBefore", "type": "uint256"},
+    {"name": "nonce", "type": "bytes32"},
+]
+
+
+def transfer_authorization(
+    token_domain: Dict[str, Any],
+    sender: str,
+    to: str,
+    value: int,
+    *,
+    valid_after: int = 0,
+    valid_before: Optional[int] = None,
+    valid_for: int = 3600,
+    nonce: Optional[Union[bytes, str]] = None,
+    receive: bool = False,
+) -> Dict[str, Any]:
+    """
+    Builds an EIP-3009 ``TransferWithAuthorization``, or with ``receive`` a
+    ``ReceiveWithAuthorization``, which only ``to`` itself can submit.
+
+    Args:
+        token_domain: The token's EIP-712 domain, as for ``permit``.
+        sender: Address of the holder, who must sign the authorization.
+        to: Recipient of the transfer.
+        value: Amount transferred, in the token's smallest unit.
+        valid_after: Unix time after which the authorization can be used.
+        valid_before: Unix time before which it must be used; by default
+            ``valid_for`` seconds from now.
+        valid_for: Lifetime of the authorization when ``valid_before`` is not given.
+        nonce: 32-byte nonce, as bytes or hex. EIP-3009 nonces are random rather
+            than sequential, so by default a fresh one is generated.
+
+    Returns:
+        The typed data, for ``sign_permit`` or any EIP-712 signer. Its nonce is in
+        ``["message"]["nonce"]``, for the ``transferWithAuthorization`` call.
+    """
+    if valid_before is None:
+        valid_before = int(time.time()) + valid_for
+    if valid_before <= valid_after:
+        raise ValueError(
+            f"validBefore ({valid_before}) must be later than "
+            f"validAfter ({valid_after})"
+        )
+    nonce = secrets.token_bytes(32) if nonce is None else HexBytes(nonce)
+    if len(nonce) != 32:
+        raise ValueError(f"nonce must be 32 bytes, got {len(nonce)}")
+
+    primary_type = (
+        "ReceiveWithAuthorization" if receive else "TransferWithAuthorization"
+    )
+    domain = _eip712_domain(token_domain)
+    return {
+        "types": {"EIP712Domain": domain["types"], primary_type: _AUTHORIZATION_TYPE},
+        "primaryType": primary_type,
+        "domain": domain["domain"],
+        "message": {
+            "from": sender,
+            "to": to,
+            "value": str(value),
+            "validAfter": str(valid_after),
+            "validBefore": str(valid_before),
+            "nonce": "0x" + bytes(nonce).hex(),
+        },
+    }
@@ -0,0 +1,419 @@
+/*!
+Signing policy enforced on every transaction and typed data message before it is signed.
+
+The policy lives on the Rust side and is checked inside `sign_transaction` itself, after
+the payload has been parsed into the exact transaction that will be signed. Code that
+only controls the payload handed to ferrite therefore can't get a transaction outside
+the policy signed. Once locked, the policy can't be replaced or removed for the rest of
+the process, so neither can code that controls ferrite's Python API.
+
+A cap on transaction fees alone can also be set apart from the policy, as a guard against
+fee estimation bugs that doesn't require configuring anything else.
+
+Typed data is checked against the allowed EIP-712 domains, so that a bug can't get
+Permit or order signatures produced for unknown contracts.
+
+A bare hash can't be checked: it may be the sighash of a transaction the policy forbids,
+or the digest of typed data for a domain it doesn't allow. While a policy is in force,
+`sign_hash`, `sign_hashes`, `sign_hash_remote` and `sign_user_operation` therefore
+refuse to sign one unless the policy sets `allowRawHashes`, which is locked along with
+the rest of it. EIP-191 personal messages are still signed when the caller passes the
+prefixed message the hash is of, since such a hash can't also be a transaction or typed
+data digest.
+*/
+
+use std::sync::RwLock;
+
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::transaction::eip712::EIP712Domain;
+use ethers_core::types::{Address, H256, U256};
+use ethers_core::utils::to_checksum;
+use pyo3::prelude::*;
+
+use crate::errors;
+use crate::keccak;
+use crate::request::{self, Fields};
+
+const KEYS: [&str; 8] = [
+    "maxValue",
+    "maxFee",
+    "chainIds",
+    "allowTo",
+    "denyTo",
+    "selectors",
+    "domains",
+    "allowRawHashes",
+];
+
+/// What every EIP-191 personal message starts with.
+const PERSONAL_MESSAGE_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n";
+
+const DOMAIN_KEYS: [&str; 3] = ["name", "chainId", "verifyingContract"];
+
+/// An allowed EIP-712 domain. Fields it leaves out match any value.
+pub struct Domain {
+    name: Option<String>,
+    chain_id: Option<U256>,
+    verifying_contract: Option<Address>,
+}
+
+impl Domain {
+    pub fn parse(fields: &Fields) -> Result<Self, String> {
+        if let Some(key) = fields.keys().find(|key| !DOMAIN_KEYS.contains(&key.as_str())) {
+            return Err(format!("unknown domain field `{}`", key));
+        }
+        if fields.is_empty() {
+            return Err("an empty domain would allow every domain".into());
+        }
+
+        Ok(Domain {
+            name: request::field(fields, "name")?,
+            chain_id: request::quantity(fields, "chainId", 256)?,
+            verifying_contract: request::field(fields, "verifyingContract")?,
+        })
+    }
+
+    pub fn matches(&self, domain: &EIP712Domain) -> bool {
+        self.name.as_ref().is_none_or(|name| domain.name.as_ref() == Some(name))
+            && self.chain_id.is_none_or(|id| domain.chain_id == Some(id))
+            && self
+                .verifying_contract
+                .is_none_or(|contract| domain.verifying_contract == Some(contract))
+    }
+}
+
+/// Limits on the transactions and typed data ferrite will sign. Every limit is optional.
+struct Policy {
+    /// Largest `value` transferred.
+    max_value: Option<U256>,
+    /// Largest fee the transaction can cost, as [`fee`] counts it.
+    max_fee: Option<U256>,
+    /// Chain ids that may be signed for. Excludes legacy transactions without one.
+    chain_ids: Option<Vec<u64>>,
+    /// Recipients that may be called. Excludes contract creation.
+    allow_to: Option<Vec<Address>>,
+    /// Recipients that may never be called.
+    deny_to: Vec<Address>,
+    /// Function selectors non-empty calldata may start with.
+    selectors: Option<Vec<[u8; 4]>>,
+    /// EIP-712 domains typed data may be signed for.
+    domains: Option<Vec<Domain>>,
+    /// Whether bare hashes, which none of the other limits can be checked against, may
+    /// be signed.
+    allow_raw_hashes: bool,
+}
+
+struct State {
+    policy: Option<Policy>,
+    locked: bool,
+}
+
+static STATE: RwLock<State> = RwLock::new(State { policy: None, locked: false });
+
+/// Cap on any transaction's fee, independent of the policy.
+static MAX_TRANSACTION_FEE: RwLock<Option<U256>> = RwLock::new(None);
+
+/// Blob gas each blob of an EIP-4844 transaction uses.
+const GAS_PER_BLOB: u64 = 1 << 17;
+
+/// Returns the most `tx` can cost in fees: `gas` times `gasPrice`, or times
+/// `maxFeePerGas` for a dynamic fee transaction, plus `maxFeePerBlobGas` times the blob
+/// gas of the `blobVersionedHashes` in the `payload` it was parsed from. `None` if that
+/// overflows 256 bits.
+fn fee(tx: &TypedTransaction, payload: &str) -> Result<Option<U256>, String> {
+    let fields: Fields = serde_json::from_str(payload)
+        .map_err(|e| format!("Invalid Transaction JSON: {}", e))?;
+    let blobs = request::field::<Vec<H256>>(&fields, "blobVersionedHashes")?
+        .map_or(0, |hashes| hashes.len());
+    let max_fee_per_blob_gas =
+        request::quantity(&fields, "maxFeePerBlobGas", 256)?.unwrap_or_default();
+    let blob_fee = max_fee_per_blob_gas.checked_mul(U256::from(GAS_PER_BLOB) * blobs);
+
+    let gas = tx.gas().copied().unwrap_or_default();
+    let gas_fee = gas.checked_mul(tx.gas_price().unwrap_or_default());
+    Ok(gas_fee.zip(blob_fee).and_then(|(gas_fee, blob_fee)| gas_fee.checked_add(blob_fee)))
+}
+
+/// Describes how the fee of `tx`, parsed from `payload`, exceeds `max_fee`, if it does.
+/// Fails if `payload`'s blob fields are malformed.
+pub(crate) fn fee_over(
+    tx: &TypedTransaction,
+    payload: &str,
+    max_fee: U256,
+) -> Result<Option<String>, String> {
+    let fee = fee(tx, payload)?;
+    if fee.is_some_and(|fee| fee <= max_fee) {
+        return Ok(None);
+    }
+    let fee = fee.map_or("more than 2^256 - 1".to_string(), |fee| fee.to_string());
+    Ok(Some(format!("fee {} exceeds the maximum of {}", fee, max_fee)))
+}
+
+fn invalid(message: String) -> PyErr {
+    PyErr::new::<errors::FerriteError, _>(format!("Invalid signing policy: {}", message))
+}
+
+fn selector(text: &str) -> Result<[u8; 4], String> {
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    let mut selector = [0u8; 4];
+    hex::decode_to_slice(digits, &mut selector).map_err(|_| {
+        format!("Invalid `selectors`: expected 4 bytes as 8 hex digits, got {:?}", text)
+    })?;
+    Ok(selector)
+}
+
+impl Policy {
+    fn parse(payload: &str) -> PyResult<Self> {
+        let fields: Fields =
+            serde_json::from_str(payload).map_err(|e| invalid(e.to_string()))?;
+
+        // A misspelled limit would otherwise silently not apply
+        if let Some(key) = fields.keys().find(|key| !KEYS.contains(&key.as_str())) {
+            return Err(invalid(format!("unknown limit `{}`", key)));
+        }
+
+        let selectors = request::field::<Vec<String>>(&fields, "selectors")
+            .map_err(invalid)?
+            .map(|selectors| selectors.iter().map(|text| selector(text)).collect())
+            .transpose()
+            .map_err(invalid)?;
+        let domains = request::field::<Vec<Fields>>(&fields, "domains")
+            .map_err(invalid)?
+            .map(|domains| domains.iter().map(Domain::parse).collect())
+            .transpose()
+            .map_err(|e| invalid(format!("Invalid `domains`: {}", e)))?;
+
+        Ok(Policy {
+            max_value: request::quantity(&fields, "maxValue", 256).map_err(invalid)?,
+            max_fee: request::quantity(&fields, "maxFee", 256).map_err(invalid)?,
+            chain_ids: request::field(&fields, "chainIds").map_err(invalid)?,
+            allow_to: request::field(&fields, "allowTo").map_err(invalid)?,
+            deny_to: request::field(&fields, "denyTo").map_err(invalid)?.unwrap_or_default(),
+            selectors,
+            domains,
+            allow_raw_hashes: request::field(&fields, "allowRawHashes")
+                .map_err(invalid)?
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Returns a description of every way `tx`, parsed from `payload`, falls outside the
+    /// policy.
+    fn violations(&self, tx: &TypedTransaction, payload: &str) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        let value = tx.value().copied().unwrap_or_default();
+        if let Some(max_value) = self.max_value.filter(|max_value| value > *max_value) {
+            violations.push(format!("`value` {} exceeds the maximum of {}", value, max_value));
+        }
+
+        if let Some(max_fee) = self.max_fee {
+            match fee_over(tx, payload, max_fee) {
+                Ok(Some(message)) | Err(message) => violations.push(message),
+                Ok(None) => {}
+            }
+        }
+
+        if let Some(chain_ids) = &self.chain_ids {
+            match tx.chain_id() {
+                Some(id) if chain_ids.contains(&id.as_u64()) => {}
+                Some(id) => violations.push(format!("chainId {} is not allowed", id)),
+                None => violations.push("transactions without a chainId are not allowed".into()),
+            }
+        }
+
+        match tx.to_addr() {
+            Some(to) if self.deny_to.contains(to) => {
+                violations.push(format!("recipient {:?} is denied", to));
+            }
+            Some(to) if self.allow_to.as_ref().is_some_and(|allow| !allow.contains(to)) => {
+                violations.push(format!("recipient {:?} is not allowed", to));
+            }
+            None if self.allow_to.is_some() => {
+                violations.push("contract creation is not allowed".into());
+            }
+            _ => {}
+        }
+
+        let data = tx.data().map_or(&[][..], |data| data.as_ref());
+        if let (Some(selectors), false) = (&self.selectors, data.is_empty()) {
+            match data.get(..4) {
+                Some(selector) if selectors.iter().any(|allowed| allowed == selector) => {}
+                Some(selector) => violations.push(format!(
+                    "function selector 0x{} is not allowed",
+                    hex::encode(selector)
+                )),
+                None => violations.push(format!(
+                    "calldata is too short for a function selector ({} bytes)",
+                    data.len()
+                )),
+            }
+        }
+
+        violations
+    }
+}
+
+/// Checks `tx` against the current policy, if one is set.
+///
+/// `tx` must be the transaction exactly as it will be signed, chain id included, and
+/// `payload` the JSON it was parsed from.
+pub fn check(tx: &TypedTransaction, payload: &str) -> PyResult<()> {
+    let state = STATE.read().unwrap_or_else(|e| e.into_inner());
+    let violations = match &state.policy {
+        Some(policy) => policy.violations(tx, payload),
+        None => return Ok(()),
+    };
+
+    if violations.is_empty() {
+        tracing::debug!("transaction allowed by the signing policy");
+        return Ok(());
+    }
+    let violations = violations.join("; ");
+    tracing::info!(%violations, "transaction denied by the signing policy");
+    Err(PyErr::new::<errors::PolicyViolationError, _>(format!(
+        "Transaction violates the signing policy: {}",
+        violations
+    )))
+}
+
+/// Checks the domain of typed data against the current policy, if one is set.
+///
+/// Digests of typed data signed as bare hashes are held back by [`check_raw_hash`]
+/// instead, unless the policy sets `allowRawHashes`, which leaves the allowed domains
+/// unenforced for them.
+pub fn check_domain(domain: &EIP712Domain) -> PyResult<()> {
+    let state = STATE.read().unwrap_or_else(|e| e.into_inner());
+    let Some(domains) = state.policy.as_ref().and_then(|policy| policy.domains.as_ref())
+    else {
+        return Ok(());
+    };
+    if domains.iter().any(|allowed| allowed.matches(domain)) {
+        tracing::debug!(domain = %describe(domain), "domain allowed by the signing policy");
+        return Ok(());
+    }
+    tracing::info!(domain = %describe(domain), "domain denied by the signing policy");
+
+    Err(PyErr::new::<errors::PolicyViolationError, _>(format!(
+        "Typed data violates the signing policy: domain {} is not allowed",
+        describe(domain)
+    )))
+}
+
+/// Returns whether `hash` is of an EIP-191 personal message, as shown by `preimage`.
+pub fn is_personal_message(hash: H256, preimage: Option<&[u8]>) -> bool {
+    preimage.is_some_and(|preimage| {
+        preimage.starts_with(PERSONAL_MESSAGE_PREFIX) && keccak::keccak256(preimage) == hash.0
+    })
+}
+
+/// Refuses to sign a bare hash while a policy is in force, unless it allows them.
+///
+/// Callers that know the hash is of an EIP-191 personal message, with
+/// [`is_personal_message`], don't need to check it.
+pub fn check_raw_hash() -> PyResult<()> {
+    let state = STATE.read().unwrap_or_else(|e| e.into_inner());
+    if state.policy.as_ref().is_none_or(|policy| policy.allow_raw_hashes) {
+        return Ok(());
+    }
+    tracing::info!("raw hash denied by the signing policy");
+
+    Err(PyErr::new::<errors::PolicyViolationError, _>(
+        "Hash violates the signing policy: a bare hash can't be checked against it; sign \
+         the transaction or typed data itself, or set `allowRawHashes` in the policy",
+    ))
+}
+
+/// Describes `domain` by the fields an allowed [`Domain`] can match.
+pub fn describe(domain: &EIP712Domain) -> String {
+    format!(
+        "(name {:?}, chainId {}, verifyingContract {})",
+        domain.name.as_deref().unwrap_or_default(),
+        domain.chain_id.map_or("none".to_string(), |id| id.to_string()),
+        domain.verifying_contract.map_or("none".to_string(), |to| to_checksum(&to, None)),
+    )
+}
+
+/// Checks the fee of `tx`, parsed from `payload`, against the cap set with
+/// `set_max_transaction_fee`, if any.
+pub fn check_fee(tx: &TypedTransaction, payload: &str) -> PyResult<()> {
+    let max_fee = *MAX_TRANSACTION_FEE.read().unwrap_or_else(|e| e.into_inner());
+    let over = max_fee
+        .map(|max_fee| fee_over(tx, payload, max_fee))
+        .transpose()
+        .map_err(PyErr::new::<errors::InvalidTransactionError, _>)?;
+    match over.flatten() {
+        Some(message) => {
+            tracing::info!(reason = %message, "transaction denied by the fee cap");
+            Err(PyErr::new::<errors::InvalidTransactionError, _>(format!(
+                "Invalid Transaction: {} set with set_max_transaction_fee",
+                message
+            )))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Returns whether a signing policy is in force.
+pub fn is_active() -> bool {
+    STATE.read().unwrap_or_else(|e| e.into_inner()).policy.is_some()
+}
+
+/// Sets the policy every transaction and typed data message must satisfy before it is
+/// signed.
+///
+/// # Arguments
+/// * `policy` - JSON object of limits, or `None` to remove the policy. Every limit is
+///   optional: `maxValue`, `maxFee` (`gas` times the fee per gas, plus any blob fee),
+///   `chainIds`, `allowTo`, `denyTo`, `selectors` (4-byte function selectors as hex)
+///   and `domains` (EIP-712 domains as objects of `name`, `chainId` and
+///   `verifyingContract`, any of which may be left out to match any value).
+///   `allowRawHashes` lets bare hashes other than of personal messages be signed,
+///   unchecked, as they are without a policy.
+/// * `lock` - Whether to keep this policy for the rest of the process. Once locked,
+///   any further call raises `FerriteError`.
+#[pyfunction]
+#[pyo3(signature = (policy, *, lock = false))]
+pub fn set_signing_policy(policy: Option<&str>, lock: bool) -> PyResult<()> {
+    let policy = policy.map(Policy::parse).transpose()?;
+
+    let mut state = STATE.write().unwrap_or_else(|e| e.into_inner());
+    if state.locked {
+        return Err(PyErr::new::<errors::FerriteError, _>(
+            "The signing policy is locked and can't be changed",
+        ));
+    }
+    *state = State { policy, locked: lock };
+    Ok(())
+}
+
+/// Returns whether the signing policy is locked.
+#[pyfunction]
+pub fn signing_policy_locked() -> bool {
+    STATE.read().unwrap_or_else(|e| e.into_inner()).locked
+}
+
+/// Caps the fee of every transaction, as protection against fee estimation bugs.
+///
+/// Unlike the signing policy's `maxFee`, the cap is set on its own, so it applies
+/// whether or not a policy is in force.
+///
+/// # Arguments
+/// * `max_fee` - Largest fee in wei, as a decimal or `0x`-prefixed hex string, that a
+///   transaction may cost: `gas` times `gasPrice`, or times `maxFeePerGas` for a
+///   dynamic fee transaction, plus `maxFeePerBlobGas` times the blob gas of any
+///   `blobVersionedHashes`. `None` removes the cap.
+#[pyfunction]
+#[pyo3(signature = (max_fee))]
+pub fn set_max_transaction_fee(max_fee: Option<&str>) -> PyResult<()> {
+    let max_fee = max_fee
+        .map(|text| {
+            let fields = Fields::from_iter([("max_fee".to_string(), text.into())]);
+            request::quantity(&fields, "max_fee", 256)?.ok_or_else(|| "Missing `max_fee`".into())
+        })
+        .transpose()
+        .map_err(PyErr::new::<errors::FerriteError, String>)?;
+
+    *MAX_TRANSACTION_FEE.write().unwrap_or_else(|e| e.into_inner()) = max_fee;
+    Ok(())
+}
@@ -0,0 +1,270 @@
+"""
+Prometheus metrics for the signer servers.
+
+``prometheus_metrics()`` renders the per-key counters of ``get_metrics()`` in the
+Prometheus text format, along with what the servers in this process have seen: requests
+by server, method and outcome with their latency, and the health of each key's
+backend. ``SignerServer`` serves it at ``GET /metrics`` on its own port. The IPC and
+gRPC servers have no HTTP endpoint to add it to, so ``MetricsServer`` serves it on a
+port of its own.
+
+A backend is up until a signature through it fails for a reason of its own, such as a
+KMS outage or a bad signature from an HSM, rather than a policy denial or a malformed
+request, and up again after the next signature it produces.
+"""
+
+import threading
+import time
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from typing import Any, Callable, Dict, List, Optional, Tuple, TypeVar
+
+from _ferrite import FerriteError, SigningError, get_metrics  # type: ignore
+
+CONTENT_TYPE = "text/plain; version=0.0.4; charset=utf-8"
+
+DEFAULT_METRICS_PORT = 9464
+
+# Upper bounds of the request latency buckets, in seconds. Requests include the JSON
+# handling and any wait on a remote backend, so these reach further than signing alone
+_REQUEST_BUCKETS = (0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0, 5.0)
+
+_T = TypeVar("_T")
+
+_lock = threading.Lock()
+# (server, method, outcome) -> count
+_requests: Dict[Tuple[str, str, str], int] = {}
+# (server, method) -> (counts per bucket, the last counting everything slower; sum)
+_latencies: Dict[Tuple[str, str], Tuple[List[int], float]] = {}
+# address -> [backend, up, failures]
+_backends: Dict[str, List[Any]] = {}
+
+
+def _backend(account: Any) -> str:
+    """Names where ``account``'s key is held: in process memory, or behind a signer."""
+    return "local" if hasattr(account, "key") else "remote"
+
+
+def observe_request(server: str, method: str, ok: bool, seconds: float) -> None:
+    """Counts one request to ``server`` that took ``seconds`` to answer."""
+    bucket = next(
+        (i for i, bound in enumerate(_REQUEST_BUCKETS) if seconds <= bound),
+        len(_REQUEST_BUCKETS),
+    )
+    with _lock:
+        key = (server, method, "ok" if ok else "error")
+        _requests[key] = _requests.get(key, 0) + 1
+        empty = ([0] * (len(_REQUEST_BUCKETS) + 1), 0.0)
+        counts, total = _latencies.get((server, method), empty)
+        counts[bucket] += 1
+        _latencies[(server, method)] = (counts, total + seconds)
+
+
+def timed(server: str, method: str, call: Callable[[], _T]) -> _T:
+    """Returns ``call()``, counting it as a request to ``server``."""
+    started = time.perf_counter()
+    ok = False
+    try:
+        result = call()
+        ok = True
+        return result
+    finally:
+        observe_request(server, method, ok, time.perf_counter() - started)
+
+
+def watch_backend(account: Any) -> None:
+    """Reports ``account``'s backend as up until it first signs."""
+    with _lock:
+        _backends.setdefault(account.address, [_backend(account), 1, 0])
+
+
+def signed_with(account: Any, sign: Callable[[], _T]) -> _T:
+    """Returns ``sign()``, marking ``account``'s backend down if it fails on its own."""
+    try:
+        result = sign()
+    except Exception as e:
+        # ferrite raises its own errors for denials and bad input, except SigningError
+        failed = isinstance(e, SigningError) or not isinstance(
+            e, (FerriteError, ValueError, TypeError)
+        )
+        with _lock:
+            state = _backends.setdefault(account.address, [_backend(account), 1, 0])
+            if failed:
+                state[1] = 0
+                state[2] += 1
+        raise
+    with _lock:
+        _backends.setdefault(account.address, [_backend(account), 1, 0])[1] = 1
+    return result
+
+
+def _labels(**labels: Any) -> str:
+    escaped = (
+        str(value).replace("\\", "\\\\").replace('"', '\\"').replace("\n", "\\n")
+        for value in labels.values()
+    )
+    pairs = ",".join(f'{name}="{value}"' for name, value in zip(labels, escaped))
+    return "{" + pairs + "}"
+
+
+def _histogram(
+    lines: List[str],
+    name: str,
+    labels: Dict[str, Any],
+    buckets: List[float],
+    counts: List[int],
+    total: float,
+) -> None:
+    cumulative = 0
+    for bound, count in zip(buckets, counts):
+        cumulative += count
+        lines.append(f"{name}_bucket{_labels(**labels, le=bound)} {cumulative}")
+    count = sum(counts)
+    lines.append(f'{name}_bucket{_labels(**labels, le="+Inf")} {count}')
+    lines.append(f"{name}_sum{_labels(**labels)} {total}")
+    lines.append(f"{name}_count{_labels(**labels)} {count}")
+
+
+def prometheus_metrics() -> str:
+    """
+    Returns the signing and server metrics in the Prometheus text exposition format.
+
+    Returns:
+        ``ferrite_signatures_total``, ``ferrite_signing_failures_total`` and
+        ``ferrite_signing_denials_total`` counters and a
+        ``ferrite_signing_duration_seconds`` histogram per key, as ``get_metrics``
+        reports them; ``ferrite_requests_total`` and a
+        ``ferrite_request_duration_seconds`` histogram per server and method; and
+        ``ferrite_backend_up`` and ``ferrite_backend_failures_total`` per key served.
+    """
+    keys = get_metrics()
+    with _lock:
+        requests = dict(_requests)
+        latencies = {key: (list(c), total) for key, (c, total) in _latencies.items()}
+        backends = {address: list(state) for address, state in _backends.items()}
+
+    lines: List[str] = []
+
+    def metric(name: str, kind: str, description: str) -> None:
+        lines.append(f"# HELP {name} {description}")
+        lines.append(f"# TYPE {name} {kind}")
+
+    metric("ferrite_signatures_total", "counter", "Signatures produced, by kind.")
+    for address, key in keys.items():
+        for kind, count in key["signatures"].items():
+            labels = _labels(address=address, kind=kind)
+            lines.append(f"ferrite_signatures_total{labels} {count}")
+    metric("ferrite_signing_failures_total", "counter", "Signing calls that failed.")
+    for address, key in keys.items():
+        labels = _labels(address=address)
+        lines.append(f"ferrite_signing_failures_total{labels} {key['failures']}")
+    metric(
+        "ferrite_signing_denials_total",
+        "counter",
+        "Signing calls denied by the policy, approval hook or rate limit.",
+    )
+    for address, key in keys.items():
+        labels = _labels(address=address)
+        lines.append(f"ferrite_signing_denials_total{labels} {key['denials']}")
+    metric("ferrite_signing_duration_seconds", "histogram", "Signing call latency.")
+    for address, key in keys.items():
+        latency = key["latency"]
+        _histogram(
+            lines,
+            "ferrite_signing_duration_seconds",
+            {"address": address},
+            latency["buckets"],
+            latency["counts"],
+            latency["sum"],
+        )
+
+    metric("ferrite_requests_total", "counter", "Requests answered, by outcome.")
+    for (server, method, outcome), count in sorted(requests.items()):
+        labels = _labels(server=server, method=method, outcome=outcome)
+        lines.append(f"ferrite_requests_total{labels} {count}")
+    metric("ferrite_request_duration_seconds", "histogram", "Request latency.")
+    for (server, method), (counts, total) in sorted(latencies.items()):
+        _histogram(
+            lines,
+            "ferrite_request_duration_seconds",
+            {"server": server, "method": method},
+            list(_REQUEST_BUCKETS),
+            counts,
+            total,
+        )
+
+    metric(
+        "ferrite_backend_up",
+        "gauge",
+        "Whether the last signature through the key's backend succeeded.",
+    )
+    for address, (backend, up, _) in sorted(backends.items()):
+        labels = _labels(address=address, backend=backend)
+        lines.append(f"ferrite_backend_up{labels} {up}")
+    metric(
+        "ferrite_backend_failures_total", "counter", "Signatures the backend failed."
+    )
+    for address, (backend, _, failures) in sorted(backends.items()):
+        labels = _labels(address=address, backend=backend)
+        lines.append(f"ferrite_backend_failures_total{labels} {failures}")
+    return "\n".join(lines) + "\n"
+
+
+def send_metrics(handler: BaseHTTPRequestHandler) -> None:
+    """Answers the request ``handler`` is handling with the metrics."""
+    body = prometheus_metrics().encode()
+    handler.send_response(200)
+    handler.send_header("Content-Type", CONTENT_TYPE)
+    handler.send_header("Content-Length", str(len(body)))
+    handler.end_headers()
+    handler.wfile.write(body)
+
+
+class _MetricsHandler(BaseHTTPRequestHandler):
+    def do_GET(self) -> None:
+        if self.path.split("?")[0] != "/metrics":
+            self.send_error(404)
+            return
+        send_metrics(self)
+
+    def log_message(self, format: str, *args: Any) -> None:
+        pass
+
+
+class MetricsServer:
+    """
+    Serves ``prometheus_metrics()`` at ``GET /metrics``, for servers that don't speak
+    HTTP themselves.
+
+    Args:
+        host: The interface to listen on.
+        port: The port to listen on, or 0 for any free port.
+    """
+
+    def __init__(self, host: str = "127.0.0.1", port: int = DEFAULT_METRICS_PORT):
+        self._server = ThreadingHTTPServer((host, port), _MetricsHandler)
+        self._thread: Optional[threading.Thread] = None
+
+    @property
+    def url(self) -> str:
+        """The URL of the metrics."""
+        host, port = self._server.server_address[:2]
+        return f"http://{host}:{port}/metrics"
+
+    def start(self) -> "MetricsServer":
+        """Starts serving in a background thread."""
+        self._thread = threading.Thread(target=self._server.serve_forever, daemon=True)
+        self._thread.start()
+        return self
+
+    def shutdown(self) -> None:
+        """Stops serving and closes the socket."""
+        if self._thread is not None:
+            self._server.shutdown()
+            self._thread.join()
+        self._server.server_close()
+
+    def __enter__(self) -> "MetricsServer":
+        return self.start()
+
+    def __exit__(self, *exc_info: Any) -> None:
+        self.shutdown()
@@ -0,0 +1,347 @@
+"""
+Air-gapped signing over QR codes, as EIP-4527 describes and Keystone-style wallets use.
+
+A request to sign is encoded as an ``eth-sign-request`` Uniform Resource (UR): CBOR
+wrapped in Bytewords text that fits QR alphanumeric mode. Requests too large for one
+QR code are split into numbered parts, shown one after another. The wallet answers
+with an ``eth-signature`` UR, which ferrite turns back into a signed transaction
+through the same path as a ``RemoteAccount``, checking the signature against the
+expected address before anything is broadcast.
+
+Only sequential multi-part URs are decoded. Wallets that display fountain-coded parts
+beyond the first ``n`` still show every sequential part in turn, so scanning until all
+of them have been seen is enough.
+"""
+
+import json
+import uuid
+import zlib
+from typing import Any, Dict, List, NamedTuple, Optional, Sequence, Tuple, Union
+
+from eth_account.datastructures import SignedTransaction
+from _ferrite import encode_unsigned_transaction  # type: ignore
+from .account import _sanitize_transaction
+from .remote import RemoteAccount
+
+# BCR-2020-012 Bytewords; the minimal encoding keeps each word's first and last letter
+_BYTEWORDS = (
+    "able acid also apex aqua arch atom aunt away axis back bald barn belt beta bias "
+    "blue body brag brew bulb buzz calm cash cats chef city claw code cola cook cost "
+    "crux curl cusp cyan dark data days deli dice diet door down draw drop drum dull "
+    "duty each easy echo edge epic even exam exit eyes fact fair fern figs film fish "
+    "fizz flap flew flux foxy free frog fuel fund gala game gear gems gift girl glow "
+    "good gray grim guru gush gyro half hang hard hawk heat help high hill holy hope "
+    "horn huts iced idea idle inch inky into iris iron item jade jazz join jolt jowl "
+    "judo jugs jump junk jury keep keno kept keys kick kiln king kite kiwi knob lamb "
+    "lava lazy leaf legs liar limp lion list logo loud love luau luck lung main many "
+    "math maze memo menu meow mild mint miss monk nail navy need news next noon note "
+    "numb obey oboe omit onyx open oval owls paid part peck play plus poem pool pose "
+    "puff puma purr quad quiz race ramp real redo rich road rock roof ruby ruin runs "
+    "rust safe saga scar sets silk skew slot soap solo song stub surf swan taco task "
+    "taxi tent tied time tiny toil tomb toys trip tuna twin ugly undo unit urge user "
+    "vast very veto vial vibe view visa void vows wall wand warm wasp wave waxy webs "
+    "what when whiz wolf work yank yawn yell yoga yurt zaps zero zest zinc zone zoom"
+).split()
+_MINIMAL = [word[0] + word[-1] for word in _BYTEWORDS]
+_MINIMAL_INDEX = {word: i for i, word in enumerate(_MINIMAL)}
+
+# EIP-4527 data types
+_TRANSACTION = 1
+_TYPED_DATA = 2
+_PERSONAL_MESSAGE = 3
+_TYPED_TRANSACTION = 4
+
+_UUID_TAG = 37
+_KEYPATH_TAG = 304
+
+
+class _Tagged(NamedTuple):
+    tag: int
+    value: Any
+
+
+class SignRequest(NamedTuple):
+    """An ``eth-sign-request``, as the UR parts to show as QR codes."""
+
+    request_id: uuid.UUID
+    parts: List[str]
+
+
+def _cbor_head(major: int, value: int) -> bytes:
+    if value < 24:
+        return bytes([major << 5 | value])
+    for info, length in ((24, 1), (25, 2), (26, 4), (27, 8)):
+        if value < 1 << (8 * length):
+            return bytes([major << 5 | info]) + value.to_bytes(length, "big")
+    raise ValueError(f"{value} is too large for CBOR")
+
+
+def _cbor_encode(value: Any) -> bytes:
+    """Encodes the subset of CBOR URs use, with map keys in the order given."""
+    if isinstance(value, bool):
+        return b"\xf5" if value else b"\xf4"
+    if isinstance(value, int):
+        if value < 0:
+            return _cbor_head(1, -1 - value)
+        return _cbor_head(0, value)
+    if isinstance(value, bytes):
+        return _cbor_head(2, len(value)) + value
+    if isinstance(value, str):
+        text = value.encode("utf-8")
+        return _cbor_head(3, len(text)) + text
+    if isinstance(value, _Tagged):
+        return _cbor_head(6, value.tag) + _cbor_encode(value.value)
+    if isinstance(value, (list, tuple)):
+        return _cbor_head(4, len(value)) + b"".join(map(_cbor_encode, value))
+    if isinstance(value, dict):
+        items = b"".join(_cbor_encode(k) + _cbor_encode(v) for k, v in value.items())
+        return _cbor_head(5, len(value)) + items
+    raise TypeError(f"Can't encode {type(value).__name__} as CBOR")
+
+
+def _cbor_decode(data: bytes, offset: int = 0) -> Tuple[Any, int]:
+    """Decodes one CBOR item at ``offset``, returning it and the offset after it."""
+    initial = data[offset]
+    major, info = initial >> 5, initial & 0x1F
+    offset += 1
+    if major == 7:
+        if info in (20, 21):
+            return info == 21, offset
+        if info == 22:
+            return None, offset
+        raise ValueError(f"Unsupported CBOR simple value {info}")
+    if info < 24:
+        value = info
+    elif info <= 27:
+        length = 1 << (info - 24)
+        value = int.from_bytes(data[offset : offset + length], "big")
+        offset += length
+    else:
+        raise ValueError("Indefinite-length CBOR items are not supported")
+
+    if major == 0:
+        return value, offset
+    if major == 1:
+        return -1 - value, offset
+    if major in (2, 3):
+        if offset + value > len(data):
+            raise ValueError("Truncated CBOR")
+        item = data[offset : offset + value]
+        return (bytes(item) if major == 2 else item.decode("utf-8")), offset + value
+    if major == 4:
+        items = []
+        for _ in range(value):
+            item, offset = _cbor_decode(data, offset)
+            items.append(item)
+        return items, offset
+    if major == 5:
+        entries = {}
+        for _ in range(value):
+            key, offset = _cbor_decode(data, offset)
+            entries[key], offset = _cbor_decode(data, offset)
+        return entries, offset
+    item, offset = _cbor_decode(data, offset)
+    return _Tagged(value, item), offset
+
+
+def _bytewords(data: bytes) -> str:
+    checksum = zlib.crc32(data).to_bytes(4, "big")
+    return "".join(_MINIMAL[byte] for byte in data + checksum)
+
+
+def _from_bytewords(text: str) -> bytes:
+    if len(text) % 2 or len(text) < 8:
+        raise ValueError("Invalid UR: Bytewords payload has the wrong length")
+    try:
+        data = bytes(_MINIMAL_INDEX[text[i : i + 2]] for i in range(0, len(text), 2))
+    except KeyError as e:
+        raise ValueError(f"Invalid UR: {e.args[0]!r} is not a Bytewords word") from None
+    if zlib.crc32(data[:-4]).to_bytes(4, "big") != data[-4:]:
+        raise ValueError("Invalid UR: checksum mismatch")
+    return data[:-4]
+
+
+def _encode_ur(ur_type: str, message: bytes, max_fragment_length: int) -> List[str]:
+    """Encodes ``message`` as one UR, or as sequential parts if it is too long."""
+    if len(message) <= max_fragment_length:
+        return [f"ur:{ur_type}/{_bytewords(message)}"]
+
+    count = -(-len(message) // max_fragment_length)
+    length = -(-len(message) // count)
+    padded = message.ljust(length * count, b"\0")
+    checksum = zlib.crc32(message)
+    parts = []
+    for seq in range(1, count + 1):
+        fragment = padded[(seq - 1) * length : seq * length]
+        part = _cbor_encode([seq, count, len(message), checksum, fragment])
+        parts.append(f"ur:{ur_type}/{seq}-{count}/{_bytewords(part)}")
+    return parts
+
+
+def _decode_ur(parts: Union[str, Sequence[str]]) -> Tuple[str, bytes]:
+    """Reassembles a UR from one or more scanned parts, returning its type and CBOR."""
+    if isinstance(parts, str):
+        parts = [parts]
+
+    ur_type = None
+    fragments: Dict[int, bytes] = {}
+    expected = None
+    for part in parts:
+        components = part.strip().lower().split("/")
+        if not components[0].startswith("ur:") or len(components) not in (2, 3):
+            raise ValueError(f"Invalid UR: {part[:32]!r}")
+        if ur_type not in (None, components[0][3:]):
+            raise ValueError("Invalid UR: parts of different types")
+        ur_type = components[0][3:]
+        if len(components) == 2:
+            return ur_type, _from_bytewords(components[1])
+
+        item, _ = _cbor_decode(_from_bytewords(components[2]))
+        seq, count, message_length, checksum, fragment = item
+        if expected not in (None, (count, message_length, checksum)):
+            raise ValueError("Invalid UR: parts of different messages")
+        expected = (count, message_length, checksum)
+        # Fountain-coded parts mix several fragments; the sequential ones suffice
+        if seq <= count:
+            fragments[seq] = fragment
+
+    if expected is None:
+        raise ValueError("No UR parts given")
+    count, message_length, checksum = expected
+    missing = [seq for seq in range(1, count + 1) if seq not in fragments]
+    if missing:
+        raise ValueError(
+            f"Incomplete UR: missing parts {', '.join(map(str, missing))} of {count}"
+        )
+    message = b"".join(fragments[seq] for seq in range(1, count + 1))[:message_length]
+    if zlib.crc32(message) != checksum:
+        raise ValueError("Invalid UR: message checksum mismatch")
+    return ur_type, message
+
+
+def _keypath(derivation_path: str, source_fingerprint: Optional[int]) -> _Tagged:
+    components: List[Any] = []
+    for component in derivation_path.split("/"):
+        if component in ("m", ""):
+            continue
+        hardened = component[-1] in "'h"
+        components += [int(component.rstrip("'h")), hardened]
+    keypath: Dict[int, Any] = {1: components}
+    if source_fingerprint is not None:
+        keypath[2] = source_fingerprint
+    return _Tagged(_KEYPATH_TAG, keypath)
+
+
+def qr_sign_request(
+    address: str,
+    derivation_path: str,
+    *,
+    transaction: Optional[Dict[str, Any]] = None,
+    typed_data: Optional[Dict[str, Any]] = None,
+    message: Optional[bytes] = None,
+    source_fingerprint: Optional[int] = None,
+    request_id: Optional[uuid.UUID] = None,
+    origin: Optional[str] = None,
+    max_fragment_length: int = 200,
+) -> SignRequest:
+    """
+    Encodes a request to sign a transaction, typed data or message as an
+    ``eth-sign-request`` UR for an air-gapped wallet to scan.
+
+    Args:
+        address: Address of the key to sign with.
+        derivation_path: BIP-32 path of the key in the wallet, such as
+            ``"m/44'/60'/0'/0/0"``.
+        transaction: Transaction to sign, as for ``sign_transaction``.
+        typed_data: EIP-712 typed data to sign, instead of a transaction.
+        message: EIP-191 personal message to sign, instead of a transaction.
+        source_fingerprint: Fingerprint of the wallet's master key, which some
+            wallets require to find the key.
+        request_id: Id to match the response to the request. Random if omitted.
+        origin: Name of the requesting application, shown by the wallet.
+        max_fragment_length: Longest CBOR payload, in bytes, per QR code.
+
+    Returns:
+        The request id, and the UR parts to display in turn.
+    """
+    chain_id = None
+    if sum(data is not None for data in (transaction, typed_data, message)) != 1:
+        raise ValueError("Pass exactly one of transaction, typed_data or message")
+    if transaction is not None:
+        transaction = _sanitize_transaction(transaction)
+        sign_data = encode_unsigned_transaction(json.dumps(transaction))
+        data_type = _TRANSACTION if sign_data[0] >= 0xC0 else _TYPED_TRANSACTION
+        chain_id = transaction.get("chainId")
+    elif typed_data is not None:
+        sign_data = json.dumps(typed_data).encode("utf-8")
+        data_type = _TYPED_DATA
+        chain_id = typed_data.get("domain", {}).get("chainId")
+    else:
+        sign_data, data_type = bytes(message), _PERSONAL_MESSAGE
+
+    request_id = request_id or uuid.uuid4()
+    request: Dict[int, Any] = {
+        1: _Tagged(_UUID_TAG, request_id.bytes),
+        2: sign_data,
+        3: data_type,
+    }
+    if chain_id is not None:
+        request[4] = int(chain_id, 0) if isinstance(chain_id, str) else chain_id
+    request[5] = _keypath(derivation_path, source_fingerprint)
+    request[6] = bytes.fromhex(address[2:])
+    if origin is not None:
+        request[7] = origin
+
+    parts = _encode_ur("eth-sign-request", _cbor_encode(request), max_fragment_length)
+    return SignRequest(request_id, parts)
+
+
+def decode_qr_signature(
+    parts: Union[str, Sequence[str]]
+) -> Tuple[Optional[uuid.UUID], bytes]:
+    """
+    Decodes an ``eth-signature`` UR scanned from an air-gapped wallet.
+
+    Args:
+        parts: The scanned UR, or all of its parts in any order.
+
+    Returns:
+        The id of the request it answers, if the wallet included one, and the
+        65-byte signature.
+    """
+    ur_type, message = _decode_ur(parts)
+    if ur_type != "eth-signature":
+        raise ValueError(f"Expected an eth-signature UR, got {ur_type}")
+    response, _ = _cbor_decode(message)
+    request_id = response.get(1)
+    if request_id is not None:
+        request_id = uuid.UUID(bytes=request_id.value)
+    return request_id, response[2]
+
+
+def signed_transaction_from_qr(
+    transaction: Dict[str, Any],
+    address: str,
+    parts: Union[str, Sequence[str]],
+    *,
+    request_id: Optional[uuid.UUID] = None,
+) -> SignedTransaction:
+    """
+    Assembles the broadcastable transaction an air-gapped wallet signed.
+
+    The signature goes through the same checks as any other: it must be by
+    ``address``, and the signing policy, approval hook, rate limits and audit log apply.
+
+    Args:
+        transaction: The transaction passed to ``qr_sign_request``.
+        address: Address of the key it was sent to.
+        parts: The scanned ``eth-signature`` UR, or all of its parts.
+        request_id: Id of the request, to check the response answers it.
+    """
+    response_id, signature = decode_qr_signature(parts)
+    if request_id is not None and response_id not in (None, request_id):
+        raise ValueError(
+            f"The signature answers request {response_id}, not {request_id}"
+        )
+    account = RemoteAccount(address, lambda digest: signature[:64])
+    return account.sign_transaction(transaction)
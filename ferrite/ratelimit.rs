@@ -0,0 +1,155 @@
+/*!
+Per-key signing rate limits.
+
+Each limited key keeps the times of its recent signatures, one log per window, and a
+signature is refused when it would put more than the limit in any second or minute.
+Limits are set per address rather than per private key, so configuring them never
+involves key material. Batches count every signature they would produce and are
+refused as a whole rather than signed in part.
+*/
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use ethers_core::types::Address;
+use ethers_core::utils::to_checksum;
+use pyo3::prelude::*;
+
+use crate::{address, errors};
+
+/// Signatures made within the last `window`, capped at `limit`.
+struct Window {
+    window: Duration,
+    limit: usize,
+    times: VecDeque<Instant>,
+}
+
+impl Window {
+    fn new(window: Duration, limit: usize) -> Self {
+        Window { window, limit, times: VecDeque::new() }
+    }
+
+    /// Forgets signatures that have left the window.
+    fn prune(&mut self, now: Instant) {
+        while self.times.front().is_some_and(|time| now - *time >= self.window) {
+            self.times.pop_front();
+        }
+    }
+
+    fn fits(&self, count: usize) -> bool {
+        self.times.len() + count <= self.limit
+    }
+
+    /// Returns how long until `count` more signatures fit, or `None` if they never
+    /// will because the batch alone is over the limit.
+    fn retry_after(&self, now: Instant, count: usize) -> Option<Duration> {
+        // Enough of the oldest signatures must leave the window to make room
+        let expiring = (self.times.len() + count).checked_sub(self.limit + 1)?;
+        let oldest = self.times.get(expiring).copied()?;
+        Some(self.window.saturating_sub(now - oldest))
+    }
+
+    fn unit(&self) -> &'static str {
+        if self.window.as_secs() == 1 {
+            "second"
+        } else {
+            "minute"
+        }
+    }
+}
+
+struct Limiter {
+    windows: Vec<Window>,
+    locked: bool,
+}
+
+static LIMITERS: Mutex<Option<HashMap<Address, Limiter>>> = Mutex::new(None);
+
+/// Set while any key is limited, so signing without limits never takes the lock.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Counts `count` signatures by `address` against its limits, refusing them all if
+/// any limit would be exceeded.
+pub fn take(address: Address, count: usize) -> PyResult<()> {
+    if !ENABLED.load(Ordering::Acquire) {
+        return Ok(());
+    }
+
+    let mut limiters = LIMITERS.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(limiter) = limiters.as_mut().and_then(|limiters| limiters.get_mut(&address))
+    else {
+        return Ok(());
+    };
+
+    let now = Instant::now();
+    for window in &mut limiter.windows {
+        window.prune(now);
+        if window.fits(count) {
+            continue;
+        }
+
+        let retry = match window.retry_after(now, count) {
+            Some(wait) => format!("retry in {:.3}s", wait.as_secs_f64()),
+            None => format!("a batch of {} can never fit", count),
+        };
+        tracing::info!(signer = ?address, count, "denied by the rate limit");
+        return Err(PyErr::new::<errors::RateLimitError, _>(format!(
+            "Rate limit of {} signatures per {} exceeded for {}: {}",
+            window.limit,
+            window.unit(),
+            to_checksum(&address, None),
+            retry
+        )));
+    }
+
+    for window in &mut limiter.windows {
+        window.times.extend(std::iter::repeat_n(now, count));
+    }
+    Ok(())
+}
+
+/// Limits how fast the key behind `address` can sign.
+///
+/// # Arguments
+/// * `address` - Address of the key to limit, as hex.
+/// * `per_second` - Most signatures allowed in any one second.
+/// * `per_minute` - Most signatures allowed in any one minute.
+/// * `lock` - Whether to keep these limits for the rest of the process. Once locked,
+///   the key's limits can't be changed or removed.
+///
+/// With neither limit, any existing limits on the key are removed.
+#[pyfunction]
+#[pyo3(signature = (address, *, per_second = None, per_minute = None, lock = false))]
+pub fn set_rate_limit(
+    address: &str,
+    per_second: Option<usize>,
+    per_minute: Option<usize>,
+    lock: bool,
+) -> PyResult<()> {
+    let address = address::parse(address)?;
+
+    let limits = [(Duration::from_secs(1), per_second), (Duration::from_secs(60), per_minute)];
+    let windows: Vec<Window> = limits
+        .into_iter()
+        .filter_map(|(window, limit)| limit.map(|limit| Window::new(window, limit)))
+        .collect();
+
+    let mut limiters = LIMITERS.lock().unwrap_or_else(|e| e.into_inner());
+    let limiters = limiters.get_or_insert_with(HashMap::new);
+    if limiters.get(&address).is_some_and(|limiter| limiter.locked) {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "The rate limits of {} are locked and can't be changed",
+            to_checksum(&address, None)
+        )));
+    }
+
+    if windows.is_empty() {
+        limiters.remove(&address);
+    } else {
+        limiters.insert(address, Limiter { windows, locked: lock });
+    }
+    ENABLED.store(!limiters.is_empty(), Ordering::Release);
+    Ok(())
+}
@@ -0,0 +1,892 @@
+"""
+Accounts whose keys live in a KMS, HSM or hardware wallet rather than in process memory.
+
+A ``RemoteAccount`` is an address plus a callable that signs 32-byte digests with the
+key behind it. Signing goes through the same Rust paths as local keys, so the signing
+policy, approval hook, rate limits, audit log and metrics all apply. The functions
+below build one for each supported key service; their SDKs are imported only when
+used and are not dependencies of ferrite.
+
+Other custody setups plug in without patching ferrite: any object with an ``address``
+and a ``sign`` method is a ``Signer``, and ``register_signer`` makes a factory for
+them available to ``signer_account`` by name. Packages can also register factories
+under the ``ferrite.signers`` entry point group, which is loaded on first use.
+"""
+
+import asyncio
+import base64
+import functools
+import json
+import sys
+import threading
+import time
+import urllib.request
+from contextlib import contextmanager
+from typing import Any, Callable, Dict, Iterator, List, Optional, Protocol, Tuple
+from typing import TypeVar, Union
+
+from eth_account.datastructures import SignedMessage, SignedTransaction
+from eth_account.messages import SignableMessage, _hash_eip191_message
+from hexbytes import HexBytes
+from _ferrite import SigningError, address_from_public_key  # type: ignore
+from _ferrite import rlp_decode, rlp_encode  # type: ignore
+from _ferrite import (  # type: ignore
+    sign_hash_remote as rust_sign_hash_remote,
+    sign_transaction_remote as rust_sign_transaction_remote,
+    sign_typed_data_remote as rust_sign_typed_data_remote,
+)
+from .account import _sanitize_transaction
+
+T = TypeVar("T")
+
+
+def _signed_message(
+    message_hash: bytes, signature_dict: Dict[str, Any]
+) -> SignedMessage:
+    """Builds a SignedMessage from a Rust signature dictionary."""
+    return SignedMessage(
+        message_hash=HexBytes(message_hash),
+        r=int.from_bytes(signature_dict["r"], "big"),
+        s=int.from_bytes(signature_dict["s"], "big"),
+        v=signature_dict["v"],
+        signature=HexBytes(signature_dict["signature"]),
+    )
+
+
+def _pem_to_der(pem: str) -> bytes:
+    """Strips the armor from a PEM public key."""
+    return base64.b64decode(
+        "".join(line for line in pem.splitlines() if not line.startswith("-----"))
+    )
+
+
+async def _in_thread(function: Callable[..., T], *args: Any) -> T:
+    """Runs a blocking call on the loop's default executor."""
+    loop = asyncio.get_running_loop()
+    return await loop.run_in_executor(None, functools.partial(function, *args))
+
+
+class RemoteAccount:
+    """
+    An eth-account style account that signs through a remote key.
+
+    ``sign`` is called with each 32-byte digest and must return its secp256k1 ECDSA
+    signature, DER encoded or as the 64-byte ``r || s``. ferrite works out the
+    recovery id and normalizes ``s``, so any standard ECDSA signer will do. With
+    ``prehashed=False``, ``sign`` is given the message instead and must sign its
+    keccak256; such accounts can't sign bare hashes.
+
+    Each signing method has an ``_async`` variant that waits for the key service on a
+    worker thread, so the round trip blocks neither the event loop nor, while the SDK
+    waits on the network, the GIL.
+    """
+
+    def __init__(
+        self, address: str, sign: Callable[[bytes], bytes], *, prehashed: bool = True
+    ) -> None:
+        self._address = address
+        self._sign = sign
+        self._prehashed = prehashed
+
+    @property
+    def address(self) -> str:
+        return self._address
+
+    def unsafe_sign_hash(self, message_hash: bytes) -> SignedMessage:
+        """Signs a raw 32-byte hash."""
+        return self._sign_hash(message_hash, None)
+
+    def sign_message(self, signable_message: SignableMessage) -> SignedMessage:
+        """Signs an EIP-191 message."""
+        preimage = (
+            b"\x19"
+            + signable_message.version
+            + signable_message.header
+            + signable_message.body
+        )
+        return self._sign_hash(_hash_eip191_message(signable_message), preimage)
+
+    def _sign_hash(
+        self, message_hash: bytes, preimage: Optional[bytes]
+    ) -> SignedMessage:
+        signature_dict = rust_sign_hash_remote(
+            message_hash,
+            self._address,
+            self._sign,
+            prehashed=self._prehashed,
+            preimage=preimage,
+        )
+        return _signed_message(message_hash, signature_dict)
+
+    def sign_typed_data(self, full_message: Dict[str, Any]) -> SignedMessage:
+        """Signs an EIP-712 typed data message."""
+        signature_dict = rust_sign_typed_data_remote(
+            json.dumps(full_message),
+            self._address,
+            self._sign,
+            prehashed=self._prehashed,
+        )
+        return _signed_message(b"", signature_dict)
+
+    def sign_transaction(self, transaction_dict: Dict[str, Any]) -> SignedTransaction:
+        """Signs a transaction."""
+        json_payload = json.dumps(_sanitize_transaction(transaction_dict))
+        signature_dict = rust_sign_transaction_remote(
+            json_payload, self._address, self._sign, prehashed=self._prehashed
+        )
+        return SignedTransaction(
+            raw_transaction=HexBytes(signature_dict["rawTransaction"]),
+            hash=HexBytes(signature_dict["hash"]),
+            r=int.from_bytes(signature_dict["r"], "big"),
+            s=int.from_bytes(signature_dict["s"], "big"),
+            v=signature_dict["v"],
+        )
+
+    async def unsafe_sign_hash_async(self, message_hash: bytes) -> SignedMessage:
+        """Signs a raw 32-byte hash without blocking the event loop."""
+        return await _in_thread(self.unsafe_sign_hash, message_hash)
+
+    async def sign_message_async(
+        self, signable_message: SignableMessage
+    ) -> SignedMessage:
+        """Signs an EIP-191 message without blocking the event loop."""
+        return await _in_thread(self.sign_message, signable_message)
+
+    async def sign_typed_data_async(
+        self, full_message: Dict[str, Any]
+    ) -> SignedMessage:
+        """Signs an EIP-712 typed data message without blocking the event loop."""
+        return await _in_thread(self.sign_typed_data, full_message)
+
+    async def sign_transaction_async(
+        self, transaction_dict: Dict[str, Any]
+    ) -> SignedTransaction:
+        """Signs a transaction without blocking the event loop."""
+        return await _in_thread(self.sign_transaction, transaction_dict)
+
+    def __repr__(self) -> str:
+        return f"RemoteAccount({self._address!r})"
+
+
+class Signer(Protocol):
+    """
+    A key ferrite can sign with, wherever it is held.
+
+    ``sign`` takes a 32-byte digest and returns its secp256k1 ECDSA signature, DER
+    encoded or as the 64-byte ``r || s``. A signer may set ``prehashed = False`` to be
+    given messages instead, as for ``RemoteAccount``. Signers written in Rust only need
+    to expose the same two members through PyO3.
+    """
+
+    @property
+    def address(self) -> str: ...
+
+    def sign(self, digest: bytes) -> bytes: ...
+
+
+def aws_kms_account(key_id: str, client: Optional[Any] = None) -> RemoteAccount:
+    """
+    Returns an account for an ``ECC_SECG_P256K1`` signing key in AWS KMS.
+
+    The key never leaves KMS: each signature is one ``Sign`` call on the digest.
+
+    Args:
+        key_id: Key id, ARN or alias of the KMS key.
+        client: A boto3 KMS client. Defaults to ``boto3.client("kms")``.
+    """
+    if client is None:
+        import boto3  # type: ignore
+
+        client = boto3.client("kms")
+
+    public_key = client.get_public_key(KeyId=key_id)["PublicKey"]
+
+    def sign(digest: bytes) -> bytes:
+        return client.sign(
+            KeyId=key_id,
+            Message=digest,
+            MessageType="DIGEST",
+            SigningAlgorithm="ECDSA_SHA_256",
+        )["Signature"]
+
+    return RemoteAccount(address_from_public_key(public_key), sign)
+
+
+def gcp_kms_account(key_version: str, client: Optional[Any] = None) -> RemoteAccount:
+    """
+    Returns an account for an ``EC_SIGN_SECP256K1_SHA256`` key in Google Cloud KMS.
+
+    The key never leaves Cloud KMS: each signature is one ``AsymmetricSign`` call on
+    the digest. Use the account's ``_async`` methods from asyncio code.
+
+    Args:
+        key_version: Resource name of the key version, ending in
+            ``cryptoKeyVersions/<n>``.
+        client: A ``google.cloud.kms.KeyManagementServiceClient``. Created with the
+            default credentials if omitted.
+    """
+    if client is None:
+        from google.cloud import kms  # type: ignore
+
+        client = kms.KeyManagementServiceClient()
+
+    public_key = _pem_to_der(client.get_public_key(request={"name": key_version}).pem)
+
+    def sign(digest: bytes) -> bytes:
+        request = {"name": key_version, "digest": {"sha256": digest}}
+        return client.asymmetric_sign(request=request).signature
+
+    return RemoteAccount(address_from_public_key(public_key), sign)
+
+
+def azure_key_vault_account(
+    vault_url: str,
+    key_name: str,
+    *,
+    version: Optional[str] = None,
+    credential: Optional[Any] = None,
+    client: Optional[Any] = None,
+) -> RemoteAccount:
+    """
+    Returns an account for a ``P-256K`` key in Azure Key Vault or Managed HSM.
+
+    The key never leaves the vault: each signature is one ``sign`` operation with
+    ``ES256K`` on the digest.
+
+    Args:
+        vault_url: URL of the vault or Managed HSM.
+        key_name: Name of the key.
+        version: Key version. Defaults to the latest when the account is created.
+        credential: An ``azure.identity`` credential. Defaults to the managed identity
+            of the host, whose tokens azure-identity renews as they expire.
+        client: An ``azure.keyvault.keys.KeyClient``, instead of one created from
+            ``vault_url`` and ``credential``.
+    """
+    if client is None:
+        from azure.identity import ManagedIdentityCredential  # type: ignore
+        from azure.keyvault.keys import KeyClient  # type: ignore
+
+        client = KeyClient(vault_url, credential or ManagedIdentityCredential())
+
+    try:
+        from azure.keyvault.keys.crypto import SignatureAlgorithm  # type: ignore
+
+        algorithm: Any = SignatureAlgorithm.es256_k
+    except ImportError:
+        algorithm = "ES256K"
+
+    key = client.get_key(key_name, version)
+    # Pin the version, so that rotating the key can't change the address mid-process
+    crypto = client.get_cryptography_client(
+        key_name, key_version=key.properties.version
+    )
+
+    def sign(digest: bytes) -> bytes:
+        return crypto.sign(algorithm, digest).signature
+
+    return RemoteAccount(address_from_public_key(key.key.x + key.key.y), sign)
+
+
+@contextmanager
+def yubihsm_session(
+    connector_url: str = "http://localhost:12345",
+    auth_key_id: int = 1,
+    password: str = "password",
+) -> Iterator[Any]:
+    """
+    Opens an authenticated YubiHSM 2 session, closing it and the connector on exit.
+
+    Args:
+        connector_url: URL of the yubihsm-connector, or ``yhusb://`` for direct USB.
+        auth_key_id: Object id of the authentication key.
+        password: Password the authentication key was derived from.
+    """
+    from yubihsm import YubiHsm  # type: ignore
+
+    hsm = YubiHsm.connect(connector_url)
+    try:
+        session = hsm.create_session_derived(auth_key_id, password)
+        try:
+            yield session
+        finally:
+            session.close()
+    finally:
+        hsm.close()
+
+
+def yubihsm_account(
+    session: Any, *, key_id: Optional[int] = None, label: Optional[str] = None
+) -> RemoteAccount:
+    """
+    Returns an account for an ``EC_K256`` asymmetric key on a YubiHSM 2.
+
+    The key never leaves the HSM: each signature is one ``SIGN_ECDSA`` command on the
+    digest. The account signs only while ``session`` is open.
+
+    Args:
+        session: An authenticated session, as from ``yubihsm_session``.
+        key_id: Object id of the key.
+        label: Label of the key, instead of ``key_id``. Must match exactly one
+            asymmetric key.
+    """
+    from cryptography.hazmat.primitives import hashes, serialization  # type: ignore
+    from cryptography.hazmat.primitives.asymmetric import utils  # type: ignore
+    from yubihsm.defs import OBJECT  # type: ignore
+
+    if (key_id is None) == (label is None):
+        raise ValueError("Pass exactly one of key_id and label")
+    if label is not None:
+        keys = session.list_objects(object_type=OBJECT.ASYMMETRIC_KEY, label=label)
+        if len(keys) != 1:
+            raise ValueError(
+                f"Expected one asymmetric key labeled {label!r}, found {len(keys)}"
+            )
+        key = keys[0]
+    else:
+        key = session.get_object(key_id, OBJECT.ASYMMETRIC_KEY)
+
+    public_key = key.get_public_key().public_bytes(
+        serialization.Encoding.X962, serialization.PublicFormat.UncompressedPoint
+    )
+
+    def sign(digest: bytes) -> bytes:
+        return key.sign_ecdsa(digest, hash=utils.Prehashed(hashes.SHA256()))
+
+    return RemoteAccount(address_from_public_key(public_key), sign)
+
+
+@contextmanager
+def pkcs11_session(
+    module: str,
+    *,
+    pin: str,
+    token_label: Optional[str] = None,
+    slot: Optional[int] = None,
+) -> Iterator[Any]:
+    """
+    Opens a logged-in PKCS#11 session, closing it on exit.
+
+    Args:
+        module: Path of the HSM vendor's PKCS#11 library, such as
+            ``/usr/lib/softhsm/libsofthsm2.so``.
+        pin: User PIN of the token.
+        token_label: Label of the token to open.
+        slot: Index of the slot to open, instead of ``token_label``.
+    """
+    import pkcs11  # type: ignore
+
+    if (token_label is None) == (slot is None):
+        raise ValueError("Pass exactly one of token_label and slot")
+    lib = pkcs11.lib(module)
+    if token_label is not None:
+        token = lib.get_token(token_label=token_label)
+    else:
+        token = lib.get_slots(token_present=True)[slot].get_token()
+
+    with token.open(user_pin=pin) as session:
+        yield session
+
+
+def pkcs11_account(session: Any, label: str) -> RemoteAccount:
+    """
+    Returns an account for a secp256k1 key pair on any PKCS#11 token.
+
+    The private key never leaves the token: each signature is one ``CKM_ECDSA``
+    operation on the digest. The account signs only while ``session`` is open.
+
+    Args:
+        session: A logged-in session, as from ``pkcs11_session``.
+        label: Label shared by the private key and its public key.
+    """
+    from pkcs11 import Attribute, KeyType, Mechanism, ObjectClass  # type: ignore
+
+    private_key = session.get_key(
+        object_class=ObjectClass.PRIVATE_KEY, key_type=KeyType.EC, label=label
+    )
+    public_key = session.get_key(
+        object_class=ObjectClass.PUBLIC_KEY, key_type=KeyType.EC, label=label
+    )
+    point = bytes(public_key[Attribute.EC_POINT])
+    # CKA_EC_POINT is a DER OCTET STRING around the SEC1 point, though some tokens
+    # return the bare point
+    if len(point) == 67 and point[:2] == b"\x04\x41":
+        point = point[2:]
+
+    def sign(digest: bytes) -> bytes:
+        return private_key.sign(digest, mechanism=Mechanism.ECDSA)
+
+    return RemoteAccount(address_from_public_key(point), sign)
+
+
+def _http(
+    session: Optional[Any], method: str, url: str, body: Optional[Any] = None
+) -> bytes:
+    """Sends a JSON request with ``session``, or with urllib when it is None."""
+    if session is not None:
+        response = session.request(method, url, json=body, timeout=30)
+        response.raise_for_status()
+        return response.content
+
+    data = None if body is None else json.dumps(body).encode("utf-8")
+    request = urllib.request.Request(
+        url, data=data, method=method, headers={"Content-Type": "application/json"}
+    )
+    with urllib.request.urlopen(request, timeout=30) as response:
+        return response.read()
+
+
+def web3signer_account(
+    url: str, address: str, *, session: Optional[Any] = None
+) -> RemoteAccount:
+    """
+    Returns an account for a secp256k1 key served by a Web3Signer-compatible signer.
+
+    Web3Signer hashes what it signs, so ferrite sends it the message rather than the
+    digest: the EIP-191 message, EIP-712 encoding or unsigned transaction. The account
+    can't sign bare hashes.
+
+    Args:
+        url: Base URL of the signer, such as ``http://localhost:9000``.
+        address: Address of the key to sign with, which the signer must serve.
+        session: A ``requests.Session``, for authentication or TLS settings.
+            Requests are sent with urllib if omitted.
+    """
+    url = url.rstrip("/")
+    public_keys = json.loads(_http(session, "GET", f"{url}/api/v1/eth1/publicKeys"))
+    for public_key in public_keys:
+        key_address = address_from_public_key(bytes.fromhex(public_key[2:]))
+        if key_address.lower() == address.lower():
+            break
+    else:
+        raise ValueError(f"{url} has no key for {address}")
+
+    def sign(message: bytes) -> bytes:
+        body = {"data": "0x" + message.hex()}
+        signature = _http(session, "POST", f"{url}/api/v1/eth1/sign/{public_key}", body)
+        # `r || s || v`; ferrite recovers `v` itself
+        return bytes.fromhex(signature.decode("ascii").strip()[2:])[:64]
+
+    return RemoteAccount(key_address, sign, prehashed=False)
+
+
+def _token_renewer(client: Any, margin: float) -> Callable[[], None]:
+    """
+    Returns a function renewing the client's Vault token once it is within ``margin``
+    seconds of expiring. Tokens that don't expire or can't be renewed are left alone.
+    """
+    token = client.auth.token.lookup_self()["data"]
+    if not token.get("renewable") or not token.get("ttl"):
+        return lambda: None
+
+    lock = threading.Lock()
+    expires = time.monotonic() + token["ttl"]
+
+    def renew() -> None:
+        nonlocal expires
+        with lock:
+            if time.monotonic() < expires - margin:
+                return
+            lease = client.auth.token.renew_self()["auth"]["lease_duration"]
+            expires = time.monotonic() + lease
+
+    return renew
+
+
+def vault_transit_account(
+    key_name: str,
+    *,
+    client: Optional[Any] = None,
+    mount_point: str = "transit",
+    renew_margin: float = 300,
+) -> RemoteAccount:
+    """
+    Returns an account for a secp256k1 key in a HashiCorp Vault transit engine.
+
+    The key never leaves Vault: each signature is one transit ``sign`` call on the
+    digest, marshaled as DER. Vault's built-in transit key types are NIST curves, so
+    the engine must serve secp256k1 keys, as some plugins and managed keys do; other
+    keys are rejected when the account is created.
+
+    Args:
+        key_name: Name of the transit key.
+        client: An ``hvac.Client``. Defaults to one configured from ``VAULT_ADDR`` and
+            ``VAULT_TOKEN``.
+        mount_point: Path the transit engine is mounted at.
+        renew_margin: Seconds before expiry at which a renewable token is renewed,
+            checked before each signature.
+    """
+    if client is None:
+        import hvac  # type: ignore
+
+        client = hvac.Client()
+
+    transit = client.secrets.transit
+    key = transit.read_key(name=key_name, mount_point=mount_point)["data"]
+    # Pin the version, so that rotating the key can't change the address mid-process
+    version = key["latest_version"]
+    public_key = _pem_to_der(key["keys"][str(version)]["public_key"])
+    renew = _token_renewer(client, renew_margin)
+
+    def sign(digest: bytes) -> bytes:
+        renew()
+        signature = transit.sign_data(
+            name=key_name,
+            hash_input=base64.b64encode(digest).decode("ascii"),
+            key_version=version,
+            hash_algorithm="sha2-256",
+            prehashed=True,
+            marshaling_algorithm="asn1",
+            mount_point=mount_point,
+        )["data"]["signature"]
+        # `vault:v<version>:<base64>`
+        return base64.b64decode(signature.rsplit(":", 1)[1])
+
+    return RemoteAccount(address_from_public_key(public_key), sign)
+
+
+_DEFAULT_PATH = "m/44'/60'/0'/0/0"
+
+_PERSONAL_MESSAGE_PREFIX = b"\x19Ethereum Signed Message:\n"
+
+
+def _derivation_path(path: str) -> List[int]:
+    """Parses a BIP-32 path such as ``m/44'/60'/0'/0/0`` into its indices."""
+    indices = []
+    for part in path.split("/")[1:] if path.startswith("m/") else path.split("/"):
+        hardened = part.endswith(("'", "h", "H"))
+        digits = part[:-1] if hardened else part
+        if not digits.isdigit() or int(digits) >= 1 << 31:
+            raise ValueError(f"Invalid derivation path {path!r}")
+        indices.append(int(digits) | (1 << 31 if hardened else 0))
+    return indices
+
+
+def _device_request(message: bytes) -> Tuple[str, Any]:
+    """
+    Works out what a hardware wallet is asked to sign from the message a
+    ``prehashed=False`` signer is given, since devices display and hash it themselves.
+
+    Returns ``("transaction", unsigned)`` with the unsigned transaction encoding,
+    ``("message", body)`` with the body of an EIP-191 personal message, or
+    ``("typed_data", (domain_separator, struct_hash))``.
+    """
+    if message[:2] == b"\x19\x01" and len(message) == 66:
+        return "typed_data", (message[2:34], message[34:])
+    if message.startswith(_PERSONAL_MESSAGE_PREFIX):
+        rest = message[len(_PERSONAL_MESSAGE_PREFIX) :]
+        # The decimal length runs straight into the body, so find where it ends
+        for end in range(1, len(rest) + 1):
+            if not rest[:end].isdigit() or (rest[:1] == b"0" and end > 1):
+                break
+            if int(rest[:end]) == len(rest) - end:
+                return "message", rest[end:]
+    if message[:1] not in (b"", b"\x19"):
+        return "transaction", message
+    raise SigningError(
+        "Signing failed: hardware wallets sign transactions, personal messages and "
+        "typed data only"
+    )
+
+
+def _apdu(instruction: int, p1: int, p2: int, data: bytes) -> bytes:
+    """Builds a command APDU for the Ledger Ethereum app."""
+    return bytes([0xE0, instruction, p1, p2, len(data)]) + data
+
+
+def ledger_account(
+    path: str = _DEFAULT_PATH, *, dongle: Optional[Any] = None
+) -> RemoteAccount:
+    """
+    Returns an account for a key on a Ledger running the Ethereum app.
+
+    The key never leaves the device, which shows each transaction, personal message
+    and typed data hash and signs it once confirmed there. Ledgers hash what they
+    sign, so the account can't sign bare hashes.
+
+    Args:
+        path: BIP-32 derivation path of the key.
+        dongle: A connected ``ledgerblue`` dongle, or any object whose
+            ``exchange(apdu)`` returns the device's response. Defaults to the first
+            Ledger found over USB, through ``ledgerblue``.
+    """
+    if dongle is None:
+        from ledgerblue.comm import getDongle  # type: ignore
+
+        dongle = getDongle()
+
+    indices = _derivation_path(path)
+    encoded_path = bytes([len(indices)]) + b"".join(
+        index.to_bytes(4, "big") for index in indices
+    )
+
+    # GET ETH PUBLIC ADDRESS, without showing it on the device
+    response = bytes(dongle.exchange(_apdu(0x02, 0x00, 0x00, encoded_path)))
+    public_key = response[1 : 1 + response[0]]
+
+    def send(instruction: int, payload: bytes, last_chunk_from: int = 0) -> bytes:
+        # Commands longer than one APDU continue in chunks with P1 = 0x80
+        data = encoded_path + payload
+        offset = 0
+        while True:
+            size = min(150, len(data) - offset)
+            if last_chunk_from and offset + size >= last_chunk_from:
+                # The app misreads an EIP-155 chain id split across chunks
+                size = len(data) - offset
+            chunk = data[offset : offset + size]
+            response = dongle.exchange(
+                _apdu(instruction, 0x80 if offset else 0x00, 0x00, chunk)
+            )
+            offset += size
+            if offset == len(data):
+                # `v || r || s`; ferrite recovers `v` itself
+                return bytes(response)[1:65]
+
+    def sign(message: bytes) -> bytes:
+        kind, request = _device_request(message)
+        if kind == "typed_data":
+            # SIGN ETH EIP 712, from the domain separator and struct hash
+            return send(0x0C, request[0] + request[1])
+        if kind == "message":
+            # SIGN ETH PERSONAL MESSAGE, which the app prefixes itself
+            return send(0x08, len(request).to_bytes(4, "big") + request)
+        # SIGN ETH TRANSACTION
+        last_chunk_from = 0
+        if request[0] >= 0xC0:
+            fields = rlp_decode(request)
+            if len(fields) == 9:
+                # Where the EIP-155 `chainId, 0, 0` starts, past the list's header byte
+                tail = len(rlp_encode(fields[6:])) - 1
+                last_chunk_from = len(encoded_path) + len(request) - tail
+        return send(0x04, request, last_chunk_from)
+
+    return RemoteAccount(address_from_public_key(public_key), sign, prehashed=False)
+
+
+class _TrezorAccount(RemoteAccount):
+    """A ``RemoteAccount`` that hands its Trezor whole typed data messages."""
+
+    def __init__(
+        self, address: str, sign: Callable[[bytes], bytes], pending: threading.local
+    ) -> None:
+        super().__init__(address, sign, prehashed=False)
+        self._pending = pending
+
+    def sign_typed_data(self, full_message: Dict[str, Any]) -> SignedMessage:
+        """Signs an EIP-712 typed data message."""
+        # Only the Trezor One signs typed data from its hashes; later models need the
+        # message itself, to display it
+        self._pending.full_message = full_message
+        try:
+            return super().sign_typed_data(full_message)
+        finally:
+            self._pending.full_message = None
+
+
+class _TrezorPassphraseUI:
+    """A ``trezorlib`` UI answering passphrase requests from the host."""
+
+    def __init__(self, ui: Any, passphrase: str) -> None:
+        self._ui = ui
+        self._passphrase = passphrase
+
+    def button_request(self, request: Any) -> None:
+        self._ui.button_request(request)
+
+    def get_pin(self, code: Optional[Any] = None) -> str:
+        return self._ui.get_pin(code)
+
+    def get_passphrase(self, available_on_device: bool) -> str:
+        return self._passphrase
+
+
+def _trezor_transaction(client: Any, n: List[int], unsigned: bytes) -> bytes:
+    """Signs an unsigned transaction encoding on a Trezor, returning ``r || s``."""
+    from trezorlib import ethereum, messages  # type: ignore
+
+    def integer(value: bytes) -> int:
+        return int.from_bytes(value, "big")
+
+    def address(value: bytes) -> str:
+        # Contract creation has no recipient
+        return "0x" + value.hex() if value else ""
+
+    if unsigned[0] >= 0xC0:
+        fields = rlp_decode(unsigned)
+        nonce, gas_price, gas, to, value, data = fields[:6]
+        _, r, s = ethereum.sign_tx(
+            client,
+            n,
+            nonce=integer(nonce),
+            gas_price=integer(gas_price),
+            gas_limit=integer(gas),
+            to=address(to),
+            value=integer(value),
+            data=data,
+            chain_id=integer(fields[6]) if len(fields) == 9 else None,
+        )
+    elif unsigned[0] == 0x02:
+        fields = rlp_decode(unsigned[1:])
+        chain_id, nonce, priority_fee, max_fee, gas, to, value, data = fields[:8]
+        access_list = [
+            messages.EthereumAccessList(address=address(item), storage_keys=keys)
+            for item, keys in fields[8]
+        ]
+        _, r, s = ethereum.sign_tx_eip1559(
+            client,
+            n,
+            nonce=integer(nonce),
+            gas_limit=integer(gas),
+            to=address(to),
+            value=integer(value),
+            data=data,
+            chain_id=integer(chain_id),
+            max_gas_fee=integer(max_fee),
+            max_priority_fee=integer(priority_fee),
+            access_list=access_list,
+        )
+    else:
+        raise SigningError(
+            f"Signing failed: Trezors can't sign type {unsigned[0]} transactions"
+        )
+    return bytes(r).rjust(32, b"\x00") + bytes(s).rjust(32, b"\x00")
+
+
+def trezor_account(
+    path: str = _DEFAULT_PATH,
+    *,
+    passphrase: Optional[str] = None,
+    client: Optional[Any] = None,
+) -> RemoteAccount:
+    """
+    Returns an account for a key on a Trezor.
+
+    The key never leaves the device, which shows each transaction, personal message
+    and typed data message and signs it once confirmed there. Trezors hash what they
+    sign, so the account can't sign bare hashes, nor EIP-2930 transactions, which
+    their firmware doesn't support.
+
+    Args:
+        path: BIP-32 derivation path of the key.
+        passphrase: BIP-39 passphrase of the hidden wallet holding the key, sent from
+            the host rather than typed on the device.
+        client: A ``trezorlib`` client, instead of one for the first Trezor found
+            over USB. Its own UI then supplies any passphrase.
+    """
+    from trezorlib import ethereum  # type: ignore
+
+    if client is None:
+        from trezorlib.client import get_default_client  # type: ignore
+        from trezorlib.ui import ClickUI  # type: ignore
+
+        ui = ClickUI()
+        if passphrase is not None:
+            ui = _TrezorPassphraseUI(ui, passphrase)
+        client = get_default_client(ui=ui)
+    elif passphrase is not None:
+        raise ValueError("Pass the passphrase through the client's UI instead")
+
+    n = _derivation_path(path)
+    pending = threading.local()
+
+    def sign(message: bytes) -> bytes:
+        kind, request = _device_request(message)
+        if kind == "typed_data":
+            full_message = getattr(pending, "full_message", None)
+            if full_message is not None:
+                signed = ethereum.sign_typed_data(
+                    client, n, full_message, metamask_v4_compat=True
+                )
+            else:
+                signed = ethereum.sign_typed_data_hash(client, n, *request)
+            return signed.signature[:64]
+        if kind == "message":
+            return ethereum.sign_message(client, n, request).signature[:64]
+        return _trezor_transaction(client, n, request)
+
+    return _TrezorAccount(ethereum.get_address(client, n), sign, pending)
+
+
+SignerFactory = Callable[..., Union[Signer, RemoteAccount]]
+
+_SIGNERS: Dict[str, SignerFactory] = {
+    "aws_kms": aws_kms_account,
+    "gcp_kms": gcp_kms_account,
+    "azure_key_vault": azure_key_vault_account,
+    "yubihsm": yubihsm_account,
+    "pkcs11": pkcs11_account,
+    "vault_transit": vault_transit_account,
+    "web3signer": web3signer_account,
+    "ledger": ledger_account,
+    "trezor": trezor_account,
+}
+_entry_points_loaded = False
+
+
+def _load_entry_points() -> None:
+    """Registers the factories installed packages declare under ``ferrite.signers``."""
+    global _entry_points_loaded
+    if _entry_points_loaded:
+        return
+    _entry_points_loaded = True
+
+    from importlib import metadata
+
+    if sys.version_info >= (3, 10):
+        entry_points = list(metadata.entry_points(group="ferrite.signers"))
+    else:
+        entry_points = metadata.entry_points().get("ferrite.signers", [])
+    for entry_point in entry_points:
+        # Signers registered at runtime take precedence over installed ones
+        _SIGNERS.setdefault(entry_point.name, entry_point.load())
+
+
+def register_signer(
+    name: str, factory: SignerFactory, *, replace: bool = False
+) -> None:
+    """
+    Makes a signer backend available to ``signer_account`` under ``name``.
+
+    Args:
+        name: Name to select the backend by.
+        factory: Callable taking the backend's options as keyword arguments and
+            returning a ``Signer`` or a ``RemoteAccount``.
+        replace: Whether to replace a backend already registered under ``name``.
+    """
+    if not callable(factory):
+        raise TypeError(
+            f"Signer factory must be callable, got {type(factory).__name__}"
+        )
+    _load_entry_points()
+    if name in _SIGNERS and not replace:
+        raise ValueError(f"A signer named {name!r} is already registered")
+    _SIGNERS[name] = factory
+
+
+def registered_signers() -> List[str]:
+    """Returns the names of the registered signer backends."""
+    _load_entry_points()
+    return sorted(_SIGNERS)
+
+
+def signer_account(name: str, **options: Any) -> RemoteAccount:
+    """
+    Returns an account signing through the registered signer backend ``name``.
+
+    Args:
+        name: Name the backend was registered under, such as ``"aws_kms"``.
+        **options: Keyword arguments for the backend's factory.
+    """
+    _load_entry_points()
+    try:
+        factory = _SIGNERS[name]
+    except KeyError:
+        raise ValueError(
+            f"No signer named {name!r}; registered: {', '.join(sorted(_SIGNERS))}"
+        ) from None
+
+    signer = factory(**options)
+    if isinstance(signer, RemoteAccount):
+        return signer
+    return RemoteAccount(
+        signer.address, signer.sign, prehashed=getattr(signer, "prehashed", True)
+    )
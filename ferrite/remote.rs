@@ -0,0 +1,237 @@
+/*!
+Remote signers: keys held in a KMS or HSM rather than in process memory.
+
+A remote signer is an address plus a Python callable that signs a 32-byte digest with
+the key behind it, typically through a cloud KMS or HSM SDK. Everything else happens as
+it does for keys in memory: parsing, hashing, the signing policy, approval hook, rate
+limits, audit log and metrics.
+
+The callable may return a DER-encoded ECDSA signature, as AWS and Google Cloud KMS do,
+or the raw 64-byte `r || s`, as Azure Key Vault and PKCS#11 tokens do. Neither carries
+the recovery id Ethereum signatures need, so it is found by recovering the public key
+both ways and matching the address. KMSs don't promise low-s signatures either, so `s`
+is normalized before anything is returned.
+
+Signers that hash what they sign themselves, as hardware wallets and Web3Signer do, are
+given the message instead of its digest, and so can't sign bare hashes without the
+message behind them.
+
+Parsing and hashing run without the GIL, which is taken back in the calling interpreter
+only to call the signer; SDKs release it again while they wait on the network.
+*/
+
+use std::time::Instant;
+
+use ethers_core::types::{Address, Signature, H256, U256};
+use ethers_core::utils::to_checksum;
+use k256::ecdsa::{RecoveryId, VerifyingKey};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::{address, audit, errors, interpreter, keccak, metrics, request, DigestSigner};
+
+/// A key behind a Python signing callable.
+pub struct Remote {
+    address: Address,
+    sign: PyObject,
+    /// Whether `sign` takes the digest, rather than the message it keccak256-hashes.
+    prehashed: bool,
+}
+
+impl Remote {
+    fn new(address: &str, sign: Bound<PyAny>, prehashed: bool) -> PyResult<Self> {
+        if !sign.is_callable() {
+            return Err(PyTypeError::new_err(format!(
+                "Remote signer must be callable, got {}",
+                sign.get_type().name()?
+            )));
+        }
+        Ok(Remote { address: address::parse(address)?, sign: sign.unbind(), prehashed })
+    }
+}
+
+impl DigestSigner for Remote {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn sign(
+        &self,
+        hash: H256,
+        preimage: impl FnOnce() -> Option<Vec<u8>>,
+    ) -> PyResult<Signature> {
+        let message = if self.prehashed {
+            hash.as_bytes().to_vec()
+        } else {
+            preimage().filter(|preimage| keccak::keccak256(preimage) == hash.0).ok_or_else(
+                || {
+                    PyErr::new::<errors::SigningError, _>(
+                        "Signing failed: the remote signer hashes messages itself, and the \
+                         message behind this hash is unknown",
+                    )
+                },
+            )?
+        };
+        let _span = tracing::debug_span!("backend", signer = ?self.address).entered();
+        let signature: Vec<u8> = interpreter::with_gil(|py| {
+            let sign = || self.sign.bind(py).call1((PyBytes::new(py, &message),))?.extract();
+            // Formatted here, as formatting an error elsewhere takes the GIL afresh
+            sign().map_err(|mut e| {
+                errors::normalize(py, &mut e);
+                tracing::warn!(error = %e.value(py), "remote signer failed");
+                e
+            })
+        })?;
+        recoverable_signature(&signature, hash, self.address).map_err(|message| {
+            tracing::warn!(error = %message, "remote signer returned a bad signature");
+            PyErr::new::<errors::SigningError, _>(format!("Signing failed: {}", message))
+        })
+    }
+}
+
+/// Turns the DER or raw `r || s` signature of `hash` by `address` into a low-s
+/// Ethereum signature with `v` set to the recovery id + 27, or describes why it can't.
+fn recoverable_signature(
+    signature: &[u8],
+    hash: H256,
+    address: Address,
+) -> Result<Signature, String> {
+    let signature = match signature.len() {
+        64 => k256::ecdsa::Signature::from_slice(signature),
+        _ => k256::ecdsa::Signature::from_der(signature),
+    }
+    .map_err(|_| {
+        format!(
+            "remote signer returned {} bytes that are neither a DER nor a 64-byte signature",
+            signature.len()
+        )
+    })?;
+    let signature = signature.normalize_s().unwrap_or(signature);
+
+    let recovery_id = [RecoveryId::new(false, false), RecoveryId::new(true, false)]
+        .into_iter()
+        .find(|&id| {
+            VerifyingKey::recover_from_prehash(hash.as_bytes(), &signature, id)
+                .is_ok_and(|key| address::key_address(&key) == address)
+        })
+        .ok_or_else(|| {
+            format!(
+                "remote signature is not by {}; check the signer's key",
+                to_checksum(&address, None)
+            )
+        })?;
+
+    Ok(Signature {
+        r: U256::from_big_endian(&signature.r().to_bytes()),
+        s: U256::from_big_endian(&signature.s().to_bytes()),
+        v: recovery_id.to_byte() as u64 + 27,
+    })
+}
+
+/// Signs a 32-byte hash with a remote signer.
+///
+/// # Arguments
+/// * `hash` - 32-byte message hash to sign.
+/// * `address` - Address of the remote key, as hex.
+/// * `sign` - Callable taking the 32-byte digest and returning its secp256k1 ECDSA
+///   signature, DER-encoded or as the 64-byte `r || s`.
+/// * `prehashed` - Whether `sign` takes the digest. When false, it takes the message
+///   and signs its keccak256 instead.
+/// * `preimage` - The message `hash` is the keccak256 of, for signers that aren't
+///   prehashed.
+///
+/// # Returns
+/// A Python dictionary with the signature components:
+/// `r`, `s`, `v`, `y_parity`, and `signature`.
+#[pyfunction]
+#[pyo3(signature = (hash, address, sign, *, prehashed = true, preimage = None))]
+pub fn sign_hash_remote(
+    py: Python,
+    hash: &[u8],
+    address: &str,
+    sign: Bound<PyAny>,
+    prehashed: bool,
+    preimage: Option<&[u8]>,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let remote = Remote::new(address, sign, prehashed)?;
+    let address = remote.address;
+    let context = audit::context(py)?;
+    let mut result = interpreter::allow_threads(py, || {
+        crate::hash_signature_with(hash, preimage, || Ok(remote))
+    });
+    metrics::observe(py, "hash", Some(address), result.as_mut().map(|_| 1), started);
+    let (signature, record) = result?;
+    audit::record(py, &[record], context.as_ref())?;
+
+    Ok(crate::signature_dict(py, &signature, None)?.into_any().unbind())
+}
+
+/// Signs an EIP-712 typed data object with a remote signer.
+///
+/// # Arguments
+/// * `payload` - JSON string of the EIP-712 TypedData.
+/// * `address` - Address of the remote key, as hex.
+/// * `sign` - Callable signing a 32-byte digest, as for [`sign_hash_remote`].
+/// * `prehashed` - Whether `sign` takes the digest, as for [`sign_hash_remote`].
+///
+/// # Returns
+/// A Python dictionary with the signature components:
+/// `r`, `s`, `v`, `y_parity`, and `signature`.
+#[pyfunction]
+#[pyo3(signature = (payload, address, sign, *, prehashed = true))]
+pub fn sign_typed_data_remote(
+    py: Python,
+    payload: &str,
+    address: &str,
+    sign: Bound<PyAny>,
+    prehashed: bool,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let remote = Remote::new(address, sign, prehashed)?;
+    let address = remote.address;
+    let context = audit::context(py)?;
+    let mut result = interpreter::allow_threads(py, || {
+        crate::typed_data_signature_with(payload, || Ok(remote))
+    });
+    metrics::observe(py, "typed_data", Some(address), result.as_mut().map(|_| 1), started);
+    let (signature, record) = result?;
+    audit::record(py, &[record], context.as_ref())?;
+
+    Ok(crate::signature_dict(py, &signature, None)?.into_any().unbind())
+}
+
+/// Signs a transaction object with a remote signer.
+///
+/// # Arguments
+/// * `payload` - JSON string of the transaction dictionary.
+/// * `address` - Address of the remote key, as hex.
+/// * `sign` - Callable signing a 32-byte digest, as for [`sign_hash_remote`].
+/// * `prehashed` - Whether `sign` takes the digest, as for [`sign_hash_remote`].
+///
+/// # Returns
+/// A Python dictionary with the signature components and raw transaction:
+/// `r`, `s`, `v`, `y_parity`, `signature`, `hash`, `rawTransaction` (bytes).
+#[pyfunction]
+#[pyo3(signature = (payload, address, sign, *, prehashed = true))]
+pub fn sign_transaction_remote(
+    py: Python,
+    payload: &str,
+    address: &str,
+    sign: Bound<PyAny>,
+    prehashed: bool,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let remote = Remote::new(address, sign, prehashed)?;
+    let address = remote.address;
+    let context = audit::context(py)?;
+    let mut signed = interpreter::allow_threads(py, || {
+        crate::signed_transaction_with(payload, None, request::Checks::default(), || Ok(remote))
+    });
+    metrics::observe(py, "transaction", Some(address), signed.as_mut().map(|_| 1), started);
+    let signed = signed?;
+    audit::record(py, std::slice::from_ref(&signed.record), context.as_ref())?;
+
+    Ok(crate::transaction_dict(py, &signed, None)?.into_any().unbind())
+}
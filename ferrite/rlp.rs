@@ -0,0 +1,147 @@
+/*!
+RLP encoding and decoding of arbitrary nested lists and byte strings.
+
+Transactions are encoded field by field in [`crate::tx`]; this exposes the same encoder
+to Python for tooling that builds or takes apart raw transactions, receipts and trie
+proofs itself. Decoding is strict: non-canonical lengths, single bytes wrapped in a
+string header and trailing bytes are all rejected, as consensus clients reject them.
+*/
+
+use ethers_core::utils::rlp::{Rlp, RlpStream};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyByteArray, PyBytes, PyInt, PyList, PyMemoryView, PyString, PyTuple};
+
+use crate::errors;
+
+/// How deeply lists may nest in decoded input, which is far deeper than any Ethereum
+/// structure but keeps hostile input from exhausting the stack.
+const MAX_DEPTH: usize = 1024;
+
+/// An item to encode, gathered from Python objects while the GIL is held.
+enum Item {
+    Bytes(Vec<u8>),
+    List(Vec<Item>),
+}
+
+impl Item {
+    fn extract(value: &Bound<PyAny>) -> PyResult<Self> {
+        if let Ok(bytes) = value.downcast::<PyBytes>() {
+            return Ok(Item::Bytes(bytes.as_bytes().to_vec()));
+        }
+        if let Ok(bytes) = value.downcast::<PyByteArray>() {
+            return Ok(Item::Bytes(bytes.to_vec()));
+        }
+        if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+            let items = value.try_iter()?.map(|item| Item::extract(&item?));
+            return items.collect::<PyResult<_>>().map(Item::List);
+        }
+        if let Ok(int) = value.downcast::<PyInt>() {
+            return Self::extract_int(int);
+        }
+        if let Ok(view) = value.downcast::<PyMemoryView>() {
+            return Ok(Item::Bytes(view.call_method0("tobytes")?.extract()?));
+        }
+        let hint = if value.is_instance_of::<PyString>() { "; encode strings first" } else { "" };
+        Err(PyTypeError::new_err(format!(
+            "Cannot RLP-encode '{}'; expected bytes, an int or a list{}",
+            value.get_type().name()?,
+            hint
+        )))
+    }
+
+    /// Encodes an integer as its minimal big-endian bytes, with zero as the empty string.
+    fn extract_int(int: &Bound<PyInt>) -> PyResult<Self> {
+        if let Ok(value) = int.extract::<u128>() {
+            let bytes = value.to_be_bytes();
+            let skip = bytes.iter().take_while(|b| **b == 0).count();
+            return Ok(Item::Bytes(bytes[skip..].to_vec()));
+        }
+        if int.lt(0)? {
+            return Err(PyErr::new::<errors::FerriteError, _>(
+                "Cannot RLP-encode a negative integer",
+            ));
+        }
+        let bits: usize = int.call_method0("bit_length")?.extract()?;
+        Ok(Item::Bytes(int.call_method1("to_bytes", (bits.div_ceil(8), "big"))?.extract()?))
+    }
+
+    fn append_to(&self, rlp: &mut RlpStream) {
+        match self {
+            Item::Bytes(bytes) => {
+                rlp.append(&bytes.as_slice());
+            }
+            Item::List(items) => {
+                rlp.begin_list(items.len());
+                items.iter().for_each(|item| item.append_to(rlp));
+            }
+        }
+    }
+}
+
+fn invalid(reason: impl std::fmt::Display) -> PyErr {
+    PyErr::new::<errors::FerriteError, _>(format!("Invalid RLP: {}", reason))
+}
+
+/// Splits the first complete item off `bytes`.
+fn split_item(bytes: &[u8]) -> PyResult<(&[u8], &[u8])> {
+    let info = Rlp::new(bytes).payload_info().map_err(invalid)?;
+    let len = info.header_len + info.value_len;
+    if bytes.len() < len {
+        return Err(invalid("item is longer than its input"));
+    }
+    Ok(bytes.split_at(len))
+}
+
+/// Decodes a single complete item, nested `depth` lists deep, into bytes or a list.
+fn decode_item(py: Python, item: &[u8], depth: usize) -> PyResult<PyObject> {
+    let rlp = Rlp::new(item);
+    if !rlp.is_list() {
+        let data = rlp.decoder().decode_value(|data| Ok(data.to_vec())).map_err(invalid)?;
+        return Ok(PyBytes::new(py, &data).into_any().unbind());
+    }
+    if depth == MAX_DEPTH {
+        return Err(invalid(format!("lists nest more than {} deep", MAX_DEPTH)));
+    }
+    let header_len = rlp.payload_info().map_err(invalid)?.header_len;
+    let list = PyList::empty(py);
+    let mut payload = &item[header_len..];
+    while !payload.is_empty() {
+        let (child, rest) = split_item(payload)?;
+        list.append(decode_item(py, child, depth + 1)?)?;
+        payload = rest;
+    }
+    Ok(list.into_any().unbind())
+}
+
+/// RLP-encodes nested lists of byte strings.
+///
+/// # Arguments
+/// * `item` - `bytes`, `bytearray` or `memoryview` for a string, a non-negative `int`
+///   for its minimal big-endian encoding, or a list or tuple of items.
+///
+/// # Returns
+/// The encoded bytes.
+#[pyfunction]
+pub fn rlp_encode(py: Python, item: &Bound<PyAny>) -> PyResult<PyObject> {
+    let item = Item::extract(item)?;
+    let mut rlp = RlpStream::new();
+    item.append_to(&mut rlp);
+    Ok(PyBytes::new(py, &rlp.out()).into_any().unbind())
+}
+
+/// Decodes RLP into nested lists of byte strings.
+///
+/// # Arguments
+/// * `data` - The encoding of a single item, with nothing after it.
+///
+/// # Returns
+/// `bytes` for a string, or a list of decoded items for a list.
+#[pyfunction]
+pub fn rlp_decode(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    let (item, rest) = split_item(data)?;
+    if !rest.is_empty() {
+        return Err(invalid("input continues after the item"));
+    }
+    decode_item(py, item, 0)
+}
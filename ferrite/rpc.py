@@ -0,0 +1,134 @@
+"""
+Filling in transactions from a node before they're signed.
+
+``fill_transaction`` asks a JSON-RPC endpoint for a transaction's missing nonce, gas
+limit, chain ID and fees in a single batch request, sent from Rust without the GIL,
+instead of the round trip per field web3.py makes. ``sign_and_send`` goes on to sign the
+transaction and broadcast it, retrying with backoff while the endpoint is unreachable.
+
+The client speaks plain HTTP only, so ``https://`` providers have to be reached through
+a local node or a TLS-terminating proxy.
+
+Only available when the extension is built with the ``rpc`` feature; importing this
+module raises ``ImportError`` otherwise.
+"""
+
+import json
+from typing import Any, Dict
+
+from hexbytes import HexBytes
+
+try:
+    from _ferrite import (  # type: ignore
+        fill_transaction as rust_fill_transaction,
+        send_raw_transaction as rust_send_raw_transaction,
+    )
+except ImportError as e:
+    raise ImportError(
+        "ferrite was built without the JSON-RPC client; rebuild it with "
+        "`maturin develop --features rpc`"
+    ) from e
+
+from _ferrite import addresses_from_keys as rust_addresses_from_keys  # type: ignore
+from _ferrite import sign_transaction as rust_sign_transaction  # type: ignore
+
+from .account import _private_key_bytes, _sanitize_transaction
+
+
+def fill_transaction(
+    transaction_dict: Dict[str, Any], rpc_url: str, *, timeout: float = 10.0
+) -> Dict[str, Any]:
+    """
+    Returns a copy of a transaction with its missing fields filled in from a node.
+
+    The transaction must have a ``from`` address. A missing ``nonce`` is the sender's
+    pending transaction count, ``gas`` comes from ``eth_estimateGas`` and ``chainId``
+    from ``eth_chainId``. Transactions with a ``gasPrice`` or of type 0 or 1 get a
+    missing ``gasPrice`` from ``eth_gasPrice``; others get ``maxPriorityFeePerGas``
+    from ``eth_maxPriorityFeePerGas`` and ``maxFeePerGas`` as twice the latest base fee
+    plus the priority fee. Fields that are already set are left alone.
+
+    ``timeout`` is in seconds, for connecting and for each read or write.
+    """
+    filled = rust_fill_transaction(
+        json.dumps(_sanitize_transaction(transaction_dict)), rpc_url, timeout
+    )
+    return {
+        **transaction_dict,
+        **{field: int(value, 16) for field, value in filled.items()},
+    }
+
+
+def send_raw_transaction(
+    raw_transaction: bytes,
+    rpc_url: str,
+    *,
+    timeout: float = 10.0,
+    retries: int = 3,
+    backoff: float = 0.1,
+) -> HexBytes:
+    """
+    Broadcasts a signed transaction with ``eth_sendRawTransaction``, returning its hash.
+
+    When the endpoint can't be reached, times out or answers HTTP 429, 502, 503 or 504,
+    the transaction is sent again up to ``retries`` times, waiting ``backoff`` seconds
+    before the first retry and twice as long before each one after. A node answering
+    that it already has the transaction counts as success; any other error from the
+    node raises ``FerriteError`` straight away.
+    """
+    return HexBytes(
+        rust_send_raw_transaction(
+            bytes(raw_transaction), rpc_url, timeout, retries, backoff
+        )
+    )
+
+
+def sign_and_send(
+    transaction_dict: Dict[str, Any],
+    signer: Any,
+    rpc_url: str,
+    *,
+    fill: bool = True,
+    timeout: float = 10.0,
+    retries: int = 3,
+    backoff: float = 0.1,
+) -> HexBytes:
+    """
+    Signs a transaction and broadcasts it, returning its hash.
+
+    Args:
+        transaction_dict: The transaction. Its ``from``, if given, must be the
+            signer's address.
+        signer: The private key, as bytes or hex, or an account with ``address`` and
+            ``sign_transaction`` such as a ``RemoteAccount``.
+        rpc_url: An ``http://`` JSON-RPC endpoint.
+        fill: Whether to fill in missing fields first, as ``fill_transaction`` does.
+        timeout: Seconds to wait to connect and for each read or write.
+        retries: How many times to resend while the endpoint is unreachable.
+        backoff: Seconds to wait before the first retry, doubling for each one after.
+
+    Returns:
+        The transaction hash, once a node has accepted the transaction.
+    """
+    if hasattr(signer, "sign_transaction"):
+        address = signer.address
+    else:
+        private_key = _private_key_bytes(signer)
+        address = rust_addresses_from_keys([private_key])[0]
+    sender = transaction_dict.get("from")
+    if sender is not None and str(sender).lower() != address.lower():
+        raise ValueError(f"Transaction is from {sender}, but the signer is {address}")
+
+    transaction = {**transaction_dict, "from": address}
+    if fill:
+        transaction = fill_transaction(transaction, rpc_url, timeout=timeout)
+    del transaction["from"]
+
+    if hasattr(signer, "sign_transaction"):
+        raw_transaction = signer.sign_transaction(transaction).raw_transaction
+    else:
+        payload = json.dumps(_sanitize_transaction(transaction))
+        raw_transaction = rust_sign_transaction(payload, private_key)["rawTransaction"]
+    return send_raw_transaction(
+        raw_transaction, rpc_url, timeout=timeout, retries=retries, backoff=backoff
+    )
@@ -0,0 +1,455 @@
+/*!
+A minimal JSON-RPC client for filling in transactions before they're signed.
+
+Filling a transaction takes a nonce, a gas estimate, the chain ID and fees from a node.
+Doing that from Python costs a round trip per field; here every missing field is asked
+for in a single JSON-RPC batch, sent without the GIL.
+
+The client speaks plain HTTP/1.1 over a TCP socket and nothing more, so that it adds no
+dependencies: `https://` endpoints are refused, and a hosted provider has to be reached
+through a local node or a TLS-terminating proxy.
+
+Raw transactions are broadcast the same way, retrying with exponential backoff when the
+endpoint can't be reached or is overloaded. Resending a transaction is harmless: a node
+that already has it answers "already known", which counts as success.
+
+Only built with the `rpc` feature.
+*/
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use ethers_core::types::U256;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use serde_json::{json, Map, Value};
+
+use crate::errors;
+use crate::keccak::keccak256;
+
+/// Fields of a transaction passed on to `eth_estimateGas`.
+const CALL_FIELDS: [&str; 7] =
+    ["from", "to", "value", "data", "input", "accessList", "authorizationList"];
+
+/// Phrases nodes answer `eth_sendRawTransaction` with when they already have the
+/// transaction, from geth, Erigon, Nethermind, Besu and OpenEthereum.
+const ALREADY_KNOWN: [&str; 4] =
+    ["already known", "alreadyknown", "known transaction", "already imported"];
+
+/// Why a request failed, and so whether sending it again might succeed.
+enum Failure {
+    /// The endpoint couldn't be reached, timed out or was overloaded.
+    Transient(String),
+    /// The endpoint answered, and would answer the same again.
+    Fatal(String),
+}
+
+impl From<Failure> for String {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Transient(message) | Failure::Fatal(message) => message,
+        }
+    }
+}
+
+/// The parts of an `http://` URL a request needs.
+struct Endpoint {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self, String> {
+        let rest = match url.split_once("://") {
+            Some(("http", rest)) => rest,
+            Some(("https", _)) => {
+                return Err(
+                    "https:// endpoints are not supported; use a local node or a TLS proxy"
+                        .to_string(),
+                )
+            }
+            _ => return Err(format!("Invalid RPC URL {:?}, expected http://", url)),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        if authority.contains('@') {
+            return Err("RPC URLs with credentials are not supported".to_string());
+        }
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                let port = port.parse().map_err(|_| format!("Invalid RPC port {:?}", port))?;
+                (host, port)
+            }
+            _ => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(format!("Invalid RPC URL {:?}, missing host", url));
+        }
+        Ok(Endpoint { host: host.to_string(), port, path: path.to_string() })
+    }
+
+    /// Sends a JSON body with a POST request and returns the response body.
+    fn post(&self, body: &str, timeout: Duration) -> Result<Vec<u8>, Failure> {
+        let host = self.host.trim_start_matches('[').trim_end_matches(']');
+        let addresses = (host, self.port)
+            .to_socket_addrs()
+            .map_err(|e| Failure::Transient(format!("Could not resolve {}: {}", self.host, e)))?;
+        let mut last_error = format!("Could not resolve {}", self.host);
+        let mut stream = None;
+        for address in addresses {
+            match TcpStream::connect_timeout(&address, timeout) {
+                Ok(connected) => {
+                    stream = Some(connected);
+                    break;
+                }
+                Err(e) => last_error = format!("Could not connect to {}: {}", address, e),
+            }
+        }
+        let mut stream = stream.ok_or(Failure::Transient(last_error))?;
+        let io_error = |e: std::io::Error| Failure::Transient(format!("RPC request failed: {}", e));
+        stream.set_read_timeout(Some(timeout)).map_err(io_error)?;
+        stream.set_write_timeout(Some(timeout)).map_err(io_error)?;
+
+        let host_header = match self.port {
+            80 => self.host.clone(),
+            port => format!("{}:{}", self.host, port),
+        };
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            host_header,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).map_err(io_error)?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).map_err(io_error)?;
+        response_body(&response)
+    }
+}
+
+/// Splits an HTTP/1.1 response into its body, which must come with status 200.
+fn response_body(response: &[u8]) -> Result<Vec<u8>, Failure> {
+    let malformed = || Failure::Fatal("Malformed HTTP response from the RPC endpoint".to_string());
+    let split = response.windows(4).position(|window| window == b"\r\n\r\n").ok_or_else(malformed)?;
+    let head = std::str::from_utf8(&response[..split]).map_err(|_| malformed())?;
+    let body = &response[split + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines.next().and_then(|line| line.split(' ').nth(1)).ok_or_else(malformed)?;
+    if status != "200" {
+        let message = format!("RPC endpoint returned HTTP {}", status);
+        return Err(match status {
+            "429" | "502" | "503" | "504" => Failure::Transient(message),
+            _ => Failure::Fatal(message),
+        });
+    }
+    let mut chunked = false;
+    let mut length = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            length = Some(value.parse::<usize>().map_err(|_| malformed())?);
+        }
+    }
+
+    if chunked {
+        return dechunk(body).ok_or_else(malformed);
+    }
+    match length {
+        Some(length) if length > body.len() => Err(malformed()),
+        Some(length) => Ok(body[..length].to_vec()),
+        None => Ok(body.to_vec()),
+    }
+}
+
+/// Decodes a body sent with chunked transfer encoding.
+fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+/// Sends calls as one JSON-RPC batch and returns their results in order.
+fn batch(
+    endpoint: &Endpoint,
+    calls: &[(&str, Value)],
+    timeout: Duration,
+) -> Result<Vec<Value>, String> {
+    let requests: Vec<Value> = calls
+        .iter()
+        .enumerate()
+        .map(|(id, (method, params))| {
+            json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+        })
+        .collect();
+    let body = endpoint.post(&Value::Array(requests).to_string(), timeout)?;
+    let responses: Value = serde_json::from_slice(&body)
+        .map_err(|e| format!("Invalid JSON-RPC response: {}", e))?;
+    // A node that rejects the whole batch answers with a single error object
+    let responses = match responses {
+        Value::Array(responses) => responses,
+        response => vec![response],
+    };
+
+    let mut results = vec![None; calls.len()];
+    for mut response in responses {
+        if let Some(error) = response.get("error") {
+            let method = response["id"]
+                .as_u64()
+                .and_then(|id| calls.get(id as usize))
+                .map_or("batch", |(method, _)| method);
+            let message =
+                error["message"].as_str().map_or_else(|| error.to_string(), str::to_string);
+            return Err(format!("{} failed: {}", method, message));
+        }
+        let slot = response["id"].as_u64().and_then(|id| results.get_mut(id as usize));
+        match slot {
+            Some(slot) => *slot = Some(response["result"].take()),
+            None => return Err("JSON-RPC response with an unknown id".to_string()),
+        }
+    }
+    results
+        .into_iter()
+        .zip(calls)
+        .map(|(result, (method, _))| result.ok_or_else(|| format!("No response to {}", method)))
+        .collect()
+}
+
+fn quantity(value: &Value, what: &str) -> Result<U256, String> {
+    value
+        .as_str()
+        .and_then(|hex| hex.strip_prefix("0x"))
+        .and_then(|hex| U256::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| format!("Invalid {} from the RPC endpoint: {}", what, value))
+}
+
+fn missing(tx: &Map<String, Value>, field: &str) -> bool {
+    tx.get(field).is_none_or(Value::is_null)
+}
+
+/// Asks the node for every field the transaction is missing, in a single batch.
+fn fill(
+    tx: &Map<String, Value>,
+    endpoint: &Endpoint,
+    timeout: Duration,
+) -> Result<Vec<(&'static str, U256)>, String> {
+    let from = tx
+        .get("from")
+        .and_then(Value::as_str)
+        .ok_or("Transaction needs a 'from' address to be filled")?;
+    let legacy = !missing(tx, "gasPrice")
+        || matches!(tx.get("type").and_then(Value::as_str), Some("0x0" | "0x00" | "0x1" | "0x01"))
+        || matches!(tx.get("type").and_then(Value::as_u64), Some(0 | 1));
+
+    let mut calls = Vec::new();
+    if missing(tx, "nonce") {
+        calls.push(("eth_getTransactionCount", json!([from, "pending"])));
+    }
+    if missing(tx, "gas") {
+        let call: Map<String, Value> = CALL_FIELDS
+            .iter()
+            .filter_map(|&field| tx.get(field).map(|value| (field.to_string(), value.clone())))
+            .filter(|(_, value)| !value.is_null())
+            .collect();
+        calls.push(("eth_estimateGas", json!([call])));
+    }
+    if missing(tx, "chainId") {
+        calls.push(("eth_chainId", json!([])));
+    }
+    if legacy {
+        if missing(tx, "gasPrice") {
+            calls.push(("eth_gasPrice", json!([])));
+        }
+    } else {
+        if missing(tx, "maxPriorityFeePerGas") {
+            calls.push(("eth_maxPriorityFeePerGas", json!([])));
+        }
+        if missing(tx, "maxFeePerGas") {
+            calls.push(("eth_getBlockByNumber", json!(["latest", false])));
+        }
+    }
+    if calls.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let results = batch(endpoint, &calls, timeout)?;
+    let mut filled = Vec::new();
+    let mut base_fee = None;
+    for ((method, _), result) in calls.iter().zip(&results) {
+        match *method {
+            "eth_getTransactionCount" => filled.push(("nonce", quantity(result, "nonce")?)),
+            "eth_estimateGas" => filled.push(("gas", quantity(result, "gas estimate")?)),
+            "eth_chainId" => filled.push(("chainId", quantity(result, "chain ID")?)),
+            "eth_gasPrice" => filled.push(("gasPrice", quantity(result, "gas price")?)),
+            "eth_maxPriorityFeePerGas" => {
+                filled.push(("maxPriorityFeePerGas", quantity(result, "priority fee")?))
+            }
+            _ => {
+                let fee = result.get("baseFeePerGas").ok_or(
+                    "Latest block has no base fee; pass gasPrice for a legacy transaction",
+                )?;
+                base_fee = Some(quantity(fee, "base fee")?);
+            }
+        }
+    }
+
+    // Like ethers and viem, leave room for the base fee to double before the next block
+    if let Some(base_fee) = base_fee {
+        let priority_fee = match filled.iter().find(|(field, _)| *field == "maxPriorityFeePerGas") {
+            Some((_, fee)) => *fee,
+            None => quantity(&tx["maxPriorityFeePerGas"], "maxPriorityFeePerGas")
+                .map_err(|_| "maxPriorityFeePerGas must be a hex quantity".to_string())?,
+        };
+        let max_fee = base_fee
+            .checked_mul(U256::from(2))
+            .and_then(|fee| fee.checked_add(priority_fee))
+            .ok_or("Base fee overflows")?;
+        filled.push(("maxFeePerGas", max_fee));
+    }
+    Ok(filled)
+}
+
+fn invalid_seconds(what: &str, seconds: f64) -> PyErr {
+    PyErr::new::<errors::FerriteError, _>(format!("Invalid {} {}", what, seconds))
+}
+
+fn timeout(seconds: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(seconds)
+        .ok()
+        .filter(|timeout| !timeout.is_zero())
+        .ok_or_else(|| invalid_seconds("timeout", seconds))
+}
+
+/// Fills in a transaction's missing nonce, gas limit, chain ID and fees from a node.
+///
+/// # Arguments
+/// * `payload` - The transaction as JSON, with integers as hex quantities. It must have
+///   a `from` address.
+/// * `rpc_url` - An `http://` JSON-RPC endpoint.
+/// * `timeout` - Seconds to wait to connect and for each read or write.
+///
+/// # Returns
+/// A dict of the fields that were missing, as hex quantities: `nonce` from the pending
+/// transaction count, `gas` from `eth_estimateGas`, `chainId`, and either `gasPrice` for
+/// legacy transactions or `maxPriorityFeePerGas` and `maxFeePerGas`, the latter twice
+/// the latest base fee plus the priority fee.
+#[pyfunction]
+#[pyo3(signature = (payload, rpc_url, timeout = 10.0))]
+pub fn fill_transaction(
+    py: Python,
+    payload: &str,
+    rpc_url: &str,
+    timeout: f64,
+) -> PyResult<PyObject> {
+    let error = |message: String| PyErr::new::<errors::FerriteError, _>(message);
+    let tx: Map<String, Value> = serde_json::from_str(payload)
+        .map_err(|e| error(format!("Invalid transaction JSON: {}", e)))?;
+    let timeout = self::timeout(timeout)?;
+    let endpoint = Endpoint::parse(rpc_url).map_err(error)?;
+
+    let filled = py.allow_threads(|| fill(&tx, &endpoint, timeout)).map_err(error)?;
+    let dict = PyDict::new(py);
+    for (field, value) in filled {
+        dict.set_item(field, format!("{:#x}", value))?;
+    }
+    Ok(dict.into_any().unbind())
+}
+
+/// Broadcasts a raw transaction once, returning its hash.
+fn send(
+    endpoint: &Endpoint,
+    raw_transaction: &[u8],
+    timeout: Duration,
+) -> Result<[u8; 32], Failure> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": "eth_sendRawTransaction",
+        "params": [format!("0x{}", hex::encode(raw_transaction))],
+    });
+    let body = endpoint.post(&request.to_string(), timeout)?;
+    let response: Value = serde_json::from_slice(&body)
+        .map_err(|e| Failure::Fatal(format!("Invalid JSON-RPC response: {}", e)))?;
+    let hash = keccak256(raw_transaction);
+
+    if let Some(error) = response.get("error") {
+        let message =
+            error["message"].as_str().map_or_else(|| error.to_string(), str::to_string);
+        let lowercase = message.to_lowercase();
+        if ALREADY_KNOWN.iter().any(|known| lowercase.contains(known)) {
+            return Ok(hash);
+        }
+        return Err(Failure::Fatal(format!("eth_sendRawTransaction failed: {}", message)));
+    }
+    let returned = response["result"].as_str().and_then(|result| result.strip_prefix("0x"));
+    if returned.is_none_or(|returned| !returned.eq_ignore_ascii_case(&hex::encode(hash))) {
+        return Err(Failure::Fatal(format!(
+            "eth_sendRawTransaction returned {} rather than the transaction hash",
+            response["result"]
+        )));
+    }
+    Ok(hash)
+}
+
+/// Broadcasts a signed transaction with `eth_sendRawTransaction`, retrying on failure.
+///
+/// # Arguments
+/// * `raw_transaction` - The signed transaction, as it is sent to the network.
+/// * `rpc_url` - An `http://` JSON-RPC endpoint.
+/// * `timeout` - Seconds to wait to connect and for each read or write.
+/// * `retries` - How many times to resend when the endpoint can't be reached, times out
+///   or answers HTTP 429, 502, 503 or 504. Errors from the node itself aren't retried.
+/// * `backoff` - Seconds to wait before the first retry, doubling for each one after.
+///
+/// # Returns
+/// The 32-byte transaction hash, once a node has accepted the transaction or answered
+/// that it already has it.
+#[pyfunction]
+#[pyo3(signature = (raw_transaction, rpc_url, timeout = 10.0, retries = 3, backoff = 0.1))]
+pub fn send_raw_transaction(
+    py: Python,
+    raw_transaction: &[u8],
+    rpc_url: &str,
+    timeout: f64,
+    retries: u32,
+    backoff: f64,
+) -> PyResult<PyObject> {
+    let error = |message: String| PyErr::new::<errors::FerriteError, _>(message);
+    let timeout = self::timeout(timeout)?;
+    let backoff =
+        Duration::try_from_secs_f64(backoff).map_err(|_| invalid_seconds("backoff", backoff))?;
+    let endpoint = Endpoint::parse(rpc_url).map_err(error)?;
+
+    let hash = py.allow_threads(|| {
+        let mut attempt = 0;
+        loop {
+            match send(&endpoint, raw_transaction, timeout) {
+                Err(Failure::Transient(_)) if attempt < retries => {
+                    thread::sleep(backoff.saturating_mul(1 << attempt.min(16)));
+                    attempt += 1;
+                }
+                result => return result.map_err(String::from),
+            }
+        }
+    });
+    Ok(PyBytes::new(py, &hash.map_err(error)?).into_any().unbind())
+}
@@ -0,0 +1,224 @@
+"""
+Safe (formerly Gnosis Safe) multisig transactions.
+
+A Safe executes a transaction once enough owners have signed its ``SafeTx``, an
+EIP-712 structure whose domain is the Safe itself. The helpers here build that
+structure, compute its ``safeTxHash``, sign it with an owner's key, and pack the
+owners' signatures into the ``signatures`` bytes ``execTransaction`` takes.
+
+Off-chain messages work the same way: owners sign a ``SafeMessage`` in the Safe's
+domain, and the packed signatures satisfy the Safe's EIP-1271 ``isValidSignature``.
+"""
+
+import json
+from typing import Any, Dict, Iterable, NamedTuple, Union
+
+from eth_account.messages import _hash_eip191_message, encode_defunct
+from hexbytes import HexBytes
+from _ferrite import hash_typed_data as rust_hash_typed_data  # type: ignore
+from .account import _json_value, _sign_typed_data_with
+
+_ZERO_ADDRESS = "0x" + "00" * 20
+
+_SAFE_TX_TYPE = [
+    {"name": "to", "type": "address"},
+    {"name": "value", "type": "uint256"},
+    {"name": "data", "type": "bytes"},
+    {"name": "operation", "type": "uint8"},
+    {"name": "safeTxGas", "type": "uint256"},
+    {"name": "baseGas", "type": "uint256"},
+    {"name": "gasPrice", "type": "uint256"},
+    {"name": "gasToken", "type": "address"},
+    {"name": "refundReceiver", "type": "address"},
+    {"name": "nonce", "type": "uint256"},
+]
+
+
+_SAFE_MESSAGE_TYPE = [{"name": "message", "type": "bytes"}]
+
+
+class SafeSignature(NamedTuple):
+    """An owner's signature of a ``safeTxHash`` or ``SafeMessage`` hash."""
+
+    owner: str
+    signature: HexBytes
+
+
+def _version(version: str) -> tuple:
+    return tuple(int(part) for part in version.split("+")[0].split(".")[:3])
+
+
+def _domain(safe: str, chain_id: int, version: str) -> Dict[str, Any]:
+    """Returns the Safe's EIP-712 domain, which has a chain id from v1.3.0 on."""
+    if _version(version) < (1, 0, 0):
+        raise ValueError(f"Safe {version} is not supported; use Safe 1.0.0 or later")
+    if _version(version) < (1, 3, 0):
+        fields = [{"name": "verifyingContract", "type": "address"}]
+        return {"types": fields, "domain": {"verifyingContract": safe}}
+
+    fields = [
+        {"name": "chainId", "type": "uint256"},
+        {"name": "verifyingContract", "type": "address"},
+    ]
+    domain = {"chainId": str(chain_id), "verifyingContract": safe}
+    return {"types": fields, "domain": domain}
+
+
+def safe_tx(
+    safe: str,
+    chain_id: int,
+    to: str,
+    value: int = 0,
+    data: Union[bytes, str] = b"",
+    operation: int = 0,
+    *,
+    nonce: int,
+    safe_tx_gas: int = 0,
+    base_gas: int = 0,
+    gas_price: int = 0,
+    gas_token: str = _ZERO_ADDRESS,
+    refund_receiver: str = _ZERO_ADDRESS,
+    version: str = "1.3.0",
+) -> Dict[str, Any]:
+    """
+    Builds the ``SafeTx`` EIP-712 typed data for a Safe transaction.
+
+    Args:
+        safe: Address of the Safe.
+        chain_id: Chain the Safe is deployed on.
+        to: Destination of the transaction.
+        value: Wei sent with it.
+        data: Calldata, as bytes or hex.
+        operation: 0 for a call, 1 for a delegatecall.
+        nonce: The Safe's nonce for this transaction.
+        safe_tx_gas: Gas for the inner call, or 0 for all available.
+        base_gas: Gas paid for independently of the inner call, for refunds.
+        gas_price: Gas price for the refund, or 0 for no refund.
+        gas_token: Token the refund is paid in, or the zero address for ether.
+        refund_receiver: Receiver of the refund, or the zero address for
+            ``tx.origin``.
+        version: Version of the Safe contract, which decides the domain.
+
+    Returns:
+        The typed data, for ``safe_tx_hash``, ``sign_safe_tx`` or any EIP-712 signer.
+    """
+    if operation not in (0, 1):
+        raise ValueError(
+            f"operation must be 0 (call) or 1 (delegatecall), got {operation}"
+        )
+    domain = _domain(safe, chain_id, version)
+    return {
+        "types": {"EIP712Domain": domain["types"], "SafeTx": _SAFE_TX_TYPE},
+        "primaryType": "SafeTx",
+        "domain": domain["domain"],
+        # uint256 values as decimal strings, since wei amounts overflow JSON numbers
+        "message": {
+            "to": to,
+            "value": str(value),
+            "data": _json_value("data", HexBytes(data)),
+            "operation": operation,
+            "safeTxGas": str(safe_tx_gas),
+            "baseGas": str(base_gas),
+            "gasPrice": str(gas_price),
+            "gasToken": gas_token,
+            "refundReceiver": refund_receiver,
+            "nonce": str(nonce),
+        },
+    }
+
+
+def safe_tx_hash(safe_tx: Dict[str, Any]) -> HexBytes:
+    """Returns the ``safeTxHash`` the Safe checks owner signatures against."""
+    return HexBytes(rust_hash_typed_data(json.dumps(safe_tx)))
+
+
+def _sign(typed_data: Dict[str, Any], owner: Any) -> SafeSignature:
+    """Signs Safe typed data with an owner's private key or account."""
+    return SafeSignature(*_sign_typed_data_with(typed_data, owner))
+
+
+def sign_safe_tx(safe_tx: Dict[str, Any], owner: Any) -> SafeSignature:
+    """
+    Signs a ``SafeTx`` as one of the Safe's owners.
+
+    Args:
+        safe_tx: The typed data ``safe_tx`` built.
+        owner: The owner's private key, as bytes or hex, or an account with
+            ``address`` and ``sign_typed_data``, such as a ``RemoteAccount``.
+    """
+    return _sign(safe_tx, owner)
+
+
+def safe_message(
+    safe: str,
+    chain_id: int,
+    message: Union[str, bytes, Dict[str, Any]],
+    *,
+    version: str = "1.3.0",
+) -> Dict[str, Any]:
+    """
+    Builds the ``SafeMessage`` EIP-712 typed data for an off-chain Safe message.
+
+    Args:
+        safe: Address of the Safe.
+        chain_id: Chain the Safe is deployed on.
+        message: A text message, hashed per EIP-191; EIP-712 typed data, hashed per
+            EIP-712; or the exact bytes the verifier passes to ``isValidSignature``,
+            such as a 32-byte hash.
+        version: Version of the Safe contract, which decides the domain.
+
+    Returns:
+        The typed data, for ``safe_message_hash`` or ``sign_safe_message``.
+    """
+    if isinstance(message, str):
+        data = _hash_eip191_message(encode_defunct(text=message))
+    elif isinstance(message, dict):
+        data = rust_hash_typed_data(json.dumps(message))
+    else:
+        data = bytes(message)
+    domain = _domain(safe, chain_id, version)
+    return {
+        "types": {"EIP712Domain": domain["types"], "SafeMessage": _SAFE_MESSAGE_TYPE},
+        "primaryType": "SafeMessage",
+        "domain": domain["domain"],
+        "message": {"message": "0x" + bytes(data).hex()},
+    }
+
+
+def safe_message_hash(safe_message: Dict[str, Any]) -> HexBytes:
+    """Returns the hash of a ``SafeMessage``, as ``getMessageHash`` computes it."""
+    return HexBytes(rust_hash_typed_data(json.dumps(safe_message)))
+
+
+def sign_safe_message(safe_message: Dict[str, Any], owner: Any) -> SafeSignature:
+    """
+    Signs a ``SafeMessage`` as one of the Safe's owners.
+
+    Once enough owners have signed, ``pack_safe_signatures`` gives the signature the
+    Safe's ``isValidSignature`` accepts for the message.
+
+    Args:
+        safe_message: The typed data ``safe_message`` built.
+        owner: The owner's private key or account, as for ``sign_safe_tx``.
+    """
+    return _sign(safe_message, owner)
+
+
+def pack_safe_signatures(signatures: Iterable[SafeSignature]) -> HexBytes:
+    """
+    Packs owner signatures into the ``signatures`` argument of ``execTransaction``, or
+    the signature ``isValidSignature`` takes.
+
+    The Safe requires them ordered by owner address, ascending, with no owner twice.
+    """
+    signatures = sorted(signatures, key=lambda signature: int(signature.owner, 16))
+    for previous, signature in zip(signatures, signatures[1:]):
+        if int(previous.owner, 16) == int(signature.owner, 16):
+            raise ValueError(f"{signature.owner} signed more than once")
+    for signature in signatures:
+        if len(signature.signature) != 65:
+            raise ValueError(
+                f"Signature by {signature.owner} is {len(signature.signature)} bytes, "
+                "expected 65"
+            )
+    return HexBytes(b"".join(signature.signature for signature in signatures))
@@ -0,0 +1,216 @@
+"""
+Seaport marketplace orders.
+
+A Seaport order is an ``OrderComponents`` struct, whose offer and consideration are
+arrays of nested ``OfferItem`` and ``ConsiderationItem`` structs, signed in the domain
+of the Seaport contract. The helpers here build it with the offerer's current counter,
+compute its order hash and sign it for fulfillment or an off-chain order book.
+"""
+
+import json
+import secrets
+from typing import Any, Dict, Iterable, NamedTuple, Optional, Union
+
+from hexbytes import HexBytes
+from _ferrite import hash_typed_data as rust_hash_typed_data  # type: ignore
+from .account import _sign_typed_data_with
+
+# Seaport is deployed at the same address on every chain, one per version
+SEAPORT_ADDRESSES = {
+    "1.5": "0x00000000000000ADc04C56Bf30aC9d3c0aAF14dC",
+    "1.6": "0x0000000000000068F116a894984e2DB1123eB395",
+}
+
+# Item types, in the order of Seaport's ItemType enum
+NATIVE, ERC20, ERC721, ERC1155, ERC721_WITH_CRITERIA, ERC1155_WITH_CRITERIA = range(6)
+
+_ZERO_ADDRESS = "0x" + "00" * 20
+_ZERO_BYTES32 = b"\x00" * 32
+
+_OFFER_ITEM_TYPE = [
+    {"name": "itemType", "type": "uint8"},
+    {"name": "token", "type": "address"},
+    {"name": "identifierOrCriteria", "type": "uint256"},
+    {"name": "startAmount", "type": "uint256"},
+    {"name": "endAmount", "type": "uint256"},
+]
+
+_CONSIDERATION_ITEM_TYPE = _OFFER_ITEM_TYPE + [{"name": "recipient", "type": "address"}]
+
+_ORDER_COMPONENTS_TYPE = [
+    {"name": "offerer", "type": "address"},
+    {"name": "zone", "type": "address"},
+    {"name": "offer", "type": "OfferItem[]"},
+    {"name": "consideration", "type": "ConsiderationItem[]"},
+    {"name": "orderType", "type": "uint8"},
+    {"name": "startTime", "type": "uint256"},
+    {"name": "endTime", "type": "uint256"},
+    {"name": "zoneHash", "type": "bytes32"},
+    {"name": "salt", "type": "uint256"},
+    {"name": "conduitKey", "type": "bytes32"},
+    {"name": "counter", "type": "uint256"},
+]
+
+
+class OfferItem(NamedTuple):
+    """An item the offerer gives up. Differing start and end amounts ramp linearly."""
+
+    item_type: int
+    token: str
+    identifier_or_criteria: int
+    start_amount: int
+    end_amount: int
+
+
+class ConsiderationItem(NamedTuple):
+    """An item the offerer or another party must receive for the order to fill."""
+
+    item_type: int
+    token: str
+    identifier_or_criteria: int
+    start_amount: int
+    end_amount: int
+    recipient: str
+
+
+class SeaportSignature(NamedTuple):
+    """An offerer's signature of an order, and the order's hash."""
+
+    offerer: str
+    signature: HexBytes
+    order_hash: HexBytes
+
+
+def _item(item: Union[OfferItem, ConsiderationItem]) -> Dict[str, Any]:
+    if not NATIVE <= item.item_type <= ERC1155_WITH_CRITERIA:
+        raise ValueError(f"Unknown Seaport item type {item.item_type}")
+    fields = {
+        "itemType": item.item_type,
+        "token": item.token,
+        "identifierOrCriteria": str(item.identifier_or_criteria),
+        "startAmount": str(item.start_amount),
+        "endAmount": str(item.end_amount),
+    }
+    if isinstance(item, ConsiderationItem):
+        fields["recipient"] = item.recipient
+    return fields
+
+
+def _bytes32(name: str, value: Union[bytes, str]) -> str:
+    value = HexBytes(value)
+    if len(value) != 32:
+        raise ValueError(f"{name} must be 32 bytes, got {len(value)}")
+    return "0x" + bytes(value).hex()
+
+
+def seaport_order(
+    chain_id: int,
+    offerer: str,
+    offer: Iterable[OfferItem],
+    consideration: Iterable[ConsiderationItem],
+    *,
+    start_time: int,
+    end_time: int,
+    counter: int,
+    order_type: int = 0,
+    zone: str = _ZERO_ADDRESS,
+    zone_hash: Union[bytes, str] = _ZERO_BYTES32,
+    conduit_key: Union[bytes, str] = _ZERO_BYTES32,
+    salt: Optional[int] = None,
+    version: str = "1.6",
+    seaport: Optional[str] = None,
+) -> Dict[str, Any]:
+    """
+    Builds the Seaport ``OrderComponents`` EIP-712 typed data.
+
+    Args:
+        chain_id: Chain the order is for.
+        offerer: Address of the offerer, who must sign the order.
+        offer: Items the offerer gives up.
+        consideration: Items that must be received, each by its ``recipient``.
+        start_time: Unix time the order becomes valid.
+        end_time: Unix time the order expires.
+        counter: The offerer's current ``getCounter(offerer)`` on Seaport.
+        order_type: 0 for full open, 1 for partial open, 2 and 3 for the restricted
+            variants that need the ``zone``'s approval.
+        zone: Zone of a restricted order.
+        zone_hash: 32 bytes passed to the zone, as bytes or hex.
+        conduit_key: Key of the conduit the offer's approvals are on, or zero for
+            approvals on Seaport itself.
+        salt: Salt making the order unique; random by default.
+        version: Seaport version, which decides the domain and default address.
+        seaport: Address of the Seaport contract, for other deployments.
+
+    Returns:
+        The typed data, for ``sign_seaport_order`` or any EIP-712 signer.
+    """
+    if version not in SEAPORT_ADDRESSES and seaport is None:
+        raise ValueError(f"Unknown Seaport version {version}; pass its address")
+    if salt is None:
+        salt = int.from_bytes(secrets.token_bytes(32), "big")
+
+    return {
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"},
+            ],
+            "OrderComponents": _ORDER_COMPONENTS_TYPE,
+            "OfferItem": _OFFER_ITEM_TYPE,
+            "ConsiderationItem": _CONSIDERATION_ITEM_TYPE,
+        },
+        "primaryType": "OrderComponents",
+        "domain": {
+            "name": "Seaport",
+            "version": version,
+            "chainId": str(chain_id),
+            "verifyingContract": seaport or SEAPORT_ADDRESSES[version],
+        },
+        "message": {
+            "offerer": offerer,
+            "zone": zone,
+            "offer": [_item(OfferItem(*item)) for item in offer],
+            "consideration": [
+                _item(ConsiderationItem(*item)) for item in consideration
+            ],
+            "orderType": order_type,
+            "startTime": str(start_time),
+            "endTime": str(end_time),
+            "zoneHash": _bytes32("zoneHash", zone_hash),
+            "salt": str(salt),
+            "conduitKey": _bytes32("conduitKey", conduit_key),
+            "counter": str(counter),
+        },
+    }
+
+
+def seaport_order_hash(order: Dict[str, Any]) -> HexBytes:
+    """
+    Returns an order's hash as Seaport's ``getOrderHash`` computes it, the struct hash
+    of its components that fulfillments and order books identify it by.
+
+    This is not the digest the offerer signs, which also covers the domain.
+    """
+    return HexBytes(rust_hash_typed_data(json.dumps(order), struct_hash=True))
+
+
+def sign_seaport_order(order: Dict[str, Any], offerer: Any) -> SeaportSignature:
+    """
+    Signs an order as its offerer.
+
+    Args:
+        order: The typed data ``seaport_order`` built.
+        offerer: The offerer's private key, as bytes or hex, or an account with
+            ``address`` and ``sign_typed_data``, such as a ``RemoteAccount``.
+
+    Raises:
+        ValueError: If the signer is not the order's offerer, since Seaport would
+            reject the signature.
+    """
+    address, signature = _sign_typed_data_with(order, offerer)
+    named = order["message"]["offerer"]
+    if int(named, 16) != int(address, 16):
+        raise ValueError(f"Order is from offerer {named}, but {address} signed it")
+    return SeaportSignature(address, signature, seaport_order_hash(order))
@@ -0,0 +1,109 @@
+/*!
+Locked memory for long-lived secrets.
+
+[`LockedBox`] places its value on pages of its own, locked into RAM so they are never
+written to swap and, on Linux, excluded from core dumps. Locking is best effort: under
+a low `RLIMIT_MEMLOCK` the value still lives on its own pages and is still wiped on
+drop, it just isn't pinned.
+*/
+
+use std::alloc::{self, Layout};
+use std::ptr::NonNull;
+
+use zeroize::Zeroize;
+
+/// Owned value on dedicated, locked, non-dumpable pages, wiped when dropped.
+pub struct LockedBox<T> {
+    ptr: NonNull<T>,
+    layout: Layout,
+    locked: bool,
+}
+
+// SAFETY: `LockedBox` owns its value exclusively, like `Box`.
+unsafe impl<T: Send> Send for LockedBox<T> {}
+unsafe impl<T: Sync> Sync for LockedBox<T> {}
+
+fn page_size() -> usize {
+    #[cfg(unix)]
+    {
+        // SAFETY: sysconf has no preconditions
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if size > 0 {
+            return size as usize;
+        }
+    }
+    4096
+}
+
+impl<T> LockedBox<T> {
+    /// Moves `value` onto freshly allocated pages and locks them.
+    pub fn new(value: T) -> Self {
+        // Whole pages, so that locking and excluding them from dumps affects nothing else
+        let page = page_size();
+        let size = std::mem::size_of::<T>().max(1).next_multiple_of(page);
+        let layout = Layout::from_size_align(size, page.max(std::mem::align_of::<T>()))
+            .expect("page-aligned layout");
+
+        // SAFETY: `layout` has a non-zero size
+        let ptr = unsafe { alloc::alloc_zeroed(layout) } as *mut T;
+        let ptr = NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        // SAFETY: `ptr` is valid for writes of `T` and suitably aligned
+        unsafe { ptr.as_ptr().write(value) };
+
+        let locked = lock(ptr.as_ptr() as *mut u8, size);
+        Self { ptr, layout, locked }
+    }
+
+    /// Returns whether the pages are locked into RAM.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
+
+#[cfg(unix)]
+fn lock(ptr: *mut u8, len: usize) -> bool {
+    // SAFETY: `ptr..ptr + len` is a live allocation of whole pages owned by the caller
+    unsafe {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        libc::madvise(ptr as *mut libc::c_void, len, libc::MADV_DONTDUMP);
+        libc::mlock(ptr as *const libc::c_void, len) == 0
+    }
+}
+
+#[cfg(not(unix))]
+fn lock(_ptr: *mut u8, _len: usize) -> bool {
+    false
+}
+
+impl<T> std::ops::Deref for LockedBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: `ptr` holds an initialized `T` for the lifetime of `self`
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> std::ops::DerefMut for LockedBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: `ptr` holds an initialized `T` and `self` is borrowed mutably
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for LockedBox<T> {
+    fn drop(&mut self) {
+        let bytes = self.ptr.as_ptr() as *mut u8;
+        // SAFETY: the value is initialized and dropped exactly once, after which the
+        // pages are plain bytes owned by `self` until deallocated with their layout
+        unsafe {
+            std::ptr::drop_in_place(self.ptr.as_ptr());
+            std::slice::from_raw_parts_mut(bytes, self.layout.size()).zeroize();
+            #[cfg(unix)]
+            if self.locked {
+                libc::munlock(bytes as *const libc::c_void, self.layout.size());
+            }
+            alloc::dealloc(bytes, self.layout);
+        }
+    }
+}
@@ -0,0 +1,634 @@
+"""
+A local JSON-RPC signer, so that tools outside Python can sign with ferrite's keys.
+
+The server answers ``eth_accounts``, ``eth_sign``, ``eth_signTransaction`` and
+``eth_signTypedData_v4`` over HTTP and WebSocket on the same port, the way Clef does.
+Foundry, ethers.js and other services can point their signer at it while the keys,
+and the signing policy, rate limits, approval hook and audit log guarding them, stay
+in this process. It signs only; nothing is broadcast.
+
+The HTTP server has no authentication of its own, so it listens on 127.0.0.1 by
+default. Loopback alone doesn't keep out the browser, so requests from web pages are
+refused too: WebSocket upgrades and POSTs whose ``Origin`` isn't explicitly allowed,
+POSTs that aren't ``application/json``, and requests whose ``Host`` isn't the server
+itself, which DNS rebinding would otherwise get past. Requests over 1 MiB are refused.
+
+Where no TCP port may be opened, ``IpcSignerServer`` serves the same API over a Unix
+domain socket instead, admitting only processes whose peer credentials show an allowed
+user or group.
+
+Run with ``python -m ferrite.server --keystore KEYFILE``, adding ``--ipc PATH`` to
+serve on a socket or ``--grpc HOST:PORT`` for the gRPC service of
+``ferrite.grpc_signer``, or start one from Python with
+``SignerServer(accounts).start()``. ``--metrics HOST:PORT`` also serves Prometheus
+metrics, which the HTTP server already answers at ``/metrics``. ``--config FILE``
+takes the keys, policy and server settings from a TOML file; see ``ferrite.config``.
+"""
+
+import argparse
+import base64
+import getpass
+import hashlib
+import json
+import logging
+import os
+import socket
+import socketserver
+import stat
+import struct
+import sys
+import threading
+import time
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from typing import Any, Collection, Dict, Iterable, List, Optional, Tuple
+
+from eth_account import Account
+from eth_account.messages import encode_defunct
+
+from . import prometheus
+from .account import _INTEGER_FIELDS
+
+# The GUID RFC 6455 hashes into the Sec-WebSocket-Accept header
+_WEBSOCKET_GUID = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11"
+
+log = logging.getLogger(__name__)
+
+DEFAULT_PORT = 8550
+
+# Largest request a client may send, over HTTP, WebSocket or IPC
+_MAX_REQUEST = 1 << 20
+
+# Host names that always reach the server from the machine it runs on
+_LOOPBACK_HOSTS = frozenset({"localhost", "127.0.0.1", "::1"})
+
+_PARSE_ERROR = json.dumps(
+    {"jsonrpc": "2.0", "id": None, "error": {"code": -32700, "message": "parse error"}}
+).encode()
+
+
+class _RpcError(Exception):
+    def __init__(self, code: int, message: str) -> None:
+        super().__init__(message)
+        self.code = code
+
+
+def _invalid_params(message: str) -> _RpcError:
+    return _RpcError(-32602, message)
+
+
+def _hex(data: bytes) -> str:
+    return "0x" + bytes(data).hex()
+
+
+def _account(signer: Any) -> Any:
+    """Returns ``signer`` as an account, parsing it if it's a private key."""
+    if hasattr(signer, "sign_transaction"):
+        return signer
+    return Account.from_key(signer)
+
+
+def _transaction_fields(tx: Dict[str, Any]) -> Dict[str, Any]:
+    """Converts a JSON-RPC transaction object to the fields eth-account signs."""
+    tx = dict(tx)
+    if "input" in tx:
+        tx.setdefault("data", tx.pop("input"))
+    for field in _INTEGER_FIELDS:
+        value = tx.get(field)
+        if isinstance(value, str):
+            try:
+                tx[field] = int(value, 16)
+            except ValueError:
+                raise ValueError(f"Invalid {field} {value!r}") from None
+    return tx
+
+
+class SignerServer:
+    """
+    A JSON-RPC server signing with a fixed set of accounts.
+
+    Args:
+        accounts: Private keys, as bytes or hex, or accounts with ``address``,
+            ``sign_message``, ``sign_transaction`` and ``sign_typed_data`` such as
+            eth-account's ``LocalAccount`` or a ``RemoteAccount``.
+        host: The interface to listen on.
+        port: The port to listen on, or 0 for any free port.
+        allowed_origins: Web page origins, such as ``"https://app.example"``, that
+            may call the server from a browser. Requests from any other page are
+            refused; requests without an ``Origin`` don't come from one.
+        allowed_hosts: Host names clients may reach the server by, besides the
+            loopback names and ``host`` itself.
+
+    ``GET /metrics`` on the same port returns ``prometheus_metrics()`` for Prometheus
+    to scrape.
+    """
+
+    # The ``server`` label of this server's requests in the metrics
+    _metrics_name = "http"
+
+    def __init__(
+        self,
+        accounts: Iterable[Any],
+        host: str = "127.0.0.1",
+        port: int = DEFAULT_PORT,
+        *,
+        allowed_origins: Collection[str] = (),
+        allowed_hosts: Collection[str] = (),
+    ) -> None:
+        self._allowed_origins = frozenset(allowed_origins)
+        self._allowed_hosts = _LOOPBACK_HOSTS | set(allowed_hosts)
+        if host not in ("", "0.0.0.0", "::"):
+            self._allowed_hosts |= {host}
+        self._listen(accounts, ThreadingHTTPServer((host, port), _handler(self)))
+
+    def _listen(self, accounts: Iterable[Any], server: socketserver.BaseServer) -> None:
+        self._accounts: Dict[str, Any] = {}
+        for signer in accounts:
+            account = _account(signer)
+            self._accounts[account.address.lower()] = account
+            prometheus.watch_backend(account)
+        self._addresses = [account.address for account in self._accounts.values()]
+        self._server = server
+        self._thread: Optional[threading.Thread] = None
+        self._serving = False
+
+    @property
+    def url(self) -> str:
+        """The server's ``http://`` URL; ``ws://`` at the same address also works."""
+        host, port = self._server.server_address[:2]
+        return f"http://{host}:{port}"
+
+    def start(self) -> "SignerServer":
+        """Starts serving in a background thread."""
+        self._serving = True
+        self._thread = threading.Thread(target=self._server.serve_forever, daemon=True)
+        self._thread.start()
+        return self
+
+    def serve_forever(self) -> None:
+        """Serves in the calling thread until ``shutdown`` is called."""
+        self._serving = True
+        self._server.serve_forever()
+
+    def shutdown(self) -> None:
+        """Stops serving and closes the socket."""
+        if self._serving:
+            self._server.shutdown()
+        self._server.server_close()
+        if self._thread is not None:
+            self._thread.join()
+
+    def __enter__(self) -> "SignerServer":
+        return self.start()
+
+    def __exit__(self, *exc_info: Any) -> None:
+        self.shutdown()
+
+    def _signer(self, address: Any) -> Any:
+        if not isinstance(address, str):
+            raise _invalid_params(f"Expected an address, got {address!r}")
+        try:
+            return self._accounts[address.lower()]
+        except KeyError:
+            raise _RpcError(-32000, f"unknown account {address}") from None
+
+    def _sign_transaction(self, tx: Any) -> str:
+        if not isinstance(tx, dict) or "from" not in tx:
+            raise _invalid_params("Expected a transaction object with a 'from' address")
+        tx = dict(tx)
+        signer = self._signer(tx.pop("from"))
+        try:
+            tx = _transaction_fields(tx)
+        except ValueError as e:
+            raise _invalid_params(str(e)) from None
+        signed = prometheus.signed_with(signer, lambda: signer.sign_transaction(tx))
+        return _hex(signed.raw_transaction)
+
+    def call(self, method: str, params: List[Any]) -> Any:
+        """Handles one JSON-RPC call, returning its result."""
+        if method == "eth_accounts":
+            return list(self._addresses)
+        if method == "eth_sign":
+            address, data = (params + [None, None])[:2]
+            if not isinstance(data, str) or not data.startswith("0x"):
+                raise _invalid_params("Expected the data to sign as hex")
+            signer = self._signer(address)
+            message = encode_defunct(hexstr=data)
+            signed = prometheus.signed_with(
+                signer, lambda: signer.sign_message(message)
+            )
+            return _hex(signed.signature)
+        if method == "eth_signTransaction":
+            return self._sign_transaction(params[0] if params else None)
+        if method in ("eth_signTypedData", "eth_signTypedData_v4"):
+            address, typed_data = (params + [None, None])[:2]
+            if isinstance(typed_data, str):
+                typed_data = json.loads(typed_data)
+            if not isinstance(typed_data, dict):
+                raise _invalid_params("Expected typed data as an object or JSON")
+            signer = self._signer(address)
+            signed = prometheus.signed_with(
+                signer, lambda: signer.sign_typed_data(full_message=typed_data)
+            )
+            return _hex(signed.signature)
+        raise _RpcError(-32601, f"the method {method} does not exist/is not available")
+
+    def _response(self, request: Any) -> Optional[Dict[str, Any]]:
+        if not isinstance(request, dict) or not isinstance(request.get("method"), str):
+            error = {"code": -32600, "message": "invalid request"}
+            return {"jsonrpc": "2.0", "id": None, "error": error}
+        params = request.get("params", [])
+        started = time.perf_counter()
+        try:
+            if not isinstance(params, list):
+                raise _invalid_params("Expected params as an array")
+            response = {"result": self.call(request["method"], params)}
+        except _RpcError as e:
+            response = {"error": {"code": e.code, "message": str(e)}}
+        except Exception as e:
+            # Signing errors such as policy violations go back to the caller
+            response = {"error": {"code": -32000, "message": str(e)}}
+        prometheus.observe_request(
+            self._metrics_name,
+            request["method"],
+            "error" not in response,
+            time.perf_counter() - started,
+        )
+        if "id" not in request:
+            return None
+        return {"jsonrpc": "2.0", "id": request["id"], **response}
+
+    def handle(self, body: bytes) -> Optional[bytes]:
+        """Handles a JSON-RPC request or batch, returning the response to send."""
+        try:
+            payload = json.loads(body)
+        except ValueError:
+            return _PARSE_ERROR
+        return self._reply(payload)
+
+    def _reply(self, payload: Any) -> Optional[bytes]:
+        if isinstance(payload, list) and payload:
+            responses = [self._response(request) for request in payload]
+            responses = [response for response in responses if response is not None]
+            return json.dumps(responses).encode() if responses else None
+        response = self._response(payload)
+        return None if response is None else json.dumps(response).encode()
+
+
+def _host_name(host: str) -> str:
+    """Returns the name in a ``Host`` header, without the port."""
+    if host.startswith("["):
+        return host[1:].partition("]")[0]
+    return host.rpartition(":")[0] if host.count(":") == 1 else host
+
+
+def _handler(server: SignerServer) -> type:
+    class Handler(BaseHTTPRequestHandler):
+        protocol_version = "HTTP/1.1"
+
+        def _refused(self) -> Optional[Tuple[int, str]]:
+            """Returns the status and reason to refuse a request from a browser with."""
+            host = _host_name(self.headers.get("Host") or "").lower()
+            if host not in server._allowed_hosts:
+                return 403, "Host not allowed"
+            origin = self.headers.get("Origin")
+            if origin is not None and origin not in server._allowed_origins:
+                return 403, "Origin not allowed"
+            return None
+
+        def do_POST(self) -> None:
+            refused = self._refused()
+            content_type = self.headers.get("Content-Type") or ""
+            if refused is None and content_type.split(";")[0].strip() != (
+                "application/json"
+            ):
+                refused = 415, "Requests must be application/json"
+            try:
+                length = int(self.headers.get("Content-Length") or 0)
+            except ValueError:
+                length = -1
+            if refused is None and not 0 <= length <= _MAX_REQUEST:
+                refused = 413, f"Requests are limited to {_MAX_REQUEST} bytes"
+            if refused is not None:
+                self.close_connection = True
+                self.send_error(*refused)
+                return
+            response = server.handle(self.rfile.read(length)) or b""
+            self.send_response(200 if response else 204)
+            self.send_header("Content-Type", "application/json")
+            self.send_header("Content-Length", str(len(response)))
+            self.end_headers()
+            self.wfile.write(response)
+
+        def do_GET(self) -> None:
+            if self.path.split("?")[0] == "/metrics":
+                prometheus.send_metrics(self)
+                return
+            key = self.headers.get("Sec-WebSocket-Key")
+            if (self.headers.get("Upgrade") or "").lower() != "websocket" or not key:
+                self.send_error(405, "JSON-RPC requests must be POSTed")
+                return
+            refused = self._refused()
+            if refused is not None:
+                self.send_error(*refused)
+                return
+            accept = hashlib.sha1(key.encode() + _WEBSOCKET_GUID).digest()
+            self.send_response(101)
+            self.send_header("Upgrade", "websocket")
+            self.send_header("Connection", "Upgrade")
+            self.send_header("Sec-WebSocket-Accept", base64.b64encode(accept).decode())
+            self.end_headers()
+            self.close_connection = True
+            self._websocket()
+
+        def _frame(self) -> Optional[tuple]:
+            header = self.rfile.read(2)
+            if len(header) < 2:
+                return None
+            fin, opcode = header[0] & 0x80, header[0] & 0x0F
+            length = header[1] & 0x7F
+            if not header[1] & 0x80:
+                # Clients must mask every frame
+                return None
+            if length == 126:
+                length = struct.unpack(">H", self.rfile.read(2))[0]
+            elif length == 127:
+                length = struct.unpack(">Q", self.rfile.read(8))[0]
+            if length > _MAX_REQUEST:
+                self._close_too_big()
+                return None
+            mask = self.rfile.read(4)
+            data = self.rfile.read(length)
+            if len(data) < length:
+                return None
+            return fin, opcode, bytes(b ^ mask[i % 4] for i, b in enumerate(data))
+
+        def _send_frame(self, opcode: int, data: bytes) -> None:
+            if len(data) < 126:
+                header = struct.pack(">BB", 0x80 | opcode, len(data))
+            elif len(data) < 1 << 16:
+                header = struct.pack(">BBH", 0x80 | opcode, 126, len(data))
+            else:
+                header = struct.pack(">BBQ", 0x80 | opcode, 127, len(data))
+            self.wfile.write(header + data)
+            self.wfile.flush()
+
+        def _close_too_big(self) -> None:
+            # 1009: the message is too big to process
+            self._send_frame(0x8, struct.pack(">H", 1009))
+
+        def _websocket(self) -> None:
+            message = b""
+            while True:
+                frame = self._frame()
+                if frame is None:
+                    return
+                fin, opcode, data = frame
+                if opcode == 0x8:
+                    self._send_frame(0x8, data[:2])
+                    return
+                if opcode == 0x9:
+                    self._send_frame(0xA, data)
+                    continue
+                if opcode not in (0x0, 0x1, 0x2):
+                    continue
+                message += data
+                if len(message) > _MAX_REQUEST:
+                    self._close_too_big()
+                    return
+                if fin:
+                    response = server.handle(message)
+                    message = b""
+                    if response is not None:
+                        self._send_frame(0x1, response)
+
+        def log_message(self, format: str, *args: Any) -> None:
+            pass
+
+    return Handler
+
+
+def _peer_credentials(connection: socket.socket) -> Tuple[int, int]:
+    """Returns the user and group IDs of the process at the other end of a socket."""
+    if hasattr(socket, "SO_PEERCRED"):
+        creds = connection.getsockopt(
+            socket.SOL_SOCKET, socket.SO_PEERCRED, struct.calcsize("3i")
+        )
+        _, uid, gid = struct.unpack("3i", creds)
+        return uid, gid
+    if sys.platform == "darwin":
+        # LOCAL_PEERCRED at SOL_LOCAL, returning a struct xucred
+        creds = connection.getsockopt(0, 1, struct.calcsize("IIh16I"))
+        _, uid, _, gid = struct.unpack("IIh16I", creds)[:4]
+        return uid, gid
+    raise OSError("Peer credentials of Unix sockets are unavailable on this platform")
+
+
+if hasattr(socketserver, "UnixStreamServer"):
+
+    class _UnixServer(socketserver.ThreadingMixIn, socketserver.UnixStreamServer):
+        daemon_threads = True
+
+
+class IpcSignerServer(SignerServer):
+    """
+    A JSON-RPC signer listening on a Unix domain socket, like geth's IPC endpoint.
+
+    Requests and responses are JSON values streamed over the connection, with no HTTP
+    framing. Each connection's peer credentials are checked before anything is read:
+    only processes running as one of ``allowed_uids`` (by default the server's own
+    user) or with a primary group in ``allowed_gids`` may sign. The socket file is
+    given ``mode`` permissions as a second line of defence.
+
+    Args:
+        accounts: The accounts to serve, as for ``SignerServer``.
+        path: The socket path. A stale socket left there by a server that exited
+            uncleanly is replaced; a live one raises ``OSError``.
+        allowed_uids: User IDs allowed to connect.
+        allowed_gids: Primary group IDs allowed to connect.
+        mode: Permissions of the socket file.
+
+    Metrics are served separately, by a ``MetricsServer``.
+    """
+
+    _metrics_name = "ipc"
+
+    def __init__(
+        self,
+        accounts: Iterable[Any],
+        path: str,
+        *,
+        allowed_uids: Optional[Collection[int]] = None,
+        allowed_gids: Collection[int] = (),
+        mode: int = 0o600,
+    ) -> None:
+        if not hasattr(socketserver, "UnixStreamServer"):
+            raise OSError("Unix domain sockets are unavailable on this platform")
+        self._allowed_uids = (
+            {os.getuid()} if allowed_uids is None else set(allowed_uids)
+        )
+        self._allowed_gids = set(allowed_gids)
+        _remove_stale_socket(path)
+        server = _UnixServer(path, _ipc_handler(self))
+        os.chmod(path, mode)
+        self._listen(accounts, server)
+
+    @property
+    def url(self) -> str:
+        """The socket path, which IPC clients take in place of a URL."""
+        return self._server.server_address  # type: ignore
+
+    def shutdown(self) -> None:
+        """Stops serving and removes the socket file."""
+        super().shutdown()
+        try:
+            os.unlink(self.url)
+        except FileNotFoundError:
+            pass
+
+    def _allowed(self, connection: socket.socket) -> bool:
+        try:
+            uid, gid = _peer_credentials(connection)
+        except OSError as e:
+            log.warning(f"Rejected IPC connection without peer credentials: {e}")
+            return False
+        if uid in self._allowed_uids or gid in self._allowed_gids:
+            return True
+        log.warning(f"Rejected IPC connection from uid {uid}, gid {gid}")
+        return False
+
+
+def _remove_stale_socket(path: str) -> None:
+    try:
+        if not stat.S_ISSOCK(os.stat(path).st_mode):
+            raise FileExistsError(f"{path} exists and isn't a socket")
+    except FileNotFoundError:
+        return
+    with socket.socket(socket.AF_UNIX, socket.SOCK_STREAM) as probe:
+        try:
+            probe.connect(path)
+        except ConnectionRefusedError:
+            os.unlink(path)
+            return
+    raise OSError(f"Another server is listening on {path}")
+
+
+def _ipc_handler(server: IpcSignerServer) -> type:
+    class Handler(socketserver.BaseRequestHandler):
+        def handle(self) -> None:
+            if not server._allowed(self.request):
+                return
+            decoder = json.JSONDecoder()
+            buffer = ""
+            while True:
+                data = self.request.recv(65536)
+                if not data:
+                    return
+                buffer += data.decode("utf-8", "replace")
+                while buffer.strip():
+                    buffer = buffer.lstrip()
+                    try:
+                        payload, end = decoder.raw_decode(buffer)
+                    except ValueError:
+                        # Wait for the rest of a request split across reads, unless
+                        # it was already terminated and can only be malformed
+                        if buffer.endswith("\n") or len(buffer) > _MAX_REQUEST:
+                            self.request.sendall(_PARSE_ERROR + b"\n")
+                            buffer = ""
+                        break
+                    buffer = buffer[end:]
+                    response = server._reply(payload)
+                    if response is not None:
+                        self.request.sendall(response + b"\n")
+
+    return Handler
+
+
+def main(argv: Optional[List[str]] = None) -> None:
+    parser = argparse.ArgumentParser(
+        prog="python -m ferrite.server",
+        description="Serve keystore accounts as a local JSON-RPC signer.",
+    )
+    parser.add_argument("--keystore", action="append", help="a keystore file to serve")
+    parser.add_argument(
+        "--config", metavar="FILE", help="take keys and settings from a TOML file"
+    )
+    parser.add_argument("--host", default="127.0.0.1")
+    parser.add_argument("--port", type=int, default=DEFAULT_PORT)
+    parser.add_argument(
+        "--ipc", metavar="PATH", help="serve on this Unix socket instead of HTTP"
+    )
+    parser.add_argument(
+        "--grpc", metavar="HOST:PORT", help="serve the gRPC signer instead of HTTP"
+    )
+    parser.add_argument(
+        "--metrics",
+        metavar="HOST:PORT",
+        help="also serve Prometheus metrics here, for --ipc and --grpc",
+    )
+    parser.add_argument(
+        "--allow-origin",
+        action="append",
+        default=[],
+        metavar="ORIGIN",
+        help="a web page origin allowed to call the HTTP server from a browser",
+    )
+    args = parser.parse_args(argv)
+    if not args.keystore and not args.config:
+        parser.error("one of --keystore and --config is required")
+
+    accounts = []
+    ipc_options: Dict[str, Any] = {}
+    grpc_options: Dict[str, Any] = {}
+    if args.config:
+        from .config import load_config
+
+        config = load_config(args.config)
+        config.apply()
+        accounts = config.accounts(
+            lambda paths: getpass.getpass(f"Password for {', '.join(paths)}: ")
+        )
+        settings = config.server
+        args.host, args.port = settings.host, settings.port
+        args.metrics = args.metrics or settings.metrics
+        args.allow_origin += settings.allowed_origins
+        if settings.transport == "ipc":
+            args.ipc = settings.path
+            ipc_options = {
+                "allowed_uids": settings.allowed_uids,
+                "allowed_gids": settings.allowed_gids,
+            }
+        elif settings.transport == "grpc":
+            args.grpc = settings.address
+            grpc_options = {"key_management": settings.key_management}
+
+    for path in args.keystore or []:
+        with open(path) as keyfile:
+            keystore = json.load(keyfile)
+        password = getpass.getpass(f"Password for {path}: ")
+        accounts.append(Account.from_key(Account.decrypt(keystore, password)))
+
+    if args.metrics:
+        host, _, port = args.metrics.rpartition(":")
+        metrics = prometheus.MetricsServer(host or "127.0.0.1", int(port)).start()
+        print(f"Serving metrics at {metrics.url}")
+    if args.grpc:
+        from .grpc_signer import serve_grpc
+
+        print(f"Serving the gRPC signer at {args.grpc}")
+        serve_grpc(accounts, args.grpc, **grpc_options).wait_for_termination()
+        return
+    if args.ipc:
+        server: SignerServer = IpcSignerServer(accounts, args.ipc, **ipc_options)
+    else:
+        server = SignerServer(
+            accounts, args.host, args.port, allowed_origins=args.allow_origin
+        )
+    print(f"Signing for {', '.join(server.call('eth_accounts', []))} at {server.url}")
+    try:
+        server.serve_forever()
+    except KeyboardInterrupt:
+        pass
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,149 @@
+/*!
+SSZ hash tree roots of the containers behind validator deposits.
+
+A deposit commits to its `DepositData` by `hash_tree_root`, and the validator's key signs
+the root of its `DepositMessage` under the deposit domain. Only the fixed-size containers
+deposits need are implemented, each merkleized straight from its fields.
+
+Deposits are signed with BLS12-381 keys, which ferrite doesn't hold: [`crate::bls`] is on
+BN254, for ERC-4337 aggregators. The signing root is computed here for a BLS12-381 signer
+such as a remote signer or `py_ecc`, and its signature goes back into the data root.
+*/
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use sha2::{Digest, Sha256};
+
+use crate::errors;
+
+type Chunk = [u8; 32];
+
+/// `DOMAIN_DEPOSIT` from the consensus specs.
+const DOMAIN_DEPOSIT: [u8; 4] = [3, 0, 0, 0];
+
+/// Merkleizes chunks, padding them with zero chunks to a power of two.
+fn merkleize(chunks: &[Chunk]) -> Chunk {
+    let mut layer = chunks.to_vec();
+    layer.resize(chunks.len().next_power_of_two(), [0; 32]);
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| Sha256::new().chain_update(pair[0]).chain_update(pair[1]).finalize().into())
+            .collect();
+    }
+    layer[0]
+}
+
+/// Returns the root of a fixed-size byte vector, packed into zero-padded chunks.
+fn bytes_root(bytes: &[u8]) -> Chunk {
+    let chunks: Vec<Chunk> = bytes
+        .chunks(32)
+        .map(|bytes| {
+            let mut chunk = [0; 32];
+            chunk[..bytes.len()].copy_from_slice(bytes);
+            chunk
+        })
+        .collect();
+    merkleize(&chunks)
+}
+
+fn uint64_root(value: u64) -> Chunk {
+    let mut chunk = [0; 32];
+    chunk[..8].copy_from_slice(&value.to_le_bytes());
+    chunk
+}
+
+fn fixed<const N: usize>(value: &[u8], name: &str) -> PyResult<[u8; N]> {
+    value.try_into().map_err(|_| {
+        PyErr::new::<errors::FerriteError, _>(format!(
+            "{} must be exactly {} bytes, got {}",
+            name,
+            N,
+            value.len()
+        ))
+    })
+}
+
+fn message_root(pubkey: &[u8], withdrawal_credentials: &[u8], amount: u64) -> PyResult<Chunk> {
+    let pubkey: [u8; 48] = fixed(pubkey, "Public key")?;
+    let withdrawal_credentials: Chunk = fixed(withdrawal_credentials, "Withdrawal credentials")?;
+    Ok(merkleize(&[bytes_root(&pubkey), withdrawal_credentials, uint64_root(amount)]))
+}
+
+/// Computes the `hash_tree_root` of a `DepositMessage`, the deposit without its signature.
+///
+/// # Arguments
+/// * `pubkey` - The validator's 48-byte BLS12-381 public key.
+/// * `withdrawal_credentials` - The 32-byte withdrawal credentials.
+/// * `amount` - The deposit amount in gwei.
+///
+/// # Returns
+/// The 32-byte root, the `deposit_message_root` of deposit JSON files.
+#[pyfunction]
+pub fn deposit_message_root(
+    py: Python,
+    pubkey: &[u8],
+    withdrawal_credentials: &[u8],
+    amount: u64,
+) -> PyResult<PyObject> {
+    let root = message_root(pubkey, withdrawal_credentials, amount)?;
+    Ok(PyBytes::new(py, &root).into_any().unbind())
+}
+
+/// Computes the root a validator's key signs to make a deposit.
+///
+/// # Arguments
+/// * `pubkey` - The validator's 48-byte BLS12-381 public key.
+/// * `withdrawal_credentials` - The 32-byte withdrawal credentials.
+/// * `amount` - The deposit amount in gwei.
+/// * `fork_version` - The 4-byte genesis fork version of the network.
+///
+/// # Returns
+/// The 32-byte signing root of the `DepositMessage` under the deposit domain, which
+/// uses a zero genesis validators root so that deposits are valid before genesis.
+#[pyfunction]
+pub fn deposit_signing_root(
+    py: Python,
+    pubkey: &[u8],
+    withdrawal_credentials: &[u8],
+    amount: u64,
+    fork_version: &[u8],
+) -> PyResult<PyObject> {
+    let object_root = message_root(pubkey, withdrawal_credentials, amount)?;
+    let fork_version: [u8; 4] = fixed(fork_version, "Fork version")?;
+    let fork_data_root = merkleize(&[bytes_root(&fork_version), [0; 32]]);
+    let mut domain = [0; 32];
+    domain[..4].copy_from_slice(&DOMAIN_DEPOSIT);
+    domain[4..].copy_from_slice(&fork_data_root[..28]);
+    Ok(PyBytes::new(py, &merkleize(&[object_root, domain])).into_any().unbind())
+}
+
+/// Computes the `hash_tree_root` of a `DepositData`, which the deposit contract takes.
+///
+/// # Arguments
+/// * `pubkey` - The validator's 48-byte BLS12-381 public key.
+/// * `withdrawal_credentials` - The 32-byte withdrawal credentials.
+/// * `amount` - The deposit amount in gwei.
+/// * `signature` - The 96-byte BLS12-381 signature of the deposit's signing root.
+///
+/// # Returns
+/// The 32-byte root, the `deposit_data_root` argument of the contract's `deposit`.
+#[pyfunction]
+pub fn deposit_data_root(
+    py: Python,
+    pubkey: &[u8],
+    withdrawal_credentials: &[u8],
+    amount: u64,
+    signature: &[u8],
+) -> PyResult<PyObject> {
+    let pubkey: [u8; 48] = fixed(pubkey, "Public key")?;
+    let withdrawal_credentials: Chunk = fixed(withdrawal_credentials, "Withdrawal credentials")?;
+    let signature: [u8; 96] = fixed(signature, "Signature")?;
+    let root = merkleize(&[
+        bytes_root(&pubkey),
+        withdrawal_credentials,
+        uint64_root(amount),
+        bytes_root(&signature),
+    ]);
+    Ok(PyBytes::new(py, &root).into_any().unbind())
+}
@@ -0,0 +1,257 @@
+"""
+STARK-curve keys and signatures, for perpetuals venues settling on StarkEx or Starknet.
+
+These venues sign orders with a key on the STARK-friendly curve, derived from the
+user's Ethereum key: the user signs a venue-specific onboarding message, and the Stark
+key is ground from the signature's ``r``, so losing it loses nothing that the Ethereum
+key can't recover. Orders are then signed with the Stark key, either over a hash the
+venue's SDK defines or, on Starknet, over typed data much like EIP-712.
+
+Only available when the extension is built with the ``stark`` feature; importing this
+module raises ``ImportError`` otherwise.
+"""
+
+from typing import Any, Dict, Iterable, List, NamedTuple, Set, Union
+
+from eth_account.messages import encode_defunct
+from eth_utils import keccak
+
+try:
+    from _ferrite import (  # type: ignore
+        stark_grind_key as rust_stark_grind_key,
+        stark_pedersen_hash as rust_stark_pedersen_hash,
+        stark_public_key as rust_stark_public_key,
+        stark_sign_hash as rust_stark_sign_hash,
+    )
+except ImportError as e:
+    raise ImportError(
+        "ferrite was built without STARK-curve support; rebuild it with "
+        "`maturin develop --features stark`"
+    ) from e
+
+from .account import (
+    _account_sign_message_wrapper,
+    _private_key_bytes,
+    _sign_typed_data_with,
+)
+
+FIELD_PRIME = 2**251 + 17 * 2**192 + 1
+
+_MASK_250 = 2**250 - 1
+
+
+class StarkSignature(NamedTuple):
+    """A STARK-curve ECDSA signature."""
+
+    r: int
+    s: int
+
+
+def _felt_bytes(value: int) -> bytes:
+    if not 0 <= value < 2**256:
+        raise ValueError(f"{value} is out of range for a STARK-curve value")
+    return value.to_bytes(32, "big")
+
+
+def _int(data: bytes) -> int:
+    return int.from_bytes(data, "big")
+
+
+def pedersen_hash(a: int, b: int) -> int:
+    """Computes the StarkWare Pedersen hash of two field elements."""
+    return _int(rust_stark_pedersen_hash(_felt_bytes(a), _felt_bytes(b)))
+
+
+def compute_hash_on_elements(elements: Iterable[int]) -> int:
+    """
+    Hashes a list of field elements by chaining Pedersen hashes from zero over the
+    elements and their count, as Starknet and StarkEx hash arrays.
+    """
+    result = count = 0
+    for element in elements:
+        result = pedersen_hash(result, element)
+        count += 1
+    return pedersen_hash(result, count)
+
+
+def stark_key_from_signature(signature: bytes) -> int:
+    """
+    Grinds the Stark private key out of the Ethereum signature of a venue's key
+    derivation message, as StarkEx's ``getPrivateKeyFromEthSignature`` does.
+
+    Args:
+        signature: The 65-byte Ethereum signature, whose ``r`` seeds the key.
+
+    Returns:
+        The Stark private key.
+    """
+    signature = bytes(signature)
+    if len(signature) != 65:
+        raise ValueError(f"Expected a 65-byte signature, got {len(signature)} bytes")
+    return _int(rust_stark_grind_key(signature[:32]))
+
+
+def derive_stark_key(message: Union[str, Dict[str, Any]], signer: Any) -> int:
+    """
+    Derives the Stark private key a venue associates with an Ethereum key, by signing
+    its key derivation message.
+
+    Args:
+        message: The venue's key derivation message, as text for an EIP-191
+            signature or as EIP-712 typed data.
+        signer: The Ethereum private key, as bytes or hex, or an account with
+            ``address``, ``sign_message`` and ``sign_typed_data``.
+
+    Returns:
+        The Stark private key.
+    """
+    if isinstance(message, dict):
+        _, signature = _sign_typed_data_with(message, signer)
+    elif hasattr(signer, "sign_message"):
+        signature = signer.sign_message(encode_defunct(text=message)).signature
+    else:
+        message = encode_defunct(text=message)
+        signed = _account_sign_message_wrapper(message, _private_key_bytes(signer))
+        signature = signed.signature
+    return stark_key_from_signature(signature)
+
+
+def stark_public_key(private_key: int) -> int:
+    """Returns the Stark public key, the x coordinate of the private key's point."""
+    return _int(rust_stark_public_key(_felt_bytes(private_key)))
+
+
+def sign_stark_hash(msg_hash: int, private_key: int) -> StarkSignature:
+    """
+    Signs a message hash with a Stark private key, with the deterministic nonces of
+    StarkWare's reference implementation.
+
+    Args:
+        msg_hash: The hash to sign, below 2**251, such as a StarkEx order hash or a
+            Starknet typed data hash.
+        private_key: The Stark private key.
+
+    Returns:
+        The signature's ``r`` and ``s``.
+    """
+    r, s = rust_stark_sign_hash(_felt_bytes(msg_hash), _felt_bytes(private_key))
+    return StarkSignature(_int(r), _int(s))
+
+
+def _short_string(text: str) -> int:
+    """Encodes text of up to 31 ASCII characters as a Cairo short string."""
+    if len(text) > 31 or not text.isascii():
+        raise ValueError(f"{text!r} is not a short string of up to 31 ASCII characters")
+    return _int(text.encode())
+
+
+def _starknet_keccak(data: bytes) -> int:
+    return _int(keccak(data)) & _MASK_250
+
+
+def _felt(value: Any) -> int:
+    """Converts an integer, numeric string or short string to a field element."""
+    if isinstance(value, str):
+        if value[:2].lower() == "0x":
+            value = int(value, 16)
+        elif value.isdigit():
+            value = int(value)
+        else:
+            value = _short_string(value)
+    if not 0 <= value < FIELD_PRIME:
+        raise ValueError(f"{value} is out of range for a field element")
+    return value
+
+
+def _dependencies(types: Dict[str, Any], name: str, found: Set[str]) -> Set[str]:
+    """Collects ``name`` and every struct type it references, however deeply."""
+    name = name.rstrip("*")
+    if name not in found and name in types:
+        found.add(name)
+        for field in types[name]:
+            _dependencies(types, field["type"], found)
+    return found
+
+
+def _type_hash(types: Dict[str, Any], name: str) -> int:
+    dependencies = _dependencies(types, name, set()) - {name}
+    encoded = "".join(
+        dependency
+        + "("
+        + ",".join(f"{field['name']}:{field['type']}" for field in types[dependency])
+        + ")"
+        for dependency in [name, *sorted(dependencies)]
+    )
+    return _starknet_keccak(encoded.encode())
+
+
+def _encode_value(types: Dict[str, Any], type_name: str, value: Any) -> int:
+    if type_name in types:
+        return _struct_hash(types, type_name, value)
+    if type_name.endswith("*"):
+        element_type = type_name[:-1]
+        return compute_hash_on_elements(
+            _encode_value(types, element_type, element) for element in value
+        )
+    if type_name == "selector":
+        if isinstance(value, str) and value[:2].lower() != "0x":
+            return _starknet_keccak(value.encode())
+        return _felt(value)
+    if type_name == "merkletree":
+        raise ValueError("Merkle tree fields in Starknet typed data are not supported")
+    return _felt(value)
+
+
+def _struct_hash(types: Dict[str, Any], name: str, data: Dict[str, Any]) -> int:
+    return compute_hash_on_elements(
+        [
+            _type_hash(types, name),
+            *(
+                _encode_value(types, field["type"], data[field["name"]])
+                for field in types[name]
+            ),
+        ]
+    )
+
+
+def starknet_typed_data_hash(
+    typed_data: Dict[str, Any], account: Union[int, str]
+) -> int:
+    """
+    Computes the hash a Starknet account signs for typed data, per the original
+    (revision 0) ``StarkNetDomain`` encoding that venues such as Paradex use for orders.
+
+    Args:
+        typed_data: The typed data, with ``types`` (including ``StarkNetDomain``),
+            ``primaryType``, ``domain`` and ``message``. String values that aren't
+            decimal or hex numbers are encoded as short strings, as in Starknet SDKs.
+        account: The address of the Starknet account signing.
+
+    Returns:
+        The message hash.
+    """
+    types = typed_data["types"]
+    elements: List[int] = [
+        _short_string("StarkNet Message"),
+        _struct_hash(types, "StarkNetDomain", typed_data["domain"]),
+        _felt(account),
+        _struct_hash(types, typed_data["primaryType"], typed_data["message"]),
+    ]
+    return compute_hash_on_elements(elements)
+
+
+def sign_starknet_typed_data(
+    typed_data: Dict[str, Any], account: Union[int, str], private_key: int
+) -> StarkSignature:
+    """
+    Signs Starknet typed data, such as an order, for a Starknet account.
+
+    Args:
+        typed_data: The typed data, as for ``starknet_typed_data_hash``.
+        account: The address of the Starknet account the key controls.
+        private_key: The Stark private key.
+
+    Returns:
+        The signature's ``r`` and ``s``.
+    """
+    return sign_stark_hash(starknet_typed_data_hash(typed_data, account), private_key)
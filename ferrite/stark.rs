@@ -0,0 +1,295 @@
+/*!
+STARK-curve keys and signatures, for exchanges settling on StarkEx or Starknet.
+
+Those venues identify an account by a key on the STARK-friendly curve rather than on
+secp256k1, but derive it from the user's Ethereum key: the user signs a venue-specific
+message and the Stark key is ground from the signature's `r`, so it can always be
+recovered from the Ethereum key alone. Orders are then signed with the Stark key over
+Pedersen hashes of their fields.
+
+Signatures follow the StarkWare reference implementation, including its RFC 6979 nonces,
+so they match byte for byte what the venues' own SDKs produce.
+
+Only built with the `stark` feature.
+*/
+
+use crypto_bigint::modular::constant_mod::{Residue, ResidueParams};
+use crypto_bigint::{impl_modulus, Encoding, NonZero, U256};
+use hmac::{Hmac, Mac};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use sha2::{Digest, Sha256};
+use subtle::Choice;
+use zeroize::Zeroize;
+
+use crate::curve::{self, Curve};
+use crate::errors;
+
+impl_modulus!(
+    FieldPrime,
+    U256,
+    "0800000000000011000000000000000000000000000000000000000000000001"
+);
+impl_modulus!(
+    CurveOrder,
+    U256,
+    "0800000000000010ffffffffffffffffb781126dcae7b2321e66a241adc64d2f"
+);
+
+/// An element of the field the curve is defined over.
+type Felt = Residue<FieldPrime, { U256::LIMBS }>;
+/// An integer modulo the order of the curve.
+type Scalar = Residue<CurveOrder, { U256::LIMBS }>;
+
+const FIELD_PRIME: &str = "the field prime";
+
+/// Message hashes and both halves of a signature must be below 2^251.
+const SIGNATURE_BOUND: U256 = U256::ONE.shl_vartime(251);
+
+const GENERATOR: Point = Point::from_hex(
+    "01ef15c18599971b7beced415a40f0c7deacfd9b0d1819e03d723d8bc943cfca",
+    "005668060aa49730b7be4801df46ec62de53ecd11abe43a32873000c36e8dc1f",
+);
+
+/// The shift point and the four points the Pedersen hash multiplies its inputs' low 248
+/// and high 4 bits by.
+const PEDERSEN_POINTS: [Point; 5] = [
+    Point::from_hex(
+        "049ee3eba8c1600700ee1b87eb599f16716b0b1022947733551fde4050ca6804",
+        "03ca0cfe4b3bc6ddf346d49d06ea0ed34e621062c0e056c1d0405d266e10268a",
+    ),
+    Point::from_hex(
+        "0234287dcbaffe7f969c748655fca9e58fa8120b6d56eb0c1080d17957ebe47b",
+        "03b056f100f96fb21e889527d41f4e39940135dd7a6c94cc6ed0268ee89e5615",
+    ),
+    Point::from_hex(
+        "04fa56f376c83db33f9dab2656558f3399099ec1de5e3018b7a6932dba8aa378",
+        "03fa0984c931c9e38113e0c0e47e4401562761f92a7a23b45168f4e80ff5b54d",
+    ),
+    Point::from_hex(
+        "04ba4cc166be8dec764910f75b45f74b40c690c74709e90f3aa372f0bd2d6997",
+        "0040301cf5c1751f4b971e46c4ede85fcac5c59a5ce5ae7c48151f27b24b219c",
+    ),
+    Point::from_hex(
+        "054302dcb0e6cc1c6e44cca8f61a63bb2ca65048d53fb325d36ff12c49a58202",
+        "01b77b3e37d13504b348046268d8ae25ce98ad783c25561a879dcc77e99c2426",
+    ),
+];
+
+const fn felt(hex: &str) -> Felt {
+    Felt::new(&U256::from_be_hex(hex))
+}
+
+/// The STARK-friendly curve `y² = x³ + x + β`.
+#[derive(Clone, Copy)]
+struct StarkCurve;
+
+impl Curve for StarkCurve {
+    type Element = Felt;
+    const A: Felt = Felt::ONE;
+    const B: Felt = felt("06f21413efbe40de150e596d72f7a8c5609ad26c15c915c1f4cdfcb99cee9e89");
+}
+
+type Point = curve::Point<StarkCurve>;
+
+/// Computes the StarkWare Pedersen hash of two field elements.
+fn pedersen(a: &U256, b: &U256) -> U256 {
+    let [shift, p1, p2, p3, p4] = &PEDERSEN_POINTS;
+    let low = U256::MAX.shr_vartime(8);
+    let point = shift
+        .add(&p1.mul(&(*a & low), 248))
+        .add(&p2.mul(&a.shr_vartime(248), 4))
+        .add(&p3.mul(&(*b & low), 248))
+        .add(&p4.mul(&b.shr_vartime(248), 4));
+    // Reaching infinity would take a discrete log relation between the points
+    point.x().expect("Pedersen hash reached the point at infinity")
+}
+
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// Generates the RFC 6979 nonce for signing `msg_hash`, as python-ecdsa does for the
+/// curve's 252-bit order.
+fn generate_k(msg_hash: &U256, private_key: &U256, extra_entropy: &[u8]) -> U256 {
+    // The reference pads hashes a nibble short of whole bytes before truncating them to
+    // 252 bits, which for hashes below 2^251 always gives back the hash itself
+    let mut x = private_key.to_be_bytes();
+    let h = msg_hash.to_be_bytes();
+    let mut v = [1u8; 32];
+    let mut k = hmac_sha256(&[0u8; 32], &[&v, &[0], &x, &h, extra_entropy]);
+    v = hmac_sha256(&k, &[&v]);
+    k = hmac_sha256(&k, &[&v, &[1], &x, &h, extra_entropy]);
+    v = hmac_sha256(&k, &[&v]);
+    x.zeroize();
+
+    let nonce = loop {
+        v = hmac_sha256(&k, &[&v]);
+        let candidate = U256::from_be_slice(&v).shr_vartime(4);
+        if candidate != U256::ZERO && candidate < CurveOrder::MODULUS {
+            break candidate;
+        }
+        k = hmac_sha256(&k, &[&v, &[0]]);
+        v = hmac_sha256(&k, &[&v]);
+    };
+    k.zeroize();
+    v.zeroize();
+    nonce
+}
+
+/// Signs `msg_hash`, retrying with fresh nonces until `r` and `w = s⁻¹` both fall below
+/// 2^251, which StarkEx's verifier requires.
+fn sign(msg_hash: &U256, private_key: &U256) -> (U256, U256) {
+    let d = Scalar::new(private_key);
+    let z = Scalar::new(msg_hash);
+    let mut seed = 0u64;
+    loop {
+        // The first attempt takes no extra entropy, the following ones a counter
+        let extra = seed.to_be_bytes();
+        let extra = &extra[(seed.leading_zeros() / 8) as usize..];
+        seed += 1;
+
+        let mut k = generate_k(msg_hash, private_key, extra);
+        let r = GENERATOR.mul(&k, 252).x().expect("nonce is below the curve order");
+        if r == U256::ZERO || r >= SIGNATURE_BOUND {
+            k.zeroize();
+            continue;
+        }
+        let (denominator_inv, invertible) = (z + Scalar::new(&r) * d).invert();
+        let w = (Scalar::new(&k) * denominator_inv).retrieve();
+        k.zeroize();
+        if !bool::from(Choice::from(invertible)) || w == U256::ZERO || w >= SIGNATURE_BOUND {
+            continue;
+        }
+        return (r, Scalar::new(&w).invert().0.retrieve());
+    }
+}
+
+/// Grinds a private key below the curve order out of `seed`, hashing it with an
+/// increasing index until the hash falls below the largest multiple of the order that
+/// fits in 256 bits, so that reducing it is unbiased.
+fn grind_key(seed: &[u8]) -> U256 {
+    let order = NonZero::new(CurveOrder::MODULUS).unwrap();
+    let limit = U256::ZERO.wrapping_sub(&U256::MAX.rem(&order).wrapping_add(&U256::ONE));
+    for index in 0u64.. {
+        let index = index.to_be_bytes();
+        let start = index.iter().position(|&b| b != 0).unwrap_or(7);
+        let mut digest: [u8; 32] = Sha256::new()
+            .chain_update(seed)
+            .chain_update(&index[start..])
+            .finalize()
+            .into();
+        let mut key = U256::from_be_slice(&digest);
+        digest.zeroize();
+        if key < limit {
+            let reduced = key.rem(&order);
+            key.zeroize();
+            return reduced;
+        }
+        key.zeroize();
+    }
+    unreachable!("the index space outlasts any run of rejected hashes")
+}
+
+/// Parses a big-endian integer of at most 32 bytes that must be below `bound`, described
+/// by `limit` in the error otherwise.
+fn integer(bytes: &[u8], bound: &U256, what: &str, limit: &str) -> PyResult<U256> {
+    let value = (bytes.len() <= 32).then(|| {
+        let mut padded = [0u8; 32];
+        padded[32 - bytes.len()..].copy_from_slice(bytes);
+        U256::from_be_slice(&padded)
+    });
+    match value {
+        Some(value) if value < *bound => Ok(value),
+        _ => Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "{} must be below {}",
+            what, limit
+        ))),
+    }
+}
+
+/// Parses a Stark private key, which must be non-zero and below the curve order.
+fn private_key(bytes: &[u8]) -> PyResult<U256> {
+    let key = integer(bytes, &CurveOrder::MODULUS, "Stark private key", "the curve order")
+        .map_err(|e| PyErr::new::<errors::InvalidKeyError, _>(e.to_string()))?;
+    if key == U256::ZERO {
+        return Err(PyErr::new::<errors::InvalidKeyError, _>("Stark private key is zero"));
+    }
+    Ok(key)
+}
+
+/// Computes the StarkWare Pedersen hash of two field elements.
+///
+/// # Arguments
+/// * `a` - First field element, big-endian, below the field prime.
+/// * `b` - Second field element, big-endian, below the field prime.
+///
+/// # Returns
+/// The 32-byte hash.
+#[pyfunction]
+pub fn stark_pedersen_hash(py: Python, a: &[u8], b: &[u8]) -> PyResult<PyObject> {
+    let a = integer(a, &FieldPrime::MODULUS, "Pedersen hash input", FIELD_PRIME)?;
+    let b = integer(b, &FieldPrime::MODULUS, "Pedersen hash input", FIELD_PRIME)?;
+    let hash = py.allow_threads(|| pedersen(&a, &b));
+    Ok(PyBytes::new(py, &hash.to_be_bytes()).into_any().unbind())
+}
+
+/// Grinds a Stark private key out of a seed, as StarkEx's `getPrivateKeyFromEthSignature`
+/// does with the `r` of an Ethereum signature.
+///
+/// # Arguments
+/// * `seed` - The seed bytes, hashed as given.
+///
+/// # Returns
+/// The 32-byte private key.
+#[pyfunction]
+pub fn stark_grind_key(py: Python, seed: &[u8]) -> PyResult<PyObject> {
+    let mut key = py.allow_threads(|| grind_key(seed));
+    let out = PyBytes::new(py, &key.to_be_bytes()).into_any().unbind();
+    key.zeroize();
+    Ok(out)
+}
+
+/// Computes the Stark public key of a private key: the x coordinate of its point.
+///
+/// # Arguments
+/// * `private_key` - The 32-byte big-endian private key.
+///
+/// # Returns
+/// The 32-byte public key.
+#[pyfunction]
+pub fn stark_public_key(py: Python, private_key: &[u8]) -> PyResult<PyObject> {
+    let mut key = self::private_key(private_key)?;
+    let public = py.allow_threads(|| GENERATOR.mul(&key, 252).x());
+    key.zeroize();
+    let public = public.expect("private key is below the curve order");
+    Ok(PyBytes::new(py, &public.to_be_bytes()).into_any().unbind())
+}
+
+/// Signs a message hash with a Stark private key.
+///
+/// # Arguments
+/// * `msg_hash` - The hash to sign, big-endian, below 2^251.
+/// * `private_key` - The 32-byte big-endian private key.
+///
+/// # Returns
+/// A tuple of the 32-byte `r` and `s`.
+#[pyfunction]
+pub fn stark_sign_hash(
+    py: Python,
+    msg_hash: &[u8],
+    private_key: &[u8],
+) -> PyResult<(PyObject, PyObject)> {
+    let msg_hash = integer(msg_hash, &SIGNATURE_BOUND, "Stark message hash", "2^251")?;
+    let mut key = self::private_key(private_key)?;
+    let (r, s) = py.allow_threads(|| sign(&msg_hash, &key));
+    key.zeroize();
+    Ok((
+        PyBytes::new(py, &r.to_be_bytes()).into_any().unbind(),
+        PyBytes::new(py, &s.to_be_bytes()).into_any().unbind(),
+    ))
+}
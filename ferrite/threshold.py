@@ -0,0 +1,98 @@
+"""
+Threshold ECDSA accounts, whose key is split between several parties.
+
+ferrite computes each digest as for any other account, sends it to every party in the
+signing quorum through a caller-supplied transport, and combines their partial
+signatures into one ECDSA signature. The combined signature then goes through the same
+Rust path as a ``RemoteAccount``'s: ``v`` is recovered, ``s`` normalized, and the
+signature checked against the account's address, so a wrong or malicious share is
+caught rather than broadcast.
+
+The default combiner covers protocols in the GG18/GG20 family, where the parties agree
+on ``r`` and each returns an additive share of ``s``. Schnorr-based schemes such as
+FROST don't produce ECDSA signatures and can't sign for Ethereum accounts.
+"""
+
+import time
+from concurrent.futures import ThreadPoolExecutor
+from typing import Any, Callable, Optional, Sequence
+
+from _ferrite import SigningError  # type: ignore
+from .remote import RemoteAccount
+
+# Order of the secp256k1 group
+_N = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+
+
+def combine_additive_shares(partials: Sequence[bytes]) -> bytes:
+    """
+    Combines partial signatures ``r || s_i`` that share ``r`` into ``r || sum(s_i)``.
+
+    Args:
+        partials: One 64-byte partial signature per party.
+    """
+    if any(len(partial) != 64 for partial in partials):
+        raise SigningError(
+            "Threshold signing failed: partial signatures must be 64 bytes (r || s_i)"
+        )
+    if len({partial[:32] for partial in partials}) != 1:
+        raise SigningError(
+            "Threshold signing failed: parties returned partial signatures for "
+            "different nonces"
+        )
+    s = sum(int.from_bytes(partial[32:], "big") for partial in partials) % _N
+    return partials[0][:32] + s.to_bytes(32, "big")
+
+
+def threshold_account(
+    address: str,
+    parties: Sequence[Any],
+    transport: Callable[[Any, bytes], bytes],
+    *,
+    combine: Callable[[Sequence[bytes]], bytes] = combine_additive_shares,
+    timeout: Optional[float] = None,
+) -> RemoteAccount:
+    """
+    Returns an account signing with a threshold ECDSA key held by ``parties``.
+
+    Args:
+        address: Address of the shared key.
+        parties: The signing quorum, in whatever form ``transport`` identifies
+            parties by. Every party must return a partial signature.
+        transport: Callable taking a party and a 32-byte digest, and returning that
+            party's partial signature. Called for all parties concurrently.
+        combine: Callable combining the partial signatures, in the order of
+            ``parties``, into a DER or 64-byte ``r || s`` signature. Defaults to
+            summing additive shares of ``s``.
+        timeout: Seconds to wait for all parties, or no limit if None. A party still
+            signing by then is abandoned rather than waited for.
+    """
+    parties = list(parties)
+    if not parties:
+        raise ValueError("A threshold account needs at least one party")
+
+    def sign(digest: bytes) -> bytes:
+        deadline = None if timeout is None else time.monotonic() + timeout
+        pool = ThreadPoolExecutor(max_workers=len(parties))
+        futures = [pool.submit(transport, party, digest) for party in parties]
+        partials = []
+        try:
+            for party, future in zip(parties, futures):
+                remaining = None
+                if deadline is not None:
+                    remaining = max(0.0, deadline - time.monotonic())
+                try:
+                    partials.append(bytes(future.result(timeout=remaining)))
+                except Exception as e:
+                    raise SigningError(
+                        f"Threshold signing failed: party {party!r} did not return a "
+                        f"partial signature: {e!r}"
+                    ) from e
+        finally:
+            # Leaving a `with` block would wait for a hung party's transport to return
+            for pending in futures:
+                pending.cancel()
+            pool.shutdown(wait=False)
+        return combine(partials)
+
+    return RemoteAccount(address, sign)
@@ -0,0 +1,68 @@
+/*!
+EIP-2718 typed transaction envelopes.
+
+Transactions themselves are encoded by `ferrite_core::tx`. The envelope, a type byte
+followed by an opaque payload, is exposed to Python on its own so that experimental
+transaction types can be prototyped on top.
+*/
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyTuple};
+
+use crate::errors;
+
+pub use ferrite_core::tx::UnsignedFields;
+
+/// The largest EIP-2718 transaction type. Legacy transactions start with an RLP list
+/// header, at least `0xc0`, so type bytes from here to `0xbf` are never valid.
+const MAX_TX_TYPE: u8 = 0x7f;
+
+/// Wraps a payload in an EIP-2718 typed transaction envelope.
+///
+/// # Arguments
+/// * `tx_type` - The transaction type, from 0 to `0x7f`.
+/// * `payload` - The type-specific payload, usually an RLP list.
+///
+/// # Returns
+/// The type byte followed by the payload, as broadcast and hashed for the transaction
+/// hash.
+#[pyfunction]
+pub fn wrap_typed_transaction(py: Python, tx_type: u8, payload: &[u8]) -> PyResult<PyObject> {
+    if tx_type > MAX_TX_TYPE {
+        return Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "Transaction type must be at most 0x7f, got {:#04x}",
+            tx_type
+        )));
+    }
+    let envelope = [&[tx_type][..], payload].concat();
+    Ok(PyBytes::new(py, &envelope).into_any().unbind())
+}
+
+/// Splits an EIP-2718 typed transaction envelope into its type and payload.
+///
+/// # Arguments
+/// * `envelope` - A typed transaction or receipt, such as a raw transaction.
+///
+/// # Returns
+/// A tuple of the type and the payload. Legacy transactions, which start with an RLP
+/// list rather than a type byte, are rejected.
+#[pyfunction]
+pub fn unwrap_typed_transaction(py: Python, envelope: &[u8]) -> PyResult<PyObject> {
+    match envelope.split_first() {
+        Some((&tx_type, payload)) if tx_type <= MAX_TX_TYPE => {
+            let payload = PyBytes::new(py, payload).into_any();
+            let tx_type = tx_type.into_pyobject(py)?.into_any();
+            Ok(PyTuple::new(py, [tx_type, payload])?.into_any().unbind())
+        }
+        Some((&first, _)) if first >= 0xc0 => Err(PyErr::new::<errors::FerriteError, _>(
+            "Not a typed transaction envelope: this is a legacy transaction",
+        )),
+        Some((&first, _)) => Err(PyErr::new::<errors::FerriteError, _>(format!(
+            "Not a typed transaction envelope: invalid type byte {:#04x}",
+            first
+        ))),
+        None => Err(PyErr::new::<errors::FerriteError, _>(
+            "Not a typed transaction envelope: empty input",
+        )),
+    }
+}
@@ -0,0 +1,131 @@
+"""
+Exact conversion between wei and larger denominations.
+
+Amounts are scaled by moving the decimal point of their exact decimal digits, never
+through binary floating point, so ``to_wei("0.1", "ether")`` is exactly ``10**17``.
+Floats are taken as their shortest ``repr``, the number as written. Unlike eth-utils,
+amounts with more decimals than the unit allows raise instead of being truncated to a
+whole number of wei.
+"""
+
+from decimal import Decimal, InvalidOperation, localcontext
+from typing import Dict, Union
+
+# The number of decimals of each unit, by the names eth-utils accepts
+_UNITS: Dict[str, int] = {
+    "wei": 0,
+    "kwei": 3,
+    "babbage": 3,
+    "femtoether": 3,
+    "mwei": 6,
+    "lovelace": 6,
+    "picoether": 6,
+    "gwei": 9,
+    "shannon": 9,
+    "nanoether": 9,
+    "nano": 9,
+    "szabo": 12,
+    "microether": 12,
+    "micro": 12,
+    "finney": 15,
+    "milliether": 15,
+    "milli": 15,
+    "ether": 18,
+    "kether": 21,
+    "grand": 21,
+    "mether": 24,
+    "gether": 27,
+    "tether": 30,
+}
+
+_MAX_WEI = 2**256 - 1
+
+
+def _decimals(unit: Union[str, int]) -> int:
+    if isinstance(unit, int) and not isinstance(unit, bool):
+        if not 0 <= unit <= 77:
+            raise ValueError(f"A unit must have 0 to 77 decimals, got {unit}")
+        return unit
+    if isinstance(unit, str) and unit.lower() in _UNITS:
+        return _UNITS[unit.lower()]
+    raise ValueError(f"Unknown unit {unit!r}; expected one of {', '.join(_UNITS)}")
+
+
+def to_wei(
+    number: Union[int, float, str, Decimal], unit: Union[str, int] = "ether"
+) -> int:
+    """
+    Converts an amount in ``unit`` to wei.
+
+    Args:
+        number: The amount, as an int, a decimal string, a ``Decimal`` or a float.
+        unit: A unit name such as ``"gwei"`` or ``"ether"``, in any case, or a number
+            of decimals, such as 6 for a token like USDC.
+
+    Returns:
+        The amount in wei.
+
+    Raises:
+        ValueError: If the amount isn't a whole number of wei or isn't between 0 and
+            ``2**256 - 1`` wei.
+    """
+    decimals = _decimals(unit)
+    if isinstance(number, bool):
+        raise TypeError("Cannot convert a bool to wei")
+    if isinstance(number, float):
+        number = repr(number)
+    if isinstance(number, (int, str)):
+        try:
+            number = Decimal(number.strip() if isinstance(number, str) else number)
+        except InvalidOperation:
+            raise ValueError(f"Not a decimal number: {number!r}") from None
+    if not isinstance(number, Decimal):
+        raise TypeError(f"Cannot convert {type(number).__name__!r} to wei")
+    if not number.is_finite():
+        raise ValueError(f"Cannot convert {number} to wei")
+
+    sign, digits, exponent = number.as_tuple()
+    exponent = int(exponent) + decimals
+    coefficient = int("".join(map(str, digits)))
+    # Bound the exponent before raising 10 to it, so "1e999999999" fails fast
+    if coefficient == 0:
+        wei = 0
+    elif exponent >= 0:
+        if len(digits) + exponent > 78:
+            raise ValueError(f"{number} {unit} is more than 2**256 - 1 wei")
+        wei = coefficient * 10**exponent
+    else:
+        if -exponent > len(digits):
+            raise ValueError(f"{number} {unit} is not a whole number of wei")
+        wei, remainder = divmod(coefficient, 10**-exponent)
+        if remainder:
+            raise ValueError(f"{number} {unit} is not a whole number of wei")
+    if sign and wei:
+        raise ValueError(f"Cannot convert negative amount {number} to wei")
+    if wei > _MAX_WEI:
+        raise ValueError(f"{number} {unit} is more than 2**256 - 1 wei")
+    return wei
+
+
+def from_wei(number: int, unit: Union[str, int] = "ether") -> Union[int, Decimal]:
+    """
+    Converts an amount in wei to ``unit``, as eth-utils' ``from_wei`` does.
+
+    Args:
+        number: The amount in wei.
+        unit: A unit name or a number of decimals, as for ``to_wei``.
+
+    Returns:
+        The exact amount as a ``Decimal``, or ``0`` for zero.
+    """
+    decimals = _decimals(unit)
+    if isinstance(number, bool) or not isinstance(number, int):
+        raise TypeError(f"Expected an int number of wei, got {type(number).__name__!r}")
+    if not 0 <= number <= _MAX_WEI:
+        raise ValueError(f"{number} wei is not between 0 and 2**256 - 1")
+    if number == 0:
+        return 0
+    with localcontext() as context:
+        # Enough digits for any uint256, so dividing by a power of ten is exact
+        context.prec = 999
+        return Decimal(number) / Decimal(10**decimals)
@@ -0,0 +1,369 @@
+/*!
+ERC-4337 user operations, as the v0.6 and v0.7 EntryPoints hash them.
+
+The `userOpHash` commits to every field but the signature, with the variable-length
+fields replaced by their keccak256, and then to the EntryPoint and chain id, so that a
+signature can't be replayed against another EntryPoint or chain. Accounts such as the
+reference `SimpleAccount` check an EIP-191 signature of that hash, so the hash is
+signed as a personal message unless asked otherwise.
+
+v0.7 hashes the `PackedUserOperation` the EntryPoint receives: gas limits and fees
+packed in pairs into `accountGasLimits` and `gasFees`, and the factory and paymaster
+folded into `initCode` and `paymasterAndData`. Bundler RPCs take the unpacked fields
+instead, so either form is accepted.
+*/
+
+use std::time::Instant;
+
+use ethers_core::abi::{encode, Token};
+use ethers_core::types::{Address, Bytes, H160, H256, U256};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::request::required;
+use crate::{address, audit, errors, interpreter, keccak, metrics, request};
+
+/// The EntryPoint versions whose hashing is supported.
+#[derive(Clone, Copy, PartialEq)]
+enum Version {
+    V06,
+    V07,
+}
+
+/// The canonical v0.6 and v0.7 EntryPoint deployments, identifying the version from
+/// the address alone.
+const ENTRY_POINTS: [(Address, Version); 2] = [
+    (
+        H160([
+            0x5f, 0xf1, 0x37, 0xd4, 0xb0, 0xfd, 0xcd, 0x49, 0xdc, 0xa3, 0x0c, 0x7c, 0xf5, 0x7e,
+            0x57, 0x8a, 0x02, 0x6d, 0x27, 0x89,
+        ]),
+        Version::V06,
+    ),
+    (
+        H160([
+            0x00, 0x00, 0x00, 0x00, 0x71, 0x72, 0x7d, 0xe2, 0x2e, 0x5e, 0x9d, 0x8b, 0xaf, 0x0e,
+            0xda, 0xc6, 0xf3, 0x7d, 0xa0, 0x32,
+        ]),
+        Version::V07,
+    ),
+];
+
+/// Fields only v0.7 user operations have, packed or not.
+const V07_FIELDS: [&str; 4] = ["accountGasLimits", "gasFees", "factory", "paymaster"];
+
+impl Version {
+    /// Parses an explicit version, or picks one from the EntryPoint and then `fields`.
+    fn detect(
+        version: Option<&str>,
+        entry_point: Address,
+        fields: &request::Fields,
+    ) -> PyResult<Self> {
+        match version {
+            Some("0.6") => return Ok(Version::V06),
+            Some("0.7") => return Ok(Version::V07),
+            Some(other) => {
+                return Err(PyErr::new::<errors::FerriteError, _>(format!(
+                    "Unsupported EntryPoint version {:?}, expected \"0.6\" or \"0.7\"",
+                    other
+                )))
+            }
+            None => {}
+        }
+        if let Some((_, version)) = ENTRY_POINTS.iter().find(|(a, _)| *a == entry_point) {
+            return Ok(*version);
+        }
+        let v07 = V07_FIELDS.iter().any(|name| fields.get(*name).is_some_and(|v| !v.is_null()));
+        Ok(if v07 { Version::V07 } else { Version::V06 })
+    }
+}
+
+/// How a user operation's gas limits and fees are hashed.
+enum Gas {
+    /// v0.6 hashes each as its own word.
+    Unpacked {
+        call_gas_limit: U256,
+        verification_gas_limit: U256,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+    /// v0.7 packs them in pairs of 128-bit halves, high half first.
+    Packed { account_gas_limits: H256, gas_fees: H256 },
+}
+
+/// The fields of a user operation that its hash commits to.
+struct UserOperation {
+    sender: Address,
+    nonce: U256,
+    init_code: Bytes,
+    call_data: Bytes,
+    pre_verification_gas: U256,
+    paymaster_and_data: Bytes,
+    gas: Gas,
+}
+
+/// Packs two 128-bit quantities into one word, `high` first.
+fn pack(high: U256, low: U256) -> H256 {
+    let mut word = [0u8; 32];
+    (high << 128 | low).to_big_endian(&mut word);
+    H256(word)
+}
+
+/// Parses the bytes32 field `name`.
+fn word(fields: &request::Fields, name: &str) -> request::FieldResult<H256> {
+    match request::hex_data(fields, name)? {
+        Some(data) if data.len() != 32 => {
+            Err(format!("Invalid `{}`: expected 32 bytes, got {}", name, data.len()))
+        }
+        data => Ok(data.map(|data| H256::from_slice(&data))),
+    }
+}
+
+impl UserOperation {
+    /// Parses a user operation JSON payload for the EntryPoint at `entry_point`,
+    /// reporting every invalid field at once.
+    fn parse(payload: &str, entry_point: Address, version: Option<&str>) -> PyResult<Self> {
+        let fields: request::Fields = serde_json::from_str(payload).map_err(|e| {
+            PyErr::new::<errors::InvalidTransactionError, _>(format!(
+                "Invalid UserOperation JSON: {}",
+                e
+            ))
+        })?;
+        let version = Version::detect(version, entry_point, &fields)?;
+
+        let mut problems = request::Problems::default();
+        let sender = request::address(&fields, "sender", false);
+        let sender = problems.check(required("sender", sender));
+        let nonce = problems.check(required("nonce", request::quantity(&fields, "nonce", 256)));
+        let pre_verification_gas = request::quantity(&fields, "preVerificationGas", 256);
+        let pre_verification_gas =
+            problems.check(required("preVerificationGas", pre_verification_gas));
+        let call_data = problems.check(request::hex_data(&fields, "callData"));
+        let (init_code, paymaster_and_data, gas) = match version {
+            Version::V06 => Self::parse_unpacked(&fields, &mut problems),
+            Version::V07 => Self::parse_packed(&fields, &mut problems),
+        };
+        problems.into_message().map_err(errors::invalid_transaction)?;
+
+        // Every required field parsed, or `into_message` would have failed
+        Ok(UserOperation {
+            sender: sender.unwrap(),
+            nonce: nonce.unwrap(),
+            init_code: init_code.unwrap_or_default(),
+            call_data: call_data.unwrap_or_default(),
+            pre_verification_gas: pre_verification_gas.unwrap(),
+            paymaster_and_data: paymaster_and_data.unwrap_or_default(),
+            gas,
+        })
+    }
+
+    /// Parses the v0.6 init code, paymaster and gas fields.
+    fn parse_unpacked(
+        fields: &request::Fields,
+        problems: &mut request::Problems,
+    ) -> (Option<Bytes>, Option<Bytes>, Gas) {
+        let mut quantity = |name: &str| {
+            problems.check(required(name, request::quantity(fields, name, 256))).unwrap_or_default()
+        };
+        let gas = Gas::Unpacked {
+            call_gas_limit: quantity("callGasLimit"),
+            verification_gas_limit: quantity("verificationGasLimit"),
+            max_fee_per_gas: quantity("maxFeePerGas"),
+            max_priority_fee_per_gas: quantity("maxPriorityFeePerGas"),
+        };
+        (
+            problems.check(request::hex_data(fields, "initCode")),
+            problems.check(request::hex_data(fields, "paymasterAndData")),
+            gas,
+        )
+    }
+
+    /// Parses the v0.7 fields, each given either packed as the EntryPoint takes them or
+    /// unpacked as bundler RPCs do.
+    fn parse_packed(
+        fields: &request::Fields,
+        problems: &mut request::Problems,
+    ) -> (Option<Bytes>, Option<Bytes>, Gas) {
+        // Gas limits and fees are 128 bits, to fit in half a word
+        let quantity = |problems: &mut request::Problems, name: &str| {
+            problems
+                .check(required(name, request::quantity(fields, name, 128)))
+                .unwrap_or_default()
+        };
+        // The packed form is used where given, even if invalid, so that an invalid field
+        // isn't also reported as the unpacked ones missing
+        let present = |name: &str| fields.get(name).is_some_and(|value| !value.is_null());
+
+        let account_gas_limits = if present("accountGasLimits") {
+            problems.check(word(fields, "accountGasLimits")).unwrap_or_default()
+        } else {
+            pack(quantity(problems, "verificationGasLimit"), quantity(problems, "callGasLimit"))
+        };
+        let gas_fees = if present("gasFees") {
+            problems.check(word(fields, "gasFees")).unwrap_or_default()
+        } else {
+            pack(quantity(problems, "maxPriorityFeePerGas"), quantity(problems, "maxFeePerGas"))
+        };
+
+        let init_code = if present("factory") {
+            let factory = problems.check(request::address(fields, "factory", false));
+            let data = problems.check(request::hex_data(fields, "factoryData"));
+            let data = data.unwrap_or_default();
+            factory.map(|factory| [factory.as_bytes(), &data].concat().into())
+        } else {
+            problems.check(request::hex_data(fields, "initCode"))
+        };
+        let paymaster_and_data = if present("paymaster") {
+            let paymaster = problems.check(request::address(fields, "paymaster", false));
+            let gas_limits = pack(
+                quantity(problems, "paymasterVerificationGasLimit"),
+                quantity(problems, "paymasterPostOpGasLimit"),
+            );
+            let data = problems.check(request::hex_data(fields, "paymasterData"));
+            let data = data.unwrap_or_default();
+            paymaster.map(|paymaster| {
+                [paymaster.as_bytes(), gas_limits.as_bytes(), &data].concat().into()
+            })
+        } else {
+            problems.check(request::hex_data(fields, "paymasterAndData"))
+        };
+
+        (init_code, paymaster_and_data, Gas::Packed { account_gas_limits, gas_fees })
+    }
+
+    /// Returns the hash of the fields alone, which the `userOpHash` and the BLS
+    /// aggregator's hash wrap.
+    fn struct_hash(&self) -> H256 {
+        let hash = |data: &Bytes| Token::FixedBytes(keccak::keccak256(data).to_vec());
+        let mut tokens = vec![
+            Token::Address(self.sender),
+            Token::Uint(self.nonce),
+            hash(&self.init_code),
+            hash(&self.call_data),
+        ];
+        match &self.gas {
+            Gas::Unpacked {
+                call_gas_limit,
+                verification_gas_limit,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => tokens.extend([
+                Token::Uint(*call_gas_limit),
+                Token::Uint(*verification_gas_limit),
+                Token::Uint(self.pre_verification_gas),
+                Token::Uint(*max_fee_per_gas),
+                Token::Uint(*max_priority_fee_per_gas),
+            ]),
+            Gas::Packed { account_gas_limits, gas_fees } => tokens.extend([
+                Token::FixedBytes(account_gas_limits.as_bytes().to_vec()),
+                Token::Uint(self.pre_verification_gas),
+                Token::FixedBytes(gas_fees.as_bytes().to_vec()),
+            ]),
+        }
+        tokens.push(hash(&self.paymaster_and_data));
+        H256(keccak::keccak256(&encode(&tokens)))
+    }
+
+    /// Returns the `userOpHash` the EntryPoint at `entry_point` computes on `chain_id`.
+    fn hash(&self, entry_point: Address, chain_id: u64) -> H256 {
+        H256(keccak::keccak256(&encode(&[
+            Token::FixedBytes(self.struct_hash().as_bytes().to_vec()),
+            Token::Address(entry_point),
+            Token::Uint(U256::from(chain_id)),
+        ])))
+    }
+}
+
+/// Parses `payload` and returns its `userOpHash`. Does not touch the GIL.
+fn user_op_hash(
+    payload: &str,
+    entry_point: &str,
+    chain_id: u64,
+    version: Option<&str>,
+) -> PyResult<H256> {
+    let entry_point = address::parse(entry_point)?;
+    Ok(UserOperation::parse(payload, entry_point, version)?.hash(entry_point, chain_id))
+}
+
+/// Parses `payload` and returns the hash of its fields alone, as a contract other than
+/// the EntryPoint wraps it. Does not touch the GIL.
+pub fn user_op_struct_hash(payload: &str, version: Option<&str>) -> PyResult<H256> {
+    // Without an EntryPoint to go by, the version comes from `version` or the fields
+    Ok(UserOperation::parse(payload, Address::zero(), version)?.struct_hash())
+}
+
+/// Computes the hash an ERC-4337 EntryPoint assigns a user operation.
+///
+/// # Arguments
+/// * `payload` - JSON string of the user operation, with camelCase field names.
+/// * `entry_point` - Address of the EntryPoint contract.
+/// * `chain_id` - Chain the EntryPoint is deployed on.
+/// * `version` - EntryPoint version, `"0.6"` or `"0.7"`. Detected from the canonical
+///   EntryPoint addresses, or else from the fields only v0.7 has, if omitted.
+///
+/// # Returns
+/// The 32-byte `userOpHash`.
+#[pyfunction]
+#[pyo3(signature = (payload, entry_point, chain_id, *, version = None))]
+pub fn user_operation_hash(
+    py: Python,
+    payload: &str,
+    entry_point: &str,
+    chain_id: u64,
+    version: Option<&str>,
+) -> PyResult<PyObject> {
+    let hash = py.allow_threads(|| user_op_hash(payload, entry_point, chain_id, version))?;
+    Ok(PyBytes::new(py, hash.as_bytes()).into_any().unbind())
+}
+
+/// Signs an ERC-4337 user operation.
+///
+/// The signing policy can't check the calls a user operation makes, so while one is in
+/// force this raises `PolicyViolationError` unless the policy sets `allowRawHashes`.
+///
+/// # Arguments
+/// * `payload` - JSON string of the user operation, with camelCase field names.
+/// * `entry_point` - Address of the EntryPoint contract.
+/// * `chain_id` - Chain the EntryPoint is deployed on.
+/// * `private_key` - 32-byte raw private key.
+/// * `eip191` - Whether to sign the `userOpHash` as an EIP-191 personal message, as
+///   `SimpleAccount` expects, rather than the bare hash.
+/// * `version` - EntryPoint version, as for [`user_operation_hash`].
+///
+/// # Returns
+/// A Python dictionary with the signature components `r`, `s`, `v`, `y_parity` and
+/// `signature`, plus the `userOpHash`.
+#[pyfunction]
+#[pyo3(signature = (payload, entry_point, chain_id, private_key, *, eip191 = true, version = None))]
+pub fn sign_user_operation(
+    py: Python,
+    payload: &str,
+    entry_point: &str,
+    chain_id: u64,
+    private_key: &[u8],
+    eip191: bool,
+    version: Option<&str>,
+) -> PyResult<PyObject> {
+    let started = Instant::now();
+    let context = audit::context(py)?;
+    let mut address = None;
+    let mut result = interpreter::allow_threads(py, || {
+        let hash = user_op_hash(payload, entry_point, chain_id, version)?;
+        let digest = if eip191 {
+            let message = [b"\x19Ethereum Signed Message:\n32", hash.as_bytes()].concat();
+            H256(keccak::keccak256(&message))
+        } else {
+            hash
+        };
+        let signed =
+            crate::hash_signature(digest.as_bytes(), None, private_key, None, &mut address)?;
+        Ok((hash, signed))
+    });
+    metrics::observe(py, "hash", address, result.as_mut().map(|_| 1), started);
+    let (hash, (signature, record)) = result?;
+    audit::record(py, &[record], context.as_ref())?;
+
+    let result = crate::signature_dict(py, &signature, None)?;
+    result.set_item("userOpHash", PyBytes::new(py, hash.as_bytes()))?;
+    Ok(result.into_any().unbind())
+}
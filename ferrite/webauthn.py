@@ -0,0 +1,193 @@
+"""
+WebAuthn assertions as smart account signatures.
+
+A passkey never signs the account's hash directly: it signs its authenticator data
+followed by the SHA-256 of a client data JSON that embeds the hash, base64url encoded,
+as the ``challenge``. Validators such as Coinbase Smart Wallet's (built on the
+``WebAuthn`` library it shares with Solady) take both blobs along with the offsets of
+the ``type`` and ``challenge`` keys in the JSON, so they can check the challenge without
+parsing JSON on chain, and the P-256 ``r`` and ``s``.
+"""
+
+import base64
+import hashlib
+import json
+from typing import Any, NamedTuple, Sequence, Tuple, Union
+
+from hexbytes import HexBytes
+from .p256 import _ORDER, _signature_words, p256_sign_hash
+
+# Authenticator data flags: the user was present, and verified by PIN or biometrics
+WEBAUTHN_USER_PRESENT = 0x01
+WEBAUTHN_USER_VERIFIED = 0x04
+
+
+class WebAuthnAuth(NamedTuple):
+    """A WebAuthn assertion, in the fields of the on-chain ``WebAuthnAuth`` struct."""
+
+    authenticator_data: HexBytes
+    client_data_json: str
+    challenge_index: int
+    type_index: int
+    r: int
+    s: int
+
+
+def _abi_encode_struct(values: Sequence[Union[int, bytes]]) -> bytes:
+    """
+    ABI-encodes a struct of ``uint256`` and ``bytes`` or ``string`` fields as the single
+    parameter of ``abi.encode``.
+    """
+    head, tail = b"", b""
+    for value in values:
+        if isinstance(value, int):
+            head += value.to_bytes(32, "big")
+        else:
+            head += (32 * len(values) + len(tail)).to_bytes(32, "big")
+            padding = b"\x00" * (-len(value) % 32)
+            tail += len(value).to_bytes(32, "big") + value + padding
+    return (32).to_bytes(32, "big") + head + tail
+
+
+def webauthn_authenticator_data(
+    rp_id: str,
+    *,
+    flags: int = WEBAUTHN_USER_PRESENT | WEBAUTHN_USER_VERIFIED,
+    sign_count: int = 0,
+) -> HexBytes:
+    """
+    Builds the authenticator data of an assertion: the SHA-256 of the relying party
+    ID, the flags byte and the signature counter.
+    """
+    rp_id_hash = hashlib.sha256(rp_id.encode()).digest()
+    return HexBytes(rp_id_hash + bytes([flags]) + sign_count.to_bytes(4, "big"))
+
+
+def webauthn_client_data_json(
+    challenge: bytes, origin: str, *, cross_origin: bool = False
+) -> str:
+    """
+    Builds the client data JSON a browser produces for ``navigator.credentials.get``,
+    with the challenge base64url encoded without padding.
+    """
+    encoded = base64.urlsafe_b64encode(bytes(HexBytes(challenge))).rstrip(b"=")
+    return (
+        f'{{"type":"webauthn.get","challenge":"{encoded.decode()}",'
+        f'"origin":{json.dumps(origin)},"crossOrigin":{json.dumps(cross_origin)}}}'
+    )
+
+
+def webauthn_message_hash(authenticator_data: bytes, client_data_json: str) -> HexBytes:
+    """Returns the hash the passkey signs for an assertion."""
+    client_data_hash = hashlib.sha256(client_data_json.encode()).digest()
+    return HexBytes(
+        hashlib.sha256(bytes(HexBytes(authenticator_data)) + client_data_hash).digest()
+    )
+
+
+def webauthn_auth(
+    authenticator_data: bytes,
+    client_data_json: str,
+    signature: Union[Tuple[int, int], bytes, str],
+) -> WebAuthnAuth:
+    """
+    Assembles the ``WebAuthnAuth`` struct for an assertion, such as one returned by a
+    browser or authenticator.
+
+    Args:
+        authenticator_data: The assertion's ``authenticatorData``.
+        client_data_json: The assertion's ``clientDataJSON``, exactly as signed.
+        signature: The P-256 signature, as ``(r, s)`` or 64 bytes of ``r || s``.
+            Authenticators return DER, which must be decoded first.
+
+    Returns:
+        The struct fields, with the key offsets located and ``s`` moved to the low
+        half of the order, which the validators require.
+    """
+    client_data = client_data_json.encode()
+    try:
+        challenge_index = client_data.index(b'"challenge":"')
+        type_index = client_data.index(b'"type":"webauthn.get"')
+    except ValueError:
+        raise ValueError(
+            "clientDataJSON has no challenge or isn't for a webauthn.get assertion"
+        ) from None
+
+    r, s = (int.from_bytes(word, "big") for word in _signature_words(signature))
+    if s > _ORDER // 2:
+        s = _ORDER - s
+    return WebAuthnAuth(
+        HexBytes(authenticator_data),
+        client_data_json,
+        challenge_index,
+        type_index,
+        r,
+        s,
+    )
+
+
+def encode_webauthn_auth(auth: WebAuthnAuth) -> HexBytes:
+    """ABI-encodes a ``WebAuthnAuth`` struct, as validators ``abi.decode`` it."""
+    return HexBytes(
+        _abi_encode_struct(
+            [
+                bytes(auth.authenticator_data),
+                auth.client_data_json.encode(),
+                auth.challenge_index,
+                auth.type_index,
+                auth.r,
+                auth.s,
+            ]
+        )
+    )
+
+
+def sign_webauthn(
+    challenge: bytes,
+    private_key: Any,
+    *,
+    rp_id: str,
+    origin: str,
+    flags: int = WEBAUTHN_USER_PRESENT | WEBAUTHN_USER_VERIFIED,
+    sign_count: int = 0,
+) -> WebAuthnAuth:
+    """
+    Signs a challenge as a passkey would, with a P-256 key held by the caller, such as
+    a backend signer registered with the account as a WebAuthn owner.
+
+    Args:
+        challenge: The hash the account validates, such as a user operation hash or
+            its replay-safe hash.
+        private_key: The P-256 private key, as bytes or hex.
+        rp_id: The relying party ID the authenticator data commits to.
+        origin: The origin recorded in the client data.
+        flags: The authenticator data flags.
+        sign_count: The authenticator's signature counter.
+
+    Returns:
+        The ``WebAuthnAuth`` struct of the assertion.
+    """
+    authenticator_data = webauthn_authenticator_data(
+        rp_id, flags=flags, sign_count=sign_count
+    )
+    client_data_json = webauthn_client_data_json(challenge, origin)
+    message_hash = webauthn_message_hash(authenticator_data, client_data_json)
+    signature = p256_sign_hash(message_hash, private_key)
+    return webauthn_auth(authenticator_data, client_data_json, signature)
+
+
+def coinbase_smart_wallet_signature(
+    owner_index: int, signature: Union[WebAuthnAuth, bytes]
+) -> HexBytes:
+    """
+    Wraps an owner's signature in the ``SignatureWrapper`` that Coinbase Smart Wallet
+    expects, naming the owner by its index.
+
+    Args:
+        owner_index: The index of the signing owner in the wallet.
+        signature: A ``WebAuthnAuth`` for passkey owners, or the 65-byte
+            ``r || s || v`` signature for Ethereum address owners.
+    """
+    if isinstance(signature, WebAuthnAuth):
+        signature = encode_webauthn_auth(signature)
+    return HexBytes(_abi_encode_struct([owner_index, bytes(signature)]))
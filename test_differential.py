@@ -0,0 +1,220 @@
+"""
+Differential tests of ferrite against stock eth-account.
+
+Hypothesis generates random keys, messages, transactions and typed data; each is
+signed through the public eth-account API once with ferrite patched in and once
+with the original implementation, and the outputs must be byte-identical.
+"""
+
+import os
+from contextlib import contextmanager
+
+import pytest
+from eth_account import Account
+from eth_account.messages import encode_defunct
+from eth_utils import to_checksum_address
+from hypothesis import HealthCheck, given, settings
+from hypothesis import strategies as st
+
+import ferrite
+from ferrite.account import patch_eth_account, unpatch_eth_account
+
+SECP256K1_ORDER = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+
+settings.register_profile(
+    "differential",
+    max_examples=int(os.environ.get("FERRITE_FUZZ_EXAMPLES", "200")),
+    deadline=None,
+    suppress_health_check=[HealthCheck.too_slow],
+)
+settings.load_profile("differential")
+
+
+@pytest.fixture(autouse=True, scope="module")
+def patched():
+    """Makes sure ferrite is patched in for the module."""
+    ferrite.install()
+
+
+@contextmanager
+def stock_eth_account():
+    """Temporarily restores the original eth-account signing methods."""
+    unpatch_eth_account()
+    try:
+        yield
+    finally:
+        patch_eth_account()
+
+
+def both(sign):
+    """Returns the results of ``sign()`` with stock eth-account and with ferrite."""
+    with stock_eth_account():
+        expected = sign()
+    return expected, sign()
+
+
+def uint(bits):
+    return st.integers(min_value=0, max_value=2**bits - 1)
+
+
+def hex_bytes(min_size=0, max_size=None):
+    return st.binary(min_size=min_size, max_size=max_size).map(lambda b: "0x" + b.hex())
+
+
+def texts(max_size=None):
+    # Lone surrogates can't be encoded as UTF-8 by either implementation
+    return st.text(alphabet=st.characters(blacklist_categories=("Cs",)), max_size=max_size)
+
+
+private_keys = st.integers(min_value=1, max_value=SECP256K1_ORDER - 1).map(
+    lambda key: "0x" + key.to_bytes(32, "big").hex()
+)
+addresses = st.binary(min_size=20, max_size=20).map(to_checksum_address)
+chain_ids = st.integers(min_value=1, max_value=2**32)
+
+messages = st.one_of(
+    texts().map(lambda text: encode_defunct(text=text)),
+    st.binary(max_size=1024).map(lambda data: encode_defunct(primitive=data)),
+)
+
+access_lists = st.lists(
+    st.fixed_dictionaries(
+        {
+            "address": addresses,
+            "storageKeys": st.lists(hex_bytes(32, 32), max_size=3),
+        }
+    ),
+    max_size=3,
+)
+
+
+def common_fields():
+    return {
+        "to": addresses,
+        "value": uint(256),
+        "gas": uint(64),
+        "nonce": uint(64),
+        "data": hex_bytes(max_size=512),
+    }
+
+
+legacy_transactions = st.fixed_dictionaries(
+    {**common_fields(), "gasPrice": uint(256)},
+    optional={"chainId": st.one_of(st.none(), st.just(0), chain_ids)},
+)
+
+access_list_transactions = st.fixed_dictionaries(
+    {
+        **common_fields(),
+        "type": st.just(1),
+        "gasPrice": uint(256),
+        "chainId": chain_ids,
+        "accessList": access_lists,
+    }
+)
+
+
+@st.composite
+def dynamic_fee_transactions(draw):
+    transaction = draw(
+        st.fixed_dictionaries(
+            {
+                **common_fields(),
+                "type": st.just(2),
+                "maxFeePerGas": uint(256),
+                "chainId": chain_ids,
+                "accessList": access_lists,
+            }
+        )
+    )
+    # ferrite rejects a priority fee above the max fee by default, eth-account doesn't
+    transaction["maxPriorityFeePerGas"] = draw(
+        st.integers(min_value=0, max_value=transaction["maxFeePerGas"])
+    )
+    return transaction
+
+
+transactions = st.one_of(
+    legacy_transactions, access_list_transactions, dynamic_fee_transactions()
+)
+
+# uint values are kept within 64 bits and signed integer types are left out: ferrite
+# passes typed data to Rust as JSON, where larger numbers and negative integers are
+# not yet accepted.
+ORDER_TYPES = {
+    "EIP712Domain": [
+        {"name": "name", "type": "string"},
+        {"name": "version", "type": "string"},
+        {"name": "chainId", "type": "uint256"},
+        {"name": "verifyingContract", "type": "address"},
+    ],
+    "Party": [
+        {"name": "wallet", "type": "address"},
+        {"name": "label", "type": "string"},
+    ],
+    "Order": [
+        {"name": "maker", "type": "Party"},
+        {"name": "takers", "type": "Party[]"},
+        {"name": "amount", "type": "uint256"},
+        {"name": "amounts", "type": "uint64[]"},
+        {"name": "salt", "type": "bytes32"},
+        {"name": "payload", "type": "bytes"},
+        {"name": "partial", "type": "bool"},
+    ],
+}
+
+parties = st.fixed_dictionaries({"wallet": addresses, "label": texts(max_size=32)})
+
+typed_data = st.fixed_dictionaries(
+    {
+        "types": st.just(ORDER_TYPES),
+        "primaryType": st.just("Order"),
+        "domain": st.fixed_dictionaries(
+            {
+                "name": texts(max_size=32),
+                "version": texts(max_size=8),
+                "chainId": chain_ids,
+                "verifyingContract": addresses,
+            }
+        ),
+        "message": st.fixed_dictionaries(
+            {
+                "maker": parties,
+                "takers": st.lists(parties, max_size=3),
+                "amount": uint(64),
+                "amounts": st.lists(uint(64), max_size=4),
+                "salt": hex_bytes(32, 32),
+                "payload": hex_bytes(max_size=128),
+                "partial": st.booleans(),
+            }
+        ),
+    }
+)
+
+
+@given(private_key=private_keys, message=messages)
+def test_messages_match_eth_account(private_key, message):
+    """Test that random messages sign identically."""
+    expected, signed = both(lambda: Account.sign_message(message, private_key))
+
+    assert signed.signature == expected.signature
+
+
+@given(private_key=private_keys, transaction=transactions)
+def test_transactions_match_eth_account(private_key, transaction):
+    """Test that random transactions of every type sign identically."""
+    expected, signed = both(lambda: Account.sign_transaction(transaction, private_key))
+
+    assert signed.raw_transaction == expected.raw_transaction
+    assert signed.hash == expected.hash
+    assert (signed.r, signed.s, signed.v) == (expected.r, expected.s, expected.v)
+
+
+@given(private_key=private_keys, full_message=typed_data)
+def test_typed_data_matches_eth_account(private_key, full_message):
+    """Test that random EIP-712 typed data signs identically."""
+    expected, signed = both(
+        lambda: Account.sign_typed_data(private_key, full_message=full_message)
+    )
+
+    assert signed.signature == expected.signature
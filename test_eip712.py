@@ -91,3 +91,21 @@ def test_correctness(private_key):
     expected_address = account.address
 
     assert recovered_address == expected_address
+
+
+def test_sign_typed_data_batch(private_key):
+    """Test that batch signing matches signing each message individually."""
+    messages = [
+        dict(EIP712_EXAMPLE["message"], contents=f"Hello, Bob #{i}!") for i in range(8)
+    ]
+
+    signed = ferrite.sign_typed_data_batch(
+        EIP712_EXAMPLE["domain"], EIP712_EXAMPLE["types"], messages, private_key
+    )
+
+    assert len(signed) == len(messages)
+    for message, batch_signed in zip(messages, signed):
+        single = ferrite.sign_typed_data(
+            dict(EIP712_EXAMPLE, message=message), private_key
+        )
+        assert batch_signed.signature == single.signature
@@ -6,9 +6,28 @@ This module provides simple tests to verify that Ferrite's signing
 operations work correctly and produce valid signatures.
 """
 
+import base64
+import ctypes
+from concurrent.futures import ThreadPoolExecutor
+from decimal import Decimal
+from http.server import BaseHTTPRequestHandler, HTTPServer
+import json
+import logging
+import os
+import shutil
+import socket
+import struct
+import subprocess
+import sys
+import threading
+import time
+import urllib.error
+import urllib.request
+
 import pytest
 from eth_account import Account
 from eth_account.messages import encode_defunct
+import _ferrite
 import ferrite
 
 
@@ -87,3 +106,3319 @@ def test_private_key_formats():
 
     assert sig1.signature is not None
     assert sig2.signature is not None
+
+
+def test_key_cache_clear(private_key):
+    """Test that clearing the key cache does not change signatures."""
+    message = encode_defunct(text="Cache test")
+
+    sig1 = Account.sign_message(message, private_key)
+    ferrite.clear_key_cache()
+    sig2 = Account.sign_message(message, private_key)
+
+    assert sig1.signature == sig2.signature
+
+
+def test_key_cache_eviction_and_locking():
+    """Test that keys evicted from the locked key cache still sign correctly."""
+    keys = ["0x" + os.urandom(32).hex() for _ in range(100)]
+    message = encode_defunct(text="Eviction test")
+
+    first = [Account.sign_message(message, key).signature for key in keys]
+    second = [Account.sign_message(message, key).signature for key in keys]
+
+    assert first == second
+    assert isinstance(ferrite.key_cache_locked(), bool)
+
+
+def test_batch_hash_signing(private_key):
+    """Test that batch signing matches signing each hash individually."""
+    message_hashes = [bytes([i]) * 32 for i in range(16)]
+    signed = ferrite.sign_hashes(message_hashes, private_key)
+
+    assert len(signed) == len(message_hashes)
+    for message_hash, batch_signed in zip(message_hashes, signed):
+        single = Account._sign_hash(message_hash, private_key)
+        assert batch_signed.signature == single.signature
+
+
+def test_signature_output_buffer(private_key):
+    """Test writing the signature in place into a caller-provided buffer."""
+    key_bytes = bytes.fromhex(private_key[2:])
+    message_hash = b"\x01" * 32
+    buffer = bytearray(128)
+
+    result = _ferrite.sign_hash(message_hash, key_bytes, signature_out=buffer)
+    expected = _ferrite.sign_hash(message_hash, key_bytes)
+
+    assert result["signature"] is buffer
+    assert bytes(buffer[:65]) == expected["signature"]
+    assert len(buffer) == 128
+
+    view = memoryview(bytearray(65))
+    result = _ferrite.sign_hash(message_hash, key_bytes, signature_out=view)
+    assert result["signature"] is view
+    assert bytes(view) == expected["signature"]
+
+    with pytest.raises(ValueError, match="65"):
+        _ferrite.sign_hash(message_hash, key_bytes, signature_out=bytearray(64))
+    with pytest.raises(TypeError, match="writable"):
+        _ferrite.sign_hash(message_hash, key_bytes, signature_out=bytes(65))
+    with pytest.raises(TypeError, match="contiguous"):
+        strided = memoryview(bytearray(130))[::2]
+        _ferrite.sign_hash(message_hash, key_bytes, signature_out=strided)
+
+
+def test_async_signing(private_key):
+    """Test that async signing matches the synchronous result."""
+    import asyncio
+
+    message = encode_defunct(text="Async test")
+    expected = Account.sign_message(message, private_key)
+
+    signed = asyncio.run(ferrite.sign_message_async(message, private_key))
+
+    assert signed.signature == expected.signature
+
+
+def test_keystore_round_trip(private_key):
+    """Test that keystores round-trip and stay compatible with eth-account."""
+    keyfile = Account.encrypt(private_key, "password", kdf="pbkdf2", iterations=1000)
+
+    assert Account.decrypt(keyfile, "password") == bytes.fromhex(private_key[2:])
+    assert ferrite.decrypt_keystores([keyfile, keyfile], "password") == [
+        bytes.fromhex(private_key[2:])
+    ] * 2
+
+    with pytest.raises(ValueError):
+        Account.decrypt(keyfile, "wrong password")
+
+    # Untrusted KDF parameters are bounded before anything is allocated
+    for field, value in (("n", 2**30), ("r", 2**20)):
+        crafted = Account.encrypt(private_key, "password", kdf="scrypt", iterations=2)
+        crafted["crypto"]["kdfparams"][field] = value
+        with pytest.raises(ferrite.FerriteError, match="limit"):
+            ferrite.decrypt_keystores([crafted], "password")
+    keyfile["crypto"]["kdfparams"]["c"] = 2**31
+    with pytest.raises(ferrite.FerriteError, match="limit"):
+        ferrite.decrypt_keystores([keyfile], "password")
+
+
+def test_batch_address_derivation(private_key):
+    """Test batch address derivation from keys and from a mnemonic."""
+    keys = [private_key, "0x" + "0" * 63 + "2"]
+    expected = [Account.from_key(key).address for key in keys]
+
+    assert ferrite.addresses_from_keys(keys) == expected
+
+    mnemonic = "test test test test test test test test test test test junk"
+    assert ferrite.addresses_from_mnemonic(mnemonic, count=2) == [
+        "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+    ]
+
+
+def test_transaction_precomputed_sighash(private_key):
+    """Test that signing a precomputed sighash yields the same raw transaction."""
+    from eth_account._utils.legacy_transactions import (
+        serializable_unsigned_transaction_from_dict,
+    )
+
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    sighash = serializable_unsigned_transaction_from_dict(transaction).hash()
+    payload = json.dumps(
+        {
+            key: hex(value) if isinstance(value, int) else value
+            for key, value in transaction.items()
+        }
+    )
+    key_bytes = bytes.fromhex(private_key[2:])
+
+    expected = _ferrite.sign_transaction(payload, key_bytes)
+    signed = _ferrite.sign_transaction(payload, key_bytes, sighash=sighash)
+
+    assert signed["rawTransaction"] == expected["rawTransaction"]
+
+
+@pytest.mark.parametrize(
+    "transaction",
+    [
+        {"to": "0x12"},
+        {"data": "0x123"},
+        {"value": "0x" + "f" * 65},
+        {"chainId": "0xffffffffffffffff"},
+    ],
+)
+def test_malformed_transaction_raises_value_error(private_key, transaction):
+    """Test that malformed transactions raise ValueError instead of panicking."""
+    with pytest.raises(ValueError):
+        _ferrite.sign_transaction(json.dumps(transaction), bytes.fromhex(private_key[2:]))
+
+
+def test_exception_hierarchy(private_key):
+    """Test that failures raise specific ferrite exceptions."""
+    key_bytes = bytes.fromhex(private_key[2:])
+
+    with pytest.raises(ferrite.InvalidKeyError):
+        _ferrite.sign_hash(b"\x01" * 32, b"\x00" * 32)
+    with pytest.raises(ferrite.InvalidTransactionError):
+        _ferrite.sign_transaction(json.dumps({"to": "0x12"}), key_bytes)
+    with pytest.raises(ferrite.TypedDataError):
+        _ferrite.sign_typed_data("{}", key_bytes)
+
+    assert issubclass(ferrite.FerriteError, ValueError)
+    assert issubclass(ferrite.SigningError, ferrite.FerriteError)
+
+
+def test_recipient_checksum_validation(private_key):
+    """Test that recipient addresses are validated against their EIP-55 checksum."""
+    key_bytes = bytes.fromhex(private_key[2:])
+    checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+
+    def sign(to, **kwargs):
+        return _ferrite.sign_transaction(json.dumps({"to": to}), key_bytes, **kwargs)
+
+    assert sign(checksummed) == sign(checksummed.lower())
+    assert sign(checksummed, require_checksum=True) == sign(checksummed)
+
+    with pytest.raises(ferrite.InvalidTransactionError, match="`to`"):
+        sign(checksummed.replace("aA", "AA"))
+    with pytest.raises(ferrite.InvalidTransactionError, match="`to`"):
+        sign(checksummed.lower(), require_checksum=True)
+
+
+@pytest.mark.parametrize(
+    "data, message",
+    [
+        ("0x123", "odd number of hex digits"),
+        ("0x12zz", "non-hex character 'z' at offset 4"),
+        ("0x" + "00" * (128 * 1024 + 1), "byte limit"),
+    ],
+)
+def test_malformed_data_errors(private_key, data, message):
+    """Test that malformed calldata is rejected with a precise error."""
+    with pytest.raises(ferrite.InvalidTransactionError, match=message):
+        _ferrite.sign_transaction(
+            json.dumps({"data": data}), bytes.fromhex(private_key[2:])
+        )
+
+
+@pytest.mark.parametrize(
+    "transaction, message",
+    [
+        ({"nonce": hex(2**64)}, "`nonce`: .* exceeds the maximum of 2\\^64 - 1"),
+        ({"value": hex(2**256)}, "`value`: .* exceeds the maximum of 2\\^256 - 1"),
+        ({"gas": "-0x1"}, "`gas`: must not be negative"),
+        ({"chainId": "abc"}, "`chainId`: \"abc\" is not an integer"),
+    ],
+)
+def test_numeric_field_ranges(private_key, transaction, message):
+    """Test that out-of-range numeric fields name the field and its limit."""
+    with pytest.raises(ferrite.InvalidTransactionError, match=message):
+        _ferrite.sign_transaction(
+            json.dumps(transaction), bytes.fromhex(private_key[2:])
+        )
+
+
+@pytest.mark.parametrize(
+    "key_bytes",
+    [
+        # The secp256k1 group order, which is not a valid private key
+        bytes.fromhex(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141"
+        ),
+        b"\xab" * 31,
+    ],
+)
+def test_errors_never_contain_key_material(key_bytes):
+    """Test that exception text and tracebacks never include the private key."""
+    import traceback
+
+    with pytest.raises(ferrite.InvalidKeyError) as excinfo:
+        _ferrite.sign_hash(b"\x01" * 32, key_bytes)
+
+    rendered = "".join(traceback.format_exception(excinfo.value)) + repr(excinfo.value)
+    for form in (key_bytes.hex(), key_bytes.hex().upper(), str(list(key_bytes))):
+        assert form not in rendered
+
+
+def test_transaction_errors_list_every_field(private_key):
+    """Test that one exception reports every invalid field of a transaction."""
+    transaction = {"to": "0x12", "nonce": -1, "gas": "zz", "data": "0xabc"}
+    with pytest.raises(ferrite.InvalidTransactionError) as excinfo:
+        _ferrite.sign_transaction(
+            json.dumps(transaction), bytes.fromhex(private_key[2:])
+        )
+
+    message = str(excinfo.value)
+    assert message.startswith("4 invalid fields")
+    for name in transaction:
+        assert f"`{name}`" in message
+
+
+def test_priority_fee_above_max_fee(private_key):
+    """Test that a priority fee above the max fee is rejected unless overridden."""
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 0,
+        "gas": 21000,
+        "maxFeePerGas": 1_000_000_000,
+        "maxPriorityFeePerGas": 2_000_000_000,
+        "nonce": 0,
+        "chainId": 1,
+    }
+
+    with pytest.raises(ferrite.InvalidTransactionError, match="maxPriorityFeePerGas"):
+        Account.sign_transaction(transaction, private_key)
+
+    signed = Account.sign_transaction(transaction, private_key, check_fee_cap=False)
+    assert signed.raw_transaction[0] == 2
+
+
+@pytest.mark.parametrize("chain_id", ["absent", None, 0, "0x0", 1, 1337])
+def test_legacy_chain_id_matches_eth_account(private_key, chain_id):
+    """Test that legacy transactions handle a missing, null or zero chainId like eth-account."""
+    from ferrite.account import patch_eth_account, unpatch_eth_account
+
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "nonce": 0,
+    }
+    if chain_id != "absent":
+        transaction["chainId"] = chain_id
+
+    unpatch_eth_account()
+    try:
+        expected = Account.sign_transaction(transaction, private_key)
+    finally:
+        patch_eth_account()
+    signed = Account.sign_transaction(transaction, private_key)
+
+    assert signed.raw_transaction == expected.raw_transaction
+    assert signed.v == expected.v
+
+
+def test_signatures_are_low_s(private_key):
+    """Test that every signing path emits canonical low-s signatures."""
+    half_order = (
+        0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141 // 2
+    )
+    key_bytes = bytes.fromhex(private_key[2:])
+    hashes = [os.urandom(32) for _ in range(256)]
+
+    signatures = _ferrite.sign_hashes(hashes, key_bytes)
+    signatures.append(_ferrite.sign_hash(hashes[0], key_bytes))
+    for signature in signatures:
+        assert int.from_bytes(signature["s"], "big") <= half_order
+
+    for nonce in range(64):
+        signed = Account.sign_transaction(
+            {
+                "to": "0x" + "11" * 20,
+                "value": 0,
+                "gas": 21000,
+                "gasPrice": 10**9,
+                "nonce": nonce,
+                "chainId": 1,
+            },
+            private_key,
+        )
+        assert signed.s <= half_order
+
+
+@pytest.mark.parametrize(
+    "field, value, message",
+    [
+        ("value", Decimal(1), "'value' must be an integer"),
+        ("gas", 21000.0, "'gas' must be an integer"),
+        ("data", object(), "'data' has unsupported type object"),
+        (1, 2, "keys must be strings, got 1"),
+    ],
+)
+def test_unexpected_transaction_types_raise_type_error(
+    private_key, field, value, message
+):
+    """Test that unsupported keys and values raise a TypeError naming the key."""
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 0,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    transaction[field] = value
+
+    with pytest.raises(TypeError, match=message):
+        Account.sign_transaction(transaction, private_key)
+
+
+def test_signature_dict_shape_is_consistent(private_key):
+    """Test that every signing entry point returns the same signature fields."""
+    key_bytes = bytes.fromhex(private_key[2:])
+    signature_fields = {"r", "s", "v", "y_parity", "signature"}
+
+    signed_hash = _ferrite.sign_hash(b"\x01" * 32, key_bytes)
+    assert set(signed_hash) == signature_fields
+    assert set(_ferrite.sign_hashes([b"\x01" * 32], key_bytes)[0]) == signature_fields
+
+    signed = _ferrite.sign_transaction(
+        json.dumps({"to": "0x" + "11" * 20, "gas": 21000, "gasPrice": 1, "chainId": 5}),
+        key_bytes,
+    )
+    assert set(signed) == signature_fields | {"rawTransaction", "hash"}
+    assert signed["y_parity"] == (signed["v"] - 35) % 2
+    assert signed["signature"] == signed["r"] + signed["s"] + bytes([27 + signed["y_parity"]])
+
+
+def test_extra_entropy_hedges_nonces(private_key):
+    """Test that extra entropy changes the signature but not the signer."""
+    key_bytes = bytes.fromhex(private_key[2:])
+    message_hash = b"\x01" * 32
+    deterministic = _ferrite.sign_hash(message_hash, key_bytes)
+    hedged = _ferrite.sign_hash(message_hash, key_bytes, extra_entropy=os.urandom(32))
+
+    assert hedged["signature"] != deterministic["signature"]
+    assert Account._recover_hash(message_hash, signature=hedged["signature"]) == (
+        Account.from_key(private_key).address
+    )
+
+    with pytest.raises(ferrite.FerriteError, match="32 bytes"):
+        _ferrite.sign_hash(message_hash, key_bytes, extra_entropy=b"\x00" * 16)
+
+
+def test_timing_harness_runs():
+    """Test that every timing check runs and yields a finite statistic."""
+    import math
+
+    from ferrite import timing
+
+    results = timing.run(samples=200)
+
+    assert set(results) == {name for name, _ in timing.CHECKS}
+    assert all(math.isfinite(t) for t in results.values())
+
+
+def test_signing_policy_is_enforced(private_key):
+    """Test that transactions outside the signing policy are refused."""
+    allowed = "0x" + "11" * 20
+    transaction = {
+        "to": allowed,
+        "value": 10**18,
+        "gas": 100_000,
+        "maxFeePerGas": 10**9,
+        "maxPriorityFeePerGas": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+        "data": "0xa9059cbb" + "00" * 64,
+    }
+    ferrite.install()
+    ferrite.set_signing_policy(
+        {
+            "maxValue": 10**18,
+            "maxFee": 10**14,
+            "chainIds": [1],
+            "allowTo": [allowed],
+            "selectors": ["0xa9059cbb"],
+        }
+    )
+    try:
+        assert Account.sign_transaction(transaction, private_key).raw_transaction
+
+        outside = {
+            **transaction,
+            "value": 10**18 + 1,
+            "chainId": 5,
+            "to": "0x" + "22" * 20,
+            "data": "0x095ea7b3",
+        }
+        with pytest.raises(ferrite.PolicyViolationError) as excinfo:
+            Account.sign_transaction(outside, private_key)
+        for reason in ("`value`", "chainId 5", "recipient", "selector 0x095ea7b3"):
+            assert reason in str(excinfo.value)
+
+        with pytest.raises(ferrite.FerriteError, match="unknown limit"):
+            ferrite.set_signing_policy({"maxValu": 0})
+    finally:
+        ferrite.set_signing_policy(None)
+
+    assert not ferrite.signing_policy_locked()
+
+
+_LOCKED_POLICY_SCRIPT = """
+import json, sys
+import _ferrite, ferrite
+from eth_account import Account
+from eth_account.messages import encode_defunct
+
+key = bytes.fromhex(sys.argv[1][2:])
+ferrite.install()
+ferrite.set_signing_policy({"chainIds": [1]}, lock=True)
+
+# A transaction the policy forbids, hashed outside of sign_transaction
+tx = {"to": "0x" + "11" * 20, "value": 0, "gas": 21000, "gasPrice": 1, "nonce": 0}
+unsigned = _ferrite.encode_unsigned_transaction(json.dumps({**tx, "chainId": "0x5"}))
+user_op = {
+    "sender": "0x" + "22" * 20,
+    "nonce": 0,
+    "initCode": "0x",
+    "callData": "0x",
+    "callGasLimit": 100000,
+    "verificationGasLimit": 100000,
+    "preVerificationGas": 50000,
+    "maxFeePerGas": 10**9,
+    "maxPriorityFeePerGas": 10**9,
+    "paymasterAndData": "0x",
+    "signature": "0x",
+}
+refused = []
+for sign in (
+    lambda: _ferrite.sign_hash(_ferrite.keccak(unsigned), key),
+    lambda: _ferrite.sign_hashes([_ferrite.keccak(unsigned)], key),
+    lambda: Account.unsafe_sign_hash(_ferrite.keccak(unsigned), key),
+    lambda: ferrite.sign_user_operation(user_op, "0x" + "33" * 20, 1, key),
+):
+    try:
+        sign()
+    except ferrite.PolicyViolationError:
+        refused.append(True)
+
+try:
+    ferrite.set_signing_policy({"chainIds": [1], "allowRawHashes": True})
+except ferrite.FerriteError:
+    refused.append(True)
+
+print(json.dumps({
+    "refused": len(refused),
+    "message": Account.sign_message(encode_defunct(text="hi"), key).signature.hex(),
+}))
+"""
+
+
+def test_locked_signing_policy_refuses_raw_hashes(private_key):
+    """Test that a locked policy can't be bypassed by signing a transaction's hash."""
+    # A locked policy lasts for the process, so it is set in one of its own
+    result = subprocess.run(
+        [sys.executable, "-c", _LOCKED_POLICY_SCRIPT, private_key],
+        capture_output=True,
+        text=True,
+        check=True,
+    )
+    output = json.loads(result.stdout)
+    assert output["refused"] == 5
+    message = ferrite.sign_message(encode_defunct(text="hi"), private_key)
+    assert output["message"] == message.signature.hex()
+
+    ferrite.set_signing_policy({"allowRawHashes": True})
+    try:
+        assert ferrite.sign_hashes([b"\x01" * 32], private_key)[0].signature
+    finally:
+        ferrite.set_signing_policy(None)
+
+
+def test_approval_hook_gates_signing(private_key):
+    """Test that the approval hook sees what is signed and can refuse it."""
+    summaries = []
+
+    def approve(summary):
+        summaries.append(summary)
+        return summary["transaction"]["value"] <= 10**18
+
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 10**18,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    ferrite.install()
+    ferrite.set_approval_hook(approve)
+    try:
+        signed = Account.sign_transaction(transaction, private_key)
+        with pytest.raises(ferrite.ApprovalDeniedError):
+            Account.sign_transaction({**transaction, "value": 10**18 + 1}, private_key)
+    finally:
+        ferrite.set_approval_hook(None)
+
+    summary = summaries[0]
+    assert summary["kind"] == "transaction"
+    assert summary["address"] == Account.from_key(private_key).address
+    assert summary["transaction"]["to"] == transaction["to"]
+    assert summary["transaction"]["chainId"] == 1
+    assert signed.hash != summary["hash"]
+    assert len(summaries) == 2
+
+
+def test_approval_hook_checks_supplied_sighash(private_key):
+    """Test that a supplied sighash can't sign another transaction than the hook saw."""
+    from eth_account._utils.legacy_transactions import (
+        serializable_unsigned_transaction_from_dict,
+    )
+
+    shown = {
+        "to": "0x" + "22" * 20,
+        "value": 1,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    hidden = {**shown, "to": "0x" + "33" * 20, "value": 10**18}
+    hidden_sighash = serializable_unsigned_transaction_from_dict(hidden).hash()
+    key_bytes = bytes.fromhex(private_key[2:])
+
+    def payload(transaction):
+        return json.dumps(
+            {k: hex(v) if isinstance(v, int) else v for k, v in transaction.items()}
+        )
+
+    ferrite.set_approval_hook(lambda summary: summary["transaction"]["value"] == 1)
+    try:
+        assert _ferrite.sign_transaction(payload(shown), key_bytes)["rawTransaction"]
+        with pytest.raises(ferrite.ApprovalDeniedError):
+            _ferrite.sign_transaction(payload(hidden), key_bytes)
+        with pytest.raises(ferrite.PolicyViolationError, match="sighash"):
+            _ferrite.sign_transaction(payload(shown), key_bytes, sighash=hidden_sighash)
+    finally:
+        ferrite.set_approval_hook(None)
+
+
+_SUBINTERPRETER_SCRIPT = """
+import json, sys
+import _testcapi
+import _ferrite
+
+key = bytes.fromhex(sys.argv[1][2:])
+main_hook = []
+_ferrite.set_approval_hook(lambda summary: main_hook.append(summary) or True)
+
+sub = '''
+import asyncio, json, sys
+import _ferrite
+
+key = bytes.fromhex(sys.argv[1][2:])
+hooked = []
+_ferrite.set_approval_hook(lambda summary: hooked.append(summary) or True)
+signatures = _ferrite.sign_hashes([bytes(32)] * 8, key)
+try:
+    _ferrite.sign_hash(b"short", key)
+except _ferrite.FerriteError:
+    caught = True
+
+async def sign():
+    try:
+        await _ferrite.sign_hash_async(bytes(32), bytes(32))
+    except _ferrite.InvalidKeyError:
+        return True
+
+print(json.dumps({
+    "signatures": len(signatures),
+    "hooked": len(hooked),
+    "caught": caught,
+    "async_caught": asyncio.run(sign()),
+    "class": id(_ferrite.FerriteError),
+}))
+'''
+assert _testcapi.run_in_subinterp(sub) == 0
+print(json.dumps({"hooked": len(main_hook), "class": id(_ferrite.FerriteError)}))
+"""
+
+
+def test_subinterpreters_keep_their_own_state(private_key):
+    """Test that a subinterpreter gets its own hook and exception classes."""
+    pytest.importorskip("_testcapi")
+    result = subprocess.run(
+        [sys.executable, "-c", _SUBINTERPRETER_SCRIPT, private_key],
+        capture_output=True,
+        text=True,
+        timeout=60,
+    )
+    assert result.returncode == 0, result.stderr
+    sub, main = [json.loads(line) for line in result.stdout.splitlines()]
+
+    assert sub["signatures"] == 8
+    assert sub["hooked"] == 8
+    assert sub["caught"] and sub["async_caught"]
+    assert sub["class"] != main["class"]
+    assert main["hooked"] == 0
+
+
+def test_audit_log_records_every_signature(private_key, tmp_path):
+    """Test that audit records carry the signed object and the caller's context."""
+    path = tmp_path / "audit.jsonl"
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 10**20,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    ferrite.install()
+    ferrite.set_audit_log(str(path))
+    try:
+        with ferrite.audit_context(request_id="r-1"):
+            signed = Account.sign_transaction(transaction, private_key)
+        Account.sign_message(encode_defunct(text="hi"), private_key)
+    finally:
+        ferrite.set_audit_log()
+    Account.sign_message(encode_defunct(text="unaudited"), private_key)
+
+    first, second = [json.loads(line) for line in path.read_text().splitlines()]
+    assert first["kind"] == "transaction"
+    assert first["address"] == Account.from_key(private_key).address
+    assert first["hash"] == "0x" + signed.hash.hex().removeprefix("0x")
+    assert (first["chainId"], first["to"]) == (1, transaction["to"])
+    assert first["value"] == str(10**20)
+    assert first["context"] == {"request_id": "r-1"}
+    assert second["kind"] == "hash" and second["context"] is None
+
+
+def test_rate_limit_per_key(private_key):
+    """Test that a key's rate limit refuses signatures over it, batches included."""
+    other_key = "0x" + "0" * 63 + "2"
+    address = Account.from_key(private_key).address
+    message = encode_defunct(text="limited")
+
+    ferrite.install()
+    ferrite.set_rate_limit(address, per_minute=3)
+    try:
+        for _ in range(3):
+            Account.sign_message(message, private_key)
+        with pytest.raises(ferrite.RateLimitError, match="3 signatures per minute"):
+            Account.sign_message(message, private_key)
+        with pytest.raises(ferrite.RateLimitError, match="never fit"):
+            ferrite.sign_hashes([b"\x01" * 32] * 4, private_key)
+        # Other keys are unaffected
+        Account.sign_message(message, other_key)
+    finally:
+        ferrite.set_rate_limit(address)
+
+    Account.sign_message(message, private_key)
+
+
+def test_signing_policy_domain_allowlist(private_key):
+    """Test that typed data is only signed for allowed EIP-712 domains."""
+    token = "0x" + "33" * 20
+    full_message = {
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"},
+            ],
+            "Mail": [{"name": "contents", "type": "string"}],
+        },
+        "primaryType": "Mail",
+        "domain": {"name": "Token", "chainId": 1, "verifyingContract": token},
+        "message": {"contents": "hello"},
+    }
+    ferrite.set_signing_policy(
+        {"domains": [{"name": "Token", "chainId": 1, "verifyingContract": token}]}
+    )
+    try:
+        assert ferrite.sign_typed_data(full_message, private_key).signature
+
+        other_chain = {**full_message, "domain": {**full_message["domain"], "chainId": 5}}
+        with pytest.raises(ferrite.PolicyViolationError, match="chainId 5"):
+            ferrite.sign_typed_data(other_chain, private_key)
+
+        # Nor can its digest be signed as a bare hash, locally or through a signer
+        digest = _ferrite.hash_typed_data(json.dumps(other_chain))
+        with pytest.raises(ferrite.PolicyViolationError, match="bare hash"):
+            _ferrite.sign_hash(digest, bytes.fromhex(private_key[2:]))
+
+        def sign(digest):
+            raise AssertionError("the signer must not be reached")
+
+        address = Account.from_key(private_key).address
+        with pytest.raises(ferrite.PolicyViolationError, match="bare hash"):
+            ferrite.RemoteAccount(address, sign).unsafe_sign_hash(digest)
+    finally:
+        ferrite.set_signing_policy(None)
+
+
+def test_dangerous_typed_data_guard(private_key):
+    """Test that Permit typed data warns by default and can be blocked or allowed."""
+    token = "0x" + "44" * 20
+    permit = {
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "verifyingContract", "type": "address"},
+            ],
+            "Permit": [{"name": "value", "type": "uint256"}],
+        },
+        "primaryType": "Permit",
+        "domain": {"name": "Token", "verifyingContract": token},
+        "message": {"value": 1},
+    }
+
+    with pytest.warns(ferrite.DangerousTypedDataWarning, match="Permit"):
+        ferrite.sign_typed_data(permit, private_key)
+
+    try:
+        ferrite.set_typed_data_guard("block")
+        with pytest.raises(ferrite.PolicyViolationError, match="Permit"):
+            ferrite.sign_typed_data(permit, private_key)
+        # Its digest can't be signed as a bare hash instead, but messages still can be
+        digest = _ferrite.hash_typed_data(json.dumps(permit))
+        with pytest.raises(ferrite.PolicyViolationError, match="bare hash"):
+            ferrite.sign_hashes([digest], private_key)
+        # Nor as the supplied sighash of an unrelated transaction
+        transaction = json.dumps(
+            {"to": "0x" + "11" * 20, "gas": "0x5208", "gasPrice": "0x1", "nonce": "0x0"}
+        )
+        with pytest.raises(ferrite.PolicyViolationError, match="bare hash"):
+            _ferrite.sign_transaction(
+                transaction, bytes.fromhex(private_key[2:]), sighash=digest
+            )
+        assert ferrite.sign_message(encode_defunct(text="hi"), private_key).signature
+
+        ferrite.set_typed_data_guard("block", allow_raw_hashes=True)
+        assert ferrite.sign_hashes([digest], private_key)[0].signature
+
+        ferrite.set_typed_data_guard("block", allow=[{"verifyingContract": token}])
+        assert ferrite.sign_typed_data(permit, private_key).signature
+    finally:
+        ferrite.set_typed_data_guard()
+
+
+def test_max_transaction_fee(private_key):
+    """Test that transactions whose fee exceeds the global cap are refused."""
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 0,
+        "gas": 21000,
+        "maxFeePerGas": 10**9,
+        "maxPriorityFeePerGas": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    ferrite.install()
+    ferrite.set_max_transaction_fee(21000 * 10**9)
+    try:
+        assert Account.sign_transaction(transaction, private_key).raw_transaction
+
+        fee_bomb = {**transaction, "maxFeePerGas": 10**15}
+        with pytest.raises(
+            ferrite.InvalidTransactionError, match="exceeds the maximum"
+        ):
+            Account.sign_transaction(fee_bomb, private_key)
+
+        # Blob gas counts towards the fee, 2**17 per blob
+        key_bytes = bytes.fromhex(private_key[2:])
+        blob_bomb = {
+            **transaction,
+            "maxFeePerBlobGas": 1,
+            "blobVersionedHashes": ["0x01" + "00" * 31],
+        }
+        blob_fee = "fee 21000000131072 "
+        with pytest.raises(ferrite.InvalidTransactionError, match=blob_fee):
+            _ferrite.sign_transaction(json.dumps(blob_bomb), key_bytes)
+
+        # The cap is parsed like any other quantity
+        _ferrite.set_max_transaction_fee("0X3B9ACA00")
+        with pytest.raises(ferrite.InvalidTransactionError, match="of 1000000000 "):
+            Account.sign_transaction(transaction, private_key)
+    finally:
+        ferrite.set_max_transaction_fee(None)
+
+
+def test_metrics_count_signatures_per_key(private_key):
+    """Test that signatures, denials and latency are counted per key."""
+    address = Account.from_key(private_key).address
+    ferrite.get_metrics(reset=True)
+    ferrite.sign_hashes([b"\x01" * 32, b"\x02" * 32], private_key)
+
+    ferrite.set_rate_limit(address, per_second=0)
+    try:
+        with pytest.raises(ferrite.RateLimitError):
+            ferrite.sign_hashes([b"\x03" * 32], private_key)
+    finally:
+        ferrite.set_rate_limit(address)
+
+    metrics = ferrite.get_metrics(reset=True)[address]
+    assert metrics["signatures"] == {"hash": 2, "typed_data": 0, "transaction": 0}
+    assert metrics["denials"] == 1
+    assert metrics["failures"] == 0
+    assert metrics["latency"]["count"] == 2
+    assert len(metrics["latency"]["counts"]) == len(metrics["latency"]["buckets"]) + 1
+    assert ferrite.get_metrics() == {}
+
+
+def test_keychain_password_round_trip(private_key):
+    """Test that keystore passwords can be kept in the OS keychain."""
+    keyring = pytest.importorskip("keyring")
+    from keyring.backend import KeyringBackend
+
+    class MemoryKeyring(KeyringBackend):
+        priority = 1
+        passwords = {}  # type: ignore
+
+        def get_password(self, service, username):
+            return self.passwords.get((service, username))
+
+        def set_password(self, service, username, password):
+            self.passwords[(service, username)] = password
+
+        def delete_password(self, service, username):
+            del self.passwords[(service, username)]
+
+    previous = keyring.get_keyring()
+    keyring.set_keyring(MemoryKeyring())
+    try:
+        with pytest.raises(ferrite.FerriteError, match="No password"):
+            ferrite.keychain_password("ferrite-test", "hot-wallet")
+
+        ferrite.store_keychain_password("ferrite-test", "hot-wallet", "password")
+        password = ferrite.keychain_password("ferrite-test", "hot-wallet")
+        keyfile = Account.encrypt(private_key, password, kdf="pbkdf2", iterations=1000)
+        assert ferrite.decrypt_keystores([keyfile], password) == [
+            bytes.fromhex(private_key[2:])
+        ]
+    finally:
+        keyring.set_keyring(previous)
+
+
+def test_audit_log_receipts_are_chained(private_key, tmp_path):
+    """Test that chained audit logs detect edited and dropped records."""
+    path = tmp_path / "audit.jsonl"
+    ferrite.set_audit_log(str(path), chain=True)
+    try:
+        ferrite.sign_hashes([b"\x01" * 32, b"\x02" * 32], private_key)
+        ferrite.set_audit_log(str(path), chain=True)
+        ferrite.sign_hash(b"\x03" * 32, private_key)
+    finally:
+        ferrite.set_audit_log()
+
+    lines = path.read_text().splitlines()
+    receipt = ferrite.verify_audit_log(str(path))
+    assert receipt == json.loads(lines[-1])["receipt"]
+
+    path.write_text("\n".join(lines[:1] + lines[2:]) + "\n")
+    with pytest.raises(ferrite.FerriteError, match="line 2"):
+        ferrite.verify_audit_log(str(path))
+
+    path.write_text("\n".join([lines[0].replace("hash", "hash ", 1)] + lines[1:]))
+    with pytest.raises(ferrite.FerriteError, match="line 1"):
+        ferrite.verify_audit_log(str(path))
+
+
+def test_audit_callback_receives_chained_records_in_order(private_key):
+    """Test that chained records reach the callback in the order they link."""
+    records = []
+
+    def callback(record):
+        # Gives up the GIL mid-delivery, letting other threads sign in between
+        time.sleep(0.001)
+        records.append(record)
+
+    def sign(i):
+        return ferrite.sign_hash(bytes([i]) * 32, private_key)
+
+    ferrite.set_audit_log(callback=callback, chain=True)
+    try:
+        with ThreadPoolExecutor(8) as pool:
+            list(pool.map(sign, range(1, 65)))
+    finally:
+        ferrite.set_audit_log()
+
+    assert len(records) == 64
+    previous = "0x" + "00" * 32
+    for record in records:
+        assert record["previous"] == previous
+        previous = record["receipt"]
+
+    ferrite.set_audit_log(callback=lambda record: sign(1))
+    try:
+        with pytest.raises(ferrite.FerriteError, match="callbacks can't sign"):
+            sign(2)
+    finally:
+        ferrite.set_audit_log()
+
+
+_SECP256K1_ORDER = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+_SECP256K1_SPKI = bytes.fromhex("3056301006072a8648ce3d020106052b8104000a034200")
+
+
+def _der_signature(r, s):
+    """DER-encodes an ECDSA signature, as KMS and HSM SDKs return it."""
+
+    def integer(value):
+        encoded = value.to_bytes(33, "big").lstrip(b"\x00")
+        if encoded[0] & 0x80:
+            encoded = b"\x00" + encoded
+        return b"\x02" + bytes([len(encoded)]) + encoded
+
+    body = integer(r) + integer(s)
+    return b"\x30" + bytes([len(body)]) + body
+
+
+def test_aws_kms_account_signs_like_a_local_key(private_key):
+    """Test that KMS signatures are normalized and match local signatures."""
+    from eth_keys import keys
+
+    key_bytes = bytes.fromhex(private_key[2:])
+    public_key = keys.PrivateKey(key_bytes).public_key.to_bytes()
+
+    class FakeKms:
+        def get_public_key(self, KeyId):
+            return {"PublicKey": _SECP256K1_SPKI + b"\x04" + public_key}
+
+        def sign(self, KeyId, Message, MessageType, SigningAlgorithm):
+            assert MessageType == "DIGEST"
+            signature = _ferrite.sign_hash(Message, key_bytes)
+            r = int.from_bytes(signature["r"], "big")
+            s = int.from_bytes(signature["s"], "big")
+            # KMS may return either `s`; give the high one
+            return {"Signature": _der_signature(r, _SECP256K1_ORDER - s)}
+
+    account = ferrite.aws_kms_account("alias/hot-wallet", client=FakeKms())
+    local = Account.from_key(private_key)
+    assert account.address == local.address
+
+    message = encode_defunct(text="remote")
+    assert account.sign_message(message) == local.sign_message(message)
+
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "gas": 21000,
+        "maxFeePerGas": 10**9,
+        "maxPriorityFeePerGas": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    assert (
+        account.sign_transaction(transaction).raw_transaction
+        == local.sign_transaction(transaction).raw_transaction
+    )
+
+    def sign(digest):
+        return FakeKms().sign("", digest, "DIGEST", "ECDSA_SHA_256")["Signature"]
+
+    impostor = ferrite.RemoteAccount("0x" + "00" * 20, sign)
+    with pytest.raises(ferrite.SigningError, match="not by"):
+        impostor.unsafe_sign_hash(b"\x01" * 32)
+
+
+def test_gcp_kms_account_signs_asynchronously(private_key):
+    """Test that Cloud KMS accounts sign from asyncio code."""
+    import asyncio
+    from types import SimpleNamespace
+
+    from eth_keys import keys
+
+    key_bytes = bytes.fromhex(private_key[2:])
+    public_key = keys.PrivateKey(key_bytes).public_key.to_bytes()
+    spki = base64.b64encode(_SECP256K1_SPKI + b"\x04" + public_key).decode()
+
+    class FakeCloudKms:
+        def get_public_key(self, request):
+            pem = f"-----BEGIN PUBLIC KEY-----\n{spki}\n-----END PUBLIC KEY-----\n"
+            return SimpleNamespace(pem=pem)
+
+        def asymmetric_sign(self, request):
+            signature = _ferrite.sign_hash(request["digest"]["sha256"], key_bytes)
+            r = int.from_bytes(signature["r"], "big")
+            s = int.from_bytes(signature["s"], "big")
+            return SimpleNamespace(signature=_der_signature(r, s))
+
+    name = "projects/p/locations/global/keyRings/r/cryptoKeys/k/cryptoKeyVersions/1"
+    account = ferrite.gcp_kms_account(name, client=FakeCloudKms())
+    local = Account.from_key(private_key)
+    assert account.address == local.address
+
+    message = encode_defunct(text="remote")
+    signed = asyncio.run(account.sign_message_async(message))
+    assert signed == local.sign_message(message)
+
+
+def test_azure_key_vault_account_signs_with_raw_signatures(private_key):
+    """Test that Key Vault's raw `r || s` signatures are accepted."""
+    from types import SimpleNamespace
+
+    from eth_keys import keys
+
+    key_bytes = bytes.fromhex(private_key[2:])
+    public_key = keys.PrivateKey(key_bytes).public_key.to_bytes()
+
+    class FakeCryptographyClient:
+        def sign(self, algorithm, digest):
+            assert algorithm == "ES256K"
+            signature = _ferrite.sign_hash(digest, key_bytes)["signature"]
+            return SimpleNamespace(signature=bytes(signature[:64]))
+
+    class FakeKeyClient:
+        def get_key(self, name, version=None):
+            return SimpleNamespace(
+                key=SimpleNamespace(x=public_key[:32], y=public_key[32:]),
+                properties=SimpleNamespace(version="1"),
+            )
+
+        def get_cryptography_client(self, name, key_version=None):
+            assert key_version == "1"
+            return FakeCryptographyClient()
+
+    account = ferrite.azure_key_vault_account(
+        "https://vault.vault.azure.net", "hot-wallet", client=FakeKeyClient()
+    )
+    local = Account.from_key(private_key)
+    assert account.address == local.address
+
+    message = encode_defunct(text="remote")
+    assert account.sign_message(message) == local.sign_message(message)
+
+
+def test_yubihsm_account_selects_keys_by_label(private_key):
+    """Test that YubiHSM keys are found by label and sign prehashed digests."""
+    pytest.importorskip("yubihsm")
+    from cryptography.hazmat.primitives.asymmetric import ec
+    from cryptography.hazmat.primitives.asymmetric.utils import Prehashed
+    from eth_keys import keys
+
+    key_bytes = bytes.fromhex(private_key[2:])
+    public_key = keys.PrivateKey(key_bytes).public_key.to_bytes()
+
+    class FakeKey:
+        def get_public_key(self):
+            return ec.EllipticCurvePublicKey.from_encoded_point(
+                ec.SECP256K1(), b"\x04" + public_key
+            )
+
+        def sign_ecdsa(self, data, hash):
+            assert isinstance(hash, Prehashed)
+            signature = _ferrite.sign_hash(data, key_bytes)
+            r = int.from_bytes(signature["r"], "big")
+            s = int.from_bytes(signature["s"], "big")
+            return _der_signature(r, s)
+
+    class FakeSession:
+        def list_objects(self, object_type, label):
+            return [FakeKey()] if label == "hot-wallet" else []
+
+    with pytest.raises(ValueError, match="found 0"):
+        ferrite.yubihsm_account(FakeSession(), label="cold-wallet")
+
+    account = ferrite.yubihsm_account(FakeSession(), label="hot-wallet")
+    local = Account.from_key(private_key)
+    assert account.address == local.address
+
+    message = encode_defunct(text="remote")
+    assert account.sign_message(message) == local.sign_message(message)
+
+
+def test_pkcs11_account_reads_der_wrapped_points(private_key):
+    """Test that PKCS#11 keys are found by label and their EC points unwrapped."""
+    pytest.importorskip("pkcs11")
+    from pkcs11 import Attribute, Mechanism, ObjectClass
+    from eth_keys import keys
+
+    key_bytes = bytes.fromhex(private_key[2:])
+    public_key = keys.PrivateKey(key_bytes).public_key.to_bytes()
+
+    class FakePrivateKey:
+        def sign(self, data, mechanism):
+            assert mechanism == Mechanism.ECDSA
+            return bytes(_ferrite.sign_hash(data, key_bytes)["signature"][:64])
+
+    class FakeSession:
+        def get_key(self, object_class, key_type, label):
+            assert label == "hot-wallet"
+            if object_class == ObjectClass.PRIVATE_KEY:
+                return FakePrivateKey()
+            return {Attribute.EC_POINT: b"\x04\x41\x04" + public_key}
+
+    account = ferrite.pkcs11_account(FakeSession(), "hot-wallet")
+    local = Account.from_key(private_key)
+    assert account.address == local.address
+
+    message = encode_defunct(text="remote")
+    assert account.sign_message(message) == local.sign_message(message)
+
+
+def test_web3signer_account_sends_messages_not_digests(private_key):
+    """Test that Web3Signer is sent the message and signs its keccak256."""
+    from types import SimpleNamespace
+
+    from eth_keys import keys
+    from eth_utils import keccak
+
+    key_bytes = bytes.fromhex(private_key[2:])
+    public_key = "0x" + keys.PrivateKey(key_bytes).public_key.to_bytes().hex()
+
+    class FakeSession:
+        def request(self, method, url, json=None, timeout=None):
+            if method == "GET":
+                assert url == "http://signer/api/v1/eth1/publicKeys"
+                content = f'["{public_key}"]'.encode()
+            else:
+                assert url == f"http://signer/api/v1/eth1/sign/{public_key}"
+                digest = keccak(bytes.fromhex(json["data"][2:]))
+                signature = _ferrite.sign_hash(digest, key_bytes)["signature"]
+                content = ("0x" + bytes(signature).hex()).encode()
+            return SimpleNamespace(content=content, raise_for_status=lambda: None)
+
+    local = Account.from_key(private_key)
+    account = ferrite.web3signer_account(
+        "http://signer/", local.address, session=FakeSession()
+    )
+
+    message = encode_defunct(text="remote")
+    assert account.sign_message(message) == local.sign_message(message)
+
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    assert (
+        account.sign_transaction(transaction).raw_transaction
+        == local.sign_transaction(transaction).raw_transaction
+    )
+
+    with pytest.raises(ferrite.SigningError, match="hashes messages itself"):
+        account.unsafe_sign_hash(b"\x01" * 32)
+
+
+def test_ledger_account_speaks_the_ethereum_app_protocol(private_key):
+    """Test that a Ledger is sent chunked APDUs and signs what its app would hash."""
+    from eth_keys import keys
+    from eth_utils import keccak
+
+    key_bytes = bytes.fromhex(private_key[2:])
+    public_key = b"\x04" + keys.PrivateKey(key_bytes).public_key.to_bytes()
+
+    class FakeDongle:
+        """Answers like the Ethereum app, without asking anyone to confirm."""
+
+        def __init__(self):
+            self.data = b""
+            self.apdus = []
+
+        def exchange(self, apdu):
+            assert apdu[0] == 0xE0 and apdu[4] == len(apdu) - 5 <= 255
+            self.apdus.append(apdu.hex())
+            instruction, p1, data = apdu[1], apdu[2], apdu[5:]
+            if instruction == 0x02:
+                return bytes([65]) + public_key + bytes([40]) + b"0" * 40
+            self.data = data if p1 == 0x00 else self.data + data
+            payload = self.data[1 + 4 * self.data[0] :]
+            if instruction == 0x08:
+                length = int.from_bytes(payload[:4], "big")
+                payload = b"\x19Ethereum Signed Message:\n%d" % length + payload[4:]
+            elif instruction == 0x0C:
+                payload = b"\x19\x01" + payload
+            signature = _ferrite.sign_hash(keccak(payload), key_bytes)["signature"]
+            return signature[64:] + signature[:64]
+
+    dongle = FakeDongle()
+    account = ferrite.ledger_account(dongle=dongle)
+    local = Account.from_key(private_key)
+    assert account.address == local.address
+    # m/44'/60'/0'/0/0 is five big-endian indices after their count
+    path = "05" "8000002c" "8000003c" "80000000" "00000000" "00000000"
+    assert dongle.apdus == ["e0020000" "15" + path]
+
+    transaction = {
+        "to": "0x28ee52a8f3d6e5d15f8b131996950d7f296c7952",
+        "value": 0x2BD72A24874000,
+        "gas": 21000,
+        "gasPrice": 21 * 10**9,
+        "nonce": 1,
+        "chainId": 1,
+        "data": "0x",
+    }
+    dongle.apdus.clear()
+    assert (
+        account.sign_transaction(transaction).raw_transaction
+        == local.sign_transaction(transaction).raw_transaction
+    )
+    # Nonce, gas price, gas, recipient, value and data, then EIP-155's chain id, 0, 0
+    unsigned = (
+        "eb" "01" "8504e3b29200" "825208" "9428ee52a8f3d6e5d15f8b131996950d7f296c7952"
+        "872bd72a24874000" "80" "01" "80" "80"
+    )
+    assert dongle.apdus == ["e0040000" "41" + path + unsigned]
+
+    # 21 path bytes and 239 transaction bytes, so a full chunk and a P1 = 0x80 one
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "gas": 100_000,
+        "gasPrice": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+        "data": "0x" + "ab" * 200,
+    }
+    dongle.apdus.clear()
+    assert (
+        account.sign_transaction(transaction).raw_transaction
+        == local.sign_transaction(transaction).raw_transaction
+    )
+    fields = "f8ed80843b9aca00830186a094" + "11" * 20 + "01b8c8"
+    data = path + fields + "ab" * 200 + "018080"
+    assert dongle.apdus == [
+        "e0040000" "96" + data[:300],
+        "e0048000" "6e" + data[300:],
+    ]
+
+    # With 92 data bytes a 150 byte chunk would split `chainId, 0, 0`, which the
+    # app misreads, so the whole command goes in one
+    transaction["data"] = "0x" + "ab" * 92
+    dongle.apdus.clear()
+    assert (
+        account.sign_transaction(transaction).raw_transaction
+        == local.sign_transaction(transaction).raw_transaction
+    )
+    assert [int(apdu[8:10], 16) for apdu in dongle.apdus] == [152]
+
+    for text in ("remote", "12345", ""):
+        message = encode_defunct(text=text)
+        assert account.sign_message(message) == local.sign_message(message)
+
+    typed_data = {
+        "types": {
+            "EIP712Domain": [{"name": "name", "type": "string"}],
+            "Mail": [{"name": "contents", "type": "string"}],
+        },
+        "primaryType": "Mail",
+        "domain": {"name": "Ledger"},
+        "message": {"contents": "hi"},
+    }
+    assert (
+        account.sign_typed_data(typed_data).signature
+        == local.sign_typed_data(full_message=typed_data).signature
+    )
+
+    with pytest.raises(ferrite.SigningError, match="hashes messages itself"):
+        account.unsafe_sign_hash(b"\x01" * 32)
+
+
+def test_trezor_account_decodes_transactions_for_the_device(private_key, monkeypatch):
+    """Test that a Trezor is sent transaction fields, messages and whole typed data."""
+    pytest.importorskip("trezorlib")
+    from types import SimpleNamespace
+
+    from trezorlib import client as trezor_client, ethereum
+
+    local = Account.from_key(private_key)
+    uis = []
+    calls = []
+
+    def get_default_client(ui):
+        uis.append(ui)
+        return "client"
+
+    def get_address(client, n):
+        calls.append(("get_address", client, n))
+        return local.address
+
+    def sign_tx(client, n, **fields):
+        calls.append(("sign_tx", client, n))
+        transaction = {
+            "nonce": fields["nonce"],
+            "gasPrice": fields["gas_price"],
+            "gas": fields["gas_limit"],
+            "to": fields["to"],
+            "value": fields["value"],
+            "data": fields["data"],
+            "chainId": fields["chain_id"],
+        }
+        signed = local.sign_transaction(transaction)
+        return signed.v, signed.r.to_bytes(32, "big"), signed.s.to_bytes(32, "big")
+
+    def sign_message(client, n, message):
+        calls.append(("sign_message", client, n))
+        signed = local.sign_message(encode_defunct(primitive=message))
+        return SimpleNamespace(signature=bytes(signed.signature))
+
+    def sign_typed_data(client, n, data, metamask_v4_compat):
+        calls.append(("sign_typed_data", client, n))
+        signed = local.sign_typed_data(full_message=data)
+        return SimpleNamespace(signature=bytes(signed.signature))
+
+    monkeypatch.setattr(trezor_client, "get_default_client", get_default_client)
+    monkeypatch.setattr(ethereum, "get_address", get_address)
+    monkeypatch.setattr(ethereum, "sign_tx", sign_tx)
+    monkeypatch.setattr(ethereum, "sign_message", sign_message)
+    monkeypatch.setattr(ethereum, "sign_typed_data", sign_typed_data)
+
+    with pytest.raises(ValueError, match="client's UI"):
+        ferrite.trezor_account(client=object(), passphrase="hidden")
+    account = ferrite.trezor_account("m/44'/60'/1'/0/7", passphrase="hidden")
+    assert account.address == local.address
+    # The passphrase is answered from the host, whatever the device offers
+    assert uis[0].get_passphrase(available_on_device=True) == "hidden"
+
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "nonce": 3,
+        "chainId": 137,
+        "data": "0xabcd",
+    }
+    assert (
+        account.sign_transaction(transaction).raw_transaction
+        == local.sign_transaction(transaction).raw_transaction
+    )
+
+    message = encode_defunct(text="remote")
+    assert account.sign_message(message) == local.sign_message(message)
+
+    typed_data = {
+        "types": {
+            "EIP712Domain": [{"name": "name", "type": "string"}],
+            "Mail": [{"name": "contents", "type": "string"}],
+        },
+        "primaryType": "Mail",
+        "domain": {"name": "Trezor"},
+        "message": {"contents": "hi"},
+    }
+    assert (
+        account.sign_typed_data(typed_data).signature
+        == local.sign_typed_data(full_message=typed_data).signature
+    )
+    hardened = 1 << 31
+    n = [44 | hardened, 60 | hardened, 1 | hardened, 0, 7]
+    assert calls == [
+        (call, "client", n)
+        for call in ("get_address", "sign_tx", "sign_message", "sign_typed_data")
+    ]
+
+    with pytest.raises(ferrite.SigningError, match="type 1"):
+        account.sign_transaction({**transaction, "type": 1, "accessList": []})
+
+
+def test_vault_transit_account_renews_expiring_tokens(private_key):
+    """Test that transit signatures are unmarshaled and tokens renewed near expiry."""
+    from types import SimpleNamespace
+
+    from eth_keys import keys
+
+    key_bytes = bytes.fromhex(private_key[2:])
+    public_key = keys.PrivateKey(key_bytes).public_key.to_bytes()
+    spki = base64.b64encode(_SECP256K1_SPKI + b"\x04" + public_key).decode()
+    renewals = []
+
+    class FakeTransit:
+        def read_key(self, name, mount_point):
+            assert (name, mount_point) == ("hot-wallet", "transit")
+            pem = f"-----BEGIN PUBLIC KEY-----\n{spki}\n-----END PUBLIC KEY-----\n"
+            return {"data": {"latest_version": 2, "keys": {"2": {"public_key": pem}}}}
+
+        def sign_data(self, name, hash_input, key_version, prehashed, **kwargs):
+            assert key_version == 2 and prehashed
+            assert kwargs["marshaling_algorithm"] == "asn1"
+            signature = _ferrite.sign_hash(base64.b64decode(hash_input), key_bytes)
+            r = int.from_bytes(signature["r"], "big")
+            s = int.from_bytes(signature["s"], "big")
+            der = base64.b64encode(_der_signature(r, s)).decode()
+            return {"data": {"signature": f"vault:v2:{der}"}}
+
+    def renew_self():
+        renewals.append(True)
+        return {"auth": {"lease_duration": 3600}}
+
+    token = SimpleNamespace(
+        lookup_self=lambda: {"data": {"renewable": True, "ttl": 60}},
+        renew_self=renew_self,
+    )
+    client = SimpleNamespace(
+        secrets=SimpleNamespace(transit=FakeTransit()),
+        auth=SimpleNamespace(token=token),
+    )
+
+    account = ferrite.vault_transit_account("hot-wallet", client=client)
+    local = Account.from_key(private_key)
+    assert account.address == local.address
+
+    message = encode_defunct(text="remote")
+    assert account.sign_message(message) == local.sign_message(message)
+    assert account.sign_message(message) == local.sign_message(message)
+    assert renewals == [True]
+
+
+def test_registered_signers_build_remote_accounts(private_key):
+    """Test that third-party signer factories are registered and used by name."""
+    key_bytes = bytes.fromhex(private_key[2:])
+    local = Account.from_key(private_key)
+
+    class CustodySigner:
+        def __init__(self, vault):
+            assert vault == "cold-room"
+            self.address = local.address
+
+        def sign(self, digest):
+            return bytes(_ferrite.sign_hash(digest, key_bytes)["signature"][:64])
+
+    ferrite.register_signer("custody", CustodySigner)
+    assert "custody" in ferrite.registered_signers()
+    assert "aws_kms" in ferrite.registered_signers()
+    with pytest.raises(ValueError, match="already registered"):
+        ferrite.register_signer("custody", CustodySigner)
+
+    account = ferrite.signer_account("custody", vault="cold-room")
+    message = encode_defunct(text="remote")
+    assert account.sign_message(message) == local.sign_message(message)
+
+    with pytest.raises(ValueError, match="No signer named 'missing'"):
+        ferrite.signer_account("missing")
+
+
+def test_threshold_account_combines_additive_shares(private_key):
+    """Test that partial signatures are collected, combined and checked."""
+    key_bytes = bytes.fromhex(private_key[2:])
+    local = Account.from_key(private_key)
+
+    def transport(party, digest):
+        signature = _ferrite.sign_hash(digest, key_bytes)
+        r, s = bytes(signature["r"]), int.from_bytes(signature["s"], "big")
+        share = 12345 if party == "alice" else (s - 12345) % _SECP256K1_ORDER
+        if party == "mallory":
+            share += 1
+        return r + share.to_bytes(32, "big")
+
+    account = ferrite.threshold_account(local.address, ["alice", "bob"], transport)
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    assert (
+        account.sign_transaction(transaction).raw_transaction
+        == local.sign_transaction(transaction).raw_transaction
+    )
+
+    account = ferrite.threshold_account(local.address, ["alice", "mallory"], transport)
+    with pytest.raises(ferrite.SigningError, match="is not by"):
+        account.sign_transaction(transaction)
+
+    def unreachable(party, digest):
+        raise ConnectionError("party offline")
+
+    account = ferrite.threshold_account(local.address, ["alice"], unreachable)
+    with pytest.raises(ferrite.SigningError, match="party 'alice'"):
+        account.sign_transaction(transaction)
+
+    released = threading.Event()
+
+    def hung(party, digest):
+        if party == "bob":
+            released.wait(10)
+        return transport(party, digest)
+
+    account = ferrite.threshold_account(
+        local.address, ["alice", "bob"], hung, timeout=0.2
+    )
+    started = time.monotonic()
+    try:
+        with pytest.raises(ferrite.SigningError, match="party 'bob'"):
+            account.sign_transaction(transaction)
+        assert time.monotonic() - started < 5
+    finally:
+        released.set()
+
+
+def test_qr_sign_request_round_trips_through_an_air_gapped_wallet(private_key):
+    """Test that multi-part sign requests decode and signed answers are assembled."""
+    import uuid
+
+    from eth_utils import keccak
+    from ferrite import qr
+
+    key_bytes = bytes.fromhex(private_key[2:])
+    local = Account.from_key(private_key)
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "gas": 21000,
+        "maxFeePerGas": 10**9,
+        "maxPriorityFeePerGas": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+        "data": "0x" + "ab" * 100,
+    }
+    request = ferrite.qr_sign_request(
+        local.address,
+        "m/44'/60'/0'/0/0",
+        transaction=transaction,
+        source_fingerprint=0x12345678,
+        max_fragment_length=50,
+    )
+    assert len(request.parts) > 1
+    assert request.parts[0].startswith("ur:eth-sign-request/1-")
+
+    # The wallet scans the parts in any order
+    ur_type, message = qr._decode_ur(list(reversed(request.parts)))
+    assert ur_type == "eth-sign-request"
+    sign_request, _ = qr._cbor_decode(message)
+    assert sign_request[1].value == request.request_id.bytes
+    assert sign_request[3] == 4 and sign_request[4] == 1
+    keypath = [44, True, 60, True, 0, True, 0, False, 0, False]
+    assert sign_request[5].value == {1: keypath, 2: 0x12345678}
+    assert sign_request[6] == bytes.fromhex(local.address[2:])
+
+    digest = keccak(sign_request[2])
+    signature = bytes(_ferrite.sign_hash(digest, key_bytes)["signature"])
+    answer = qr._encode_ur(
+        "eth-signature",
+        qr._cbor_encode({1: qr._Tagged(37, request.request_id.bytes), 2: signature}),
+        200,
+    )
+
+    assert ferrite.decode_qr_signature(answer[0].upper()) == (
+        request.request_id,
+        signature,
+    )
+    signed = ferrite.signed_transaction_from_qr(
+        transaction, local.address, answer, request_id=request.request_id
+    )
+    assert signed.raw_transaction == local.sign_transaction(transaction).raw_transaction
+
+    with pytest.raises(ValueError, match="not"):
+        ferrite.signed_transaction_from_qr(
+            transaction, local.address, answer, request_id=uuid.uuid4()
+        )
+    with pytest.raises(ValueError, match="missing parts 1"):
+        qr._decode_ur(request.parts[1:])
+
+
+def test_sign_user_operation_signs_the_entry_point_hash(private_key):
+    """Test that the v0.6 userOpHash is computed and signed as a personal message."""
+    from eth_abi import encode
+    from eth_utils import keccak
+
+    entry_point = "0x5FF137D4b0FDCd49DcA30c7CF57E578a026d2789"
+    user_op = {
+        "sender": "0x" + "22" * 20,
+        "nonce": 7,
+        "initCode": "0x",
+        "callData": "0xb61d27f6" + "00" * 96,
+        "callGasLimit": 100000,
+        "verificationGasLimit": "0x186a0",
+        "preVerificationGas": 50000,
+        "maxFeePerGas": 2 * 10**9,
+        "maxPriorityFeePerGas": 10**9,
+        "paymasterAndData": b"",
+        "signature": "0x",
+    }
+
+    packed = encode(
+        ["address", "uint256", "bytes32", "bytes32"] + ["uint256"] * 5 + ["bytes32"],
+        [
+            user_op["sender"],
+            7,
+            keccak(b""),
+            keccak(hexstr=user_op["callData"]),
+            100000,
+            100000,
+            50000,
+            2 * 10**9,
+            10**9,
+            keccak(b""),
+        ],
+    )
+    expected = keccak(
+        encode(["bytes32", "address", "uint256"], [keccak(packed), entry_point, 1])
+    )
+    assert ferrite.user_operation_hash(user_op, entry_point, 1) == expected
+
+    signed = ferrite.sign_user_operation(user_op, entry_point, 1, private_key)
+    assert signed.message_hash == expected
+    local = Account.from_key(private_key)
+    personal = local.sign_message(encode_defunct(primitive=expected))
+    assert signed.signature == personal.signature
+
+    bare = ferrite.sign_user_operation(
+        user_op, entry_point, 1, private_key, eip191=False
+    )
+    assert bare.signature == local.unsafe_sign_hash(expected).signature
+
+    del user_op["callGasLimit"]
+    with pytest.raises(ferrite.InvalidTransactionError, match="Missing `callGasLimit`"):
+        ferrite.user_operation_hash(user_op, entry_point, 1)
+
+
+def test_user_operation_hash_packs_v07_operations(private_key):
+    """Test that v0.7 operations hash the same packed or unpacked, and are detected."""
+    from eth_abi import encode
+    from eth_utils import keccak
+
+    entry_point = "0x0000000071727De22E5E9d8BAf0edAc6f37da032"
+    factory, paymaster = "0x" + "33" * 20, "0x" + "44" * 20
+    unpacked = {
+        "sender": "0x" + "22" * 20,
+        "nonce": 7,
+        "factory": factory,
+        "factoryData": "0xabcd",
+        "callData": "0xb61d27f6",
+        "callGasLimit": 100000,
+        "verificationGasLimit": 200000,
+        "preVerificationGas": 50000,
+        "maxFeePerGas": 2 * 10**9,
+        "maxPriorityFeePerGas": 10**9,
+        "paymaster": paymaster,
+        "paymasterVerificationGasLimit": 30000,
+        "paymasterPostOpGasLimit": 40000,
+        "paymasterData": "0x01",
+    }
+    account_gas_limits = (200000 << 128 | 100000).to_bytes(32, "big")
+    gas_fees = (10**9 << 128 | 2 * 10**9).to_bytes(32, "big")
+    init_code = bytes.fromhex(factory[2:] + "abcd")
+    paymaster_and_data = (
+        bytes.fromhex(paymaster[2:])
+        + (30000).to_bytes(16, "big")
+        + (40000).to_bytes(16, "big")
+        + b"\x01"
+    )
+    packed = {
+        "sender": unpacked["sender"],
+        "nonce": 7,
+        "initCode": init_code,
+        "callData": "0xb61d27f6",
+        "accountGasLimits": account_gas_limits,
+        "preVerificationGas": 50000,
+        "gasFees": gas_fees,
+        "paymasterAndData": paymaster_and_data,
+    }
+
+    encoded = encode(
+        ["address", "uint256", "bytes32", "bytes32"]
+        + ["bytes32", "uint256", "bytes32", "bytes32"],
+        [
+            unpacked["sender"],
+            7,
+            keccak(init_code),
+            keccak(hexstr="0xb61d27f6"),
+            account_gas_limits,
+            50000,
+            gas_fees,
+            keccak(paymaster_and_data),
+        ],
+    )
+    expected = keccak(
+        encode(["bytes32", "address", "uint256"], [keccak(encoded), entry_point, 1])
+    )
+    assert ferrite.user_operation_hash(unpacked, entry_point, 1) == expected
+    assert ferrite.user_operation_hash(packed, entry_point, 1) == expected
+
+    # Off the canonical EntryPoint, the packed fields still identify v0.7
+    other = "0x" + "55" * 20
+    assert ferrite.user_operation_hash(packed, other, 1) == ferrite.user_operation_hash(
+        unpacked, other, 1, version="0.7"
+    )
+    with pytest.raises(ferrite.FerriteError, match="Unsupported EntryPoint version"):
+        ferrite.user_operation_hash(packed, entry_point, 1, version="0.8")
+
+
+def test_safe_tx_signatures_are_packed_by_owner(private_key):
+    """Test that SafeTx hashes match eth-account and signatures pack in owner order."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    safe = "0x" + "aa" * 20
+    tx = ferrite.safe_tx(safe, 1, "0x" + "bb" * 20, 10**20, "0xa9059cbb", nonce=3)
+    expected = encode_typed_data(full_message=tx)
+    assert ferrite.safe_tx_hash(tx) == _hash_eip191_message(expected)
+
+    owners = [Account.from_key(private_key), Account.create()]
+    signatures = [
+        ferrite.sign_safe_tx(tx, owners[0].key),
+        ferrite.sign_safe_tx(tx, owners[1]),
+    ]
+    for owner, signature in zip(owners, signatures):
+        assert signature.owner == owner.address
+        assert signature.signature == owner.sign_message(expected).signature
+
+    packed = ferrite.pack_safe_signatures(signatures)
+    ordered = sorted(signatures, key=lambda signature: int(signature.owner, 16))
+    assert packed == ordered[0].signature + ordered[1].signature
+
+    with pytest.raises(ValueError, match="signed more than once"):
+        ferrite.pack_safe_signatures(signatures[:1] * 2)
+
+
+def test_safe_message_wraps_eip191_hashes(private_key):
+    """Test that text messages are EIP-191 hashed and wrapped in a SafeMessage."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    safe = "0x" + "aa" * 20
+    message_hash = _hash_eip191_message(encode_defunct(text="hello"))
+    message = ferrite.safe_message(safe, 5, "hello")
+    assert message == ferrite.safe_message(safe, 5, message_hash)
+    assert message["message"] == {"message": "0x" + message_hash.hex()}
+
+    expected = _hash_eip191_message(encode_typed_data(full_message=message))
+    assert ferrite.safe_message_hash(message) == expected
+
+    owner = Account.from_key(private_key)
+    signature = ferrite.sign_safe_message(message, private_key)
+    assert signature.owner == owner.address
+    assert signature.signature == owner.unsafe_sign_hash(expected).signature
+
+
+def test_permit_signature_splits_into_v_r_s(private_key):
+    """Test that EIP-2612 permits hash like eth-account and split into v, r and s."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    owner = Account.from_key(private_key)
+    token = {
+        "name": "USD Coin",
+        "version": "2",
+        "chainId": 1,
+        "verifyingContract": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+    }
+    spender = "0x" + "bb" * 20
+    permit = ferrite.permit(token, owner.address, spender, 2**256 - 1, 0, 2**32)
+    expected = _hash_eip191_message(encode_typed_data(full_message=permit))
+    assert ferrite.permit_hash(permit) == expected
+
+    with pytest.warns(ferrite.DangerousTypedDataWarning, match="Permit"):
+        signed = ferrite.sign_permit(permit, private_key)
+    assert signed.owner == owner.address
+    assert signed.signature == owner.unsafe_sign_hash(expected).signature
+    assert signed.signature == signed.r + signed.s + bytes([signed.v])
+    assert signed.v in (27, 28)
+
+    with pytest.raises(ValueError, match="but"), pytest.warns(
+        ferrite.DangerousTypedDataWarning
+    ):
+        ferrite.sign_permit(permit, Account.create().key)
+
+
+def test_permit2_structures_hash_like_eth_account(private_key):
+    """Test that the Permit2 builders produce typed data eth-account agrees with."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    owner = Account.from_key(private_key)
+    token, spender = "0x" + "aa" * 20, "0x" + "bb" * 20
+    details = ferrite.PermitDetails(token, 2**160 - 1, 2**48 - 1, 0)
+    permits = [
+        ferrite.permit2_single(1, details, spender, 2**32),
+        ferrite.permit2_batch(1, [details, details._replace(nonce=1)], spender, 2**32),
+        ferrite.permit2_transfer_from(1, token, 10**18, spender, 7, 2**32),
+    ]
+    for permit in permits:
+        assert permit["domain"]["verifyingContract"] == ferrite.PERMIT2_ADDRESS
+        expected = _hash_eip191_message(encode_typed_data(full_message=permit))
+        assert ferrite.permit_hash(permit) == expected
+        with pytest.warns(ferrite.DangerousTypedDataWarning):
+            signed = ferrite.sign_permit(permit, private_key)
+        assert signed.signature == owner.unsafe_sign_hash(expected).signature
+
+    with pytest.raises(ValueError, match="uint160"):
+        ferrite.permit2_single(1, details._replace(amount=2**160), spender, 0)
+
+
+def test_dai_permit_uses_the_legacy_layout(private_key):
+    """Test that DAI-style permits have the holder/expiry/allowed fields and sign."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    holder = Account.from_key(private_key)
+    dai = {
+        "name": "Dai Stablecoin",
+        "version": "1",
+        "chainId": 1,
+        "verifyingContract": "0x6B175474E89094C44Da98b954EedeAC495271d0F",
+    }
+    permit = ferrite.dai_permit(dai, holder.address, "0x" + "bb" * 20, 0, 0)
+    fields = [field["name"] for field in permit["types"]["Permit"]]
+    assert fields == ["holder", "spender", "nonce", "expiry", "allowed"]
+    assert permit["message"]["allowed"] is True
+
+    expected = _hash_eip191_message(encode_typed_data(full_message=permit))
+    assert ferrite.permit_hash(permit) == expected
+    with pytest.warns(ferrite.DangerousTypedDataWarning):
+        signed = ferrite.sign_permit(permit, private_key)
+    assert signed.signature == holder.unsafe_sign_hash(expected).signature
+
+    with pytest.raises(ValueError, match="but"), pytest.warns(
+        ferrite.DangerousTypedDataWarning
+    ):
+        ferrite.sign_permit(permit, Account.create().key)
+
+
+def test_transfer_authorization_generates_nonces(private_key):
+    """Test that EIP-3009 authorizations get random nonces and a validity window."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    sender = Account.from_key(private_key)
+    usdc = {
+        "name": "USD Coin",
+        "version": "2",
+        "chainId": 1,
+        "verifyingContract": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+    }
+    to = "0x" + "bb" * 20
+    first = ferrite.transfer_authorization(usdc, sender.address, to, 10**6)
+    second = ferrite.transfer_authorization(usdc, sender.address, to, 10**6)
+    assert first["message"]["nonce"] != second["message"]["nonce"]
+    assert int(first["message"]["validBefore"]) > time.time()
+
+    receive = ferrite.transfer_authorization(
+        usdc, sender.address, to, 1, valid_after=10, valid_before=20, receive=True
+    )
+    assert receive["primaryType"] == "ReceiveWithAuthorization"
+    expected = _hash_eip191_message(encode_typed_data(full_message=receive))
+    with pytest.warns(ferrite.DangerousTypedDataWarning):
+        signed = ferrite.sign_permit(receive, private_key)
+    assert signed.signature == sender.unsafe_sign_hash(expected).signature
+
+    with pytest.raises(ValueError, match="validBefore"):
+        ferrite.transfer_authorization(usdc, sender.address, to, 1, valid_before=0)
+
+
+def test_forward_request_injects_nonce(private_key):
+    """Test that ForwardRequests take their nonce from a callable and sign."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    sender = Account.from_key(private_key)
+    forwarder = "0x" + "aa" * 20
+    looked_up = []
+
+    def get_nonce(address):
+        looked_up.append(address)
+        return 5
+
+    to = "0x" + "bb" * 20
+    request = ferrite.forward_request(
+        forwarder, 1, sender.address, to, "0x1234", gas=10**5, nonce=get_nonce
+    )
+    assert looked_up == [sender.address]
+    assert request["message"]["nonce"] == "5"
+    assert request["domain"]["name"] == "MinimalForwarder"
+
+    expected = _hash_eip191_message(encode_typed_data(full_message=request))
+    assert ferrite.forward_request_hash(request) == expected
+    signed = ferrite.sign_forward_request(request, private_key)
+    assert signed.request == request["message"]
+    assert signed.signature == sender.unsafe_sign_hash(expected).signature
+
+    with pytest.raises(ValueError, match="but"):
+        ferrite.sign_forward_request(request, Account.create().key)
+
+
+def test_cow_orders_sign_singly_and_in_batches(private_key):
+    """Test that GPv2 orders hash like eth-account and batches match single signing."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    owner = Account.from_key(private_key)
+    weth, dai = "0x" + "aa" * 20, "0x" + "bb" * 20
+    orders = [
+        ferrite.cow_order(1, weth, dai, 10**18, 3 * 10**21, 2**32 - 1),
+        ferrite.cow_order(1, dai, weth, 10**21, 10**17, 1, kind="buy"),
+    ]
+    expected = _hash_eip191_message(encode_typed_data(full_message=orders[0]))
+    assert ferrite.cow_order_hash(orders[0]) == expected
+
+    signed = ferrite.sign_cow_order(orders[0], private_key)
+    assert signed.owner == owner.address
+    assert signed.signature == owner.unsafe_sign_hash(expected).signature
+    assert signed.uid == expected + bytes.fromhex(owner.address[2:]) + b"\xff" * 4
+
+    assert ferrite.sign_cow_orders(orders, private_key) == [
+        ferrite.sign_cow_order(order, private_key) for order in orders
+    ]
+    with pytest.raises(ValueError, match="kind"):
+        ferrite.cow_order(1, weth, dai, 1, 1, 1, kind="swap")
+
+
+def test_seaport_order_hash_is_the_struct_hash(private_key):
+    """Test that Seaport orders with nested item arrays hash and sign correctly."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    offerer = Account.from_key(private_key)
+    nft, weth = "0x" + "aa" * 20, "0x" + "bb" * 20
+    offer = [ferrite.OfferItem(2, nft, 1234, 1, 1)]
+    price, fee = 95 * 10**16, 5 * 10**16
+    consideration = [
+        ferrite.ConsiderationItem(1, weth, 0, price, price, offerer.address),
+        ferrite.ConsiderationItem(1, weth, 0, fee, fee, "0x" + "cc" * 20),
+    ]
+    window = {"start_time": 0, "end_time": 2**32, "counter": 0}
+    order = ferrite.seaport_order(1, offerer.address, offer, consideration, **window)
+    assert order["message"]["salt"] != ferrite.seaport_order(
+        1, offerer.address, offer, consideration, **window
+    )["message"]["salt"]
+    assert order["domain"]["verifyingContract"] == ferrite.SEAPORT_ADDRESSES["1.6"]
+
+    encoded = encode_typed_data(full_message=order)
+    assert ferrite.seaport_order_hash(order) == encoded.body
+    signed = ferrite.sign_seaport_order(order, private_key)
+    assert signed.order_hash == encoded.body
+    expected = _hash_eip191_message(encoded)
+    assert signed.signature == offerer.unsafe_sign_hash(expected).signature
+
+    bad_offer = [ferrite.OfferItem(9, nft, 0, 1, 1)]
+    with pytest.raises(ValueError, match="item type"):
+        ferrite.seaport_order(1, offerer.address, bad_offer, [], **window)
+
+
+def test_flashbots_signature_signs_the_body_hash(private_key):
+    """Test that the Flashbots header signs the hex keccak of the body per EIP-191."""
+    from eth_utils import keccak
+
+    searcher = Account.from_key(private_key)
+    body = '{"jsonrpc":"2.0","id":1,"method":"eth_sendBundle","params":[]}'
+    header = ferrite.flashbots_signature(body, private_key)
+
+    address, signature = header.split(":")
+    assert address == searcher.address
+    message = encode_defunct(text="0x" + keccak(text=body).hex())
+    assert signature == "0x" + bytes(searcher.sign_message(message).signature).hex()
+    assert ferrite.flashbots_signature(body.encode(), searcher) == header
+
+
+def test_op_stack_withdrawal_and_deposit_hashes():
+    """Test the OP Stack withdrawal hash, deposit source hash and deposit tx hash."""
+    import rlp
+    from eth_abi import encode
+    from eth_utils import keccak
+
+    sender, target = "0x" + "11" * 20, "0x" + "22" * 20
+    withdrawal = {
+        "nonce": 1 << 240,
+        "sender": sender,
+        "target": target,
+        "value": 10**18,
+        "gasLimit": 100_000,
+        "data": b"\xde\xad",
+    }
+    types = ["uint256", "address", "address", "uint256", "uint256", "bytes"]
+    expected = keccak(encode(types, list(withdrawal.values())))
+    assert ferrite.op_withdrawal_hash(withdrawal) == expected
+
+    block_hash = bytes(range(32))
+    source_hash = ferrite.op_deposit_source_hash(block_hash, 5)
+    deposit_id = keccak(block_hash + (5).to_bytes(32, "big"))
+    assert source_hash == keccak(bytes(32) + deposit_id)
+    l1_info = ferrite.op_l1_info_deposit_source_hash(block_hash, 5)
+    assert l1_info == keccak((1).to_bytes(32, "big") + deposit_id)
+
+    deposit = {
+        "sourceHash": source_hash,
+        "from": sender,
+        "to": target,
+        "mint": 10**17,
+        "value": 0,
+        "gas": 21_000,
+        "isSystemTransaction": False,
+        "data": b"",
+    }
+    fields = [source_hash, bytes.fromhex(sender[2:]), bytes.fromhex(target[2:])]
+    encoded = rlp.encode(fields + [10**17, 0, 21_000, 0, b""])
+    assert ferrite.op_deposit_transaction_hash(deposit) == keccak(b"\x7e" + encoded)
+    creation = ferrite.op_deposit_transaction_hash({**deposit, "to": None})
+    assert creation != ferrite.op_deposit_transaction_hash(deposit)
+
+    with pytest.raises(ferrite.InvalidTransactionError, match="gasLimit"):
+        ferrite.op_withdrawal_hash({**withdrawal, "gasLimit": None})
+
+
+def test_erc7739_nests_typed_data_for_smart_accounts(private_key):
+    """Test that ERC-7739 signatures nest typed data and append the contents."""
+    from eth_account.messages import _hash_eip191_message, encode_typed_data
+
+    owner = Account.from_key(private_key)
+    account = {
+        "name": "Account",
+        "version": "1",
+        "chainId": 1,
+        "verifyingContract": "0x" + "ab" * 20,
+    }
+    # Animal sorts before Zoo, so the contents name is appended explicitly
+    typed_data = {
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+            ],
+            "Zoo": [{"name": "animals", "type": "Animal[]"}],
+            "Animal": [{"name": "name", "type": "string"}],
+        },
+        "primaryType": "Zoo",
+        "domain": {"name": "App", "chainId": 1},
+        "message": {"animals": [{"name": "zebra"}]},
+    }
+    signature = ferrite.sign_erc7739_typed_data(typed_data, account, private_key)
+
+    nested = ferrite.erc7739_typed_data(typed_data, account)
+    assert nested["message"]["salt"] == "0x" + "00" * 32
+    nested_hash = _hash_eip191_message(encode_typed_data(full_message=nested))
+    assert signature[:65] == owner.unsafe_sign_hash(nested_hash).signature
+
+    app = encode_typed_data(full_message=typed_data)
+    description = b"Animal(string name)Zoo(Animal[] animals)Zoo"
+    assert signature[65:] == (
+        app.header + app.body + description + len(description).to_bytes(2, "big")
+    )
+
+    message = ferrite.erc7739_personal_message("hello", account)
+    expected = _hash_eip191_message(encode_typed_data(full_message=message))
+    personal = ferrite.sign_erc7739_personal_message("hello", account, owner)
+    assert personal == owner.unsafe_sign_hash(expected).signature
+
+    permit = ferrite.permit(
+        {"name": "Token", "chainId": 1}, owner.address, "0x" + "bb" * 20, 1, 0, 1
+    )
+    with pytest.warns(ferrite.DangerousTypedDataWarning, match="Permit"):
+        ferrite.sign_erc7739_typed_data(permit, account, private_key)
+
+
+def test_stark_keys_and_signatures():
+    """The optional STARK-curve module matches the StarkWare and starknet.js vectors."""
+    stark = pytest.importorskip("ferrite.stark")
+
+    signature = bytes.fromhex(
+        "21fbf0696d5e0aa2ef41a2b4ffb623bcaf070461d61cf7251c74161f82fec3a4"
+        "370854bc0a34b3ab487c1bc021cd318c734c51ae29374f2beb0e6f2dd49b4bf41c"
+    )
+    key = stark.stark_key_from_signature(signature)
+    assert key == 0x766F11E90CD7C7B43085B56DA35C781F8C067AC0D578EABDCEEBC4886435BDA
+
+    owner = Account.create()
+    derived = stark.derive_stark_key("Onboarding", owner.key)
+    assert derived == stark.derive_stark_key("Onboarding", owner)
+
+    assert stark.stark_public_key(
+        0x3C1E9550E66958296D11B60F8E8E7A7AD990D07FA65D5F7652C4A6C87D4E3CC
+    ) == 0x77A3B314DB07C45076D11F62B6F9E748A39790441823307743CF00D6597EA43
+    assert stark.pedersen_hash(
+        0x3D937C035C878245CAF64531A5756109C53068DA139362728FEB561405371CB,
+        0x208A0A10250E382E1E4BBE2880906C2791BF6275695E02FBBC6AEFF9CD8B31A,
+    ) == 0x30E480BED5FE53FA909CC0F8C4D99B8F9F2C016BE4C41E13A4848797979C662
+    assert stark.sign_stark_hash(
+        0xC465DD6B1BBFFDB05442EB17F5CA38AD1AA78A6F56BF4415BDEE219114A47,
+        0x2DCCCE1DA22003777062EE0870E9881B460A8B7ECA276870F57C601F182136C,
+    ) == (
+        0x5F496F6F210B5810B2711C74C15C05244DAD43D18ECBBDBE6ED55584BC3B0A2,
+        0x4E8657B153787F741A67C0666BAD6426C3741B478C8EAA3155196FC571416F3,
+    )
+
+    typed_data = {
+        "types": {
+            "StarkNetDomain": [
+                {"name": "name", "type": "felt"},
+                {"name": "version", "type": "felt"},
+                {"name": "chainId", "type": "felt"},
+            ],
+            "Person": [
+                {"name": "name", "type": "felt"},
+                {"name": "wallet", "type": "felt"},
+            ],
+            "Mail": [
+                {"name": "from", "type": "Person"},
+                {"name": "to", "type": "Person"},
+                {"name": "contents", "type": "felt"},
+            ],
+        },
+        "primaryType": "Mail",
+        "domain": {"name": "StarkNet Mail", "version": "1", "chainId": 1},
+        "message": {
+            "from": {
+                "name": "Cow",
+                "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826",
+            },
+            "to": {
+                "name": "Bob",
+                "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB",
+            },
+            "contents": "Hello, Bob!",
+        },
+    }
+    account = "0xcd2a3d9f938e13cd947ec05abc7fe734df8dd826"
+    message_hash = stark.starknet_typed_data_hash(typed_data, account)
+    assert message_hash == (
+        0x6FCFF244F63E38B9D88B9E3378D44757710D1B244282B435CB472053C8D78D0
+    )
+    assert stark.sign_starknet_typed_data(
+        typed_data, account, key
+    ) == stark.sign_stark_hash(message_hash, key)
+
+    with pytest.raises(ValueError, match="below 2\\^251"):
+        stark.sign_stark_hash(2**251, key)
+
+
+def test_p256_signatures():
+    """P-256 signing matches RFC 6979's vectors, with s normalized low."""
+    import hashlib
+
+    private_key = "0xC9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721"
+    public_key = ferrite.p256_public_key(private_key)
+    assert public_key == (
+        0x60FED4BA255A9D31C961EB74C6356D68C049B8923B61FA6CE669622E60F29FB6,
+        0x7903FE1008B8BC99A41AE9E95628BC64F2F1B20C2D7E9F5177A3C294D4462299,
+    )
+
+    order = 0xFFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551
+    message_hash = hashlib.sha256(b"sample").digest()
+    signature = ferrite.p256_sign_hash(message_hash, private_key)
+    assert signature == (
+        0xEFD48B2AACB6A8FD1140DD9CD45E81D69D2C877B56AAF991C34D0EA84EAF3716,
+        order - 0xF7CB1C942D657C41D436C7A1B6E29F65F3E900DBB9AFF4064DC4AB2F843ACDA8,
+    )
+    assert ferrite.p256_verify(message_hash, signature, public_key)
+    high_s = (signature.r, order - signature.s)
+    assert ferrite.p256_verify(message_hash, high_s, public_key)
+    other_hash = hashlib.sha256(b"test").digest()
+    assert not ferrite.p256_verify(other_hash, signature, public_key)
+
+    precompile_input = ferrite.p256_precompile_input(
+        message_hash, signature, public_key
+    )
+    assert len(precompile_input) == 160
+    assert ferrite.p256_verify(
+        precompile_input[:32], precompile_input[32:96], precompile_input[96:]
+    )
+
+    session_key = ferrite.create_p256_key()
+    session_signature = ferrite.p256_sign_hash(message_hash, session_key)
+    assert ferrite.p256_verify(
+        message_hash, session_signature, ferrite.p256_public_key(session_key)
+    )
+    with pytest.raises(ferrite.InvalidKeyError):
+        ferrite.p256_verify(message_hash, signature, (public_key.x, public_key.y + 1))
+
+
+def test_webauthn_signatures():
+    """WebAuthn assertions encode as Coinbase Smart Wallet's SignatureWrapper."""
+    import hashlib
+
+    from eth_abi import decode
+
+    private_key = ferrite.create_p256_key()
+    challenge = hashlib.sha256(b"user operation").digest()
+    auth = ferrite.sign_webauthn(
+        challenge, private_key, rp_id="keys.example.com", origin="https://example.com"
+    )
+
+    client_data = auth.client_data_json.encode()
+    assert client_data[auth.type_index :].startswith(b'"type":"webauthn.get"')
+    encoded_challenge = base64.urlsafe_b64encode(challenge).rstrip(b"=")
+    assert client_data[auth.challenge_index :].startswith(
+        b'"challenge":"' + encoded_challenge + b'"'
+    )
+    assert auth.authenticator_data[:32] == hashlib.sha256(b"keys.example.com").digest()
+    assert auth.authenticator_data[32] == 0x05
+    order = 0xFFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551
+    assert auth.s <= order // 2
+
+    message_hash = ferrite.webauthn_message_hash(
+        auth.authenticator_data, auth.client_data_json
+    )
+    public_key = ferrite.p256_public_key(private_key)
+    assert ferrite.p256_verify(message_hash, (auth.r, auth.s), public_key)
+
+    wrapper = ferrite.coinbase_smart_wallet_signature(1, auth)
+    ((owner_index, signature_data),) = decode(["(uint256,bytes)"], wrapper)
+    assert owner_index == 1
+    struct = "(bytes,string,uint256,uint256,uint256,uint256)"
+    (decoded,) = decode([struct], signature_data)
+    assert decoded == (
+        bytes(auth.authenticator_data),
+        auth.client_data_json,
+        auth.challenge_index,
+        auth.type_index,
+        auth.r,
+        auth.s,
+    )
+
+    with pytest.raises(ValueError, match="webauthn.get"):
+        ferrite.webauthn_auth(auth.authenticator_data, "{}", (auth.r, auth.s))
+
+
+def test_sign_bls_user_operation_matches_the_reference_aggregator():
+    """Test BLS user operation hashing, signing and aggregation on BN254."""
+    from eth_abi import encode
+    from eth_utils import keccak
+
+    secret_key = (12345).to_bytes(32, "big")
+    signature = ferrite.bls_sign(b"\x11" * 32, secret_key)
+    assert signature == bytes.fromhex(
+        "059c75c5373b4e80a07a5e2202eb39348c89c127b39475ce8afa16aa2f5c5b9b"
+        "0687cb93f902f5bf645a03a9446c157cee004a0f3ebbb0fde16323c45d64c820"
+    )
+    public_key = ferrite.bls_public_key(secret_key)
+    assert public_key[:32] == bytes.fromhex(
+        "00fde667faf46ac5c419be1d6f28ff535a43c9efe5600584162084d55d8b508a"
+    )
+
+    aggregator = "0x" + "55" * 20
+    user_op = {
+        "sender": "0x" + "22" * 20,
+        "nonce": 7,
+        "initCode": "0x",
+        "callData": "0xb61d27f6",
+        "callGasLimit": 100000,
+        "verificationGasLimit": 100000,
+        "preVerificationGas": 50000,
+        "maxFeePerGas": 2 * 10**9,
+        "maxPriorityFeePerGas": 10**9,
+        "paymasterAndData": "0x",
+    }
+    packed = encode(
+        ["address", "uint256", "bytes32", "bytes32"] + ["uint256"] * 5 + ["bytes32"],
+        [
+            user_op["sender"],
+            7,
+            keccak(b""),
+            keccak(hexstr=user_op["callData"]),
+            100000,
+            100000,
+            50000,
+            2 * 10**9,
+            10**9,
+            keccak(b""),
+        ],
+    )
+    expected = keccak(
+        encode(
+            ["bytes32", "bytes32", "address", "uint256"],
+            [keccak(packed), keccak(public_key), aggregator, 1],
+        )
+    )
+    user_op_hash = ferrite.bls_user_operation_hash(user_op, aggregator, 1, public_key)
+    assert user_op_hash == expected
+    signed = ferrite.sign_bls_user_operation(user_op, aggregator, 1, secret_key)
+    assert signed == ferrite.bls_sign(user_op_hash, secret_key)
+
+    # Signatures of one message aggregate to the signature of the summed keys
+    partials = [ferrite.bls_sign(b"bundle", (k).to_bytes(32, "big")) for k in (5, 7)]
+    assert ferrite.aggregate_bls_signatures(partials) == ferrite.bls_sign(
+        b"bundle", (12).to_bytes(32, "big")
+    )
+    with pytest.raises(ferrite.FerriteError, match="not a point"):
+        ferrite.aggregate_bls_signatures([b"\x01" * 64])
+
+
+def test_keccak_accepts_any_bytes_like_object():
+    """Test that keccak hashes bytes, bytearrays and memoryviews, large or small."""
+    from eth_utils import keccak
+
+    assert ferrite.keccak(b"") == keccak(b"")
+    data = bytes(range(256)) * 64
+    expected = keccak(data)
+    assert ferrite.keccak(data) == expected
+    assert ferrite.keccak(bytearray(data)) == expected
+    assert ferrite.keccak(memoryview(data)) == expected
+    assert ferrite.keccak(memoryview(data)[:3]) == keccak(data[:3])
+
+    with pytest.raises(TypeError):
+        ferrite.keccak("not bytes")
+    with pytest.raises(TypeError):
+        ferrite.keccak(32)
+
+
+def test_rlp_round_trips_nested_lists():
+    """Test RLP encoding and strict decoding against the Ethereum test vectors."""
+    assert ferrite.rlp_encode(b"dog") == b"\x83dog"
+    assert ferrite.rlp_encode([b"cat", b"dog"]) == b"\xc8\x83cat\x83dog"
+    assert ferrite.rlp_encode(0) == b"\x80"
+    assert ferrite.rlp_encode(15) == b"\x0f"
+    assert ferrite.rlp_encode(1024) == b"\x82\x04\x00"
+    assert ferrite.rlp_encode((bytearray(b"a"), memoryview(b"bc"))) == b"\xc4a\x82bc"
+
+    nested = [[], [[]], [[], [[]]]]
+    encoded = bytes.fromhex("c7c0c1c0c3c0c1c0")
+    assert ferrite.rlp_encode(nested) == encoded
+    assert ferrite.rlp_decode(encoded) == nested
+
+    text = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit"
+    assert ferrite.rlp_decode(ferrite.rlp_encode([text, 2**300])) == [
+        text,
+        (2**300).to_bytes(38, "big"),
+    ]
+
+    for malformed in [b"", b"\x81\x05", b"\x83do", b"\x80\x00", b"\xb8\x05hello"]:
+        with pytest.raises(ferrite.FerriteError, match="Invalid RLP"):
+            ferrite.rlp_decode(malformed)
+    with pytest.raises(TypeError):
+        ferrite.rlp_encode("dog")
+    with pytest.raises(ferrite.FerriteError, match="negative"):
+        ferrite.rlp_encode(-1)
+
+
+def test_encode_calldata_from_a_signature():
+    """Test calldata encoding against the Solidity ABI specification's examples."""
+    from eth_utils import keccak
+
+    to = "0x" + "ab" * 20
+    calldata = ferrite.encode_calldata("transfer(address,uint256)", [to, 10])
+    assert calldata == bytes.fromhex(
+        "a9059cbb" + "00" * 12 + "ab" * 20 + (10).to_bytes(32, "big").hex()
+    )
+    assert ferrite.encode_calldata("transfer(address, uint)", (to, 10)) == calldata
+
+    dave = int.from_bytes(b"dave".ljust(32, b"\0"), "big")
+    words = [0x60, 1, 0xA0, 4, dave, 3, 1, 2, 3]
+    assert ferrite.encode_calldata(
+        "sam(bytes,bool,uint256[])", [b"dave", True, [1, 2, 3]]
+    ) == bytes.fromhex("a5643bf2") + b"".join(w.to_bytes(32, "big") for w in words)
+    assert ferrite.encode_calldata("f(int8)", [-1]) == keccak(text="f(int8)")[:4] + (
+        b"\xff" * 32
+    )
+
+    with pytest.raises(ferrite.FerriteError, match="unknown type"):
+        ferrite.encode_calldata("transfer(adress,uint256)", [to, 10])
+    with pytest.raises(ferrite.FerriteError, match="out of range"):
+        ferrite.encode_calldata("approve(address,uint8)", [to, 256])
+    with pytest.raises(ferrite.FerriteError, match="takes 2 arguments"):
+        ferrite.encode_calldata("transfer(address,uint256)", [to])
+
+
+def test_selectors_and_topics_from_signatures():
+    """Test that selectors and topics hash the canonical form of the signature."""
+    assert ferrite.function_selector("transfer(address,uint256)").hex() == "a9059cbb"
+    assert ferrite.function_selector("function transfer(address to, uint amount)") == (
+        bytes.fromhex("a9059cbb")
+    )
+    transfer = bytes.fromhex(
+        "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+    )
+    assert ferrite.event_topic("Transfer(address,address,uint256)") == transfer
+    assert ferrite.event_topics(
+        [
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+            "Approval(address,address,uint256)",
+        ]
+    ) == [
+        transfer,
+        bytes.fromhex(
+            "8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925"
+        ),
+    ]
+    signatures = ["balanceOf(address)", "fill((address,bytes))"]
+    assert ferrite.function_selectors(signatures) == [
+        bytes.fromhex("70a08231"),
+        ferrite.function_selector("fill(tuple(address sender, bytes data) order)"),
+    ]
+
+    with pytest.raises(ferrite.FerriteError, match="unknown type"):
+        ferrite.event_topic("Transfer(adress,address,uint256)")
+
+
+def test_solidity_keccak_packs_like_web3():
+    """Test that solidity_keccak matches web3.py's solidity_keccak."""
+    assert ferrite.solidity_keccak(["bool"], [True]) == bytes.fromhex(
+        "5fe7f977e71dba2ea1a68e21057beebb9be2ac30c6410aa38d4f3fbe41dcffd2"
+    )
+    assert ferrite.solidity_keccak(["uint8[]"], [[97, 98, 99]]) == bytes.fromhex(
+        "233002c671295529bcc50b76a2ef2b0de2dac2d93945fca745255de1a9e4017e"
+    )
+    address = "0x49EdDD3769c0712032808D86597B84ac5c2F5614"
+    assert ferrite.solidity_keccak(["address"], [address]) == bytes.fromhex(
+        "2ff37b5607484cd4eecf6d13292e22bd6e5401eaffcc07e279583bc742c68882"
+    )
+    packed = ferrite.solidity_keccak(["uint8", "int8", "bytes32"], [1, -1, b"\x01"])
+    assert packed == ferrite.keccak(bytes.fromhex("01ff01"))
+    assert ferrite.solidity_keccak(["int16[]"], [[-1]]) == ferrite.keccak(b"\xff" * 32)
+
+    with pytest.raises(ferrite.FerriteError, match="out of range"):
+        ferrite.solidity_keccak(["uint8"], [256])
+    with pytest.raises(ferrite.FerriteError, match="Length mismatch"):
+        ferrite.solidity_keccak(["address", "uint256"], [address])
+
+
+def test_checksum_addresses():
+    """Test EIP-55 and EIP-1191 checksums."""
+    address = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+    eip55 = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+    rsk = "0x5aaEB6053f3e94c9b9a09f33669435E7ef1bEAeD"
+    assert ferrite.to_checksum_address(address) == eip55
+    assert ferrite.to_checksum_address(address[2:].upper(), chain_id=30) == rsk
+    assert ferrite.to_checksum_addresses([address, eip55]) == [eip55, eip55]
+    assert ferrite.is_checksum_address(eip55)
+    assert ferrite.is_checksum_address(rsk, chain_id=30)
+    assert not ferrite.is_checksum_address(rsk)
+    assert not ferrite.is_checksum_address(address)
+    assert ferrite.is_checksum_addresses([eip55, eip55[2:], "0x12"]) == [
+        True,
+        False,
+        False,
+    ]
+
+    with pytest.raises(ferrite.FerriteError, match="40 hex digits"):
+        ferrite.to_checksum_address("0x12")
+
+
+def test_contract_addresses():
+    """Test CREATE and CREATE2 addresses, and mining a CREATE2 salt."""
+    deployer = "0x6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0"
+    assert ferrite.create_address(deployer, 0) == (
+        "0xcd234A471b72ba2F1Ccf0A70FCABA648a5eeCD8d"
+    )
+    assert ferrite.create_address(deployer, 1) == (
+        "0x343c43A37D37dfF08AE8C4A11544c718AbB4fCF8"
+    )
+    init_code_hash = ferrite.keccak(b"\x00")
+    factory = "0xdeadbeef00000000000000000000000000000000"
+    assert ferrite.create2_address(factory, bytes(32), init_code_hash) == (
+        "0xB928f69Bb1D91Cd65274e3c79d8986362984fDA3"
+    )
+
+    salt_prefix = bytes.fromhex(deployer[2:])
+    mined = ferrite.mine_create2_salt(
+        factory, init_code_hash, prefix="0xa", suffix="b", salt_prefix=salt_prefix
+    )
+    assert mined is not None
+    salt, address = mined
+    assert salt.startswith(salt_prefix)
+    assert address.lower().startswith("0xa") and address.lower().endswith("b")
+    assert ferrite.create2_address(factory, salt, init_code_hash) == address
+    assert ferrite.mine_create2_salt(
+        factory, init_code_hash, prefix="ffffff", attempts=100
+    ) is None
+
+    with pytest.raises(ferrite.FerriteError, match="32 bytes"):
+        ferrite.create2_address(factory, b"\x00", init_code_hash)
+
+
+def test_ens_name_hashing():
+    """Test namehash, labelhash and DNS encoding of ENS names."""
+    assert ferrite.namehash("") == bytes(32)
+    assert ferrite.namehash("eth").hex() == (
+        "93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+    )
+    node = bytes.fromhex(
+        "ee6c4522aab0003e8d14cd40a6af439055fd2577951148c14b6cea9a53475835"
+    )
+    assert ferrite.namehash("vitalik.eth") == node
+    label = ferrite.labelhash("vitalik")
+    assert label == ferrite.keccak(b"vitalik")
+    assert ferrite.namehash(f"[{label.hex()}].eth") == node
+    assert ferrite.dns_encode("vitalik.eth") == b"\x07vitalik\x03eth\x00"
+    assert ferrite.dns_encode("") == b"\x00"
+
+    with pytest.raises(ferrite.FerriteError, match="empty label"):
+        ferrite.namehash("vitalik..eth")
+    with pytest.raises(ferrite.FerriteError, match="single non-empty label"):
+        ferrite.labelhash("vitalik.eth")
+
+
+def test_decode_event_logs():
+    """Test decoding logs from an event signature or ABI entry."""
+    event = "event Transfer(address indexed from, address indexed to, uint256 value)"
+    sender, recipient = "0x" + "11" * 20, "0x" + "22" * 20
+    topics = [
+        ferrite.event_topic(event),
+        bytes(12) + bytes.fromhex(sender[2:]),
+        "0x" + "00" * 12 + recipient[2:],
+    ]
+    data = (10**30).to_bytes(32, "big")
+    transfer = {"from": sender, "to": recipient, "value": 10**30}
+    assert ferrite.decode_log(event, topics, data) == transfer
+    log = {"topics": topics, "data": "0x" + data.hex()}
+    assert ferrite.decode_logs(event, [log]) == [transfer]
+
+    abi = {
+        "type": "event",
+        "name": "Filled",
+        "anonymous": False,
+        "inputs": [
+            {"name": "tag", "type": "string", "indexed": True},
+            {"name": "delta", "type": "int256", "indexed": False},
+            {
+                "name": "legs",
+                "type": "tuple[]",
+                "indexed": False,
+                "components": [
+                    {"name": "side", "type": "int8"},
+                    {"name": "venue", "type": "string"},
+                ],
+            },
+        ],
+    }
+    signature = "Filled(string,int256,(int8,string)[])"
+    calldata = ferrite.encode_calldata(
+        "f(int256,(int8,string)[])", [-(10**40), [(-1, "x")]]
+    )
+    decoded = ferrite.decode_logs(
+        abi, [([ferrite.event_topic(signature), ferrite.keccak(b"tag")], calldata[4:])]
+    )
+    assert decoded == [
+        {"tag": ferrite.keccak(b"tag"), "delta": -(10**40), "legs": [(-1, "x")]}
+    ]
+
+    with pytest.raises(ferrite.FerriteError, match="expected 2 indexed topics"):
+        ferrite.decode_log(event, topics[:2], data)
+    with pytest.raises(ferrite.FerriteError, match="not a valid address"):
+        ferrite.decode_log(event, [topics[0], b"\xff" * 32, topics[2]], data)
+    with pytest.raises(ferrite.FerriteError, match="at index 1: data is too short"):
+        ferrite.decode_logs(event, [(topics, data), (topics, data[:31])])
+
+
+def test_wei_unit_conversion_is_exact():
+    """Test that to_wei and from_wei convert without rounding."""
+    assert ferrite.to_wei("0.1", "ether") == 10**17
+    assert ferrite.to_wei(0.1, "ether") == 10**17
+    assert ferrite.to_wei(Decimal("1.5"), 6) == 1_500_000
+    assert ferrite.to_wei(3, "GWEI") == 3 * 10**9
+    assert ferrite.from_wei(10**18, "ether") == 1
+    assert ferrite.from_wei(1, "ether") == Decimal("1e-18")
+    assert ferrite.from_wei(1_234_567, 6) == Decimal("1.234567")
+    assert ferrite.from_wei(0) == 0
+    for amount in ["0.1", "123.456789", "0.000000000000000001"]:
+        assert ferrite.from_wei(ferrite.to_wei(amount)) == Decimal(amount)
+
+    with pytest.raises(ValueError, match="not a whole number of wei"):
+        ferrite.to_wei("0.0000000001", "gwei")
+    with pytest.raises(ValueError, match="negative"):
+        ferrite.to_wei(-1, "wei")
+    with pytest.raises(ValueError, match="Unknown unit"):
+        ferrite.to_wei(1, "ethers")
+
+
+def test_hex_conversions_match_eth_utils():
+    """Test to_hex, to_bytes and to_int against eth-utils' rules."""
+    assert ferrite.to_hex(b"\x01\xff") == "0x01ff"
+    assert ferrite.to_hex(0) == "0x0"
+    assert ferrite.to_hex(-5) == "-0x5"
+    assert ferrite.to_hex(True) == "0x1"
+    assert ferrite.to_hex(hexstr="ABC") == "0xabc"
+    assert ferrite.to_hex(text="hi") == "0x6869"
+    assert ferrite.to_bytes(0) == b"\x00"
+    assert ferrite.to_bytes(256) == b"\x01\x00"
+    assert ferrite.to_bytes(hexstr="0xabc") == b"\x0a\xbc"
+    assert ferrite.to_bytes(text="hi") == b"hi"
+    assert ferrite.to_int(b"\x01\x00") == 256
+    assert ferrite.to_int(hexstr="0x1f") == 31
+    assert ferrite.to_int(text="12") == 12
+
+    with pytest.raises(TypeError, match="keyword hexstr or text"):
+        ferrite.to_int("12")
+    with pytest.raises(TypeError, match="Exactly one"):
+        ferrite.to_hex(1, text="a")
+
+
+def test_typed_transaction_envelopes():
+    """Test wrapping and unwrapping EIP-2718 typed transaction envelopes."""
+    payload = ferrite.rlp_encode([1, 0, b"", b"\x01"])
+    envelope = ferrite.wrap_typed_transaction(0x42, payload)
+    assert envelope == b"\x42" + payload
+    assert ferrite.unwrap_typed_transaction(envelope) == (0x42, payload)
+
+    with pytest.raises(ferrite.FerriteError, match="at most 0x7f"):
+        ferrite.wrap_typed_transaction(0x80, payload)
+    with pytest.raises(ferrite.FerriteError, match="legacy transaction"):
+        ferrite.unwrap_typed_transaction(payload)
+    with pytest.raises(ferrite.FerriteError, match="empty input"):
+        ferrite.unwrap_typed_transaction(b"")
+
+
+def test_validator_deposit_data():
+    """Test the SSZ roots of deposit data."""
+    pubkey = bytes(range(48))
+    credentials = ferrite.withdrawal_credentials("0x" + "22" * 20)
+    assert credentials == b"\x01" + bytes(11) + b"\x22" * 20
+    signing_root = ferrite.deposit_signing_root(pubkey, credentials, network="sepolia")
+    assert signing_root.hex() == (
+        "7a259c66f88413223c80bab196604121040d8bb126eaf55b2938144d0b5a4c25"
+    )
+
+    signed = []
+    entry = ferrite.deposit_data(
+        pubkey,
+        credentials,
+        lambda root: signed.append(root) or bytes(range(96)),
+        network="sepolia",
+    )
+    assert signed == [signing_root]
+    assert entry["amount"] == ferrite.DEPOSIT_AMOUNT
+    assert entry["fork_version"] == "90000069"
+    assert entry["deposit_message_root"] == (
+        "93b1e0c351562d45ecd336cfb371539f7140c25a72a9e72cbf39a429b49b025a"
+    )
+    assert entry["deposit_data_root"] == (
+        "66c5b10666417895d7753d3461d889315b19718ef10c1d5d8d60bbc16c3cf1ff"
+    )
+
+    with pytest.raises(ValueError, match="Unknown network"):
+        ferrite.deposit_signing_root(pubkey, credentials, network="goerli")
+    with pytest.raises(ferrite.FerriteError, match="48 bytes"):
+        ferrite.deposit_signing_root(pubkey[:32], credentials)
+
+
+def test_multicall3_bundles():
+    """Test building aggregate3 calldata and decoding its results."""
+    token = "0x" + "11" * 20
+    balance_of = ferrite.encode_calldata("balanceOf(address)", ["0x" + "22" * 20])
+    calldata = ferrite.multicall3_calldata(
+        [(token, balance_of), ferrite.Call3(token, "0x18160ddd", True)]
+    )
+    assert calldata == ferrite.encode_calldata(
+        "aggregate3((address,bool,bytes)[])",
+        [[(token, False, balance_of), (token, True, bytes.fromhex("18160ddd"))]],
+    )
+    assert calldata[:4].hex() == "82ad56cb"
+
+    returned = ferrite.encode_calldata(
+        "f((bool,bytes)[])", [[(True, (5).to_bytes(32, "big")), (False, b"")]]
+    )[4:]
+    results = ferrite.decode_multicall3_results(returned)
+    assert results == [(True, (5).to_bytes(32, "big")), (False, b"")]
+    assert ferrite.decode_abi(["uint256"], results[0].return_data) == [5]
+    assert not results[1].success
+
+    with pytest.raises(ferrite.FerriteError, match="data is too short"):
+        ferrite.decode_abi(["uint256"], b"\x00")
+
+
+def test_fill_transaction_batches_rpc_calls():
+    """The optional JSON-RPC client fills missing fields from one batch request."""
+    rpc = pytest.importorskip("ferrite.rpc")
+    results = {
+        "eth_getTransactionCount": "0x7",
+        "eth_estimateGas": "0x5208",
+        "eth_chainId": "0x1",
+        "eth_maxPriorityFeePerGas": "0x3b9aca00",
+        "eth_getBlockByNumber": {"number": "0x10", "baseFeePerGas": "0x2540be400"},
+        "eth_gasPrice": "0x4a817c800",
+    }
+    batches = []
+
+    class Handler(BaseHTTPRequestHandler):
+        def do_POST(self):
+            batch = json.loads(self.rfile.read(int(self.headers["Content-Length"])))
+            batches.append([call["method"] for call in batch])
+            responses = [
+                {"jsonrpc": "2.0", "id": call["id"], "result": results[call["method"]]}
+                for call in reversed(batch)
+            ]
+            body = json.dumps(responses).encode()
+            self.send_response(200)
+            self.send_header("Content-Length", str(len(body)))
+            self.end_headers()
+            self.wfile.write(body)
+
+        def log_message(self, *args):
+            pass
+
+    server = HTTPServer(("127.0.0.1", 0), Handler)
+    threading.Thread(target=server.serve_forever, daemon=True).start()
+    url = f"http://127.0.0.1:{server.server_port}/"
+    sender = Account.create()
+    tx = {"from": sender.address, "to": "0x" + "11" * 20, "value": 1}
+    try:
+        filled = rpc.fill_transaction(tx, url)
+        assert filled == {
+            **tx,
+            "nonce": 7,
+            "gas": 21000,
+            "chainId": 1,
+            "maxPriorityFeePerGas": 10**9,
+            "maxFeePerGas": 2 * 10**10 + 10**9,
+        }
+        assert len(batches) == 1
+        sender.sign_transaction({k: v for k, v in filled.items() if k != "from"})
+
+        legacy = rpc.fill_transaction({**tx, "type": 0, "nonce": 1, "gas": 30000}, url)
+        assert legacy["gasPrice"] == 2 * 10**10 and legacy["nonce"] == 1
+        assert batches[-1] == ["eth_chainId", "eth_gasPrice"]
+    finally:
+        server.shutdown()
+
+    with pytest.raises(ferrite.FerriteError, match="'from'"):
+        rpc.fill_transaction({"to": tx["to"]}, url)
+    with pytest.raises(ferrite.FerriteError, match="https"):
+        rpc.fill_transaction(tx, "https://example.com")
+
+
+def test_sign_and_send_retries_unavailable_endpoints():
+    """Broadcasts retry HTTP 503s and count an already known transaction as sent."""
+    rpc = pytest.importorskip("ferrite.rpc")
+    statuses = [503, 200, 200]
+    received = []
+
+    class Handler(BaseHTTPRequestHandler):
+        def do_POST(self):
+            request = json.loads(self.rfile.read(int(self.headers["Content-Length"])))
+            received.append(request["params"][0])
+            status = statuses.pop(0)
+            raw = bytes.fromhex(request["params"][0][2:])
+            response = {"jsonrpc": "2.0", "id": request["id"]}
+            if len(received) == 3:
+                response["error"] = {"code": -32000, "message": "already known"}
+            else:
+                response["result"] = "0x" + ferrite.keccak(raw).hex()
+            body = json.dumps(response).encode()
+            self.send_response(status)
+            self.send_header("Content-Length", str(len(body)))
+            self.end_headers()
+            self.wfile.write(body)
+
+        def log_message(self, *args):
+            pass
+
+    server = HTTPServer(("127.0.0.1", 0), Handler)
+    threading.Thread(target=server.serve_forever, daemon=True).start()
+    url = f"http://127.0.0.1:{server.server_port}"
+    sender = Account.create()
+    tx = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "nonce": 0,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "chainId": 1,
+    }
+    try:
+        tx_hash = rpc.sign_and_send(tx, sender.key, url, fill=False, backoff=0.01)
+        signed = sender.sign_transaction(tx)
+        assert tx_hash == signed.hash
+        assert received == ["0x" + bytes(signed.raw_transaction).hex()] * 2
+        assert rpc.send_raw_transaction(signed.raw_transaction, url) == signed.hash
+    finally:
+        server.shutdown()
+
+    with pytest.raises(ValueError, match="signer"):
+        rpc.sign_and_send({**tx, "from": "0x" + "22" * 20}, sender.key, url)
+
+
+def test_signer_server_over_http_and_websocket():
+    """The JSON-RPC signer serves eth-account signatures over HTTP and WebSocket."""
+    account = Account.create()
+    typed_data = {
+        "types": {
+            "EIP712Domain": [{"name": "name", "type": "string"}],
+            "Mail": [{"name": "contents", "type": "string"}],
+        },
+        "primaryType": "Mail",
+        "domain": {"name": "Test"},
+        "message": {"contents": "hello"},
+    }
+    tx = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "nonce": 0,
+        "gas": 21000,
+        "gasPrice": 10**9,
+        "chainId": 1,
+    }
+    with ferrite.SignerServer([account.key], port=0) as server:
+
+        def rpc(method, *params):
+            body = json.dumps(
+                {"jsonrpc": "2.0", "id": 1, "method": method, "params": list(params)}
+            ).encode()
+            request = urllib.request.Request(
+                server.url, body, {"Content-Type": "application/json"}
+            )
+            with urllib.request.urlopen(request) as response:
+                return json.loads(response.read())
+
+        assert rpc("eth_accounts")["result"] == [account.address]
+        signed = rpc("eth_sign", account.address, "0x" + b"hello".hex())["result"]
+        expected = account.sign_message(encode_defunct(b"hello")).signature
+        assert signed == "0x" + bytes(expected).hex()
+        quantities = {k: hex(v) if isinstance(v, int) else v for k, v in tx.items()}
+        raw = rpc("eth_signTransaction", {**quantities, "from": account.address})
+        expected = account.sign_transaction(tx).raw_transaction
+        assert raw["result"] == "0x" + bytes(expected).hex()
+        signature = rpc("eth_signTypedData_v4", account.address, json.dumps(typed_data))
+        expected = account.sign_typed_data(full_message=typed_data).signature
+        assert signature["result"] == "0x" + bytes(expected).hex()
+        error = rpc("eth_sign", "0x" + "22" * 20, "0x00")["error"]
+        assert "unknown account" in error["message"]
+        assert rpc("eth_sendTransaction", tx)["error"]["code"] == -32601
+
+        # Requests a web page could make are refused
+        body = b'{"jsonrpc": "2.0", "id": 1, "method": "eth_accounts"}'
+        for headers, status in (
+            ({"Origin": "https://evil.example"}, 403),
+            ({"Host": "evil.example"}, 403),
+            ({"Content-Type": "text/plain"}, 415),
+        ):
+            headers = {"Content-Type": "application/json", **headers}
+            request = urllib.request.Request(server.url, body, headers)
+            with pytest.raises(urllib.error.HTTPError) as refused:
+                urllib.request.urlopen(request)
+            assert refused.value.code == status
+        large = b" " * ((1 << 20) + 1)
+        request = urllib.request.Request(
+            server.url, large, {"Content-Type": "application/json"}
+        )
+        with pytest.raises(urllib.error.HTTPError) as refused:
+            urllib.request.urlopen(request)
+        assert refused.value.code == 413
+
+        host, port = server.url[len("http://") :].split(":")
+        with socket.create_connection((host, int(port))) as sock:
+            key = base64.b64encode(b"0123456789abcdef").decode()
+            sock.sendall(
+                (
+                    f"GET / HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\n"
+                    f"Connection: Upgrade\r\nSec-WebSocket-Key: {key}\r\n"
+                    "Sec-WebSocket-Version: 13\r\n\r\n"
+                ).encode()
+            )
+            handshake = b""
+            while b"\r\n\r\n" not in handshake:
+                handshake += sock.recv(1)
+            assert handshake.startswith(b"HTTP/1.1 101")
+            message = json.dumps(
+                {"jsonrpc": "2.0", "id": 2, "method": "eth_accounts", "params": []}
+            ).encode()
+            mask = b"\x01\x02\x03\x04"
+            masked = bytes(b ^ mask[i % 4] for i, b in enumerate(message))
+            sock.sendall(bytes([0x81, 0x80 | len(message)]) + mask + masked)
+            header = sock.recv(2)
+            assert header[0] == 0x81
+            payload = b""
+            while len(payload) < header[1]:
+                payload += sock.recv(header[1] - len(payload))
+            assert json.loads(payload) == {
+                "jsonrpc": "2.0",
+                "id": 2,
+                "result": [account.address],
+            }
+
+            # A frame claiming more than the size limit closes the connection
+            sock.sendall(bytes([0x81, 0xFF]) + struct.pack(">Q", 1 << 40) + mask)
+            assert sock.recv(4) == bytes([0x88, 2]) + struct.pack(">H", 1009)
+
+        # And web pages can't open a WebSocket to the server
+        with socket.create_connection((host, int(port))) as sock:
+            sock.sendall(
+                (
+                    f"GET / HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\n"
+                    f"Connection: Upgrade\r\nSec-WebSocket-Key: {key}\r\n"
+                    "Origin: https://evil.example\r\n\r\n"
+                ).encode()
+            )
+            response = b""
+            while chunk := sock.recv(4096):
+                response += chunk
+            assert response.startswith(b"HTTP/1.1 403")
+
+
+@pytest.mark.skipif(not hasattr(socket, "AF_UNIX"), reason="needs Unix sockets")
+def test_ipc_signer_server_checks_peer_credentials(tmp_path):
+    """The IPC signer streams JSON-RPC over a Unix socket to allowed users only."""
+    account = Account.create()
+    path = str(tmp_path / "signer.ipc")
+    requests = [
+        {"jsonrpc": "2.0", "id": 1, "method": "eth_accounts", "params": []},
+        {"jsonrpc": "2.0", "id": 2, "method": "eth_chainId", "params": []},
+    ]
+    stream = "".join(json.dumps(request) for request in requests).encode()
+
+    with ferrite.IpcSignerServer([account.key], path) as server:
+        assert server.url == path
+        assert os.stat(path).st_mode & 0o777 == 0o600
+        with socket.socket(socket.AF_UNIX) as sock:
+            sock.connect(path)
+            # Requests may arrive split across reads, and several in one
+            sock.sendall(stream[:10])
+            sock.sendall(stream[10:] + b"\n")
+            received = b""
+            while received.count(b"\n") < 2:
+                received += sock.recv(4096)
+        responses = [json.loads(line) for line in received.splitlines()]
+        assert responses[0] == {"jsonrpc": "2.0", "id": 1, "result": [account.address]}
+        assert responses[1]["error"]["code"] == -32601
+    assert not os.path.exists(path)
+
+    with ferrite.IpcSignerServer(
+        [account.key], path, allowed_uids=[os.getuid() + 1]
+    ):
+        with socket.socket(socket.AF_UNIX) as sock:
+            sock.connect(path)
+            # Closed before anything is read
+            assert sock.recv(4096) == b""
+
+
+def test_grpc_signer_service():
+    """The gRPC signer's messages match signer.proto and its calls sign like Account."""
+    from ferrite import grpc_signer
+
+    request = {"address": "0x" + "ab" * 20, "hash": b"\x01" * 32}
+    encoded = grpc_signer.encode_message("SignHashRequest", request)
+    address_field = b"\x0a\x2a" + request["address"].encode()
+    assert encoded == address_field + b"\x12\x20" + b"\x01" * 32
+    assert grpc_signer.decode_message("SignHashRequest", encoded) == request
+    assert grpc_signer.decode_message("RemoveKeyResponse", b"") == {"removed": False}
+
+    grpc = pytest.importorskip("grpc")
+    account = Account.create()
+    server = grpc.server(ThreadPoolExecutor(max_workers=2))
+    ferrite.SignerService([account.key]).add_to_server(server)
+    port = server.add_insecure_port("127.0.0.1:0")
+    server.start()
+    try:
+        channel = grpc.insecure_channel(f"127.0.0.1:{port}")
+
+        def call(method, request_type, response_type, fields):
+            stub = channel.unary_unary(
+                f"/{grpc_signer.SERVICE_NAME}/{method}",
+                request_serializer=lambda message: grpc_signer.encode_message(
+                    request_type, message
+                ),
+                response_deserializer=lambda data: grpc_signer.decode_message(
+                    response_type, data
+                ),
+            )
+            return stub(fields)
+
+        keys = call("ListKeys", "ListKeysRequest", "ListKeysResponse", {})
+        assert keys == {"addresses": [account.address]}
+        message_hash = b"\x02" * 32
+        signed = call(
+            "SignHash",
+            "SignHashRequest",
+            "Signature",
+            {"address": account.address, "hash": message_hash},
+        )
+        expected = ferrite.sign_hashes([message_hash], account.key)[0]
+        assert signed["signature"] == bytes(expected.signature)
+
+        tx = {"to": "0x" + "11" * 20, "value": 1, "nonce": 0, "gas": 21000}
+        tx.update(gasPrice=10**9, chainId=1)
+        raw = call(
+            "SignTransaction",
+            "SignTransactionRequest",
+            "SignedTransaction",
+            {"address": account.address, "transaction_json": json.dumps(tx)},
+        )
+        expected = account.sign_transaction(tx).raw_transaction
+        assert raw["raw_transaction"] == bytes(expected)
+
+        with pytest.raises(grpc.RpcError) as error:
+            call(
+                "AddKey",
+                "AddKeyRequest",
+                "AddKeyResponse",
+                {"private_key": b"\x01" * 32},
+            )
+        assert error.value.code() == grpc.StatusCode.PERMISSION_DENIED
+        with pytest.raises(grpc.RpcError) as error:
+            call(
+                "SignHash",
+                "SignHashRequest",
+                "Signature",
+                {"address": "0x" + "22" * 20, "hash": message_hash},
+            )
+        assert error.value.code() == grpc.StatusCode.NOT_FOUND
+    finally:
+        server.stop(None)
+
+
+def test_cli_binary_signs_like_the_extension(tmp_path):
+    root = os.path.dirname(os.path.abspath(__file__))
+    binaries = [
+        os.path.join(root, "target", profile, "ferrite")
+        for profile in ("release", "debug")
+    ]
+    binary = next((path for path in binaries if os.path.exists(path)), None)
+    if binary is None:
+        pytest.skip("build it with `cargo build --bin ferrite`")
+
+    account = Account.create()
+    (tmp_path / "key").write_text(account.key.hex() + "\n")
+    tx = {"to": "0x" + "11" * 20, "value": 1, "nonce": 0, "gas": 21000}
+    tx.update(maxFeePerGas=2 * 10**9, maxPriorityFeePerGas=10**9, chainId=1)
+    key = ["--private-key-file", str(tmp_path / "key")]
+
+    def run(*args, payload=None):
+        return subprocess.run(
+            [binary, *args, *key], input=payload, capture_output=True, text=True
+        )
+
+    assert run("address").stdout.strip() == account.address
+    signed = run("sign-transaction", payload=json.dumps(tx))
+    expected = account.sign_transaction(tx).raw_transaction
+    assert signed.stdout.strip() == "0x" + bytes(expected).hex()
+
+    wrong_from = json.dumps({**tx, "from": "0x" + "22" * 20})
+    refused = run("sign-transaction", payload=wrong_from)
+    assert refused.returncode == 1 and "`from`" in refused.stderr
+    assert run("sign-transaction", "--max-fee", "1", payload=json.dumps(tx)).returncode
+    assert run("sign-transaction", "--index", "1").returncode == 2
+
+
+def test_c_library_signs_like_the_extension():
+    root = os.path.dirname(os.path.abspath(__file__))
+    libraries = [
+        os.path.join(root, "target", profile, name)
+        for profile in ("release", "debug")
+        for name in ("libferrite.so", "libferrite.dylib")
+    ]
+    library = next((path for path in libraries if os.path.exists(path)), None)
+    if library is None:
+        pytest.skip("build it with `cargo build -p ferrite-ffi`")
+    lib = ctypes.CDLL(library)
+    lib.ferrite_last_error.restype = ctypes.c_char_p
+
+    account = Account.create()
+    key = bytes(account.key)
+    address = ctypes.create_string_buffer(43)
+    assert lib.ferrite_address(key, address) == 0
+    assert address.value.decode() == account.address
+
+    message_hash = b"\x03" * 32
+    signature = ctypes.create_string_buffer(65)
+    assert lib.ferrite_sign_hash(message_hash, key, signature) == 0
+    expected = ferrite.sign_hashes([message_hash], account.key)[0]
+    assert signature.raw == bytes(expected.signature)
+
+    tx = {"to": "0x" + "11" * 20, "value": 1, "nonce": 0, "gas": 21000}
+    tx.update(maxFeePerGas=2 * 10**9, maxPriorityFeePerGas=10**9, chainId=1)
+    payload = json.dumps(tx).encode()
+    raw, raw_len = ctypes.create_string_buffer(1), ctypes.c_size_t()
+    tx_hash = ctypes.create_string_buffer(32)
+    # Too small a buffer reports the length needed
+    raw_len_out = ctypes.byref(raw_len)
+    assert lib.ferrite_sign_transaction(payload, key, raw, 1, raw_len_out, None) == 6
+    raw = ctypes.create_string_buffer(raw_len.value)
+    status = lib.ferrite_sign_transaction(
+        payload, key, raw, raw_len.value, ctypes.byref(raw_len), tx_hash
+    )
+    assert status == 0
+    signed = account.sign_transaction(tx)
+    assert raw.raw == bytes(signed.raw_transaction)
+    assert tx_hash.raw == bytes(signed.hash)
+
+    status = lib.ferrite_sign_transaction(
+        b'{"gas": -1}', key, raw, raw_len.value, ctypes.byref(raw_len), None
+    )
+    assert status == 3
+    assert b"`gas`" in lib.ferrite_last_error()
+
+
+def test_wasm_module_signs_like_the_extension():
+    root = os.path.dirname(os.path.abspath(__file__))
+    target = os.path.join(root, "target", "wasm32-unknown-unknown")
+    modules = [
+        os.path.join(target, profile, "ferrite_wasm.wasm")
+        for profile in ("release", "debug")
+    ]
+    module = next((path for path in modules if os.path.exists(path)), None)
+    if module is None or shutil.which("node") is None:
+        pytest.skip(
+            "build it with "
+            "`cargo build -p ferrite-wasm --target wasm32-unknown-unknown`"
+        )
+
+    account = Account.create()
+    tx = {"to": "0x" + "11" * 20, "value": 1, "nonce": 0, "gas": 21000}
+    tx.update(maxFeePerGas=2 * 10**9, maxPriorityFeePerGas=10**9, chainId=1)
+    script = """
+        const { readFileSync } = await import("node:fs");
+        const { init } = await import(process.argv[1]);
+        const ferrite = await init(readFileSync(process.argv[2]));
+        const [tx, key] = JSON.parse(readFileSync(0, "utf8"));
+        let error;
+        try { ferrite.signTransaction({ gas: -1 }, key); } catch (e) { error = e.kind; }
+        const signed = ferrite.signTransaction(tx, key);
+        console.log(JSON.stringify({ address: ferrite.address(key), signed, error }));
+    """
+    wrapper = "file://" + os.path.join(root, "ferrite-wasm", "ferrite.js")
+    result = subprocess.run(
+        ["node", "--input-type=module", "-e", script, wrapper, module],
+        input=json.dumps([tx, account.key.hex()]),
+        capture_output=True,
+        text=True,
+        check=True,
+    )
+    output = json.loads(result.stdout)
+    assert output["address"] == account.address
+    assert output["error"] == "invalid_transaction"
+    signed = account.sign_transaction(tx)
+    raw = "0x" + bytes(signed.raw_transaction).hex()
+    assert output["signed"]["rawTransaction"] == raw
+    assert output["signed"]["hash"] == "0x" + bytes(signed.hash).hex()
+
+
+def test_tracing_is_forwarded_to_logging(private_key):
+    """Test that spans and policy decisions reach Python loggers once enabled."""
+    records = []
+
+    class Collect(logging.Handler):
+        def emit(self, record):
+            # The Python side logs to the same loggers
+            if hasattr(record, "ferrite_fields"):
+                records.append(record)
+
+    logger = logging.getLogger("ferrite")
+    handler = Collect(level=logging.DEBUG)
+    previous = logger.level
+    logger.addHandler(handler)
+    logger.setLevel(logging.DEBUG)
+    transaction = {
+        "to": "0x" + "11" * 20,
+        "value": 1,
+        "gas": 21000,
+        "maxFeePerGas": 10**9,
+        "maxPriorityFeePerGas": 10**9,
+        "nonce": 0,
+        "chainId": 1,
+    }
+    ferrite.install()
+    try:
+        Account.sign_transaction(transaction, private_key)
+        ferrite.flush_logs()
+        assert records == []
+
+        ferrite.set_log_level("DEBUG")
+        Account.sign_transaction(transaction, private_key)
+        ferrite.set_signing_policy({"maxValue": 0})
+        with pytest.raises(ferrite.PolicyViolationError):
+            Account.sign_transaction(transaction, private_key)
+        ferrite.flush_logs()
+    finally:
+        ferrite.set_signing_policy(None)
+        ferrite.set_log_level(None)
+        logger.removeHandler(handler)
+        logger.setLevel(previous)
+
+    spans = [r.getMessage() for r in records if "finished in" in r.getMessage()]
+    for step in ("parse", "hash", "sign"):
+        assert any(f":{step}" in message for message in spans)
+    denied = [r for r in records if r.name == "ferrite.policy"]
+    assert denied and denied[0].levelno == logging.INFO
+    assert denied[0].ferrite_fields["chain_id"] == 1
+    assert private_key[2:] not in "".join(r.getMessage() for r in records)
+    with pytest.raises(ferrite.FerriteError, match="Unknown log level"):
+        ferrite.set_log_level("LOUD")
+
+
+def test_prometheus_metrics_endpoint():
+    """Signer servers expose signing counters and backend health to Prometheus."""
+    account = Account.create()
+    offline = Account.create()
+
+    def unreachable(digest):
+        raise ConnectionError("KMS is unreachable")
+
+    remote = ferrite.RemoteAccount(offline.address, unreachable)
+    with ferrite.SignerServer([account.key, remote], port=0) as server:
+
+        def rpc(method, *params):
+            body = json.dumps(
+                {"jsonrpc": "2.0", "id": 1, "method": method, "params": list(params)}
+            ).encode()
+            request = urllib.request.Request(
+                server.url, body, {"Content-Type": "application/json"}
+            )
+            with urllib.request.urlopen(request) as response:
+                return json.loads(response.read())
+
+        assert "result" in rpc("eth_sign", account.address, "0x00")
+        assert "error" in rpc("eth_sign", offline.address, "0x00")
+        with urllib.request.urlopen(server.url + "/metrics") as response:
+            assert response.headers["Content-Type"].startswith("text/plain")
+            text = response.read().decode()
+
+    assert "# TYPE ferrite_signing_duration_seconds histogram" in text
+    address = f'address="{account.address}"'
+    assert f'ferrite_signatures_total{{{address},kind="hash"}}' in text
+    assert f'ferrite_signing_duration_seconds_bucket{{{address},le="+Inf"}}' in text
+    labels = 'server="http",method="eth_sign"'
+    assert f'ferrite_requests_total{{{labels},outcome="ok"}}' in text
+    assert f'ferrite_requests_total{{{labels},outcome="error"}}' in text
+    assert f'ferrite_backend_up{{{address},backend="local"}} 1' in text
+    down = f'address="{offline.address}",backend="remote"'
+    assert f"ferrite_backend_up{{{down}}} 0" in text
+    assert f"ferrite_backend_failures_total{{{down}}} 1" in text
+
+    with ferrite.MetricsServer(port=0) as metrics:
+        with urllib.request.urlopen(metrics.url) as response:
+            assert f"ferrite_backend_up{{{down}}} 0" in response.read().decode()
+
+
+def test_config_file_is_loaded_and_validated(private_key, tmp_path, monkeypatch):
+    """A TOML config loads keys, policy and chains, and errors name the bad key"""
+    (tmp_path / "keys").mkdir()
+    keyfile = Account.encrypt(private_key, "password", kdf="pbkdf2", iterations=1000)
+    (tmp_path / "keys" / "hot.json").write_text(json.dumps(keyfile))
+    monkeypatch.setenv("FERRITE_TEST_PASSWORD", "password")
+    path = tmp_path / "ferrite.toml"
+    path.write_text(
+        """
+        [[keys]]
+        keystore_dir = "keys"
+        password_env = "FERRITE_TEST_PASSWORD"
+
+        [policy]
+        max_transaction_fee = 1_000_000_000_000_000
+
+        [policy.limits]
+        chainIds = ["sepolia"]
+
+        [chains.sepolia]
+        chain_id = 11155111
+        rpc_url = "https://rpc.sepolia.org"
+
+        [server]
+        transport = "ipc"
+        path = "signer.ipc"
+        """
+    )
+
+    config = ferrite.load_config(str(path))
+    assert config.chains["sepolia"].chain_id == 11155111
+    assert config.policy == {"chainIds": [11155111]}
+    assert config.server.path == str(tmp_path / "signer.ipc")
+    [account] = config.accounts()
+    assert account.address == Account.from_key(private_key).address
+
+    config.apply()
+    try:
+        transaction = {
+            "to": "0x" + "11" * 20,
+            "value": 0,
+            "gas": 21000,
+            "gasPrice": 10**9,
+            "nonce": 0,
+        }
+        with pytest.raises(ferrite.PolicyViolationError):
+            account.sign_transaction({**transaction, "chainId": 1})
+        account.sign_transaction({**transaction, "chainId": 11155111})
+    finally:
+        ferrite.set_signing_policy(None)
+        ferrite.set_max_transaction_fee(None)
+
+    path.write_text('[[keys]]\nkeystore_dir = "keys"\npassword_env = 1\n')
+    with pytest.raises(ferrite.ConfigError) as error:
+        ferrite.load_config(str(path))
+    assert error.value.key == "keys[0].password_env"
+    assert "expected a string, got an integer" in str(error.value)
+    path.write_text('[policy.limits]\nchainIds = ["mainnet"]\n')
+    with pytest.raises(ferrite.ConfigError, match=r"policy\.limits\.chainIds\[0\]"):
+        ferrite.load_config(str(path))
+
+    # Limits and rate limits are checked before anything is applied
+    for text, key in (
+        ("[policy.limits]\nmaxValu = 1\n", "policy.limits.maxValu"),
+        ('[policy.limits]\nallowTo = ["0x12"]\n', "policy.limits.allowTo[0]"),
+        ("[policy.limits]\nmaxFee = -1\n", "policy.limits.maxFee"),
+        (
+            '[policy]\nmax_transaction_fee = 1\n[[policy.rate_limits]]\naddress = "0x'
+            + "11" * 20
+            + '"\nper_second = -1\n',
+            "policy.rate_limits[0].per_second",
+        ),
+    ):
+        path.write_text(text)
+        with pytest.raises(ferrite.ConfigError) as error:
+            ferrite.load_config(str(path))
+        assert error.value.key == key